@@ -0,0 +1,140 @@
+//! Recognition of type alias definitions.
+//!
+//! An alias is declared explicitly with `Pairs: TypeAlias = List[...]`
+//! (PEP 613) or `type Pairs = List[...]` (PEP 695), or implicitly by a
+//! module-level assignment whose value can only be a type, such as
+//! `Scores = Dict[str, List[int]]` or `Number = int | float`. Assigning a
+//! class or another plain name isn't taken for an alias, as it may as
+//! well be a value.
+
+use tree_sitter::Node;
+
+use super::annotation_type;
+use crate::types::Type;
+
+/// How `TypeAlias` itself may be named in an annotation.
+const TYPE_ALIAS: &[&str] = &["TypeAlias", "typing.TypeAlias", "typing_extensions.TypeAlias"];
+
+/// Generic types whose subscripts are types rather than values.
+const TYPE_CONSTRUCTORS: &[&str] = &[
+    "Callable",
+    "Dict",
+    "FrozenSet",
+    "List",
+    "Literal",
+    "Optional",
+    "Set",
+    "Tuple",
+    "Type",
+    "Union",
+    "dict",
+    "frozenset",
+    "list",
+    "set",
+    "tuple",
+    "type",
+];
+
+/// Builtin names that can only stand for a type in a `|` union.
+const SCALARS: &[&str] = &["None", "bool", "bytes", "float", "int", "str"];
+
+/// The name and type of the alias `node` defines, if it is an assignment
+/// or `type` statement defining one. Implicit aliases are only recognized
+/// at the module level, where `module` is true.
+pub(super) fn alias_definition(node: &Node, source: &[u8], module: bool) -> Option<(String, Type)> {
+    let text = |n: &Node| n.utf8_text(source).unwrap_or_default();
+    match node.kind() {
+        "type_alias_statement" => {
+            let (name, value) = (node.named_child(0)?, node.named_child(1)?);
+            // `type Pairs[T] = ...` names the alias before its parameters.
+            let name = name.named_child(0)?;
+            let name = match name.kind() {
+                "generic_type" => name.named_child(0)?,
+                _ => name,
+            };
+            Some((text(&name).to_string(), annotation_type(&value, source)))
+        },
+        "assignment" => {
+            let (left, right) =
+                (node.child_by_field_name("left")?, node.child_by_field_name("right")?);
+            if left.kind() != "identifier" {
+                return None;
+            }
+            let alias = match node.child_by_field_name("type") {
+                Some(annotation) => TYPE_ALIAS.contains(&text(&annotation)),
+                None => module && is_type(&right, source),
+            };
+            alias.then(|| (text(&left).to_string(), annotation_type(&right, source)))
+        },
+        _ => None,
+    }
+}
+
+/// Whether the expression `node` can only be a type: a subscript of a
+/// generic type, or a `|` union of those and builtin scalar types.
+fn is_type(node: &Node, source: &[u8]) -> bool {
+    let text = |n: &Node| n.utf8_text(source).unwrap_or_default();
+    match node.kind() {
+        "subscript" => node.child_by_field_name("value").is_some_and(|value| {
+            let name = text(&value);
+            let name = name.strip_prefix("typing.").unwrap_or(name);
+            TYPE_CONSTRUCTORS.contains(&name)
+        }),
+        "binary_operator" => {
+            let operand = |field| {
+                node.child_by_field_name(field).is_some_and(|n| {
+                    SCALARS.contains(&text(&n)) || n.kind() == "none" || is_type(&n, source)
+                })
+            };
+            node.child_by_field_name("operator")
+                .is_some_and(|op| op.kind() == "|")
+                && operand("left")
+                && operand("right")
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn aliases(source: &str) -> Vec<String> {
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let root = tree.root_node();
+        let mut cursor = root.walk();
+        let statements: Vec<Node> = root.named_children(&mut cursor).collect();
+        statements
+            .iter()
+            .flat_map(|statement| [Some(*statement), statement.named_child(0)])
+            .flatten()
+            .filter_map(|node| alias_definition(&node, source.as_bytes(), true))
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect()
+    }
+
+    #[test]
+    fn test_alias_definitions() {
+        let source = "from typing import TypeAlias\n\
+                      Scores = Dict[str, List[int]]\n\
+                      Number = int | float | None\n\
+                      Pairs: TypeAlias = list[tuple[str, int]]\n\
+                      type Tree = int | list[Tree]\n\
+                      type Box[T] = list[T]\n\
+                      Base = object\n\
+                      first = items[0]\n\
+                      mask = flags | other\n\
+                      count: int = 0\n";
+        assert_eq!(
+            aliases(source),
+            [
+                "Scores: Dict[str, List[int]]",
+                "Number: None | int | float",
+                "Pairs: List[Tuple[str, int]]",
+                "Tree: int | List[Tree]",
+                "Box: List[T]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Checks assignments to variables against their declared annotations.
+//!
+//! Once a variable is declared with an annotation (`x: int = 0`), later
+//! assignments to it in the same scope must assign a value the annotation
+//! accepts (see [`Type::accepts`]), and a variable declared as one of the
+//! file's classes must be assigned instances of it or of the classes that
+//! derive from it. An annotation naming a type alias is
+//! checked as the type the alias stands for, though the message names the
+//! alias. The diagnostic points at the assigned value and notes where the
+//! variable was declared.
+
+use std::collections::HashMap;
+use tree_sitter::{Node, Tree};
+
+use super::{annotation_type, rejects, Analyzer};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Checks the assignments of every scope in a parsed Python file.
+/// `analyzer` must have analyzed the same tree.
+pub(super) fn check_assignments(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let source = file.text().as_bytes();
+    let mut diagnostics = Vec::new();
+    let classes = analyzer.class_hierarchy();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        let body = match node.kind() {
+            "module" => node,
+            "function_definition" | "class_definition" => {
+                let Some(body) = node.child_by_field_name("body") else {
+                    continue;
+                };
+                body
+            },
+            _ => continue,
+        };
+        // The declaring assignment and declared type of each variable.
+        let mut declared: HashMap<&str, (Node, Type)> = HashMap::new();
+        for assignment in assignments(&body) {
+            if let Some((name, annotation)) = declaration(&assignment, source) {
+                declared.insert(name, (assignment, annotation_type(&annotation, source)));
+                continue;
+            }
+            let (Some(left), Some(right)) =
+                (assignment.child_by_field_name("left"), assignment.child_by_field_name("right"))
+            else {
+                continue;
+            };
+            let name = left.utf8_text(source).unwrap_or_default();
+            let (Some((declaration, expected)), Some(found)) =
+                (declared.get(name), analyzer.assigned_at(assignment.start_byte()))
+            else {
+                continue;
+            };
+            let expected = analyzer.expand_aliases(expected);
+            if left.kind() == "identifier"
+                && rejects(&expected, &analyzer.expand_aliases(found), &classes)
+            {
+                let annotation = declaration
+                    .child_by_field_name("type")
+                    .unwrap_or(*declaration);
+                diagnostics.push(
+                    Diagnostic::from_message(
+                        Severity::Error,
+                        file.path(),
+                        file.node_span(&right),
+                        MessageId::IncompatibleAssignment,
+                        &[
+                            ("name", name),
+                            ("expected", file.slice(&file.node_span(&annotation))),
+                            ("found", &found.to_string()),
+                        ],
+                    )
+                    .with_related(
+                        file.path(),
+                        file.node_span(declaration),
+                        "Declared here",
+                    ),
+                );
+            }
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// The assignments of a scope's body in source order, outside nested
+/// scopes. Chained assignments (`a = b = 1`) contribute one per target.
+fn assignments<'t>(body: &Node<'t>) -> Vec<Node<'t>> {
+    let mut assignments = Vec::new();
+    let mut stack = vec![*body];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "function_definition" | "class_definition" | "lambda" => continue,
+            "assignment" => assignments.push(node),
+            _ => {},
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    assignments.sort_by_key(|a| a.start_byte());
+    assignments
+}
+
+/// The variable an annotated assignment declares and its annotation.
+fn declaration<'t, 's>(assignment: &Node<'t>, source: &'s [u8]) -> Option<(&'s str, Node<'t>)> {
+    let annotation = assignment.child_by_field_name("type")?;
+    let left = assignment.child_by_field_name("left")?;
+    let name = left.utf8_text(source).ok()?;
+    (left.kind() == "identifier").then_some((name, annotation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::run_check;
+
+    fn check(source: &str) -> Vec<String> {
+        run_check(source, check_assignments)
+            .iter()
+            .map(|d| {
+                let declared = d.related.iter().map(|r| r.span.start.line);
+                format!(
+                    "{}: {} (declared {:?})",
+                    d.span.start.line,
+                    d.message,
+                    declared.collect::<Vec<_>>()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_incompatible_reassignments() {
+        let source = "count: int = 0\ncount = 'many'\nratio: float = 1\nratio = 2\n\
+                      def f(name: str):\n    label: Optional[str] = None\n    label = name\n    \
+                      label = 3\n    items: list[int] = []\n    items = [name]\n    \
+                      count = 'local'\n\nclass C:\n    size: int\n    size = None\n";
+        assert_eq!(
+            check(source),
+            [
+                "1: 'count' is declared as int, but is assigned str (declared [0])",
+                "7: 'label' is declared as Optional[str], but is assigned int (declared [5])",
+                "9: 'items' is declared as list[int], but is assigned List[str] (declared [8])",
+                "14: 'size' is declared as int, but is assigned None (declared [13])",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_aliased_declarations() {
+        let source = "from typing import TypeAlias\n\
+                      Scores = Dict[str, List[int]]\nLabel: TypeAlias = str | None\n\
+                      type Ids = list[int]\n\n\
+                      scores: Scores = {}\nscores = {'a': [1]}\nscores = 3\n\
+                      label: Label = None\nlabel = 'x'\nlabel = 1.5\n\
+                      ids: Ids = []\nids = ['a']\n";
+        assert_eq!(
+            check(source),
+            [
+                "7: 'scores' is declared as Scores, but is assigned int (declared [5])",
+                "10: 'label' is declared as Label, but is assigned float (declared [8])",
+                "12: 'ids' is declared as Ids, but is assigned List[str] (declared [11])",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_declarations() {
+        let source = "class Animal: pass\nclass Dog(Animal): pass\nclass Car: pass\n\
+                      class Proxy(Remote): pass\nclass Named(Protocol): pass\n\n\
+                      pet: Animal = Dog()\npet = Car()\npet = Proxy()\n\
+                      dog: Dog = Dog()\ndog = Animal()\nnamed: Named = Car()\n";
+        assert_eq!(
+            check(source),
+            [
+                "7: 'pet' is declared as Animal, but is assigned Car (declared [6])",
+                "10: 'dog' is declared as Dog, but is assigned Animal (declared [9])",
+            ]
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! Detects reads of attributes a receiver's type doesn't have.
+//!
+//! A receiver's type is known when it is a literal, or a variable the
+//! analyzer has typed as a builtin scalar or container, or as a class
+//! whose attribute table is complete: the class and every base it names
+//! are defined in the file, so attributes can be looked up along its
+//! method resolution order, and none of them defines `__getattr__`,
+//! `__slots__`, or `__new__`, has a decorator other than `dataclass`, or
+//! is an empty namespace class.
+//! Dunder names, assignments to attributes, variables a function reads
+//! from an enclosing scope, and the first parameter of methods and other
+//! instances of the enclosing class (which may be instances of a
+//! subclass) are not checked.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tree_sitter::{Node, Tree};
+
+use super::calls::scope_bindings;
+use super::mro::mro;
+use super::{expression_type, Analyzer, ClassInfo, FunctionKind};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+const INT: &[&str] = &[
+    "as_integer_ratio",
+    "bit_count",
+    "bit_length",
+    "conjugate",
+    "denominator",
+    "from_bytes",
+    "imag",
+    "is_integer",
+    "numerator",
+    "real",
+    "to_bytes",
+];
+const FLOAT: &[&str] =
+    &["as_integer_ratio", "conjugate", "fromhex", "hex", "imag", "is_integer", "real"];
+/// Methods `str` and `bytes` share.
+const STRING: &[&str] = &[
+    "capitalize",
+    "center",
+    "count",
+    "endswith",
+    "expandtabs",
+    "find",
+    "index",
+    "isalnum",
+    "isalpha",
+    "isascii",
+    "isdigit",
+    "islower",
+    "isspace",
+    "istitle",
+    "isupper",
+    "join",
+    "ljust",
+    "lower",
+    "lstrip",
+    "maketrans",
+    "partition",
+    "removeprefix",
+    "removesuffix",
+    "replace",
+    "rfind",
+    "rindex",
+    "rjust",
+    "rpartition",
+    "rsplit",
+    "rstrip",
+    "split",
+    "splitlines",
+    "startswith",
+    "strip",
+    "swapcase",
+    "title",
+    "translate",
+    "upper",
+    "zfill",
+];
+const STR: &[&str] = &[
+    "casefold",
+    "encode",
+    "format",
+    "format_map",
+    "isdecimal",
+    "isidentifier",
+    "isnumeric",
+    "isprintable",
+];
+const BYTES: &[&str] = &["decode", "fromhex", "hex"];
+const LIST: &[&str] = &[
+    "append", "clear", "copy", "count", "extend", "index", "insert", "pop", "remove", "reverse",
+    "sort",
+];
+const DICT: &[&str] = &[
+    "clear",
+    "copy",
+    "fromkeys",
+    "get",
+    "items",
+    "keys",
+    "pop",
+    "popitem",
+    "setdefault",
+    "update",
+    "values",
+];
+const SET: &[&str] = &[
+    "add",
+    "clear",
+    "copy",
+    "difference",
+    "difference_update",
+    "discard",
+    "intersection",
+    "intersection_update",
+    "isdisjoint",
+    "issubset",
+    "issuperset",
+    "pop",
+    "remove",
+    "symmetric_difference",
+    "symmetric_difference_update",
+    "union",
+    "update",
+];
+const TUPLE: &[&str] = &["count", "index"];
+
+/// Checks every attribute read in a parsed Python file. `analyzer` must
+/// have analyzed the same tree.
+pub(super) fn check_attributes(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut bindings = HashMap::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() != "attribute" || is_store(&node) {
+            continue;
+        }
+        let (Some(object), Some(attribute)) =
+            (node.child_by_field_name("object"), node.child_by_field_name("attribute"))
+        else {
+            continue;
+        };
+        let name = text(file, &attribute);
+        if name.starts_with("__") && name.ends_with("__") {
+            continue;
+        }
+        let ty = match object.kind() {
+            "identifier" => match variable_type(&object, file, analyzer, &mut bindings) {
+                Some(ty) => ty.widened(),
+                None => continue,
+            },
+            _ => expression_type(&object, file.text().as_bytes()),
+        };
+        let members = match &ty {
+            Type::Union(members) => members.as_slice(),
+            ty => std::slice::from_ref(ty),
+        };
+        let found: Option<Vec<bool>> = members
+            .iter()
+            .map(|member| has_attribute(member, name, analyzer.classes()))
+            .collect();
+        if found.is_some_and(|found| !found.contains(&true)) {
+            diagnostics.push(Diagnostic::from_message(
+                Severity::Error,
+                file.path(),
+                file.node_span(&attribute),
+                MessageId::UnknownAttribute,
+                &[("type", &ty.to_string()), ("name", name)],
+            ));
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// The type of a variable whose attributes are checked, or `None` if its
+/// type where it is read isn't known: class names, method receivers,
+/// instances of the enclosing class, and variables from enclosing scopes.
+/// `bindings` caches the names bound by each function, by node id.
+pub(super) fn variable_type<'a>(
+    identifier: &Node,
+    file: &SourceFile,
+    analyzer: &'a Analyzer,
+    bindings: &mut HashMap<usize, Vec<String>>,
+) -> Option<&'a Type> {
+    let name = text(file, identifier);
+    match analyzer.type_at(identifier.start_byte())? {
+        // The class itself rather than an instance of it.
+        Type::Named(class) if class.rsplit('.').next() == Some(name) => None,
+        _ if is_method_receiver(identifier, file) => None,
+        Type::Named(class) if in_class_of(identifier, class, file) => None,
+        _ if !is_local(identifier, file, bindings) => None,
+        ty => Some(ty),
+    }
+}
+
+/// Whether a type has an attribute, or `None` if its attributes aren't
+/// known.
+fn has_attribute(ty: &Type, name: &str, classes: &BTreeMap<String, ClassInfo>) -> Option<bool> {
+    let builtin: &[&[&str]] = match ty {
+        Type::None => &[],
+        Type::Bool | Type::Int => &[INT],
+        Type::Float => &[FLOAT],
+        Type::Str => &[STRING, STR],
+        Type::Bytes => &[STRING, BYTES],
+        Type::List(_) => &[LIST],
+        Type::Dict(..) => &[DICT],
+        Type::Set(_) => &[SET],
+        Type::Tuple(_) => &[TUPLE],
+        Type::Named(class) => return class_has_attribute(class, name, classes),
+        _ => return None,
+    };
+    Some(builtin.iter().any(|names| names.contains(&name)))
+}
+
+/// Whether a class or a class along its MRO defines an attribute.
+fn class_has_attribute(
+    class: &str,
+    name: &str,
+    classes: &BTreeMap<String, ClassInfo>,
+) -> Option<bool> {
+    for class in mro(class, classes)? {
+        let info = &classes[class];
+        let attributes = &info.attributes;
+        if attributes.contains_key(name) {
+            return Some(true);
+        }
+        // These classes get their attributes some other way: computed, from
+        // `__slots__` and `__new__`, or filled in on an empty namespace.
+        let dynamic = ["__getattr__", "__getattribute__", "__slots__", "__new__"];
+        if dynamic.iter().any(|d| attributes.contains_key(*d))
+            || (attributes.is_empty() && info.bases.is_empty())
+            || info.decorators.iter().any(|d| !is_dataclass(d))
+        {
+            return None;
+        }
+    }
+    Some(false)
+}
+
+/// Whether a class decorator is `dataclass`, which adds only dunder
+/// methods. Other decorators may replace the class.
+pub(super) fn is_dataclass(decorator: &str) -> bool {
+    let callee = decorator.split('(').next().unwrap_or_default();
+    callee.rsplit('.').next() == Some("dataclass")
+}
+
+/// Whether an attribute is assigned or deleted rather than read.
+fn is_store(attribute: &Node) -> bool {
+    let mut node = *attribute;
+    while let Some(parent) = node.parent() {
+        match parent.kind() {
+            "pattern_list"
+            | "tuple_pattern"
+            | "list_pattern"
+            | "tuple"
+            | "list"
+            | "expression_list"
+            | "list_splat_pattern"
+            | "parenthesized_expression" => {
+                node = parent;
+            },
+            "assignment" | "for_statement" | "for_in_clause" => {
+                return parent.child_by_field_name("left") == Some(node);
+            },
+            "delete_statement" => return true,
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// Whether a variable read in a function is bound in that function. A
+/// variable from an enclosing scope may be rebound after the function is
+/// defined, so its type where the function runs is unknown.
+/// `bindings` caches the names bound by each function, by node id.
+fn is_local(
+    identifier: &Node,
+    file: &SourceFile,
+    bindings: &mut HashMap<usize, Vec<String>>,
+) -> bool {
+    let function = std::iter::successors(identifier.parent(), |n| n.parent())
+        .find(|n| matches!(n.kind(), "function_definition" | "lambda"));
+    let name = text(file, identifier);
+    function.is_none_or(|function| {
+        bindings
+            .entry(function.id())
+            .or_insert_with(|| scope_bindings(&function, file.text().as_bytes()))
+            .iter()
+            .any(|bound| bound == name)
+    })
+}
+
+/// Whether an identifier names the first parameter of a method it is
+/// used in, directly or from a nested function.
+fn is_method_receiver(identifier: &Node, file: &SourceFile) -> bool {
+    let name = text(file, identifier);
+    std::iter::successors(identifier.parent(), |n| n.parent())
+        .filter(|n| n.kind() == "function_definition")
+        .any(|function| {
+            let first = function
+                .child_by_field_name("parameters")
+                .and_then(|params| params.named_child(0))
+                .map(|param| param.child_by_field_name("name").unwrap_or(param));
+            FunctionKind::of(&function, file.text().as_bytes()).has_receiver()
+                && first.is_some_and(|first| text(file, &first) == name)
+        })
+}
+
+/// Whether a node is inside the body of the class `class` names, where a
+/// value of that type may be an instance of a subclass.
+fn in_class_of(node: &Node, class: &str, file: &SourceFile) -> bool {
+    let name = class.rsplit('.').next().unwrap_or(class);
+    std::iter::successors(node.parent(), |n| n.parent())
+        .filter(|n| n.kind() == "class_definition")
+        .filter_map(|n| n.child_by_field_name("name"))
+        .any(|n| text(file, &n) == name)
+}
+
+fn text<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    file.slice(&file.node_span(node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::run_check;
+
+    fn check(source: &str) -> Vec<String> {
+        run_check(source, check_attributes)
+            .iter()
+            .map(|d| format!("{}:{}: {}", d.span.start.line, d.span.start.column, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_attributes() {
+        let source = "n = 1\nn.bit_length()\nn.upper()\nname = 'a'\nname.upper().uper()\n\
+                      'x'.strip()\nb'x'.decode()\n[1].add(2)\nn.__class__\n\
+                      def f(x: Optional[str]):\n    x.strip()\n    x.strp()\n\
+                      sorted(['a'], key=lambda s: s.lowr())\n";
+        assert_eq!(
+            check(source),
+            [
+                "2:2: 'int' has no attribute 'upper'",
+                "7:4: 'List[int]' has no attribute 'add'",
+                "11:6: 'Optional[str]' has no attribute 'strp'",
+                "12:30: 'str' has no attribute 'lowr'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_attributes() {
+        let source = "class Base:\n    kind = 'b'\n\nclass Point(Base):\n    def __init__(self):\n        \
+                      self.x = 1\n        self.later()\n\n    def norm(self) -> int:\n        \
+                      return self.x\n\nclass Dynamic(Base):\n    def __getattr__(self, name):\n        \
+                      return 0\n\nclass Other(Unknown):\n    pass\n\np = Point()\np.kind\np.norm()\n\
+                      p.y\np.z = 1\nDynamic().y\nd = Dynamic()\nd.y\no = Other()\no.y\nPoint.x\n";
+        assert_eq!(check(source), ["21:2: 'Point' has no attribute 'y'"]);
+    }
+
+    #[test]
+    fn test_inherited_attributes() {
+        let source =
+            "class A:\n    def a(self):\n        pass\n\nclass B(A):\n    def b(self):\n        \
+                      pass\n\nclass C(A):\n    size = 1\n\nclass D(B, C, Generic[T]):\n    \
+                      def d(self):\n        pass\n\nclass Bad(A, B):\n    pass\n\nx = D()\n\
+                      x.a\nx.b\nx.size\nx.z\nBad().z\n";
+        assert_eq!(check(source), ["22:2: 'D' has no attribute 'z'"]);
+    }
+
+    #[test]
+    fn test_receivers_of_unknown_type() {
+        let source = "dialog = None\n\ndef setup():\n    global dialog\n    dialog = 1\n\n\
+                      def use():\n    d = dialog\n    d.buttons\n\nclass Node:\n    if True:\n        \
+                      def walk(self):\n            self.next\n\n    def root(self):\n        \
+                      w = self\n        return w.parent\n\n@enum.global_enum\nclass Flag:\n    \
+                      A = 1\n\nFlag().value\nFlag().B\nNode().next\nn = 1\nn.buttons\n";
+        assert_eq!(check(source), ["27:2: 'int' has no attribute 'buttons'"]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Keeping analysis results on disk between runs.
+//!
+//! [`AnalysisCache`] stores one JSON entry per file under
+//! [`CACHE_DIR`], holding the file's result and the key it was computed
+//! under: the fingerprint of the file and everything it depends on, mixed
+//! with the analyzer version and the settings. An entry whose key doesn't
+//! match is simply ignored and overwritten by the next result, so changing
+//! a dependency, upgrading omnitype, or editing the configuration all
+//! invalidate it without any bookkeeping.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::AnalysisResult;
+use crate::error::{Error, Result};
+use crate::utils::{ContentHash, Fingerprint};
+
+/// Where the cache is kept, relative to the project root.
+pub const CACHE_DIR: &str = ".omnitype/cache";
+
+/// Analysis results stored in a directory, keyed by what they depend on.
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+    salt: ContentHash,
+}
+
+/// A cached result and the key it is valid for.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    key: ContentHash,
+    result: AnalysisResult,
+}
+
+impl AnalysisCache {
+    /// Opens the cache in `dir` for this version of omnitype and
+    /// `settings`, a serialized form of everything besides the analyzed
+    /// files that can change a result. The directory is created when the
+    /// first entry is stored.
+    pub fn new(dir: impl Into<PathBuf>, settings: &[u8]) -> Self {
+        let salt = Fingerprint::new()
+            .add_bytes("version", env!("CARGO_PKG_VERSION").as_bytes())
+            .add_bytes("settings", settings)
+            .finish();
+        Self { dir: dir.into(), salt }
+    }
+
+    /// The directory the entries are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The result stored for `file` if it was computed from files with
+    /// the combined `fingerprint`. Unreadable entries count as missing.
+    pub fn get(&self, file: &Path, fingerprint: ContentHash) -> Option<AnalysisResult> {
+        let text = fs::read(self.entry_path(file)).ok()?;
+        match serde_json::from_slice::<Entry>(&text) {
+            Ok(entry) if entry.key == self.key(fingerprint) => Some(entry.result),
+            Ok(_) => None,
+            Err(e) => {
+                log::debug!("Ignoring cache entry for {}: {}", file.display(), e);
+                None
+            },
+        }
+    }
+
+    /// Stores the result of `file`, computed from files with the combined
+    /// `fingerprint`, replacing any entry stored before.
+    pub fn put(
+        &self,
+        file: &Path,
+        fingerprint: ContentHash,
+        result: &AnalysisResult,
+    ) -> Result<()> {
+        if !self.dir.is_dir() {
+            fs::create_dir_all(&self.dir)?;
+            // Keep the cache out of version control without touching the
+            // project's own ignore files.
+            fs::write(self.dir.join(".gitignore"), "*\n")?;
+        }
+        let entry = Entry { key: self.key(fingerprint), result: result.clone() };
+        let text = serde_json::to_vec(&entry)
+            .map_err(|e| Error::Other(format!("Failed to serialize result: {}", e)))?;
+        // Write then rename, so a concurrent run never reads half an entry.
+        let path = self.entry_path(file);
+        let partial = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&partial, text)?;
+        fs::rename(&partial, &path)?;
+        Ok(())
+    }
+
+    fn key(&self, fingerprint: ContentHash) -> ContentHash {
+        Fingerprint::new()
+            .add("salt", self.salt)
+            .add("files", fingerprint)
+            .finish()
+    }
+
+    /// The entry of `file`, named by a hash of its path.
+    fn entry_path(&self, file: &Path) -> PathBuf {
+        let name = ContentHash::of_bytes(file.to_string_lossy().as_bytes());
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_entries_are_keyed_by_fingerprint_and_settings() {
+        let dir = tempdir().unwrap();
+        let cache_dir = dir.path().join(CACHE_DIR);
+        let file = dir.path().join("a.py");
+        let result = AnalysisResult {
+            path: file.display().to_string(),
+            function_count: 1,
+            class_count: 0,
+            diagnostics: Vec::new(),
+            inferred_returns: Default::default(),
+            inferred_parameters: Default::default(),
+            unsolved: Default::default(),
+            classes: Default::default(),
+            metrics: Default::default(),
+        };
+        let fingerprint = ContentHash::of_bytes(b"x = 1\n");
+
+        let cache = AnalysisCache::new(&cache_dir, b"strict");
+        assert!(cache.get(&file, fingerprint).is_none());
+        cache.put(&file, fingerprint, &result).unwrap();
+        assert_eq!(cache.get(&file, fingerprint).unwrap().path, result.path);
+        assert!(cache_dir.join(".gitignore").is_file());
+
+        assert!(cache
+            .get(&file, ContentHash::of_bytes(b"x = 2\n"))
+            .is_none());
+        assert!(cache.get(&dir.path().join("b.py"), fingerprint).is_none());
+        let reconfigured = AnalysisCache::new(&cache_dir, b"lenient");
+        assert!(reconfigured.get(&file, fingerprint).is_none());
+        assert!(AnalysisCache::new(&cache_dir, b"strict")
+            .get(&file, fingerprint)
+            .is_some());
+    }
+}
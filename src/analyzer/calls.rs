@@ -0,0 +1,707 @@
+//! Checks calls against the signatures of the functions they call.
+//!
+//! Calls by name to functions and classes defined at the top level of the
+//! same file are bound the way Python binds them: positional arguments
+//! fill parameters in order, keywords fill parameters by name, and
+//! required parameters left over are missing. Arguments to annotated
+//! parameters must fit the annotation, as in doctests. A class call is
+//! checked against its `__init__`. Definitions with decorators other than
+//! caching ones like `@lru_cache`, names bound more than once at the top
+//! level, and names shadowed by an enclosing scope are skipped, as are the
+//! arity checks a `*` or `**` argument makes unknowable. When the module
+//! has a `.pyi` stub, the functions and classes the stub declares are
+//! checked against their stub signatures instead.
+//!
+//! A dict literal passed to a parameter annotated with one of the file's
+//! `TypedDict` classes is checked against its keys instead.
+//!
+//! Calls to builtins the file doesn't rebind, and to functions of standard
+//! library modules imported at the top level (`os.getcwd()` after `import
+//! os`, `dumps()` after `from json import dumps`), are checked against the
+//! typeshed stubs of those modules the same way.
+
+use std::collections::HashMap;
+
+use tree_sitter::{Node, Tree};
+
+use super::decorators::preserves_signature;
+use super::doctest::accepts;
+use super::signatures::{self, is_annotated, parameter, parameter_name, ParameterKind};
+use super::stubs::Stub;
+use super::typed_dicts::TypedDicts;
+use super::typeshed::Typeshed;
+use super::{expression_type, imported_names, Analyzer};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::error::Result;
+use crate::parser::Parser;
+use crate::utils::SourceFile;
+
+#[derive(Debug)]
+pub(super) struct Parameter {
+    pub(super) name: String,
+    pub(super) kind: ParameterKind,
+    required: bool,
+    /// The annotation, if the parameter has one.
+    pub(super) ty: Option<String>,
+}
+
+/// Scopes whose bindings can shadow a top-level name.
+const SCOPES: &[&str] = &[
+    "function_definition",
+    "class_definition",
+    "lambda",
+    "list_comprehension",
+    "set_comprehension",
+    "dictionary_comprehension",
+    "generator_expression",
+];
+
+/// Checks every call in a parsed Python file. `analyzer` must have
+/// analyzed the same tree; it supplies the types of variables passed as
+/// arguments.
+pub(super) fn check_calls(
+    parser: &mut Parser,
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+    stub: Option<&Stub>,
+    typeshed: &Typeshed,
+    typed_dicts: &TypedDicts,
+) -> Result<Vec<Diagnostic>> {
+    let root = tree.root_node();
+    let mut callees = callees(&root, file);
+    if let Some(stub) = stub {
+        let declared = scope_bindings(&stub.tree.root_node(), stub.file.text().as_bytes());
+        callees.retain(|name, _| !declared.contains(name));
+        callees.extend(self::callees(&stub.tree.root_node(), &stub.file));
+    }
+    let module = scope_bindings(&root, file.text().as_bytes());
+    let imports = stdlib_imports(&root, file, &module);
+    // A `*` import may bind any name, hiding the builtin of the same name.
+    let builtins_visible = !has_wildcard_import(&root);
+    let mut stdlib = Stdlib { parser, typeshed, modules: HashMap::new() };
+    let mut diagnostics = Vec::new();
+    let mut bindings: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() != "call" {
+            continue;
+        }
+        let Some(parts) = node
+            .child_by_field_name("function")
+            .and_then(|function| dotted_name(&function, file))
+        else {
+            continue;
+        };
+        let name = parts.join(".");
+        let head = parts[0];
+        // Annotations of other modules' functions name their own classes.
+        let local = callees.contains_key(&name);
+        let callee = match (callees.get(&name), imports.get(head)) {
+            (Some(callee), _) => Some(callee),
+            (None, Some(path)) => {
+                let path = format!("{}{}", path, &name[head.len()..]);
+                match path.rsplit_once('.') {
+                    Some((module, function)) => stdlib.callee(module, function)?,
+                    None => None,
+                }
+            },
+            (None, None) if parts.len() == 1 && builtins_visible && !module.contains(&name) => {
+                stdlib.callee("builtins", &name)?
+            },
+            (None, None) => None,
+        };
+        let Some(callee) = callee else {
+            continue;
+        };
+        if !shadowed(&node, head, file.text().as_bytes(), &mut bindings) {
+            let typed_dicts = local.then_some(typed_dicts);
+            Call { file, analyzer, name: &name, parameters: callee, typed_dicts }
+                .check(&node, &mut diagnostics);
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    Ok(diagnostics)
+}
+
+/// Whether a scope enclosing `node` binds `name`, hiding the top-level
+/// name. `bindings` caches the names each scope binds, by node id.
+pub(super) fn shadowed(
+    node: &Node,
+    name: &str,
+    source: &[u8],
+    bindings: &mut HashMap<usize, Vec<String>>,
+) -> bool {
+    std::iter::successors(node.parent(), |n| n.parent())
+        .filter(|n| SCOPES.contains(&n.kind()))
+        .any(|scope| {
+            bindings
+                .entry(scope.id())
+                .or_insert_with(|| scope_bindings(&scope, source))
+                .iter()
+                .any(|bound| bound == name)
+        })
+}
+
+/// The signatures of standard library functions, loaded from typeshed as
+/// calls need them.
+struct Stdlib<'a> {
+    parser: &'a mut Parser,
+    typeshed: &'a Typeshed,
+    modules: HashMap<String, HashMap<String, Vec<Parameter>>>,
+}
+
+impl Stdlib<'_> {
+    /// The parameters of `function` in the standard library module `module`.
+    fn callee(&mut self, module: &str, function: &str) -> Result<Option<&Vec<Parameter>>> {
+        if !self.modules.contains_key(module) {
+            let callees = match self.typeshed.load(self.parser, module)? {
+                Some(stub) => callees(&stub.tree.root_node(), &stub.file),
+                None => HashMap::new(),
+            };
+            self.modules.insert(module.to_string(), callees);
+        }
+        Ok(self.modules[module].get(function))
+    }
+}
+
+/// The dotted paths that names bound once at the top level by absolute
+/// imports refer to, keyed by name: `os` for `import os.path`, `os.path`
+/// for `from os import path`. Whether the path is a standard library
+/// module or function is up to typeshed.
+fn stdlib_imports(root: &Node, file: &SourceFile, module: &[String]) -> HashMap<String, String> {
+    let mut imports = HashMap::new();
+    let mut cursor = root.walk();
+    for import in root.named_children(&mut cursor) {
+        let from = match import.kind() {
+            "import_statement" => None,
+            "import_from_statement" => match import.child_by_field_name("module_name") {
+                Some(name) if name.kind() == "dotted_name" => Some(name),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let mut cursor = import.walk();
+        for imported in import
+            .named_children(&mut cursor)
+            .filter(|n| Some(*n) != from)
+        {
+            let (path, name) = match imported.kind() {
+                "aliased_import" => {
+                    let (Some(path), Some(alias)) = (
+                        imported.child_by_field_name("name"),
+                        imported.child_by_field_name("alias"),
+                    ) else {
+                        continue;
+                    };
+                    (text(file, &path).to_string(), text(file, &alias))
+                },
+                "dotted_name" => match (from, imported.named_child(0)) {
+                    (Some(_), _) => (text(file, &imported).to_string(), text(file, &imported)),
+                    // `import a.b` binds `a`.
+                    (None, Some(first)) => (text(file, &first).to_string(), text(file, &first)),
+                    (None, None) => continue,
+                },
+                _ => continue,
+            };
+            if module.iter().filter(|bound| *bound == name).count() != 1 {
+                continue;
+            }
+            let path = match from {
+                Some(from) => format!("{}.{}", text(file, &from), path),
+                None => path,
+            };
+            imports.insert(name.to_string(), path);
+        }
+    }
+    imports
+}
+
+/// Whether a module has a `from ... import *` statement, which Python only
+/// allows outside functions and classes but possibly in an `if` or `try`.
+pub(super) fn has_wildcard_import(root: &Node) -> bool {
+    let mut stack = vec![*root];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "wildcard_import" {
+            return true;
+        }
+        if !SCOPES.contains(&node.kind()) {
+            let mut cursor = node.walk();
+            stack.extend(node.named_children(&mut cursor));
+        }
+    }
+    false
+}
+
+/// The parts of a called name like `f` or `os.path.join`, or `None` if the
+/// callee is another kind of expression.
+fn dotted_name<'f>(function: &Node, file: &'f SourceFile) -> Option<Vec<&'f str>> {
+    match function.kind() {
+        "identifier" => Some(vec![text(file, function)]),
+        "attribute" => {
+            let mut parts = dotted_name(&function.child_by_field_name("object")?, file)?;
+            parts.push(text(file, &function.child_by_field_name("attribute")?));
+            Some(parts)
+        },
+        _ => None,
+    }
+}
+
+/// The parameters of the functions and classes defined at the top level
+/// whose decorators keep their signature, keyed by name. Names bound more than once are left out.
+fn callees(root: &Node, file: &SourceFile) -> HashMap<String, Vec<Parameter>> {
+    let module = scope_bindings(root, file.text().as_bytes());
+    let mut callees = HashMap::new();
+    let mut cursor = root.walk();
+    for definition in root.named_children(&mut cursor) {
+        let definition = match definition.kind() {
+            "decorated_definition" => match definition.child_by_field_name("definition") {
+                Some(inner) if preserves_signature(&inner, file.text().as_bytes()) => inner,
+                _ => continue,
+            },
+            _ => definition,
+        };
+        let Some(name) = definition.child_by_field_name("name") else {
+            continue;
+        };
+        let name = text(file, &name);
+        if module.iter().filter(|bound| *bound == name).count() != 1 {
+            continue;
+        }
+        let parameters = match definition.kind() {
+            "function_definition" => parameters(&definition, file),
+            "class_definition" => {
+                let Some(mut parameters) = constructor(&definition, file) else {
+                    continue;
+                };
+                // The instance is passed implicitly, possibly through `*args`.
+                if parameters.first().is_some_and(|p| {
+                    matches!(p.kind, ParameterKind::PositionalOnly | ParameterKind::Positional)
+                }) {
+                    parameters.remove(0);
+                }
+                parameters
+            },
+            _ => continue,
+        };
+        callees.insert(name.to_string(), parameters);
+    }
+    callees
+}
+
+/// The names a scope binds directly: its parameters, the targets of its
+/// assignments, loops, `with` and `except` clauses, and imports, and the
+/// functions and classes it defines. Nested scopes are not searched.
+pub(super) fn scope_bindings(scope: &Node, source: &[u8]) -> Vec<String> {
+    let text = |node: &Node| node.utf8_text(source).unwrap_or_default().to_string();
+    let mut names = Vec::new();
+    if let Some(params) = scope.child_by_field_name("parameters") {
+        for (param, _) in signatures::parameters(&params) {
+            if let Some(name) = parameter_name(&param) {
+                names.push(text(&name));
+            }
+        }
+    }
+    let mut cursor = scope.walk();
+    let mut stack: Vec<Node> = scope.named_children(&mut cursor).collect();
+    while let Some(node) = stack.pop() {
+        let targets = match node.kind() {
+            "function_definition" | "class_definition" => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    names.push(text(&name));
+                }
+                continue;
+            },
+            kind if SCOPES.contains(&kind) => continue,
+            "assignment" | "augmented_assignment" | "for_statement" | "for_in_clause" => {
+                node.child_by_field_name("left")
+            },
+            "named_expression" => node.child_by_field_name("name"),
+            "as_pattern" => node
+                .child_by_field_name("alias")
+                .and_then(|alias| alias.named_child(0)),
+            "import_statement" | "import_from_statement" => {
+                let imported = imported_names(&node, source);
+                names.extend(imported.into_iter().map(str::to_string));
+                continue;
+            },
+            _ => None,
+        };
+        if let Some(targets) = targets {
+            names.extend(
+                bound_names(&targets, source)
+                    .into_iter()
+                    .map(str::to_string),
+            );
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    names
+}
+
+/// The parameters of a class's `__init__`, if its body defines one.
+fn constructor(class: &Node, file: &SourceFile) -> Option<Vec<Parameter>> {
+    let body = class.child_by_field_name("body")?;
+    let mut cursor = body.walk();
+    let init = body.named_children(&mut cursor).find(|n| {
+        n.kind() == "function_definition"
+            && n.child_by_field_name("name")
+                .is_some_and(|name| text(file, &name) == "__init__")
+    })?;
+    Some(parameters(&init, file))
+}
+
+/// The parameters of a function definition, typed by their annotations only.
+pub(super) fn parameters(function: &Node, file: &SourceFile) -> Vec<Parameter> {
+    let Some(params) = function.child_by_field_name("parameters") else {
+        return Vec::new();
+    };
+    signatures::parameters(&params)
+        .into_iter()
+        .filter_map(|(param, kind)| {
+            let inferred = parameter(&param, kind, file)?;
+            Some(Parameter {
+                name: inferred.name,
+                kind,
+                required: !matches!(kind, ParameterKind::VarPositional | ParameterKind::VarKeyword)
+                    && param.child_by_field_name("value").is_none(),
+                // A default's type says little about what else is accepted.
+                ty: inferred.ty.filter(|_| is_annotated(&param)),
+            })
+        })
+        .collect()
+}
+
+/// The names an assignment or loop target binds.
+pub(super) fn bound_names<'s>(target: &Node, source: &'s [u8]) -> Vec<&'s str> {
+    match target.kind() {
+        "identifier" => vec![target.utf8_text(source).unwrap_or_default()],
+        "pattern_list" | "tuple_pattern" | "list_pattern" | "list_splat_pattern" => {
+            let mut cursor = target.walk();
+            let elements: Vec<Node> = target.named_children(&mut cursor).collect();
+            elements
+                .iter()
+                .flat_map(|e| bound_names(e, source))
+                .collect()
+        },
+        _ => Vec::new(),
+    }
+}
+
+struct Call<'a> {
+    file: &'a SourceFile,
+    analyzer: &'a Analyzer,
+    name: &'a str,
+    parameters: &'a [Parameter],
+    typed_dicts: Option<&'a TypedDicts>,
+}
+
+impl Call<'_> {
+    fn check(&self, call: &Node, out: &mut Vec<Diagnostic>) {
+        let Some(arguments) = call.child_by_field_name("arguments") else {
+            return;
+        };
+        let parameters = self.parameters;
+        let positional: Vec<usize> = (0..parameters.len())
+            .filter(|&i| {
+                matches!(
+                    parameters[i].kind,
+                    ParameterKind::PositionalOnly | ParameterKind::Positional
+                )
+            })
+            .collect();
+        let variadic = parameters
+            .iter()
+            .find(|p| p.kind == ParameterKind::VarPositional);
+        let accepts_keywords = parameters
+            .iter()
+            .any(|p| p.kind == ParameterKind::VarKeyword);
+
+        let mut bound = vec![false; parameters.len()];
+        let mut unpacked = false;
+        let mut index = 0;
+        // `f(x for x in xs)` passes the generator without an argument list.
+        let arguments: Vec<Node> = if arguments.kind() == "argument_list" {
+            let mut cursor = arguments.walk();
+            arguments
+                .named_children(&mut cursor)
+                .filter(|a| !a.is_extra())
+                .collect()
+        } else {
+            vec![arguments]
+        };
+        let given = arguments
+            .iter()
+            .filter(|a| !matches!(a.kind(), "keyword_argument" | "dictionary_splat"))
+            .count();
+        for argument in &arguments {
+            match argument.kind() {
+                "list_splat" | "dictionary_splat" => unpacked = true,
+                "keyword_argument" => {
+                    let (Some(name), Some(value)) = (
+                        argument.child_by_field_name("name"),
+                        argument.child_by_field_name("value"),
+                    ) else {
+                        continue;
+                    };
+                    let name = text(self.file, &name);
+                    let found = parameters.iter().position(|p| {
+                        p.name == name
+                            && matches!(
+                                p.kind,
+                                ParameterKind::Positional | ParameterKind::KeywordOnly
+                            )
+                    });
+                    match found {
+                        Some(i) => {
+                            bound[i] = true;
+                            self.check_type(&parameters[i], &value, out);
+                        },
+                        None if !accepts_keywords => out.push(self.diagnostic(
+                            Severity::Error,
+                            argument,
+                            MessageId::UnknownKeyword,
+                            &[("name", name)],
+                        )),
+                        None => {},
+                    }
+                },
+                // After a `*` argument the position of the rest is unknown.
+                _ if unpacked => {},
+                _ => {
+                    index += 1;
+                    match positional.get(index - 1) {
+                        Some(&i) => {
+                            bound[i] = true;
+                            self.check_type(&parameters[i], argument, out);
+                        },
+                        None => match variadic {
+                            Some(parameter) => self.check_type(parameter, argument, out),
+                            None if index == positional.len() + 1 => out.push(self.diagnostic(
+                                Severity::Error,
+                                argument,
+                                MessageId::TooManyArguments,
+                                &[
+                                    ("expected", &positional.len().to_string()),
+                                    ("found", &given.to_string()),
+                                ],
+                            )),
+                            None => {},
+                        },
+                    }
+                },
+            }
+        }
+
+        if unpacked {
+            return;
+        }
+        for (parameter, bound) in parameters.iter().zip(bound) {
+            if parameter.required && !bound {
+                out.push(self.diagnostic(
+                    Severity::Error,
+                    call,
+                    MessageId::MissingArgument,
+                    &[("name", &parameter.name)],
+                ));
+            }
+        }
+    }
+
+    /// Reports an argument whose type clearly doesn't fit its parameter.
+    fn check_type(&self, parameter: &Parameter, value: &Node, out: &mut Vec<Diagnostic>) {
+        let Some(expected) = &parameter.ty else {
+            return;
+        };
+        if let Some(typed_dicts) = self
+            .typed_dicts
+            .filter(|t| value.kind() == "dictionary" && t.contains(expected))
+        {
+            typed_dicts.check_literal(expected, value, self.file, self.analyzer, out);
+            return;
+        }
+        let found = match value.kind() {
+            "identifier" => match self.analyzer.type_at(value.start_byte()) {
+                Some(ty) => ty.clone(),
+                None => return,
+            },
+            _ => expression_type(value, self.file.text().as_bytes()),
+        };
+        // An alias is checked as the type it stands for.
+        let alias = self
+            .analyzer
+            .aliases
+            .alias(expected)
+            .map(ToString::to_string);
+        if accepts(alias.as_deref().unwrap_or(expected), &found) == Some(false) {
+            out.push(self.diagnostic(
+                Severity::Warning,
+                value,
+                MessageId::ArgumentType,
+                &[
+                    ("name", &parameter.name),
+                    ("expected", expected),
+                    ("found", &found.widened().to_string()),
+                ],
+            ));
+        }
+    }
+
+    fn diagnostic(
+        &self,
+        severity: Severity,
+        node: &Node,
+        id: MessageId,
+        args: &[(&str, &str)],
+    ) -> Diagnostic {
+        let mut all = vec![("function", self.name)];
+        all.extend_from_slice(args);
+        Diagnostic::from_message(severity, self.file.path(), self.file.node_span(node), id, &all)
+    }
+}
+
+fn text<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    file.slice(&file.node_span(node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<String> {
+        check_with(source, None, &Typeshed::new(None))
+    }
+
+    fn check_with(source: &str, stub: Option<&str>, typeshed: &Typeshed) -> Vec<String> {
+        let file = SourceFile::from_text("c.py", source);
+        let mut parser = Parser::new().unwrap();
+        let tree = parser.parse_string(source).unwrap();
+        let stub = stub
+            .map(|stub| Stub::parse(&mut parser, SourceFile::from_text("c.pyi", stub)).unwrap());
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let typed_dicts = TypedDicts::collect(&tree.root_node(), source.as_bytes());
+        check_calls(&mut parser, &tree, &file, &analyzer, stub.as_ref(), typeshed, &typed_dicts)
+            .unwrap()
+            .iter()
+            .map(|d| format!("{}: {}", d.span.start.line, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_stub_signatures_take_precedence() {
+        let source = "def f(x):\n    pass\n\ndef g(a, b):\n    pass\n\nf(1, 2)\ng(1)\nh(1)\n";
+        let stub = "def f(x, y: int) -> None: ...\nclass h:\n    def __init__(self) -> None: ...\n";
+        assert_eq!(
+            check_with(source, Some(stub), &Typeshed::new(None)),
+            [
+                "7: Call to 'g' is missing an argument for parameter 'b'",
+                "8: 'h' takes 0 positional arguments, but 1 were given",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builtin_and_stdlib_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        for (module, stub) in [
+            ("os/__init__.pyi", "def getcwd() -> str: ...\n"),
+            ("os/path.pyi", "def exists(path: str) -> bool: ...\n"),
+            ("json/__init__.pyi", "def dumps(obj, *, indent: int | None = None) -> str: ...\n"),
+        ] {
+            let path = dir.path().join("stdlib").join(module);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, stub).unwrap();
+        }
+        let source = "import os\nimport os.path as osp\nfrom json import dumps\n\
+                      from json import loads as parse\n\nlen('a', 'b')\nos.getcwd(1)\n\
+                      osp.exists()\nos.path.exists('a', 'b')\ndumps({}, indent='2')\n\
+                      parse('x')\nprint('a', sep=1)\n\ndef f(len, os):\n    len(1, 2)\n    \
+                      os.getcwd(1)\n";
+        assert_eq!(
+            check_with(source, None, &Typeshed::new(Some(dir.path()))),
+            [
+                "5: 'len' takes 1 positional arguments, but 2 were given",
+                "6: 'os.getcwd' takes 0 positional arguments, but 1 were given",
+                "7: Call to 'osp.exists' is missing an argument for parameter 'path'",
+                "8: 'os.path.exists' takes 1 positional arguments, but 2 were given",
+                "9: Call passes str to parameter 'indent' of 'dumps', which expects int | None",
+                "11: Call passes int to parameter 'sep' of 'print', which expects str | None",
+            ]
+        );
+        // Without typeshed only the bundled builtins are known, and a `*`
+        // import may rebind them.
+        let source = "import os\n\nlen()\nos.getcwd(1)\n";
+        assert_eq!(check(source), ["2: Call to 'len' is missing an argument for parameter 'obj'"]);
+        assert!(check("try:\n    from tkinter import *\nexcept ImportError:\n    pass\nlen()\n")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_call_arity_and_keywords() {
+        let source = "def f(a, /, b: int, c=1, *, d, **kw):\n    pass\n\ndef g(x):\n    pass\n\n\
+                      f(1, 2, d=3)\nf(1, d=3, e=4)\nf(a=1, b=2, d=3)\ng(1, 2, 3)\ng(y=1)\n\
+                      g(*xs)\nf(1, **opts)\n";
+        assert_eq!(
+            check(source),
+            [
+                "7: Call to 'f' is missing an argument for parameter 'b'",
+                "8: Call to 'f' is missing an argument for parameter 'a'",
+                "9: 'g' takes 1 positional arguments, but 3 were given",
+                "10: Call to 'g' is missing an argument for parameter 'x'",
+                "10: 'g' has no parameter named 'y'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_argument_types() {
+        let source = "class Point:\n    def __init__(self, x: int, y: float = 0.0):\n        \
+                      pass\n\nclass Proxy:\n    def __init__(*args):\n        pass\n\nProxy(1)\n\ndef h(*names: str, flag: bool = False, n=0):\n    pass\n\nname = 'p'\n\
+                      Point(name, y=True)\nh('a', 2, flag=1.5, n='x')\nPoint(1, 2.5)\n";
+        assert_eq!(
+            check(source),
+            [
+                "14: Call passes str to parameter 'x' of 'Point', which expects int",
+                "15: Call passes int to parameter 'names' of 'h', which expects str",
+                "15: Call passes float to parameter 'flag' of 'h', which expects bool",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typed_dict_arguments() {
+        let source = "class Point(TypedDict):\n    x: int\n    y: int\n\n\
+                      def draw(p: Point, label: str = ''):\n    pass\n\n\
+                      draw({'x': 1, 'y': 2})\ndraw({'x': 1}, label='a')\n\
+                      draw(p={'x': 1, 'y': 'two', 'z': 3})\n";
+        assert_eq!(
+            check(source),
+            [
+                "8: Missing key 'y' for TypedDict 'Point'",
+                "9: Key 'y' of TypedDict 'Point' expects int, but is given str",
+                "9: TypedDict 'Point' has no key 'z'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rebound_and_decorated_names_skipped() {
+        let source = "def f(x):\n    pass\n\n@register\ndef g(x):\n    pass\n\ndef h(x):\n    \
+                      pass\n\nh = print\n\ndef k(f):\n    f()\n\nf()\ng()\nh()\n\
+                      call = lambda f: f()\n[f() for f in fs]\nf(x for x in xs)\nf(1, \\\n  2)\n\
+                      @functools.lru_cache(maxsize=None)\ndef m(x):\n    pass\n\nm()\n";
+        assert_eq!(
+            check(source),
+            [
+                "15: Call to 'f' is missing an argument for parameter 'x'",
+                "22: 'f' takes 1 positional arguments, but 2 were given",
+                "27: Call to 'm' is missing an argument for parameter 'x'",
+            ]
+        );
+    }
+}
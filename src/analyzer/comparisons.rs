@@ -0,0 +1,170 @@
+//! Detects comparisons whose outcome is known from the operands' types.
+//!
+//! Values of different builtin types are never equal, except that `bool`,
+//! `int`, and `float` compare by value, so `==` between a `str` and an
+//! `int` is always false and `!=` always true. Likewise `x in c` is always
+//! false when no element, or key, of the builtin container `c` can equal
+//! `x`. Only builtin types are compared, since classes can define `__eq__`
+//! and `__contains__` however they like, and a union is only reported if
+//! every member is.
+
+use std::collections::HashMap;
+
+use tree_sitter::{Node, Tree};
+
+use super::attributes::variable_type;
+use super::unreachable::is_unannotated_parameter;
+use super::Analyzer;
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Reports the `==`, `!=`, `in`, and `not in` comparisons in a parsed
+/// Python file that can only have one outcome. `analyzer` must have
+/// analyzed the same tree.
+pub(super) fn check_comparisons(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let source = file.text().as_bytes();
+    let mut diagnostics = Vec::new();
+    let mut bindings = HashMap::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() != "comparison_operator" {
+            continue;
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node
+            .children(&mut cursor)
+            .filter(|c| !c.is_extra())
+            .collect();
+        let operands: Vec<usize> = (0..children.len())
+            .filter(|&i| children[i].is_named())
+            .collect();
+        for pair in operands.windows(2) {
+            let &[left, right] = pair else {
+                continue;
+            };
+            let Some(operator) = children.get(left + 1).filter(|_| right > left + 1) else {
+                continue;
+            };
+            let (left, right) = (children[left], children[right]);
+            let outcome = match operator.kind() {
+                "==" | "in" => "False",
+                "!=" | "not in" => "True",
+                _ => continue,
+            };
+            let Some((left_type, right_type)) = analyzer.operands_at(operator.start_byte()) else {
+                continue;
+            };
+            let mut operand = |node: &Node, recorded: &Type| match node.kind() {
+                "identifier" if is_unannotated_parameter(node, source) => None,
+                "identifier" => variable_type(node, file, analyzer, &mut bindings).cloned(),
+                _ => Some(recorded.clone()),
+            };
+            let (Some(left_type), Some(right_type)) =
+                (operand(&left, left_type), operand(&right, right_type))
+            else {
+                continue;
+            };
+            let (left_type, right_type) = (left_type.widened(), right_type.widened());
+            let never = match operator.kind() {
+                "==" | "!=" => never_equal(&left_type, &right_type),
+                _ => never_contains(&right_type, &left_type),
+            };
+            if never {
+                diagnostics.push(Diagnostic::from_message(
+                    Severity::Warning,
+                    file.path(),
+                    file.span(left.start_byte(), right.end_byte()),
+                    MessageId::SuspiciousComparison,
+                    &[
+                        ("operator", operator.kind()),
+                        ("left", &left_type.to_string()),
+                        ("right", &right_type.to_string()),
+                        ("outcome", outcome),
+                    ],
+                ));
+            }
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// Whether no value of type `a` equals any value of type `b`.
+fn never_equal(a: &Type, b: &Type) -> bool {
+    let builtin = |ty: &Type| {
+        matches!(
+            ty,
+            Type::None
+                | Type::Bool
+                | Type::Int
+                | Type::Float
+                | Type::Str
+                | Type::Bytes
+                | Type::List(_)
+                | Type::Dict(..)
+                | Type::Set(_)
+                | Type::Tuple(_)
+        )
+    };
+    let numeric = |ty: &Type| matches!(ty, Type::Bool | Type::Int | Type::Float);
+    match (a, b) {
+        (Type::Union(members), _) => members.iter().all(|member| never_equal(member, b)),
+        (_, Type::Union(members)) => members.iter().all(|member| never_equal(a, member)),
+        (a, b) if !builtin(a) || !builtin(b) => false,
+        (a, b) if numeric(a) && numeric(b) => false,
+        (a, b) => std::mem::discriminant(a) != std::mem::discriminant(b),
+    }
+}
+
+/// Whether a value of type `item` is never in a builtin `container`.
+fn never_contains(container: &Type, item: &Type) -> bool {
+    match container {
+        Type::Union(members) => members.iter().all(|member| never_contains(member, item)),
+        Type::List(element) | Type::Set(element) | Type::Dict(element, _) => {
+            never_equal(element, item)
+        },
+        Type::Tuple(elements) => {
+            !elements.is_empty() && elements.iter().all(|element| never_equal(element, item))
+        },
+        // `in` on a string looks for a substring, and anything else raises.
+        Type::Str => never_equal(&Type::Str, item),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::check_messages;
+
+    fn check(source: &str) -> Vec<String> {
+        check_messages(source, check_comparisons)
+    }
+
+    #[test]
+    fn test_suspicious_comparisons() {
+        let source = "def f(name: str, data: bytes, names: list[str], n: int, default=0):\n    \
+                      name == 1\n    n == 1.0\n    data in names\n    name not in names\n    \
+                      n != None\n    name in 'abc'\n    n in 'abc'\n    default == 'a'\n    \
+                      x = name if n else n\n    x == b''\n    x == 1\n    n in (1, 'a')\n    \
+                      n in {'a': 1}\n    name == other\n";
+        assert_eq!(
+            check(source),
+            [
+                "1: Comparison 'str == int' is always False",
+                "3: Comparison 'bytes in List[str]' is always False",
+                "5: Comparison 'int != None' is always True",
+                "7: Comparison 'int in str' is always False",
+                "10: Comparison 'int | str == bytes' is always False",
+                "13: Comparison 'int in Dict[str, int]' is always False",
+            ]
+        );
+    }
+}
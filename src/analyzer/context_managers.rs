@@ -0,0 +1,138 @@
+//! Checks that `with` statements enter context managers.
+//!
+//! A `with` statement calls `__enter__` and `__exit__` on each value it
+//! enters, and `async with` calls `__aenter__` and `__aexit__`. Instances
+//! of classes defined in the file must have both methods along their MRO,
+//! and builtin scalars and containers have none of them. Tuples are left
+//! alone, since `with (a, b):` enters `a` and `b`.
+
+use std::collections::HashMap;
+
+use tree_sitter::Tree;
+
+use super::attributes::variable_type;
+use super::mro::member;
+use super::Analyzer;
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Checks the items of every `with` statement in a parsed Python file.
+/// `analyzer` must have analyzed the same tree.
+pub(super) fn check_with_statements(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut bindings = HashMap::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() != "with_item" {
+            continue;
+        }
+        let is_async = node
+            .parent()
+            .and_then(|clause| clause.parent())
+            .and_then(|statement| statement.child(0))
+            .is_some_and(|c| c.kind() == "async");
+        let Some(value) = node.child_by_field_name("value") else {
+            continue;
+        };
+        let manager = match value.kind() {
+            "as_pattern" => value.named_child(0),
+            _ => Some(value),
+        };
+        let Some(manager) = manager else {
+            continue;
+        };
+        let ty = match manager.kind() {
+            "identifier" => variable_type(&manager, file, analyzer, &mut bindings),
+            _ => analyzer.manager_at(node.start_byte()),
+        };
+        let Some(ty) = ty else {
+            continue;
+        };
+        let (statement, methods) = match is_async {
+            true => ("async with", ["__aenter__", "__aexit__"]),
+            false => ("with", ["__enter__", "__exit__"]),
+        };
+        let missing = methods
+            .iter()
+            .find(|method| implements(ty, method, analyzer) == Some(false));
+        if let Some(method) = missing {
+            diagnostics.push(Diagnostic::from_message(
+                Severity::Error,
+                file.path(),
+                file.node_span(&manager),
+                MessageId::NotContextManager,
+                &[("type", &ty.to_string()), ("method", method), ("statement", statement)],
+            ));
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// Whether values of type `ty` have a method, or `None` if that isn't
+/// known.
+fn implements(ty: &Type, method: &str, analyzer: &Analyzer) -> Option<bool> {
+    match ty {
+        Type::Named(class) => member(class, method, analyzer.classes()).map(|m| m.is_some()),
+        Type::Tuple(_) => None,
+        Type::Literal(_) => implements(&ty.widened(), method, analyzer),
+        ty if ty.is_builtin() => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::check_messages;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<String> {
+        check_messages(source, check_with_statements)
+    }
+
+    #[test]
+    fn test_context_managers() {
+        let source = "class Base:\n    def __exit__(self, *exc):\n        pass\n\n\
+                      class Session(Base):\n    def __enter__(self) -> Session:\n        \
+                      return self\n\nclass Lock:\n    def acquire(self):\n        pass\n\n\
+                      class Pool:\n    async def __aenter__(self) -> Session:\n        \
+                      return Session()\n\n    async def __aexit__(self, *exc):\n        pass\n\n\
+                      lock = Lock()\nwith Session() as s, lock:\n    pass\n\
+                      with (Session() as a, 'name'):\n    pass\nwith Pool():\n    pass\n\
+                      async def run():\n    async with Pool() as p, Session():\n        pass\n\
+                      with (open(path), lock2):\n    pass\n";
+        assert_eq!(
+            check(source),
+            [
+                "20: 'Lock' doesn't implement __enter__, which 'with' calls",
+                "22: 'str' doesn't implement __enter__, which 'with' calls",
+                "24: 'Pool' doesn't implement __enter__, which 'with' calls",
+                "27: 'Session' doesn't implement __aenter__, which 'async with' calls",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_as_targets_bound_to_enter_result() {
+        let source =
+            "class Conn:\n    pass\n\nclass Pool:\n    def __enter__(self) -> Conn:\n        \
+                      return Conn()\n\n    async def __aenter__(self) -> Conn:\n        \
+                      return Conn()\n\nwith Pool() as a, open(path) as b:\n    pass\n\n\
+                      async def run():\n    async with Pool() as c:\n        pass\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let module = &analyzer.scope("").unwrap().variables;
+        assert_eq!(module["a"].to_string(), "Conn");
+        assert_eq!(module["b"], Type::Unknown);
+        assert_eq!(analyzer.scope("run").unwrap().variables["c"].to_string(), "Conn");
+    }
+}
@@ -0,0 +1,263 @@
+//! How decorators change what a `def` binds.
+//!
+//! `@property` turns a method into an attribute holding its return value,
+//! `@staticmethod` and `@classmethod` change what the first parameter
+//! receives, and caching decorators such as `@functools.lru_cache` keep the
+//! signature of the function they wrap. A decorator typed with a
+//! `ParamSpec`, such as `def retry(f: Callable[P, R]) -> Callable[P, R]`,
+//! passes the parameters of the function along to what it returns. Any
+//! other decorator may replace the function with something else entirely.
+
+use tree_sitter::Node;
+
+use super::signatures::{parameters, ParameterKind};
+use crate::types::{Param, Type, TypeEnv};
+
+/// Methods Python passes the class to without a `@classmethod` decorator.
+const IMPLICIT_CLASS_METHODS: &[&str] = &["__new__", "__init_subclass__", "__class_getitem__"];
+
+/// Decorators that return a callable with the signature of the function
+/// they wrap.
+const SIGNATURE_PRESERVING: &[&str] =
+    &["lru_cache", "cache", "abstractmethod", "final", "override"];
+
+/// What a function definition binds its name to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FunctionKind {
+    /// A function outside a class body.
+    Function,
+    /// An instance method; the first parameter receives the instance.
+    Method,
+    /// A `@classmethod`; the first parameter receives the class.
+    ClassMethod,
+    /// A `@staticmethod`; every parameter is passed explicitly.
+    StaticMethod,
+    /// A property getter, setter, or deleter, read as an attribute.
+    Property,
+}
+
+impl FunctionKind {
+    /// Classifies a function definition by where it is defined and how it
+    /// is decorated. Methods may be defined under `if` or `try` in the
+    /// class body, and `__new__` receives the class like a class method.
+    pub(crate) fn of(function: &Node, source: &[u8]) -> Self {
+        let in_class = std::iter::successors(function.parent(), |n| n.parent())
+            .find(|n| matches!(n.kind(), "function_definition" | "class_definition"))
+            .is_some_and(|n| n.kind() == "class_definition");
+        if !in_class {
+            return Self::Function;
+        }
+        let name = function
+            .child_by_field_name("name")
+            .and_then(|name| name.utf8_text(source).ok());
+        let mut kind = match name {
+            Some(name) if IMPLICIT_CLASS_METHODS.contains(&name) => Self::ClassMethod,
+            _ => Self::Method,
+        };
+        for decorator in decorators(function, source) {
+            let callee = callee(decorator);
+            match callee.rsplit('.').next().unwrap_or(callee) {
+                "staticmethod" => kind = Self::StaticMethod,
+                "classmethod" => kind = Self::ClassMethod,
+                "property" | "cached_property" | "setter" | "getter" | "deleter" => {
+                    kind = Self::Property;
+                },
+                _ => {},
+            }
+        }
+        kind
+    }
+
+    /// Whether the first parameter is passed implicitly, so it needs no
+    /// annotation and takes no argument at call sites.
+    pub(crate) fn has_receiver(self) -> bool {
+        matches!(self, Self::Method | Self::ClassMethod | Self::Property)
+    }
+
+    /// The parameter in `params` that receives the instance or class: the
+    /// first one, if it is passed implicitly and can be passed by position.
+    pub(crate) fn receiver<'t>(self, params: &Node<'t>) -> Option<Node<'t>> {
+        let (first, kind) = *parameters(params).first()?;
+        let positional = matches!(kind, ParameterKind::PositionalOnly | ParameterKind::Positional);
+        (self.has_receiver() && positional).then_some(first)
+    }
+}
+
+/// The decorator expressions applied to a definition, without the `@`,
+/// outermost first.
+pub(crate) fn decorators<'s>(definition: &Node, source: &'s [u8]) -> Vec<&'s str> {
+    let Some(parent) = definition
+        .parent()
+        .filter(|p| p.kind() == "decorated_definition")
+    else {
+        return Vec::new();
+    };
+    let mut cursor = parent.walk();
+    parent
+        .named_children(&mut cursor)
+        .filter(|c| c.kind() == "decorator")
+        .filter_map(|d| d.named_child(0)?.utf8_text(source).ok())
+        .collect()
+}
+
+/// Whether calling a decorated function calls it with its own signature:
+/// it has no decorators other than caching and marker decorators and the
+/// ones [`FunctionKind`] accounts for.
+pub(crate) fn preserves_signature(function: &Node, source: &[u8]) -> bool {
+    decorators(function, source)
+        .into_iter()
+        .all(keeps_signature)
+}
+
+/// What a decorated function's name is bound to, given the type of the
+/// function itself: that type if the decorators preserve its signature,
+/// else what the `ParamSpec` decorators bound in `env` make of it, applied
+/// innermost first, else `Unknown`.
+pub(crate) fn decorated_type(
+    definition: &Node,
+    source: &[u8],
+    env: &TypeEnv,
+    function: Type,
+) -> Type {
+    let mut ty = function;
+    for decorator in decorators(definition, source).into_iter().rev() {
+        if keeps_signature(decorator) {
+            continue;
+        }
+        // A decorator called with arguments is a factory, whose result isn't typed here.
+        let applied = env
+            .lookup(decorator)
+            .filter(|_| callee(decorator) == decorator)
+            .and_then(|d| apply_decorator(d, &ty));
+        match applied {
+            Some(applied) => ty = applied,
+            None => return Type::Unknown,
+        }
+    }
+    ty
+}
+
+/// Whether a decorator returns a callable with the signature of the
+/// function it wraps, or is one of those [`FunctionKind`] accounts for.
+fn keeps_signature(decorator: &str) -> bool {
+    let callee = callee(decorator);
+    let name = callee.rsplit('.').next().unwrap_or(callee);
+    SIGNATURE_PRESERVING.contains(&name) || matches!(name, "staticmethod" | "classmethod")
+}
+
+/// The type of applying a decorator of type `decorator` to a function of
+/// type `function`, if the decorator takes a `Callable[P, R]`, possibly
+/// with parameters `Concatenate`d before `P`: what it returns, with `P`
+/// replaced by the function's parameters after those, and `R` by its
+/// return type. `None` for other decorators.
+fn apply_decorator(decorator: &Type, function: &Type) -> Option<Type> {
+    let Type::Function { params, returns: result } = decorator else {
+        return None;
+    };
+    let Type::Function { params: pattern, returns: pattern_returns } = &params.first()?.ty else {
+        return None;
+    };
+    let Type::Function { params: function_params, returns } = function else {
+        return None;
+    };
+    let spec = Type::param_spec(pattern)?;
+    let captured = function_params.get(pattern.len() - 1..)?;
+    let returned = match &**pattern_returns {
+        Type::Named(name) => Some((name.as_str(), &**returns)),
+        _ => None,
+    };
+    Some(substitute_spec(result, spec, captured, returned))
+}
+
+/// `ty` with the `ParamSpec` `spec` replaced by the parameters `captured`,
+/// and the type variable `returned` names, if any, by its type.
+fn substitute_spec(
+    ty: &Type,
+    spec: &str,
+    captured: &[Param],
+    returned: Option<(&str, &Type)>,
+) -> Type {
+    let sub = |ty: &Type| substitute_spec(ty, spec, captured, returned);
+    let sub_all = |types: &[Type]| types.iter().map(sub).collect::<Vec<_>>();
+    match ty {
+        Type::Named(name) => match returned {
+            Some((var, ty)) if var == name => ty.clone(),
+            _ => Type::Named(name.clone()),
+        },
+        Type::Function { params, returns } => {
+            let mut params: Vec<Param> = params
+                .iter()
+                .map(|p| p.clone().map_type(|ty| sub(&ty)))
+                .collect();
+            if Type::param_spec(&params) == Some(spec) {
+                params.pop();
+                params.extend_from_slice(captured);
+            }
+            Type::Function { params, returns: Box::new(sub(returns)) }
+        },
+        Type::List(inner) => Type::List(Box::new(sub(inner))),
+        Type::Set(inner) => Type::Set(Box::new(sub(inner))),
+        Type::TypeOf(inner) => Type::TypeOf(Box::new(sub(inner))),
+        Type::Dict(key, value) => Type::Dict(Box::new(sub(key)), Box::new(sub(value))),
+        Type::Tuple(items) => Type::Tuple(sub_all(items)),
+        Type::Union(members) => Type::union_of(sub_all(members)),
+        Type::Generic { name, params } => {
+            Type::Generic { name: name.clone(), params: sub_all(params) }
+        },
+        ty => ty.clone(),
+    }
+}
+
+/// The decorator called, without any arguments: `lru_cache` for
+/// `lru_cache(maxsize=None)`.
+pub(crate) fn callee(decorator: &str) -> &str {
+    decorator.split('(').next().unwrap_or(decorator).trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_function_kinds() {
+        let source = "@functools.lru_cache(maxsize=None)\ndef f(x):\n    pass\n\nclass C:\n    \
+                      def m(self):\n        def inner(x):\n            pass\n\n    \
+                      @staticmethod\n    def s(x):\n        pass\n\n    @classmethod\n    \
+                      def c(cls):\n        pass\n\n    @property\n    def p(self):\n        \
+                      pass\n\n    @p.setter\n    def p(self, value):\n        pass\n\n    \
+                      @register\n    def r(self):\n        pass\n\n    \
+                      def __new__(cls):\n        pass\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut functions = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            let mut cursor = node.walk();
+            stack.extend(node.named_children(&mut cursor));
+            if node.kind() == "function_definition" {
+                functions.push(node);
+            }
+        }
+        functions.sort_by_key(|f| f.start_byte());
+        let kinds: Vec<(FunctionKind, bool)> = functions
+            .iter()
+            .map(|f| {
+                (FunctionKind::of(f, source.as_bytes()), preserves_signature(f, source.as_bytes()))
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (FunctionKind::Function, true),
+                (FunctionKind::Method, true),
+                (FunctionKind::Function, true),
+                (FunctionKind::StaticMethod, true),
+                (FunctionKind::ClassMethod, true),
+                (FunctionKind::Property, false),
+                (FunctionKind::Property, false),
+                (FunctionKind::Method, false),
+                (FunctionKind::ClassMethod, true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! Checks doctest examples against the signatures of the functions they
+//! call.
+//!
+//! Each call in a `>>>` example whose callee is defined in the same file is
+//! matched against that function's signature: literal arguments must fit
+//! their parameters' annotations, and a literal expected output must fit the
+//! declared or inferred return type. Parameters without annotations accept
+//! anything, since a default's type says little about what else is passed.
+//! Only builtin scalar types are compared; anything else is assumed to
+//! match.
+
+use std::collections::HashMap;
+
+use tree_sitter::Node;
+
+use super::calls::{self, Parameter};
+use super::signatures::{for_each_function, signature, ParameterKind};
+use super::{expression_type, InferredSignature};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::error::Result;
+use crate::parser::{Injection, Parser};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Type names [`accepts`] understands.
+const SCALARS: &[&str] = &["None", "bool", "int", "float", "complex", "str", "bytes"];
+
+/// Checks every doctest in a parsed Python file.
+pub(super) fn check_doctests(
+    parser: &mut Parser,
+    tree: &tree_sitter::Tree,
+    file: &SourceFile,
+) -> Result<Vec<Diagnostic>> {
+    let injections = parser.parse_injections(tree, file)?;
+    if injections.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut callees = HashMap::new();
+    for_each_function(&tree.root_node(), file, &mut |function, name| {
+        callees.insert(name.clone(), Callee::new(&function, file, name));
+    });
+
+    let mut diagnostics = Vec::new();
+    for injection in &injections {
+        let enclosing = enclosing_function(tree, file, injection);
+        let check = DoctestCheck { file, injection, callees: &callees, enclosing };
+        check.run(parser, &mut diagnostics);
+    }
+    Ok(diagnostics)
+}
+
+/// A function a doctest can call.
+struct Callee {
+    /// Supplies the return type.
+    signature: InferredSignature,
+    /// Supplies the annotations arguments are checked against.
+    parameters: Vec<Parameter>,
+}
+
+impl Callee {
+    fn new(function: &Node, file: &SourceFile, name: String) -> Self {
+        Self {
+            signature: signature(function, file, name),
+            parameters: calls::parameters(function, file),
+        }
+    }
+}
+
+/// The function whose docstring holds `injection`, under its unqualified
+/// name.
+fn enclosing_function(
+    tree: &tree_sitter::Tree,
+    file: &SourceFile,
+    injection: &Injection,
+) -> Option<Callee> {
+    let doc = &injection.docstring;
+    let node = tree
+        .root_node()
+        .descendant_for_byte_range(doc.start_byte, doc.end_byte)?;
+    let function = std::iter::successors(Some(node), |n| n.parent())
+        .find(|n| n.kind() == "function_definition")?;
+    let name = function.child_by_field_name("name")?;
+    Some(Callee::new(&function, file, text(file, &name).to_string()))
+}
+
+struct DoctestCheck<'a> {
+    file: &'a SourceFile,
+    injection: &'a Injection,
+    callees: &'a HashMap<String, Callee>,
+    enclosing: Option<Callee>,
+}
+
+impl DoctestCheck<'_> {
+    fn run(&self, parser: &mut Parser, out: &mut Vec<Diagnostic>) {
+        let root = self.injection.tree.root_node();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if node.kind() == "call" {
+                self.check_arguments(&node, out);
+            }
+            let mut cursor = node.walk();
+            stack.extend(node.named_children(&mut cursor));
+        }
+
+        let mut cursor = root.walk();
+        for statement in root.named_children(&mut cursor) {
+            let line = statement.start_position().row;
+            let Some((_, output)) = self.injection.outputs.iter().find(|(l, _)| *l == line) else {
+                continue;
+            };
+            let call = Some(statement)
+                .filter(|s| s.kind() == "expression_statement" && s.named_child_count() == 1)
+                .and_then(|s| s.named_child(0))
+                .filter(|e| e.kind() == "call");
+            if let Some(call) = call {
+                self.check_output(parser, &call, output, out);
+            }
+        }
+    }
+
+    /// Resolves a callee to the enclosing function or a function defined
+    /// in the host file.
+    fn callee(&self, call: &Node) -> Option<&Callee> {
+        let function = call.child_by_field_name("function")?;
+        let name = self.source_text(&function);
+        self.enclosing
+            .as_ref()
+            .filter(|c| c.signature.name == name)
+            .or_else(|| self.callees.get(name))
+    }
+
+    fn check_arguments(&self, call: &Node, out: &mut Vec<Diagnostic>) {
+        let (Some(callee), Some(arguments)) =
+            (self.callee(call), call.child_by_field_name("arguments"))
+        else {
+            return;
+        };
+        let positional: Vec<_> = callee
+            .parameters
+            .iter()
+            .filter(|p| matches!(p.kind, ParameterKind::PositionalOnly | ParameterKind::Positional))
+            .collect();
+
+        let mut index = 0;
+        let mut cursor = arguments.walk();
+        for argument in arguments.named_children(&mut cursor) {
+            let (parameter, value) = match argument.kind() {
+                "keyword_argument" => {
+                    let (Some(name), Some(value)) = (
+                        argument.child_by_field_name("name"),
+                        argument.child_by_field_name("value"),
+                    ) else {
+                        continue;
+                    };
+                    let name = self.source_text(&name);
+                    let parameter = callee.parameters.iter().find(|p| {
+                        p.name == name
+                            && matches!(
+                                p.kind,
+                                ParameterKind::Positional | ParameterKind::KeywordOnly
+                            )
+                    });
+                    (parameter, value)
+                },
+                "list_splat" | "dictionary_splat" => return,
+                "comment" => continue,
+                _ => {
+                    index += 1;
+                    (positional.get(index - 1).copied(), argument)
+                },
+            };
+            let Some(parameter) = parameter else {
+                continue;
+            };
+            let Some(expected) = &parameter.ty else {
+                continue;
+            };
+            let found = expression_type(&value, self.injection.source.text().as_bytes());
+            if accepts(expected, &found) == Some(false) {
+                out.push(Diagnostic::from_message(
+                    Severity::Warning,
+                    self.file.path(),
+                    self.host_span(&value),
+                    MessageId::DoctestArgumentType,
+                    &[
+                        ("function", &callee.signature.name),
+                        ("name", &parameter.name),
+                        ("expected", expected),
+                        ("found", &found.to_string()),
+                    ],
+                ));
+            }
+        }
+    }
+
+    fn check_output(
+        &self,
+        parser: &mut Parser,
+        call: &Node,
+        output: &str,
+        out: &mut Vec<Diagnostic>,
+    ) {
+        let Some(signature) = self.callee(call).map(|c| &c.signature) else {
+            return;
+        };
+        let Some(expected) = &signature.returns else {
+            return;
+        };
+        let Ok(tree) = parser.parse_string(output) else {
+            return;
+        };
+        let root = tree.root_node();
+        let value = Some(root)
+            .filter(|r| !r.has_error() && r.named_child_count() == 1)
+            .and_then(|r| r.named_child(0))
+            .filter(|s| s.kind() == "expression_statement" && s.named_child_count() == 1)
+            .and_then(|s| s.named_child(0));
+        let Some(value) = value else {
+            return;
+        };
+        let found = expression_type(&value, output.as_bytes());
+        if accepts(expected, &found) == Some(false) {
+            out.push(Diagnostic::from_message(
+                Severity::Warning,
+                self.file.path(),
+                self.host_span(call),
+                MessageId::DoctestReturnType,
+                &[
+                    ("function", &signature.name),
+                    ("expected", expected),
+                    ("found", &found.to_string()),
+                ],
+            ));
+        }
+    }
+
+    fn source_text(&self, node: &Node) -> &str {
+        let source = &self.injection.source;
+        source.slice(&source.node_span(node))
+    }
+
+    fn host_span(&self, node: &Node) -> crate::utils::Span {
+        self.injection
+            .host_span(&self.injection.source.node_span(node), self.file)
+    }
+}
+
+/// Whether a value of type `found` fits the annotation `expected`, with
+/// the numeric promotions `bool` to `int` to `float` to `complex`. `None`
+/// when either side is not a union of builtin scalars.
+pub(super) fn accepts(expected: &str, found: &Type) -> Option<bool> {
+    let expected: Vec<&str> = expected
+        .split('|')
+        .map(|t| match t.trim() {
+            "NoneType" => "None",
+            t => t,
+        })
+        .collect();
+    let found = found.widened().to_string();
+    if !SCALARS.contains(&found.as_str()) || !expected.iter().all(|t| SCALARS.contains(t)) {
+        return None;
+    }
+    let rank = |t: &str| {
+        ["bool", "int", "float", "complex"]
+            .iter()
+            .position(|n| *n == t)
+    };
+    Some(expected.iter().any(|t| match (rank(&found), rank(t)) {
+        (Some(found), Some(expected)) => found <= expected,
+        _ => *t == found,
+    }))
+}
+
+fn text<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    file.slice(&file.node_span(node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<String> {
+        let file = SourceFile::from_text("t.py", source);
+        let mut parser = Parser::new().unwrap();
+        let tree = parser.parse_string(source).unwrap();
+        check_doctests(&mut parser, &tree, &file)
+            .unwrap()
+            .iter()
+            .map(|d| format!("{}: {}", d.span.start.line, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_doctest_checked_against_signature() {
+        let source = "def double(x: int) -> int:\n    \"\"\"Double x.\n\n    >>> double(2)\n    \
+                      4\n    >>> double('a')\n    'aa'\n    >>> double(x=True)\n    2\n    \
+                      \"\"\"\n    return x * 2\n";
+        assert_eq!(
+            check(source),
+            [
+                "5: Doctest passes str to parameter 'x' of 'double', which expects int",
+                "5: Doctest expects 'double' to return str, but it returns int",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doctest_uses_inferred_return_type() {
+        let source = "def name(flag=False):\n    \"\"\"\n    >>> name(1.5)\n    >>> label()\n    \
+                      'x'\n    \"\"\"\n    return 'n'\n\ndef label():\n    return None\n";
+        assert_eq!(
+            check(source),
+            ["3: Doctest expects 'label' to return str, but it returns None"]
+        );
+    }
+
+    #[test]
+    fn test_accepts() {
+        assert_eq!(accepts("float", &Type::Int), Some(true));
+        assert_eq!(accepts("int | None", &Type::None), Some(true));
+        assert_eq!(accepts("int", &Type::Str), Some(false));
+        assert_eq!(accepts("List[int]", &Type::Str), None);
+        assert_eq!(accepts("int", &Type::Unknown), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! Detects functions and classes defined twice in the same block.
+//!
+//! A second `def` or `class` of a name replaces the first, which is then
+//! unreachable by that name, so the first is usually a leftover from
+//! copying code. A redefinition is fine when the earlier definition was
+//! used in between, as by `@name.setter` or by code that keeps a reference,
+//! and when either definition is an `@overload`. Definitions in different
+//! branches of an `if` or `try`, such as `if TYPE_CHECKING:` and its
+//! `else:`, are in different blocks and never conflict.
+
+use std::collections::HashMap;
+
+use tree_sitter::{Node, Tree};
+
+use super::decorators::{callee, decorators};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::utils::SourceFile;
+
+/// Reports the definitions in a parsed Python file that replace an unused
+/// definition of the same name in the same block.
+pub(super) fn check_duplicates(tree: &Tree, file: &SourceFile) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if matches!(node.kind(), "module" | "block") {
+            check_block(&node, file, &mut diagnostics);
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+fn check_block(block: &Node, file: &SourceFile, out: &mut Vec<Diagnostic>) {
+    let source = file.text().as_bytes();
+    let mut cursor = block.walk();
+    let statements: Vec<Node> = block.named_children(&mut cursor).collect();
+    // The statement index and name node of each name's latest definition.
+    let mut defined: HashMap<&str, (usize, Node)> = HashMap::new();
+    for (i, statement) in statements.iter().enumerate() {
+        let Some(current) = definition(statement) else {
+            continue;
+        };
+        let Some(name) = current.child_by_field_name("name") else {
+            continue;
+        };
+        let text = name.utf8_text(source).unwrap_or_default();
+        if let Some(&(first, earlier)) = defined.get(text) {
+            let used = statements[first + 1..i]
+                .iter()
+                .chain(std::iter::once(statement))
+                .any(|s| references(s, &current, text, source));
+            let overloaded = [&statements[first], statement]
+                .iter()
+                .any(|s| definition(s).is_some_and(|d| is_overload(&d, source)));
+            if !used && !overloaded && text != "_" {
+                let line = (file.node_span(&earlier).start.line + 1).to_string();
+                out.push(
+                    Diagnostic::from_message(
+                        Severity::Warning,
+                        file.path(),
+                        file.node_span(&name),
+                        MessageId::DuplicateDefinition,
+                        &[("name", text), ("line", &line)],
+                    )
+                    .with_related(
+                        file.path(),
+                        file.node_span(&earlier),
+                        "First defined here",
+                    ),
+                );
+            }
+        }
+        defined.insert(text, (i, name));
+    }
+}
+
+/// The function or class a statement defines, decorated or not.
+fn definition<'t>(statement: &Node<'t>) -> Option<Node<'t>> {
+    match statement.kind() {
+        "function_definition" | "class_definition" => Some(*statement),
+        "decorated_definition" => statement.child_by_field_name("definition"),
+        _ => None,
+    }
+}
+
+/// Whether `node` mentions `name` outside of `definition`, so the
+/// decorators of a decorated definition count but its body doesn't.
+fn references(node: &Node, definition: &Node, name: &str, source: &[u8]) -> bool {
+    if node == definition {
+        return false;
+    }
+    if node.kind() == "identifier" {
+        return node.utf8_text(source) == Ok(name);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    children
+        .iter()
+        .any(|child| references(child, definition, name, source))
+}
+
+fn is_overload(definition: &Node, source: &[u8]) -> bool {
+    decorators(definition, source).into_iter().any(|decorator| {
+        let callee = callee(decorator);
+        callee.rsplit('.').next() == Some("overload")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_duplicate_definitions() {
+        let source = "def f():\n    pass\n\ndef f():\n    pass\n\n\
+                      class C:\n    @property\n    def x(self):\n        return 1\n\
+                      \x20   @x.setter\n    def x(self, v):\n        pass\n\
+                      \x20   @overload\n    def g(self, a: int) -> int: ...\n\
+                      \x20   def g(self, a):\n        return a\n\n\
+                      if TYPE_CHECKING:\n    class D: ...\nelse:\n    class D:\n        pass\n\n\
+                      def h():\n    pass\nold_h = h\ndef h():\n    pass\n\n\
+                      class C:\n    pass\n";
+        let file = SourceFile::from_text("a.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let diagnostics = check_duplicates(&tree, &file);
+        let found: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.span.start.line, d.args["name"].as_str()))
+            .collect();
+        assert_eq!(found, [(3, "f"), (30, "C")]);
+        assert_eq!(diagnostics[0].args["line"], "1");
+        assert_eq!(diagnostics[1].related[0].span.start.line, 6);
+    }
+}
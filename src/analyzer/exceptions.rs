@@ -0,0 +1,452 @@
+//! Checks the exception classes `except` clauses catch.
+//!
+//! An `except` clause must name an exception class or a tuple of them;
+//! catching anything else raises `TypeError` once an exception reaches the
+//! clause. Clauses are tried in order, so a clause whose classes all derive
+//! from classes an earlier clause of the same `try` catches never runs.
+//! Classes are the builtin exceptions and classes defined in the file whose
+//! bases are known; names from `import *` and other modules aren't checked.
+
+use std::collections::BTreeMap;
+
+use tree_sitter::{Node, Tree};
+
+use super::calls::has_wildcard_import;
+use super::mro::{is_typing_base, resolve_base};
+use super::{Analyzer, ClassInfo};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::{Type, TypeEnv};
+use crate::utils::SourceFile;
+
+/// Each builtin exception class with its base.
+const BUILTIN_EXCEPTIONS: &[(&str, &str)] = &[
+    ("BaseException", ""),
+    ("BaseExceptionGroup", "BaseException"),
+    ("GeneratorExit", "BaseException"),
+    ("KeyboardInterrupt", "BaseException"),
+    ("SystemExit", "BaseException"),
+    ("Exception", "BaseException"),
+    ("ArithmeticError", "Exception"),
+    ("FloatingPointError", "ArithmeticError"),
+    ("OverflowError", "ArithmeticError"),
+    ("ZeroDivisionError", "ArithmeticError"),
+    ("AssertionError", "Exception"),
+    ("AttributeError", "Exception"),
+    ("BufferError", "Exception"),
+    ("EOFError", "Exception"),
+    ("ExceptionGroup", "Exception"),
+    ("ImportError", "Exception"),
+    ("ModuleNotFoundError", "ImportError"),
+    ("LookupError", "Exception"),
+    ("IndexError", "LookupError"),
+    ("KeyError", "LookupError"),
+    ("MemoryError", "Exception"),
+    ("NameError", "Exception"),
+    ("UnboundLocalError", "NameError"),
+    ("OSError", "Exception"),
+    ("BlockingIOError", "OSError"),
+    ("ChildProcessError", "OSError"),
+    ("ConnectionError", "OSError"),
+    ("BrokenPipeError", "ConnectionError"),
+    ("ConnectionAbortedError", "ConnectionError"),
+    ("ConnectionRefusedError", "ConnectionError"),
+    ("ConnectionResetError", "ConnectionError"),
+    ("FileExistsError", "OSError"),
+    ("FileNotFoundError", "OSError"),
+    ("InterruptedError", "OSError"),
+    ("IsADirectoryError", "OSError"),
+    ("NotADirectoryError", "OSError"),
+    ("PermissionError", "OSError"),
+    ("ProcessLookupError", "OSError"),
+    ("TimeoutError", "OSError"),
+    ("ReferenceError", "Exception"),
+    ("RuntimeError", "Exception"),
+    ("NotImplementedError", "RuntimeError"),
+    ("RecursionError", "RuntimeError"),
+    ("StopAsyncIteration", "Exception"),
+    ("StopIteration", "Exception"),
+    ("SyntaxError", "Exception"),
+    ("IndentationError", "SyntaxError"),
+    ("TabError", "IndentationError"),
+    ("SystemError", "Exception"),
+    ("TypeError", "Exception"),
+    ("ValueError", "Exception"),
+    ("UnicodeError", "ValueError"),
+    ("UnicodeDecodeError", "UnicodeError"),
+    ("UnicodeEncodeError", "UnicodeError"),
+    ("UnicodeTranslateError", "UnicodeError"),
+    ("Warning", "Exception"),
+    ("BytesWarning", "Warning"),
+    ("DeprecationWarning", "Warning"),
+    ("EncodingWarning", "Warning"),
+    ("FutureWarning", "Warning"),
+    ("ImportWarning", "Warning"),
+    ("PendingDeprecationWarning", "Warning"),
+    ("ResourceWarning", "Warning"),
+    ("RuntimeWarning", "Warning"),
+    ("SyntaxWarning", "Warning"),
+    ("UnicodeWarning", "Warning"),
+    ("UserWarning", "Warning"),
+];
+
+/// Builtin names for `OSError`.
+const OS_ERROR_ALIASES: &[&str] = &["EnvironmentError", "IOError"];
+
+/// Builtin classes that aren't exceptions.
+const BUILTIN_CLASSES: &[&str] = &[
+    "bool",
+    "bytearray",
+    "bytes",
+    "complex",
+    "dict",
+    "float",
+    "frozenset",
+    "int",
+    "list",
+    "object",
+    "set",
+    "str",
+    "tuple",
+    "type",
+];
+
+/// What an element of an `except` clause's expression names.
+enum Caught {
+    /// An exception class: a builtin's name or a class defined in the file.
+    Class(String),
+    /// A value that isn't an exception class.
+    NotClass,
+    /// Anything else, which may or may not be an exception class.
+    Unknown,
+}
+
+/// Whether an `except` clause is an `except*` clause, which catches the
+/// members of an exception group.
+pub(super) fn catches_group(clause: &Node) -> bool {
+    clause.child(1).is_some_and(|c| c.kind() == "*")
+}
+
+/// The type of the exception an `except` clause with the given expression
+/// binds, resolving names through `env`.
+pub(super) fn caught_type(node: &Node, source: &[u8], env: &TypeEnv) -> Type {
+    match node.kind() {
+        "identifier" => {
+            let name = node.utf8_text(source).unwrap_or_default();
+            match env.lookup(name) {
+                Some(Type::Named(class)) if class.rsplit('.').next() == Some(name) => {
+                    Type::Named(class.clone())
+                },
+                Some(_) => Type::Unknown,
+                None => match builtin_exception(name) {
+                    Some(name) => Type::Named(name.to_string()),
+                    None => Type::Unknown,
+                },
+            }
+        },
+        "parenthesized_expression" | "tuple" => {
+            let mut cursor = node.walk();
+            let types: Vec<Type> = node
+                .named_children(&mut cursor)
+                .filter(|n| n.kind() != "comment")
+                .map(|n| caught_type(&n, source, env))
+                .collect();
+            if types.contains(&Type::Unknown) {
+                Type::Unknown
+            } else {
+                Type::union_of(types)
+            }
+        },
+        _ => Type::Unknown,
+    }
+}
+
+/// Checks the `except` clauses of every `try` statement in a parsed
+/// Python file. `analyzer` must have analyzed the same tree.
+pub(super) fn check_handlers(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let wildcard = has_wildcard_import(&tree.root_node());
+    let mut diagnostics = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() != "try_statement" {
+            continue;
+        }
+        // The classes earlier clauses catch, with how they are written.
+        let mut earlier: Vec<(String, &str)> = Vec::new();
+        let mut cursor = node.walk();
+        for clause in node
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "except_clause")
+        {
+            let Some(expression) = handled_expression(&clause) else {
+                // A bare `except:` must come last.
+                break;
+            };
+            let elements = elements(&expression);
+            let caught: Vec<Caught> = elements
+                .iter()
+                .map(|element| classify(element, file, analyzer, wildcard))
+                .collect();
+            for (element, caught) in elements.iter().zip(&caught) {
+                if let Caught::NotClass = caught {
+                    diagnostics.push(Diagnostic::from_message(
+                        Severity::Error,
+                        file.path(),
+                        file.node_span(element),
+                        MessageId::NotExceptionClass,
+                        &[("expression", text(file, element))],
+                    ));
+                }
+            }
+            let classes: Vec<&str> = caught
+                .iter()
+                .map_while(|caught| match caught {
+                    Caught::Class(class) => Some(class.as_str()),
+                    _ => None,
+                })
+                .collect();
+            if classes.len() != caught.len() {
+                continue;
+            }
+            let shadowing: Option<Vec<&str>> = classes
+                .iter()
+                .map(|class| {
+                    let ancestors = ancestors(class, analyzer.classes()).0;
+                    earlier
+                        .iter()
+                        .find(|(caught, _)| ancestors.contains(caught))
+                        .map(|(_, written)| *written)
+                })
+                .collect();
+            if let Some(shadowing) = shadowing {
+                diagnostics.push(Diagnostic::from_message(
+                    Severity::Warning,
+                    file.path(),
+                    file.node_span(&expression),
+                    MessageId::ShadowedExceptClause,
+                    &[("caught", text(file, &expression)), ("earlier", shadowing[0])],
+                ));
+            }
+            for (class, element) in classes.into_iter().zip(&elements) {
+                earlier.push((class.to_string(), text(file, element)));
+            }
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// The expression naming what an `except` clause catches, or `None` for a
+/// bare `except:`.
+fn handled_expression<'t>(clause: &Node<'t>) -> Option<Node<'t>> {
+    let mut cursor = clause.walk();
+    let expression = clause
+        .named_children(&mut cursor)
+        .find(|c| !matches!(c.kind(), "block" | "comment"))?;
+    match expression.kind() {
+        "as_pattern" => expression.named_child(0),
+        _ => Some(expression),
+    }
+}
+
+/// The elements of an `except` clause's expression: the items of a tuple,
+/// else the expression itself.
+fn elements<'t>(expression: &Node<'t>) -> Vec<Node<'t>> {
+    match expression.kind() {
+        "parenthesized_expression" | "tuple" => {
+            let mut cursor = expression.walk();
+            expression
+                .named_children(&mut cursor)
+                .filter(|n| n.kind() != "comment")
+                .flat_map(|n| elements(&n))
+                .collect()
+        },
+        _ => vec![*expression],
+    }
+}
+
+fn classify(element: &Node, file: &SourceFile, analyzer: &Analyzer, wildcard: bool) -> Caught {
+    let classes = analyzer.classes();
+    match element.kind() {
+        "identifier" => {
+            let name = text(file, element);
+            match analyzer.type_at(element.start_byte()) {
+                Some(Type::Named(class)) if class.rsplit('.').next() == Some(name) => {
+                    match is_exception(class, classes) {
+                        Some(false) => Caught::NotClass,
+                        _ => Caught::Class(class.clone()),
+                    }
+                },
+                Some(Type::Tuple(_)) => Caught::Unknown,
+                Some(ty) if ty.widened().is_builtin() => Caught::NotClass,
+                Some(_) => Caught::Unknown,
+                None if wildcard => Caught::Unknown,
+                None => match builtin_exception(name) {
+                    Some(name) => Caught::Class(name.to_string()),
+                    None if BUILTIN_CLASSES.contains(&name) => Caught::NotClass,
+                    None => Caught::Unknown,
+                },
+            }
+        },
+        "integer"
+        | "float"
+        | "string"
+        | "concatenated_string"
+        | "true"
+        | "false"
+        | "none"
+        | "list"
+        | "dictionary"
+        | "set" => Caught::NotClass,
+        // An instance of an exception class rather than the class.
+        "call" => {
+            let callee = element
+                .child_by_field_name("function")
+                .filter(|f| f.kind() == "identifier");
+            match callee.map(|callee| classify(&callee, file, analyzer, wildcard)) {
+                Some(Caught::Class(_)) => Caught::NotClass,
+                _ => Caught::Unknown,
+            }
+        },
+        _ => Caught::Unknown,
+    }
+}
+
+/// The builtin exception class a name refers to, if any.
+fn builtin_exception(name: &str) -> Option<&'static str> {
+    if OS_ERROR_ALIASES.contains(&name) {
+        return Some("OSError");
+    }
+    BUILTIN_EXCEPTIONS
+        .iter()
+        .find(|(class, _)| *class == name)
+        .map(|(class, _)| *class)
+}
+
+/// Whether a class defined in the file derives from `BaseException`, or
+/// `None` if some of its bases aren't known.
+fn is_exception(class: &str, classes: &BTreeMap<String, ClassInfo>) -> Option<bool> {
+    let (ancestors, complete) = ancestors(class, classes);
+    if ancestors.iter().any(|a| a == "BaseException") {
+        Some(true)
+    } else if complete {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// The known classes a class derives from, the class itself first, and
+/// whether all of them are known.
+fn ancestors(class: &str, classes: &BTreeMap<String, ClassInfo>) -> (Vec<String>, bool) {
+    let mut ancestors = Vec::new();
+    let mut complete = true;
+    let mut pending = vec![class.to_string()];
+    while let Some(class) = pending.pop() {
+        if ancestors.contains(&class) {
+            continue;
+        }
+        if let Some(info) = classes.get(&class) {
+            for base in &info.bases {
+                // Keyword arguments like `metaclass=ABCMeta` aren't bases.
+                if is_typing_base(base) || base.contains('=') {
+                    continue;
+                }
+                match resolve_base(&class, base, classes) {
+                    Some(base) => pending.push(base.to_string()),
+                    None => match builtin_exception(base) {
+                        Some(base) => pending.push(base.to_string()),
+                        None => complete = false,
+                    },
+                }
+            }
+        } else if let Some((_, base)) = BUILTIN_EXCEPTIONS.iter().find(|(c, _)| *c == class) {
+            if !base.is_empty() {
+                pending.push(base.to_string());
+            }
+        }
+        ancestors.push(class);
+    }
+    (ancestors, complete)
+}
+
+fn text<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    file.slice(&file.node_span(node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn analyze(source: &str) -> (Tree, Analyzer) {
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        (tree, analyzer)
+    }
+
+    fn check(source: &str) -> Vec<String> {
+        let file = SourceFile::from_text("a.py", source);
+        let (tree, analyzer) = analyze(source);
+        check_handlers(&tree, &file, &analyzer)
+            .iter()
+            .map(|d| format!("{}: {}", d.span.start.line, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_except_clauses() {
+        let source =
+            "class AppError(Exception):\n    pass\n\nclass Timeout(AppError, OSError):\n    \
+                      pass\n\nclass Plain:\n    pass\n\nlimit = 3\ntry:\n    run()\n\
+                      except (KeyError, AppError):\n    pass\nexcept IndexError:\n    pass\n\
+                      except LookupError:\n    pass\nexcept (KeyError, Timeout):\n    pass\n\
+                      except Plain:\n    pass\nexcept (ValueError(), 'x', limit, int):\n    pass\n\
+                      except IOError:\n    pass\nexcept FileNotFoundError:\n    pass\n\
+                      except:\n    pass\n";
+        assert_eq!(
+            check(source),
+            [
+                "18: Clause for '(KeyError, Timeout)' never runs, since the earlier clause for \
+                 'KeyError' catches the same exceptions",
+                "20: 'Plain' is not an exception class and can't be caught",
+                "22: 'ValueError()' is not an exception class and can't be caught",
+                "22: ''x'' is not an exception class and can't be caught",
+                "22: 'limit' is not an exception class and can't be caught",
+                "22: 'int' is not an exception class and can't be caught",
+                "26: Clause for 'FileNotFoundError' never runs, since the earlier clause for \
+                 'IOError' catches the same exceptions",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_exception_classes() {
+        let source = "from errors import Failure\n\nclass Wrapped(Failure):\n    pass\n\n\
+                      class Mixed(Base, Exception):\n    pass\n\nE = (KeyError, Failure)\ntry:\n    \
+                      run()\nexcept Failure:\n    pass\nexcept Wrapped:\n    pass\nexcept Mixed:\n    \
+                      pass\nexcept E:\n    pass\nexcept socket.timeout:\n    pass\n\
+                      except factory():\n    pass\n";
+        assert!(check(source).is_empty());
+    }
+
+    #[test]
+    fn test_except_targets_bound_to_exception_types() {
+        let source = "class AppError(Exception):\n    pass\n\ntry:\n    run()\n\
+                      except AppError as a:\n    pass\nexcept (KeyError, IOError) as b:\n    pass\n\
+                      except errors.Failure as c:\n    pass\n\ntry:\n    run()\n\
+                      except* ValueError as g:\n    pass\n";
+        let (_, analyzer) = analyze(source);
+        let module = &analyzer.scope("").unwrap().variables;
+        assert_eq!(module["a"].to_string(), "AppError");
+        assert_eq!(module["b"].to_string(), "KeyError | OSError");
+        assert_eq!(module["c"], Type::Unknown);
+        assert_eq!(module["g"].to_string(), "ExceptionGroup[ValueError]");
+    }
+}
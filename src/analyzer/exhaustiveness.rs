@@ -0,0 +1,278 @@
+//! Checks that `match` statements handle every value of their subject.
+//!
+//! When the subject is a variable whose type has finitely many values, an
+//! enum defined in the file, a `Literal[...]`, `bool`, `None`, or a union
+//! of those and classes, each case pattern is resolved to the values it
+//! can match. A match whose cases leave values unhandled is reported, as
+//! is a case that only matches values earlier cases already handle. A
+//! pattern whose values can't be resolved, such as a subclass of a class
+//! in the union or a mapping pattern, leaves the statement unchecked.
+
+use std::collections::BTreeSet;
+use tree_sitter::{Node, Tree};
+
+use super::Analyzer;
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Bases that make a class an enum whose members are its class variables.
+const ENUM_BASES: &[&str] = &["Enum", "IntEnum", "StrEnum"];
+
+/// Checks every `match` statement in a parsed Python file. `analyzer` must
+/// have analyzed the same tree.
+pub(super) fn check_matches(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let source = file.text().as_bytes();
+    let mut diagnostics = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    'statements: while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        let subject = node
+            .child_by_field_name("subject")
+            .filter(|s| node.kind() == "match_statement" && s.kind() == "identifier");
+        let Some(subject) = subject else {
+            continue;
+        };
+        let Some(domain) = analyzer
+            .type_at(subject.start_byte())
+            .and_then(|ty| domain(ty, analyzer, false))
+        else {
+            continue;
+        };
+        let mut handled = BTreeSet::new();
+        let mut unreachable = Vec::new();
+        for case in case_clauses(&node) {
+            let mut cursor = case.walk();
+            let patterns: Vec<Node> = case
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "case_pattern")
+                .collect();
+            let [pattern] = patterns.as_slice() else {
+                continue 'statements;
+            };
+            let Some((matches, covers)) = resolve(pattern, source, &domain) else {
+                continue 'statements;
+            };
+            if !matches.is_empty() && matches.is_subset(&handled) {
+                unreachable.push(case);
+            }
+            if case.child_by_field_name("guard").is_none() {
+                handled.extend(covers);
+            }
+        }
+        for case in unreachable {
+            diagnostics.push(Diagnostic::from_message(
+                Severity::Warning,
+                file.path(),
+                file.node_span(&case),
+                MessageId::UnreachableCode,
+                &[],
+            ));
+        }
+        let missing: Vec<&str> = domain
+            .iter()
+            .filter(|value| !handled.contains(*value))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            diagnostics.push(Diagnostic::from_message(
+                Severity::Warning,
+                file.path(),
+                file.span(node.start_byte(), subject.end_byte()),
+                MessageId::NonExhaustiveMatch,
+                &[
+                    ("subject", subject.utf8_text(source).unwrap_or_default()),
+                    ("missing", &missing.join(", ")),
+                ],
+            ));
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// The values a subject of type `ty` can have, as case patterns spell
+/// them, or `None` if there are too many to list. Classes only count as
+/// values in a union, where each stands for one branch of a closed set.
+fn domain(ty: &Type, analyzer: &Analyzer, in_union: bool) -> Option<Vec<String>> {
+    match ty {
+        Type::Union(members) => {
+            let mut values = Vec::new();
+            for member in members {
+                for value in domain(member, analyzer, true)? {
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+            }
+            Some(values)
+        },
+        Type::None => Some(vec!["None".to_string()]),
+        Type::Bool => Some(vec!["True".to_string(), "False".to_string()]),
+        Type::Literal(value) => Some(vec![value.clone()]),
+        Type::Named(name) => match enum_members(name, analyzer) {
+            Some(members) => Some(members),
+            None => in_union.then(|| vec![name.clone()]),
+        },
+        _ => None,
+    }
+}
+
+/// The members of an enum class defined in the file, as `Color.RED`.
+/// Methods, nested classes, and private names aren't members.
+fn enum_members(name: &str, analyzer: &Analyzer) -> Option<Vec<String>> {
+    let class = analyzer.classes.get(name)?;
+    let is_enum = class.bases.iter().any(|base| {
+        let base = base.rsplit('.').next().unwrap_or(base);
+        ENUM_BASES.contains(&base)
+    });
+    if !is_enum {
+        return None;
+    }
+    let members = class
+        .attributes
+        .iter()
+        .filter(|(member, ty)| {
+            let nested_class = matches!(ty, Type::Named(n) if n == &format!("{name}.{member}"));
+            !member.starts_with('_') && !matches!(ty, Type::Function { .. }) && !nested_class
+        })
+        .map(|(member, _)| format!("{name}.{member}"))
+        .collect();
+    Some(members)
+}
+
+/// The `case` clauses of a match statement, in order.
+fn case_clauses<'t>(statement: &Node<'t>) -> Vec<Node<'t>> {
+    let mut cursor = statement.walk();
+    let blocks: Vec<Node> = statement
+        .named_children(&mut cursor)
+        .filter(|c| c.kind() == "block")
+        .collect();
+    blocks
+        .iter()
+        .flat_map(|block| {
+            let mut cursor = block.walk();
+            block
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "case_clause")
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The values of `domain` a pattern may match, and the ones it matches
+/// whenever the subject has them, or `None` if the pattern refers to
+/// values outside the domain or is too complex to resolve.
+fn resolve(
+    pattern: &Node,
+    source: &[u8],
+    domain: &[String],
+) -> Option<(BTreeSet<String>, BTreeSet<String>)> {
+    let text = pattern.utf8_text(source).unwrap_or_default();
+    let everything = || {
+        let all: BTreeSet<String> = domain.iter().cloned().collect();
+        Some((all.clone(), all))
+    };
+    let value = |value: String| {
+        domain
+            .contains(&value)
+            .then(|| (BTreeSet::from([value.clone()]), BTreeSet::from([value])))
+    };
+    match pattern.kind() {
+        // `_` is an unnamed child.
+        "case_pattern" => match pattern.named_child(0) {
+            Some(inner) => resolve(&inner, source, domain),
+            None => everything(),
+        },
+        "as_pattern" => resolve(&pattern.named_child(0)?, source, domain),
+        "union_pattern" => {
+            let mut cursor = pattern.walk();
+            let alternatives: Vec<Node> = pattern.named_children(&mut cursor).collect();
+            let (mut matches, mut covers) = (BTreeSet::new(), BTreeSet::new());
+            for alternative in alternatives {
+                let (m, c) = resolve(&alternative, source, domain)?;
+                matches.extend(m);
+                covers.extend(c);
+            }
+            Some((matches, covers))
+        },
+        // A bare name captures the subject.
+        "dotted_name" if pattern.named_child_count() == 1 => everything(),
+        "dotted_name" | "integer" | "none" | "true" | "false" => value(text.to_string()),
+        "string" if text.starts_with(['"', '\'']) => {
+            value(format!("'{}'", text.trim_matches(['"', '\''])))
+        },
+        "class_pattern" => {
+            let class = pattern.named_child(0)?.utf8_text(source).ok()?;
+            let matches: BTreeSet<String> = if domain.iter().any(|v| v == class) {
+                BTreeSet::from([class.to_string()])
+            } else {
+                // `Color()` matches every member of the enum.
+                let prefix = format!("{class}.");
+                domain
+                    .iter()
+                    .filter(|v| v.starts_with(&prefix))
+                    .cloned()
+                    .collect()
+            };
+            if matches.is_empty() {
+                return None;
+            }
+            // Sub-patterns may not match every instance.
+            let covers = match pattern.named_child_count() {
+                1 => matches.clone(),
+                _ => BTreeSet::new(),
+            };
+            Some((matches, covers))
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::check_messages;
+
+    fn check(source: &str) -> Vec<String> {
+        check_messages(source, check_matches)
+    }
+
+    #[test]
+    fn test_unhandled_values() {
+        let source = "class Color(Enum):\n    RED = 1\n    GREEN = 2\n    BLUE = 3\n\n    \
+                      def describe(self):\n        pass\n\n\
+                      def f(c: Color, mode: Literal['r', 'w'], shape: Circle | Square | None):\n    \
+                      match c:\n        case Color.RED | Color.GREEN:\n            pass\n    \
+                      match mode:\n        case 'r':\n            pass\n    \
+                      match shape:\n        case Circle():\n            pass\n        \
+                      case Square(side=1) | None:\n            pass\n    \
+                      match c:\n        case Color():\n            pass\n    \
+                      match c:\n        case Color.RED:\n            pass\n        \
+                      case {'key': value}:\n            pass\n";
+        assert_eq!(
+            check(source),
+            [
+                "9: Match on 'c' doesn't handle Color.BLUE",
+                "12: Match on 'mode' doesn't handle 'w'",
+                "15: Match on 'shape' doesn't handle Square",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subsumed_cases() {
+        let source = "def f(flag: bool, mode: Optional[Literal[\"r\", \"w\"]]):\n    \
+                      match flag:\n        case True:\n            pass\n        \
+                      case False:\n            pass\n        case _:\n            pass\n    \
+                      match mode:\n        case 'r' | None:\n            pass\n        \
+                      case \"w\" if ready:\n            pass\n        case 'w':\n            \
+                      pass\n        case None:\n            pass\n";
+        assert_eq!(check(source), ["6: Code is unreachable", "15: Code is unreachable"]);
+    }
+}
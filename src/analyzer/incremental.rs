@@ -0,0 +1,531 @@
+//! Incremental checking: reusing the analysis of files that haven't
+//! changed.
+//!
+//! A file's analysis depends on its own text, its `.pyi` stub, and the
+//! project modules it imports, whose own imports it depends on in turn.
+//! [`DependencyGraph`] records those edges. [`IncrementalAnalysis`] keeps
+//! each file's result together with a fingerprint of the content hashes of
+//! every file it transitively depends on, so a later run only re-analyzes
+//! files that changed or that import, directly or not, a module that did.
+//! Every file reached is hashed on each run, but imports are only parsed
+//! again from files whose content changed. With an [`AnalysisCache`],
+//! results also outlive the process, so the first run of a new process
+//! only analyzes what changed since the last one.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use tree_sitter::Node;
+
+use super::cache::AnalysisCache;
+use super::parallel::{ParallelAnalysis, ResultCollector};
+use super::stubs::{find_stub, StubPaths};
+use super::AnalysisResult;
+use crate::error::{Error, Partial, Result};
+use crate::parser::Parser;
+use crate::utils::{
+    hash_file, infer_module_name, ContentHash, Fingerprint, ModuleResolver, SourceFile,
+};
+
+/// The files each file depends on: the project modules it imports and its
+/// stub.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    edges: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl DependencyGraph {
+    /// Creates an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the graph of `files` and of the modules they import,
+    /// transitively. Imports are resolved against `root` and the
+    /// directories the files' top-level packages are in; modules that
+    /// resolve nowhere, like the standard library's, are left out. Files
+    /// that fail to read or parse are returned as errors.
+    pub fn build(root: &Path, files: &[PathBuf], stubs: &StubPaths) -> Partial<Self> {
+        let mut imports = ImportCache::default();
+        let mut batch = Partial::new(Self::new());
+        let mut parser = match batch.record(Parser::new()) {
+            Some(parser) => parser,
+            None => return batch,
+        };
+        let (graph, _) = imports.graph(&mut parser, root, files, stubs, &mut batch.errors);
+        batch.value = graph;
+        batch
+    }
+
+    /// Records the files `file` depends on, replacing any recorded before.
+    pub fn insert(&mut self, file: PathBuf, dependencies: BTreeSet<PathBuf>) {
+        self.edges.insert(file, dependencies);
+    }
+
+    /// The files `file` depends on directly.
+    pub fn dependencies(&self, file: &Path) -> impl Iterator<Item = &Path> {
+        self.edges
+            .get(file)
+            .into_iter()
+            .flatten()
+            .map(PathBuf::as_path)
+    }
+
+    /// The files `file` depends on directly or through other files, in
+    /// path order. Import cycles are followed once.
+    pub fn transitive_dependencies(&self, file: &Path) -> BTreeSet<&Path> {
+        let mut seen = BTreeSet::new();
+        let mut stack: Vec<&Path> = self.dependencies(file).collect();
+        while let Some(dependency) = stack.pop() {
+            if dependency != file && seen.insert(dependency) {
+                stack.extend(self.dependencies(dependency));
+            }
+        }
+        seen
+    }
+}
+
+/// Analysis results kept from one run over a set of files to the next.
+#[derive(Debug, Default)]
+pub struct IncrementalAnalysis {
+    /// The modules each file parsed imports, with the hash of its text.
+    imports: ImportCache,
+    /// The result of each analyzed file, with the fingerprint of the files
+    /// it was computed from.
+    results: HashMap<PathBuf, (ContentHash, AnalysisResult)>,
+    /// Where results missing from `results` are looked up and new ones
+    /// stored.
+    cache: Option<AnalysisCache>,
+}
+
+/// The outcome of [`IncrementalAnalysis::analyze`].
+#[derive(Debug, Default)]
+pub struct IncrementalRun {
+    /// Results of the files analyzed without error, in the order given.
+    pub results: Vec<AnalysisResult>,
+    /// Files analyzed again, because they or a file they depend on changed
+    /// since the last run, or they weren't analyzed before.
+    pub analyzed: Vec<PathBuf>,
+}
+
+impl IncrementalAnalysis {
+    /// Creates a state with no results, so the first run analyzes every
+    /// file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also keeps results in `cache`, reusing those stored by earlier
+    /// processes.
+    pub fn with_cache(mut self, cache: AnalysisCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// The number of files with a kept result.
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Returns true if no results are kept.
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Forgets the files that no longer exist.
+    pub fn prune(&mut self) {
+        self.results.retain(|path, _| path.exists());
+        self.imports.0.retain(|path, _| path.exists());
+    }
+
+    /// Analyzes `files` with `engine`, reusing the result of each file
+    /// whose text and dependencies are unchanged since an earlier run.
+    /// Imports are resolved as in [`DependencyGraph::build`].
+    pub fn analyze(
+        &mut self,
+        root: &Path,
+        files: &[PathBuf],
+        engine: &ParallelAnalysis,
+    ) -> Partial<IncrementalRun> {
+        let stubs = engine.stubs();
+        let mut run = Partial::new(IncrementalRun::default());
+        let mut parser = match run.record(Parser::new()) {
+            Some(parser) => parser,
+            None => return run,
+        };
+        // Files that can't be read or parsed fail again when analyzed.
+        let (graph, hashes) = self
+            .imports
+            .graph(&mut parser, root, files, stubs, &mut Vec::new());
+
+        let mut fingerprints = HashMap::new();
+        let mut stale = Vec::new();
+        for file in files {
+            let mut fingerprint = Fingerprint::new();
+            if let Some(typeshed) = &stubs.typeshed {
+                fingerprint.add_bytes("typeshed", typeshed.to_string_lossy().as_bytes());
+            }
+            for path in std::iter::once(file.as_path()).chain(graph.transitive_dependencies(file)) {
+                if let Some(hash) = hashes.get(path) {
+                    fingerprint.add(path.to_string_lossy(), *hash);
+                }
+            }
+            let fingerprint = fingerprint.finish();
+            let hashed = hashes.contains_key(file.as_path());
+            let mut current = hashed
+                && self
+                    .results
+                    .get(file)
+                    .is_some_and(|(kept, _)| *kept == fingerprint);
+            if hashed && !current {
+                if let Some(result) = self.cache.as_ref().and_then(|c| c.get(file, fingerprint)) {
+                    self.results.insert(file.clone(), (fingerprint, result));
+                    current = true;
+                }
+            }
+            if !current {
+                stale.push(file.clone());
+            }
+            fingerprints.insert(file.clone(), fingerprint);
+        }
+
+        let collector = ResultCollector::new();
+        engine.run(stale.iter().cloned(), &collector);
+        for (file, result) in collector.into_outcomes() {
+            match result {
+                Ok(result) => {
+                    let fingerprint = fingerprints[&file];
+                    if let Some(cache) = &self.cache {
+                        // A result that can't be stored is only analyzed
+                        // again next time.
+                        if let Err(e) = cache.put(&file, fingerprint, &result) {
+                            log::warn!("{}", e);
+                        }
+                    }
+                    self.results.insert(file, (fingerprint, result));
+                },
+                Err(e) => {
+                    self.results.remove(&file);
+                    run.errors.push(e);
+                },
+            }
+        }
+        run.value.results = files
+            .iter()
+            .filter_map(|file| self.results.get(file))
+            .map(|(_, result)| result.clone())
+            .collect();
+        run.value.analyzed = stale;
+        run
+    }
+}
+
+/// The modules each Python file imports, by absolute name, with the hash
+/// of the text they were parsed from.
+#[derive(Debug, Default)]
+struct ImportCache(HashMap<PathBuf, (ContentHash, Vec<String>)>);
+
+impl ImportCache {
+    /// The dependency graph of `files` and everything they import, with
+    /// the hash of every file in it. Files that can't be hashed or parsed
+    /// have no edges, and their errors are added to `errors`.
+    fn graph(
+        &mut self,
+        parser: &mut Parser,
+        root: &Path,
+        files: &[PathBuf],
+        stubs: &StubPaths,
+        errors: &mut Vec<Error>,
+    ) -> (DependencyGraph, HashMap<PathBuf, ContentHash>) {
+        let resolver = module_resolver(root, files);
+        let mut graph = DependencyGraph::new();
+        let mut hashes = HashMap::new();
+        let mut pending: Vec<PathBuf> = files.iter().rev().cloned().collect();
+        while let Some(path) = pending.pop() {
+            if graph.edges.contains_key(&path) {
+                continue;
+            }
+            graph.insert(path.clone(), BTreeSet::new());
+            let hash = match hash_file(&path) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                },
+            };
+            hashes.insert(path.clone(), hash);
+            if !path
+                .extension()
+                .is_some_and(|ext| ext == "py" || ext == "pyi")
+            {
+                continue;
+            }
+            let imports = match self.0.get(&path) {
+                Some((parsed, imports)) if *parsed == hash => imports.clone(),
+                _ => match imported_modules(parser, &path, &resolver) {
+                    Ok(imports) => {
+                        self.0.insert(path.clone(), (hash, imports.clone()));
+                        imports
+                    },
+                    Err(e) => {
+                        errors.push(e.with_path(&path));
+                        continue;
+                    },
+                },
+            };
+            let dependencies: BTreeSet<PathBuf> = imports
+                .iter()
+                .filter_map(|module| resolver.resolve_file(module))
+                .chain(find_stub(&path, &stubs.dirs))
+                .filter(|dependency| *dependency != path)
+                .collect();
+            pending.extend(dependencies.iter().cloned());
+            graph.insert(path, dependencies);
+        }
+        (graph, hashes)
+    }
+}
+
+/// Resolves imports the way running the files would: from the directories
+/// their top-level packages are in, then from `root`.
+fn module_resolver(root: &Path, files: &[PathBuf]) -> ModuleResolver {
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for file in files {
+        let Some(module) = infer_module_name(file) else {
+            continue;
+        };
+        // `pkg/sub/mod.py` is `pkg.sub.mod` and `pkg/sub/__init__.py` is
+        // `pkg.sub`; both are three levels below the root.
+        let mut depth = module.split('.').count();
+        if file.file_stem().is_some_and(|stem| stem == "__init__") {
+            depth += 1;
+        }
+        if let Some(package_root) = file.ancestors().nth(depth) {
+            if !roots.iter().any(|r| r == package_root) {
+                roots.push(package_root.to_path_buf());
+            }
+        }
+    }
+    if !roots.iter().any(|r| r == root) {
+        roots.push(root.to_path_buf());
+    }
+    ModuleResolver::new(roots)
+}
+
+/// The absolute names of the modules a Python file imports, including the
+/// packages `import a.b` imports first and the names `from a import b`
+/// imports, which may be submodules. Relative imports are resolved from the
+/// file's own module name.
+fn imported_modules(
+    parser: &mut Parser,
+    path: &Path,
+    resolver: &ModuleResolver,
+) -> Result<Vec<String>> {
+    let file = SourceFile::read(path)?;
+    let tree = parser.parse_string(file.text())?;
+    let source = file.text().as_bytes();
+    let module = resolver.module_name(path).unwrap_or_default();
+    let is_package = path.file_stem().is_some_and(|stem| stem == "__init__");
+    let mut modules = BTreeSet::new();
+    let mut add = |name: &str| {
+        let mut prefix = String::new();
+        for part in name.split('.') {
+            if !prefix.is_empty() {
+                prefix.push('.');
+            }
+            prefix.push_str(part);
+            modules.insert(prefix.clone());
+        }
+    };
+
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "import_statement" => {
+                for name in imported_names(&node, source) {
+                    add(name);
+                }
+            },
+            "import_from_statement" => {
+                let Some(from) = node
+                    .child_by_field_name("module_name")
+                    .and_then(|m| absolute_module(&m, source, &module, is_package))
+                else {
+                    continue;
+                };
+                add(&from);
+                for name in imported_names(&node, source) {
+                    add(&format!("{}.{}", from, name));
+                }
+            },
+            _ => {
+                let mut cursor = node.walk();
+                stack.extend(node.named_children(&mut cursor));
+            },
+        }
+    }
+    Ok(modules.into_iter().collect())
+}
+
+/// The dotted names an import statement imports, without aliases. The
+/// module of a `from` import is not among them.
+fn imported_names<'s>(statement: &Node, source: &'s [u8]) -> Vec<&'s str> {
+    let mut cursor = statement.walk();
+    statement
+        .children_by_field_name("name", &mut cursor)
+        .filter_map(|name| match name.kind() {
+            "aliased_import" => name.child_by_field_name("name"),
+            _ => Some(name),
+        })
+        .filter_map(|name| name.utf8_text(source).ok())
+        .collect()
+}
+
+/// The absolute name of the module a `from` import imports from, or `None`
+/// if a relative import goes above the top-level package.
+fn absolute_module(
+    module_name: &Node,
+    source: &[u8],
+    module: &str,
+    is_package: bool,
+) -> Option<String> {
+    let text = module_name.utf8_text(source).ok()?;
+    if module_name.kind() != "relative_import" {
+        return Some(text.to_string());
+    }
+    let dots = text.chars().take_while(|c| *c == '.').count();
+    let relative = &text[dots..];
+    let mut package: Vec<&str> = module.split('.').filter(|p| !p.is_empty()).collect();
+    if !is_package {
+        package.pop();
+    }
+    for _ in 1..dots {
+        package.pop();
+    }
+    if package.is_empty() {
+        return None;
+    }
+    if !relative.is_empty() {
+        package.push(relative);
+    }
+    Some(package.join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::CACHE_DIR;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, files: &[(&str, &str)]) {
+        for (file, text) in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            &[
+                ("src/app/__init__.py", ""),
+                ("src/app/main.py", "import os\nfrom . import util\nfrom .models import User\n"),
+                ("src/app/util.py", "def helper():\n    from app.models import base\n"),
+                ("src/app/util.pyi", "def helper() -> None: ...\n"),
+                ("src/app/models/__init__.py", "from .base import Base\n"),
+                ("src/app/models/base.py", "from ... import nowhere\nimport app.main\n"),
+            ],
+        );
+        let src = root.join("src/app");
+        let files: Vec<PathBuf> = ["main.py", "util.py"].iter().map(|f| src.join(f)).collect();
+        let graph = DependencyGraph::build(root, &files, &StubPaths::default())
+            .into_result()
+            .unwrap();
+        let dependencies = |file: &str| -> Vec<String> {
+            graph
+                .dependencies(&src.join(file))
+                .map(|p| p.strip_prefix(&src).unwrap().display().to_string())
+                .collect()
+        };
+        assert_eq!(dependencies("main.py"), ["__init__.py", "models/__init__.py", "util.pyi"]);
+        assert_eq!(
+            dependencies("util.py"),
+            ["__init__.py", "models/__init__.py", "models/base.py", "util.pyi"]
+        );
+        assert_eq!(dependencies("models/base.py"), ["__init__.py", "main.py"]);
+        assert!(graph
+            .transitive_dependencies(&src.join("util.pyi"))
+            .is_empty());
+        assert_eq!(graph.transitive_dependencies(&src.join("main.py")).len(), 4);
+    }
+
+    #[test]
+    fn test_reanalyzes_changed_files_and_importers() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            &[
+                ("a.py", "import b\n"),
+                ("b.py", "from c import f\n"),
+                ("c.py", "def f():\n    pass\n"),
+                ("d.py", "x = 1\n"),
+            ],
+        );
+        let files: Vec<PathBuf> = ["a.py", "b.py", "c.py", "d.py"]
+            .iter()
+            .map(|f| root.join(f))
+            .collect();
+        let engine = ParallelAnalysis::new(StubPaths::default());
+        let mut state = IncrementalAnalysis::new();
+        let run = |state: &mut IncrementalAnalysis| {
+            let run = state.analyze(root, &files, &engine).into_result().unwrap();
+            assert_eq!(run.results.len(), 4);
+            run.analyzed
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(&mut state), ["a.py", "b.py", "c.py", "d.py"]);
+        assert!(run(&mut state).is_empty());
+
+        fs::write(root.join("c.py"), "def f(x):\n    pass\n").unwrap();
+        assert_eq!(run(&mut state), ["a.py", "b.py", "c.py"]);
+
+        // A new module can change what an unchanged import refers to.
+        write(root, &[("c.pyi", "def f(x: int) -> None: ...\n")]);
+        assert_eq!(run(&mut state), ["a.py", "b.py", "c.py"]);
+        fs::write(root.join("d.py"), "x = 2\n").unwrap();
+        assert_eq!(run(&mut state), ["d.py"]);
+        assert_eq!(state.len(), 4);
+    }
+
+    #[test]
+    fn test_results_persist_in_cache() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, &[("a.py", "import b\n"), ("b.py", "x = 1\n"), ("c.py", "y = 1\n")]);
+        let files: Vec<PathBuf> = ["a.py", "b.py", "c.py"]
+            .iter()
+            .map(|f| root.join(f))
+            .collect();
+        let engine = ParallelAnalysis::new(StubPaths::default());
+        let cache = AnalysisCache::new(root.join(CACHE_DIR), b"");
+        let run = |cache: &AnalysisCache| {
+            let mut state = IncrementalAnalysis::new().with_cache(cache.clone());
+            let run = state.analyze(root, &files, &engine).into_result().unwrap();
+            assert_eq!(run.results.len(), 3);
+            run.analyzed.len()
+        };
+        assert_eq!(run(&cache), 3);
+        assert_eq!(run(&cache), 0);
+        fs::write(root.join("b.py"), "x = 2\n").unwrap();
+        assert_eq!(run(&cache), 2);
+        assert_eq!(run(&AnalysisCache::new(root.join(CACHE_DIR), b"strict")), 3);
+    }
+}
@@ -0,0 +1,614 @@
+//! Type inference for Python expressions whose type follows from their
+//! syntax and the variables in scope: literals, container displays,
+//! operators, and names bound in a [`TypeEnv`].
+
+use tree_sitter::Node;
+
+use super::parameter_type;
+use super::signatures::{parameters, ParameterKind};
+use crate::parser::Parser;
+use crate::types::{Type, TypeEnv};
+
+/// Infers the type of an expression without consulting any bindings.
+/// Expressions that need name resolution or call evaluation are `Unknown`.
+pub fn expression_type(node: &Node, source: &[u8]) -> Type {
+    expression_type_in(node, source, &TypeEnv::new())
+}
+
+/// Infers the type of an expression, resolving names through `env`.
+/// Unbound names, and calls to anything but classes and functions bound
+/// in `env` and a few builtins, are `Unknown`.
+pub fn expression_type_in(node: &Node, source: &[u8], env: &TypeEnv) -> Type {
+    let expression_type = |n: &Node, source: &[u8]| expression_type_in(n, source, env);
+    match node.kind() {
+        "identifier" => {
+            let name = node.utf8_text(source).unwrap_or_default();
+            env.lookup(name).cloned().unwrap_or_default()
+        },
+        "integer" => Type::Int,
+        "float" => Type::Float,
+        "true" | "false" => Type::Bool,
+        "none" => Type::None,
+        "string" | "concatenated_string" => string_type(node, source),
+        "not_operator" => Type::Bool,
+        "comparison_operator" => comparison_type(node, source, env),
+        "parenthesized_expression" => named_children(node)
+            .first()
+            .map_or(Type::Unknown, |n| expression_type(n, source)),
+        "binary_operator" => {
+            let side = |field| {
+                node.child_by_field_name(field)
+                    .map_or(Type::Unknown, |n| expression_type(&n, source))
+            };
+            let operator = node
+                .child_by_field_name("operator")
+                .map(|op| op.kind())
+                .unwrap_or_default();
+            binary_type(operator, side("left"), side("right"))
+        },
+        "conditional_expression" => {
+            let branches: Vec<Type> = named_children(node)
+                .iter()
+                .step_by(2)
+                .map(|n| expression_type(n, source))
+                .collect();
+            if branches.contains(&Type::Unknown) {
+                Type::Unknown
+            } else {
+                Type::union_of(branches)
+            }
+        },
+        "unary_operator" => node
+            .child_by_field_name("argument")
+            .map_or(Type::Unknown, |arg| expression_type(&arg, source).widened()),
+        "list" => Type::List(Box::new(element_type(node, source, env))),
+        "set" => Type::Set(Box::new(element_type(node, source, env))),
+        "tuple" => Type::Tuple(
+            named_children(node)
+                .iter()
+                .map(|n| expression_type(n, source).widened())
+                .collect(),
+        ),
+        "dictionary" => {
+            let pairs: Vec<(Type, Type)> = named_children(node)
+                .iter()
+                .filter(|n| n.kind() == "pair")
+                .map(|pair| {
+                    let side = |field| {
+                        pair.child_by_field_name(field)
+                            .map_or(Type::Unknown, |n| expression_type(&n, source).widened())
+                    };
+                    (side("key"), side("value"))
+                })
+                .collect();
+            if pairs.is_empty() {
+                return Type::Dict(Box::new(Type::Any), Box::new(Type::Any));
+            }
+            let (keys, values) = pairs.into_iter().unzip();
+            Type::Dict(Box::new(Type::union_of(keys)), Box::new(Type::union_of(values)))
+        },
+        "call" => call_type(node, source, env),
+        "lambda" => lambda_type(node, source, env),
+        "await" => named_children(node)
+            .first()
+            .map_or(Type::Unknown, |n| expression_type(n, source).awaited()),
+        "list_comprehension" => Type::List(Box::new(Type::Unknown)),
+        "set_comprehension" => Type::Set(Box::new(Type::Unknown)),
+        "dictionary_comprehension" => Type::Dict(Box::new(Type::Unknown), Box::new(Type::Unknown)),
+        _ => Type::Unknown,
+    }
+}
+
+/// The result of a call: an instance when calling a class by its own
+/// name, the return type when calling a function, and the list or
+/// iterator the builtins `list`, `sorted`, `map`, and `filter` build.
+fn call_type(node: &Node, source: &[u8], env: &TypeEnv) -> Type {
+    let Some(function) = node
+        .child_by_field_name("function")
+        .filter(|f| f.kind() == "identifier")
+    else {
+        return Type::Unknown;
+    };
+    let name = function.utf8_text(source).unwrap_or_default();
+    match env.lookup(name) {
+        Some(Type::Named(class)) if class.rsplit('.').next() == Some(name) => {
+            return Type::Named(class.clone());
+        },
+        // Calling `cls` in a class method.
+        Some(ty) if ty.instance().is_some() => return ty.instance().cloned().unwrap_or_default(),
+        Some(Type::Function { returns, .. }) => return *returns.clone(),
+        Some(_) => return Type::Unknown,
+        None => {},
+    }
+    let arguments: Vec<Node> = node
+        .child_by_field_name("arguments")
+        .map(|args| named_children(&args))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|a| a.kind() != "keyword_argument")
+        .collect();
+    let argument = |i: usize| {
+        arguments
+            .get(i)
+            .map_or(Type::Unknown, |a| expression_type_in(a, source, env))
+    };
+    match (name, arguments.len()) {
+        ("list" | "sorted", 1..) => Type::List(Box::new(argument(0).iterated())),
+        ("map", 2..) => match argument(0) {
+            Type::Function { returns, .. } => Type::iterator(*returns),
+            _ => Type::iterator(Type::Unknown),
+        },
+        ("filter", 2) => Type::iterator(argument(1).iterated()),
+        _ => Type::Unknown,
+    }
+}
+
+/// The type of a lambda: a function of its parameters, typed as in
+/// [`lambda_scope`], returning the type of its body.
+fn lambda_type(node: &Node, source: &[u8], env: &TypeEnv) -> Type {
+    let (params, scope) = lambda_scope(node, source, env);
+    let returns = node
+        .child_by_field_name("body")
+        .map_or(Type::Unknown, |body| expression_type_in(&body, source, &scope));
+    Type::callable(params, returns)
+}
+
+/// The types of a lambda's positional parameters and the environment its
+/// body runs in. Parameters are typed by the arguments the lambda is
+/// called with when it is passed to a builtin (see [`lambda_arguments`]),
+/// else by their defaults.
+pub(super) fn lambda_scope(node: &Node, source: &[u8], env: &TypeEnv) -> (Vec<Type>, TypeEnv) {
+    let arguments = lambda_arguments(node, source, env);
+    let mut scope = TypeEnv::nested(env.clone());
+    let mut params = Vec::new();
+    let parameters = node
+        .child_by_field_name("parameters")
+        .map(|p| parameters(&p))
+        .unwrap_or_default();
+    for (param, kind) in parameters {
+        let Some((name, ty)) = parameter_type(&param, source, env) else {
+            continue;
+        };
+        if !matches!(kind, ParameterKind::PositionalOnly | ParameterKind::Positional) {
+            scope.bind(name, ty);
+            continue;
+        }
+        let ty = arguments.get(params.len()).cloned().unwrap_or(ty);
+        scope.bind(name, ty.clone());
+        params.push(ty);
+    }
+    (params, scope)
+}
+
+/// The types of the arguments a builtin calls a lambda with: the elements
+/// of the iterables passed to `map`, of the iterable passed to `filter`,
+/// and of the iterable a `key=` lambda orders in `sorted`, `min`, and
+/// `max`. Empty when the lambda is passed anywhere else.
+fn lambda_arguments(node: &Node, source: &[u8], env: &TypeEnv) -> Vec<Type> {
+    let keyword = node
+        .parent()
+        .filter(|p| p.kind() == "keyword_argument")
+        .and_then(|p| p.child_by_field_name("name"))
+        .map(|name| name.utf8_text(source).unwrap_or_default());
+    let list = match keyword {
+        Some(_) => node.parent().and_then(|p| p.parent()),
+        None => node.parent(),
+    };
+    let Some(call) = list
+        .filter(|l| l.kind() == "argument_list")
+        .and_then(|l| l.parent())
+    else {
+        return Vec::new();
+    };
+    let Some(name) = call
+        .child_by_field_name("function")
+        .filter(|f| f.kind() == "identifier")
+        .and_then(|f| f.utf8_text(source).ok())
+        .filter(|name| env.lookup(name).is_none())
+    else {
+        return Vec::new();
+    };
+    let positional: Vec<Node> = list
+        .map(|l| named_children(&l))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|a| a.kind() != "keyword_argument")
+        .collect();
+    let elements = |arguments: &[Node]| -> Vec<Type> {
+        arguments
+            .iter()
+            .map(|a| expression_type_in(a, source, env).iterated())
+            .collect()
+    };
+    let first = positional.first().is_some_and(|a| a == node);
+    match (name, keyword) {
+        ("map" | "filter", None) if first => elements(&positional[1..]),
+        ("sorted" | "min" | "max", Some("key")) => elements(&positional[..positional.len().min(1)]),
+        _ => Vec::new(),
+    }
+}
+
+/// The result of a comparison: `bool` for identity and membership tests,
+/// which can't be overloaded, and for ordering and equality of builtin
+/// values; unknown when an operand could overload the operator.
+fn comparison_type(node: &Node, source: &[u8], env: &TypeEnv) -> Type {
+    let operands = named_children(node);
+    let mut cursor = node.walk();
+    let overloadable = node
+        .children(&mut cursor)
+        .filter(|c| !c.is_named())
+        .any(|op| !matches!(op.kind(), "is" | "is not" | "in" | "not in" | "not"));
+    let builtin = |ty: Type| {
+        matches!(
+            ty,
+            Type::None
+                | Type::Bool
+                | Type::Int
+                | Type::Float
+                | Type::Str
+                | Type::Bytes
+                | Type::List(_)
+                | Type::Dict(..)
+                | Type::Set(_)
+                | Type::Tuple(_)
+        )
+    };
+    if !overloadable
+        || operands
+            .iter()
+            .all(|operand| builtin(expression_type_in(operand, source, env).widened()))
+    {
+        Type::Bool
+    } else {
+        Type::Unknown
+    }
+}
+
+/// The result of an arithmetic operator: numeric operands widen to the
+/// wider of the two (`/` always gives `float`), and `+` joins sequences of
+/// the same kind.
+pub(super) fn binary_type(operator: &str, left: Type, right: Type) -> Type {
+    let (left, right) = (left.widened(), right.widened());
+    let rank = |ty: &Type| match ty {
+        Type::Bool => Some(0),
+        Type::Int => Some(1),
+        Type::Float => Some(2),
+        _ => None,
+    };
+    match (rank(&left), rank(&right)) {
+        (Some(l), Some(r)) => match operator {
+            "/" => Type::Float,
+            "+" | "-" | "*" | "//" | "%" | "**" => {
+                if l.max(r) == 2 {
+                    Type::Float
+                } else {
+                    Type::Int
+                }
+            },
+            "&" | "|" | "^" if l.max(r) == 0 => Type::Bool,
+            "&" | "|" | "^" | "<<" | ">>" if l.max(r) < 2 => Type::Int,
+            _ => Type::Unknown,
+        },
+        _ => match (operator, left, right) {
+            ("+", Type::Str, Type::Str) | ("%", Type::Str, _) => Type::Str,
+            ("*", Type::Str, Type::Int) | ("*", Type::Int, Type::Str) => Type::Str,
+            ("+", Type::Bytes, Type::Bytes) => Type::Bytes,
+            ("+", Type::List(l), Type::List(r)) => {
+                Type::List(Box::new(Type::union_of(vec![*l, *r])))
+            },
+            _ => Type::Unknown,
+        },
+    }
+}
+
+/// Converts a type annotation such as `Optional[List[int]]` or `int | None`
+/// into a [`Type`]. Names that aren't builtins become [`Type::Named`].
+pub fn annotation_type(node: &Node, source: &[u8]) -> Type {
+    let text = |n: &Node| n.utf8_text(source).unwrap_or_default().to_string();
+    match node.kind() {
+        "type" => node
+            .named_child(0)
+            .map_or(Type::Unknown, |n| annotation_type(&n, source)),
+        "none" => Type::None,
+        // Only valid as the parameter list of `Callable`.
+        "list" => Type::Tuple(
+            named_children(node)
+                .iter()
+                .map(|n| annotation_type(n, source))
+                .collect(),
+        ),
+        "identifier" | "attribute" => match text(node).as_str() {
+            "None" => Type::None,
+            "Any" | "typing.Any" => Type::Any,
+            // Bound to the receiver's class where the method is looked up.
+            "Self" | "typing.Self" | "typing_extensions.Self" => Type::Named("Self".to_string()),
+            "bool" => Type::Bool,
+            "int" => Type::Int,
+            "float" => Type::Float,
+            "str" => Type::Str,
+            "bytes" => Type::Bytes,
+            "list" | "List" => Type::List(Box::new(Type::Any)),
+            "dict" | "Dict" => Type::Dict(Box::new(Type::Any), Box::new(Type::Any)),
+            "set" | "Set" => Type::Set(Box::new(Type::Any)),
+            name => Type::Named(name.to_string()),
+        },
+        "union_type" => Type::union_of(
+            named_children(node)
+                .iter()
+                .map(|n| annotation_type(n, source))
+                .collect(),
+        ),
+        "binary_operator" => {
+            let side = |field| {
+                node.child_by_field_name(field)
+                    .map_or(Type::Unknown, |n| annotation_type(&n, source))
+            };
+            Type::union_of(vec![side("left"), side("right")])
+        },
+        "string" => {
+            let quoted = text(node);
+            forward_reference(quoted.trim_matches(['"', '\'']))
+        },
+        "subscript" | "generic_type" => {
+            let (name, args) = match node.kind() {
+                "subscript" => {
+                    let Some(value) = node.child_by_field_name("value") else {
+                        return Type::Unknown;
+                    };
+                    let mut cursor = node.walk();
+                    let args: Vec<Node> = node
+                        .children_by_field_name("subscript", &mut cursor)
+                        .collect();
+                    (text(&value), args)
+                },
+                _ => {
+                    let (Some(name), Some(params)) = (node.named_child(0), node.named_child(1))
+                    else {
+                        return Type::Unknown;
+                    };
+                    (text(&name), named_children(&params))
+                },
+            };
+            let args: Vec<Node> = args
+                .iter()
+                .flat_map(|a| match a.kind() {
+                    "tuple" | "expression_list" => named_children(a),
+                    _ => vec![*a],
+                })
+                .collect();
+            let name_tail = name.trim_start_matches("typing.");
+            if name_tail == "Literal" {
+                return Type::union_of(args.iter().map(|a| literal_type(a, source)).collect());
+            }
+            if let ("tuple" | "Tuple", [item, ellipsis]) = (name_tail, args.as_slice()) {
+                if ellipsis.kind() == "ellipsis" {
+                    return Type::variadic_tuple(annotation_type(item, source));
+                }
+            }
+            let args: Vec<Type> = args.iter().map(|a| annotation_type(a, source)).collect();
+            let arg = |i: usize| Box::new(args.get(i).cloned().unwrap_or(Type::Any));
+            match name.trim_start_matches("typing.") {
+                "Final" => *arg(0),
+                "list" | "List" => Type::List(arg(0)),
+                "set" | "Set" | "frozenset" | "FrozenSet" => Type::Set(arg(0)),
+                "dict" | "Dict" => Type::Dict(arg(0), arg(1)),
+                "tuple" | "Tuple" => Type::Tuple(args),
+                "type" | "Type" => Type::class_object(*arg(0)),
+                "Optional" => Type::optional(*arg(0)),
+                "Union" => Type::union_of(args),
+                "Callable" => {
+                    let params = match args.first() {
+                        Some(Type::Tuple(params)) => params.clone(),
+                        // `Callable[P, R]` takes the parameters of the
+                        // `ParamSpec` `P`, after any `Concatenate`d ones.
+                        Some(Type::Named(spec)) => vec![Type::ParamSpec(spec.clone())],
+                        Some(Type::Generic { name, params })
+                            if name.trim_start_matches("typing.") == "Concatenate" =>
+                        {
+                            let mut params = params.clone();
+                            if let Some(Type::Named(spec)) = params.last().cloned() {
+                                params.pop();
+                                params.push(Type::ParamSpec(spec));
+                            }
+                            params
+                        },
+                        _ => Vec::new(),
+                    };
+                    Type::callable(params, *arg(1))
+                },
+                _ => Type::Generic { name, params: args },
+            }
+        },
+        _ => Type::Unknown,
+    }
+}
+
+/// The type a quoted forward reference such as `"Optional[Node]"` names,
+/// parsed like the annotation it holds. Text that doesn't parse as an
+/// expression is kept as a name.
+fn forward_reference(inner: &str) -> Type {
+    let tree = Parser::new().and_then(|mut parser| parser.parse_string(inner));
+    let Ok(tree) = tree else {
+        return Type::Named(inner.to_string());
+    };
+    let root = tree.root_node();
+    let expression = Some(root)
+        .filter(|r| !r.has_error() && r.named_child_count() == 1)
+        .and_then(|r| r.named_child(0))
+        .filter(|s| s.kind() == "expression_statement" && s.named_child_count() == 1)
+        .and_then(|s| s.named_child(0));
+    match expression {
+        Some(expression) => annotation_type(&expression, inner.as_bytes()),
+        None => Type::Named(inner.to_string()),
+    }
+}
+
+/// The `Literal` type of a constant expression: a string without a prefix,
+/// an integer, possibly negated, `True`, or `False`. `None` for anything
+/// else, including enum members, which look like any other attribute.
+pub(super) fn constant_type(node: &Node, source: &[u8]) -> Option<Type> {
+    let constant = match node.kind() {
+        "parenthesized_expression" => return constant_type(&node.named_child(0)?, source),
+        "string" | "integer" | "true" | "false" => true,
+        "unary_operator" => {
+            node.child(0).is_some_and(|op| op.kind() == "-")
+                && node
+                    .child_by_field_name("argument")
+                    .is_some_and(|argument| argument.kind() == "integer")
+        },
+        _ => false,
+    };
+    match literal_type(node, source) {
+        ty @ Type::Literal(_) if constant => Some(ty),
+        _ => None,
+    }
+}
+
+/// The type of one value of a `Literal[...]` annotation. Strings are
+/// normalized to single quotes; prefixed strings are only typed as `str`
+/// or `bytes`.
+fn literal_type(node: &Node, source: &[u8]) -> Type {
+    let text = node.utf8_text(source).unwrap_or_default();
+    match node.kind() {
+        "type" => node
+            .named_child(0)
+            .map_or(Type::Unknown, |n| literal_type(&n, source)),
+        "none" => Type::None,
+        "string" if text.starts_with(['"', '\'']) => {
+            Type::Literal(format!("'{}'", text.trim_matches(['"', '\''])))
+        },
+        "string" => string_type(node, source),
+        "integer" | "true" | "false" | "unary_operator" | "attribute" => {
+            Type::Literal(text.to_string())
+        },
+        _ => Type::Unknown,
+    }
+}
+
+/// `str`, or `bytes` for literals with a `b` prefix.
+fn string_type(node: &Node, source: &[u8]) -> Type {
+    let text = node.utf8_text(source).unwrap_or_default();
+    let prefix = text.split(['"', '\'']).next().unwrap_or_default();
+    if prefix.contains(['b', 'B']) {
+        Type::Bytes
+    } else {
+        Type::Str
+    }
+}
+
+/// The union of a display's element types; `Any` when it is empty.
+fn element_type(node: &Node, source: &[u8], env: &TypeEnv) -> Type {
+    let elements: Vec<Type> = named_children(node)
+        .iter()
+        .map(|n| expression_type_in(n, source, env).widened())
+        .collect();
+    if elements.is_empty() {
+        Type::Any
+    } else {
+        Type::union_of(elements)
+    }
+}
+
+fn named_children<'t>(node: &Node<'t>) -> Vec<Node<'t>> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|n| n.kind() != "comment")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn infer(expr: &str) -> Type {
+        let source = format!("x = {}\n", expr);
+        let tree = Parser::new().unwrap().parse_string(&source).unwrap();
+        let assignment = tree
+            .root_node()
+            .named_child(0)
+            .and_then(|stmt| stmt.named_child(0))
+            .unwrap();
+        let right = assignment.child_by_field_name("right").unwrap();
+        expression_type(&right, source.as_bytes())
+    }
+
+    #[test]
+    fn test_literal_types() {
+        assert_eq!(infer("1"), Type::Int);
+        assert_eq!(infer("-2.5"), Type::Float);
+        assert_eq!(infer("b'raw'"), Type::Bytes);
+        assert_eq!(infer("f'{x}'"), Type::Str);
+        assert_eq!(infer("[1, 2.0]").to_string(), "List[int | float]");
+        assert_eq!(infer("{'a': None}").to_string(), "Dict[str, None]");
+        assert_eq!(infer("(1, 'a')").to_string(), "Tuple[int, str]");
+        assert_eq!(infer("[]").to_string(), "List[Any]");
+        assert_eq!(infer("f(1)"), Type::Unknown);
+        assert_eq!(infer("1 + 2.0"), Type::Float);
+        assert_eq!(infer("'a' * 3"), Type::Str);
+        assert_eq!(infer("1 if c else 'a'").to_string(), "int | str");
+    }
+
+    #[test]
+    fn test_constant_types() {
+        let constant = |expr: &str| {
+            let source = format!("x = {}\n", expr);
+            let tree = Parser::new().unwrap().parse_string(&source).unwrap();
+            let right = tree
+                .root_node()
+                .named_child(0)
+                .and_then(|stmt| stmt.named_child(0))
+                .and_then(|assignment| assignment.child_by_field_name("right"))
+                .unwrap();
+            constant_type(&right, source.as_bytes()).map(|ty| ty.to_string())
+        };
+        assert_eq!(constant("\"GET\"").as_deref(), Some("Literal['GET']"));
+        assert_eq!(constant("(42)").as_deref(), Some("Literal[42]"));
+        assert_eq!(constant("-1").as_deref(), Some("Literal[-1]"));
+        assert_eq!(constant("False").as_deref(), Some("Literal[False]"));
+        for expr in ["f'{x}'", "b'x'", "1.5", "~1", "None", "Color.RED", "'a' 'b'"] {
+            assert_eq!(constant(expr), None, "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_lambda_and_builtin_call_types() {
+        assert_eq!(infer("lambda a, b=1.5: b").to_string(), "Callable[[Unknown, float], float]");
+        assert_eq!(infer("lambda: None").to_string(), "Callable[[], None]");
+        assert_eq!(infer("list(map(lambda n: n * 2, [1, 2]))").to_string(), "List[int]");
+        assert_eq!(infer("map(lambda a, b: a + b, ['x'], ['y'])").to_string(), "Iterator[str]");
+        assert_eq!(infer("filter(lambda n: n > 0, (1.5,))").to_string(), "Iterator[float]");
+        assert_eq!(infer("sorted(b'ab', key=lambda c: -c)").to_string(), "List[int]");
+        assert_eq!(infer("list()"), Type::Unknown);
+    }
+
+    #[test]
+    fn test_annotation_types() {
+        let annotation = |text: &str| {
+            let source = format!("x: {}\n", text);
+            let tree = Parser::new().unwrap().parse_string(&source).unwrap();
+            let assignment = tree
+                .root_node()
+                .named_child(0)
+                .and_then(|stmt| stmt.named_child(0))
+                .unwrap();
+            let ty = assignment.child_by_field_name("type").unwrap();
+            annotation_type(&ty, source.as_bytes()).to_string()
+        };
+        assert_eq!(annotation("int"), "int");
+        assert_eq!(annotation("Optional[List[str]]"), "Optional[List[str]]");
+        assert_eq!(annotation("dict[str, int] | None"), "Optional[Dict[str, int]]");
+        assert_eq!(annotation("Tuple[int, float]"), "Tuple[int, float]");
+        assert_eq!(annotation("'Node'"), "Node");
+        assert_eq!(annotation("'Optional[List[Node]]'"), "Optional[List[Node]]");
+        assert_eq!(annotation("\"int | None\""), "Optional[int]");
+        assert_eq!(annotation("'not an annotation ('"), "not an annotation (");
+        assert_eq!(annotation("Callable[[int], str]"), "Callable[[int], str]");
+        assert_eq!(annotation("type[Widget]"), "Type[Widget]");
+        assert_eq!(annotation("Type[int | str]"), "Type[int | str]");
+        assert_eq!(
+            annotation("Literal[\"r\", 1, None, Color.RED]"),
+            "None | Literal['r'] | Literal[1] | Literal[Color.RED]"
+        );
+    }
+}
@@ -1,30 +1,77 @@
 //! Static analysis for type inference and checking.
 
-use std::collections::HashMap;
-use std::path::Path;
+mod aliases;
+mod assignments;
+mod attributes;
+mod cache;
+mod calls;
+mod comparisons;
+mod context_managers;
+mod decorators;
+mod doctest;
+mod duplicates;
+mod exceptions;
+mod exhaustiveness;
+mod incremental;
+mod infer;
+mod mro;
+mod narrow;
+mod operators;
+mod parallel;
+mod returns;
+mod rules;
+mod script;
+mod shadowing;
+mod signatures;
+mod solving;
+mod stats;
+mod stubs;
+mod symbols;
+mod typed_dicts;
+mod typeshed;
+mod unreachable;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use tree_sitter::{Node, Tree};
 
-use crate::error::Result;
-use crate::parser::Parser;
-use crate::types::Type;
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::error::{Error, Partial, Result};
+use crate::parser::{extract_comments, Comment, Language, Parser};
+use crate::solver::Unsolved;
+use crate::types::{
+    ClassHierarchy, Interned, Param, Type, TypeEnv, TypeInterner, MAX_UNION_MEMBERS,
+};
+use crate::utils::{LineCol, SourceFile, Span};
+use aliases::alias_definition;
+use decorators::{decorated_type, decorators};
+use narrow::{always_exits, narrow};
 
-/// Simple diagnostic record produced by lightweight analysis.
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct Diagnostic {
-    /// File path of the diagnostic.
-    pub path: String,
-    /// 0-based line number where the issue was found.
-    pub line: usize,
-    /// 0-based column number where the issue was found.
-    pub column: usize,
-    /// Human-readable message.
-    pub message: String,
-    /// Severity string (e.g., "warning", "error").
-    pub severity: String,
-}
+pub use cache::{AnalysisCache, CACHE_DIR};
+pub(crate) use decorators::FunctionKind;
+pub use incremental::{DependencyGraph, IncrementalAnalysis, IncrementalRun};
+pub use infer::{annotation_type, expression_type, expression_type_in};
+use infer::{constant_type, lambda_scope};
+use mro::{member, resolve_base};
+pub use parallel::{ParallelAnalysis, ResultCollector};
+pub use rules::{Rule, RuleSet, RULES_DIR};
+pub use signatures::{
+    infer_signatures, InferredParameter, InferredSignature, ParameterKind, TypeDrift, TypeSnapshot,
+    TYPE_SNAPSHOT_VERSION,
+};
+pub(crate) use signatures::{is_annotated, parameter_name, parameters};
+pub use solving::ParameterInference;
+pub use stats::{
+    stats_to_csv, stats_to_jsonl, FileStats, FunctionStats, TypeMetrics, STATS_CSV_COLUMNS,
+};
+use stubs::Stub;
+pub use stubs::StubPaths;
+pub use symbols::{Symbol, SymbolKind, SymbolTable};
+use typed_dicts::TypedDicts;
+use typeshed::Typeshed;
 
 /// Per-file, lightweight analysis summary.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AnalysisResult {
     /// Absolute or input path of the analyzed file.
     pub path: String,
@@ -34,13 +81,158 @@ pub struct AnalysisResult {
     pub class_count: usize,
     /// Collected diagnostics for this file.
     pub diagnostics: Vec<Diagnostic>,
+    /// Return types inferred from the bodies of functions that have no
+    /// return annotation, keyed by dotted function name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inferred_returns: BTreeMap<String, Type>,
+    /// Types of the unannotated parameters of top-level functions, inferred
+    /// from the calls in the file, keyed by function name, then parameter
+    /// name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inferred_parameters: BTreeMap<String, BTreeMap<String, Type>>,
+    /// Warnings for the unannotated parameters whose types couldn't be
+    /// inferred, set aside from `diagnostics` until the configuration
+    /// decides whether to report them or type them `Any` or `Unknown` (see
+    /// [`Config::unsolved`](crate::config::Config::unsolved)).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsolved: Vec<Diagnostic>,
+    /// Attribute tables of the file's classes, keyed by dotted class name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub classes: BTreeMap<String, ClassInfo>,
+    /// How much of the file is annotated.
+    #[serde(default)]
+    pub metrics: TypeMetrics,
+}
+
+impl AnalysisResult {
+    /// Settles the parameters whose types couldn't be inferred, set aside
+    /// in `unsolved`: they are typed `Any` or `Unknown` in
+    /// `inferred_parameters`, or reported in `diagnostics`, as `policy`
+    /// says.
+    pub fn apply_unsolved(&mut self, policy: Unsolved) {
+        let fallback = policy.fallback();
+        for diagnostic in std::mem::take(&mut self.unsolved) {
+            match (&fallback, diagnostic.args.get("function"), diagnostic.args.get("name")) {
+                (Some(ty), Some(function), Some(name)) => {
+                    self.inferred_parameters
+                        .entry(function.clone())
+                        .or_default()
+                        .insert(name.clone(), ty.clone());
+                },
+                _ => self.diagnostics.push(diagnostic),
+            }
+        }
+    }
+}
+
+/// The variable types observed in one module, class, or function scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ScopeTypes {
+    /// Dotted name of the scope's class or function; empty for the module.
+    pub name: String,
+    /// Union of every type assigned to each variable in the scope.
+    pub variables: BTreeMap<String, Type>,
+    /// For a function, the union of the types it returns, including `None`
+    /// when the end of the body is reachable. `None` for other scopes,
+    /// generators, and functions whose returns can't all be inferred.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<Type>,
+    /// For a function, its return annotation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared_returns: Option<Type>,
+}
+
+/// The attributes of a class: variables assigned in its body, methods,
+/// and attributes assigned through the first parameter of its methods
+/// (`self.x = ...`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ClassInfo {
+    /// Base classes, and keywords such as `metaclass=...`, as written in
+    /// the class statement.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bases: Vec<String>,
+    /// Decorator expressions applied to the class, without the `@`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub decorators: Vec<String>,
+    /// Union of the types assigned to each attribute; methods are
+    /// `Callable` types without their implicit first parameter, properties
+    /// the type their getter returns.
+    pub attributes: BTreeMap<String, Type>,
 }
 
 /// The main analyzer that performs static type checking and inference.
-#[allow(dead_code)]
+///
+/// Function bodies are walked in order with a [`TypeEnv`] per scope, so a
+/// variable's type follows its assignments: after `x = 1; x = "a"` a read
+/// of `x` is `str`, while the scope records `x` as `int | str`. Branches
+/// are visited on copies of the environment and joined afterwards. Each
+/// function and class body gets a [`TypeEnv`] nested in the one of the
+/// scope it is defined in, except that methods skip their class's, as
+/// Python resolves names in the enclosing functions and the module.
 pub struct Analyzer {
-    /// Type environment storing variable types in different scopes.
-    type_env: HashMap<String, Type>,
+    /// Variable types of each scope, in the order the scopes were entered.
+    scopes: Vec<ScopeTypes>,
+    /// The distinct types bound in scope environments and recorded in the
+    /// per-node tables below, each stored once. Inferred returns keep
+    /// their own copies.
+    types: TypeInterner,
+    /// Type of each variable read, keyed by the byte offset of the name.
+    references: BTreeMap<usize, Interned>,
+    /// Type each parameter or assignment target binds, keyed by the byte
+    /// offset of the name.
+    definitions: BTreeMap<usize, Interned>,
+    /// Class whose body is being visited, for typing `self`.
+    class: Option<String>,
+    /// Attribute tables of the classes visited, by dotted name.
+    classes: BTreeMap<String, ClassInfo>,
+    /// The first parameter of the method being visited and its class, for
+    /// collecting `self.attr = ...` assignments.
+    receiver: Option<(String, String)>,
+    /// Types returned so far by each function scope being visited, or
+    /// `None` once it turns out to be a generator.
+    returns: BTreeMap<usize, Option<Vec<Type>>>,
+    /// Type of the value each `return` statement returns, keyed by the
+    /// byte offset of the statement.
+    returned: BTreeMap<usize, Interned>,
+    /// Type of the value each assignment assigns, keyed by the byte offset
+    /// of the assignment.
+    assigned: BTreeMap<usize, Interned>,
+    /// Types of the left and right operands of each binary or comparison
+    /// operator, keyed by the byte offset of the operator token.
+    operands: BTreeMap<usize, (Interned, Interned)>,
+    /// Type of the context manager each `with` item enters, keyed by the
+    /// byte offset of the item.
+    managers: BTreeMap<usize, Interned>,
+    /// Jumps out of each loop being visited, innermost last.
+    loops: Vec<LoopJumps>,
+    /// Variables some function rebinds with `global` or `nonlocal`, whose
+    /// types are unknown wherever they are read.
+    rebound: BTreeSet<String>,
+    /// Names the module binds exactly once and no function rebinds, whose
+    /// constant values keep their `Literal` type.
+    constants: BTreeSet<String>,
+    /// Type aliases the module defines, for expanding the types compared
+    /// where annotations are checked.
+    aliases: TypeEnv,
+    /// Whether this is a scratch copy visiting a loop body ahead of time.
+    scratch: bool,
+    /// Most members an inferred union keeps before it is widened.
+    max_union_members: usize,
+}
+
+/// How a loop starts each iteration.
+enum Loop<'t> {
+    /// A `for` loop binds its target to the next element.
+    For { target: Node<'t>, element: Type },
+    /// A `while` loop runs while its condition holds.
+    While { condition: Node<'t> },
+}
+
+/// The environments at the `break` and `continue` statements of a loop.
+#[derive(Default)]
+struct LoopJumps {
+    breaks: Vec<TypeEnv>,
+    continues: Vec<TypeEnv>,
 }
 
 impl Default for Analyzer {
@@ -50,45 +242,1225 @@ impl Default for Analyzer {
 }
 
 impl Analyzer {
-    /// Creates a new analyzer with an empty type environment.
+    /// Creates a new analyzer with no recorded scopes.
     pub fn new() -> Self {
-        Self { type_env: HashMap::new() }
+        Self {
+            scopes: Vec::new(),
+            types: TypeInterner::new(),
+            references: BTreeMap::new(),
+            definitions: BTreeMap::new(),
+            class: None,
+            classes: BTreeMap::new(),
+            receiver: None,
+            returns: BTreeMap::new(),
+            returned: BTreeMap::new(),
+            assigned: BTreeMap::new(),
+            operands: BTreeMap::new(),
+            managers: BTreeMap::new(),
+            loops: Vec::new(),
+            rebound: BTreeSet::new(),
+            constants: BTreeSet::new(),
+            aliases: TypeEnv::new(),
+            scratch: false,
+            max_union_members: MAX_UNION_MEMBERS,
+        }
+    }
+
+    /// Widens the unions inferred by joining the types of several
+    /// assignments, branches, or returns once they have more than `max`
+    /// members, as [`Type::capped`] does. The default is
+    /// [`MAX_UNION_MEMBERS`].
+    pub fn with_max_union_members(mut self, max: usize) -> Self {
+        self.max_union_members = max;
+        self
     }
 
     /// Analyzes a syntax tree and infers types.
     pub fn analyze(&mut self, tree: &Tree, source: &[u8]) -> Result<()> {
+        *self = Self { max_union_members: self.max_union_members, ..Self::new() };
+        self.scopes.push(ScopeTypes::default());
         let root_node = tree.root_node();
-        self.visit_node(&root_node, source)?;
+        self.rebound = global_names(&root_node, source);
+        let mut bindings: BTreeMap<String, usize> = BTreeMap::new();
+        for name in calls::scope_bindings(&root_node, source) {
+            *bindings.entry(name).or_default() += 1;
+        }
+        self.constants = bindings
+            .into_iter()
+            .filter(|(name, count)| *count == 1 && !self.rebound.contains(name))
+            .map(|(name, _)| name)
+            .collect();
+        let mut env = TypeEnv::new().with_max_union_members(self.max_union_members);
+        self.visit_node(&root_node, source, &mut env, 0)?;
+        Ok(())
+    }
+
+    /// Variable types of every scope visited by [`Analyzer::analyze`], the
+    /// module first.
+    pub fn scopes(&self) -> &[ScopeTypes] {
+        &self.scopes
+    }
+
+    /// Variable types of the scope with the given dotted name.
+    pub fn scope(&self, name: &str) -> Option<&ScopeTypes> {
+        self.scopes.iter().find(|s| s.name == name)
+    }
+
+    /// Attribute tables of every class visited, keyed by dotted name.
+    pub fn classes(&self) -> &BTreeMap<String, ClassInfo> {
+        &self.classes
+    }
+
+    /// The classes visited as a [`ClassHierarchy`], with their attributes
+    /// as members. Bases are named without their type arguments, resolved
+    /// to the dotted name of the file's class they refer to, if any.
+    /// `Generic[T]` bases, which only introduce type parameters, are left
+    /// out.
+    pub fn class_hierarchy(&self) -> ClassHierarchy {
+        let mut hierarchy = ClassHierarchy::new();
+        for (name, class) in &self.classes {
+            let bases = class
+                .bases
+                .iter()
+                // Keywords such as `metaclass=ABCMeta` aren't bases.
+                .filter(|base| !base.contains('='))
+                .map(|base| base.split('[').next().unwrap_or(base))
+                .filter(|base| base.rsplit('.').next() != Some("Generic"))
+                .map(|base| {
+                    resolve_base(name, base, &self.classes)
+                        .unwrap_or(base)
+                        .to_string()
+                });
+            hierarchy.add_class(name.clone(), bases.collect());
+            for (attribute, ty) in &class.attributes {
+                hierarchy.add_member(name.clone(), attribute.clone(), ty.clone());
+            }
+        }
+        hierarchy
+    }
+
+    /// Type aliases the module defines, by name.
+    pub fn aliases(&self) -> impl Iterator<Item = (&String, &Type)> {
+        self.aliases.aliases()
+    }
+
+    /// `ty` with the module's type aliases replaced by the types they
+    /// stand for, to compare it with another type. Messages should show
+    /// `ty` itself, which keeps the alias names.
+    pub fn expand_aliases(&self, ty: &Type) -> Type {
+        self.aliases.expand(ty)
+    }
+
+    /// Return types inferred for the functions that have no return
+    /// annotation, keyed by dotted name, with `Literal` types widened to
+    /// the types of their values. A name defined more than once keeps its
+    /// first definition.
+    pub fn inferred_returns(&self) -> BTreeMap<String, Type> {
+        let mut returns = BTreeMap::new();
+        for scope in &self.scopes {
+            if let (Some(ty), None) = (&scope.returns, &scope.declared_returns) {
+                returns
+                    .entry(scope.name.clone())
+                    .or_insert_with(|| ty.widened());
+            }
+        }
+        returns
+    }
+
+    /// Types of the unannotated parameters of the top-level functions in a
+    /// file, inferred by solving the constraints the calls to them place
+    /// on the parameters. Parameters that can't be inferred are handled as
+    /// `unsolved` says. `tree` must be the tree this analyzer analyzed.
+    pub fn infer_parameters(
+        &self,
+        tree: &Tree,
+        file: &SourceFile,
+        unsolved: Unsolved,
+    ) -> ParameterInference {
+        solving::infer_parameters(tree, file, self, unsolved)
+    }
+
+    /// The type of the variable read at `byte`, as of that point in the
+    /// code. `None` if no variable read starts there or it is unbound.
+    pub fn type_at(&self, byte: usize) -> Option<&Type> {
+        self.references.get(&byte).map(|ty| &**ty)
+    }
+
+    /// The type bound to the parameter or assignment target whose name
+    /// starts at `byte`.
+    pub fn defined_at(&self, byte: usize) -> Option<&Type> {
+        self.definitions.get(&byte).map(|ty| &**ty)
+    }
+
+    /// The type of the identifier or expression at `byte`: the type of a
+    /// variable read or bound there, else of the innermost expression
+    /// containing it, with names typed as of where they are read. `None`
+    /// if the type isn't known. `root` must be the tree this analyzer
+    /// analyzed.
+    pub fn type_at_byte(&self, root: &Node, source: &[u8], byte: usize) -> Option<Type> {
+        let mut node = root.named_descendant_for_byte_range(byte, byte)?;
+        // The pieces of a string stand for the whole string.
+        while matches!(
+            node.kind(),
+            "string_start" | "string_content" | "string_end" | "escape_sequence"
+        ) {
+            node = node.parent()?;
+        }
+        if node.kind() == "identifier" {
+            return self
+                .type_at(node.start_byte())
+                .or_else(|| self.defined_at(node.start_byte()))
+                .cloned();
+        }
+        Some(self.expression_type_at(&node, source)).filter(|ty| *ty != Type::Unknown)
+    }
+
+    /// The type of the expression `node`, with the names in it typed as of
+    /// where they are read.
+    fn expression_type_at(&self, node: &Node, source: &[u8]) -> Type {
+        let mut env = TypeEnv::new();
+        let mut stack = vec![*node];
+        while let Some(n) = stack.pop() {
+            if n.kind() == "identifier" {
+                if let Some(ty) = self.type_at(n.start_byte()) {
+                    env.bind(n.utf8_text(source).unwrap_or_default().to_string(), ty.clone());
+                }
+            }
+            let mut cursor = n.walk();
+            stack.extend(n.named_children(&mut cursor));
+        }
+        expression_type_in(node, source, &env)
+    }
+
+    /// The type of the value returned by the `return` statement starting
+    /// at `byte`: `None` for a bare `return`.
+    pub fn returned_at(&self, byte: usize) -> Option<&Type> {
+        self.returned.get(&byte).map(|ty| &**ty)
+    }
+
+    /// The type of the value assigned by the assignment starting at
+    /// `byte`: `None` for a bare annotation (`x: int`).
+    pub fn assigned_at(&self, byte: usize) -> Option<&Type> {
+        self.assigned.get(&byte).map(|ty| &**ty)
+    }
+
+    /// The types of the operands of the binary or comparison operator
+    /// whose first token starts at `byte`, as of that point in the code.
+    pub fn operands_at(&self, byte: usize) -> Option<(&Type, &Type)> {
+        self.operands
+            .get(&byte)
+            .map(|(left, right)| (&**left, &**right))
+    }
+
+    /// The type of the context manager the `with` item starting at `byte`
+    /// enters.
+    pub fn manager_at(&self, byte: usize) -> Option<&Type> {
+        self.managers.get(&byte).map(|ty| &**ty)
+    }
+
+    /// Visits a node in the syntax tree, updating `env` with the bindings
+    /// it makes. `scope` indexes the [`ScopeTypes`] assignments go into.
+    fn visit_node(
+        &mut self,
+        node: &Node,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+    ) -> Result<()> {
+        match node.kind() {
+            "identifier" => {
+                let name = node.utf8_text(source).unwrap_or_default();
+                if let Some(ty) = env.lookup(name) {
+                    let ty = self.types.intern(ty);
+                    self.references.insert(node.start_byte(), ty);
+                }
+            },
+            "function_definition" => self.visit_function(node, source, env, scope)?,
+            "class_definition" => {
+                let name = field_text(node, "name", source);
+                self.visit_decorated(node, source, env, scope)?;
+                if let Some(superclasses) = node.child_by_field_name("superclasses") {
+                    self.visit_node(&superclasses, source, env, scope)?;
+                }
+                let inner = self.enter_scope(&name, scope);
+                let qualified = self.scopes[inner].name.clone();
+                let bases = node
+                    .child_by_field_name("superclasses")
+                    .map(|args| {
+                        let mut cursor = args.walk();
+                        args.named_children(&mut cursor)
+                            .filter(|a| a.kind() != "comment")
+                            .filter_map(|a| a.utf8_text(source).ok())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let decorators = decorators(node, source)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect();
+                self.classes.entry(qualified.clone()).or_insert(ClassInfo {
+                    bases,
+                    decorators,
+                    attributes: BTreeMap::new(),
+                });
+                let outer_class = self.class.replace(qualified.clone());
+                let mut class_env = TypeEnv::nested(env.clone());
+                if let Some(body) = node.child_by_field_name("body") {
+                    self.visit_node(&body, source, &mut class_env, inner)?;
+                }
+                self.class = outer_class;
+                let class_variables = self.scopes[inner].variables.clone();
+                for (attribute, ty) in class_variables {
+                    self.add_attribute(&qualified, &attribute, ty);
+                }
+                self.bind(env, scope, &name, Type::Named(qualified));
+            },
+            "assignment" => {
+                self.visit_assignment(node, source, env, scope)?;
+            },
+            "type_alias_statement" => self.define_alias(node, source, env, scope),
+            "augmented_assignment" => {
+                if let Some(right) = node.child_by_field_name("right") {
+                    self.visit_node(&right, source, env, scope)?;
+                }
+                let Some(left) = node.child_by_field_name("left") else {
+                    return Ok(());
+                };
+                if left.kind() != "identifier" {
+                    return self.visit_node(&left, source, env, scope);
+                }
+                self.visit_node(&left, source, env, scope)?;
+                let operator = node
+                    .child_by_field_name("operator")
+                    .map(|op| op.kind().trim_end_matches('='))
+                    .unwrap_or_default();
+                let current = expression_type_in(&left, source, env);
+                let right = node
+                    .child_by_field_name("right")
+                    .map_or(Type::Unknown, |r| expression_type_in(&r, source, env));
+                let name = left.utf8_text(source).unwrap_or_default();
+                self.bind(env, scope, name, infer::binary_type(operator, current, right));
+            },
+            "if_statement" => {
+                let condition = node.child_by_field_name("condition");
+                if let Some(condition) = &condition {
+                    self.visit_node(condition, source, env, scope)?;
+                }
+                let (mut branch, mut rest) = match &condition {
+                    Some(condition) => narrow(condition, source, env),
+                    None => (env.clone(), env.clone()),
+                };
+                // Paths that fall through to the statement after the `if`.
+                let mut exits = Vec::new();
+                if let Some(body) = node.child_by_field_name("consequence") {
+                    self.visit_node(&body, source, &mut branch, scope)?;
+                    if !always_exits(&body, source) {
+                        exits.push(branch);
+                    }
+                }
+                let mut cursor = node.walk();
+                let alternatives: Vec<Node> = node
+                    .children_by_field_name("alternative", &mut cursor)
+                    .collect();
+                let mut has_else = false;
+                for alternative in alternatives {
+                    let mut branch = rest.clone();
+                    if let Some(condition) = alternative.child_by_field_name("condition") {
+                        self.visit_node(&condition, source, &mut rest, scope)?;
+                        (branch, rest) = narrow(&condition, source, &rest);
+                    } else {
+                        has_else = true;
+                    }
+                    let body = alternative
+                        .child_by_field_name("consequence")
+                        .or_else(|| alternative.child_by_field_name("body"));
+                    if let Some(body) = body {
+                        self.visit_node(&body, source, &mut branch, scope)?;
+                        if !always_exits(&body, source) {
+                            exits.push(branch);
+                        }
+                    }
+                }
+                if !has_else {
+                    exits.push(rest);
+                }
+                let mut exits = exits.into_iter();
+                if let Some(first) = exits.next() {
+                    *env = first;
+                    for exit in exits {
+                        env.join(&exit);
+                    }
+                }
+            },
+            "for_statement" => {
+                let Some(left) = node.child_by_field_name("left") else {
+                    return Ok(());
+                };
+                let iterable = match node.child_by_field_name("right") {
+                    Some(right) => {
+                        self.visit_node(&right, source, env, scope)?;
+                        expression_type_in(&right, source, env)
+                    },
+                    None => Type::Unknown,
+                };
+                // `async for` iterates with `__aiter__`, not the builtin way.
+                let element = match node.child(0).is_some_and(|c| c.kind() == "async") {
+                    true => iterable.async_iterated(),
+                    false => iterable.iterated(),
+                };
+                self.visit_loop(node, source, env, scope, &Loop::For { target: left, element })?;
+            },
+            "while_statement" => {
+                let Some(condition) = node.child_by_field_name("condition") else {
+                    return Ok(());
+                };
+                self.visit_node(&condition, source, env, scope)?;
+                self.visit_loop(node, source, env, scope, &Loop::While { condition })?;
+            },
+            "try_statement" => {
+                let before = env.clone();
+                if let Some(body) = node.child_by_field_name("body") {
+                    self.visit_node(&body, source, env, scope)?;
+                }
+                // A handler may run after any part of the body has.
+                let mut entry = before;
+                entry.join(env);
+                let mut cursor = node.walk();
+                let clauses: Vec<Node> = node.named_children(&mut cursor).skip(1).collect();
+                let mut exits = Vec::new();
+                for clause in &clauses {
+                    match clause.kind() {
+                        "except_clause" => {
+                            let mut branch = entry.clone();
+                            self.visit_except_clause(clause, source, &mut branch, scope)?;
+                            exits.push(branch);
+                        },
+                        "else_clause" => self.visit_node(clause, source, env, scope)?,
+                        _ => {},
+                    }
+                }
+                for exit in &exits {
+                    env.join(exit);
+                }
+                for clause in clauses.iter().filter(|c| c.kind() == "finally_clause") {
+                    self.visit_node(clause, source, env, scope)?;
+                }
+            },
+            "with_statement" => {
+                let is_async = node.child(0).is_some_and(|c| c.kind() == "async");
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    if child.kind() != "with_clause" {
+                        self.visit_node(&child, source, env, scope)?;
+                        continue;
+                    }
+                    let mut cursor = child.walk();
+                    for item in child
+                        .named_children(&mut cursor)
+                        .filter(|c| c.kind() == "with_item")
+                    {
+                        self.visit_with_item(&item, is_async, source, env, scope)?;
+                    }
+                }
+            },
+            "as_pattern" => {
+                if let Some(value) = node.named_child(0) {
+                    self.visit_node(&value, source, env, scope)?;
+                }
+                if let Some(alias) = node.child_by_field_name("alias") {
+                    if let Some(target) = alias.named_child(0) {
+                        self.bind_target(&target, Type::Unknown, source, env, scope)?;
+                    }
+                }
+            },
+            "attribute" => {
+                if let Some(object) = node.child_by_field_name("object") {
+                    self.visit_node(&object, source, env, scope)?;
+                }
+            },
+            "binary_operator" | "comparison_operator" => {
+                let mut cursor = node.walk();
+                let children: Vec<Node> = node
+                    .children(&mut cursor)
+                    .filter(|c| !c.is_extra())
+                    .collect();
+                for operand in children.iter().filter(|c| c.is_named()) {
+                    self.visit_node(operand, source, env, scope)?;
+                }
+                // Nested operators are left untyped: typing them again at each
+                // level of a chain like `a + b + c + ...` takes quadratic time.
+                let operand_type = |operand: &Node| match operand.kind() {
+                    "binary_operator" | "comparison_operator" => Type::Unknown,
+                    _ => expression_type_in(operand, source, env),
+                };
+                // Operators sit between their operands, and are keyed by their
+                // first token: `not in` and `is not` are two tokens.
+                let operands: Vec<usize> = (0..children.len())
+                    .filter(|&i| children[i].is_named())
+                    .collect();
+                for pair in operands.windows(2) {
+                    if let &[left, right] = pair {
+                        if right > left + 1 {
+                            let types = (
+                                self.types.intern(&operand_type(&children[left])),
+                                self.types.intern(&operand_type(&children[right])),
+                            );
+                            self.operands.insert(children[left + 1].start_byte(), types);
+                        }
+                    }
+                }
+            },
+            "keyword_argument" => {
+                if let Some(value) = node.child_by_field_name("value") {
+                    self.visit_node(&value, source, env, scope)?;
+                }
+            },
+            "return_statement" => {
+                let ty = match node.named_child(0) {
+                    Some(value) => {
+                        self.visit_node(&value, source, env, scope)?;
+                        expression_type_in(&value, source, env)
+                    },
+                    None => Type::None,
+                };
+                let interned = self.types.intern(&ty);
+                self.returned.insert(node.start_byte(), interned);
+                if let Some(Some(types)) = self.returns.get_mut(&scope) {
+                    types.push(ty);
+                }
+            },
+            "break_statement" => {
+                if let Some(jumps) = self.loops.last_mut() {
+                    jumps.breaks.push(env.clone());
+                }
+            },
+            "continue_statement" => {
+                if let Some(jumps) = self.loops.last_mut() {
+                    jumps.continues.push(env.clone());
+                }
+            },
+            "yield" => {
+                self.returns.insert(scope, None);
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    self.visit_node(&child, source, env, scope)?;
+                }
+            },
+            // Imported names hide any earlier binding, with types unknown.
+            "import_statement" | "import_from_statement" => {
+                for name in imported_names(node, source) {
+                    env.bind(name.to_string(), Type::Unknown);
+                }
+            },
+            // Defaults are evaluated where the lambda is; the body runs
+            // with the parameters bound.
+            "lambda" => {
+                let (_, mut lambda_env) = lambda_scope(node, source, env);
+                if let Some(params) = node.child_by_field_name("parameters") {
+                    let mut cursor = params.walk();
+                    for param in params.named_children(&mut cursor) {
+                        if let Some(value) = param.child_by_field_name("value") {
+                            self.visit_node(&value, source, env, scope)?;
+                        }
+                    }
+                }
+                if let Some(body) = node.child_by_field_name("body") {
+                    self.visit_node(&body, source, &mut lambda_env, scope)?;
+                }
+            },
+            // Names in these are not variable reads; nested expression
+            // scopes are left to their own inference.
+            "future_import_statement"
+            | "global_statement"
+            | "nonlocal_statement"
+            | "type"
+            | "list_comprehension"
+            | "set_comprehension"
+            | "dictionary_comprehension"
+            | "generator_expression" => {},
+            _ => {
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor) {
+                    self.visit_node(&child, source, env, scope)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Visits an assignment, binding its targets, and returns the type of
+    /// the assigned value. Chained assignments (`a = b = 1`) nest.
+    fn visit_assignment(
+        &mut self,
+        node: &Node,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+    ) -> Result<Type> {
+        self.define_alias(node, source, env, scope);
+        let value = match node.child_by_field_name("right") {
+            Some(right) if right.kind() == "assignment" => {
+                self.visit_assignment(&right, source, env, scope)?
+            },
+            Some(right) => {
+                self.visit_node(&right, source, env, scope)?;
+                expression_type_in(&right, source, env)
+            },
+            None => Type::Unknown,
+        };
+        let annotation = node.child_by_field_name("type");
+        let is_final = annotation.is_some_and(|annotation| {
+            let text = annotation.utf8_text(source).unwrap_or_default();
+            matches!(text, "Final" | "typing.Final" | "typing_extensions.Final")
+        });
+        let declared = annotation
+            .filter(|_| !is_final)
+            .map(|annotation| annotation_type(&annotation, source));
+        let left = node.child_by_field_name("left");
+        // Constants, and values declared as one of a few literals, keep
+        // their exact value.
+        let constant = is_final
+            || declared.as_ref().is_some_and(|ty| ty.widened() != *ty)
+            || (scope == 0
+                && left.is_some_and(|left| {
+                    left.kind() == "identifier"
+                        && self
+                            .constants
+                            .contains(left.utf8_text(source).unwrap_or_default())
+                }));
+        let value = match node.child_by_field_name("right") {
+            Some(right) if constant => constant_type(&right, source).unwrap_or(value),
+            _ => value,
+        };
+        if node.child_by_field_name("right").is_some() {
+            let interned = self.types.intern(&value);
+            self.assigned.insert(node.start_byte(), interned);
+        }
+        if let Some(left) = left {
+            // A bare annotation (`x: int`) declares without binding a value.
+            if declared.is_some() || node.child_by_field_name("right").is_some() {
+                let ty = declared.unwrap_or_else(|| value.clone());
+                self.bind_target(&left, ty, source, env, scope)?;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Binds an assignment target, destructuring tuple and list patterns
+    /// against a tuple type. Other targets (attributes, subscripts) are
+    /// visited as reads.
+    fn bind_target(
+        &mut self,
+        target: &Node,
+        ty: Type,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+    ) -> Result<()> {
+        match target.kind() {
+            "identifier" => {
+                let name = target.utf8_text(source).unwrap_or_default();
+                let interned = self.types.intern(&ty);
+                self.definitions.insert(target.start_byte(), interned);
+                self.bind(env, scope, name, ty);
+            },
+            "pattern_list" | "tuple_pattern" | "list_pattern" | "expression_list" | "tuple"
+            | "list" => {
+                let mut cursor = target.walk();
+                let elements: Vec<Node> = target.named_children(&mut cursor).collect();
+                let items = match ty {
+                    Type::Tuple(items) if items.len() == elements.len() => items,
+                    Type::List(inner) => vec![*inner; elements.len()],
+                    _ => vec![Type::Unknown; elements.len()],
+                };
+                for (element, ty) in elements.iter().zip(items) {
+                    self.bind_target(element, ty, source, env, scope)?;
+                }
+            },
+            "list_splat_pattern" => {
+                if let Some(inner) = target.named_child(0) {
+                    self.bind_target(&inner, Type::Unknown, source, env, scope)?;
+                }
+            },
+            "attribute" => {
+                let object = target.child_by_field_name("object");
+                let attribute = target.child_by_field_name("attribute");
+                if let (Some(object), Some(attribute)) = (object, attribute) {
+                    let name = object.utf8_text(source).unwrap_or_default();
+                    // `self.x = ...` in a method, or `obj.x = ...` on an instance.
+                    let class = match (&self.receiver, env.lookup(name)) {
+                        (Some((receiver, class)), _) if receiver == name => Some(class.clone()),
+                        (_, Some(Type::Named(class))) if object.kind() == "identifier" => {
+                            Some(class.clone())
+                        },
+                        _ => None,
+                    };
+                    if let Some(class) = class {
+                        let attribute = attribute.utf8_text(source).unwrap_or_default();
+                        self.add_attribute(&class, attribute, ty);
+                    }
+                }
+                self.visit_node(target, source, env, scope)?;
+            },
+            _ => self.visit_node(target, source, env, scope)?,
+        }
+        Ok(())
+    }
+
+    /// Visits a loop whose body may run zero or more times, then its
+    /// `else` clause. `env` is the environment on entry to the loop.
+    ///
+    /// The body is first visited on a scratch analyzer to find the types
+    /// variables have when it repeats; the real visit starts from those, so
+    /// `x = None` before a loop that assigns `x` doesn't leave `x` as
+    /// `None` throughout the body.
+    fn visit_loop(
+        &mut self,
+        node: &Node,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+        kind: &Loop,
+    ) -> Result<()> {
+        let mut head = env.clone();
+        // Loops nested in a scratch visit skip their own, which keeps the
+        // cost quadratic rather than exponential in the nesting depth.
+        if !self.scratch {
+            let mut scratch = Self {
+                scopes: self.scopes.clone(),
+                class: self.class.clone(),
+                receiver: self.receiver.clone(),
+                rebound: self.rebound.clone(),
+                constants: self.constants.clone(),
+                scratch: true,
+                max_union_members: self.max_union_members,
+                ..Self::new()
+            };
+            let mut repeated = env.clone();
+            let jumps = scratch.visit_iteration(node, source, &mut repeated, scope, kind)?;
+            head.join(&repeated);
+            for continued in &jumps.continues {
+                head.join(continued);
+            }
+        }
+
+        *env = head.clone();
+        let jumps = self.visit_iteration(node, source, env, scope, kind)?;
+        env.join(&head);
+        for continued in &jumps.continues {
+            env.join(continued);
+        }
+        if let Loop::While { condition } = kind {
+            (_, *env) = narrow(condition, source, env);
+        }
+        if let Some(alternative) = node.child_by_field_name("alternative") {
+            self.visit_node(&alternative, source, env, scope)?;
+        }
+        for broken in &jumps.breaks {
+            env.join(broken);
+        }
         Ok(())
     }
 
-    /// Visits a node in the syntax tree and processes it.
-    #[allow(clippy::only_used_in_recursion)]
-    fn visit_node(&mut self, _node: &Node, _source: &[u8]) -> Result<()> {
-        // TODO: Implement node visiting logic for type inference
+    /// Visits one iteration of a loop body, starting from the environment
+    /// at the loop head, and returns the jumps out of it.
+    fn visit_iteration(
+        &mut self,
+        node: &Node,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+        kind: &Loop,
+    ) -> Result<LoopJumps> {
+        match kind {
+            Loop::For { target, element } => {
+                self.bind_target(target, element.clone(), source, env, scope)?;
+            },
+            Loop::While { condition } => (*env, _) = narrow(condition, source, env),
+        }
+        self.loops.push(LoopJumps::default());
+        if let Some(body) = node.child_by_field_name("body") {
+            self.visit_node(&body, source, env, scope)?;
+        }
+        Ok(self.loops.pop().unwrap_or_default())
+    }
+
+    /// Visits a function definition: decorators and defaults in the
+    /// enclosing scope, then the body in a scope of its own with the
+    /// parameters bound.
+    fn visit_function(
+        &mut self,
+        node: &Node,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+    ) -> Result<()> {
+        self.visit_decorated(node, source, env, scope)?;
+        let name = field_text(node, "name", source);
+        let inner = self.enter_scope(&name, scope);
+        self.scopes[inner].declared_returns = node
+            .child_by_field_name("return_type")
+            .map(|annotation| annotation_type(&annotation, source));
+        // Names bound in a class body aren't visible in its methods.
+        let enclosing = match &self.class {
+            Some(_) => env.parent().cloned().unwrap_or_default(),
+            None => env.clone(),
+        };
+        let mut function_env = TypeEnv::nested(enclosing);
+        let class = self.class.take();
+        let outer_receiver = self.receiver.take();
+        let kind = FunctionKind::of(node, source);
 
-        // Recursively visit children
-        let mut cursor = _node.walk();
-        for child in _node.children(&mut cursor) {
-            self.visit_node(&child, _source)?;
+        if let Some(params) = node.child_by_field_name("parameters") {
+            let receiver = kind.receiver(&params);
+            for (param, _) in parameters(&params) {
+                if let Some(value) = param.child_by_field_name("value") {
+                    self.visit_node(&value, source, env, scope)?;
+                }
+                let Some((name, ty)) = parameter_type(&param, source, env) else {
+                    continue;
+                };
+                // `self` is an instance of the class and `cls` the class
+                // object; in the body `Self` is the class itself.
+                let ty = match (&class, ty) {
+                    (Some(class), Type::Unknown) if Some(param) == receiver => {
+                        if kind == FunctionKind::ClassMethod {
+                            Type::class_object(Type::Named(class.clone()))
+                        } else {
+                            self.receiver = Some((name.clone(), class.clone()));
+                            Type::Named(class.clone())
+                        }
+                    },
+                    (Some(class), ty) => ty.bind_self(&Type::Named(class.clone())),
+                    (None, ty) => ty,
+                };
+                if let Some(identifier) = parameter_name(&param) {
+                    let interned = self.types.intern(&ty);
+                    self.definitions.insert(identifier.start_byte(), interned);
+                }
+                self.bind(&mut function_env, inner, &name, ty);
+            }
         }
+        self.returns.insert(inner, Some(Vec::new()));
+        if let Some(body) = node.child_by_field_name("body") {
+            self.visit_node(&body, source, &mut function_env, inner)?;
+            if !always_exits(&body, source) {
+                if let Some(Some(types)) = self.returns.get_mut(&inner) {
+                    types.push(Type::None);
+                }
+            }
+        }
+        let returned = self.returns.remove(&inner).flatten();
+        let generator = returned.is_none();
+        self.scopes[inner].returns = returned
+            .and_then(|types| types.into_iter().reduce(Type::join))
+            .map(|ty| ty.capped(self.max_union_members))
+            .filter(|ty| *ty != Type::Unknown);
+        self.receiver = outer_receiver;
 
+        let scope = &self.scopes[inner];
+        // The receiver is bound by the call, so it isn't part of the type.
+        let params = match node.child_by_field_name("parameters") {
+            Some(params) => {
+                let receiver = kind.receiver(&params);
+                parameters(&params)
+                    .into_iter()
+                    .filter(|(p, _)| Some(*p) != receiver)
+                    .filter_map(|(p, kind)| {
+                        let (name, _) = parameter_type(&p, source, env)?;
+                        let ty = scope.variables.get(&name).cloned().unwrap_or_default();
+                        // Variadic parameters are typed by each argument.
+                        let ty = match (kind, ty) {
+                            (ParameterKind::VarPositional, Type::Generic { name, params })
+                                if name == "tuple" =>
+                            {
+                                params.into_iter().next().unwrap_or_default()
+                            },
+                            (ParameterKind::VarKeyword, Type::Dict(_, value)) => *value,
+                            (_, ty) => ty,
+                        };
+                        let default = p
+                            .child_by_field_name("value")
+                            .map(|v| v.utf8_text(source).unwrap_or_default().to_string());
+                        Some(Param { name: Some(name), kind, ty, default })
+                    })
+                    .collect()
+            },
+            None => Vec::new(),
+        };
+        let mut returns = scope
+            .declared_returns
+            .clone()
+            .or_else(|| scope.returns.clone())
+            .unwrap_or_default();
+        // Calling an `async def` creates a coroutine, unless it is an async
+        // generator.
+        if node.child(0).is_some_and(|c| c.kind() == "async") && !generator {
+            returns = Type::coroutine(returns);
+        }
+        let function = Type::Function { params, returns: Box::new(returns.clone()) };
+        let function = decorated_type(node, source, env, function);
+        match &class {
+            // A setter or deleter redefines the property its getter added.
+            Some(class)
+                if kind == FunctionKind::Property
+                    && self
+                        .classes
+                        .get(class)
+                        .is_some_and(|c| c.attributes.contains_key(&name)) => {},
+            Some(class) if kind == FunctionKind::Property => {
+                self.add_attribute(class, &name, returns);
+            },
+            Some(class) => self.add_attribute(class, &name, function),
+            None => {
+                let function = if self.rebound.contains(&name) { Type::Unknown } else { function };
+                env.bind(name, function);
+            },
+        }
+        self.class = class;
+        Ok(())
+    }
+
+    /// Visits an item of a `with` statement, binding its `as` target to
+    /// what the context manager's `__enter__` returns, or for `async with`
+    /// what awaiting its `__aenter__` gives. `Self` there is the manager's
+    /// own class, which may be a subclass of the one defining the method.
+    fn visit_with_item(
+        &mut self,
+        item: &Node,
+        is_async: bool,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+    ) -> Result<()> {
+        let Some(value) = item.child_by_field_name("value") else {
+            return Ok(());
+        };
+        let (manager, target) = match value.kind() {
+            "as_pattern" => (
+                value.named_child(0),
+                value
+                    .child_by_field_name("alias")
+                    .and_then(|alias| alias.named_child(0)),
+            ),
+            _ => (Some(value), None),
+        };
+        let Some(manager) = manager else {
+            return Ok(());
+        };
+        self.visit_node(&manager, source, env, scope)?;
+        let ty = expression_type_in(&manager, source, env);
+        let method = if is_async { "__aenter__" } else { "__enter__" };
+        let entered = match &ty {
+            Type::Named(class) => match member(class, method, &self.classes) {
+                Some(Some(Type::Function { returns, .. })) if is_async => returns.awaited(),
+                Some(Some(Type::Function { returns, .. })) => (**returns).clone(),
+                _ => Type::Unknown,
+            },
+            _ => Type::Unknown,
+        };
+        let entered = entered.bind_self(&ty);
+        self.managers
+            .insert(item.start_byte(), self.types.intern(&ty));
+        if let Some(target) = target {
+            self.bind_target(&target, entered, source, env, scope)?;
+        }
         Ok(())
     }
 
+    /// Visits an `except` clause, binding its `as` target to the exception
+    /// it catches: an `ExceptionGroup` of them for `except*`.
+    fn visit_except_clause(
+        &mut self,
+        clause: &Node,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+    ) -> Result<()> {
+        let mut cursor = clause.walk();
+        for child in clause.named_children(&mut cursor) {
+            if child.kind() != "as_pattern" {
+                self.visit_node(&child, source, env, scope)?;
+                continue;
+            }
+            let Some(value) = child.named_child(0) else {
+                continue;
+            };
+            self.visit_node(&value, source, env, scope)?;
+            let ty = match exceptions::caught_type(&value, source, env) {
+                ty if exceptions::catches_group(clause) && ty != Type::Unknown => {
+                    Type::Generic { name: "ExceptionGroup".to_string(), params: vec![ty] }
+                },
+                ty => ty,
+            };
+            if let Some(target) = child
+                .child_by_field_name("alias")
+                .and_then(|alias| alias.named_child(0))
+            {
+                self.bind_target(&target, ty, source, env, scope)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Visits the decorators of a definition, which live on its parent.
+    fn visit_decorated(
+        &mut self,
+        node: &Node,
+        source: &[u8],
+        env: &mut TypeEnv,
+        scope: usize,
+    ) -> Result<()> {
+        let Some(parent) = node.parent().filter(|p| p.kind() == "decorated_definition") else {
+            return Ok(());
+        };
+        let mut cursor = parent.walk();
+        for decorator in parent
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "decorator")
+        {
+            self.visit_node(&decorator, source, env, scope)?;
+        }
+        Ok(())
+    }
+
+    /// Records an attribute of a class, joining it with any type already
+    /// seen for the same attribute.
+    fn add_attribute(&mut self, class: &str, attribute: &str, ty: Type) {
+        let Some(info) = self.classes.get_mut(class) else {
+            return;
+        };
+        let joined = match info.attributes.remove(attribute) {
+            Some(seen) => seen.join(ty).capped(self.max_union_members),
+            None => ty,
+        };
+        info.attributes.insert(attribute.to_string(), joined);
+    }
+
+    /// Starts recording a scope nested in `parent` and returns its index.
+    fn enter_scope(&mut self, name: &str, parent: usize) -> usize {
+        let name = match self.scopes[parent].name.as_str() {
+            "" => name.to_string(),
+            prefix => format!("{}.{}", prefix, name),
+        };
+        self.scopes
+            .push(ScopeTypes { name, ..ScopeTypes::default() });
+        self.scopes.len() - 1
+    }
+
+    /// Binds a variable for the code that follows and records the type
+    /// among those the scope has seen for it. A variable other scopes
+    /// rebind is bound as `Unknown`.
+    fn bind(&mut self, env: &mut TypeEnv, scope: usize, name: &str, ty: Type) {
+        let seen = self.scopes[scope].variables.remove(name);
+        let recorded = match seen {
+            Some(seen) => seen.join(ty.clone()).capped(self.max_union_members),
+            None => ty.clone(),
+        };
+        self.scopes[scope]
+            .variables
+            .insert(name.to_string(), recorded);
+        let ty = if self.rebound.contains(name) { Type::Unknown } else { ty };
+        env.bind_interned(name.to_string(), self.types.intern(&ty));
+    }
+
+    /// Defines the type alias `node` defines, if any, in `env`, and among
+    /// the module's if it is at the top level.
+    fn define_alias(&mut self, node: &Node, source: &[u8], env: &mut TypeEnv, scope: usize) {
+        let Some((name, ty)) = alias_definition(node, source, scope == 0) else {
+            return;
+        };
+        if scope == 0 {
+            self.aliases.define_alias(name.clone(), ty.clone());
+        }
+        env.define_alias(name, ty);
+    }
+
     /// Infers the type of an expression node.
     #[allow(dead_code)]
-    fn infer_expression_type(&self, _node: &Node, _source: &[u8]) -> Result<Type> {
-        // TODO: Implement expression type inference
-        Ok(Type::Unknown)
+    fn infer_expression_type(&self, node: &Node, source: &[u8]) -> Result<Type> {
+        Ok(expression_type(node, source))
+    }
+}
+
+/// Names declared `global` or `nonlocal` anywhere in a tree.
+fn global_names(root: &Node, source: &[u8]) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut stack = vec![*root];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        if matches!(node.kind(), "global_statement" | "nonlocal_statement") {
+            names.extend(
+                node.named_children(&mut cursor)
+                    .filter_map(|n| n.utf8_text(source).ok())
+                    .map(str::to_string),
+            );
+        } else {
+            stack.extend(node.named_children(&mut cursor));
+        }
     }
+    names
+}
+
+/// The name a parameter binds and its declared type, else the type of its
+/// default other than `None`, else `Unknown`. `*args: T` binds a
+/// `tuple[T, ...]` and `**kwargs: T` a `Dict[str, T]`. Separators bind
+/// nothing.
+fn parameter_type(param: &Node, source: &[u8], env: &TypeEnv) -> Option<(String, Type)> {
+    let name = parameter_name(param)?;
+    let declared = param
+        .child_by_field_name("type")
+        .map(|t| annotation_type(&t, source));
+    let pattern = match param.kind() {
+        "typed_parameter" => param.named_child(0)?,
+        _ => *param,
+    };
+    let ty = match pattern.kind() {
+        "list_splat_pattern" => Type::variadic_tuple(declared.unwrap_or(Type::Any)),
+        "dictionary_splat_pattern" => {
+            Type::Dict(Box::new(Type::Str), Box::new(declared.unwrap_or(Type::Any)))
+        },
+        // A `None` default stands in for an argument of any type.
+        _ => declared.unwrap_or_else(|| {
+            param
+                .child_by_field_name("value")
+                .map(|v| expression_type_in(&v, source, env).widened())
+                .filter(|ty| *ty != Type::None)
+                .unwrap_or_default()
+        }),
+    };
+    Some((name.utf8_text(source).unwrap_or_default().to_string(), ty))
+}
+
+/// The names an `import` or `from ... import` statement binds: the alias
+/// if there is one, else the first component of the module path.
+fn imported_names<'s>(import: &Node, source: &'s [u8]) -> Vec<&'s str> {
+    let module = import.child_by_field_name("module_name");
+    let mut cursor = import.walk();
+    import
+        .named_children(&mut cursor)
+        .filter(|n| Some(*n) != module)
+        .filter_map(|n| match n.kind() {
+            "aliased_import" => n.child_by_field_name("alias"),
+            "dotted_name" => n.named_child(0),
+            _ => None,
+        })
+        .filter_map(|n| n.utf8_text(source).ok())
+        .collect()
+}
+
+/// Text of a node's field, or an empty string when it has none.
+fn field_text(node: &Node, field: &str, source: &[u8]) -> String {
+    node.child_by_field_name(field)
+        .and_then(|n| n.utf8_text(source).ok())
+        .unwrap_or_default()
+        .to_string()
 }
 
 impl Analyzer {
+    /// Analyzes a source file, dispatching on the language detected from its extension.
+    pub fn analyze_file(path: &Path) -> Result<AnalysisResult> {
+        Self::analyze_file_with_stubs(path, &StubPaths::default())
+    }
+
+    /// Analyzes a source file like [`Analyzer::analyze_file`], also looking
+    /// up the stubs of a Python module in `stubs`.
+    pub fn analyze_file_with_stubs(path: &Path, stubs: &StubPaths) -> Result<AnalysisResult> {
+        match Language::from_path(path) {
+            Some(Language::Python) => {
+                Self::analyze_python_source_with_stubs(&SourceFile::read(path)?, stubs)
+            },
+            Some(language) if language.is_script() => script::analyze_script_file(path, language),
+            _ => Err(Error::invalid_value(
+                "path",
+                path.display().to_string(),
+                "unsupported file type",
+            )),
+        }
+    }
+
+    /// Analyzes already loaded source text, such as an editor buffer.
+    pub fn analyze_source(file: &SourceFile, language: Language) -> Result<AnalysisResult> {
+        match language {
+            Language::Python => Self::analyze_python_source(file),
+            language => script::analyze_script_source(file, language),
+        }
+    }
+
+    /// Analyzes each file in turn, continuing past files that fail to read
+    /// or parse. Failures are returned alongside the successful results.
+    pub fn analyze_files<I>(paths: I) -> Partial<Vec<AnalysisResult>>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        Self::analyze_files_with_stubs(paths, &StubPaths::default())
+    }
+
+    /// Analyzes each file like [`Analyzer::analyze_files`], also looking
+    /// up the stubs of Python modules in `stubs`.
+    pub fn analyze_files_with_stubs<I>(paths: I, stubs: &StubPaths) -> Partial<Vec<AnalysisResult>>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        let mut batch = Partial::new(Vec::new());
+        for path in paths {
+            let result = Self::analyze_file_with_stubs(&path, stubs).map_err(|e| match e.path() {
+                Some(_) => e,
+                None => e.with_path(&path),
+            });
+            if let Some(res) = batch.record(result) {
+                batch.value.push(res);
+            }
+        }
+        batch
+    }
+
+    /// The type of the identifier or expression at a 0-based line and byte
+    /// column of a Python file, as [`Analyzer::type_at_byte`] finds it.
+    /// `None` if the position is past the end of its line or the type
+    /// isn't known.
+    pub fn type_at_position(path: &Path, line: usize, column: usize) -> Result<Option<Type>> {
+        Self::type_at_position_in(&SourceFile::read(path)?, line, column)
+    }
+
+    /// Looks up a type like [`Analyzer::type_at_position`] in loaded
+    /// source, such as an editor buffer.
+    pub fn type_at_position_in(
+        file: &SourceFile,
+        line: usize,
+        column: usize,
+    ) -> Result<Option<Type>> {
+        let Some(byte) = file.line_index().offset(LineCol { line, col: column }) else {
+            return Ok(None);
+        };
+        let tree = Parser::new()?.parse_string(file.text())?;
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, file.text().as_bytes())?;
+        Ok(analyzer.type_at_byte(&tree.root_node(), file.text().as_bytes(), byte))
+    }
+
     /// Performs a minimal analysis on a Python source file: counts functions and classes.
     pub fn analyze_python_file(path: &Path) -> Result<AnalysisResult> {
+        Self::analyze_python_source(&SourceFile::read(path)?)
+    }
+
+    /// Performs the same analysis as [`Analyzer::analyze_python_file`] on loaded source.
+    pub fn analyze_python_source(file: &SourceFile) -> Result<AnalysisResult> {
+        Self::analyze_python_source_with_stubs(file, &StubPaths::default())
+    }
+
+    /// Analyzes loaded source like [`Analyzer::analyze_python_source`].
+    /// Calls are checked against the module's `.pyi` stub, if there is one
+    /// next to the file or in `stubs.dirs`, and calls into the standard
+    /// library against typeshed's stubs.
+    pub fn analyze_python_source_with_stubs(
+        file: &SourceFile,
+        stubs: &StubPaths,
+    ) -> Result<AnalysisResult> {
         let mut parser = Parser::new()?;
-        let tree = parser.parse_file(path)?;
+        let tree = parser.parse_string(file.text())?;
 
         let root = tree.root_node();
         let mut cursor = root.walk();
@@ -111,37 +1483,35 @@ impl Analyzer {
                     // Check for missing parameter and return annotations
                     // parameters node is available via field name
                     if let Some(params) = node.child_by_field_name("parameters") {
-                        // Iterate over parameters' children
-                        let mut c = params.walk();
-                        for p in params.children(&mut c) {
-                            let p_kind = p.kind();
-                            let is_typed = p_kind == "typed_parameter";
-                            // If it's clearly a parameter and not typed, flag it
-                            if !is_typed
-                                && (p_kind == "identifier" || p_kind == "default_parameter")
-                            {
-                                let pos = p.start_position();
-                                diagnostics.push(Diagnostic {
-                                    path: path.to_string_lossy().to_string(),
-                                    line: pos.row,
-                                    column: pos.column,
-                                    message: "Missing type annotation for parameter".to_string(),
-                                    severity: "warning".to_string(),
-                                });
+                        // `self` and `cls` are passed implicitly and need no annotation
+                        let receiver =
+                            FunctionKind::of(&node, file.text().as_bytes()).receiver(&params);
+                        for (p, _) in parameters(&params) {
+                            if Some(p) == receiver || is_annotated(&p) {
+                                continue;
                             }
+                            let Some(name) = parameter_name(&p) else {
+                                continue;
+                            };
+                            diagnostics.push(Diagnostic::from_message(
+                                Severity::Warning,
+                                file.path(),
+                                file.node_span(&p),
+                                MessageId::UnannotatedParameter,
+                                &[("name", file.slice(&file.node_span(&name)))],
+                            ));
                         }
                     }
 
                     // Return annotation: field name is often "return_type" in tree-sitter-python
                     if node.child_by_field_name("return_type").is_none() {
-                        let pos = node.start_position();
-                        diagnostics.push(Diagnostic {
-                            path: path.to_string_lossy().to_string(),
-                            line: pos.row,
-                            column: pos.column,
-                            message: "Missing return type annotation".to_string(),
-                            severity: "warning".to_string(),
-                        });
+                        diagnostics.push(Diagnostic::from_message(
+                            Severity::Warning,
+                            file.path(),
+                            signature_span(file, &node),
+                            MessageId::UnannotatedReturn,
+                            &[("name", node_name(file, &node))],
+                        ));
                     }
                 },
                 "class_definition" => class_count += 1,
@@ -154,15 +1524,141 @@ impl Analyzer {
             }
         }
 
+        // Doctest examples are checked against the signatures they call
+        diagnostics.extend(doctest::check_doctests(&mut parser, &tree, file)?);
+
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, file.text().as_bytes())?;
+        let stub = Stub::load(&mut parser, Path::new(file.path()), &stubs.dirs)?;
+        let typeshed = Typeshed::new(stubs.typeshed.as_deref());
+        let typed_dicts = TypedDicts::collect(&tree.root_node(), file.text().as_bytes());
+        diagnostics.extend(calls::check_calls(
+            &mut parser,
+            &tree,
+            file,
+            &analyzer,
+            stub.as_ref(),
+            &typeshed,
+            &typed_dicts,
+        )?);
+        diagnostics.extend(attributes::check_attributes(&tree, file, &analyzer));
+        diagnostics.extend(operators::check_operators(&tree, file, &analyzer));
+        diagnostics.extend(comparisons::check_comparisons(&tree, file, &analyzer));
+        diagnostics.extend(context_managers::check_with_statements(&tree, file, &analyzer));
+        diagnostics.extend(exceptions::check_handlers(&tree, file, &analyzer));
+        diagnostics.extend(assignments::check_assignments(&tree, file, &analyzer));
+        diagnostics.extend(typed_dicts::check_typed_dicts(&tree, file, &analyzer, &typed_dicts));
+        diagnostics.extend(returns::check_returns(&tree, file, &analyzer));
+        diagnostics.extend(exhaustiveness::check_matches(&tree, file, &analyzer));
+        diagnostics.extend(unreachable::check_unreachable(&tree, file, &analyzer));
+        diagnostics.extend(shadowing::check_shadowing(&tree, file));
+        diagnostics.extend(duplicates::check_duplicates(&tree, file));
+        let parameters = analyzer.infer_parameters(&tree, file, Unsolved::Report);
+        let (mut unsolved, reported): (Vec<Diagnostic>, Vec<Diagnostic>) = parameters
+            .diagnostics
+            .into_iter()
+            .partition(|d| d.message_id == Some(MessageId::UninferredParameter));
+        diagnostics.extend(reported);
+        let comments = extract_comments(&tree, file);
+        suppress(&mut diagnostics, &comments);
+        suppress(&mut unsolved, &comments);
+
         Ok(AnalysisResult {
-            path: path.to_string_lossy().to_string(),
+            path: file.path().to_string(),
             function_count,
             class_count,
             diagnostics,
+            inferred_returns: analyzer.inferred_returns(),
+            inferred_parameters: parameters.types,
+            unsolved,
+            classes: analyzer.classes,
+            metrics: TypeMetrics::collect(&tree, file),
         })
     }
 }
 
+/// Drops the diagnostics a `# type: ignore`, `# noqa`, or `# omnitype:
+/// ignore` comment on the line they start on suppresses, either for every
+/// code or for theirs. Diagnostics without a code are only suppressed by
+/// comments that list no codes.
+fn suppress(diagnostics: &mut Vec<Diagnostic>, comments: &[Comment]) {
+    diagnostics.retain(|d| {
+        let code = d.code.as_deref().unwrap_or_default();
+        !comments
+            .iter()
+            .any(|c| c.span.start.line == d.span.start.line && c.suppresses(code))
+    });
+}
+
+/// Whether `expected` accepts no value of type `found`. Variables hold the
+/// union of everything assigned to them on the paths that reach a read, so
+/// a union, even inside a container, is only rejected if each of its
+/// members is. An instance of one of the file's classes is rejected where
+/// another of them is expected if `classes` shows it doesn't derive from
+/// it, unless the expected class is a protocol, which is matched by
+/// structure.
+fn rejects(expected: &Type, found: &Type, classes: &ClassHierarchy) -> bool {
+    let rejects = |expected, found| rejects(expected, found, classes);
+    match (expected, found) {
+        (_, Type::Union(members)) => members.iter().all(|member| rejects(expected, member)),
+        (Type::Union(members), _) => members.iter().all(|member| rejects(member, found)),
+        (Type::List(a), Type::List(b)) | (Type::Set(a), Type::Set(b)) => rejects(a, b),
+        (Type::Dict(ak, av), Type::Dict(bk, bv)) => rejects(ak, bk) || rejects(av, bv),
+        (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => {
+            a.iter().zip(b).any(|(a, b)| rejects(a, b))
+        },
+        (Type::Named(a), Type::Named(b)) if classes.bases(a).is_some() => {
+            let protocol = classes.mro(a).is_some_and(|mro| {
+                mro.iter()
+                    .any(|base| base.rsplit('.').next() == Some("Protocol"))
+            });
+            !protocol && classes.is_subclass(b, a) == Some(false)
+        },
+        _ => expected.accepts(found) == Some(false),
+    }
+}
+
+/// Span from the start of a definition through its name (e.g. `def foo`).
+fn signature_span(file: &SourceFile, node: &Node) -> Span {
+    let end = node
+        .child_by_field_name("name")
+        .map_or(node.end_byte(), |name| name.end_byte());
+    file.span(node.start_byte(), end)
+}
+
+/// Text of a node's `name` field, or of the node itself when it has none
+/// (e.g. a bare identifier parameter).
+fn node_name<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    let name = node.child_by_field_name("name").unwrap_or(*node);
+    file.slice(&file.node_span(&name))
+}
+
+/// Analyzes `source` and runs `check`, one of the checks that read what the
+/// analyzer inferred, over it.
+#[cfg(test)]
+fn run_check(
+    source: &str,
+    check: impl FnOnce(&Tree, &SourceFile, &Analyzer) -> Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    let file = SourceFile::from_text("a.py", source);
+    let tree = Parser::new().unwrap().parse_string(source).unwrap();
+    let mut analyzer = Analyzer::new();
+    analyzer.analyze(&tree, source.as_bytes()).unwrap();
+    check(&tree, &file, &analyzer)
+}
+
+/// [`run_check`], with each diagnostic formatted as `line: message`.
+#[cfg(test)]
+fn check_messages(
+    source: &str,
+    check: impl FnOnce(&Tree, &SourceFile, &Analyzer) -> Vec<Diagnostic>,
+) -> Vec<String> {
+    run_check(source, check)
+        .iter()
+        .map(|d| format!("{}: {}", d.span.start.line, d.message))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,6 +1666,432 @@ mod tests {
     #[test]
     fn test_analyzer_initialization() {
         let analyzer = Analyzer::new();
-        assert!(analyzer.type_env.is_empty());
+        assert!(analyzer.scopes().is_empty());
+    }
+
+    #[test]
+    fn test_assignments_tracked_per_scope() {
+        let source = "x = 1\nx = 'a'\ny = x\n\ndef f(n: int, flag=False):\n    total = n\n    \
+                      if flag:\n        total = None\n    for i in [1.5]:\n        last = i\n    \
+                      return total\n\nclass C:\n    x = None\n    z = x\n    \
+                      def m(self):\n        return self, x\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+
+        let types = |scope: &str| -> Vec<String> {
+            analyzer
+                .scope(scope)
+                .unwrap()
+                .variables
+                .iter()
+                .map(|(name, ty)| format!("{}: {}", name, ty))
+                .collect()
+        };
+        assert_eq!(types(""), ["C: C", "x: int | str", "y: str"]);
+        assert_eq!(
+            types("f"),
+            ["flag: bool", "i: float", "last: float", "n: int", "total: Optional[int]"]
+        );
+        assert_eq!(types("C.m"), ["self: C"]);
+
+        let read = |needle: &str| {
+            let byte = source.find(needle).unwrap();
+            analyzer.type_at(byte).map(|t| t.to_string())
+        };
+        assert_eq!(read("x\n\ndef"), Some("str".to_string()));
+        assert_eq!(read("total\n\nclass"), Some("Optional[int]".to_string()));
+        assert_eq!(read("self, x"), Some("C".to_string()));
+        assert_eq!(read("x\n    def"), Some("None".to_string()));
+        // The method reads the module's `x`, not the class body's.
+        let method_read = source.rfind("x\n").unwrap();
+        assert_eq!(analyzer.type_at(method_read), Some(&Type::Str));
+    }
+
+    #[test]
+    fn test_none_checks_narrow_optionals() {
+        let source = "def f(x: Optional[int], y: str | None, z: int | None):\n    \
+                      if x is None:\n        return 0\n    a = x\n    if y is not None and z:\n        \
+                      b = (y, z)\n    else:\n        c = y\n    while z is None:\n        \
+                      z = 1\n    d = z\n    if y:\n        pass\n    e = y\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+
+        let variables = &analyzer.scope("f").unwrap().variables;
+        let ty = |name: &str| variables[name].to_string();
+        assert_eq!(ty("a"), "int");
+        assert_eq!(ty("b"), "Tuple[str, int]");
+        assert_eq!(ty("c"), "Optional[str]");
+        assert_eq!(ty("d"), "int");
+        assert_eq!(ty("e"), "Optional[str]");
+    }
+
+    #[test]
+    fn test_constants_keep_literal_types() {
+        let source = "METHOD = 'GET'\nRETRIES: Final = 3\nDEBUG = False\nTIMEOUT: int = 5\n\
+                      mode = 'r'\nmode = 'w'\n\n\
+                      def send(kind: Literal['get', 'post'], fallback='x'):\n    local = 'a'\n    \
+                      if kind == 'get':\n        a = kind\n    else:\n        b = kind\n    \
+                      if kind != 'post':\n        c = kind\n    return METHOD\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+
+        let module = &analyzer.scope("").unwrap().variables;
+        let ty = |name: &str| module[name].to_string();
+        assert_eq!(ty("METHOD"), "Literal['GET']");
+        assert_eq!(ty("RETRIES"), "Literal[3]");
+        assert_eq!(ty("DEBUG"), "Literal[False]");
+        assert_eq!(ty("TIMEOUT"), "int");
+        assert_eq!(ty("mode"), "str");
+
+        let send = &analyzer.scope("send").unwrap().variables;
+        let ty = |name: &str| send[name].to_string();
+        assert_eq!(ty("local"), "str");
+        assert_eq!(ty("fallback"), "str");
+        assert_eq!(ty("a"), "Literal['get']");
+        assert_eq!(ty("b"), "Literal['post']");
+        assert_eq!(ty("c"), "Literal['get']");
+        assert_eq!(analyzer.inferred_returns()["send"], Type::Str);
+    }
+
+    #[test]
+    fn test_per_node_types_are_shared() {
+        let source = format!("x = [(1, 'a')]\n{}", "y = x\nx == y\n".repeat(500));
+        let tree = Parser::new().unwrap().parse_string(&source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+
+        let recorded = analyzer.references.len()
+            + analyzer.definitions.len()
+            + analyzer.assigned.len()
+            + analyzer.operands.len();
+        assert!(recorded > 2000, "{}", recorded);
+        // Every name read and bound, and every operand, has the same list
+        // type, stored once.
+        assert_eq!(analyzer.types.len(), 1);
+        let (left, right) = &analyzer.operands[&source.find("==").unwrap()];
+        assert!(Interned::ptr_eq(left, right));
+        let first = source.find("x\n").unwrap();
+        let last = source.rfind("x\n").unwrap();
+        assert!(Interned::ptr_eq(&analyzer.references[&first], &analyzer.references[&last]));
+    }
+
+    #[test]
+    fn test_inferred_unions_are_capped() {
+        let source = "def f(n: int):\n    if n == 1:\n        x = 1\n    elif n == 2:\n        x = 'a'\n    \
+                      elif n == 3:\n        x = b''\n    elif n == 4:\n        x = 1.0\n    elif n == 5:\n        \
+                      x = ()\n    elif n == 6:\n        x = {}\n    else:\n        x = None\n    y = x\n    \
+                      if n:\n        return True\n    if n > 1:\n        return 1.5\n    return 'a'\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        assert_eq!(analyzer.scope("f").unwrap().variables["y"], Type::Any);
+        assert_eq!(analyzer.inferred_returns()["f"].to_string(), "bool | float | str");
+
+        let mut analyzer = Analyzer::new().with_max_union_members(2);
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        assert_eq!(analyzer.inferred_returns()["f"].to_string(), "float | str");
+    }
+
+    #[test]
+    fn test_type_at_position() {
+        let source = "count = 1\ncount = 'one'\n\ndef f(limit: float, names=[]):\n    \
+                      total = count + 'x'\n    return limit * 2, \"é\"\n";
+        let file = SourceFile::from_text("a.py", source);
+        let ty = |line, column| {
+            Analyzer::type_at_position_in(&file, line, column)
+                .unwrap()
+                .map(|ty| ty.to_string())
+        };
+        assert_eq!(ty(0, 2).as_deref(), Some("int"));
+        assert_eq!(ty(1, 0).as_deref(), Some("str"));
+        assert_eq!(ty(3, 8).as_deref(), Some("float"));
+        assert_eq!(ty(3, 20).as_deref(), Some("List[Any]"));
+        assert_eq!(ty(4, 13).as_deref(), Some("str"));
+        assert_eq!(ty(4, 20).as_deref(), Some("str"));
+        assert_eq!(ty(5, 17).as_deref(), Some("float"));
+        assert_eq!(ty(5, 24).as_deref(), Some("str"));
+        assert_eq!(ty(3, 0), None);
+        assert_eq!(ty(0, 40), None);
+    }
+
+    #[test]
+    fn test_loops_imports_and_isinstance() {
+        let source = "p = None\nfrom os import path as p\nq = p\nclass C:\n    pass\n\n\
+                      def f(items: list[str], v):\n    margin = None\n    found = None\n    \
+                      for item in items:\n        if margin is None:\n            margin = item\n        \
+                      else:\n            m = margin\n        if item:\n            found = 1\n            \
+                      break\n    if isinstance(v, (int, str)):\n        a = v\n    if isinstance(v, C):\n        \
+                      b = v\n    c = v < 1\n    d = found\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+
+        let variables = &analyzer.scope("f").unwrap().variables;
+        let ty = |name: &str| variables[name].to_string();
+        assert_eq!(ty("m"), "str");
+        assert_eq!(ty("a"), "int | str");
+        assert_eq!(ty("b"), "C");
+        assert_eq!(ty("c"), "Unknown");
+        assert_eq!(ty("d"), "Optional[int]");
+        assert_eq!(analyzer.scope("").unwrap().variables["q"], Type::Unknown);
+    }
+
+    #[test]
+    fn test_isinstance_intersections() {
+        let source = "class Readable:
+    pass
+
+class Closable:
+    pass
+
+\
+                      def f(r: Readable):
+    if isinstance(r, Closable):
+        a = r
+    \
+                      if isinstance(r, Readable):
+        b = r
+
+\
+                      def g(v):
+    if isinstance(v, Readable) and isinstance(v, Closable):
+        \
+                      c = v
+        if isinstance(c, Readable):
+            d = c
+";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let ty =
+            |scope: &str, name: &str| analyzer.scope(scope).unwrap().variables[name].to_string();
+        assert_eq!(ty("f", "a"), "Closable & Readable");
+        assert_eq!(ty("f", "b"), "Readable");
+        assert_eq!(ty("g", "c"), "Closable & Readable");
+        assert_eq!(ty("g", "d"), "Closable & Readable");
+    }
+
+    #[test]
+    fn test_return_types_inferred_from_bodies() {
+        let source = "def a(x: int):\n    if x:\n        return x\n\ndef b(x: int):\n    \
+                      if x:\n        return 'y'\n    else:\n        raise ValueError()\n\n\
+                      def c():\n    yield 1\n\ndef d() -> int:\n    return 1\n\ndef e(x):\n    \
+                      return x\n\ndef f():\n    pass\n";
+        let file = SourceFile::from_text("r.py", source);
+        let result = Analyzer::analyze_python_source(&file).unwrap();
+        let returns: Vec<String> = result
+            .inferred_returns
+            .iter()
+            .map(|(name, ty)| format!("{} -> {}", name, ty))
+            .collect();
+        assert_eq!(returns, ["a -> Optional[int]", "b -> str", "f -> None"]);
+    }
+
+    #[test]
+    fn test_class_attributes_collected() {
+        let source =
+            "class Point(Base):\n    dims = 2\n\n    def __init__(this, x: int, y=0.5):\n        \
+                      this.x = x\n        this.y = y\n        this.label = None\n\n    \
+                      def rename(self, label: str) -> None:\n        self.label = label\n        \
+                      other = Point(1)\n        other.z = 1\n";
+        let file = SourceFile::from_text("p.py", source);
+        let result = Analyzer::analyze_python_source(&file).unwrap();
+        let point = &result.classes["Point"];
+        assert_eq!(point.bases, ["Base"]);
+        let attributes: Vec<String> = point
+            .attributes
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                "__init__: (x: int, y: float = 0.5) -> None",
+                "dims: int",
+                "label: Optional[str]",
+                "rename: Callable[[str], None]",
+                "x: int",
+                "y: float",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_async_functions() {
+        let source = "async def fetch(n: int):\n    return 'page'\n\n\
+                      async def events() -> AsyncIterator[bytes]:\n    yield b''\n\n\
+                      async def main():\n    text = await fetch(1)\n    pending = fetch(2)\n    \
+                      async for item in [1]:\n        pass\n    async with lock as conn:\n        \
+                      pass\n    async for event in events():\n        pass\n\n\
+                      async def ticks():\n    yield 1\n\ndef plain():\n    return 1\n\n\
+                      count = plain()\nstream = ticks()\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let main = &analyzer.scope("main").unwrap().variables;
+        assert_eq!(main["text"], Type::Str);
+        assert_eq!(main["pending"].to_string(), "Coroutine[Any, Any, str]");
+        assert_eq!(main["item"], Type::Unknown);
+        assert_eq!(main["conn"], Type::Unknown);
+        assert_eq!(main["event"], Type::Bytes);
+        let module = &analyzer.scope("").unwrap().variables;
+        assert_eq!(module["count"], Type::Int);
+        assert_eq!(module["stream"], Type::Unknown);
+        assert_eq!(analyzer.inferred_returns()["fetch"], Type::Str);
+    }
+
+    #[test]
+    fn test_decorated_methods() {
+        let source =
+            "class C:\n    @property\n    def size(self) -> int:\n        return 1\n\n    \
+                      @size.setter\n    def size(self, value: int) -> None:\n        pass\n\n    \
+                      @staticmethod\n    def make(x) -> None:\n        pass\n\n    \
+                      @classmethod\n    def build(cls, y: str) -> None:\n        pass\n\n    \
+                      @register\n    def run(self, z: int) -> None:\n        pass\n";
+        let file = SourceFile::from_text("d.py", source);
+        let result = Analyzer::analyze_python_source(&file).unwrap();
+        let messages: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages, ["Missing type annotation for parameter 'x'"]);
+        let attributes: Vec<String> = result.classes["C"]
+            .attributes
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                "build: Callable[[str], None]",
+                "make: Callable[[Unknown], None]",
+                "run: Unknown",
+                "size: int",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variadic_and_keyword_only_parameters() {
+        let source = "class C:\n    def m(self, a: int, /, *args, key, **kw) -> None:\n        \
+                      pass\n\n    def splat(*args: int) -> None:\n        pass\n\n\
+                      def f(x: int, *nums: float, flag: bool = False, **opts: str) -> None:\n    \
+                      total = nums\n\nitems = f\n";
+        let file = SourceFile::from_text("v.py", source);
+        let result = Analyzer::analyze_python_source(&file).unwrap();
+        let messages: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Missing type annotation for parameter 'args'",
+                "Missing type annotation for parameter 'key'",
+                "Missing type annotation for parameter 'kw'",
+            ]
+        );
+        assert_eq!(
+            result.classes["C"].attributes["m"].to_string(),
+            "(a: int, /, *args: Any, key: Unknown, **kw: Any) -> None"
+        );
+        assert_eq!(result.classes["C"].attributes["splat"].to_string(), "(*args: int) -> None");
+
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let types =
+            |scope: &str, name: &str| analyzer.scope(scope).unwrap().variables[name].to_string();
+        assert_eq!(types("C.m", "args"), "tuple[Any, ...]");
+        assert_eq!(types("C.m", "kw"), "Dict[str, Any]");
+        assert_eq!(types("C.splat", "args"), "tuple[int, ...]");
+        assert_eq!(types("f", "total"), "tuple[float, ...]");
+        assert_eq!(types("f", "opts"), "Dict[str, str]");
+        assert_eq!(
+            types("", "items"),
+            "(x: int, *nums: float, flag: bool = False, **opts: str) -> None"
+        );
+    }
+
+    #[test]
+    fn test_param_spec_decorators() {
+        let source = "from typing import Callable, Concatenate, ParamSpec, TypeVar\n\
+                      P = ParamSpec('P')\nR = TypeVar('R')\n\n\
+                      def retry(f: Callable[P, R]) -> Callable[P, R]:\n    return f\n\n\
+                      def session(f: Callable[Concatenate[Session, P], R]) -> \
+                      Callable[P, List[R]]:\n    return f\n\n\
+                      @retry\ndef fetch(url: str, timeout: float = 1.0) -> bytes:\n    \
+                      return b''\n\n\
+                      @session\n@functools.cache\ndef query(db: Session, sql: str) -> int:\n    \
+                      return 1\n\n\
+                      @retry(times=3)\ndef other(x: int) -> int:\n    return x\n\n\
+                      a = retry\nb = fetch\nc = query\nd = other\ne = session\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let types = |name: &str| analyzer.scope("").unwrap().variables[name].to_string();
+        assert_eq!(types("a"), "Callable[[Callable[P, R]], Callable[P, R]]");
+        assert_eq!(types("b"), "(url: str, timeout: float = 1.0) -> bytes");
+        assert_eq!(types("c"), "Callable[[str], List[int]]");
+        assert_eq!(types("d"), "Unknown");
+        assert_eq!(
+            types("e"),
+            "Callable[[Callable[Concatenate[Session, P], R]], Callable[P, List[R]]]"
+        );
+    }
+
+    #[test]
+    fn test_self_and_class_receivers() {
+        let source = "from typing import Self\n\nclass Conn:\n    \
+                      def __enter__(self) -> Self:\n        return self\n\n    \
+                      def __exit__(self, *exc) -> None:\n        pass\n\n    \
+                      def same(self, other: Self) -> bool:\n        peer = other\n        \
+                      return True\n\n    @classmethod\n    def open(cls) -> Self:\n        \
+                      conn = cls()\n        return conn\n\n    \
+                      def __new__(cls) -> Self:\n        return object.__new__(cls)\n\n\
+                      class Pooled(Conn):\n    pass\n\nwith Pooled() as pooled:\n    pass\n";
+        let file = SourceFile::from_text("s.py", source);
+        let result = Analyzer::analyze_python_source(&file).unwrap();
+        let messages: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages, ["Missing type annotation for parameter 'exc'"]);
+        assert_eq!(
+            result.classes["Conn"].attributes["__enter__"].to_string(),
+            "Callable[[], Self]"
+        );
+
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let types =
+            |scope: &str, name: &str| analyzer.scope(scope).unwrap().variables[name].to_string();
+        assert_eq!(types("Conn.__enter__", "self"), "Conn");
+        assert_eq!(types("Conn.same", "peer"), "Conn");
+        assert_eq!(types("Conn.open", "cls"), "Type[Conn]");
+        assert_eq!(types("Conn.open", "conn"), "Conn");
+        assert_eq!(types("Conn.__new__", "cls"), "Type[Conn]");
+        assert_eq!(types("", "pooled"), "Pooled");
+    }
+
+    #[test]
+    fn test_analyze_files_continues_past_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good.py");
+        std::fs::write(&good, "def f(x: int) -> int:\n    return x\n").unwrap();
+        let missing = dir.path().join("missing.py");
+
+        let batch = Analyzer::analyze_files(vec![missing.clone(), good]);
+        assert_eq!(batch.value.len(), 1);
+        assert_eq!(batch.value[0].function_count, 1);
+        assert_eq!(batch.errors.len(), 1);
+        assert_eq!(batch.errors[0].path(), Some(missing.as_path()));
     }
 }
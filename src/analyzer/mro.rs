@@ -0,0 +1,146 @@
+//! Method resolution order of the classes defined in a file.
+//!
+//! Members are looked up along a class's MRO, computed with C3
+//! linearization as Python does: the class, then its bases' MROs merged so
+//! that every class comes before its bases and bases keep the order they
+//! are listed in. A hierarchy Python would reject (`class C(A, B)` where
+//! `B` subclasses `A`) has no MRO.
+
+use std::collections::BTreeMap;
+
+use super::attributes::is_dataclass;
+use super::ClassInfo;
+use crate::types::Type;
+
+/// Comparison methods `dataclass(order=True)` adds.
+const ORDER_METHODS: &[&str] = &["__lt__", "__le__", "__gt__", "__ge__"];
+
+/// The classes a class's members are looked up in, in method resolution
+/// order, starting with the class itself. `None` if a base isn't a class
+/// defined in the file or the bases can't be linearized. Bases like
+/// `object` and `Generic[T]` that add no members of interest are skipped.
+pub(super) fn mro<'c>(
+    class: &str,
+    classes: &'c BTreeMap<String, ClassInfo>,
+) -> Option<Vec<&'c str>> {
+    linearize(class, classes, &mut Vec::new())
+}
+
+/// The type of a class's member, from the first class along its MRO that
+/// defines it: `None` if the class's members aren't known, `Some(None)` if
+/// it has no such member. A class decorator other than `dataclass` may add
+/// any member, and `dataclass(order=True)` adds comparison methods.
+pub(super) fn member<'c>(
+    class: &str,
+    name: &str,
+    classes: &'c BTreeMap<String, ClassInfo>,
+) -> Option<Option<&'c Type>> {
+    let mro = mro(class, classes)?;
+    let decorated = mro.iter().any(|class| {
+        classes[*class].decorators.iter().any(|d| {
+            !is_dataclass(d) || (ORDER_METHODS.contains(&name) && d.contains("order=True"))
+        })
+    });
+    if decorated {
+        return None;
+    }
+    Some(
+        mro.iter()
+            .find_map(|class| classes[*class].attributes.get(name)),
+    )
+}
+
+fn linearize<'c>(
+    class: &str,
+    classes: &'c BTreeMap<String, ClassInfo>,
+    visiting: &mut Vec<&'c str>,
+) -> Option<Vec<&'c str>> {
+    let (class, info) = classes.get_key_value(class)?;
+    // A class can't inherit from itself, though a file can say so.
+    if visiting.contains(&class.as_str()) {
+        return None;
+    }
+    visiting.push(class);
+    let bases = info
+        .bases
+        .iter()
+        .filter(|base| !is_typing_base(base))
+        .map(|base| resolve_base(class, base, classes))
+        .collect::<Option<Vec<_>>>()?;
+    let mut sequences = bases
+        .iter()
+        .map(|base| linearize(base, classes, visiting))
+        .collect::<Option<Vec<_>>>()?;
+    visiting.pop();
+    sequences.push(bases);
+
+    let mut order = vec![class.as_str()];
+    loop {
+        sequences.retain(|sequence| !sequence.is_empty());
+        if sequences.is_empty() {
+            return Some(order);
+        }
+        // The first head that no other sequence has to put after its own.
+        let next = sequences
+            .iter()
+            .map(|sequence| sequence[0])
+            .find(|head| !sequences.iter().any(|s| s[1..].contains(head)))?;
+        order.push(next);
+        for sequence in &mut sequences {
+            if sequence[0] == next {
+                sequence.remove(0);
+            }
+        }
+    }
+}
+
+/// The class a base named in a class statement refers to. A base is named
+/// from the scope enclosing the class.
+pub(super) fn resolve_base<'c>(
+    class: &str,
+    base: &str,
+    classes: &'c BTreeMap<String, ClassInfo>,
+) -> Option<&'c str> {
+    let scoped = class
+        .rsplit_once('.')
+        .map(|(scope, _)| format!("{}.{}", scope, base))
+        .filter(|key| classes.contains_key(key));
+    let (key, _) = classes.get_key_value(scoped.as_deref().unwrap_or(base))?;
+    Some(key)
+}
+
+/// Whether a base only affects typing, not the members of instances.
+pub(super) fn is_typing_base(base: &str) -> bool {
+    let name = base.split('[').next().unwrap_or(base);
+    let name = name.rsplit('.').next().unwrap_or(name);
+    name == "object" || (base.ends_with(']') && name == "Generic") || name == "Protocol"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::Analyzer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_c3_linearization() {
+        let source = "class O: pass\nclass A(O): pass\nclass B(O): pass\nclass C(O): pass\n\
+                      class D(O): pass\nclass E(O): pass\nclass K1(A, B, C): pass\n\
+                      class K2(D, B, E): pass\nclass K3(D, A): pass\n\
+                      class Z(K1, K2, K3, Generic[T]): pass\nclass Bad(O, A): pass\n\
+                      class Loop(Loop): pass\nclass Far(Unknown): pass\n\
+                      def f():\n    class A(K3): pass\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        let classes = analyzer.classes();
+        assert_eq!(
+            mro("Z", classes).unwrap(),
+            ["Z", "K1", "K2", "K3", "D", "A", "B", "C", "E", "O"]
+        );
+        assert_eq!(mro("f.A", classes).unwrap(), ["f.A", "K3", "D", "A", "O"]);
+        assert_eq!(mro("Bad", classes), None);
+        assert_eq!(mro("Loop", classes), None);
+        assert_eq!(mro("Far", classes), None);
+    }
+}
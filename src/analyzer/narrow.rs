@@ -0,0 +1,330 @@
+//! Narrowing of variable types by `if` and `while` conditions.
+//!
+//! A condition splits the environment in two: the bindings that hold when
+//! it is true and those that hold when it is false. `x is None`,
+//! `x is not None`, `x == None`, a bare `x`, and `not`, `and`, and `or`
+//! combinations of them narrow `Optional` variables, `x == "r"` narrows a
+//! union of `Literal` types, and `isinstance(x, C)` narrows `x` to `C`, or
+//! to `B & C` when `x` was already known to be a `B`; anything else leaves
+//! both sides unchanged.
+
+use tree_sitter::Node;
+
+use super::infer::constant_type;
+use crate::types::{Type, TypeEnv};
+
+/// The environments in which `condition` is true and false.
+pub(super) fn narrow(condition: &Node, source: &[u8], env: &TypeEnv) -> (TypeEnv, TypeEnv) {
+    match condition.kind() {
+        "parenthesized_expression" => match condition.named_child(0) {
+            Some(inner) => narrow(&inner, source, env),
+            None => (env.clone(), env.clone()),
+        },
+        "not_operator" => match condition.child_by_field_name("argument") {
+            Some(argument) => {
+                let (when_true, when_false) = narrow(&argument, source, env);
+                (when_false, when_true)
+            },
+            None => (env.clone(), env.clone()),
+        },
+        "boolean_operator" => {
+            let (Some(left), Some(right)) =
+                (condition.child_by_field_name("left"), condition.child_by_field_name("right"))
+            else {
+                return (env.clone(), env.clone());
+            };
+            let (left_true, left_false) = narrow(&left, source, env);
+            let operator = condition
+                .child_by_field_name("operator")
+                .map(|op| op.kind())
+                .unwrap_or_default();
+            if operator == "and" {
+                // The right side only runs when the left is true.
+                let (both_true, right_false) = narrow(&right, source, &left_true);
+                let mut when_false = left_false;
+                when_false.join(&right_false);
+                (both_true, when_false)
+            } else {
+                let (right_true, both_false) = narrow(&right, source, &left_false);
+                let mut when_true = left_true;
+                when_true.join(&right_true);
+                (when_true, both_false)
+            }
+        },
+        "identifier" => {
+            // A truthy value isn't None; a falsy one may still be 0 or "".
+            let mut when_true = env.clone();
+            rebind(&mut when_true, condition, source, |ty| ty.unwrap_optional());
+            (when_true, env.clone())
+        },
+        "comparison_operator" => comparison_check(condition, source, env),
+        "call" => isinstance_check(condition, source, env),
+        _ => (env.clone(), env.clone()),
+    }
+}
+
+/// Narrows `isinstance(x, C)` and `isinstance(x, (C, D))` to the named
+/// builtin types and classes when true. A class the environment doesn't
+/// know makes the type unknown. When `x` is already an instance of a
+/// class, a check for a single class narrows it to the intersection of
+/// the two, which is just the class checked for when it is the same.
+fn isinstance_check(condition: &Node, source: &[u8], env: &TypeEnv) -> (TypeEnv, TypeEnv) {
+    let unchanged = (env.clone(), env.clone());
+    let is_isinstance = condition
+        .child_by_field_name("function")
+        .is_some_and(|f| f.utf8_text(source).ok() == Some("isinstance"));
+    let arguments = condition.child_by_field_name("arguments");
+    let (true, Some(arguments)) = (is_isinstance, arguments) else {
+        return unchanged;
+    };
+    let (Some(variable), Some(classes), 2) =
+        (arguments.named_child(0), arguments.named_child(1), arguments.named_child_count())
+    else {
+        return unchanged;
+    };
+    if variable.kind() != "identifier" {
+        return unchanged;
+    }
+    let classes = match classes.kind() {
+        "tuple" => {
+            let mut cursor = classes.walk();
+            classes.named_children(&mut cursor).collect()
+        },
+        _ => vec![classes],
+    };
+    let types: Vec<Type> = classes
+        .iter()
+        .map(|class| instance_type(class, source, env))
+        .collect();
+    let ty = if types.contains(&Type::Unknown) { Type::Unknown } else { Type::union_of(types) };
+    let name = variable.utf8_text(source).unwrap_or_default();
+    let ty = match (env.lookup(name), ty) {
+        // An instance of one class that passes a check for another is an
+        // instance of a class deriving from both.
+        (Some(known @ (Type::Named(_) | Type::Intersection(_))), Type::Named(class)) => {
+            Type::intersection_of(vec![known.clone(), Type::Named(class)])
+        },
+        (_, ty) => ty,
+    };
+    let mut when_true = env.clone();
+    when_true.bind(name.to_string(), ty);
+    (when_true, env.clone())
+}
+
+/// The type of instances of a class expression in `isinstance`.
+pub(super) fn instance_type(class: &Node, source: &[u8], env: &TypeEnv) -> Type {
+    let name = class.utf8_text(source).unwrap_or_default();
+    match env.lookup(name) {
+        Some(Type::Named(qualified)) if qualified.rsplit('.').next() == Some(name) => {
+            return Type::Named(qualified.clone());
+        },
+        Some(_) => return Type::Unknown,
+        None => {},
+    }
+    match name {
+        "bool" => Type::Bool,
+        "int" => Type::Int,
+        "float" => Type::Float,
+        "str" => Type::Str,
+        "bytes" => Type::Bytes,
+        "list" => Type::List(Box::new(Type::Unknown)),
+        "dict" => Type::Dict(Box::new(Type::Unknown), Box::new(Type::Unknown)),
+        "set" => Type::Set(Box::new(Type::Unknown)),
+        _ => Type::Unknown,
+    }
+}
+
+/// Narrows `x is None`, `x is not None`, `x == None`, and `x != None`, and
+/// `x == c` and `x != c` for a constant `c` when `x` is a union that has
+/// the `Literal` type of `c` among its members, with the operands in
+/// either order.
+fn comparison_check(condition: &Node, source: &[u8], env: &TypeEnv) -> (TypeEnv, TypeEnv) {
+    let unchanged = (env.clone(), env.clone());
+    if condition.named_child_count() != 2 {
+        return unchanged;
+    }
+    let (Some(left), Some(right)) = (condition.named_child(0), condition.named_child(1)) else {
+        return unchanged;
+    };
+    let (variable, other) = match (left.kind(), right.kind()) {
+        ("identifier", _) => (left, right),
+        (_, "identifier") => (right, left),
+        _ => return unchanged,
+    };
+    let operator = std::str::from_utf8(&source[left.end_byte()..right.start_byte()])
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let is_equal = match operator.as_str() {
+        "is" | "==" => true,
+        "is not" | "!=" => false,
+        _ => return unchanged,
+    };
+
+    let mut equal = env.clone();
+    let mut unequal = env.clone();
+    if other.kind() == "none" {
+        rebind(&mut equal, &variable, source, |_| Type::None);
+        rebind(&mut unequal, &variable, source, |ty| ty.unwrap_optional());
+    } else if let Some(literal) = constant_type(&other, source).filter(|_| !operator.contains("is"))
+    {
+        rebind(&mut equal, &variable, source, |ty| match ty {
+            Type::Union(members) if members.contains(&literal) => literal.clone(),
+            ty => ty,
+        });
+        rebind(&mut unequal, &variable, source, |ty| match ty {
+            Type::Union(members) => {
+                Type::union_of(members.into_iter().filter(|m| *m != literal).collect())
+            },
+            ty => ty,
+        });
+    } else {
+        return unchanged;
+    }
+    if is_equal {
+        (equal, unequal)
+    } else {
+        (unequal, equal)
+    }
+}
+
+/// Rebinds a variable that is already bound, transforming its type.
+fn rebind(env: &mut TypeEnv, variable: &Node, source: &[u8], f: impl Fn(Type) -> Type) {
+    let name = variable.utf8_text(source).unwrap_or_default();
+    if let Some(ty) = env.lookup(name).cloned() {
+        env.bind(name.to_string(), f(ty));
+    }
+}
+
+/// Calls that end the process instead of returning.
+const EXIT_CALLS: &[&str] = &["sys.exit", "exit", "quit", "os._exit", "os.abort"];
+
+/// Whether a block never falls through to the statement after it, because
+/// one of its statements returns, raises, jumps out of the loop, calls
+/// `sys.exit()`, or is `assert False`, is an `if`, `with`, `try`, or
+/// `match` all of whose paths do, or is a `while True:` loop without a
+/// `break`.
+pub(super) fn always_exits(block: &Node, source: &[u8]) -> bool {
+    block_exits(block, source, true)
+}
+
+fn block_exits(block: &Node, source: &[u8], trust_with: bool) -> bool {
+    let mut cursor = block.walk();
+    let exits = block
+        .named_children(&mut cursor)
+        .any(|statement| statement_exits(&statement, source, trust_with));
+    exits
+}
+
+/// Whether a statement never falls through to the one after it; see
+/// [`always_exits`]. Unless `trust_with` is set, a `with` statement never
+/// counts as exiting, as its context manager may suppress an exception
+/// raised in the body.
+pub(super) fn statement_exits(statement: &Node, source: &[u8], trust_with: bool) -> bool {
+    let always_exits = |body: &Node| block_exits(body, source, trust_with);
+    let exits = |field| {
+        statement
+            .child_by_field_name(field)
+            .is_some_and(|body| always_exits(&body))
+    };
+    let mut cursor = statement.walk();
+    let clauses: Vec<Node> = statement.named_children(&mut cursor).collect();
+    // The block of an `except`, `else`, or `finally` clause is its last child.
+    let clause_exits = |clause: &Node| {
+        clause
+            .named_child((clause.named_child_count() as u32).saturating_sub(1))
+            .is_some_and(|body| body.kind() == "block" && always_exits(&body))
+    };
+    match statement.kind() {
+        "return_statement" | "raise_statement" | "continue_statement" | "break_statement" => true,
+        "expression_statement" => statement
+            .named_child(0)
+            .filter(|e| e.kind() == "call")
+            .and_then(|call| call.child_by_field_name("function")?.utf8_text(source).ok())
+            .is_some_and(|function| EXIT_CALLS.contains(&function)),
+        "if_statement" => {
+            let alternatives: Vec<&Node> = clauses
+                .iter()
+                .skip(1)
+                .filter(|c| matches!(c.kind(), "elif_clause" | "else_clause"))
+                .collect();
+            exits("consequence")
+                && alternatives.iter().any(|c| c.kind() == "else_clause")
+                && alternatives.iter().all(|c| {
+                    c.child_by_field_name("consequence")
+                        .or_else(|| c.child_by_field_name("body"))
+                        .is_some_and(|body| always_exits(&body))
+                })
+        },
+        "with_statement" => trust_with && exits("body"),
+        "assert_statement" => statement
+            .named_child(0)
+            .is_some_and(|c| c.kind() == "false"),
+        "while_statement" => {
+            statement.child_by_field_name("condition").is_some_and(|c| {
+                c.kind() == "true"
+                    || (c.kind() == "integer" && c.utf8_text(source).is_ok_and(|n| n != "0"))
+            }) && statement
+                .child_by_field_name("body")
+                .is_some_and(|body| !breaks(&body))
+        },
+        "match_statement" => {
+            let cases: Vec<Node> = clauses
+                .iter()
+                .filter(|c| c.kind() == "block")
+                .flat_map(|block| {
+                    let mut cursor = block.walk();
+                    block
+                        .named_children(&mut cursor)
+                        .filter(|c| c.kind() == "case_clause")
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            // Without a `case _:` no case may match.
+            let wildcard = cases.iter().any(|case| {
+                case.child_by_field_name("guard").is_none()
+                    && case.named_child(0).is_some_and(|p| {
+                        p.kind() == "case_pattern" && p.child(0).is_some_and(|w| w.kind() == "_")
+                    })
+            });
+            wildcard
+                && cases.iter().all(|case| {
+                    case.child_by_field_name("consequence")
+                        .is_some_and(|body| always_exits(&body))
+                })
+        },
+        "try_statement" => {
+            let finally = clauses
+                .iter()
+                .any(|c| c.kind() == "finally_clause" && clause_exits(c));
+            let handlers = clauses.iter().filter(|c| c.kind() == "except_clause");
+            let normal = exits("body")
+                || clauses
+                    .iter()
+                    .any(|c| c.kind() == "else_clause" && clause_exits(c));
+            finally || (normal && handlers.clone().all(clause_exits))
+        },
+        _ => false,
+    }
+}
+
+/// Whether a loop body contains a `break` out of that loop.
+fn breaks(body: &Node) -> bool {
+    let mut cursor = body.walk();
+    let children: Vec<Node> = body.named_children(&mut cursor).collect();
+    children.iter().any(|child| match child.kind() {
+        "break_statement" => true,
+        "function_definition" | "class_definition" => false,
+        // A nested loop's `break` ends that loop, but its `else` clause
+        // runs as part of this one.
+        "for_statement" | "while_statement" => {
+            let mut cursor = child.walk();
+            let breaks_out = child
+                .named_children(&mut cursor)
+                .any(|c| c.kind() == "else_clause" && breaks(&c));
+            breaks_out
+        },
+        _ => breaks(child),
+    })
+}
@@ -0,0 +1,227 @@
+//! Checks binary and comparison operators against the operands' methods.
+//!
+//! `a + b` calls `a.__add__(b)`, then `b.__radd__(a)` if `a` has no such
+//! method or it doesn't accept `b`; comparisons like `a < b` fall back to
+//! `b.__gt__(a)`. When an operand is an instance of a class defined in the
+//! file, its methods are looked up along its MRO, and an operator neither
+//! operand's method accepts the other for is reported. Builtin types only
+//! implement operators for builtins, except that sequences repeat by
+//! objects with `__index__` and `str % x` formats anything. `==` and `!=`
+//! work on any pair of objects. Classes with a decorator other than
+//! `dataclass`, or `dataclass(order=True)`, may gain methods and are not
+//! checked, and neither are operands of a union type.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tree_sitter::{Node, Tree};
+
+use super::attributes::variable_type;
+use super::mro::{member, mro};
+use super::{Analyzer, ClassInfo};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Each operator with the method it calls on its left operand and the
+/// reflected one it calls on its right operand.
+const OPERATORS: &[(&str, &str, &str)] = &[
+    ("+", "__add__", "__radd__"),
+    ("-", "__sub__", "__rsub__"),
+    ("*", "__mul__", "__rmul__"),
+    ("@", "__matmul__", "__rmatmul__"),
+    ("/", "__truediv__", "__rtruediv__"),
+    ("//", "__floordiv__", "__rfloordiv__"),
+    ("%", "__mod__", "__rmod__"),
+    ("**", "__pow__", "__rpow__"),
+    ("<<", "__lshift__", "__rlshift__"),
+    (">>", "__rshift__", "__rrshift__"),
+    ("&", "__and__", "__rand__"),
+    ("|", "__or__", "__ror__"),
+    ("^", "__xor__", "__rxor__"),
+    ("<", "__lt__", "__gt__"),
+    ("<=", "__le__", "__ge__"),
+    (">", "__gt__", "__lt__"),
+    (">=", "__ge__", "__le__"),
+];
+
+/// Checks every binary and comparison operator in a parsed Python file.
+/// `analyzer` must have analyzed the same tree.
+pub(super) fn check_operators(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let classes = analyzer.classes();
+    let mut diagnostics = Vec::new();
+    let mut bindings = HashMap::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if !matches!(node.kind(), "binary_operator" | "comparison_operator") {
+            continue;
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node
+            .children(&mut cursor)
+            .filter(|c| !c.is_extra())
+            .collect();
+        for window in children.windows(3) {
+            let [left, operator, right] = window else {
+                continue;
+            };
+            let Some((_, method, reflected)) =
+                OPERATORS.iter().find(|(op, ..)| *op == operator.kind())
+            else {
+                continue;
+            };
+            let Some((left_type, right_type)) = analyzer.operands_at(operator.start_byte()) else {
+                continue;
+            };
+            let mut operand = |node: &Node, recorded| match node.kind() {
+                "identifier" => variable_type(node, file, analyzer, &mut bindings),
+                _ => Some(recorded),
+            };
+            let (Some(left_type), Some(right_type)) =
+                (operand(left, left_type), operand(right, right_type))
+            else {
+                continue;
+            };
+            let (left_type, right_type) = (left_type.widened(), right_type.widened());
+            if supports(&left_type, method, &right_type, classes) == Some(false)
+                && supports(&right_type, reflected, &left_type, classes) == Some(false)
+            {
+                diagnostics.push(Diagnostic::from_message(
+                    Severity::Error,
+                    file.path(),
+                    file.span(left.start_byte(), right.end_byte()),
+                    MessageId::UnsupportedOperator,
+                    &[
+                        ("operator", operator.kind()),
+                        ("left", &left_type.to_string()),
+                        ("right", &right_type.to_string()),
+                    ],
+                ));
+            }
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// Whether `ty` has the operator method `method` and it accepts `other`,
+/// or `None` if that isn't known.
+fn supports(
+    ty: &Type,
+    method: &str,
+    other: &Type,
+    classes: &BTreeMap<String, ClassInfo>,
+) -> Option<bool> {
+    if matches!(other, Type::Union(_)) {
+        return None;
+    }
+    if ty.is_builtin() {
+        // Builtins only know builtins, and other pairs aren't checked.
+        let Type::Named(class) = other else {
+            return None;
+        };
+        let indexable = matches!(ty, Type::Str | Type::Bytes | Type::List(_) | Type::Tuple(_));
+        return Some(match method {
+            "__mul__" | "__rmul__" if indexable => member(class, "__index__", classes)?.is_some(),
+            "__mod__" => matches!(ty, Type::Str | Type::Bytes),
+            _ => false,
+        });
+    }
+    let Type::Named(class) = ty else {
+        return None;
+    };
+    let Some(found) = member(class, method, classes)? else {
+        return Some(false);
+    };
+    let accepted = match found {
+        Type::Function { params, .. } => params
+            .first()
+            .is_none_or(|param| instance_of(&param.ty, other, classes) != Some(false)),
+        _ => true,
+    };
+    Some(accepted)
+}
+
+/// Whether a value of type `value` can be passed as `expected`, knowing
+/// which classes the file's classes derive from.
+fn instance_of(
+    expected: &Type,
+    value: &Type,
+    classes: &BTreeMap<String, ClassInfo>,
+) -> Option<bool> {
+    match (expected, value) {
+        (Type::Union(members), value) => {
+            let results: Vec<Option<bool>> = members
+                .iter()
+                .map(|m| instance_of(m, value, classes))
+                .collect();
+            if results.contains(&Some(true)) {
+                Some(true)
+            } else if results.contains(&None) {
+                None
+            } else {
+                Some(false)
+            }
+        },
+        (Type::Named(expected), value) if classes.contains_key(expected) => match value {
+            Type::Named(value) => Some(mro(value, classes)?.contains(&expected.as_str())),
+            value if value.is_builtin() => mro(expected, classes).map(|_| false),
+            _ => None,
+        },
+        // A class whose bases are all in the file isn't a builtin.
+        (expected, Type::Named(value)) if expected.is_builtin() => {
+            mro(value, classes).map(|_| false)
+        },
+        _ => expected.accepts(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::check_messages;
+
+    fn check(source: &str) -> Vec<String> {
+        check_messages(source, check_operators)
+    }
+
+    #[test]
+    fn test_operator_methods() {
+        let source = "class Vec:\n    def __add__(self, other: Vec) -> Vec:\n        \
+                      return self\n\n    def __mul__(self, k: float) -> Vec:\n        \
+                      return self\n\n    def __lt__(self, other):\n        return True\n\n\
+                      class Vec3(Vec):\n    def __radd__(self, other: int) -> Vec3:\n        \
+                      return self\n\nclass Token:\n    pass\n\nclass Index:\n    \
+                      def __index__(self):\n        return 0\n\nv = Vec()\nw = Vec3()\n\
+                      v + w\nv + 3\n3 + w\nw + 3\nv * 2\nv * 'a'\nv < Token()\nToken() < v\n\
+                      Token() + 1\n1 < Token() <= 2\n[1] * Index()\n'%s' % Token()\nv == 3\n\
+                      Vec + 1\n";
+        assert_eq!(
+            check(source),
+            [
+                "24: Operator '+' is not supported between Vec and int",
+                "26: Operator '+' is not supported between Vec3 and int",
+                "28: Operator '*' is not supported between Vec and str",
+                "30: Operator '<' is not supported between Token and Vec",
+                "31: Operator '+' is not supported between Token and int",
+                "32: Operator '<' is not supported between int and Token",
+                "32: Operator '<=' is not supported between Token and int",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_operands() {
+        let source = "@dataclass(order=True)\nclass Version:\n    major: int\n\n\
+                      @total_ordering\nclass Key:\n    pass\n\nclass Meta(Base):\n    pass\n\n\
+                      class Plain:\n    def compare(self, other):\n        \
+                      return self < other\n\nv = Version(1)\nv < Version(2)\nKey() < 1\n\
+                      Meta() + 1\nx = Plain() if flag else 1\nx + 1\n";
+        assert!(check(source).is_empty());
+    }
+}
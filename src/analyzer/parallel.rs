@@ -0,0 +1,160 @@
+//! Analyzing many files at once on a pool of worker threads.
+//!
+//! Each file's analysis is independent of the others', so
+//! [`ParallelAnalysis`] hands files to a rayon thread pool as they arrive,
+//! for example from [`SourceWalker::files_parallel`] while the walk is still
+//! running, and workers record their outcomes in a shared
+//! [`ResultCollector`]. Results are sorted by path once every file is done,
+//! so the output doesn't depend on which thread finished first.
+//!
+//! [`SourceWalker::files_parallel`]: crate::utils::SourceWalker::files_parallel
+
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use rayon::ThreadPoolBuilder;
+
+use super::{AnalysisResult, Analyzer, StubPaths};
+use crate::error::{Partial, Result};
+
+/// Collects the outcomes of files analyzed on several threads.
+#[derive(Debug, Default)]
+pub struct ResultCollector {
+    outcomes: Mutex<Vec<(PathBuf, Result<AnalysisResult>)>>,
+}
+
+impl ResultCollector {
+    /// Creates an empty collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the outcome of analyzing `path`. An error that names no
+    /// file is attributed to `path`.
+    pub fn record(&self, path: PathBuf, result: Result<AnalysisResult>) {
+        let result = result.map_err(|e| match e.path() {
+            Some(_) => e,
+            None => e.with_path(&path),
+        });
+        self.outcomes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((path, result));
+    }
+
+    /// Every recorded outcome, sorted by path.
+    pub fn into_outcomes(self) -> Vec<(PathBuf, Result<AnalysisResult>)> {
+        let mut outcomes = self
+            .outcomes
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+        outcomes
+    }
+
+    /// The results of the files analyzed successfully, sorted by path, and
+    /// the errors of the others.
+    pub fn finish(self) -> Partial<Vec<AnalysisResult>> {
+        let mut batch = Partial::new(Vec::new());
+        for (_, result) in self.into_outcomes() {
+            if let Some(result) = batch.record(result) {
+                batch.value.push(result);
+            }
+        }
+        batch
+    }
+}
+
+/// Analyzes files on a pool of worker threads.
+#[derive(Debug, Clone, Default)]
+pub struct ParallelAnalysis {
+    stubs: StubPaths,
+    threads: usize,
+}
+
+impl ParallelAnalysis {
+    /// Analyzes files looking up stubs in `stubs`, with one worker thread
+    /// per CPU core.
+    pub fn new(stubs: StubPaths) -> Self {
+        Self { stubs, threads: 0 }
+    }
+
+    /// Where the stubs of analyzed modules are looked up.
+    pub fn stubs(&self) -> &StubPaths {
+        &self.stubs
+    }
+
+    /// Sets the number of worker threads; 0 picks one per CPU core.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Analyzes each file like [`Analyzer::analyze_files_with_stubs`],
+    /// several at a time, and returns the results sorted by path.
+    pub fn analyze<I>(&self, paths: I) -> Partial<Vec<AnalysisResult>>
+    where
+        I: IntoIterator<Item = PathBuf>,
+        I::IntoIter: Send,
+    {
+        let collector = ResultCollector::new();
+        self.run(paths, &collector);
+        collector.finish()
+    }
+
+    /// Analyzes each file, recording its outcome in `collector` as soon
+    /// as it is known. Files are analyzed on the current thread if no
+    /// worker threads can be started, as in a browser.
+    pub fn run<I>(&self, paths: I, collector: &ResultCollector)
+    where
+        I: IntoIterator<Item = PathBuf>,
+        I::IntoIter: Send,
+    {
+        let paths = paths.into_iter();
+        let analyze = |path: PathBuf| {
+            let result = Analyzer::analyze_file_with_stubs(&path, &self.stubs);
+            collector.record(path, result);
+        };
+        match ThreadPoolBuilder::new().num_threads(self.threads).build() {
+            Ok(pool) => pool.install(|| paths.par_bridge().for_each(analyze)),
+            Err(e) => {
+                log::debug!("Analyzing files on one thread: {}", e);
+                paths.for_each(analyze);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..40 {
+            let path = dir.path().join(format!("m{:02}.py", i));
+            let source = format!("def f{}(x):\n    return x\n", i);
+            fs::write(&path, source).unwrap();
+            paths.push(path);
+        }
+        paths.push(dir.path().join("missing.py"));
+
+        let sequential = Analyzer::analyze_files(paths.clone());
+        let parallel = ParallelAnalysis::new(StubPaths::default())
+            .threads(4)
+            .analyze(paths.clone());
+        assert_eq!(parallel.errors.len(), 1);
+        assert_eq!(parallel.errors[0].path(), Some(paths[40].as_path()));
+        let summary = |results: &[AnalysisResult]| -> Vec<(String, usize)> {
+            results
+                .iter()
+                .map(|r| (r.path.clone(), r.diagnostics.len()))
+                .collect()
+        };
+        assert_eq!(summary(&parallel.value), summary(&sequential.value));
+    }
+}
@@ -0,0 +1,158 @@
+//! Checks the values functions return against their return annotations.
+//!
+//! Each `return` in a function with a return annotation must return a
+//! value the annotation accepts (see [`Type::accepts`]), and a function
+//! whose body can end without returning returns `None` implicitly.
+//! Generators, whose annotation describes what they yield, and stub
+//! bodies of only a docstring, `pass`, or `...` are not checked.
+
+use tree_sitter::{Node, Tree};
+
+use super::narrow::always_exits;
+use super::{annotation_type, rejects, signature_span, Analyzer};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Checks every annotated function in a parsed Python file. `analyzer`
+/// must have analyzed the same tree.
+pub(super) fn check_returns(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let source = file.text().as_bytes();
+    let mut diagnostics = Vec::new();
+    let classes = analyzer.class_hierarchy();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() != "function_definition" {
+            continue;
+        }
+        let (Some(name), Some(annotation), Some(body)) = (
+            node.child_by_field_name("name"),
+            node.child_by_field_name("return_type"),
+            node.child_by_field_name("body"),
+        ) else {
+            continue;
+        };
+        let Some(returns) = return_statements(&body) else {
+            continue;
+        };
+        let expected = analyzer.expand_aliases(&annotation_type(&annotation, source));
+        let mut report = |span, found: &Type| {
+            diagnostics.push(Diagnostic::from_message(
+                Severity::Error,
+                file.path(),
+                span,
+                MessageId::ReturnType,
+                &[
+                    ("function", text(file, &name)),
+                    ("expected", text(file, &annotation)),
+                    ("found", &found.to_string()),
+                ],
+            ));
+        };
+        for statement in returns {
+            let Some(found) = analyzer.returned_at(statement.start_byte()) else {
+                continue;
+            };
+            if rejects(&expected, &analyzer.expand_aliases(found), &classes) {
+                let value = statement.named_child(0).unwrap_or(statement);
+                report(file.node_span(&value), found);
+            }
+        }
+        if !always_exits(&body, source)
+            && !is_stub(&body)
+            && expected.accepts(&Type::None) == Some(false)
+        {
+            report(signature_span(file, &node), &Type::None);
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// The `return` statements of a function body, outside nested scopes, or
+/// `None` if the function is a generator.
+fn return_statements<'t>(body: &Node<'t>) -> Option<Vec<Node<'t>>> {
+    let mut returns = Vec::new();
+    let mut stack = vec![*body];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "function_definition" | "class_definition" | "lambda" => continue,
+            "yield" => return None,
+            "return_statement" => returns.push(node),
+            _ => {},
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    Some(returns)
+}
+
+/// Whether a body only holds a docstring, `pass`, or `...`, as in
+/// protocols, overloads, and abstract methods.
+fn is_stub(body: &Node) -> bool {
+    let mut cursor = body.walk();
+    let stub = body
+        .named_children(&mut cursor)
+        .all(|statement| match statement.kind() {
+            "pass_statement" | "comment" => true,
+            "expression_statement" => statement
+                .named_child(0)
+                .is_some_and(|e| matches!(e.kind(), "string" | "ellipsis")),
+            _ => false,
+        });
+    stub
+}
+
+fn text<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    file.slice(&file.node_span(node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::check_messages;
+
+    fn check(source: &str) -> Vec<String> {
+        check_messages(source, check_returns)
+    }
+
+    #[test]
+    fn test_return_type_mismatches() {
+        let source = "def name(flag: bool) -> str:\n    if flag:\n        return 1\n    \
+                      label = 'a'\n    return label\n\ndef size(x) -> Optional[int]:\n    \
+                      if x:\n        return None\n    return len(x)\n\n\
+                      def ratio(n: int) -> float:\n    return n\n\ndef find(xs) -> int:\n    \
+                      for x in xs:\n        return x\n\nasync def fetch() -> bytes:\n    \
+                      return 'text'\n\ndef pick(x: int | str) -> str:\n    \
+                      if isinstance(x, int):\n        x = str(x)\n    return x\n\n\
+                      def split(x: int | str) -> tuple[str, list[str]]:\n    \
+                      if isinstance(x, int):\n        x = str(x)\n    return (x, [x])\n";
+        assert_eq!(
+            check(source),
+            [
+                "2: 'name' returns int, but is annotated to return str",
+                "14: 'find' returns None, but is annotated to return int",
+                "19: 'fetch' returns str, but is annotated to return bytes",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exits_generators_and_stubs() {
+        let source = "def run() -> int:\n    while True:\n        pass\n\n\
+                      def pick(x) -> int:\n    match x:\n        case 1:\n            return 1\n        \
+                      case _:\n            raise ValueError(x)\n\n\
+                      def count() -> Iterator[int]:\n    yield 1\n\n\
+                      def area(self) -> float:\n    \"\"\"The area.\"\"\"\n\n\
+                      def done() -> None:\n    return\n\ndef stop() -> int:\n    \
+                      sys.exit(1)\n    \"\"\"Unreachable.\"\"\"\n\ndef ask() -> str:\n    \
+                      while 1:\n        pass\n\ndef never() -> int:\n    assert False\n";
+        assert!(check(source).is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+//! Custom lints declared as tree-sitter query files.
+//!
+//! Each `.scm` file in a project's `rules/` directory is one rule. Comment
+//! lines at the top set its properties, and every match of the query is
+//! reported as a diagnostic:
+//!
+//! ```scheme
+//! ; message: Use logging instead of {fn}()
+//! ; severity: warning
+//! ; code: no-print
+//! ((call function: (identifier) @fn) @match
+//!  (#eq? @fn "print"))
+//! ```
+//!
+//! `message` is required. `{name}` in the message is replaced by the text
+//! of the `@name` capture. `severity` defaults to `warning`, `code` to the
+//! file name without its extension, and `language` to `python`. The
+//! diagnostic covers the `@match` capture, or the first capture if the query
+//! has no `@match`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tree_sitter::Tree;
+
+use crate::config::Config;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::{Error, Result};
+use crate::parser::{Language, Parser, Query, QueryMatch};
+use crate::utils::SourceFile;
+
+/// Directory, next to the project configuration, that rule files are loaded from.
+pub const RULES_DIR: &str = "rules";
+
+/// A lint compiled from a query file.
+#[derive(Debug)]
+pub struct Rule {
+    code: String,
+    message: String,
+    severity: Severity,
+    query: Query,
+}
+
+impl Rule {
+    /// Compiles a rule from the contents of a query file. `name` is the
+    /// default rule code.
+    pub fn parse(name: &str, source: &str) -> Result<Self> {
+        let mut code = name.to_string();
+        let mut message = None;
+        let mut severity = Severity::Warning;
+        let mut language = Language::Python;
+        for line in source.lines().map(str::trim) {
+            let Some(comment) = line.strip_prefix(';') else {
+                if line.is_empty() {
+                    continue;
+                }
+                break;
+            };
+            let Some((key, value)) = comment.trim_start_matches(';').split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "message" => message = Some(value.to_string()),
+                "severity" => severity = value.parse()?,
+                "code" => code = value.to_string(),
+                "language" => {
+                    language = Language::ALL
+                        .into_iter()
+                        .find(|l| l.to_string() == value.to_ascii_lowercase())
+                        .ok_or_else(|| {
+                            Error::invalid_value("language", value, "unknown language")
+                        })?;
+                },
+                _ => {},
+            }
+        }
+        let message = message.ok_or_else(|| {
+            Error::parser_error(format!("Rule '{}' has no '; message:' header", name))
+        })?;
+        Ok(Self { code, message, severity, query: Query::new(language, source)? })
+    }
+
+    /// Loads a rule from a `.scm` file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let source = fs::read_to_string(path)?;
+        Self::parse(&name, &source).map_err(|e| e.with_path(path))
+    }
+
+    /// The rule code reported on diagnostics.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The language the query was written for.
+    pub fn language(&self) -> Language {
+        self.query.language()
+    }
+
+    /// Reports every match of the rule in a parsed file.
+    pub fn check(&self, tree: &Tree, file: &SourceFile) -> Vec<Diagnostic> {
+        self.query
+            .matches(tree, file)
+            .iter()
+            .filter_map(|m| {
+                let target = m.capture("match").or_else(|| m.captures.first())?;
+                let message = self.render(m);
+                Some(
+                    Diagnostic::new(self.severity, file.path(), target.span, message)
+                        .with_code(&self.code),
+                )
+            })
+            .collect()
+    }
+
+    /// Fills `{name}` placeholders with capture text.
+    fn render(&self, m: &QueryMatch) -> String {
+        let mut out = String::new();
+        let mut rest = self.message.as_str();
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+                break;
+            };
+            out.push_str(&rest[..open]);
+            match m.capture(&rest[open + 1..close]) {
+                Some(capture) => out.push_str(&capture.text),
+                None => out.push_str(&rest[open..=close]),
+            }
+            rest = &rest[close + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// The custom rules that apply to a project.
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Compiles every `.scm` file in a directory, in file name order.
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.retain(|p| p.extension().is_some_and(|ext| ext == "scm"));
+        paths.sort();
+        let rules = paths.iter().map(|p| Rule::load(p)).collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Loads the rules for the project containing `start`: the `rules/`
+    /// directory beside its configuration file or, without one, in `start`
+    /// itself. Returns an empty set if there is no such directory.
+    pub fn discover(start: impl AsRef<Path>) -> Result<Self> {
+        let start = start.as_ref();
+        let root = match Config::find(start)? {
+            Some(config) => config.parent().map(Path::to_path_buf),
+            None if start.is_file() => start.parent().map(Path::to_path_buf),
+            None => Some(start.to_path_buf()),
+        };
+        match root.map(|root| root.join(RULES_DIR)) {
+            Some(dir) if dir.is_dir() => Self::load_dir(&dir),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Returns true if there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The loaded rules.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Runs the rules written for `language` over a parsed file.
+    pub fn check(&self, tree: &Tree, file: &SourceFile, language: Language) -> Vec<Diagnostic> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.language() == language)
+            .flat_map(|rule| rule.check(tree, file))
+            .collect()
+    }
+
+    /// Parses loaded source and runs the rules that apply to its language.
+    pub fn check_source(&self, file: &SourceFile, language: Language) -> Result<Vec<Diagnostic>> {
+        if !self.rules.iter().any(|rule| rule.language() == language) {
+            return Ok(Vec::new());
+        }
+        let tree = Parser::for_language(language)?.parse_string(file.text())?;
+        Ok(self.check(&tree, file, language))
+    }
+
+    /// Reads, parses, and checks a file.
+    pub fn check_file(&self, path: &Path) -> Result<Vec<Diagnostic>> {
+        match Language::from_path(path) {
+            Some(language) if !self.is_empty() => {
+                self.check_source(&SourceFile::read(path)?, language)
+            },
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_reports_matches() {
+        let rule = Rule::parse(
+            "no-print",
+            "; message: Use logging instead of {fn}() {x}\n; severity: error\n\n\
+             ((call function: (identifier) @fn) @match (#eq? @fn \"print\"))\n",
+        )
+        .unwrap();
+        let source = "print('a')\nlog('b')\n";
+        let file = SourceFile::from_text("a.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+
+        let diagnostics = rule.check(&tree, &file);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "a.py:1:1: error Use logging instead of print() {x} [no-print]"
+        );
+        assert_eq!(diagnostics[0].code.as_deref(), Some("no-print"));
+        assert_eq!(diagnostics[0].span.end.column, 10);
+    }
+
+    #[test]
+    fn test_discover_rules_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(crate::config::CONFIG_FILE), "").unwrap();
+        let rules = dir.path().join(RULES_DIR);
+        fs::create_dir(&rules).unwrap();
+        fs::write(rules.join("bare.scm"), "; message: Bare except\n(except_clause) @match\n")
+            .unwrap();
+        fs::write(rules.join("notes.txt"), "ignored").unwrap();
+        let sub = dir.path().join("pkg");
+        fs::create_dir(&sub).unwrap();
+
+        let set = RuleSet::discover(&sub).unwrap();
+        assert_eq!(set.rules().len(), 1);
+        assert_eq!(set.rules()[0].code(), "bare");
+
+        fs::write(rules.join("broken.scm"), "; message: x\n(call").unwrap();
+        assert!(RuleSet::discover(&sub).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Lightweight analysis for the JavaScript family (JavaScript, TypeScript, TSX).
+//!
+//! JavaScript has no inline annotations, so functions are expected to carry a
+//! JSDoc block (`/** ... */`) instead. TypeScript files are checked for missing
+//! parameter and return type annotations, mirroring the Python checks.
+
+use std::path::Path;
+use tree_sitter::Node;
+
+use super::{node_name, signature_span, suppress, AnalysisResult};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::error::Result;
+use crate::parser::{extract_comments, Language, Parser};
+use crate::utils::SourceFile;
+
+/// Node kinds that introduce a named function or method.
+const FUNCTION_KINDS: &[&str] = &[
+    "function_declaration",
+    "generator_function_declaration",
+    "method_definition",
+    "function",
+    "function_expression",
+    "arrow_function",
+];
+
+/// Node kinds that introduce a class.
+const CLASS_KINDS: &[&str] = &["class_declaration", "class", "abstract_class_declaration"];
+
+/// Analyzes a JavaScript or TypeScript source file.
+pub(super) fn analyze_script_file(path: &Path, language: Language) -> Result<AnalysisResult> {
+    analyze_script_source(&SourceFile::read(path)?, language)
+}
+
+/// Analyzes loaded JavaScript or TypeScript source.
+pub(super) fn analyze_script_source(
+    file: &SourceFile,
+    language: Language,
+) -> Result<AnalysisResult> {
+    let tree = Parser::for_language(language)?.parse_string(file.text())?;
+
+    let mut function_count = 0usize;
+    let mut class_count = 0usize;
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    let mut stack: Vec<Node> = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let kind = node.kind();
+        // Keyword tokens such as `function` and `class` share their names with
+        // expression node kinds, so only named nodes are counted.
+        let named = node.is_named();
+        if named && FUNCTION_KINDS.contains(&kind) {
+            function_count += 1;
+            if is_declaration(&node) {
+                if language.has_type_annotations() {
+                    check_annotations(&node, file, &mut diagnostics);
+                } else if !has_jsdoc(&node, file.text().as_bytes()) {
+                    diagnostics.push(Diagnostic::from_message(
+                        Severity::Warning,
+                        file.path(),
+                        signature_span(file, &node),
+                        MessageId::MissingJsdoc,
+                        &[("name", node_name(file, &node))],
+                    ));
+                }
+            }
+        } else if named && CLASS_KINDS.contains(&kind) {
+            class_count += 1;
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            stack.push(child);
+        }
+    }
+
+    suppress(&mut diagnostics, &extract_comments(&tree, file));
+    Ok(AnalysisResult {
+        path: file.path().to_string(),
+        function_count,
+        class_count,
+        diagnostics,
+        inferred_returns: Default::default(),
+        inferred_parameters: Default::default(),
+        unsolved: Default::default(),
+        classes: Default::default(),
+        metrics: Default::default(),
+    })
+}
+
+/// Returns true for functions that form part of a module's API surface:
+/// declarations and methods, as opposed to inline callbacks.
+fn is_declaration(node: &Node) -> bool {
+    matches!(
+        node.kind(),
+        "function_declaration" | "generator_function_declaration" | "method_definition"
+    )
+}
+
+/// Reports TypeScript parameters and returns that lack a type annotation.
+fn check_annotations(node: &Node, file: &SourceFile, diagnostics: &mut Vec<Diagnostic>) {
+    if let Some(params) = node.child_by_field_name("parameters") {
+        let mut cursor = params.walk();
+        for p in params.named_children(&mut cursor) {
+            let is_param = matches!(p.kind(), "required_parameter" | "optional_parameter");
+            if is_param && p.child_by_field_name("type").is_none() {
+                let name = p.child_by_field_name("pattern").unwrap_or(p);
+                diagnostics.push(Diagnostic::from_message(
+                    Severity::Warning,
+                    file.path(),
+                    file.node_span(&p),
+                    MessageId::UnannotatedParameter,
+                    &[("name", file.slice(&file.node_span(&name)))],
+                ));
+            }
+        }
+    }
+
+    // Constructors and setters never declare a return type.
+    let name = node_name(file, node);
+    let is_setter = node.child(0).is_some_and(|c| c.kind() == "set");
+    if name != "constructor" && !is_setter && node.child_by_field_name("return_type").is_none() {
+        diagnostics.push(Diagnostic::from_message(
+            Severity::Warning,
+            file.path(),
+            signature_span(file, node),
+            MessageId::UnannotatedReturn,
+            &[("name", name)],
+        ));
+    }
+}
+
+/// Returns true if a `/** ... */` comment immediately precedes the function,
+/// looking through `export` wrappers.
+fn has_jsdoc(node: &Node, source: &[u8]) -> bool {
+    let mut target = *node;
+    if let Some(parent) = node.parent() {
+        if parent.kind() == "export_statement" {
+            target = parent;
+        }
+    }
+    target
+        .prev_sibling()
+        .filter(|prev| prev.kind() == "comment")
+        .and_then(|prev| prev.utf8_text(source).ok())
+        .is_some_and(|text| text.starts_with("/**"))
+}
+
+#[cfg(all(test, feature = "javascript"))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_javascript_missing_jsdoc() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("app.js");
+        fs::write(
+            &file,
+            "/** Adds numbers. */\nfunction add(a, b) { return a + b; }\nfunction sub(a, b) { \
+             return a - b; }\n",
+        )
+        .unwrap();
+
+        let result = analyze_script_file(&file, Language::JavaScript).unwrap();
+        assert_eq!(result.function_count, 2);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].span.start.line, 2);
+    }
+
+    #[test]
+    fn test_typescript_missing_annotations() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("app.ts");
+        fs::write(&file, "export function add(a: number, b): number { return a + b; }\n").unwrap();
+
+        let result = analyze_script_file(&file, Language::TypeScript).unwrap();
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].message_id, Some(MessageId::UnannotatedParameter));
+        assert_eq!(result.diagnostics[0].args["name"], "b");
+    }
+}
@@ -0,0 +1,233 @@
+//! Detects names that shadow builtins.
+//!
+//! A parameter, variable, or import named like a builtin function or type,
+//! such as `list`, `id`, or `type`, hides the builtin for the rest of its
+//! scope, so a later `list(...)` calls whatever the name was bound to. Each
+//! name is reported once per scope, at its first binding. Class attributes
+//! are left alone, since they don't hide builtins from the class's methods,
+//! and so are functions and classes, which usually shadow a builtin on
+//! purpose, and parameters like `len=len` that bind the builtin itself.
+
+use std::collections::HashSet;
+
+use tree_sitter::{Node, Tree};
+
+use super::signatures::{self, parameter_name};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::utils::SourceFile;
+
+/// The builtin functions and types. Exceptions are left out, since modules
+/// routinely export their own `TimeoutError` or `ConnectionError`.
+const BUILTINS: &[&str] = &[
+    "abs",
+    "aiter",
+    "all",
+    "anext",
+    "any",
+    "ascii",
+    "bin",
+    "bool",
+    "breakpoint",
+    "bytearray",
+    "bytes",
+    "callable",
+    "chr",
+    "classmethod",
+    "compile",
+    "complex",
+    "delattr",
+    "dict",
+    "dir",
+    "divmod",
+    "enumerate",
+    "eval",
+    "exec",
+    "filter",
+    "float",
+    "format",
+    "frozenset",
+    "getattr",
+    "globals",
+    "hasattr",
+    "hash",
+    "hex",
+    "id",
+    "input",
+    "int",
+    "isinstance",
+    "issubclass",
+    "iter",
+    "len",
+    "list",
+    "locals",
+    "map",
+    "max",
+    "memoryview",
+    "min",
+    "next",
+    "object",
+    "oct",
+    "open",
+    "ord",
+    "pow",
+    "print",
+    "property",
+    "range",
+    "repr",
+    "reversed",
+    "round",
+    "set",
+    "setattr",
+    "slice",
+    "sorted",
+    "staticmethod",
+    "str",
+    "sum",
+    "super",
+    "tuple",
+    "type",
+    "vars",
+    "zip",
+];
+
+/// Reports the bindings in a parsed Python file that shadow a builtin.
+pub(super) fn check_shadowing(tree: &Tree, file: &SourceFile) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    visit_scope(&tree.root_node(), file, false, &mut diagnostics);
+    diagnostics
+}
+
+/// Checks the bindings of a module, class, function, or lambda `scope`,
+/// and the scopes nested in it.
+fn visit_scope(scope: &Node, file: &SourceFile, is_class: bool, out: &mut Vec<Diagnostic>) {
+    let mut reported = HashSet::new();
+    let mut report = |name: Node| {
+        let text = file.slice(&file.node_span(&name));
+        if BUILTINS.contains(&text) && reported.insert(text.to_string()) {
+            out.push(Diagnostic::from_message(
+                Severity::Warning,
+                file.path(),
+                file.node_span(&name),
+                MessageId::ShadowedBuiltin,
+                &[("name", text)],
+            ));
+        }
+    };
+    if let Some(params) = scope.child_by_field_name("parameters") {
+        for (param, _) in signatures::parameters(&params) {
+            let Some(name) = parameter_name(&param) else {
+                continue;
+            };
+            // `len=len` binds the builtin itself, to look it up faster.
+            let default = param.child_by_field_name("value");
+            if default.is_some_and(|d| {
+                file.slice(&file.node_span(&d)) == file.slice(&file.node_span(&name))
+            }) {
+                continue;
+            }
+            report(name);
+        }
+    }
+    let body = match scope.kind() {
+        "module" => Some(*scope),
+        _ => scope.child_by_field_name("body"),
+    };
+    let mut nested = Vec::new();
+    let mut stack: Vec<Node> = body.into_iter().collect();
+    while let Some(node) = stack.pop() {
+        let targets = match node.kind() {
+            "function_definition" | "lambda" | "class_definition" => {
+                nested.push(node);
+                continue;
+            },
+            "assignment" | "for_statement" | "for_in_clause" if !is_class => {
+                node.child_by_field_name("left")
+            },
+            "named_expression" => node.child_by_field_name("name"),
+            "as_pattern" if !is_class => node
+                .child_by_field_name("alias")
+                .and_then(|alias| alias.named_child(0)),
+            "import_statement" | "import_from_statement" if !is_class => {
+                imported_names(&node).into_iter().for_each(&mut report);
+                continue;
+            },
+            _ => None,
+        };
+        if let Some(targets) = targets {
+            target_names(&targets, &mut report);
+        }
+        // Children are pushed in reverse so bindings are seen in source order.
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    for scope in nested {
+        visit_scope(&scope, file, scope.kind() == "class_definition", out);
+    }
+}
+
+/// Calls `f` with each identifier an assignment or loop target binds.
+fn target_names<'t>(target: &Node<'t>, f: &mut impl FnMut(Node<'t>)) {
+    match target.kind() {
+        "identifier" => f(*target),
+        "pattern_list" | "tuple_pattern" | "list_pattern" | "list_splat_pattern" => {
+            let mut cursor = target.walk();
+            let elements: Vec<Node> = target.named_children(&mut cursor).collect();
+            for element in &elements {
+                target_names(element, f);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// The identifiers an import binds: the alias of `as` imports, the first
+/// component of `import a.b`, and each name of `from m import a`.
+fn imported_names<'t>(import: &Node<'t>) -> Vec<Node<'t>> {
+    let mut cursor = import.walk();
+    import
+        .children_by_field_name("name", &mut cursor)
+        .filter_map(|name| match name.kind() {
+            "aliased_import" => name.child_by_field_name("alias"),
+            _ => name.named_child(0),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn shadowed(source: &str) -> Vec<(usize, String)> {
+        let file = SourceFile::from_text("a.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        check_shadowing(&tree, &file)
+            .iter()
+            .map(|d| (d.span.start.line, d.args["name"].clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_shadowed_builtins() {
+        let source = "from os import open\nimport json as type\nimport re\n\
+                      list = [1]\nlist = [2]\n\
+                      def f(id, x: str = '', *input, len=len):\n    for sum, n in x:\n        pass\n\
+                      \x20   return [max for max in x]\n\
+                      class C:\n    id = 1\n    def all(self):\n        return (len := 1)\n\
+                      def print():\n    pass\n";
+        let mut found = shadowed(source);
+        found.sort();
+        let expected = [
+            (0, "open"),
+            (1, "type"),
+            (3, "list"),
+            (5, "id"),
+            (5, "input"),
+            (6, "sum"),
+            (8, "max"),
+            (12, "len"),
+        ];
+        assert_eq!(found, expected.map(|(line, name)| (line, name.to_string())));
+    }
+}
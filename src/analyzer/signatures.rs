@@ -0,0 +1,473 @@
+//! Inferred function signatures and snapshots of them.
+//!
+//! A signature uses each parameter's annotation, or else the type of its
+//! default value, and the declared return type, or else the union of the
+//! types of the function's `return` expressions. A [`TypeSnapshot`] records
+//! the signatures of a whole project so later runs can detect drift.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+use super::expression_type;
+use crate::error::{Error, Result};
+use crate::parser::Parser;
+pub use crate::types::ParameterKind;
+use crate::types::Type;
+use crate::utils::SourceFile;
+
+/// Format version written to snapshot files.
+pub const TYPE_SNAPSHOT_VERSION: u32 = 1;
+
+/// One parameter of an inferred signature.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InferredParameter {
+    /// Name as written, without the `*` or `**` of variadic parameters.
+    pub name: String,
+    /// How arguments are passed to the parameter.
+    pub kind: ParameterKind,
+    /// Declared or inferred type, if any. The type of each argument a
+    /// variadic parameter collects rather than of the collection.
+    pub ty: Option<String>,
+}
+
+/// A function's signature as far as it is declared or can be inferred.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InferredSignature {
+    /// Dotted name including enclosing classes and functions.
+    pub name: String,
+    /// Parameters in order.
+    pub parameters: Vec<InferredParameter>,
+    /// Declared or inferred return type, if any.
+    pub returns: Option<String>,
+}
+
+impl fmt::Display for InferredSignature {
+    /// Formats as Python writes the parameter list, with the `/` and `*`
+    /// separators where the parameters' kinds change.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parameters: Vec<String> = Vec::new();
+        let mut previous = None;
+        for p in &self.parameters {
+            if previous == Some(ParameterKind::PositionalOnly)
+                && p.kind != ParameterKind::PositionalOnly
+            {
+                parameters.push("/".to_string());
+            }
+            if p.kind == ParameterKind::KeywordOnly
+                && !matches!(
+                    previous,
+                    Some(ParameterKind::VarPositional | ParameterKind::KeywordOnly)
+                )
+            {
+                parameters.push("*".to_string());
+            }
+            let stars = match p.kind {
+                ParameterKind::VarPositional => "*",
+                ParameterKind::VarKeyword => "**",
+                _ => "",
+            };
+            parameters.push(match &p.ty {
+                Some(ty) => format!("{}{}: {}", stars, p.name, ty),
+                None => format!("{}{}", stars, p.name),
+            });
+            previous = Some(p.kind);
+        }
+        if previous == Some(ParameterKind::PositionalOnly) {
+            parameters.push("/".to_string());
+        }
+        write!(f, "({})", parameters.join(", "))?;
+        if let Some(returns) = &self.returns {
+            write!(f, " -> {}", returns)?;
+        }
+        Ok(())
+    }
+}
+
+/// Infers the signature of every function and method in a Python file, in
+/// source order.
+pub fn infer_signatures(file: &SourceFile) -> Result<Vec<InferredSignature>> {
+    let tree = Parser::new()?.parse_string(file.text())?;
+    let mut out = Vec::new();
+    for_each_function(&tree.root_node(), file, &mut |node, name| {
+        out.push(signature(&node, file, name))
+    });
+    Ok(out)
+}
+
+/// Calls `f` with every function definition under `node`, in source order,
+/// and its dotted name, which includes the enclosing classes and functions.
+pub(super) fn for_each_function<'t>(
+    node: &Node<'t>,
+    file: &SourceFile,
+    f: &mut impl FnMut(Node<'t>, String),
+) {
+    visit(node, file, "", f);
+}
+
+fn visit<'t>(
+    node: &Node<'t>,
+    file: &SourceFile,
+    prefix: &str,
+    f: &mut impl FnMut(Node<'t>, String),
+) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let name = child
+            .child_by_field_name("name")
+            .map(|n| text(file, &n).to_string());
+        let qualified = |name: &str| {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", prefix, name)
+            }
+        };
+        match (child.kind(), name) {
+            ("function_definition", Some(name)) => {
+                let name = qualified(&name);
+                f(child, name.clone());
+                visit(&child, file, &name, f);
+            },
+            ("class_definition", Some(name)) => visit(&child, file, &qualified(&name), f),
+            _ => visit(&child, file, prefix, f),
+        }
+    }
+}
+
+pub(super) fn signature(node: &Node, file: &SourceFile, name: String) -> InferredSignature {
+    let parameters = match node.child_by_field_name("parameters") {
+        Some(params) => parameters(&params)
+            .iter()
+            .filter_map(|(param, kind)| parameter(param, *kind, file))
+            .collect(),
+        None => Vec::new(),
+    };
+    let returns = match node.child_by_field_name("return_type") {
+        Some(annotation) => Some(text(file, &annotation).to_string()),
+        None => node
+            .child_by_field_name("body")
+            .map(|body| return_type(&body, file))
+            .filter(|ty| *ty != Type::Unknown)
+            .map(|ty| ty.to_string()),
+    };
+    InferredSignature { name, parameters, returns }
+}
+
+/// The parameters in a parameter list, each with how arguments are passed
+/// to it. The bare `*` and `/` separators aren't parameters.
+pub(crate) fn parameters<'t>(params: &Node<'t>) -> Vec<(Node<'t>, ParameterKind)> {
+    let mut parameters: Vec<(Node, ParameterKind)> = Vec::new();
+    let mut keyword_only = false;
+    let mut cursor = params.walk();
+    for param in params.named_children(&mut cursor) {
+        // `*args: int` nests the splat pattern in a typed parameter.
+        let pattern = match param.kind() {
+            "typed_parameter" => param.named_child(0).map_or("", |n| n.kind()),
+            kind => kind,
+        };
+        let kind = match (param.kind(), pattern) {
+            ("positional_separator", _) => {
+                for (_, kind) in &mut parameters {
+                    *kind = ParameterKind::PositionalOnly;
+                }
+                continue;
+            },
+            ("keyword_separator", _) => {
+                keyword_only = true;
+                continue;
+            },
+            ("comment", _) => continue,
+            (_, "list_splat_pattern") => {
+                keyword_only = true;
+                ParameterKind::VarPositional
+            },
+            (_, "dictionary_splat_pattern") => ParameterKind::VarKeyword,
+            _ if keyword_only => ParameterKind::KeywordOnly,
+            _ => ParameterKind::Positional,
+        };
+        parameters.push((param, kind));
+    }
+    parameters
+}
+
+/// The identifier a parameter binds.
+pub(crate) fn parameter_name<'t>(param: &Node<'t>) -> Option<Node<'t>> {
+    match param.kind() {
+        "identifier" => Some(*param),
+        "default_parameter" | "typed_default_parameter" => param.child_by_field_name("name"),
+        "typed_parameter" => parameter_name(&param.named_child(0)?),
+        "list_splat_pattern" | "dictionary_splat_pattern" => param.named_child(0),
+        _ => None,
+    }
+}
+
+/// Whether a parameter has a type annotation.
+pub(crate) fn is_annotated(param: &Node) -> bool {
+    matches!(param.kind(), "typed_parameter" | "typed_default_parameter")
+}
+
+pub(super) fn parameter(
+    param: &Node,
+    kind: ParameterKind,
+    file: &SourceFile,
+) -> Option<InferredParameter> {
+    let name = text(file, &parameter_name(param)?).to_string();
+    let ty = match param.child_by_field_name("type") {
+        Some(ty) => Some(text(file, &ty).to_string()),
+        None => param
+            .child_by_field_name("value")
+            .map(|value| expression_type(&value, file.text().as_bytes()))
+            .filter(|ty| *ty != Type::Unknown)
+            .map(|ty| ty.to_string()),
+    };
+    Some(InferredParameter { name, kind, ty })
+}
+
+/// The union of the types a function body returns: `None` when it never
+/// returns a value, `Unknown` for generators or when any returned
+/// expression can't be inferred.
+fn return_type(body: &Node, file: &SourceFile) -> Type {
+    let mut types = Vec::new();
+    let mut stack = vec![*body];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            // Nested scopes return for themselves.
+            "function_definition" | "class_definition" | "lambda" => continue,
+            "yield" => return Type::Unknown,
+            "return_statement" => {
+                let ty = match node.named_child(0) {
+                    Some(value) => expression_type(&value, file.text().as_bytes()),
+                    None => Type::None,
+                };
+                if ty == Type::Unknown {
+                    return Type::Unknown;
+                }
+                types.push(ty);
+            },
+            _ => {},
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    if types.is_empty() {
+        Type::None
+    } else {
+        Type::union_of(types)
+    }
+}
+
+fn text<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    file.slice(&file.node_span(node))
+}
+
+/// Rendered signatures of a set of files, keyed by path and then by
+/// function name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeSnapshot {
+    /// Format version; see [`TYPE_SNAPSHOT_VERSION`].
+    pub version: u32,
+    /// Signatures per file, keyed by path relative to the snapshot root
+    /// with `/` separators.
+    pub files: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Default for TypeSnapshot {
+    fn default() -> Self {
+        Self { version: TYPE_SNAPSHOT_VERSION, files: BTreeMap::new() }
+    }
+}
+
+impl TypeSnapshot {
+    /// Records the signatures of `files`, keyed relative to `root`.
+    pub fn collect<'p>(root: &Path, files: impl IntoIterator<Item = &'p Path>) -> Result<Self> {
+        let mut snapshot = Self::default();
+        for path in files {
+            let key = path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let file = SourceFile::read(path)?;
+            snapshot.add_file(key, &file)?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Records the signatures of one file. A name defined more than once
+    /// gets a `#2`, `#3`, ... suffix on its later definitions.
+    pub fn add_file(&mut self, key: impl Into<String>, file: &SourceFile) -> Result<()> {
+        let mut functions = BTreeMap::new();
+        for signature in infer_signatures(file).map_err(|e| e.with_path(file.path()))? {
+            let mut name = signature.name.clone();
+            let mut n = 1;
+            while functions.contains_key(&name) {
+                n += 1;
+                name = format!("{}#{}", signature.name, n);
+            }
+            functions.insert(name, signature.to_string());
+        }
+        self.files.insert(key.into(), functions);
+        Ok(())
+    }
+
+    /// Reads a snapshot file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| Error::from(e).with_path(path))?;
+        let snapshot: Self = serde_json::from_str(&text).map_err(|e| {
+            Error::parser_error(format!("Invalid type snapshot: {}", e)).with_path(path)
+        })?;
+        if snapshot.version != TYPE_SNAPSHOT_VERSION {
+            return Err(Error::invalid_value(
+                "version",
+                snapshot.version.to_string(),
+                format!("expected type snapshot version {}", TYPE_SNAPSHOT_VERSION),
+            )
+            .with_path(path));
+        }
+        Ok(snapshot)
+    }
+
+    /// Writes the snapshot as pretty-printed JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| Error::Other(e.to_string()))?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Every function whose signature differs in `current`, including
+    /// functions that were added or removed.
+    pub fn drift(&self, current: &TypeSnapshot) -> Vec<TypeDrift> {
+        let empty = BTreeMap::new();
+        let mut paths: Vec<&String> = self.files.keys().chain(current.files.keys()).collect();
+        paths.sort();
+        paths.dedup();
+        let mut drift = Vec::new();
+        for path in paths {
+            let before = self.files.get(path).unwrap_or(&empty);
+            let after = current.files.get(path).unwrap_or(&empty);
+            let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+            names.sort();
+            names.dedup();
+            for name in names {
+                let (expected, found) = (before.get(name), after.get(name));
+                if expected != found {
+                    drift.push(TypeDrift {
+                        path: path.clone(),
+                        function: name.clone(),
+                        expected: expected.cloned(),
+                        found: found.cloned(),
+                    });
+                }
+            }
+        }
+        drift
+    }
+}
+
+/// A signature that differs from the snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeDrift {
+    /// File key in the snapshot.
+    pub path: String,
+    /// Function name.
+    pub function: String,
+    /// Signature in the snapshot; `None` for new functions.
+    pub expected: Option<String>,
+    /// Current signature; `None` for removed functions.
+    pub found: Option<String>,
+}
+
+impl fmt::Display for TypeDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.expected, &self.found) {
+            (Some(expected), Some(found)) => write!(
+                f,
+                "{}: {} changed from `{}` to `{}`",
+                self.path, self.function, expected, found
+            ),
+            (None, Some(found)) => {
+                write!(f, "{}: {} added with `{}`", self.path, self.function, found)
+            },
+            (Some(expected), None) => {
+                write!(f, "{}: {} removed (was `{}`)", self.path, self.function, expected)
+            },
+            (None, None) => write!(f, "{}: {}", self.path, self.function),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_signatures() {
+        let source = "class C:\n    def m(self, x: int, y=1.5, *args, flag=False, **kw):\n        \
+                      if x:\n            return 'a'\n        return None\n\n\
+                      def gen(n):\n    yield n\n\ndef nothing(a, *, b):\n    def inner():\n        \
+                      return 1\n    print(a)\n\ndef split(a, b=1, /, c=2, *, d: str, **kw: int):\n    \
+                      pass\n\ndef only(a, /):\n    pass\n";
+        let file = SourceFile::from_text("a.py", source);
+        let rendered: Vec<String> = infer_signatures(&file)
+            .unwrap()
+            .iter()
+            .map(|s| format!("{}{}", s.name, s))
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                "C.m(self, x: int, y: float, *args, flag: bool, **kw) -> Optional[str]",
+                "gen(n)",
+                "nothing(a, *, b) -> None",
+                "nothing.inner() -> int",
+                "split(a, b: int, /, c: int, *, d: str, **kw: int) -> None",
+                "only(a, /) -> None",
+            ]
+        );
+        let split = &infer_signatures(&file).unwrap()[4];
+        let kinds: Vec<(&str, ParameterKind)> = split
+            .parameters
+            .iter()
+            .map(|p| (p.name.as_str(), p.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("a", ParameterKind::PositionalOnly),
+                ("b", ParameterKind::PositionalOnly),
+                ("c", ParameterKind::Positional),
+                ("d", ParameterKind::KeywordOnly),
+                ("kw", ParameterKind::VarKeyword),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_drift() {
+        let mut before = TypeSnapshot::default();
+        let file =
+            SourceFile::from_text("a.py", "def f(x=1):\n    return x\n\ndef g():\n    pass\n");
+        before.add_file("a.py", &file).unwrap();
+        let mut after = TypeSnapshot::default();
+        let file =
+            SourceFile::from_text("a.py", "def f(x=''):\n    return x\n\ndef h():\n    pass\n");
+        after.add_file("a.py", &file).unwrap();
+
+        let drift: Vec<String> = before.drift(&after).iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            drift,
+            [
+                "a.py: f changed from `(x: int)` to `(x: str)`",
+                "a.py: g removed (was `() -> None`)",
+                "a.py: h added with `() -> None`",
+            ]
+        );
+        assert!(before.drift(&before).is_empty());
+    }
+}
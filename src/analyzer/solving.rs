@@ -0,0 +1,430 @@
+//! Infers the types of unannotated parameters with the constraint solver.
+//!
+//! Each unannotated parameter of a function defined at the top level of the
+//! file gets a type variable. Calls by name to the function bind their
+//! arguments as Python does, by position and by keyword, and the variable
+//! must be a supertype of every argument passed to the parameter. An
+//! argument that is itself an unannotated parameter passes its variable
+//! along, so types flow through chains of calls. Reading an attribute of
+//! the parameter requires the arguments to have it, which instances of the
+//! file's classes are checked against. Defaults are left out, since a
+//! default's type says little about what else is accepted, and so are
+//! calls with `*` or `**` arguments. A parameter whose constraints
+//! contradict each other is left uninferred rather than guessed, and so is
+//! one no call constrains; [`Unsolved`] decides whether those are typed
+//! `Any` or `Unknown` or reported as could not be inferred.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use tree_sitter::{Node, Tree};
+
+use super::calls::{scope_bindings, shadowed};
+use super::decorators::preserves_signature;
+use super::signatures::{self, is_annotated, parameter_name, ParameterKind};
+use super::Analyzer;
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::solver::{Constraint, ConstraintSolver, Origin, Unsolved};
+use crate::types::{Type, TypeVar};
+use crate::utils::{SourceFile, Span};
+
+/// What solving the constraints of a file inferred.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterInference {
+    /// Types of the unannotated parameters that the calls in the file
+    /// determine, keyed by function name, then parameter name.
+    pub types: BTreeMap<String, BTreeMap<String, Type>>,
+    /// Warnings from solving, such as inference giving up on code too
+    /// complex to solve within the solver's limits, or a parameter whose
+    /// type couldn't be inferred.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A parameter of a top-level function.
+struct Parameter {
+    name: String,
+    /// Where the parameter's name is, to report it.
+    span: Span,
+    kind: ParameterKind,
+    /// The variable standing for the parameter's type, if it is unannotated.
+    var: Option<TypeVar>,
+}
+
+/// Infers the parameter types of the top-level functions in a parsed
+/// Python file from the calls to them. `analyzer` must have analyzed the
+/// same tree; it supplies the types of the arguments and the file's
+/// classes. Parameters that can't be inferred are handled as `unsolved`
+/// says.
+pub(super) fn infer_parameters(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+    unsolved: Unsolved,
+) -> ParameterInference {
+    let root = tree.root_node();
+    let source = file.text().as_bytes();
+    let mut solver = ConstraintSolver::new()
+        .with_max_union_members(analyzer.max_union_members)
+        .with_unsolved(unsolved);
+    solver.add_hierarchy(&analyzer.class_hierarchy());
+
+    for (name, ty) in analyzer.aliases() {
+        solver.add_alias(name.clone(), ty.clone());
+    }
+
+    let functions = functions(&root, file, &mut solver);
+    // The variables of each function's parameters, keyed by node id, for
+    // arguments that pass a parameter along.
+    let scopes: HashMap<usize, HashMap<&str, TypeVar>> = functions
+        .values()
+        .map(|(node, parameters)| {
+            let vars = parameters
+                .iter()
+                .filter_map(|p| Some((p.name.as_str(), p.var?)))
+                .collect();
+            (node.id(), vars)
+        })
+        .collect();
+    let scope_var = |node: &Node| -> Option<TypeVar> {
+        let scope = std::iter::successors(node.parent(), |n| n.parent())
+            .find(|n| n.kind() == "function_definition")?;
+        let name = node.utf8_text(source).ok()?;
+        scopes.get(&scope.id())?.get(name).copied()
+    };
+
+    // The types passed to each parameter, joined into one constraint so
+    // that calls with different types infer their union.
+    let mut passed: BTreeMap<TypeVar, (Vec<Type>, Origin)> = BTreeMap::new();
+    let mut forwarded = Vec::new();
+    let mut bindings = HashMap::new();
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if node.kind() == "attribute" {
+            if let Some((var, attribute)) = attribute_read(&node, file, scope_var) {
+                let origin =
+                    Origin::new(format!("'{}' is read", file.slice(&file.node_span(&node))))
+                        .with_path(file.path())
+                        .with_span(file.node_span(&node));
+                let ty = Type::Var(solver.fresh_var());
+                solver.add_constraint_from(
+                    Constraint::HasAttribute(Type::Var(var), attribute.to_string(), ty),
+                    origin,
+                );
+            }
+            continue;
+        }
+        if node.kind() != "call" {
+            continue;
+        }
+        let Some(function) = node
+            .child_by_field_name("function")
+            .filter(|f| f.kind() == "identifier")
+        else {
+            continue;
+        };
+        let name = file.slice(&file.node_span(&function));
+        let Some((_, parameters)) = functions.get(name) else {
+            continue;
+        };
+        if shadowed(&node, name, source, &mut bindings) {
+            continue;
+        }
+        for (i, argument) in bound_arguments(&node, parameters, file).unwrap_or_default() {
+            let parameter = &parameters[i];
+            let Some(var) = parameter.var else {
+                continue;
+            };
+            let origin =
+                Origin::new(format!("argument for parameter '{}' of '{}'", parameter.name, name))
+                    .with_path(file.path())
+                    .with_span(file.node_span(&argument));
+            let found = match argument.kind() {
+                "identifier" => analyzer.type_at(argument.start_byte()).map(|ty| match ty {
+                    // A class passed by name is the class object.
+                    Type::Named(class)
+                        if class.rsplit('.').next()
+                            == Some(file.slice(&file.node_span(&argument))) =>
+                    {
+                        Type::class_object(ty.clone())
+                    },
+                    ty => ty.clone(),
+                }),
+                _ => Some(analyzer.expression_type_at(&argument, source)),
+            };
+            let ty = match (found, scope_var(&argument)) {
+                (None | Some(Type::Unknown), Some(forwarded)) => Type::Var(forwarded),
+                (Some(ty), _) if ty != Type::Unknown => ty.widened(),
+                _ => Type::Any,
+            };
+            match ty {
+                Type::Var(_) => forwarded.push((Constraint::Subtype(ty, Type::Var(var)), origin)),
+                ty => passed
+                    .entry(var)
+                    .or_insert_with(|| (Vec::new(), origin))
+                    .0
+                    .push(ty),
+            }
+        }
+    }
+    // Pending constraints are solved last to first, so the arguments of
+    // known types are solved before the parameters passed along.
+    for (constraint, origin) in forwarded.into_iter().rev() {
+        solver.add_constraint_from(constraint, origin);
+    }
+    for (var, (types, origin)) in passed {
+        let constraint = Constraint::Subtype(Type::union_of(types), Type::Var(var));
+        solver.add_constraint_from(constraint, origin);
+    }
+
+    let conflicting: HashSet<TypeVar> = solver
+        .solve_all()
+        .iter()
+        .flat_map(|(constraint, _)| match constraint {
+            Constraint::Subtype(a, b) => vec![a, b],
+            Constraint::HasAttribute(ty, ..) => vec![ty],
+            _ => Vec::new(),
+        })
+        .filter_map(|ty| match ty {
+            Type::Var(var) => Some(*var),
+            _ => None,
+        })
+        .collect();
+    let solution = solver.substitution();
+    let mut inference = ParameterInference {
+        diagnostics: solver.fallback().cloned().into_iter().collect(),
+        ..Default::default()
+    };
+    let fallback = unsolved.fallback();
+    for (name, (_, parameters)) in &functions {
+        let mut types = BTreeMap::new();
+        for parameter in parameters {
+            let Some(var) = parameter.var else {
+                continue;
+            };
+            let solved = solution
+                .get(&var)
+                .filter(|ty| !matches!(ty, Type::Var(_)) && !conflicting.contains(&var));
+            match (solved, &fallback) {
+                (Some(ty), _) | (None, Some(ty)) => {
+                    types.insert(parameter.name.clone(), ty.clone());
+                },
+                (None, None) => inference.diagnostics.push(Diagnostic::from_message(
+                    Severity::Warning,
+                    file.path(),
+                    parameter.span,
+                    MessageId::UninferredParameter,
+                    &[("name", &parameter.name), ("function", name)],
+                )),
+            }
+        }
+        if !types.is_empty() {
+            inference.types.insert(name.to_string(), types);
+        }
+    }
+    inference
+        .diagnostics
+        .sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    inference
+}
+
+/// The functions defined at the top level whose decorators keep their
+/// signature, keyed by name, with a fresh variable for each unannotated
+/// parameter. Names bound more than once are left out.
+fn functions<'t, 'f>(
+    root: &Node<'t>,
+    file: &'f SourceFile,
+    solver: &mut ConstraintSolver,
+) -> BTreeMap<&'f str, (Node<'t>, Vec<Parameter>)> {
+    let source = file.text().as_bytes();
+    let module = scope_bindings(root, source);
+    let mut functions = BTreeMap::new();
+    let mut cursor = root.walk();
+    for definition in root.named_children(&mut cursor) {
+        let definition = match definition.kind() {
+            "decorated_definition" => match definition.child_by_field_name("definition") {
+                Some(inner) if preserves_signature(&inner, source) => inner,
+                _ => continue,
+            },
+            _ => definition,
+        };
+        let (Some(name), Some(params)) =
+            (definition.child_by_field_name("name"), definition.child_by_field_name("parameters"))
+        else {
+            continue;
+        };
+        let name = file.slice(&file.node_span(&name));
+        if definition.kind() != "function_definition"
+            || module.iter().filter(|bound| *bound == name).count() != 1
+        {
+            continue;
+        }
+        let parameters = signatures::parameters(&params)
+            .into_iter()
+            .filter_map(|(param, kind)| {
+                let name = parameter_name(&param)?;
+                Some(Parameter {
+                    name: file.slice(&file.node_span(&name)).to_string(),
+                    span: file.node_span(&name),
+                    kind,
+                    var: (!is_annotated(&param)).then(|| solver.fresh_var()),
+                })
+            })
+            .collect();
+        functions.insert(name, (definition, parameters));
+    }
+    functions
+}
+
+/// The variable of the parameter whose attribute the `attribute` node
+/// reads, and the attribute's name. Assigning an attribute doesn't read it.
+fn attribute_read<'f>(
+    attribute: &Node,
+    file: &'f SourceFile,
+    scope_var: impl Fn(&Node) -> Option<TypeVar>,
+) -> Option<(TypeVar, &'f str)> {
+    let object = attribute.child_by_field_name("object")?;
+    let name = attribute.child_by_field_name("attribute")?;
+    let assigned = attribute.parent().is_some_and(|p| {
+        p.kind() == "assignment" && p.child_by_field_name("left") == Some(*attribute)
+    });
+    if object.kind() != "identifier" || assigned {
+        return None;
+    }
+    Some((scope_var(&object)?, file.slice(&file.node_span(&name))))
+}
+
+/// The arguments of `call` with the index of the parameter each binds,
+/// or `None` if `*` or `**` arguments make that unknowable. Arguments that
+/// bind no parameter, or only `*args` or `**kwargs`, are left out.
+fn bound_arguments<'t>(
+    call: &Node<'t>,
+    parameters: &[Parameter],
+    file: &SourceFile,
+) -> Option<Vec<(usize, Node<'t>)>> {
+    let arguments = call.child_by_field_name("arguments")?;
+    // `f(x for x in xs)` passes the generator without an argument list.
+    let arguments: Vec<Node> = if arguments.kind() == "argument_list" {
+        let mut cursor = arguments.walk();
+        arguments
+            .named_children(&mut cursor)
+            .filter(|a| !a.is_extra())
+            .collect()
+    } else {
+        vec![arguments]
+    };
+    let mut positional = (0..parameters.len()).filter(|&i| {
+        matches!(parameters[i].kind, ParameterKind::PositionalOnly | ParameterKind::Positional)
+    });
+    let mut bound = Vec::new();
+    for argument in arguments {
+        match argument.kind() {
+            "list_splat" | "dictionary_splat" => return None,
+            "keyword_argument" => {
+                let (Some(name), Some(value)) =
+                    (argument.child_by_field_name("name"), argument.child_by_field_name("value"))
+                else {
+                    continue;
+                };
+                let name = file.slice(&file.node_span(&name));
+                let found = parameters.iter().position(|p| {
+                    p.name == name
+                        && matches!(p.kind, ParameterKind::Positional | ParameterKind::KeywordOnly)
+                });
+                bound.extend(found.map(|i| (i, value)));
+            },
+            _ => bound.extend(positional.next().map(|i| (i, argument))),
+        }
+    }
+    Some(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn infer(source: &str, unsolved: Unsolved) -> ParameterInference {
+        let file = SourceFile::from_text("a.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        infer_parameters(&tree, &file, &analyzer, unsolved)
+    }
+
+    #[test]
+    fn test_parameters_inferred_from_calls() {
+        let source = "class C:\n    name = 'c'\n\n\
+                      def greet(who, times, flag: bool, *, sep):\n    return who.name\n\n\
+                      def twice(n):\n    return double(n)\n\n\
+                      def double(x):\n    return x * 2\n\n\
+                      def size(obj):\n    return obj.size\n\n\
+                      greet(C(), 1, True, sep=',')\ngreet(C(), 2.5, False, sep=None)\n\
+                      twice(3)\nsize(C())\nsize(*[])\n";
+        let inference = infer(source, Unsolved::Report);
+        let types: Vec<String> = inference
+            .types
+            .iter()
+            .flat_map(|(function, params)| {
+                params
+                    .iter()
+                    .map(move |(name, ty)| format!("{}.{}: {}", function, name, ty))
+            })
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "double.x: int",
+                "greet.sep: Optional[str]",
+                "greet.times: int | float",
+                "greet.who: C",
+                "twice.n: int",
+            ]
+        );
+        let messages: Vec<&str> = inference
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages, ["Could not infer the type of parameter 'obj' of 'size'"]);
+        assert_eq!(inference.diagnostics[0].span.start.line, 12);
+    }
+
+    #[test]
+    fn test_unsolved_parameters() {
+        let source = "def f(a, b):\n    pass\n\nf(1, *[])\n";
+        for (unsolved, expected) in [(Unsolved::Any, Type::Any), (Unsolved::Unknown, Type::Unknown)]
+        {
+            let inference = infer(source, unsolved);
+            assert!(inference.diagnostics.is_empty());
+            let types = &inference.types["f"];
+            assert_eq!((&types["a"], &types["b"]), (&expected, &expected));
+        }
+        let inference = infer(source, Unsolved::Report);
+        assert!(inference.types.is_empty());
+        assert_eq!(inference.diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn test_class_objects_passed() {
+        let source = "class Widget:
+    pass
+
+class Button(Widget):
+    pass
+
+\
+                      def build(factory, item):
+    return factory()
+
+\
+                      build(Widget, Widget())
+build(Button, Button())
+";
+        let inference = infer(source, Unsolved::Report);
+        assert!(inference.diagnostics.is_empty());
+        let types = &inference.types["build"];
+        assert_eq!(types["factory"].to_string(), "Type[Button] | Type[Widget]");
+        assert_eq!(types["item"].to_string(), "Button | Widget");
+    }
+}
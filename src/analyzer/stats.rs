@@ -0,0 +1,368 @@
+//! Annotation statistics per function, optionally weighted by coverage,
+//! and type-coverage metrics per file.
+
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Tree};
+
+use super::calls;
+use super::signatures::{self, is_annotated};
+use super::FunctionKind;
+use crate::coverage::FileCoverage;
+use crate::error::Result;
+use crate::parser::Parser;
+use crate::utils::{csv_record, SourceFile, Span};
+
+/// How completely one function is annotated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionStats {
+    /// Dotted name including enclosing classes and functions, e.g. `Cls.method`.
+    pub name: String,
+    /// Source range of the whole definition.
+    pub span: Span,
+    /// Source range of the body, which is what coverage is counted over:
+    /// the `def` line itself runs whenever the module is imported.
+    pub body: Span,
+    /// Parameters checked for annotations.
+    pub parameters: usize,
+    /// Parameters that have an annotation.
+    pub annotated_parameters: usize,
+    /// Whether the return type is annotated.
+    pub return_annotated: bool,
+    /// Executed lines within the body, when coverage was applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executed_lines: Option<usize>,
+}
+
+impl FunctionStats {
+    /// Number of missing parameter and return annotations.
+    pub fn missing_annotations(&self) -> usize {
+        self.parameters - self.annotated_parameters + usize::from(!self.return_annotated)
+    }
+
+    /// Returns true if every parameter and the return type are annotated.
+    pub fn is_fully_annotated(&self) -> bool {
+        self.missing_annotations() == 0
+    }
+
+    /// Number of lines of the body that ran according to `coverage`.
+    pub fn executed_lines_in(&self, coverage: &FileCoverage) -> usize {
+        coverage.executed_between(self.body.start.line + 1, self.body.end.line + 1)
+    }
+
+    /// Ranking score: missing annotations weighted by executed lines, so
+    /// heavily run untyped code comes first. Without coverage, just the
+    /// missing annotations.
+    pub fn priority(&self) -> usize {
+        match self.executed_lines {
+            Some(lines) => self.missing_annotations() * lines,
+            None => self.missing_annotations(),
+        }
+    }
+}
+
+/// Annotation statistics for one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStats {
+    /// Path of the file.
+    pub path: String,
+    /// Every function and method, in source order.
+    pub functions: Vec<FunctionStats>,
+}
+
+impl FileStats {
+    /// Collects statistics for a Python file.
+    pub fn collect(file: &SourceFile) -> Result<Self> {
+        let tree = Parser::new()?.parse_string(file.text())?;
+        let functions = functions(&tree, file);
+        Ok(Self { path: file.path().to_string(), functions })
+    }
+
+    /// Records how many lines of each function's body ran.
+    pub fn apply_coverage(&mut self, coverage: &FileCoverage) {
+        for function in &mut self.functions {
+            function.executed_lines = Some(function.executed_lines_in(coverage));
+        }
+    }
+
+    /// Sum of the functions' priorities.
+    pub fn priority(&self) -> usize {
+        self.functions.iter().map(FunctionStats::priority).sum()
+    }
+
+    /// The most deeply nested function containing the 0-based `line`.
+    pub fn innermost_function(&self, line: usize) -> Option<&FunctionStats> {
+        self.functions
+            .iter()
+            .filter(|f| f.span.start.line <= line && line <= f.span.end.line)
+            .min_by_key(|f| f.span.end.line - f.span.start.line)
+    }
+}
+
+/// How much of a file is annotated: the parameters and returns the analyzer
+/// reports missing annotations for, and the variables of the module and its
+/// classes. Variables local to functions are left out, since their types
+/// are inferred rather than declared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeMetrics {
+    /// Parameters that have an annotation.
+    pub annotated_parameters: usize,
+    /// Parameters without an annotation.
+    pub unannotated_parameters: usize,
+    /// Functions that have a return annotation.
+    pub annotated_returns: usize,
+    /// Functions without a return annotation.
+    pub unannotated_returns: usize,
+    /// Module and class variables declared with an annotation.
+    pub annotated_variables: usize,
+    /// Module and class variables that are only ever assigned.
+    pub unannotated_variables: usize,
+}
+
+impl TypeMetrics {
+    /// Counts the annotations of a parsed Python file.
+    pub fn collect(tree: &Tree, file: &SourceFile) -> Self {
+        let functions = functions(tree, file);
+        let mut metrics = Self::default();
+        for f in &functions {
+            metrics.annotated_parameters += f.annotated_parameters;
+            metrics.unannotated_parameters += f.parameters - f.annotated_parameters;
+            metrics.annotated_returns += usize::from(f.return_annotated);
+            metrics.unannotated_returns += usize::from(!f.return_annotated);
+        }
+        count_variables(&tree.root_node(), file.text().as_bytes(), &mut metrics);
+        metrics
+    }
+
+    /// Number of places that have an annotation.
+    pub fn annotated(&self) -> usize {
+        self.annotated_parameters + self.annotated_returns + self.annotated_variables
+    }
+
+    /// Number of places that could have an annotation.
+    pub fn total(&self) -> usize {
+        self.annotated()
+            + self.unannotated_parameters
+            + self.unannotated_returns
+            + self.unannotated_variables
+    }
+
+    /// Percentage of places that have an annotation, or `None` if there is
+    /// nothing to annotate.
+    pub fn typedness(&self) -> Option<f64> {
+        match self.total() {
+            0 => None,
+            total => Some(self.annotated() as f64 * 100.0 / total as f64),
+        }
+    }
+}
+
+impl AddAssign for TypeMetrics {
+    fn add_assign(&mut self, other: Self) {
+        self.annotated_parameters += other.annotated_parameters;
+        self.unannotated_parameters += other.unannotated_parameters;
+        self.annotated_returns += other.annotated_returns;
+        self.unannotated_returns += other.unannotated_returns;
+        self.annotated_variables += other.annotated_variables;
+        self.unannotated_variables += other.unannotated_variables;
+    }
+}
+
+/// Counts the variables of `scope`, a module or class, and of the classes
+/// nested in it. A variable is annotated if any of its assignments in the
+/// scope is; dunder names such as `__all__` are skipped.
+fn count_variables(scope: &Node, source: &[u8], metrics: &mut TypeMetrics) {
+    let mut variables: BTreeMap<&str, bool> = BTreeMap::new();
+    let mut cursor = scope.walk();
+    let mut stack: Vec<Node> = scope.named_children(&mut cursor).collect();
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "class_definition" => {
+                if let Some(body) = node.child_by_field_name("body") {
+                    count_variables(&body, source, metrics);
+                }
+                continue;
+            },
+            "function_definition" | "lambda" => continue,
+            "assignment" => {
+                let annotated = node.child_by_field_name("type").is_some();
+                if let Some(left) = node.child_by_field_name("left") {
+                    for name in calls::bound_names(&left, source) {
+                        if !(name.starts_with("__") && name.ends_with("__")) {
+                            *variables.entry(name).or_default() |= annotated;
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    for annotated in variables.into_values() {
+        if annotated {
+            metrics.annotated_variables += 1;
+        } else {
+            metrics.unannotated_variables += 1;
+        }
+    }
+}
+
+/// Column names of [`stats_to_csv`]. `line` is 1-based; `executed_lines`
+/// is empty without coverage.
+pub const STATS_CSV_COLUMNS: [&str; 9] = [
+    "path",
+    "function",
+    "line",
+    "parameters",
+    "annotated_parameters",
+    "return_annotated",
+    "missing_annotations",
+    "executed_lines",
+    "priority",
+];
+
+/// Formats statistics as CSV with a header row, one record per function.
+pub fn stats_to_csv(files: &[FileStats]) -> String {
+    let mut out = csv_record(STATS_CSV_COLUMNS);
+    for file in files {
+        for f in &file.functions {
+            out.push_str(&csv_record([
+                file.path.clone(),
+                f.name.clone(),
+                (f.span.start.line + 1).to_string(),
+                f.parameters.to_string(),
+                f.annotated_parameters.to_string(),
+                f.return_annotated.to_string(),
+                f.missing_annotations().to_string(),
+                f.executed_lines.map(|n| n.to_string()).unwrap_or_default(),
+                f.priority().to_string(),
+            ]));
+        }
+    }
+    out
+}
+
+/// Formats statistics as JSON Lines, one object per function with the same
+/// fields as the CSV export.
+pub fn stats_to_jsonl(files: &[FileStats]) -> serde_json::Result<String> {
+    let mut out = String::new();
+    for file in files {
+        for f in &file.functions {
+            let record = serde_json::json!({
+                "path": file.path,
+                "function": f.name,
+                "line": f.span.start.line + 1,
+                "parameters": f.parameters,
+                "annotated_parameters": f.annotated_parameters,
+                "return_annotated": f.return_annotated,
+                "missing_annotations": f.missing_annotations(),
+                "executed_lines": f.executed_lines,
+                "priority": f.priority(),
+            });
+            out.push_str(&serde_json::to_string(&record)?);
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// Statistics for every function in a parsed file, in source order.
+fn functions(tree: &Tree, file: &SourceFile) -> Vec<FunctionStats> {
+    let mut out = Vec::new();
+    signatures::for_each_function(&tree.root_node(), file, &mut |node, name| {
+        out.push(function_stats(&node, file, name))
+    });
+    out
+}
+
+/// Counts parameters the same way the analyzer reports them: every
+/// parameter, `*args` and `**kwargs` included, is counted except `self` and
+/// `cls`, and typed ones are annotated.
+fn function_stats(node: &Node, file: &SourceFile, name: String) -> FunctionStats {
+    let (mut parameters, mut annotated_parameters) = (0, 0);
+    if let Some(params) = node.child_by_field_name("parameters") {
+        let receiver = FunctionKind::of(node, file.text().as_bytes()).receiver(&params);
+        for (param, _) in signatures::parameters(&params) {
+            if Some(param) != receiver {
+                parameters += 1;
+                annotated_parameters += usize::from(is_annotated(&param));
+            }
+        }
+    }
+    let span = file.node_span(node);
+    FunctionStats {
+        name,
+        span,
+        body: node
+            .child_by_field_name("body")
+            .map_or(span, |body| file.node_span(&body)),
+        parameters,
+        annotated_parameters,
+        return_annotated: node.child_by_field_name("return_type").is_some(),
+        executed_lines: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_with_coverage() {
+        let source = "class C:\n    def m(self, x: int) -> int:\n        return x\n\n\
+                      def hot(a, b=1):\n    def inner(c) -> None:\n        pass\n    return a\n";
+        let mut stats = FileStats::collect(&SourceFile::from_text("a.py", source)).unwrap();
+        let names: Vec<&str> = stats.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["C.m", "hot", "hot.inner"]);
+        assert_eq!(stats.functions[0].missing_annotations(), 0);
+        assert_eq!(stats.functions[1].missing_annotations(), 3);
+
+        let coverage = FileCoverage { lines: [(5, 1), (6, 1), (7, 0), (8, 1)].into() };
+        stats.apply_coverage(&coverage);
+        assert_eq!(stats.functions[1].executed_lines, Some(2));
+        assert_eq!(stats.functions[1].priority(), 6);
+        assert_eq!(stats.functions[0].priority(), 0);
+        assert_eq!(stats.innermost_function(6).unwrap().name, "hot.inner");
+    }
+
+    #[test]
+    fn test_type_metrics() {
+        let source =
+            "import os\n__all__ = ['f']\nLIMIT: int = 3\nLIMIT = 4\nname, count = 'a', 1\n\
+                      class C:\n    x: int\n    y = 0\n    def m(self, a: int) -> int:\n        \
+                      local = a\n        return local\n\n\
+                      def f(a, b: str):\n    pass\n";
+        let file = SourceFile::from_text("a.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let metrics = TypeMetrics::collect(&tree, &file);
+        assert_eq!(
+            metrics,
+            TypeMetrics {
+                annotated_parameters: 2,
+                unannotated_parameters: 1,
+                annotated_returns: 1,
+                unannotated_returns: 1,
+                annotated_variables: 2,
+                unannotated_variables: 3,
+            }
+        );
+        assert_eq!(metrics.typedness(), Some(50.0));
+        assert_eq!(TypeMetrics::default().typedness(), None);
+    }
+
+    #[test]
+    fn test_stats_exports() {
+        let source = "def f(a, b: int) -> None:\n    pass\n";
+        let stats = [FileStats::collect(&SourceFile::from_text("a.py", source)).unwrap()];
+        let csv = stats_to_csv(&stats);
+        assert_eq!(csv.lines().nth(1), Some("a.py,f,1,2,1,true,1,,1"));
+
+        let jsonl = stats_to_jsonl(&stats).unwrap();
+        let record: serde_json::Value = serde_json::from_str(jsonl.trim_end()).unwrap();
+        assert_eq!(record["function"], "f");
+        assert_eq!(record["executed_lines"], serde_json::Value::Null);
+    }
+}
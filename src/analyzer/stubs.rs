@@ -0,0 +1,100 @@
+//! `.pyi` stubs that declare the signatures of a module's functions.
+//!
+//! A module's stub is the `.pyi` file next to it (`foo.pyi` for `foo.py`),
+//! else the stub for its dotted name in one of the configured stub
+//! directories, searched in order like `sys.path`. When a module has a
+//! stub, calls are checked against the stub's signatures rather than the
+//! module's own definitions, as other type checkers do. The standard
+//! library's stubs come from typeshed (see [`super::typeshed`]).
+
+use std::path::{Path, PathBuf};
+use tree_sitter::Tree;
+
+use crate::error::Result;
+use crate::parser::Parser;
+use crate::utils::{infer_module_name, ModuleResolver, SourceFile};
+
+/// Where stubs are looked up besides next to each module.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StubPaths {
+    /// Directories searched for the stubs of checked modules, in order.
+    pub dirs: Vec<PathBuf>,
+    /// A typeshed checkout whose `stdlib/` stubs declare the standard
+    /// library. Without one, only common builtin functions are declared.
+    pub typeshed: Option<PathBuf>,
+}
+
+/// A parsed stub file.
+pub(crate) struct Stub {
+    pub(crate) file: SourceFile,
+    pub(crate) tree: Tree,
+}
+
+impl Stub {
+    /// Reads and parses the stub for the module at `path`, if it has one.
+    pub(crate) fn load(
+        parser: &mut Parser,
+        path: &Path,
+        stub_dirs: &[PathBuf],
+    ) -> Result<Option<Self>> {
+        match find_stub(path, stub_dirs) {
+            Some(stub) => Self::parse(parser, SourceFile::read(&stub)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses loaded stub text.
+    pub(crate) fn parse(parser: &mut Parser, file: SourceFile) -> Result<Self> {
+        let tree = parser.parse_string(file.text())?;
+        Ok(Self { file, tree })
+    }
+}
+
+/// The stub for the Python module at `path`. Stubs themselves have none.
+pub(crate) fn find_stub(path: &Path, stub_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if path.extension().is_none_or(|ext| ext != "py") {
+        return None;
+    }
+    let sibling = path.with_extension("pyi");
+    if sibling.is_file() {
+        return Some(sibling);
+    }
+    let module = infer_module_name(path)?;
+    ModuleResolver::new(stub_dirs.iter().cloned())
+        .resolve_file(&module)
+        .filter(|file| file.extension().is_some_and(|ext| ext == "pyi"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_stub() {
+        let dir = tempdir().unwrap();
+        let stubs = dir.path().join("stubs");
+        let project = dir.path().join("project");
+        for file in [
+            "project/pkg/__init__.py",
+            "project/pkg/a.py",
+            "project/pkg/a.pyi",
+            "project/pkg/b.py",
+            "project/pkg/c.py",
+            "stubs/pkg/b.pyi",
+            "stubs/pkg/c.py",
+        ] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let stub_dirs = [stubs.clone()];
+        let find = |file: &str| find_stub(&project.join(file), &stub_dirs);
+        assert_eq!(find("pkg/a.py"), Some(project.join("pkg/a.pyi")));
+        assert_eq!(find("pkg/b.py"), Some(stubs.join("pkg/b.pyi")));
+        assert_eq!(find("pkg/c.py"), None);
+        assert_eq!(find("pkg/a.pyi"), None);
+        assert_eq!(find_stub(&project.join("pkg/b.py"), &[]), None);
+    }
+}
@@ -0,0 +1,300 @@
+//! A project-wide table of the names Python files define.
+//!
+//! Each module contributes its functions, classes, and module-level
+//! variables, plus the methods and class-level variables of its classes,
+//! under qualified names such as `pkg.models.User.save`. Definitions inside
+//! function bodies are local and left out.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+use crate::error::{Partial, Result};
+use crate::parser::Parser;
+use crate::utils::{ModuleResolver, SourceFile, Span};
+
+/// What kind of definition a symbol is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    /// A function or method.
+    Function,
+    /// A class.
+    Class,
+    /// A module-level or class-level variable.
+    Variable,
+}
+
+/// One definition of a name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    /// Qualified name: the module, enclosing classes, and the name itself.
+    pub name: String,
+    /// What the name is bound to.
+    pub kind: SymbolKind,
+    /// File containing the definition.
+    pub path: String,
+    /// Span of the defining occurrence of the name.
+    pub span: Span,
+}
+
+/// Definitions across a set of modules, queryable by qualified name.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    /// Every definition of each qualified name, in source order.
+    symbols: BTreeMap<String, Vec<Symbol>>,
+    /// Module name of each file added, by path.
+    modules: BTreeMap<String, String>,
+}
+
+impl SymbolTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a table from Python files, naming modules relative to
+    /// `root`. Files that fail to read or parse are returned as errors and
+    /// left out.
+    pub fn from_files<I>(root: &Path, paths: I) -> Partial<Self>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        let resolver = ModuleResolver::new([root]);
+        let mut batch = Partial::new(Self::new());
+        for path in paths {
+            let module = resolver.module_name(&path).unwrap_or_else(|| {
+                path.file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+            let added = SourceFile::read(&path)
+                .and_then(|file| batch.value.add_file(&module, &file))
+                .map_err(|e| match e.path() {
+                    Some(_) => e,
+                    None => e.with_path(&path),
+                });
+            batch.record(added);
+        }
+        batch
+    }
+
+    /// Adds the definitions of one file as module `module`, replacing any
+    /// definitions previously added from the same path.
+    pub fn add_file(&mut self, module: &str, file: &SourceFile) -> Result<()> {
+        let tree = Parser::new()?.parse_string(file.text())?;
+        self.remove_file(file.path());
+        let mut found = Vec::new();
+        collect(&tree.root_node(), file, module, &mut found);
+        for symbol in found {
+            self.symbols
+                .entry(symbol.name.clone())
+                .or_default()
+                .push(symbol);
+        }
+        self.modules
+            .insert(file.path().to_string(), module.to_string());
+        Ok(())
+    }
+
+    /// Removes every definition added from `path`.
+    pub fn remove_file(&mut self, path: &str) {
+        if self.modules.remove(path).is_none() {
+            return;
+        }
+        self.symbols.retain(|_, definitions| {
+            definitions.retain(|s| s.path != path);
+            !definitions.is_empty()
+        });
+    }
+
+    /// The definition a qualified name refers to: its last one, as Python
+    /// rebinds names in order.
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name).and_then(|d| d.last())
+    }
+
+    /// Every definition of a qualified name, in source order.
+    pub fn definitions(&self, name: &str) -> &[Symbol] {
+        self.symbols.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Symbols defined directly in a module or class, such as the
+    /// top-level names of `pkg.mod` or the methods of `pkg.mod.C`.
+    pub fn members<'t>(&'t self, parent: &'t str) -> impl Iterator<Item = &'t Symbol> + 't {
+        self.symbols
+            .range(format!("{}.", parent)..)
+            .take_while(move |(name, _)| {
+                name.strip_prefix(parent)
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .filter(move |(name, _)| !name[parent.len() + 1..].contains('.'))
+            .filter_map(|(_, definitions)| definitions.last())
+    }
+
+    /// The module name a file was added under.
+    pub fn module_of(&self, path: &str) -> Option<&str> {
+        self.modules.get(path).map(String::as_str)
+    }
+
+    /// Every definition, ordered by qualified name.
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.values().flatten()
+    }
+
+    /// Number of distinct qualified names.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns true if no definitions were added.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+/// Collects the definitions in a module or class body. Conditional blocks
+/// (`if TYPE_CHECKING:`, `try:` imports) are searched; function bodies are
+/// not.
+fn collect(node: &Node, file: &SourceFile, prefix: &str, out: &mut Vec<Symbol>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "function_definition" | "class_definition" => {
+                let Some(name) = child.child_by_field_name("name") else {
+                    continue;
+                };
+                let kind = if child.kind() == "class_definition" {
+                    SymbolKind::Class
+                } else {
+                    SymbolKind::Function
+                };
+                let symbol = define(file, &name, prefix, kind);
+                let class_prefix = symbol.name.clone();
+                out.push(symbol);
+                if kind == SymbolKind::Class {
+                    if let Some(body) = child.child_by_field_name("body") {
+                        collect(&body, file, &class_prefix, out);
+                    }
+                }
+            },
+            "expression_statement" => {
+                let mut cursor = child.walk();
+                for assignment in child
+                    .named_children(&mut cursor)
+                    .filter(|n| n.kind() == "assignment")
+                {
+                    assignment_targets(&assignment, file, prefix, out);
+                }
+            },
+            "decorated_definition"
+            | "if_statement"
+            | "elif_clause"
+            | "else_clause"
+            | "try_statement"
+            | "except_clause"
+            | "finally_clause"
+            | "with_statement"
+            | "block" => collect(&child, file, prefix, out),
+            _ => {},
+        }
+    }
+}
+
+/// Adds the names bound by an assignment, following chained assignments.
+fn assignment_targets(assignment: &Node, file: &SourceFile, prefix: &str, out: &mut Vec<Symbol>) {
+    if let Some(left) = assignment.child_by_field_name("left") {
+        target_names(&left, file, prefix, out);
+    }
+    if let Some(right) = assignment
+        .child_by_field_name("right")
+        .filter(|r| r.kind() == "assignment")
+    {
+        assignment_targets(&right, file, prefix, out);
+    }
+}
+
+fn target_names(target: &Node, file: &SourceFile, prefix: &str, out: &mut Vec<Symbol>) {
+    match target.kind() {
+        "identifier" => out.push(define(file, target, prefix, SymbolKind::Variable)),
+        "pattern_list" | "tuple_pattern" | "list_pattern" | "list_splat_pattern" => {
+            let mut cursor = target.walk();
+            for element in target.named_children(&mut cursor) {
+                target_names(&element, file, prefix, out);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// A symbol for the name node of a definition.
+fn define(file: &SourceFile, name: &Node, prefix: &str, kind: SymbolKind) -> Symbol {
+    let span = file.node_span(name);
+    let name = file.slice(&span);
+    Symbol {
+        name: if prefix.is_empty() { name.to_string() } else { format!("{}.{}", prefix, name) },
+        kind,
+        path: file.path().to_string(),
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_symbol_table_across_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("pkg")).unwrap();
+        fs::write(dir.path().join("pkg/__init__.py"), "").unwrap();
+        fs::write(
+            dir.path().join("pkg/models.py"),
+            "LIMIT = 10\nif TYPE_CHECKING:\n    a, *rest = 1, 2\n\n@dataclass\nclass User:\n    \
+             role: str = 'x'\n\n    def save(self):\n        local = 1\n\ndef save():\n    \
+             pass\n\nLIMIT = 20\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("main.py"), "import pkg.models\n").unwrap();
+        fs::write(dir.path().join("broken.py"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let paths = ["pkg/models.py", "main.py", "missing.py"].map(|p| dir.path().join(p));
+        let batch = SymbolTable::from_files(dir.path(), paths);
+        assert_eq!(batch.errors.len(), 1);
+        let table = batch.value;
+
+        let names: Vec<(&str, SymbolKind)> =
+            table.iter().map(|s| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(
+            names,
+            [
+                ("pkg.models.LIMIT", SymbolKind::Variable),
+                ("pkg.models.LIMIT", SymbolKind::Variable),
+                ("pkg.models.User", SymbolKind::Class),
+                ("pkg.models.User.role", SymbolKind::Variable),
+                ("pkg.models.User.save", SymbolKind::Function),
+                ("pkg.models.a", SymbolKind::Variable),
+                ("pkg.models.rest", SymbolKind::Variable),
+                ("pkg.models.save", SymbolKind::Function),
+            ]
+        );
+        assert_eq!(table.get("pkg.models.LIMIT").unwrap().span.start.line, 14);
+        assert_eq!(table.definitions("pkg.models.LIMIT").len(), 2);
+        let members: Vec<&str> = table
+            .members("pkg.models.User")
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(members, ["pkg.models.User.role", "pkg.models.User.save"]);
+        assert_eq!(table.members("pkg.models").count(), 5);
+
+        let models = dir.path().join("pkg/models.py");
+        let models = models.to_string_lossy();
+        assert_eq!(table.module_of(&models), Some("pkg.models"));
+        let mut table = table;
+        table.remove_file(&models);
+        assert!(table.is_empty());
+    }
+}
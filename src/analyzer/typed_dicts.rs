@@ -0,0 +1,392 @@
+//! Checks dict values of `TypedDict` types against their declared keys.
+//!
+//! Classes defined at the top level of a file that derive from `TypedDict`,
+//! directly or through another such class, declare the keys of their
+//! values. A dict literal passed to a parameter or assigned to a variable
+//! annotated with one of them must give every required key, no undeclared
+//! key, and values the keys' annotations accept. Keys are required unless
+//! the class says `total=False` or the key is annotated `NotRequired[...]`;
+//! `Required[...]` overrides `total=False`. After a `**` entry or a key
+//! that isn't a string literal, missing keys may have been given and are
+//! not reported. Subscripting a variable of a `TypedDict` type with a
+//! string literal must name a declared key.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use tree_sitter::{Node, Tree};
+
+use super::{annotation_type, expression_type, rejects, Analyzer};
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::{Type, TypedDictField};
+use crate::utils::SourceFile;
+
+/// How `TypedDict` itself may be named in a class's bases.
+const TYPED_DICT: &[&str] = &["TypedDict", "typing.TypedDict", "typing_extensions.TypedDict"];
+
+/// The `TypedDict` classes of a file, as [`Type::TypedDict`]s by class
+/// name.
+#[derive(Debug, Default)]
+pub(super) struct TypedDicts(HashMap<String, Type>);
+
+impl TypedDicts {
+    /// Collects the `TypedDict` classes defined at the top level of a
+    /// module. Keys inherited from another `TypedDict` keep the totality
+    /// of the class that declared them.
+    pub(super) fn collect(root: &Node, source: &[u8]) -> Self {
+        let mut typed_dicts = Self::default();
+        let mut cursor = root.walk();
+        for definition in root.named_children(&mut cursor) {
+            let class = match definition.kind() {
+                "decorated_definition" => match definition.child_by_field_name("definition") {
+                    Some(inner) => inner,
+                    None => continue,
+                },
+                _ => definition,
+            };
+            let (Some(name), Some(bases), Some(body)) = (
+                class.child_by_field_name("name"),
+                class.child_by_field_name("superclasses"),
+                class.child_by_field_name("body"),
+            ) else {
+                continue;
+            };
+            if class.kind() != "class_definition" {
+                continue;
+            }
+            let mut is_typed_dict = false;
+            let mut total = true;
+            let mut keys = BTreeMap::new();
+            let mut cursor = bases.walk();
+            for base in bases.named_children(&mut cursor) {
+                let text = base.utf8_text(source).unwrap_or_default();
+                if base.kind() == "keyword_argument" {
+                    if base
+                        .child_by_field_name("name")
+                        .is_some_and(|n| n.utf8_text(source) == Ok("total"))
+                    {
+                        total = base
+                            .child_by_field_name("value")
+                            .is_none_or(|v| v.kind() != "false");
+                    }
+                } else if TYPED_DICT.contains(&text) {
+                    is_typed_dict = true;
+                } else if let Some(inherited) = typed_dicts.keys(text) {
+                    is_typed_dict = true;
+                    keys.extend(inherited.clone());
+                }
+            }
+            if !is_typed_dict {
+                continue;
+            }
+            let mut cursor = body.walk();
+            for statement in body.named_children(&mut cursor) {
+                let Some(assignment) = statement
+                    .named_child(0)
+                    .filter(|_| statement.kind() == "expression_statement")
+                    .filter(|a| a.kind() == "assignment")
+                else {
+                    continue;
+                };
+                let (Some(key), Some(annotation)) = (
+                    assignment.child_by_field_name("left"),
+                    assignment.child_by_field_name("type"),
+                ) else {
+                    continue;
+                };
+                if key.kind() == "identifier" {
+                    let key = key.utf8_text(source).unwrap_or_default().to_string();
+                    keys.insert(key, declared_key(&annotation, source, total));
+                }
+            }
+            let name = name.utf8_text(source).unwrap_or_default().to_string();
+            let ty = Type::TypedDict { name: name.clone(), fields: keys, total };
+            typed_dicts.0.insert(name, ty);
+        }
+        typed_dicts
+    }
+
+    /// Whether `name` is a `TypedDict` of the file.
+    pub(super) fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The declared keys of the `TypedDict` `name`.
+    fn keys(&self, name: &str) -> Option<&BTreeMap<String, TypedDictField>> {
+        match self.0.get(name)? {
+            Type::TypedDict { fields, .. } => Some(fields),
+            _ => None,
+        }
+    }
+
+    /// Checks a dict literal used as a value of the `TypedDict` `name`.
+    pub(super) fn check_literal(
+        &self,
+        name: &str,
+        dict: &Node,
+        file: &SourceFile,
+        analyzer: &Analyzer,
+        out: &mut Vec<Diagnostic>,
+    ) {
+        let Some(keys) = self.keys(name) else {
+            return;
+        };
+        let source = file.text().as_bytes();
+        let mut given = HashSet::new();
+        let mut unpacked = false;
+        let mut cursor = dict.walk();
+        for entry in dict.named_children(&mut cursor) {
+            if entry.kind() == "dictionary_splat" {
+                unpacked = true;
+            }
+            let (Some(key_node), Some(value)) =
+                (entry.child_by_field_name("key"), entry.child_by_field_name("value"))
+            else {
+                continue;
+            };
+            let Some(key) = string_value(&key_node, source) else {
+                unpacked = true;
+                continue;
+            };
+            given.insert(key);
+            let Some(declared) = keys.get(key) else {
+                out.push(diagnostic(
+                    Severity::Error,
+                    file,
+                    &key_node,
+                    MessageId::UnknownTypedDictKey,
+                    &[("type", name), ("key", key)],
+                ));
+                continue;
+            };
+            match &declared.ty {
+                Type::Named(nested) if value.kind() == "dictionary" && self.contains(nested) => {
+                    self.check_literal(nested, &value, file, analyzer, out);
+                },
+                expected => {
+                    let found = match value.kind() {
+                        "identifier" => match analyzer.type_at(value.start_byte()) {
+                            Some(ty) => ty.clone(),
+                            None => continue,
+                        },
+                        _ => expression_type(&value, source),
+                    };
+                    let expected = analyzer.expand_aliases(expected);
+                    let classes = analyzer.class_hierarchy();
+                    if rejects(&expected, &analyzer.expand_aliases(&found), &classes) {
+                        out.push(diagnostic(
+                            Severity::Warning,
+                            file,
+                            &value,
+                            MessageId::TypedDictItemType,
+                            &[
+                                ("type", name),
+                                ("key", key),
+                                ("expected", &expected.to_string()),
+                                ("found", &found.to_string()),
+                            ],
+                        ));
+                    }
+                },
+            }
+        }
+        if unpacked {
+            return;
+        }
+        for (key, declared) in keys {
+            if declared.required && !given.contains(key.as_str()) {
+                out.push(diagnostic(
+                    Severity::Error,
+                    file,
+                    dict,
+                    MessageId::MissingTypedDictKey,
+                    &[("type", name), ("key", key)],
+                ));
+            }
+        }
+    }
+}
+
+/// Checks the dict literals assigned to variables annotated with a
+/// `TypedDict` type, and the string keys variables of those types are
+/// subscripted with. Literals passed as arguments are checked with the
+/// call. `analyzer` must have analyzed the same tree.
+pub(super) fn check_typed_dicts(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+    typed_dicts: &TypedDicts,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if typed_dicts.0.is_empty() {
+        return diagnostics;
+    }
+    let source = file.text().as_bytes();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        match node.kind() {
+            "assignment" => {
+                let (Some(annotation), Some(value)) =
+                    (node.child_by_field_name("type"), node.child_by_field_name("right"))
+                else {
+                    continue;
+                };
+                if let Type::Named(name) = annotation_type(&annotation, source) {
+                    if value.kind() == "dictionary" {
+                        typed_dicts.check_literal(&name, &value, file, analyzer, &mut diagnostics);
+                    }
+                }
+            },
+            "subscript" => {
+                let (Some(value), Some(key_node)) =
+                    (node.child_by_field_name("value"), node.child_by_field_name("subscript"))
+                else {
+                    continue;
+                };
+                let Some(Type::Named(name)) = analyzer.type_at(value.start_byte()) else {
+                    continue;
+                };
+                let (Some(keys), Some(key)) =
+                    (typed_dicts.keys(name), string_value(&key_node, source))
+                else {
+                    continue;
+                };
+                if value.kind() == "identifier" && !keys.contains_key(key) {
+                    diagnostics.push(diagnostic(
+                        Severity::Error,
+                        file,
+                        &key_node,
+                        MessageId::UnknownTypedDictKey,
+                        &[("type", name), ("key", key)],
+                    ));
+                }
+            },
+            _ => {},
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// The type of a key annotation and whether the key is required, after
+/// unwrapping `Required[...]`, `NotRequired[...]` and `ReadOnly[...]`.
+fn declared_key(annotation: &Node, source: &[u8], total: bool) -> TypedDictField {
+    let inner = annotation
+        .named_child(0)
+        .filter(|n| n.kind() == "generic_type");
+    let qualifier = inner
+        .and_then(|generic| generic.named_child(0))
+        .and_then(|name| name.utf8_text(source).ok())
+        .map(|name| name.rsplit('.').next().unwrap_or(name));
+    let argument = inner
+        .and_then(|generic| generic.named_child(1))
+        .filter(|parameter| parameter.kind() == "type_parameter")
+        .and_then(|parameter| parameter.named_child(0));
+    match (qualifier, argument) {
+        (Some("Required"), Some(argument)) => {
+            TypedDictField { required: true, ..declared_key(&argument, source, total) }
+        },
+        (Some("NotRequired"), Some(argument)) => {
+            TypedDictField { required: false, ..declared_key(&argument, source, total) }
+        },
+        (Some("ReadOnly"), Some(argument)) => declared_key(&argument, source, total),
+        _ => TypedDictField { ty: annotation_type(annotation, source), required: total },
+    }
+}
+
+/// The text of a plain string literal, or `None` for other expressions and
+/// for f-strings, byte strings and implicitly concatenated strings.
+fn string_value<'s>(node: &Node, source: &'s [u8]) -> Option<&'s str> {
+    if node.kind() != "string" {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let mut content = None;
+    for child in node.named_children(&mut cursor) {
+        match child.kind() {
+            "string_start" => {
+                let prefix = child.utf8_text(source).ok()?;
+                if prefix.contains(['f', 'F', 'b', 'B']) {
+                    return None;
+                }
+            },
+            "string_content" if content.is_none() => content = Some(child.utf8_text(source).ok()?),
+            "string_end" => {},
+            _ => return None,
+        }
+    }
+    // Escapes would have to be decoded to compare keys.
+    Some(content.unwrap_or("")).filter(|text| !text.contains('\\'))
+}
+
+fn diagnostic(
+    severity: Severity,
+    file: &SourceFile,
+    node: &Node,
+    id: MessageId,
+    args: &[(&str, &str)],
+) -> Diagnostic {
+    Diagnostic::from_message(severity, file.path(), file.node_span(node), id, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::check_messages;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<String> {
+        check_messages(source, |tree, file, analyzer| {
+            let typed_dicts = TypedDicts::collect(&tree.root_node(), file.text().as_bytes());
+            check_typed_dicts(tree, file, analyzer, &typed_dicts)
+        })
+    }
+
+    #[test]
+    fn test_typed_dict_types() {
+        let source = "class Movie(TypedDict, total=False):\n    title: Required[str]\n    \
+                      year: int\n\nclass Sequel(Movie):\n    prequel: Movie\n";
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let typed_dicts = TypedDicts::collect(&tree.root_node(), source.as_bytes());
+        let Some(Type::TypedDict { name, fields, total }) = typed_dicts.0.get("Sequel") else {
+            panic!("Sequel is not a TypedDict");
+        };
+        assert_eq!((name.as_str(), *total), ("Sequel", true));
+        let keys: Vec<String> = fields
+            .iter()
+            .map(|(key, field)| format!("{}: {} {}", key, field.ty, field.required))
+            .collect();
+        assert_eq!(keys, ["prequel: Movie true", "title: str true", "year: int false"]);
+        assert_eq!(typed_dicts.0["Movie"].to_string(), "Movie");
+    }
+
+    #[test]
+    fn test_typed_dict_literals_and_subscripts() {
+        let source = "from typing import TypedDict, NotRequired, Required\n\n\
+                      class Movie(TypedDict):\n    title: str\n    year: int\n    \
+                      rating: NotRequired[float]\n\n\
+                      class Options(TypedDict, total=False):\n    verbose: bool\n    \
+                      name: Required[str]\n\n\
+                      class Sequel(Movie):\n    prequel: Movie\n\n\
+                      ok: Movie = {'title': 'Up', 'year': 2009}\n\
+                      bad: Movie = {'title': 2009, 'rating': 'high', 'genre': 'x'}\n\
+                      opts: Options = {'verbose': True}\n\
+                      extra: Options = {'name': 'a', **defaults}\n\
+                      sequel: Sequel = {'title': 'B', 'year': 1, 'prequel': {'title': 'A'}}\n\n\
+                      def show(movie: Movie):\n    print(movie['title'], movie['director'])\n    \
+                      movie['year'] = 2\n    key = 'title'\n    return movie[key]\n";
+        assert_eq!(
+            check(source),
+            [
+                "15: Missing key 'year' for TypedDict 'Movie'",
+                "15: Key 'title' of TypedDict 'Movie' expects str, but is given int",
+                "15: Key 'rating' of TypedDict 'Movie' expects float, but is given str",
+                "15: TypedDict 'Movie' has no key 'genre'",
+                "16: Missing key 'name' for TypedDict 'Options'",
+                "18: Missing key 'year' for TypedDict 'Movie'",
+                "21: TypedDict 'Movie' has no key 'director'",
+            ]
+        );
+    }
+}
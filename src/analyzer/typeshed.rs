@@ -0,0 +1,84 @@
+//! Standard library stubs from typeshed.
+//!
+//! Calls to builtin functions and to the functions of imported standard
+//! library modules are checked against the stubs in a typeshed checkout's
+//! `stdlib/` directory. Without a checkout, a bundled stub declares the
+//! builtin functions, so common mistakes like `len(a, b)` are still caught.
+
+use std::path::{Path, PathBuf};
+
+use super::stubs::Stub;
+use crate::error::Result;
+use crate::parser::Parser;
+use crate::utils::{ModuleResolver, SourceFile};
+
+/// The builtin functions, declared when typeshed doesn't declare them.
+const BUNDLED_BUILTINS: &str = include_str!("typeshed/builtins.pyi");
+
+/// Finds the stubs of standard library modules.
+pub(crate) struct Typeshed {
+    stdlib: Option<ModuleResolver>,
+}
+
+impl Typeshed {
+    /// Looks up stubs in the typeshed checkout at `root`, if given.
+    pub(crate) fn new(root: Option<&Path>) -> Self {
+        Self { stdlib: root.map(|root| ModuleResolver::new([root.join("stdlib")])) }
+    }
+
+    /// The stub file of a standard library module.
+    fn find(&self, module: &str) -> Option<PathBuf> {
+        self.stdlib
+            .as_ref()?
+            .resolve_file(module)
+            .filter(|file| file.extension().is_some_and(|ext| ext == "pyi"))
+    }
+
+    /// Reads and parses the stub of a standard library module, if there is
+    /// one. `builtins` always has one.
+    pub(crate) fn load(&self, parser: &mut Parser, module: &str) -> Result<Option<Stub>> {
+        let file = match self.find(module) {
+            Some(path) => SourceFile::read(&path)?,
+            None if module == "builtins" => SourceFile::from_text("builtins.pyi", BUNDLED_BUILTINS),
+            None => return Ok(None),
+        };
+        Stub::parse(parser, file).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_stdlib_stubs() {
+        let dir = tempdir().unwrap();
+        for (file, text) in [
+            ("stdlib/os/__init__.pyi", "def getcwd() -> str: ...\n"),
+            ("stdlib/os/path.pyi", ""),
+            ("stdlib/json.py", ""),
+        ] {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        let mut parser = Parser::new().unwrap();
+        let typeshed = Typeshed::new(Some(dir.path()));
+        let load = |parser: &mut Parser, module| typeshed.load(parser, module).unwrap();
+        let os = load(&mut parser, "os").unwrap();
+        assert_eq!(os.file.text(), "def getcwd() -> str: ...\n");
+        assert!(load(&mut parser, "os.path").is_some());
+        assert!(load(&mut parser, "json").is_none());
+        assert!(load(&mut parser, "requests").is_none());
+
+        let bundled = Typeshed::new(None);
+        let builtins = bundled.load(&mut parser, "builtins").unwrap().unwrap();
+        assert!(builtins
+            .file
+            .text()
+            .contains("def len(obj: Sized, /) -> int: ..."));
+        assert!(bundled.load(&mut parser, "os").unwrap().is_none());
+    }
+}
@@ -0,0 +1,71 @@
+# The builtin functions, from typeshed's stdlib/builtins.pyi. Overloaded
+# functions are declared once, with parameters that accept the arguments
+# of every overload.
+
+from typing import Any
+
+def abs(x: Any, /) -> Any: ...
+def all(iterable: Iterable[object], /) -> bool: ...
+def any(iterable: Iterable[object], /) -> bool: ...
+def ascii(obj: object, /) -> str: ...
+def bin(number: int | SupportsIndex, /) -> str: ...
+def breakpoint(*args: Any, **kws: Any) -> None: ...
+def callable(obj: object, /) -> bool: ...
+def chr(i: int | SupportsIndex, /) -> str: ...
+def compile(
+    source: str | ReadableBuffer | AST,
+    filename: str | ReadableBuffer | PathLike[Any],
+    mode: str,
+    flags: int = 0,
+    dont_inherit: bool = False,
+    optimize: int = -1,
+    *,
+    _feature_version: int = -1,
+) -> Any: ...
+def delattr(obj: object, name: str, /) -> None: ...
+def dir(o: object = ..., /) -> list[str]: ...
+def divmod(x: Any, y: Any, /) -> Any: ...
+def eval(source: Any, /, globals: Any = None, locals: Any = None) -> Any: ...
+def exec(source: Any, /, globals: Any = None, locals: Any = None, *, closure: Any = None) -> None: ...
+def format(value: object, format_spec: str = "", /) -> str: ...
+def getattr(o: object, name: str, default: Any = ..., /) -> Any: ...
+def globals() -> dict[str, Any]: ...
+def hasattr(obj: object, name: str, /) -> bool: ...
+def hash(obj: object, /) -> int: ...
+def hex(number: int | SupportsIndex, /) -> str: ...
+def id(obj: object, /) -> int: ...
+def input(prompt: object = "", /) -> str: ...
+def isinstance(obj: object, class_or_tuple: Any, /) -> bool: ...
+def issubclass(cls: type, class_or_tuple: Any, /) -> bool: ...
+def iter(object: Any, sentinel: Any = ..., /) -> Iterator[Any]: ...
+def len(obj: Sized, /) -> int: ...
+def locals() -> dict[str, Any]: ...
+def max(*args: Any, key: Any = None, default: Any = ...) -> Any: ...
+def min(*args: Any, key: Any = None, default: Any = ...) -> Any: ...
+def next(i: Iterator[Any], default: Any = ..., /) -> Any: ...
+def oct(number: int | SupportsIndex, /) -> str: ...
+def open(
+    file: FileDescriptorOrPath,
+    mode: str = "r",
+    buffering: int = -1,
+    encoding: str | None = None,
+    errors: str | None = None,
+    newline: str | None = None,
+    closefd: bool = True,
+    opener: Any = None,
+) -> IO[Any]: ...
+def ord(c: str | bytes | bytearray, /) -> int: ...
+def pow(base: Any, exp: Any, mod: Any = None) -> Any: ...
+def print(
+    *values: object,
+    sep: str | None = " ",
+    end: str | None = "\n",
+    file: SupportsWrite[str] | None = None,
+    flush: bool = False,
+) -> None: ...
+def repr(obj: object, /) -> str: ...
+def round(number: Any, ndigits: SupportsIndex | None = None) -> Any: ...
+def setattr(obj: object, name: str, value: Any, /) -> None: ...
+def sorted(iterable: Iterable[Any], /, *, key: Any = None, reverse: bool = False) -> list[Any]: ...
+def sum(iterable: Iterable[Any], /, start: Any = 0) -> Any: ...
+def vars(object: Any = ..., /) -> dict[str, Any]: ...
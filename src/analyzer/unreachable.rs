@@ -0,0 +1,191 @@
+//! Detects code that can never run.
+//!
+//! Statements after a `return`, `raise`, `continue`, `break`, or
+//! `sys.exit()` call in the same block are unreachable, as are statements after an `if` or `try`
+//! whose every path exits. So is the body of an `if` or `elif` whose
+//! condition is `isinstance(x, C)` where `x` has a builtin type no
+//! instance of the builtin `C` can have, such as `int` and `str`. The
+//! type of an unannotated parameter only reflects its default, so checks
+//! of those are left alone.
+
+use tree_sitter::{Node, Tree};
+
+use super::narrow::{instance_type, statement_exits};
+use super::Analyzer;
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::types::{Type, TypeEnv};
+use crate::utils::SourceFile;
+
+/// Reports the unreachable code in a parsed Python file. `analyzer` must
+/// have analyzed the same tree.
+pub(super) fn check_unreachable(
+    tree: &Tree,
+    file: &SourceFile,
+    analyzer: &Analyzer,
+) -> Vec<Diagnostic> {
+    let source = file.text().as_bytes();
+    let mut diagnostics = Vec::new();
+    let mut report = |start: &Node, end: &Node| {
+        diagnostics.push(Diagnostic::from_message(
+            Severity::Warning,
+            file.path(),
+            file.span(start.start_byte(), end.end_byte()),
+            MessageId::UnreachableCode,
+            &[],
+        ));
+    };
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node
+            .named_children(&mut cursor)
+            .filter(|n| n.kind() != "comment")
+            .collect();
+        if matches!(node.kind(), "module" | "block") {
+            if let Some(exit) = children
+                .iter()
+                .position(|s| statement_exits(s, source, false))
+            {
+                if let (Some(first), Some(last)) = (children.get(exit + 1), children.last()) {
+                    report(first, last);
+                }
+            }
+        }
+        if matches!(node.kind(), "if_statement" | "elif_clause") {
+            let condition = node.child_by_field_name("condition");
+            let consequence = node.child_by_field_name("consequence");
+            if let (Some(condition), Some(consequence)) = (condition, consequence) {
+                if never_instance(&condition, source, analyzer) {
+                    report(&consequence, &consequence);
+                }
+            }
+        }
+        stack.extend(children);
+    }
+    diagnostics.sort_by_key(|d| (d.span.start.line, d.span.start.column));
+    diagnostics
+}
+
+/// Whether a condition is an `isinstance` call that is always false.
+fn never_instance(condition: &Node, source: &[u8], analyzer: &Analyzer) -> bool {
+    let is_isinstance = condition.kind() == "call"
+        && condition
+            .child_by_field_name("function")
+            .is_some_and(|f| f.utf8_text(source).ok() == Some("isinstance"));
+    let arguments = condition.child_by_field_name("arguments");
+    let (true, Some(arguments)) = (is_isinstance, arguments) else {
+        return false;
+    };
+    let (Some(variable), Some(classes), 2) =
+        (arguments.named_child(0), arguments.named_child(1), arguments.named_child_count())
+    else {
+        return false;
+    };
+    if variable.kind() != "identifier" || is_unannotated_parameter(&variable, source) {
+        return false;
+    }
+    let Some(ty) = analyzer.type_at(variable.start_byte()) else {
+        return false;
+    };
+    let classes = match classes.kind() {
+        "tuple" => {
+            let mut cursor = classes.walk();
+            classes.named_children(&mut cursor).collect()
+        },
+        _ => vec![classes],
+    };
+    classes.iter().all(|class| {
+        // Names bound in the file may not be the builtins.
+        class.kind() == "identifier"
+            && analyzer.type_at(class.start_byte()).is_none()
+            && disjoint(ty, &instance_type(class, source, &TypeEnv::new()))
+    })
+}
+
+/// Whether an identifier names a parameter without an annotation of the
+/// function it is used in.
+pub(super) fn is_unannotated_parameter(identifier: &Node, source: &[u8]) -> bool {
+    let name = identifier.utf8_text(source).unwrap_or_default();
+    let function = std::iter::successors(identifier.parent(), |n| n.parent())
+        .find(|n| matches!(n.kind(), "function_definition" | "lambda"));
+    let Some(params) = function.and_then(|f| f.child_by_field_name("parameters")) else {
+        return false;
+    };
+    let mut cursor = params.walk();
+    let unannotated = params.named_children(&mut cursor).any(|param| {
+        let param = match param.kind() {
+            "identifier" => Some(param),
+            "default_parameter" => param.child_by_field_name("name"),
+            _ => None,
+        };
+        param.is_some_and(|p| p.utf8_text(source).ok() == Some(name))
+    });
+    unannotated
+}
+
+/// Whether no value of type `ty` is an instance of the builtin type
+/// `class`. `bool` values are `int`s, and `float` stands for `int` too.
+fn disjoint(ty: &Type, class: &Type) -> bool {
+    let builtin = |ty: &Type| {
+        matches!(
+            ty,
+            Type::None
+                | Type::Bool
+                | Type::Int
+                | Type::Float
+                | Type::Str
+                | Type::Bytes
+                | Type::List(_)
+                | Type::Dict(..)
+                | Type::Set(_)
+                | Type::Tuple(_)
+        )
+    };
+    match (ty, class) {
+        (Type::Union(members), _) => members.iter().all(|member| disjoint(member, class)),
+        (Type::Literal(_), _) => disjoint(&ty.widened(), class),
+        (ty, class) if !builtin(ty) || !builtin(class) => false,
+        (Type::Bool | Type::Int, Type::Bool | Type::Int) => false,
+        (Type::Float, Type::Bool | Type::Int) => false,
+        (ty, class) => std::mem::discriminant(ty) != std::mem::discriminant(class),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Vec<(usize, usize)> {
+        let file = SourceFile::from_text("a.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, source.as_bytes()).unwrap();
+        check_unreachable(&tree, &file, &analyzer)
+            .iter()
+            .map(|d| (d.span.start.line, d.span.end.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_statements_after_exits() {
+        let source = "def f(x):\n    if x:\n        return 1\n    else:\n        raise E()\n    \
+                      print(x)\n    # done\n    return 2\n\nfor i in xs:\n    continue\n    \
+                      g(i)\n\ndef h(x):\n    if x:\n        return 1\n    with suppress(E):\n        \
+                      return 2\n    return 3\n\ndef k():\n    sys.exit(1)\n    cleanup()\n";
+        assert_eq!(check(source), [(5, 7), (11, 11), (22, 22)]);
+    }
+
+    #[test]
+    fn test_impossible_isinstance_branches() {
+        let source = "def f(x: int, y: Optional[str], z: float, w: Node, s: str):\n    \
+                      if isinstance(x, str):\n        pass\n    elif isinstance(y, (int, bytes)):\n        \
+                      pass\n    if isinstance(x, bool):\n        pass\n    \
+                      if isinstance(z, int):\n        pass\n    if isinstance(w, str):\n        \
+                      pass\n    if isinstance(y, str):\n        pass\n\ndef g(str, x: int):\n    \
+                      if isinstance(x, str):\n        pass\n\ndef h(indent='  '):\n    \
+                      if isinstance(indent, int):\n        pass\n\ndef k(table: dict):\n    \
+                      if isinstance(table['key'], list):\n        pass\n";
+        assert_eq!(check(source), [(2, 2), (4, 4)]);
+    }
+}
@@ -0,0 +1,428 @@
+//! Migration from mypy and pyright configuration.
+//!
+//! Options that have an omnitype equivalent are carried over: excluded
+//! paths, the target Python version, stub directories, a custom typeshed
+//! checkout, and flags that make missing annotations more or less severe.
+//! Everything else is listed in [`Imported::skipped`] so the user can
+//! review it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use super::Config;
+use crate::diagnostics::{MessageId, Severity};
+use crate::error::{Error, Result};
+use crate::utils::PythonVersion;
+
+/// Files checked for foreign configuration, in order of precedence.
+pub const FOREIGN_CONFIG_FILES: [&str; 4] =
+    ["pyrightconfig.json", "mypy.ini", ".mypy.ini", "setup.cfg"];
+
+/// Rule codes for missing annotations, which strict modes turn into errors.
+const ANNOTATION_CODES: [MessageId; 2] =
+    [MessageId::UnannotatedParameter, MessageId::UnannotatedReturn];
+
+/// The result of importing another checker's configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imported {
+    /// The equivalent omnitype configuration.
+    pub config: Config,
+    /// Options that could not be mapped, with the reason.
+    pub skipped: Vec<String>,
+}
+
+impl Imported {
+    fn new() -> Self {
+        Self { config: Config::default(), skipped: Vec::new() }
+    }
+
+    fn skip(&mut self, option: &str, reason: &str) {
+        self.skipped.push(format!("{}: {}", option, reason));
+    }
+
+    fn python_version(&mut self, option: &str, value: &str) {
+        match value.parse::<PythonVersion>() {
+            Ok(version) => self.config.python_version = Some(version),
+            Err(_) => self.skip(option, "not a Python version"),
+        }
+    }
+
+    /// Applies a severity for missing annotations: errors are promoted and
+    /// `None` drops the diagnostics.
+    fn annotations(&mut self, severity: Option<Severity>, codes: &[MessageId]) {
+        for id in codes {
+            let code = id.code().to_string();
+            match severity {
+                Some(Severity::Error) => push_unique(&mut self.config.warn_as_error, code),
+                None => push_unique(&mut self.config.allow, code),
+                Some(_) => {},
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Imports a `mypy.ini`, `setup.cfg`, or `pyrightconfig.json` file,
+    /// chosen by its name.
+    pub fn import(path: impl AsRef<Path>) -> Result<Imported> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        let imported = if path.extension().is_some_and(|ext| ext == "json") {
+            import_pyright(&source)
+        } else {
+            import_mypy(&source)
+        };
+        imported.map_err(|e| e.with_path(path))
+    }
+
+    /// Finds mypy or pyright configuration in `dir`. A `setup.cfg` only
+    /// counts if it has a `[mypy]` section.
+    pub fn find_foreign(dir: impl AsRef<Path>) -> Option<PathBuf> {
+        FOREIGN_CONFIG_FILES
+            .iter()
+            .map(|name| dir.as_ref().join(name))
+            .find(|path| match path.file_name().and_then(|n| n.to_str()) {
+                Some("setup.cfg") => {
+                    fs::read_to_string(path).is_ok_and(|s| s.lines().any(|l| l.trim() == "[mypy]"))
+                },
+                _ => path.is_file(),
+            })
+    }
+}
+
+/// Maps the `[mypy]` section of an INI file. Per-module sections are skipped.
+pub fn import_mypy(source: &str) -> Result<Imported> {
+    let mut imported = Imported::new();
+    for (section, key, value) in ini_entries(source) {
+        if section != "mypy" {
+            if section.starts_with("mypy-") && key.is_empty() {
+                imported.skip(&format!("[{}]", section), "per-module options are not supported");
+            }
+            continue;
+        }
+        if key.is_empty() {
+            continue;
+        }
+        let enabled = matches!(value.to_ascii_lowercase().as_str(), "true" | "1" | "yes" | "on");
+        match key.as_str() {
+            "python_version" => imported.python_version(&key, &value),
+            "exclude" => {
+                let (patterns, unsupported) = exclude_from_regex(&value);
+                imported.config.exclude.extend(patterns);
+                for pattern in unsupported {
+                    imported.skip(&format!("exclude {}", pattern), "regex has no glob equivalent");
+                }
+            },
+            "mypy_path" => imported.config.stubs.extend(
+                value
+                    .split([',', ':', '\n'])
+                    .map(str::trim)
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from),
+            ),
+            "custom_typeshed_dir" => imported.config.typeshed = Some(PathBuf::from(value)),
+            "strict" | "disallow_untyped_defs" | "disallow_incomplete_defs" if enabled => {
+                imported.annotations(Some(Severity::Error), &ANNOTATION_CODES)
+            },
+            "ignore_errors" if enabled => push_unique(&mut imported.config.allow, "*".into()),
+            "strict" | "disallow_untyped_defs" | "disallow_incomplete_defs" | "ignore_errors" => {},
+            _ => imported.skip(&key, "no omnitype equivalent"),
+        }
+    }
+    Ok(imported)
+}
+
+/// Maps a `pyrightconfig.json` document, which may contain comments.
+pub fn import_pyright(source: &str) -> Result<Imported> {
+    let doc: Value = serde_json::from_str(&strip_json_comments(source))
+        .map_err(|e| Error::parser_error(format!("Invalid pyright configuration: {}", e)))?;
+    let Value::Object(options) = doc else {
+        return Err(Error::parser_error("Invalid pyright configuration: expected an object"));
+    };
+    let mut imported = Imported::new();
+    for (key, value) in &options {
+        match (key.as_str(), value) {
+            ("exclude", Value::Array(patterns)) => imported
+                .config
+                .exclude
+                .extend(patterns.iter().filter_map(Value::as_str).map(String::from)),
+            ("pythonVersion", Value::String(version)) => imported.python_version(key, version),
+            ("stubPath", Value::String(dir)) => imported.config.stubs.push(PathBuf::from(dir)),
+            ("typeshedPath", Value::String(dir)) => {
+                imported.config.typeshed = Some(PathBuf::from(dir))
+            },
+            ("typeCheckingMode", Value::String(mode)) => match mode.as_str() {
+                "strict" => imported.annotations(Some(Severity::Error), &ANNOTATION_CODES),
+                "off" => push_unique(&mut imported.config.allow, "*".into()),
+                _ => {},
+            },
+            ("reportMissingParameterType", level) => match rule_level(level) {
+                Some(severity) => {
+                    imported.annotations(severity, &[MessageId::UnannotatedParameter])
+                },
+                None => imported.skip(key, "unknown level"),
+            },
+            _ => imported.skip(key, "no omnitype equivalent"),
+        }
+    }
+    Ok(imported)
+}
+
+/// A pyright rule level: `Some(None)` when the rule is disabled.
+fn rule_level(value: &Value) -> Option<Option<Severity>> {
+    match value {
+        Value::Bool(true) => Some(Some(Severity::Error)),
+        Value::Bool(false) => Some(None),
+        Value::String(level) if level == "none" => Some(None),
+        Value::String(level) => level.parse().ok().map(Some),
+        _ => None,
+    }
+}
+
+/// Yields `(section, key, value)` for each option of an INI file, joining
+/// indented continuation lines. Each section header is also yielded once
+/// with an empty key.
+fn ini_entries(source: &str) -> Vec<(String, String, String)> {
+    let mut entries: Vec<(String, String, String)> = Vec::new();
+    let mut section = String::new();
+    for line in source.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            if let Some(entry) = entries.last_mut().filter(|(_, key, _)| !key.is_empty()) {
+                entry.2.push('\n');
+                entry.2.push_str(trimmed);
+            }
+            continue;
+        }
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = name.trim().to_string();
+            entries.push((section.clone(), String::new(), String::new()));
+        } else if let Some((key, value)) = trimmed.split_once(['=', ':']) {
+            entries.push((section.clone(), key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    entries
+}
+
+/// Converts mypy's `exclude` regex into gitignore-style patterns. Returns the
+/// converted patterns and the alternatives that use regex features globs
+/// can't express.
+fn exclude_from_regex(regex: &str) -> (Vec<String>, Vec<String>) {
+    let mut regex = regex.trim();
+    let verbose = regex.starts_with("(?x)");
+    if verbose {
+        regex = regex[4..].trim();
+    }
+    let collapsed: String = if verbose {
+        regex
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or_default())
+            .flat_map(str::chars)
+            .filter(|c| !c.is_whitespace())
+            .collect()
+    } else {
+        regex.replace('\n', "|")
+    };
+    let collapsed = collapsed.trim();
+    let alternatives = split_alternatives(unwrap_group(collapsed).unwrap_or(collapsed));
+    let mut patterns = Vec::new();
+    let mut unsupported = Vec::new();
+    for alternative in alternatives.into_iter().filter(|a| !a.is_empty()) {
+        match glob_from_regex(alternative) {
+            Some(glob) => patterns.push(glob),
+            None => unsupported.push(alternative.to_string()),
+        }
+    }
+    (patterns, unsupported)
+}
+
+/// The inside of a regex that is a single group, such as `(a|b)`.
+fn unwrap_group(regex: &str) -> Option<&str> {
+    let inner = regex.strip_prefix('(')?.strip_suffix(')')?;
+    // `(a)|(b)` starts and ends with parentheses but isn't one group.
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for c in inner.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            _ => {},
+        }
+    }
+    Some(inner)
+}
+
+/// Splits a regex on `|` outside of groups and character classes.
+fn split_alternatives(regex: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in regex.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '|' if depth == 0 => {
+                out.push(regex[start..i].trim());
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    out.push(regex[start..].trim());
+    out
+}
+
+/// Converts one regex alternative into a glob. mypy searches the path for
+/// the regex, so without `^` or `$` it may match in the middle of a name:
+/// `_pb2\.py$` becomes `*_pb2.py` and `^build` becomes `/build*`.
+fn glob_from_regex(regex: &str) -> Option<String> {
+    let (anchored, rest) = match regex.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, regex),
+    };
+    let (ends, rest) = match rest.strip_suffix('$') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let mut glob = String::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => glob.push(chars.next()?),
+            '.' if chars.peek() == Some(&'*') => {
+                chars.next();
+                glob.push('*');
+            },
+            '.' => glob.push('?'),
+            '(' | ')' | '[' | ']' | '{' | '}' | '+' | '?' | '*' | '^' | '$' => return None,
+            c => glob.push(c),
+        }
+    }
+    if !ends && !glob.ends_with(['/', '*']) {
+        glob.push('*');
+    }
+    // A slash before the end ties a gitignore pattern to the root.
+    let nested = glob.trim_end_matches('/').contains('/');
+    let prefix = match (anchored, nested) {
+        (true, _) => "/",
+        (false, _) if glob.starts_with('*') => "",
+        (false, false) => "*",
+        (false, true) => "**/*",
+    };
+    Some(format!("{}{}", prefix, glob))
+}
+
+/// Removes `//` and `/* */` comments outside of strings.
+fn strip_json_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {},
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            },
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            },
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            },
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn push_unique(list: &mut Vec<String>, item: String) {
+    if !list.contains(&item) {
+        list.push(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_mypy() {
+        let imported = import_mypy(
+            "[mypy]\npython_version = 3.10\nstrict = True\nwarn_unused_ignores = True\n\
+             mypy_path = typings, vendor/stubs\ncustom_typeshed_dir = ../typeshed\n\
+             exclude = (?x)(\n    ^build/\n    | _pb2\\.py$\n    | (a|b)/\n  )\n\n\
+             [mypy-tests.*]\nignore_errors = True\n",
+        )
+        .unwrap();
+        let config = imported.config;
+        assert_eq!(config.python_version, Some(PythonVersion::new(3, 10)));
+        assert_eq!(config.warn_as_error, ["OT001", "OT002"]);
+        assert_eq!(config.exclude, ["/build/", "*_pb2.py"]);
+        assert_eq!(config.stubs, [PathBuf::from("typings"), PathBuf::from("vendor/stubs")]);
+        assert_eq!(config.typeshed, Some(PathBuf::from("../typeshed")));
+        assert!(config.allow.is_empty());
+        assert_eq!(imported.skipped.len(), 3, "{:?}", imported.skipped);
+    }
+
+    #[test]
+    fn test_glob_from_regex() {
+        let glob = |regex| glob_from_regex(regex).unwrap();
+        assert_eq!(glob("^build/"), "/build/");
+        assert_eq!(glob("^setup\\.py$"), "/setup.py");
+        assert_eq!(glob("_pb2\\.py$"), "*_pb2.py");
+        assert_eq!(glob("generated/"), "*generated/");
+        assert_eq!(glob("tmp"), "*tmp*");
+        assert_eq!(glob("src/gen/"), "**/*src/gen/");
+        assert_eq!(glob(".*_test\\.py$"), "*_test.py");
+        assert_eq!(glob_from_regex("(a|b)/"), None);
+    }
+
+    #[test]
+    fn test_import_pyright() {
+        let imported = import_pyright(
+            "{\n  // comment\n  \"exclude\": [\"**/node_modules\", \"build\"],\n  \
+             \"pythonVersion\": \"3.11\", /* inline */\n  \"reportMissingParameterType\": \
+             \"none\",\n  \"venv\": \".venv\",\n  \"stubPath\": \"typings\",\n  \
+             \"typeshedPath\": \"typeshed\"\n}",
+        )
+        .unwrap();
+        let config = imported.config;
+        assert_eq!(config.exclude, ["**/node_modules", "build"]);
+        assert_eq!(config.python_version, Some(PythonVersion::new(3, 11)));
+        assert_eq!(config.allow, ["OT001"]);
+        assert_eq!(config.stubs, [PathBuf::from("typings")]);
+        assert_eq!(config.typeshed, Some(PathBuf::from("typeshed")));
+        assert_eq!(imported.skipped, ["venv: no omnitype equivalent"]);
+    }
+}
@@ -0,0 +1,412 @@
+//! Project configuration.
+//!
+//! Settings are read from `omnitype.toml`, or from the `[tool.omnitype]`
+//! table of `pyproject.toml`, in the checked directory or the nearest
+//! ancestor that has one:
+//!
+//! ```toml
+//! warn_as_error = ["OT00*"]
+//! allow = ["OT010"]
+//! fail_on = "warning"
+//! exclude = ["migrations/", "*_generated.py"]
+//! python_version = "3.10"
+//! stubs = ["typings"]
+//! typeshed = "../typeshed"
+//! profile = "strict"
+//! unsolved = "report"
+//!
+//! [profiles]
+//! "tests" = "lenient"
+//!
+//! [discovery]
+//! max_file_size = 1_000_000
+//! skip_generated = true
+//! ```
+//!
+//! Existing mypy or pyright settings can be converted with [`Config::import`].
+
+mod import;
+mod profile;
+
+pub use import::{import_mypy, import_pyright, Imported, FOREIGN_CONFIG_FILES};
+pub use profile::Profile;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::{Error, Result};
+use crate::solver::Unsolved;
+use crate::utils::{DiscoveryOptions, PythonVersion, SourceWalker};
+
+/// Name of the dedicated configuration file.
+pub const CONFIG_FILE: &str = "omnitype.toml";
+
+/// Settings that control how diagnostics are reported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Rule code patterns whose warnings are promoted to errors. `*` matches
+    /// any run of characters.
+    pub warn_as_error: Vec<String>,
+    /// Rule code patterns whose diagnostics are dropped.
+    pub allow: Vec<String>,
+    /// Lowest severity that makes a check fail.
+    pub fail_on: Severity,
+    /// Gitignore-style patterns for paths to skip, relative to the checked
+    /// directory.
+    pub exclude: Vec<String>,
+    /// Python version to check against, overriding the one detected from
+    /// project metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub python_version: Option<PythonVersion>,
+    /// Directories searched for the `.pyi` stubs of checked modules, in
+    /// order, when there is no stub next to the module. Relative paths are
+    /// resolved against the checked directory.
+    pub stubs: Vec<PathBuf>,
+    /// A typeshed checkout whose standard library stubs calls are checked
+    /// against. Relative paths are resolved against the checked directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typeshed: Option<PathBuf>,
+    /// Which built-in diagnostics are reported, and how severely.
+    pub profile: Profile,
+    /// Profiles for the directories named by the keys, overriding
+    /// `profile`. See [`Profile`] for how keys are matched.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// What becomes of unannotated parameters whose types can't be
+    /// inferred: `"any"` or `"unknown"` types them so, and `"report"`
+    /// reports each one.
+    pub unsolved: Unsolved,
+    /// Size, generated-file, and extension filters for the files walked,
+    /// on top of `exclude`.
+    #[serde(skip_serializing_if = "DiscoveryOptions::accepts_all")]
+    pub discovery: DiscoveryOptions,
+    /// Directory of the file the settings were loaded from, which the keys
+    /// of `profiles` are relative to.
+    #[serde(skip)]
+    root: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            warn_as_error: Vec::new(),
+            allow: Vec::new(),
+            fail_on: Severity::Warning,
+            exclude: Vec::new(),
+            python_version: None,
+            stubs: Vec::new(),
+            typeshed: None,
+            profile: Profile::default(),
+            profiles: BTreeMap::new(),
+            unsolved: Unsolved::Unknown,
+            discovery: DiscoveryOptions::default(),
+            root: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parses an `omnitype.toml` document.
+    pub fn from_toml(source: &str) -> Result<Self> {
+        toml::from_str(source)
+            .map_err(|e| Error::parser_error(format!("Invalid configuration: {}", e)))
+    }
+
+    /// Extracts the `[tool.omnitype]` table from a `pyproject.toml` document.
+    /// Returns `None` if the table is absent.
+    pub fn from_pyproject(source: &str) -> Result<Option<Self>> {
+        let doc: toml::Table = toml::from_str(source)
+            .map_err(|e| Error::parser_error(format!("Invalid pyproject.toml: {}", e)))?;
+        let Some(table) = doc
+            .get("tool")
+            .and_then(|tool| tool.get("omnitype"))
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        table
+            .try_into()
+            .map(Some)
+            .map_err(|e| Error::parser_error(format!("Invalid [tool.omnitype] table: {}", e)))
+    }
+
+    /// Loads configuration from a specific file, which may be either an
+    /// `omnitype.toml` or a `pyproject.toml`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        let config = if path.file_name().is_some_and(|n| n == "pyproject.toml") {
+            Self::from_pyproject(&source).map(Option::unwrap_or_default)
+        } else {
+            Self::from_toml(&source)
+        };
+        config
+            .map(|config| Self { root: path.parent().map(absolute), ..config })
+            .map_err(|e| e.with_path(path))
+    }
+
+    /// Finds the configuration file that applies to `start`, searching its
+    /// ancestors. `omnitype.toml` takes precedence over `pyproject.toml` in
+    /// the same directory, and a `pyproject.toml` without a `[tool.omnitype]`
+    /// table is skipped.
+    pub fn find(start: impl AsRef<Path>) -> Result<Option<PathBuf>> {
+        let start = start.as_ref();
+        let dir = if start.is_file() { start.parent() } else { Some(start) };
+        for dir in dir.into_iter().flat_map(Path::ancestors) {
+            let dedicated = dir.join(CONFIG_FILE);
+            if dedicated.is_file() {
+                return Ok(Some(dedicated));
+            }
+            let pyproject = dir.join("pyproject.toml");
+            if pyproject.is_file() {
+                let source = fs::read_to_string(&pyproject)?;
+                if Self::from_pyproject(&source)
+                    .map_err(|e| e.with_path(&pyproject))?
+                    .is_some()
+                {
+                    return Ok(Some(pyproject));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Loads the configuration that applies to `start`, or the defaults if
+    /// there is none.
+    pub fn discover(start: impl AsRef<Path>) -> Result<Self> {
+        match Self::find(start)? {
+            Some(path) => Self::load(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Applies `allow`, `warn_as_error`, and the profile of each
+    /// diagnostic's file to a batch of diagnostics. Warnings promoted by
+    /// `warn_as_error` are reported even under the lenient profile. Every
+    /// output format and the exit code should see diagnostics only after
+    /// this step so they agree on what was reported and how severely.
+    pub fn finalize(&self, diagnostics: &mut Vec<Diagnostic>) {
+        diagnostics.retain_mut(|d| {
+            if self.is_allowed(d) {
+                return false;
+            }
+            let profile = self.profile_for(Path::new(&d.path));
+            if d.severity == Severity::Warning && (self.is_promoted(d) || profile.promotes(d)) {
+                d.severity = Severity::Error;
+            }
+            profile.reports(d)
+        });
+    }
+
+    /// The profile `path` is checked with: that of the deepest directory
+    /// named in `profiles`, else `profile`. Keys only match directories
+    /// under the one the settings were loaded from, so a file outside it
+    /// gets `profile`.
+    pub fn profile_for(&self, path: &Path) -> Profile {
+        let path = match &self.root {
+            Some(root) => match absolute(path).strip_prefix(root) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => return self.profile,
+            },
+            None => path.to_path_buf(),
+        };
+        self.profiles
+            .iter()
+            .filter_map(|(key, profile)| Some((profile::match_depth(key, &path)?, *profile)))
+            .max_by_key(|(depth, _)| *depth)
+            .map_or(self.profile, |(_, profile)| profile)
+    }
+
+    /// A walker over the source files under `root` that `exclude` and
+    /// `discovery` let through.
+    pub fn walker(&self, root: impl Into<PathBuf>) -> SourceWalker {
+        SourceWalker::new(root)
+            .exclude(self.exclude.clone())
+            .options(self.discovery.clone())
+    }
+
+    /// Returns true if any diagnostic is at least as severe as `fail_on`.
+    pub fn fails(&self, diagnostics: &[Diagnostic]) -> bool {
+        diagnostics.iter().any(|d| d.severity >= self.fail_on)
+    }
+
+    fn is_allowed(&self, diagnostic: &Diagnostic) -> bool {
+        matches_any(&self.allow, diagnostic.code.as_deref())
+    }
+
+    fn is_promoted(&self, diagnostic: &Diagnostic) -> bool {
+        matches_any(&self.warn_as_error, diagnostic.code.as_deref())
+    }
+}
+
+/// `path` joined to the current directory, unless it is already absolute.
+fn absolute(path: &Path) -> PathBuf {
+    std::env::current_dir().map_or_else(|_| path.to_path_buf(), |dir| dir.join(path))
+}
+
+fn matches_any(patterns: &[String], code: Option<&str>) -> bool {
+    code.is_some_and(|code| patterns.iter().any(|p| code_matches(p, code)))
+}
+
+/// Case-insensitive match of a rule code against a pattern where `*`
+/// matches any run of characters.
+fn code_matches(pattern: &str, code: &str) -> bool {
+    let pattern = pattern.to_ascii_uppercase();
+    let code = code.to_ascii_uppercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = code.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::MessageId;
+    use crate::utils::Span;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_code_patterns() {
+        assert!(code_matches("OT00*", "OT001"));
+        assert!(code_matches("ot010", "OT010"));
+        assert!(code_matches("OT*1", "OT011"));
+        assert!(!code_matches("OT00*", "OT010"));
+        assert!(!code_matches("OT01", "OT010"));
+    }
+
+    #[test]
+    fn test_finalize_allows_and_promotes() {
+        let config = Config::from_toml(
+            "warn_as_error = [\"OT00*\"]\nallow = [\"OT010\"]\nfail_on = \"error\"",
+        )
+        .unwrap();
+        let mut diagnostics = vec![
+            Diagnostic::warning("a.py", Span::point(0, 0), "a").with_code("OT001"),
+            Diagnostic::warning("a.py", Span::point(1, 0), "b").with_code("OT010"),
+            Diagnostic::warning("a.py", Span::point(2, 0), "c").with_code("OT020"),
+        ];
+        config.finalize(&mut diagnostics);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert!(config.fails(&diagnostics));
+        assert!(!config.fails(&diagnostics[1..]));
+    }
+
+    #[test]
+    fn test_profiles_per_directory() {
+        let config = Config::from_toml(
+            "profile = \"strict\"\n[profiles]\n\"tests\" = \"lenient\"\n\
+             \"tests/typed\" = \"standard\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.profile_for(Path::new("src/a.py")), Profile::Strict);
+        assert_eq!(config.profile_for(Path::new("/p/tests/unit/a.py")), Profile::Lenient);
+        assert_eq!(config.profile_for(Path::new("tests/typed/a.py")), Profile::Standard);
+        assert_eq!(config.profile_for(Path::new("tests.py")), Profile::Strict);
+
+        let diagnostics = |path: &str| {
+            let unannotated = Diagnostic::from_message(
+                Severity::Warning,
+                path,
+                Span::point(0, 0),
+                MessageId::UnannotatedReturn,
+                &[("name", "f")],
+            );
+            let unreachable = Diagnostic::from_message(
+                Severity::Warning,
+                path,
+                Span::point(1, 0),
+                MessageId::UnreachableCode,
+                &[],
+            );
+            let custom = Diagnostic::warning(path, Span::point(2, 0), "c").with_code("no-print");
+            let mut diagnostics = vec![unannotated, unreachable, custom];
+            config.finalize(&mut diagnostics);
+            diagnostics.iter().map(|d| d.severity).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            diagnostics("src/a.py"),
+            [Severity::Error, Severity::Warning, Severity::Warning]
+        );
+        assert_eq!(diagnostics("tests/a.py"), [Severity::Warning]);
+        assert_eq!(
+            diagnostics("tests/typed/a.py"),
+            [Severity::Warning, Severity::Warning, Severity::Warning]
+        );
+        assert!(Config::from_toml("profile = \"loose\"").is_err());
+    }
+
+    #[test]
+    fn test_profiles_relative_to_config_file() {
+        // The project itself sits in a directory named like a key.
+        let dir = tempdir().unwrap();
+        let project = dir.path().join("tests").join("app");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            project.join(CONFIG_FILE),
+            "profile = \"strict\"\n[profiles]\n\"tests\" = \"lenient\"\n",
+        )
+        .unwrap();
+        let config = Config::load(project.join(CONFIG_FILE)).unwrap();
+
+        assert_eq!(config.profile_for(&project.join("src/a.py")), Profile::Strict);
+        assert_eq!(config.profile_for(&project.join("tests/unit/a.py")), Profile::Lenient);
+        assert_eq!(config.profile_for(&dir.path().join("tests/a.py")), Profile::Strict);
+    }
+
+    #[test]
+    fn test_discover_from_pyproject() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("pyproject.toml"), "[tool.omnitype]\nallow = [\"OT002\"]\n")
+            .unwrap();
+        let sub = dir.path().join("pkg");
+        fs::create_dir(&sub).unwrap();
+
+        let config = Config::discover(&sub).unwrap();
+        assert_eq!(config.allow, vec!["OT002".to_string()]);
+        assert_eq!(config.fail_on, Severity::Warning);
+    }
+
+    #[test]
+    fn test_discovery_table() {
+        let dir = tempdir().unwrap();
+        let config =
+            Config::from_toml("[discovery]\nmax_file_size = 100\nskip_generated = true\n").unwrap();
+        assert_eq!(
+            config.discovery,
+            DiscoveryOptions::new()
+                .max_file_size(100)
+                .skip_generated(true)
+        );
+        assert!(Config::from_toml("[discovery]\nmax_size = 1\n").is_err());
+
+        fs::write(dir.path().join("small.py"), "x = 1\n").unwrap();
+        fs::write(dir.path().join("large.py"), "x = 1\n".repeat(100)).unwrap();
+        fs::write(dir.path().join("api_pb2.py"), "x = 1\n").unwrap();
+        let files: Vec<PathBuf> = config.walker(dir.path()).files().unwrap().collect();
+        assert_eq!(files, [dir.path().join("small.py")]);
+        assert!(!toml::to_string(&Config::default())
+            .unwrap()
+            .contains("discovery"));
+    }
+}
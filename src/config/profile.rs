@@ -0,0 +1,110 @@
+//! Analysis profiles, which decide how strictly a file is checked.
+//!
+//! A project picks a [`Profile`] with `profile`, and can pick another one
+//! for some of its directories in the `[profiles]` table:
+//!
+//! ```toml
+//! profile = "strict"
+//!
+//! [profiles]
+//! "tests" = "lenient"
+//! "src/legacy" = "standard"
+//! ```
+//!
+//! Keys are relative to the directory of the configuration file. A key
+//! matches the directories below it whose trailing components it names, so
+//! `"tests"` covers `tests/` at any depth in the project, but not a
+//! `tests/` directory the project itself sits in. When several keys match
+//! a file, the one naming the deepest directory wins.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::error::Error;
+
+/// Rule codes for missing annotations, which the strict profile turns into
+/// errors.
+const ANNOTATION_IDS: [MessageId; 3] =
+    [MessageId::UnannotatedParameter, MessageId::UnannotatedReturn, MessageId::MissingJsdoc];
+
+/// Which families of built-in diagnostics are reported, and how severely.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Everything is reported, and missing annotations are errors.
+    Strict,
+    /// Everything is reported at its usual severity.
+    #[default]
+    Standard,
+    /// Only definite type errors are reported.
+    Lenient,
+}
+
+impl Profile {
+    /// The lowercase name used in configuration.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Profile::Strict => "strict",
+            Profile::Standard => "standard",
+            Profile::Lenient => "lenient",
+        }
+    }
+
+    /// Returns true if the profile turns `diagnostic` into an error.
+    pub fn promotes(self, diagnostic: &Diagnostic) -> bool {
+        self == Profile::Strict
+            && diagnostic
+                .message_id
+                .is_some_and(|id| ANNOTATION_IDS.contains(&id))
+    }
+
+    /// Returns true if the profile reports `diagnostic`. Diagnostics of
+    /// custom rules are always reported.
+    pub fn reports(self, diagnostic: &Diagnostic) -> bool {
+        self != Profile::Lenient
+            || diagnostic.message_id.is_none()
+            || diagnostic.severity >= Severity::Error
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(Profile::Strict),
+            "standard" => Ok(Profile::Standard),
+            "lenient" => Ok(Profile::Lenient),
+            other => Err(Error::invalid_value("profile", other, "unknown profile")),
+        }
+    }
+}
+
+/// The depth of the deepest directory of `path`, relative to the
+/// configuration file's directory, that a `[profiles]` key names, or
+/// `None` if the key names none of them.
+pub(super) fn match_depth(key: &str, path: &Path) -> Option<usize> {
+    let key: Vec<&str> = key.split(['/', '\\']).filter(|c| !c.is_empty()).collect();
+    let dirs: Vec<String> = path
+        .parent()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if key.is_empty() {
+        return None;
+    }
+    // Prefer the window closest to the file, so the deepest directory wins.
+    dirs.windows(key.len())
+        .rposition(|window| window.iter().zip(&key).all(|(dir, k)| dir == k))
+        .map(|start| start + key.len())
+}
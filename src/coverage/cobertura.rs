@@ -0,0 +1,139 @@
+//! A minimal reader for Cobertura XML coverage reports.
+//!
+//! Only the elements coverage tools need are read: `<source>` directories,
+//! `<class filename=...>`, and the `<line number=... hits=...>` entries
+//! directly inside a class. Lines listed under `<method>` elements repeat
+//! the class's lines and are skipped.
+
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+
+/// The parts of a Cobertura report that omnitype uses.
+#[derive(Debug, Default)]
+pub(super) struct Report {
+    /// Directories that relative file names are resolved against.
+    pub sources: Vec<String>,
+    /// Hits per 1-based line, keyed by file name as written in the report.
+    pub files: BTreeMap<String, BTreeMap<usize, u64>>,
+}
+
+pub(super) fn parse(xml: &str) -> Result<Report> {
+    let invalid = |what: &str| Error::parser_error(format!("Invalid coverage XML: {}", what));
+    let mut report = Report::default();
+    let mut class: Option<String> = None;
+    let mut in_method = false;
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        let after = &rest[open + 1..];
+        // Comments, declarations, and processing instructions.
+        if after.starts_with('!') || after.starts_with('?') {
+            let end = if after.starts_with("!--") { "-->" } else { ">" };
+            let close = after
+                .find(end)
+                .ok_or_else(|| invalid("unterminated markup"))?;
+            rest = &after[close + end.len()..];
+            continue;
+        }
+        let close = after.find('>').ok_or_else(|| invalid("unterminated tag"))?;
+        let tag = &after[..close];
+        rest = &after[close + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            match name.trim() {
+                "class" => class = None,
+                "method" => in_method = false,
+                _ => {},
+            }
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        match name {
+            "source" if !self_closing => {
+                let end = rest
+                    .find("</source>")
+                    .ok_or_else(|| invalid("unclosed <source>"))?;
+                report.sources.push(unescape(rest[..end].trim()));
+                rest = &rest[end..];
+            },
+            "class" => {
+                let filename = attribute(attributes, "filename")
+                    .ok_or_else(|| invalid("<class> without filename"))?;
+                report.files.entry(filename.clone()).or_default();
+                class = (!self_closing).then_some(filename);
+            },
+            "method" => in_method = !self_closing,
+            "line" if !in_method => {
+                let Some(file) = &class else {
+                    continue;
+                };
+                let number = attribute(attributes, "number").and_then(|n| n.parse::<usize>().ok());
+                let hits = attribute(attributes, "hits").and_then(|h| h.parse::<u64>().ok());
+                if let (Some(number), Some(hits)) = (number, hits) {
+                    let lines = report.files.entry(file.clone()).or_default();
+                    *lines.entry(number).or_default() += hits;
+                }
+            },
+            _ => {},
+        }
+    }
+    Ok(report)
+}
+
+/// The unescaped value of an attribute in a tag's attribute list.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+        let end = value[1..].find(quote)? + 1;
+        if key == name {
+            return Some(unescape(&value[1..end]));
+        }
+        rest = &value[end + 1..];
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cobertura() {
+        let report = parse(
+            r#"<?xml version="1.0" ?>
+            <!-- Generated by coverage.py -->
+            <coverage version="7.4">
+              <sources><source>/work/R&amp;D</source></sources>
+              <packages><package name="pkg"><classes>
+                <class name="a.py" filename="pkg/a.py">
+                  <methods><method name="f"><lines><line number="2" hits="9"/></lines></method></methods>
+                  <lines>
+                    <line number="1" hits="1"/>
+                    <line number="2" hits="3"/>
+                    <line number="4" hits="0" branch="true"/>
+                  </lines>
+                </class>
+                <class name="empty.py" filename="pkg/empty.py"/>
+              </classes></package></packages>
+            </coverage>"#,
+        )
+        .unwrap();
+        assert_eq!(report.sources, ["/work/R&D"]);
+        let a = &report.files["pkg/a.py"];
+        assert_eq!(a.iter().map(|(&l, &h)| (l, h)).collect::<Vec<_>>(), [(1, 1), (2, 3), (4, 0)]);
+        assert!(report.files["pkg/empty.py"].is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+//! Line coverage data from coverage.py, used to rank unannotated code by how
+//! much of it actually runs.
+//!
+//! Three formats are accepted:
+//!
+//! - `coverage.xml` (Cobertura, from `coverage xml`)
+//! - `coverage.json` (from `coverage json`)
+//! - a `.coverage` data file, which is converted by running
+//!   `python -m coverage json` with the project's interpreter
+
+mod cobertura;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::analyzer::FileStats;
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Result};
+use crate::utils::select_interpreter;
+
+/// Execution counts for the lines of one file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// Hits per 1-based line number, for every line that was measured.
+    pub lines: BTreeMap<usize, u64>,
+}
+
+impl FileCoverage {
+    /// Returns true if the 1-based `line` ran at least once.
+    pub fn is_executed(&self, line: usize) -> bool {
+        self.lines.get(&line).is_some_and(|&hits| hits > 0)
+    }
+
+    /// Number of executed lines between the 1-based `first` and `last`,
+    /// inclusive.
+    pub fn executed_between(&self, first: usize, last: usize) -> usize {
+        self.lines
+            .range(first..=last)
+            .filter(|(_, &hits)| hits > 0)
+            .count()
+    }
+}
+
+/// Coverage for a set of files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    files: Vec<(PathBuf, FileCoverage)>,
+}
+
+impl Coverage {
+    /// Loads a coverage report, choosing the format from the file name.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let coverage = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("xml") => Self::from_cobertura(&fs::read_to_string(path)?),
+            Some("json") => Self::from_json(&fs::read_to_string(path)?),
+            _ => Self::from_data_file(path),
+        };
+        coverage.map_err(|e| e.with_path(path))
+    }
+
+    /// Parses a Cobertura XML report. Relative file names are resolved
+    /// against the report's first `<source>` directory.
+    pub fn from_cobertura(xml: &str) -> Result<Self> {
+        let report = cobertura::parse(xml)?;
+        let root = report.sources.first().map(PathBuf::from);
+        let files = report
+            .files
+            .into_iter()
+            .map(|(name, lines)| {
+                let path = match &root {
+                    Some(root) if Path::new(&name).is_relative() => root.join(name),
+                    _ => PathBuf::from(name),
+                };
+                (path, FileCoverage { lines })
+            })
+            .collect();
+        Ok(Self { files })
+    }
+
+    /// Parses the report written by `coverage json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Report {
+            files: BTreeMap<String, FileReport>,
+        }
+        #[derive(Deserialize)]
+        struct FileReport {
+            #[serde(default)]
+            executed_lines: Vec<usize>,
+            #[serde(default)]
+            missing_lines: Vec<usize>,
+        }
+        let report: Report = serde_json::from_str(json)
+            .map_err(|e| Error::parser_error(format!("Invalid coverage JSON: {}", e)))?;
+        let files = report
+            .files
+            .into_iter()
+            .map(|(name, file)| {
+                let executed = file.executed_lines.into_iter().map(|line| (line, 1));
+                let missing = file.missing_lines.into_iter().map(|line| (line, 0));
+                (PathBuf::from(name), FileCoverage { lines: missing.chain(executed).collect() })
+            })
+            .collect();
+        Ok(Self { files })
+    }
+
+    /// Converts a `.coverage` data file with coverage.py, which must be
+    /// installed for the project's interpreter.
+    pub fn from_data_file(path: &Path) -> Result<Self> {
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+        let dir = dir.unwrap_or(Path::new("."));
+        let output = Command::new(select_interpreter(path))
+            .args(["-m", "coverage", "json", "-o", "-", "--data-file"])
+            .arg(path)
+            .current_dir(dir)
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::Other(format!(
+                "coverage json failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let mut coverage = Self::from_json(&String::from_utf8_lossy(&output.stdout))?;
+        for (file, _) in &mut coverage.files {
+            if file.is_relative() {
+                *file = dir.join(&*file);
+            }
+        }
+        Ok(coverage)
+    }
+
+    /// The coverage recorded for `path`. Reports are often written on
+    /// another machine or from another directory, so files are matched by
+    /// the longest common suffix of their path components, which must at
+    /// least include the file name. `None` when several files match
+    /// equally well, such as two `__init__.py` files that only share
+    /// their name with `path`.
+    pub fn file(&self, path: &Path) -> Option<&FileCoverage> {
+        let wanted = normal_components(path);
+        let mut best: Option<(usize, &FileCoverage)> = None;
+        let mut tied = false;
+        for (file, coverage) in &self.files {
+            let have = normal_components(file);
+            let common = wanted
+                .iter()
+                .rev()
+                .zip(have.iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            match best {
+                _ if common == 0 => {},
+                Some((longest, _)) if common < longest => {},
+                Some((longest, _)) if common == longest => tied = true,
+                _ => {
+                    best = Some((common, coverage));
+                    tied = false;
+                },
+            }
+        }
+        best.filter(|_| !tied).map(|(_, coverage)| coverage)
+    }
+
+    /// Number of files in the report.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if the report has no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Keeps the diagnostics in code that ran: inside a function whose body
+/// has an executed line or, outside functions, on an executed line.
+/// Diagnostics in files without coverage are all dropped.
+pub fn retain_covered(
+    diagnostics: &mut Vec<Diagnostic>,
+    stats: &FileStats,
+    coverage: Option<&FileCoverage>,
+) {
+    let Some(coverage) = coverage else {
+        diagnostics.clear();
+        return;
+    };
+    diagnostics.retain(|d| {
+        let line = d.span.start.line;
+        match stats.innermost_function(line) {
+            Some(function) => function.executed_lines_in(coverage) > 0,
+            None => coverage.is_executed(line + 1),
+        }
+    });
+}
+
+fn normal_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_report_and_suffix_matching() {
+        let coverage = Coverage::from_json(
+            r#"{"meta": {}, "files": {
+                "src/pkg/a.py": {"executed_lines": [1, 2, 5], "missing_lines": [3]},
+                "tests/a.py": {"executed_lines": [1]}
+            }}"#,
+        )
+        .unwrap();
+        assert_eq!(coverage.len(), 2);
+
+        let file = coverage
+            .file(Path::new("/work/project/src/pkg/a.py"))
+            .unwrap();
+        assert!(file.is_executed(2));
+        assert!(!file.is_executed(3));
+        assert_eq!(file.executed_between(2, 5), 2);
+        let tests = coverage.file(Path::new("tests/a.py")).unwrap();
+        assert_eq!(tests.lines.len(), 1);
+        assert!(coverage.file(Path::new("src/pkg/b.py")).is_none());
+        // Only the name is shared, with both files.
+        assert!(coverage.file(Path::new("other/a.py")).is_none());
+
+        let coverage =
+            Coverage::from_json(r#"{"files": {"src/util.py": {"executed_lines": [1]}}}"#).unwrap();
+        assert!(coverage.file(Path::new("/elsewhere/util.py")).is_some());
+    }
+}
@@ -0,0 +1,238 @@
+//! A long-running check server, in the spirit of `dmypy`.
+//!
+//! `omnitype daemon start` runs a process that keeps every checked file's
+//! analysis in memory, and `omnitype daemon check` asks it for diagnostics
+//! instead of starting from scratch. Files are re-analyzed only when their
+//! content hash changes or a module they import, directly or through other
+//! modules, changes (see [`IncrementalAnalysis`]); custom rules and the
+//! configuration are re-applied on every request so edits to them take
+//! effect immediately.
+//!
+//! Clients speak JSON-RPC with `Content-Length` framing, as in the language
+//! server, over a unix socket. The methods are:
+//!
+//! - `status`: the daemon's [`Status`]
+//! - `check` with [`CheckParams`]: checks the paths and remembers them
+//! - `recheck`: checks the remembered paths again
+//! - `shutdown`: stops the daemon after responding
+
+mod socket;
+
+pub use socket::{serve, Client};
+
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::analyzer::{AnalysisResult, IncrementalAnalysis};
+use crate::error::{Error, Result};
+use crate::lsp::{codes, read_message, write_message, Message};
+use crate::project::Project;
+
+/// Socket path used when none is given, relative to the working directory.
+pub const DEFAULT_SOCKET: &str = ".omnitype.sock";
+
+/// Parameters of `check`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckParams {
+    /// Files or directories to check; relative paths are resolved against
+    /// the daemon's working directory.
+    pub paths: Vec<PathBuf>,
+}
+
+/// The result of `check` and `recheck`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckResponse {
+    /// Analysis results for each file, sorted by path.
+    pub results: Vec<AnalysisResult>,
+    /// Files that were analyzed again because they or a module they import
+    /// are new or changed.
+    pub analyzed: Vec<String>,
+    /// Paths or files that couldn't be checked.
+    pub errors: Vec<String>,
+    /// Whether any diagnostic reaches the configured `fail_on` severity.
+    pub failed: bool,
+}
+
+/// The result of `status`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    /// Process id of the daemon.
+    pub pid: u32,
+    /// Directory relative paths are resolved against.
+    pub root: PathBuf,
+    /// Seconds since the daemon started.
+    pub uptime_secs: u64,
+    /// Number of files with a cached analysis.
+    pub cached_files: usize,
+    /// Paths `recheck` checks.
+    pub paths: Vec<PathBuf>,
+}
+
+/// The daemon's state: cached analyses and the last checked paths.
+#[derive(Debug)]
+pub struct Daemon {
+    root: PathBuf,
+    started: Instant,
+    cache: IncrementalAnalysis,
+    paths: Vec<PathBuf>,
+    shutdown: bool,
+}
+
+impl Daemon {
+    /// Creates a daemon resolving relative paths against `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            started: Instant::now(),
+            cache: IncrementalAnalysis::new(),
+            paths: Vec::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Returns true once a client has sent `shutdown`.
+    pub fn is_shut_down(&self) -> bool {
+        self.shutdown
+    }
+
+    /// Serves one client connection until it closes or sends `shutdown`.
+    pub fn serve<R: BufRead, W: Write>(&mut self, mut reader: R, mut writer: W) -> Result<()> {
+        while let Some(message) = read_message(&mut reader)? {
+            if let Some(response) = self.handle(message) {
+                write_message(&mut writer, &response)?;
+            }
+            if self.shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one message, returning the response for requests.
+    pub fn handle(&mut self, message: Message) -> Option<Message> {
+        let method = message.method.clone()?;
+        let id = message.id.clone()?;
+        let result = match method.as_str() {
+            "status" => to_value(self.status()),
+            "check" => message
+                .params()
+                .and_then(|p: CheckParams| to_value(self.check(p.paths))),
+            "recheck" => to_value(self.recheck()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            },
+            _ => {
+                let text = format!("Unsupported method: {}", method);
+                return Some(Message::error_response(id, codes::METHOD_NOT_FOUND, text));
+            },
+        };
+        Some(match result {
+            Ok(result) => Message::response(id, result),
+            Err(e) => {
+                let code = match e {
+                    Error::Argument(_) => codes::INVALID_PARAMS,
+                    _ => codes::INTERNAL_ERROR,
+                };
+                Message::error_response(id, code, e.to_string())
+            },
+        })
+    }
+
+    /// The daemon's current state.
+    pub fn status(&self) -> Status {
+        Status {
+            pid: std::process::id(),
+            root: self.root.clone(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            cached_files: self.cache.len(),
+            paths: self.paths.clone(),
+        }
+    }
+
+    /// Checks `paths`, re-analyzing only files that are new or depend on a
+    /// changed file, and remembers them for [`Daemon::recheck`].
+    pub fn check(&mut self, paths: Vec<PathBuf>) -> CheckResponse {
+        self.paths = paths;
+        self.recheck()
+    }
+
+    /// Checks the paths of the last `check` again.
+    pub fn recheck(&mut self) -> CheckResponse {
+        let mut response = CheckResponse::default();
+        let paths: Vec<PathBuf> = self.paths.iter().map(|p| self.root.join(p)).collect();
+        for path in paths {
+            if let Err(e) = self.check_path(&path, &mut response) {
+                response.errors.push(e.to_string());
+            }
+        }
+        // Forget files that have been deleted since they were cached.
+        self.cache.prune();
+        response.results.sort_by(|a, b| a.path.cmp(&b.path));
+        response
+    }
+
+    fn check_path(&mut self, path: &Path, response: &mut CheckResponse) -> Result<()> {
+        let report = Project::discover(path)?.check_incremental(&mut self.cache)?;
+        response.results.extend(report.results);
+        response
+            .analyzed
+            .extend(report.analyzed.iter().map(|p| p.display().to_string()));
+        response
+            .errors
+            .extend(report.errors.iter().map(|e| e.to_string()));
+        response.failed |= report.failed;
+        Ok(())
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value> {
+    serde_json::to_value(value).map_err(|e| Error::Other(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn request(daemon: &mut Daemon, method: &str, params: Value) -> Value {
+        let response = daemon.handle(Message::request(1, method, params)).unwrap();
+        assert_eq!(response.error, None);
+        response.result.unwrap()
+    }
+
+    #[test]
+    fn test_check_reuses_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.py"), "def f(x):\n    pass\n").unwrap();
+        fs::write(dir.path().join("b.py"), "def g(y: int) -> None:\n    pass\n").unwrap();
+        let mut daemon = Daemon::new(dir.path());
+
+        let first: CheckResponse =
+            serde_json::from_value(request(&mut daemon, "check", json!({ "paths": ["."] })))
+                .unwrap();
+        assert_eq!(first.results.len(), 2);
+        assert_eq!(first.analyzed.len(), 2);
+        assert!(first.failed);
+
+        fs::write(dir.path().join("a.py"), "def f(x: int) -> None:\n    pass\n").unwrap();
+        let second: CheckResponse =
+            serde_json::from_value(request(&mut daemon, "recheck", Value::Null)).unwrap();
+        assert_eq!(second.analyzed.len(), 1);
+        assert!(second.analyzed[0].ends_with("a.py"));
+        assert!(!second.failed);
+
+        let status: Status =
+            serde_json::from_value(request(&mut daemon, "status", Value::Null)).unwrap();
+        assert_eq!(status.cached_files, 2);
+        assert_eq!(status.paths, [PathBuf::from(".")]);
+
+        request(&mut daemon, "shutdown", Value::Null);
+        assert!(daemon.is_shut_down());
+    }
+}
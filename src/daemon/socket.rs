@@ -0,0 +1,183 @@
+//! The unix socket transport between the daemon and its clients.
+//!
+//! Other platforms have no socket support yet: Windows would need a named
+//! pipe transport, which isn't written. [`serve`] and [`Client::connect`]
+//! return [`Error::NotImplemented`] there.
+
+#[cfg(unix)]
+use std::io::BufReader;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+#[cfg(unix)]
+use std::time::Duration;
+
+use serde_json::Value;
+
+use super::Daemon;
+use crate::error::{Error, Result};
+#[cfg(unix)]
+use crate::lsp::{read_message, write_message, Message};
+
+/// How long a connected client may stay silent before the daemon drops it
+/// to serve the next one.
+#[cfg(unix)]
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Listens on `socket` and serves one client at a time until a client sends
+/// `shutdown`. A socket file left behind by a daemon that died is replaced;
+/// one that still accepts connections is an error. Clients that go quiet
+/// for [`CLIENT_TIMEOUT`] are disconnected, so one that hangs can't lock
+/// the others out.
+#[cfg(unix)]
+pub fn serve(socket: &Path, daemon: &mut Daemon) -> Result<()> {
+    serve_with_timeout(socket, daemon, CLIENT_TIMEOUT)
+}
+
+#[cfg(unix)]
+fn serve_with_timeout(socket: &Path, daemon: &mut Daemon, timeout: Duration) -> Result<()> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(Error::invalid_value(
+                "socket",
+                socket.display().to_string(),
+                "a daemon is already listening",
+            ));
+        }
+        std::fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Failed to accept a connection: {}", e);
+                continue;
+            },
+        };
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let reader = BufReader::new(stream.try_clone()?);
+        // A misbehaving client only loses its own connection.
+        if let Err(e) = daemon.serve(reader, &stream) {
+            log::warn!("Client connection failed: {}", e);
+        }
+        if daemon.is_shut_down() {
+            break;
+        }
+    }
+    std::fs::remove_file(socket)?;
+    Ok(())
+}
+
+/// Listens on `socket`; unsupported on this platform.
+#[cfg(not(unix))]
+pub fn serve(_socket: &Path, _daemon: &mut Daemon) -> Result<()> {
+    Err(Error::not_implemented("daemon sockets on this platform"))
+}
+
+/// A connection to a running daemon.
+#[derive(Debug)]
+pub struct Client {
+    #[cfg(unix)]
+    reader: BufReader<UnixStream>,
+    #[cfg(unix)]
+    writer: UnixStream,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connects to the daemon listening on `socket`.
+    #[cfg(unix)]
+    pub fn connect(socket: &Path) -> Result<Self> {
+        let writer = UnixStream::connect(socket).map_err(|e| {
+            Error::Other(format!("No daemon listening on {}: {}", socket.display(), e))
+        })?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer, next_id: 1 })
+    }
+
+    /// Connects to the daemon listening on `socket`; unsupported on this
+    /// platform.
+    #[cfg(not(unix))]
+    pub fn connect(_socket: &Path) -> Result<Self> {
+        Err(Error::not_implemented("daemon sockets on this platform"))
+    }
+
+    /// Sends a request and waits for its result. Error responses are
+    /// returned as [`Error::Other`].
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.exchange(id, method, params)
+    }
+
+    #[cfg(unix)]
+    fn exchange(&mut self, id: u64, method: &str, params: Value) -> Result<Value> {
+        write_message(&mut self.writer, &Message::request(id, method, params))?;
+        let response = read_message(&mut self.reader)?
+            .ok_or_else(|| Error::Other("The daemon closed the connection".to_string()))?;
+        match response.error {
+            Some(error) => Err(Error::Other(error.message)),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn exchange(&mut self, _id: u64, _method: &str, _params: Value) -> Result<Value> {
+        Err(Error::not_implemented("daemon sockets on this platform"))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_client_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("d.sock");
+        let root = dir.path().to_path_buf();
+        let server = {
+            let socket = socket.clone();
+            std::thread::spawn(move || serve(&socket, &mut Daemon::new(root)))
+        };
+        let mut client = loop {
+            match Client::connect(&socket) {
+                Ok(client) => break client,
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        };
+        let status = client.request("status", Value::Null).unwrap();
+        assert_eq!(status["pid"], json!(std::process::id()));
+        assert!(client.request("bogus", Value::Null).is_err());
+        client.request("shutdown", Value::Null).unwrap();
+        server.join().unwrap().unwrap();
+        assert!(!socket.exists());
+    }
+
+    #[test]
+    fn test_silent_client_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("d.sock");
+        let root = dir.path().to_path_buf();
+        let server = {
+            let socket = socket.clone();
+            let timeout = Duration::from_millis(50);
+            std::thread::spawn(move || serve_with_timeout(&socket, &mut Daemon::new(root), timeout))
+        };
+        let silent = loop {
+            match UnixStream::connect(&socket) {
+                Ok(stream) => break stream,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        // Served once the silent client is dropped, while it stays connected.
+        let mut client = Client::connect(&socket).unwrap();
+        client.request("status", Value::Null).unwrap();
+        client.request("shutdown", Value::Null).unwrap();
+        server.join().unwrap().unwrap();
+        drop(silent);
+    }
+}
@@ -0,0 +1,421 @@
+//! Third-party dependency surface: which installed packages a project
+//! imports, whether type information is available for each, and which calls
+//! into them are untyped for lack of it.
+//!
+//! Imports of the standard library and of the project's own modules are
+//! ignored. For every other top-level package, type information is looked
+//! for in this order:
+//!
+//! 1. a `<package>-stubs` stub-only package (PEP 561), e.g. from `types-*`
+//! 2. a `py.typed` marker in the installed package
+//! 3. `.pyi` files shipped next to the installed package's modules
+//! 4. a typeshed checkout's `stubs/` directory, when one is given
+
+mod stdlib;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::parser::{Language, Parser};
+use crate::utils::{infer_module_name, PythonEnvironment, SourceFile, SourceWalker, Span};
+
+/// Where type information for a package comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "path", rename_all = "kebab-case")]
+pub enum StubSource {
+    /// A `<package>-stubs` directory in `site-packages`.
+    StubPackage(PathBuf),
+    /// The installed package has a `py.typed` marker.
+    PyTyped(PathBuf),
+    /// The installed package ships `.pyi` files.
+    InlineStubs(PathBuf),
+    /// A directory under typeshed's `stubs/`.
+    Typeshed(PathBuf),
+    /// No type information was found.
+    Missing,
+}
+
+impl StubSource {
+    /// Returns true if type information was found.
+    pub fn is_typed(&self) -> bool {
+        !matches!(self, Self::Missing)
+    }
+
+    /// A short label for tables.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::StubPackage(_) => "stub package",
+            Self::PyTyped(_) => "py.typed",
+            Self::InlineStubs(_) => ".pyi",
+            Self::Typeshed(_) => "typeshed",
+            Self::Missing => "missing",
+        }
+    }
+}
+
+/// A call whose callee comes from a package without type information, so
+/// its result is `Any`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallSite {
+    /// File containing the call.
+    pub path: String,
+    /// Span of the called expression.
+    pub span: Span,
+    /// The called expression as written, e.g. `requests.get`.
+    pub callee: String,
+}
+
+/// One imported third-party package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dependency {
+    /// Top-level import name.
+    pub package: String,
+    /// Name of the installed distribution that provides it, if known.
+    pub distribution: Option<String>,
+    /// Installed version of that distribution.
+    pub version: Option<String>,
+    /// Whether the package was found in the Python environment.
+    pub installed: bool,
+    /// Where type information comes from.
+    pub stubs: StubSource,
+    /// Files that import the package.
+    pub imported_by: Vec<String>,
+    /// Calls into the package that are untyped because stubs are missing.
+    pub untyped_calls: Vec<CallSite>,
+}
+
+/// The third-party packages a project imports.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepsReport {
+    /// Python environment the packages were looked up in.
+    pub environment: Option<PathBuf>,
+    /// Imported packages, sorted by name.
+    pub dependencies: Vec<Dependency>,
+}
+
+impl DepsReport {
+    /// The packages without type information.
+    pub fn missing_stubs(&self) -> impl Iterator<Item = &Dependency> {
+        self.dependencies.iter().filter(|d| !d.stubs.is_typed())
+    }
+}
+
+/// Scans a project for imported third-party packages.
+pub struct DepsAnalysis {
+    path: PathBuf,
+    environment: Option<PythonEnvironment>,
+    typeshed: Option<PathBuf>,
+    exclude: Vec<String>,
+}
+
+impl DepsAnalysis {
+    /// Creates a scan of the Python files under `path`, looking packages up
+    /// in the environment discovered for it. Fails when the configuration
+    /// that applies to `path` is invalid.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let root = if path.is_file() { path.parent().unwrap_or(&path) } else { &path };
+        let environment = PythonEnvironment::discover(root);
+        let exclude = Config::discover(&path)?.exclude;
+        Ok(Self { path, environment, typeshed: None, exclude })
+    }
+
+    /// Looks packages up in this environment instead.
+    pub fn environment(mut self, environment: Option<PythonEnvironment>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Also accepts stubs from a typeshed checkout.
+    pub fn typeshed(mut self, typeshed: impl Into<PathBuf>) -> Self {
+        self.typeshed = Some(typeshed.into());
+        self
+    }
+
+    /// Runs the scan.
+    pub fn run(&self) -> Result<DepsReport> {
+        let files: Vec<PathBuf> = if self.path.is_file() {
+            vec![self.path.clone()]
+        } else {
+            SourceWalker::new(&self.path)
+                .exclude(self.exclude.clone())
+                .files()?
+                .filter(|f| Language::from_path(f) == Some(Language::Python))
+                .collect()
+        };
+        // Top-level names of the project's own modules and packages.
+        let local: BTreeSet<String> = files
+            .iter()
+            .filter_map(|f| infer_module_name(f))
+            .filter_map(|name| name.split('.').next().map(str::to_string))
+            .collect();
+
+        let mut parser = Parser::new()?;
+        let mut imports: BTreeMap<String, (BTreeSet<String>, Vec<CallSite>)> = BTreeMap::new();
+        for path in &files {
+            let file = SourceFile::read(path)?;
+            let tree = parser.parse_string(file.text())?;
+            let scan = scan_file(&tree.root_node(), &file);
+            for (package, calls) in scan {
+                if stdlib::is_stdlib(&package) || local.contains(&package) {
+                    continue;
+                }
+                let entry = imports.entry(package).or_default();
+                entry.0.insert(file.path().to_string());
+                entry.1.extend(calls);
+            }
+        }
+
+        let distributions = match &self.environment {
+            Some(env) => env.distributions()?,
+            None => Vec::new(),
+        };
+        let dependencies = imports
+            .into_iter()
+            .map(|(package, (imported_by, calls))| {
+                let distribution = distributions
+                    .iter()
+                    .find(|d| d.top_level.contains(&package));
+                let stubs = self.find_stubs(&package);
+                Dependency {
+                    installed: self.installed(&package),
+                    distribution: distribution.map(|d| d.name.clone()),
+                    version: distribution.map(|d| d.version.clone()),
+                    untyped_calls: if stubs.is_typed() { Vec::new() } else { calls },
+                    stubs,
+                    imported_by: imported_by.into_iter().collect(),
+                    package,
+                }
+            })
+            .collect();
+        Ok(DepsReport {
+            environment: self.environment.as_ref().map(|env| env.prefix.clone()),
+            dependencies,
+        })
+    }
+
+    fn site_packages(&self) -> &[PathBuf] {
+        self.environment
+            .as_ref()
+            .map_or(&[], |env| env.site_packages.as_slice())
+    }
+
+    fn installed(&self, package: &str) -> bool {
+        self.site_packages().iter().any(|dir| {
+            dir.join(package).is_dir()
+                || dir.join(format!("{}.py", package)).is_file()
+                || has_extension_module(dir, package)
+        })
+    }
+
+    fn find_stubs(&self, package: &str) -> StubSource {
+        for dir in self.site_packages() {
+            let stubs = dir.join(format!("{}-stubs", package));
+            if stubs.is_dir() {
+                return StubSource::StubPackage(stubs);
+            }
+        }
+        for dir in self.site_packages() {
+            let installed = dir.join(package);
+            if installed.join("py.typed").is_file() {
+                return StubSource::PyTyped(installed);
+            }
+            if installed.join("__init__.pyi").is_file() {
+                return StubSource::InlineStubs(installed);
+            }
+            let module = dir.join(format!("{}.pyi", package));
+            if module.is_file() {
+                return StubSource::InlineStubs(module);
+            }
+        }
+        if let Some(found) = self
+            .typeshed
+            .as_deref()
+            .and_then(|t| typeshed_stubs(t, package))
+        {
+            return StubSource::Typeshed(found);
+        }
+        StubSource::Missing
+    }
+}
+
+/// Compiled modules are named like `pkg.cpython-311-x86_64-linux-gnu.so`.
+fn has_extension_module(dir: &Path, package: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    let prefix = format!("{}.", package);
+    entries.filter_map(|e| e.ok()).any(|entry| {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        name.starts_with(&prefix) && (name.ends_with(".so") || name.ends_with(".pyd"))
+    })
+}
+
+/// Finds `stubs/<distribution>/<package>` or `<package>.pyi` in typeshed.
+fn typeshed_stubs(typeshed: &Path, package: &str) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(typeshed.join("stubs")).ok()?;
+    let mut dirs: Vec<PathBuf> = entries.filter_map(|e| Some(e.ok()?.path())).collect();
+    dirs.sort();
+    dirs.into_iter().find_map(|dist| {
+        [dist.join(package), dist.join(format!("{}.pyi", package))]
+            .into_iter()
+            .find(|path| path.exists())
+    })
+}
+
+/// The top-level packages a file imports absolutely, with the calls made
+/// through names bound by those imports.
+fn scan_file(root: &Node, file: &SourceFile) -> HashMap<String, Vec<CallSite>> {
+    let mut bindings: HashMap<String, String> = HashMap::new();
+    let mut packages: HashMap<String, Vec<CallSite>> = HashMap::new();
+    let mut stack = vec![*root];
+    let mut calls = Vec::new();
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "import_statement" => {
+                let mut cursor = node.walk();
+                for name in node.children_by_field_name("name", &mut cursor) {
+                    let (dotted, alias) = match name.kind() {
+                        "aliased_import" => {
+                            (name.child_by_field_name("name"), name.child_by_field_name("alias"))
+                        },
+                        _ => (Some(name), None),
+                    };
+                    let Some(dotted) = dotted else {
+                        continue;
+                    };
+                    let module = file.slice(&file.node_span(&dotted));
+                    let package = top_level(module).to_string();
+                    // `import a.b` binds `a`; `import a.b as c` binds `c`.
+                    let bound = match alias {
+                        Some(alias) => file.slice(&file.node_span(&alias)).to_string(),
+                        None => package.clone(),
+                    };
+                    bindings.insert(bound, package.clone());
+                    packages.entry(package).or_default();
+                }
+            },
+            "import_from_statement" => {
+                let Some(module) = node.child_by_field_name("module_name") else {
+                    continue;
+                };
+                let module = file.slice(&file.node_span(&module));
+                if module.starts_with('.') {
+                    continue;
+                }
+                let package = top_level(module).to_string();
+                let mut cursor = node.walk();
+                for name in node.children_by_field_name("name", &mut cursor) {
+                    let bound = match name.kind() {
+                        "aliased_import" => name.child_by_field_name("alias"),
+                        _ => Some(name),
+                    };
+                    if let Some(bound) = bound {
+                        let bound = file.slice(&file.node_span(&bound)).to_string();
+                        bindings.insert(bound, package.clone());
+                    }
+                }
+                packages.entry(package).or_default();
+            },
+            "call" => calls.push(node),
+            _ => {},
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+
+    calls.sort_by_key(|call| call.start_byte());
+    for call in calls {
+        let Some(function) = call.child_by_field_name("function") else {
+            continue;
+        };
+        let mut base = function;
+        while base.kind() == "attribute" {
+            match base.child_by_field_name("object") {
+                Some(object) => base = object,
+                None => break,
+            }
+        }
+        if base.kind() != "identifier" {
+            continue;
+        }
+        let name = file.slice(&file.node_span(&base));
+        if let Some(package) = bindings.get(name) {
+            let span = file.node_span(&function);
+            packages.entry(package.clone()).or_default().push(CallSite {
+                path: file.path().to_string(),
+                callee: file.slice(&span).to_string(),
+                span,
+            });
+        }
+    }
+    packages
+}
+
+fn top_level(module: &str) -> &str {
+    module.split('.').next().unwrap_or(module).trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_deps_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("app")).unwrap();
+        fs::write(project.join("app/__init__.py"), "").unwrap();
+        fs::write(
+            project.join("app/main.py"),
+            "import os\nimport requests as rq\nfrom typedpkg import thing\n\
+             from app import util\nfrom . import sibling\n\n\
+             rq.get('u').json()\nthing()\nos.getcwd()\n",
+        )
+        .unwrap();
+
+        let site = dir.path().join("env/lib/python3.11/site-packages");
+        fs::create_dir_all(site.join("requests")).unwrap();
+        fs::create_dir_all(site.join("typedpkg")).unwrap();
+        fs::write(site.join("typedpkg/py.typed"), "").unwrap();
+        let dist = site.join("requests-2.31.0.dist-info");
+        fs::create_dir_all(&dist).unwrap();
+        fs::write(dist.join("METADATA"), "Name: requests\nVersion: 2.31.0\n").unwrap();
+        fs::write(dist.join("top_level.txt"), "requests\n").unwrap();
+
+        let env = PythonEnvironment::from_prefix(dir.path().join("env"));
+        let report = DepsAnalysis::new(&project)
+            .unwrap()
+            .environment(env)
+            .run()
+            .unwrap();
+        let names: Vec<&str> = report
+            .dependencies
+            .iter()
+            .map(|d| d.package.as_str())
+            .collect();
+        assert_eq!(names, ["requests", "typedpkg"]);
+
+        let requests = &report.dependencies[0];
+        assert!(requests.installed);
+        assert_eq!(requests.version.as_deref(), Some("2.31.0"));
+        assert_eq!(requests.stubs, StubSource::Missing);
+        let callees: Vec<&str> = requests
+            .untyped_calls
+            .iter()
+            .map(|c| c.callee.as_str())
+            .collect();
+        assert_eq!(callees, ["rq.get"]);
+
+        let typed = &report.dependencies[1];
+        assert!(matches!(typed.stubs, StubSource::PyTyped(_)));
+        assert!(typed.untyped_calls.is_empty());
+        assert_eq!(report.missing_stubs().count(), 1);
+    }
+}
@@ -0,0 +1,63 @@
+//! Line-oriented exports for spreadsheets and data pipelines.
+
+use super::Diagnostic;
+use crate::utils::csv_record;
+
+/// Column names of [`to_csv`]. Lines and columns are 1-based.
+pub const CSV_COLUMNS: [&str; 8] =
+    ["path", "line", "column", "end_line", "end_column", "severity", "code", "message"];
+
+/// Formats diagnostics as CSV with a header row, one record per diagnostic.
+pub fn to_csv(diagnostics: &[Diagnostic]) -> String {
+    let mut out = csv_record(CSV_COLUMNS);
+    for d in diagnostics {
+        out.push_str(&csv_record([
+            d.path.clone(),
+            (d.span.start.line + 1).to_string(),
+            (d.span.start.column + 1).to_string(),
+            (d.span.end.line + 1).to_string(),
+            (d.span.end.column + 1).to_string(),
+            d.severity.to_string(),
+            d.code.clone().unwrap_or_default(),
+            d.message.clone(),
+        ]));
+    }
+    out
+}
+
+/// Formats diagnostics as JSON Lines: each line is one diagnostic in the
+/// same shape as the JSON output.
+pub fn to_jsonl(diagnostics: &[Diagnostic]) -> serde_json::Result<String> {
+    let mut out = String::new();
+    for d in diagnostics {
+        out.push_str(&serde_json::to_string(d)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Span;
+
+    #[test]
+    fn test_csv_and_jsonl() {
+        let diagnostics = [
+            Diagnostic::warning("a.py", Span::point(0, 4), "Missing 'x', maybe").with_code("OT001"),
+            Diagnostic::error("b.py", Span::point(2, 0), "Bad"),
+        ];
+        let csv = to_csv(&diagnostics);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "path,line,column,end_line,end_column,severity,code,message");
+        assert_eq!(lines[1], "a.py,1,5,1,5,warning,OT001,\"Missing 'x', maybe\"");
+        assert_eq!(lines[2], "b.py,3,1,3,1,error,,Bad");
+
+        let jsonl = to_jsonl(&diagnostics).unwrap();
+        let back: Vec<Diagnostic> = jsonl
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(back, diagnostics);
+    }
+}
@@ -0,0 +1,39 @@
+# English (US) diagnostic messages.
+#
+# Each entry maps a message identifier to its text. Arguments are written as
+# placeables, e.g. `{ $name }`. Translations provide a file with the same
+# identifiers; missing entries fall back to this catalog.
+
+unannotated-parameter = Missing type annotation for parameter '{ $name }'
+unannotated-return = Missing return type annotation for '{ $name }'
+missing-jsdoc = Missing JSDoc comment for function '{ $name }'
+doctest-argument-type = Doctest passes { $found } to parameter '{ $name }' of '{ $function }',
+    which expects { $expected }
+doctest-return-type = Doctest expects '{ $function }' to return { $found }, but it returns
+    { $expected }
+missing-argument = Call to '{ $function }' is missing an argument for parameter '{ $name }'
+too-many-arguments = '{ $function }' takes { $expected } positional arguments, but { $found }
+    were given
+unknown-keyword = '{ $function }' has no parameter named '{ $name }'
+argument-type = Call passes { $found } to parameter '{ $name }' of '{ $function }', which
+    expects { $expected }
+unknown-attribute = '{ $type }' has no attribute '{ $name }'
+unreachable-code = Code is unreachable
+return-type = '{ $function }' returns { $found }, but is annotated to return { $expected }
+incompatible-assignment = '{ $name }' is declared as { $expected }, but is assigned { $found }
+non-exhaustive-match = Match on '{ $subject }' doesn't handle { $missing }
+unsupported-operator = Operator '{ $operator }' is not supported between { $left } and
+    { $right }
+not-context-manager = '{ $type }' doesn't implement { $method }, which '{ $statement }' calls
+not-exception-class = '{ $expression }' is not an exception class and can't be caught
+shadowed-except-clause = Clause for '{ $caught }' never runs, since the earlier clause for
+    '{ $earlier }' catches the same exceptions
+missing-typed-dict-key = Missing key '{ $key }' for TypedDict '{ $type }'
+unknown-typed-dict-key = TypedDict '{ $type }' has no key '{ $key }'
+typed-dict-item-type = Key '{ $key }' of TypedDict '{ $type }' expects { $expected }, but is
+    given { $found }
+shadowed-builtin = '{ $name }' shadows the builtin of the same name
+duplicate-definition = '{ $name }' replaces its definition on line { $line }, which is never used
+suspicious-comparison = Comparison '{ $left } { $operator } { $right }' is always { $outcome }
+inference-too-complex = Inference too complex ({ $reason }), falling back to Any
+uninferred-parameter = Could not infer the type of parameter '{ $name }' of '{ $function }'
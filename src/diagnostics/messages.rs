@@ -0,0 +1,350 @@
+//! Message catalog for user-facing diagnostic text.
+//!
+//! Producers create diagnostics from a [`MessageId`] plus named arguments
+//! instead of literal English. The text is looked up in a Fluent-style
+//! catalog:
+//!
+//! ```text
+//! # comment
+//! unannotated-parameter = Missing type annotation for parameter '{ $name }'
+//! ```
+//!
+//! The built-in English catalog lives in `locales/en-US.ftl`; translations
+//! are loaded with [`Catalog::load`] and fall back to English for any
+//! identifier they don't define.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+const EN_US: &str = include_str!("locales/en-US.ftl");
+
+/// Stable identifier of a diagnostic message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageId {
+    /// A parameter has no type annotation. Args: `name`.
+    UnannotatedParameter,
+    /// A function has no return annotation. Args: `name`.
+    UnannotatedReturn,
+    /// A JavaScript function has no JSDoc comment. Args: `name`.
+    MissingJsdoc,
+    /// A doctest passes an argument of the wrong type. Args: `function`,
+    /// `name`, `expected`, `found`.
+    DoctestArgumentType,
+    /// A doctest's expected output doesn't match the function's return
+    /// type. Args: `function`, `expected`, `found`.
+    DoctestReturnType,
+    /// A call leaves a required parameter without an argument. Args:
+    /// `function`, `name`.
+    MissingArgument,
+    /// A call passes more positional arguments than the function takes.
+    /// Args: `function`, `expected`, `found`.
+    TooManyArguments,
+    /// A call passes a keyword the function has no parameter for. Args:
+    /// `function`, `name`.
+    UnknownKeyword,
+    /// A call passes an argument of the wrong type. Args: `function`,
+    /// `name`, `expected`, `found`.
+    ArgumentType,
+    /// An attribute is read from a type that doesn't have it. Args:
+    /// `type`, `name`.
+    UnknownAttribute,
+    /// Code can never run. No args.
+    UnreachableCode,
+    /// A function returns a value its return annotation doesn't accept.
+    /// Args: `function`, `expected`, `found`.
+    ReturnType,
+    /// A variable is assigned a value its declared annotation doesn't
+    /// accept. Args: `name`, `expected`, `found`.
+    IncompatibleAssignment,
+    /// A `match` statement leaves values of its subject unhandled.
+    /// Args: `subject`, `missing`.
+    NonExhaustiveMatch,
+    /// An operator's operands have no method that implements it.
+    /// Args: `operator`, `left`, `right`.
+    UnsupportedOperator,
+    /// A `with` statement enters a value that isn't a context manager.
+    /// Args: `type`, `method`, `statement`.
+    NotContextManager,
+    /// An `except` clause names a value that isn't an exception class.
+    /// Args: `expression`.
+    NotExceptionClass,
+    /// An `except` clause only catches exceptions an earlier one catches.
+    /// Args: `caught`, `earlier`.
+    ShadowedExceptClause,
+    /// A dict literal of a `TypedDict` type leaves out a required key.
+    /// Args: `type`, `key`.
+    MissingTypedDictKey,
+    /// A dict literal or subscript uses a key its `TypedDict` type doesn't
+    /// declare. Args: `type`, `key`.
+    UnknownTypedDictKey,
+    /// A dict literal of a `TypedDict` type gives a key a value of the
+    /// wrong type. Args: `type`, `key`, `expected`, `found`.
+    TypedDictItemType,
+    /// A parameter, variable, or import shadows a builtin. Args: `name`.
+    ShadowedBuiltin,
+    /// A function or class replaces an unused definition of the same name
+    /// in the same block. Args: `name`, `line`.
+    DuplicateDefinition,
+    /// A comparison between values that can never be equal always has the
+    /// same outcome. Args: `operator`, `left`, `right`, `outcome`.
+    SuspiciousComparison,
+    /// Type inference exceeded its limits and gave up on some constraints,
+    /// whose variables fall back to `Any`. Args: `reason`.
+    InferenceTooComplex,
+    /// No call determines the type of an unannotated parameter, or the
+    /// calls contradict each other. Args: `name`, `function`.
+    UninferredParameter,
+}
+
+impl MessageId {
+    /// Every message identifier.
+    pub const ALL: &'static [MessageId] = &[
+        MessageId::UnannotatedParameter,
+        MessageId::UnannotatedReturn,
+        MessageId::MissingJsdoc,
+        MessageId::DoctestArgumentType,
+        MessageId::DoctestReturnType,
+        MessageId::MissingArgument,
+        MessageId::TooManyArguments,
+        MessageId::UnknownKeyword,
+        MessageId::ArgumentType,
+        MessageId::UnknownAttribute,
+        MessageId::UnreachableCode,
+        MessageId::ReturnType,
+        MessageId::IncompatibleAssignment,
+        MessageId::NonExhaustiveMatch,
+        MessageId::UnsupportedOperator,
+        MessageId::NotContextManager,
+        MessageId::NotExceptionClass,
+        MessageId::ShadowedExceptClause,
+        MessageId::MissingTypedDictKey,
+        MessageId::UnknownTypedDictKey,
+        MessageId::TypedDictItemType,
+        MessageId::ShadowedBuiltin,
+        MessageId::DuplicateDefinition,
+        MessageId::SuspiciousComparison,
+        MessageId::InferenceTooComplex,
+        MessageId::UninferredParameter,
+    ];
+
+    /// The identifier used in catalog files.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageId::UnannotatedParameter => "unannotated-parameter",
+            MessageId::UnannotatedReturn => "unannotated-return",
+            MessageId::MissingJsdoc => "missing-jsdoc",
+            MessageId::DoctestArgumentType => "doctest-argument-type",
+            MessageId::DoctestReturnType => "doctest-return-type",
+            MessageId::MissingArgument => "missing-argument",
+            MessageId::TooManyArguments => "too-many-arguments",
+            MessageId::UnknownKeyword => "unknown-keyword",
+            MessageId::ArgumentType => "argument-type",
+            MessageId::UnknownAttribute => "unknown-attribute",
+            MessageId::UnreachableCode => "unreachable-code",
+            MessageId::ReturnType => "return-type",
+            MessageId::IncompatibleAssignment => "incompatible-assignment",
+            MessageId::NonExhaustiveMatch => "non-exhaustive-match",
+            MessageId::UnsupportedOperator => "unsupported-operator",
+            MessageId::NotContextManager => "not-context-manager",
+            MessageId::NotExceptionClass => "not-exception-class",
+            MessageId::ShadowedExceptClause => "shadowed-except-clause",
+            MessageId::MissingTypedDictKey => "missing-typed-dict-key",
+            MessageId::UnknownTypedDictKey => "unknown-typed-dict-key",
+            MessageId::TypedDictItemType => "typed-dict-item-type",
+            MessageId::ShadowedBuiltin => "shadowed-builtin",
+            MessageId::DuplicateDefinition => "duplicate-definition",
+            MessageId::SuspiciousComparison => "suspicious-comparison",
+            MessageId::InferenceTooComplex => "inference-too-complex",
+            MessageId::UninferredParameter => "uninferred-parameter",
+        }
+    }
+
+    /// The rule code reported for diagnostics with this message. Codes are
+    /// never reused or renumbered, so configurations can refer to them.
+    pub fn code(self) -> &'static str {
+        match self {
+            MessageId::UnannotatedParameter => "OT001",
+            MessageId::UnannotatedReturn => "OT002",
+            MessageId::MissingJsdoc => "OT003",
+            MessageId::DoctestArgumentType => "OT004",
+            MessageId::DoctestReturnType => "OT005",
+            MessageId::MissingArgument => "OT006",
+            MessageId::TooManyArguments => "OT007",
+            MessageId::UnknownKeyword => "OT008",
+            MessageId::ArgumentType => "OT009",
+            MessageId::UnknownAttribute => "OT010",
+            MessageId::UnreachableCode => "OT011",
+            MessageId::ReturnType => "OT012",
+            MessageId::IncompatibleAssignment => "OT013",
+            MessageId::NonExhaustiveMatch => "OT014",
+            MessageId::UnsupportedOperator => "OT015",
+            MessageId::NotContextManager => "OT016",
+            MessageId::NotExceptionClass => "OT017",
+            MessageId::ShadowedExceptClause => "OT018",
+            MessageId::MissingTypedDictKey => "OT019",
+            MessageId::UnknownTypedDictKey => "OT020",
+            MessageId::TypedDictItemType => "OT021",
+            MessageId::ShadowedBuiltin => "OT022",
+            MessageId::DuplicateDefinition => "OT023",
+            MessageId::SuspiciousComparison => "OT024",
+            MessageId::InferenceTooComplex => "OT025",
+            MessageId::UninferredParameter => "OT026",
+        }
+    }
+}
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A set of message templates for one locale.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    locale: String,
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// Parses a catalog from Fluent-style `id = text` entries. Indented lines
+    /// continue the previous entry; `#` lines are comments.
+    pub fn parse(locale: impl Into<String>, source: &str) -> Result<Self> {
+        let mut messages = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                current = None;
+                continue;
+            }
+            if line.starts_with([' ', '\t']) {
+                let id = current.as_ref().ok_or_else(|| {
+                    Error::parser_error(format!("line {}: continuation without entry", index + 1))
+                })?;
+                let text: &mut String = messages.get_mut(id).expect("entry exists");
+                text.push(' ');
+                text.push_str(trimmed);
+                continue;
+            }
+            let (id, text) = line.split_once('=').ok_or_else(|| {
+                Error::parser_error(format!("line {}: expected `id = text`", index + 1))
+            })?;
+            let id = id.trim().to_string();
+            messages.insert(id.clone(), text.trim().to_string());
+            current = Some(id);
+        }
+
+        Ok(Self { locale: locale.into(), messages })
+    }
+
+    /// Loads a catalog file, taking the locale from its file stem
+    /// (e.g. `de-DE.ftl`).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        let locale = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::parse(locale, &source).map_err(|e| e.with_path(path))
+    }
+
+    /// The built-in English catalog.
+    pub fn english() -> &'static Catalog {
+        static ENGLISH: OnceLock<Catalog> = OnceLock::new();
+        ENGLISH.get_or_init(|| Catalog::parse("en-US", EN_US).expect("valid built-in catalog"))
+    }
+
+    /// The locale this catalog translates into.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Returns the raw template for a message, if this catalog defines it.
+    pub fn get(&self, id: MessageId) -> Option<&str> {
+        self.messages.get(id.as_str()).map(String::as_str)
+    }
+
+    /// Formats a message, falling back to English and then to the bare
+    /// identifier when no template exists.
+    pub fn format(&self, id: MessageId, args: &BTreeMap<String, String>) -> String {
+        match self.get(id).or_else(|| Catalog::english().get(id)) {
+            Some(template) => interpolate(template, args),
+            None => id.as_str().to_string(),
+        }
+    }
+}
+
+/// Replaces `{ $name }` placeables with argument values. Unknown arguments
+/// are left as written so missing data is visible rather than silently blank.
+fn interpolate(template: &str, args: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        let placeable = &rest[open..open + close + 1];
+        let name = placeable[1..placeable.len() - 1]
+            .trim()
+            .trim_start_matches('$');
+        match args.get(name) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(placeable),
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_catalog_covers_all_ids() {
+        let english = Catalog::english();
+        for id in MessageId::ALL {
+            assert!(english.get(*id).is_some(), "missing English text for {}", id);
+        }
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        let mut codes: Vec<&str> = MessageId::ALL.iter().map(|id| id.code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), MessageId::ALL.len());
+        assert_eq!(MessageId::UnannotatedParameter.code(), "OT001");
+    }
+
+    #[test]
+    fn test_translation_and_fallback() {
+        let catalog = Catalog::parse(
+            "de-DE",
+            "# Deutsch\nunannotated-parameter = Parameter '{ $name }'\n    ohne Typannotation\n",
+        )
+        .unwrap();
+        let args = BTreeMap::from([("name".to_string(), "x".to_string())]);
+        assert_eq!(
+            catalog.format(MessageId::UnannotatedParameter, &args),
+            "Parameter 'x' ohne Typannotation"
+        );
+        assert_eq!(
+            catalog.format(MessageId::MissingJsdoc, &args),
+            "Missing JSDoc comment for function 'x'"
+        );
+        assert!(Catalog::parse("xx", "no equals sign").is_err());
+    }
+}
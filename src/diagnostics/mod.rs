@@ -0,0 +1,278 @@
+//! Diagnostics shared by every omnitype subsystem.
+//!
+//! The analyzer, fixer, tracer, and editor integrations all report problems
+//! as [`Diagnostic`] values so they can be rendered, filtered, and serialized
+//! uniformly.
+
+mod export;
+mod messages;
+mod sarif;
+
+pub use export::{to_csv, to_jsonl, CSV_COLUMNS};
+pub use messages::{Catalog, MessageId};
+pub use sarif::to_sarif;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::utils::Span;
+
+/// How serious a diagnostic is. Variants are ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// A suggestion that doesn't indicate a problem.
+    Hint,
+    /// Informational message.
+    Info,
+    /// Likely problem that doesn't prevent checking.
+    Warning,
+    /// Definite type error.
+    Error,
+}
+
+impl Severity {
+    /// The lowercase name used in text output and configuration.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Hint => "hint",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hint" => Ok(Severity::Hint),
+            "info" | "information" | "note" => Ok(Severity::Info),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            other => Err(Error::invalid_value("severity", other, "unknown severity")),
+        }
+    }
+}
+
+/// A problem found in a source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// File path of the diagnostic.
+    pub path: String,
+    /// Source range the diagnostic refers to.
+    pub span: Span,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Stable rule code identifying the kind of problem, if assigned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Human-readable message.
+    pub message: String,
+    /// Catalog identifier the message was rendered from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<MessageId>,
+    /// Arguments interpolated into the message template.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+    /// Secondary locations that help explain the problem.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<RelatedInformation>,
+}
+
+/// A secondary location attached to a diagnostic, such as the declaration a
+/// mismatched argument is checked against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelatedInformation {
+    /// File containing the related location.
+    pub path: String,
+    /// Source range of the related location.
+    pub span: Span,
+    /// Explanation of how the location relates to the diagnostic.
+    pub message: String,
+}
+
+impl fmt::Display for RelatedInformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path, self.span, self.message)
+    }
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic with the given severity.
+    pub fn new(
+        severity: Severity,
+        path: impl Into<String>,
+        span: Span,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            span,
+            severity,
+            code: None,
+            message: message.into(),
+            message_id: None,
+            args: BTreeMap::new(),
+            related: Vec::new(),
+        }
+    }
+
+    /// Creates a diagnostic whose text comes from the message catalog,
+    /// rendered in English until [`Diagnostic::localize`] is called. The
+    /// rule code is the one assigned to the message.
+    pub fn from_message(
+        severity: Severity,
+        path: impl Into<String>,
+        span: Span,
+        id: MessageId,
+        args: &[(&str, &str)],
+    ) -> Self {
+        let args: BTreeMap<String, String> = args
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let message = Catalog::english().format(id, &args);
+        Self {
+            code: Some(id.code().to_string()),
+            message_id: Some(id),
+            args,
+            ..Self::new(severity, path, span, message)
+        }
+    }
+
+    /// Creates an error diagnostic.
+    pub fn error(path: impl Into<String>, span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, path, span, message)
+    }
+
+    /// Creates a warning diagnostic.
+    pub fn warning(path: impl Into<String>, span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, path, span, message)
+    }
+
+    /// Creates an informational diagnostic.
+    pub fn info(path: impl Into<String>, span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Info, path, span, message)
+    }
+
+    /// Sets the rule code.
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Attaches a related location.
+    pub fn with_related(
+        mut self,
+        path: impl Into<String>,
+        span: Span,
+        message: impl Into<String>,
+    ) -> Self {
+        self.related
+            .push(RelatedInformation { path: path.into(), span, message: message.into() });
+        self
+    }
+
+    /// Renders the diagnostic as text, with one indented `note:` line per
+    /// related location.
+    pub fn render_text(&self) -> String {
+        let mut out = self.to_string();
+        for related in &self.related {
+            out.push_str(&format!("\n    note: {}", related));
+        }
+        out
+    }
+
+    /// Re-renders the message with a translated catalog. Diagnostics built
+    /// from literal text are left unchanged.
+    pub fn localize(&mut self, catalog: &Catalog) {
+        if let Some(id) = self.message_id {
+            self.message = catalog.format(id, &self.args);
+        }
+    }
+
+    /// Overrides the severity.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// Formats as `path:line:column: severity message [code]`, with 1-based
+    /// positions. Diagnostics without a rule code omit the brackets.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {} {}", self.path, self.span, self.severity, self.message)?;
+        match &self.code {
+            Some(code) => write!(f, " [{}]", code),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_order_and_parse() {
+        assert!(Severity::Error > Severity::Warning);
+        assert!(Severity::Warning > Severity::Info);
+        assert_eq!("warn".parse::<Severity>().unwrap(), Severity::Warning);
+        assert!("fatal".parse::<Severity>().is_err());
+    }
+
+    #[test]
+    fn test_diagnostic_builder_and_roundtrip() {
+        let diag = Diagnostic::error("a.py", Span::point(2, 4), "Bad type").with_code("OT010");
+        assert_eq!(diag.to_string(), "a.py:3:5: error Bad type [OT010]");
+
+        let json = serde_json::to_string(&diag).unwrap();
+        assert!(json.contains("\"severity\":\"error\""));
+        let back: Diagnostic = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, diag);
+    }
+
+    #[test]
+    fn test_catalog_message_localizes() {
+        let mut diag = Diagnostic::from_message(
+            Severity::Warning,
+            "a.py",
+            Span::point(0, 6),
+            MessageId::UnannotatedParameter,
+            &[("name", "x")],
+        );
+        assert_eq!(diag.message, "Missing type annotation for parameter 'x'");
+
+        let french =
+            Catalog::parse("fr-FR", "unannotated-parameter = Paramètre '{ $name }' non annoté")
+                .unwrap();
+        diag.localize(&french);
+        assert_eq!(diag.message, "Paramètre 'x' non annoté");
+        assert_eq!(diag.message_id, Some(MessageId::UnannotatedParameter));
+    }
+
+    #[test]
+    fn test_related_information_rendering() {
+        let diag = Diagnostic::error("a.py", Span::point(9, 0), "Argument has incompatible type")
+            .with_related("b.py", Span::point(0, 6), "Parameter declared here");
+        assert_eq!(
+            diag.render_text(),
+            "a.py:10:1: error Argument has incompatible type\n    note: b.py:1:7: Parameter \
+             declared here"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! SARIF 2.1.0 output for code-scanning integrations.
+
+use serde_json::{json, Value};
+
+use super::{Diagnostic, Severity};
+use crate::utils::Span;
+
+/// Converts diagnostics into a SARIF 2.1.0 log with a single run.
+pub fn to_sarif(diagnostics: &[Diagnostic]) -> Value {
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|d| {
+            let mut result = json!({
+                "ruleId": d.code.as_deref().unwrap_or("omnitype"),
+                "level": level(d.severity),
+                "message": { "text": d.message },
+                "locations": [location(&d.path, &d.span)],
+            });
+            if !d.related.is_empty() {
+                result["relatedLocations"] = d
+                    .related
+                    .iter()
+                    .enumerate()
+                    .map(|(id, r)| {
+                        let mut loc = location(&r.path, &r.span);
+                        loc["id"] = json!(id);
+                        loc["message"] = json!({ "text": r.message });
+                        loc
+                    })
+                    .collect();
+            }
+            result
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "omnitype",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                }
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    })
+}
+
+/// Maps a severity onto a SARIF result level.
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info | Severity::Hint => "note",
+    }
+}
+
+/// Builds a SARIF physical location with 1-based line and column numbers,
+/// counting columns in UTF-16 code units as the run declares.
+fn location(path: &str, span: &Span) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": path },
+            "region": {
+                "startLine": span.start.line + 1,
+                "startColumn": span.start.utf16_column + 1,
+                "endLine": span.end.line + 1,
+                "endColumn": span.end.utf16_column + 1,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::SourceFile;
+
+    #[test]
+    fn test_sarif_levels_and_related_locations() {
+        let diag = Diagnostic::warning("a.py", Span::point(0, 0), "Missing annotation")
+            .with_related("a.py", Span::point(3, 2), "Declared here");
+        let log = to_sarif(&[diag]);
+        let result = &log["runs"][0]["results"][0];
+        assert_eq!(result["level"], "warning");
+        assert_eq!(result["relatedLocations"][0]["message"]["text"], "Declared here");
+        assert_eq!(result["relatedLocations"][0]["physicalLocation"]["region"]["startLine"], 4);
+    }
+
+    #[test]
+    fn test_sarif_columns_count_utf16_units() {
+        let source = SourceFile::from_text("a.py", "s = '\u{e9}\u{1f600}'; x\n");
+        let start = source.text().find('x').unwrap();
+        let diag = Diagnostic::warning("a.py", source.span(start, start + 1), "Unknown");
+        let log = to_sarif(&[diag]);
+        assert_eq!(log["runs"][0]["columnKind"], "utf16CodeUnits");
+        let region = &log["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"];
+        // `é` is one unit and the emoji two, where they take two and four bytes.
+        assert_eq!(region["startColumn"], 12);
+        assert_eq!(region["endColumn"], 13);
+    }
+}
@@ -0,0 +1,55 @@
+//! The git commands behind differential analysis.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Error, Result};
+
+/// Runs git in `dir` and returns its standard output.
+fn run(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(Error::Other(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// The root of the working tree containing `dir`.
+pub(super) fn toplevel(dir: &Path) -> Result<PathBuf> {
+    let out = run(dir, &["rev-parse", "--show-toplevel"])?;
+    let root = PathBuf::from(String::from_utf8_lossy(&out).trim());
+    // Match the canonical paths used for the working tree files.
+    Ok(root.canonicalize().unwrap_or(root))
+}
+
+/// The commit id a revision names.
+pub(super) fn resolve_commit(root: &Path, rev: &str) -> Result<String> {
+    let spec = format!("{}^{{commit}}", rev);
+    let out = run(root, &["rev-parse", "--verify", "--quiet", &spec])?;
+    Ok(String::from_utf8_lossy(&out).trim().to_string())
+}
+
+/// Files under `prefix` at a commit, relative to the repository root.
+pub(super) fn list_files(root: &Path, commit: &str, prefix: &Path) -> Result<Vec<PathBuf>> {
+    let prefix = prefix.to_string_lossy().replace('\\', "/");
+    let mut args = vec!["ls-tree", "-r", "-z", "--name-only", "--full-tree", commit];
+    if !prefix.is_empty() {
+        args.extend(["--", &prefix]);
+    }
+    let out = run(root, &args)?;
+    Ok(out
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| PathBuf::from(String::from_utf8_lossy(name).into_owned()))
+        .collect())
+}
+
+/// The contents of a file at a commit.
+pub(super) fn show(root: &Path, commit: &str, path: &Path) -> Result<Vec<u8>> {
+    let spec = format!("{}:{}", commit, path.to_string_lossy().replace('\\', "/"));
+    run(root, &["show", &spec])
+}
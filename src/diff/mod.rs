@@ -0,0 +1,304 @@
+//! Differential analysis between a git revision and the working tree.
+//!
+//! Both sides are analyzed with the same configuration and custom rules, and
+//! only diagnostics that appear on one side are reported. Diagnostics are
+//! matched by file, rule code, message, and the text of the line they point
+//! at, so edits elsewhere in a file that shift line numbers don't show up as
+//! changes.
+
+mod git;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analyzer::{Analyzer, RuleSet};
+use crate::config::Config;
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Result};
+use crate::parser::{Language, SourceText};
+use crate::utils::SourceFile;
+
+/// Diagnostics that differ between a base revision and the working tree.
+/// Paths are relative to the repository root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffReport {
+    /// The revision compared against, as given.
+    pub base: String,
+    /// Diagnostics in the working tree that the base revision doesn't have.
+    pub introduced: Vec<Diagnostic>,
+    /// Diagnostics in the base revision that the working tree no longer has.
+    pub resolved: Vec<Diagnostic>,
+}
+
+impl DiffReport {
+    /// Returns true if the working tree introduces no diagnostics.
+    pub fn is_clean(&self) -> bool {
+        self.introduced.is_empty()
+    }
+
+    /// A one-line count of introduced and resolved diagnostics.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} introduced, {} resolved since {}",
+            self.introduced.len(),
+            self.resolved.len(),
+            self.base
+        )
+    }
+
+    /// Renders the report as Markdown for a pull request comment. Resolved
+    /// diagnostics are collapsed.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "### omnitype: {} new, {} resolved (vs `{}`)\n",
+            self.introduced.len(),
+            self.resolved.len(),
+            self.base
+        );
+        if self.introduced.is_empty() {
+            out.push_str("\nNo new diagnostics.\n");
+        } else {
+            out.push_str("\n| Location | Severity | Code | Message |\n| --- | --- | --- | --- |\n");
+            for d in &self.introduced {
+                let _ = writeln!(
+                    out,
+                    "| `{}:{}` | {} | {} | {} |",
+                    d.path,
+                    d.span,
+                    d.severity,
+                    d.code.as_deref().unwrap_or(""),
+                    d.message.replace('|', "\\|")
+                );
+            }
+        }
+        if !self.resolved.is_empty() {
+            let _ =
+                write!(out, "\n<details><summary>Resolved ({})</summary>\n\n", self.resolved.len());
+            for d in &self.resolved {
+                let _ = write!(out, "- `{}:{}` {}", d.path, d.span, d.message);
+                let _ = match &d.code {
+                    Some(code) => writeln!(out, " ({})", code),
+                    None => writeln!(out),
+                };
+            }
+            out.push_str("\n</details>\n");
+        }
+        out
+    }
+}
+
+/// Compares diagnostics under a path between a git revision and the
+/// working tree.
+pub struct DiffCheck {
+    path: PathBuf,
+    base: String,
+    config: Config,
+    rules: RuleSet,
+}
+
+impl DiffCheck {
+    /// Creates a comparison of `path`, a file or directory inside a git
+    /// repository, against the revision `base`.
+    pub fn new(path: impl Into<PathBuf>, base: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            base: base.into(),
+            config: Config::default(),
+            rules: RuleSet::default(),
+        }
+    }
+
+    /// Sets the configuration applied to both sides.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the custom rules run on both sides.
+    pub fn rules(mut self, rules: RuleSet) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Analyzes both sides and reports the differences.
+    pub fn run(&self) -> Result<DiffReport> {
+        let path = self.path.canonicalize()?;
+        let dir = if path.is_file() { path.parent().unwrap_or(&path) } else { &path };
+        let root = git::toplevel(dir)?;
+        let commit = git::resolve_commit(&root, &self.base)
+            .map_err(|_| Error::invalid_value("--base", &self.base, "unknown revision"))?;
+        let prefix = relative(&root, &path)?;
+
+        let head_files: Vec<PathBuf> = if path.is_file() {
+            vec![path.clone()]
+        } else {
+            self.config.walker(&path).files()?.collect()
+        };
+        let mut head = Vec::new();
+        let mut head_paths = BTreeSet::new();
+        for file in &head_files {
+            let rel = relative(&root, file)?;
+            let source = SourceText::read(file)?;
+            head.extend(self.check(SourceFile::new(slash_path(&rel), source))?);
+            head_paths.insert(rel);
+        }
+
+        let mut base = Vec::new();
+        for rel in git::list_files(&root, &commit, &prefix)? {
+            // Files that still exist but aren't checked are excluded or ignored.
+            let enabled = Language::from_path(&rel).is_some_and(Language::is_enabled);
+            if !enabled || (!head_paths.contains(&rel) && root.join(&rel).exists()) {
+                continue;
+            }
+            let bytes = git::show(&root, &commit, &rel)?;
+            // A file fixed since the base shouldn't stop the comparison.
+            let source = match SourceText::decode_file(&rel, &bytes) {
+                Ok(source) => source,
+                Err(e) => {
+                    log::warn!("Skipping {} at {}: {}", rel.display(), self.base, e);
+                    continue;
+                },
+            };
+            base.extend(self.check(SourceFile::new(slash_path(&rel), source))?);
+        }
+
+        let (introduced, resolved) = difference(head, base);
+        Ok(DiffReport { base: self.base.clone(), introduced, resolved })
+    }
+
+    /// Analyzes one side of a file, returning each diagnostic with its key.
+    fn check(&self, file: SourceFile) -> Result<Vec<(Key, Diagnostic)>> {
+        let Some(language) = Language::from_path(Path::new(file.path())) else {
+            return Ok(Vec::new());
+        };
+        let mut result =
+            Analyzer::analyze_source(&file, language).map_err(|e| e.with_path(file.path()))?;
+        result.apply_unsolved(self.config.unsolved);
+        let mut diagnostics = result.diagnostics;
+        diagnostics.extend(self.rules.check_source(&file, language)?);
+        self.config.finalize(&mut diagnostics);
+        Ok(diagnostics
+            .into_iter()
+            .map(|d| {
+                let line = file.line_text(d.span.start.line).unwrap_or_default();
+                let key = (d.path.clone(), d.code.clone(), d.message.clone(), line.trim().into());
+                (key, d)
+            })
+            .collect())
+    }
+}
+
+/// What identifies "the same" diagnostic on both sides: path, code,
+/// message, and the trimmed text of its first line.
+type Key = (String, Option<String>, String, String);
+
+/// Splits keyed diagnostics into those only in `head` and those only in
+/// `base`, treating repeated keys as a multiset.
+fn difference(
+    head: Vec<(Key, Diagnostic)>,
+    base: Vec<(Key, Diagnostic)>,
+) -> (Vec<Diagnostic>, Vec<Diagnostic>) {
+    let mut counts: HashMap<Key, isize> = HashMap::new();
+    for (key, _) in &base {
+        *counts.entry(key.clone()).or_default() += 1;
+    }
+    let mut introduced = Vec::new();
+    for (key, d) in head {
+        match counts.get_mut(&key) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => introduced.push(d),
+        }
+    }
+    // What is left in `counts` went unmatched; take that many from the end.
+    let mut resolved = Vec::new();
+    for (key, d) in base.into_iter().rev() {
+        if let Some(n) = counts.get_mut(&key).filter(|n| **n > 0) {
+            *n -= 1;
+            resolved.push(d);
+        }
+    }
+    resolved.reverse();
+    (introduced, resolved)
+}
+
+fn relative(root: &Path, path: &Path) -> Result<PathBuf> {
+    path.strip_prefix(root).map(Path::to_path_buf).map_err(|_| {
+        Error::invalid_value("path", path.display().to_string(), "not inside the repository")
+    })
+}
+
+/// A repository-relative path with `/` separators, as git prints them.
+fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_diff_against_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        fs::write(root.join("a.py"), "def keep(x):\n    pass\n\ndef fixed(y):\n    pass\n")
+            .unwrap();
+        fs::write(root.join("gone.py"), "def old() -> None:\n    pass\n").unwrap();
+        fs::write(root.join("latin.py"), b"name = '\xe9'\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "base"]);
+
+        // Shift `keep` down a line, annotate `fixed`, add a new function.
+        fs::write(
+            root.join("a.py"),
+            "import os\ndef keep(x):\n    pass\n\ndef fixed(y: int) -> None:\n    pass\n\n\
+             def new(z) -> None:\n    pass\n",
+        )
+        .unwrap();
+        fs::remove_file(root.join("gone.py")).unwrap();
+        fs::write(root.join("latin.py"), "name = '\u{e9}'\n").unwrap();
+
+        let report = DiffCheck::new(root, "HEAD").run().unwrap();
+        let messages = |ds: &[Diagnostic]| -> Vec<String> {
+            ds.iter()
+                .map(|d| format!("{}:{}", d.path, d.message))
+                .collect()
+        };
+        assert_eq!(
+            messages(&report.introduced),
+            ["a.py:Missing type annotation for parameter 'z'"]
+        );
+        assert_eq!(
+            messages(&report.resolved),
+            [
+                "a.py:Missing type annotation for parameter 'y'",
+                "a.py:Missing return type annotation for 'fixed'",
+            ]
+        );
+        assert_eq!(report.summary(), "1 introduced, 2 resolved since HEAD");
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| `a.py:8:9` | warning | OT001 |"));
+        assert!(
+            markdown.contains("- `a.py:4:1` Missing return type annotation for 'fixed' (OT002)\n")
+        );
+
+        assert!(DiffCheck::new(root, "no-such-rev").run().is_err());
+    }
+}
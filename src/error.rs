@@ -1,7 +1,13 @@
 //! Error types for the omnitype crate.
 
+use std::fmt;
+use std::path::{Path, PathBuf};
+
 use thiserror::Error;
 
+use crate::types::Type;
+use crate::utils::Span;
+
 /// A type alias for `Result<T, Error>`.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -14,45 +20,269 @@ pub enum Error {
 
     /// Parser related errors.
     #[error("Parser error: {0}")]
-    Parser(String),
+    Parser(Box<ParserError>),
 
     /// Type checking related errors.
     #[error("Type error: {0}")]
-    Type(String),
+    Type(Box<TypeError>),
 
     /// Invalid argument errors.
     #[error("Invalid argument: {0}")]
-    Argument(String),
+    Argument(Box<ArgumentError>),
 
     /// Feature not implemented yet.
     #[error("Not implemented: {0}")]
     NotImplemented(String),
 
+    /// Several independent failures collected from a batch operation.
+    #[error("{}", MultipleErrors(.0))]
+    Multiple(Vec<Error>),
+
     /// Other miscellaneous errors.
     #[error("{0}")]
     Other(String),
 }
 
+/// The outcome of a batch operation that keeps going after individual
+/// failures: whatever was produced, plus every error encountered.
+#[derive(Debug)]
+pub struct Partial<T> {
+    /// The (possibly incomplete) result.
+    pub value: T,
+    /// Failures encountered while producing `value`.
+    pub errors: Vec<Error>,
+}
+
+impl<T> Partial<T> {
+    /// Starts a batch with an initial value and no errors.
+    pub fn new(value: T) -> Self {
+        Self { value, errors: Vec::new() }
+    }
+
+    /// Returns true if nothing failed.
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Records the error of a failed step and returns the value of a
+    /// successful one.
+    pub fn record<U>(&mut self, result: Result<U>) -> Option<U> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.errors.push(e);
+                None
+            },
+        }
+    }
+
+    /// Converts to a `Result`, failing if any step failed. A single failure
+    /// is returned as is; several are wrapped in [`Error::Multiple`].
+    pub fn into_result(mut self) -> Result<T> {
+        match self.errors.len() {
+            0 => Ok(self.value),
+            1 => Err(self.errors.remove(0)),
+            _ => Err(Error::Multiple(self.errors)),
+        }
+    }
+}
+
+impl<T: Default> Default for Partial<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// Details of a failure to read, decode, or parse source code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserError {
+    /// Description of the failure.
+    pub message: String,
+    /// File being parsed, if known.
+    pub path: Option<PathBuf>,
+    /// Location of the failure within the file, if known.
+    pub span: Option<Span>,
+}
+
+/// Details of a type checking failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    /// Description of the failure.
+    pub message: String,
+    /// The type that was required, if the error is a mismatch.
+    pub expected: Option<Type>,
+    /// The type that was found, if the error is a mismatch.
+    pub found: Option<Type>,
+    /// File containing the error, if known.
+    pub path: Option<PathBuf>,
+    /// Location of the error within the file, if known.
+    pub span: Option<Span>,
+}
+
+/// Details of an invalid argument or configuration value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentError {
+    /// Description of what is wrong with the value.
+    pub message: String,
+    /// Name of the offending argument or option, if known.
+    pub name: Option<String>,
+    /// The rejected value, if known.
+    pub value: Option<String>,
+}
+
+/// Writes the `path:line:column: ` prefix shared by located errors.
+fn write_location(
+    f: &mut fmt::Formatter<'_>,
+    path: &Option<PathBuf>,
+    span: &Option<Span>,
+) -> fmt::Result {
+    match (path, span) {
+        (Some(path), Some(span)) => write!(f, "{}:{}: ", path.display(), span),
+        (Some(path), None) => write!(f, "{}: ", path.display()),
+        (None, Some(span)) => write!(f, "{}: ", span),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Display of the errors in [`Error::Multiple`], which anyone can build
+/// with an empty list.
+struct MultipleErrors<'a>(&'a [Error]);
+
+impl fmt::Display for MultipleErrors<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.first() {
+            Some(first) => write!(f, "{} errors occurred; first: {}", self.0.len(), first),
+            None => f.write_str("no errors occurred"),
+        }
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_location(f, &self.path, &self.span)?;
+        f.write_str(&self.message)
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_location(f, &self.path, &self.span)?;
+        f.write_str(&self.message)?;
+        match (&self.expected, &self.found) {
+            (Some(expected), Some(found)) => {
+                write!(f, " (expected {}, found {})", expected, found)
+            },
+            (Some(expected), None) => write!(f, " (expected {})", expected),
+            (None, Some(found)) => write!(f, " (found {})", found),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{}: ", name)?;
+        }
+        f.write_str(&self.message)?;
+        if let Some(value) = &self.value {
+            write!(f, " (got '{}')", value)?;
+        }
+        Ok(())
+    }
+}
+
 impl Error {
     /// Creates a new parser error.
     pub fn parser_error(msg: impl Into<String>) -> Self {
-        Self::Parser(msg.into())
+        Self::Parser(Box::new(ParserError { message: msg.into(), path: None, span: None }))
     }
 
     /// Creates a new type error.
     pub fn type_error(msg: impl Into<String>) -> Self {
-        Self::Type(msg.into())
+        Self::Type(Box::new(TypeError {
+            message: msg.into(),
+            expected: None,
+            found: None,
+            path: None,
+            span: None,
+        }))
+    }
+
+    /// Creates a type error describing a mismatch between two types.
+    pub fn type_mismatch(msg: impl Into<String>, expected: Type, found: Type) -> Self {
+        Self::Type(Box::new(TypeError {
+            message: msg.into(),
+            expected: Some(expected),
+            found: Some(found),
+            path: None,
+            span: None,
+        }))
     }
 
     /// Creates a new argument error.
     pub fn argument_error(msg: impl Into<String>) -> Self {
-        Self::Argument(msg.into())
+        Self::Argument(Box::new(ArgumentError { message: msg.into(), name: None, value: None }))
+    }
+
+    /// Creates an argument error naming the offending option and value.
+    pub fn invalid_value(
+        name: impl Into<String>,
+        value: impl Into<String>,
+        msg: impl Into<String>,
+    ) -> Self {
+        Self::Argument(Box::new(ArgumentError {
+            message: msg.into(),
+            name: Some(name.into()),
+            value: Some(value.into()),
+        }))
     }
 
     /// Creates a new not implemented error.
     pub fn not_implemented(feature: impl Into<String>) -> Self {
         Self::NotImplemented(feature.into())
     }
+
+    /// Attaches the file an error refers to. Has no effect on variants that
+    /// don't carry a location.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        let path = Some(path.as_ref().to_path_buf());
+        match &mut self {
+            Self::Parser(e) => e.path = path,
+            Self::Type(e) => e.path = path,
+            _ => {},
+        }
+        self
+    }
+
+    /// Attaches the source location an error refers to. Has no effect on
+    /// variants that don't carry a location.
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            Self::Parser(e) => e.span = Some(span),
+            Self::Type(e) => e.span = Some(span),
+            _ => {},
+        }
+        self
+    }
+
+    /// The file the error refers to, if known.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Parser(e) => e.path.as_deref(),
+            Self::Type(e) => e.path.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The source location the error refers to, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Parser(e) => e.span,
+            Self::Type(e) => e.span,
+            _ => None,
+        }
+    }
 }
 
 impl From<&str> for Error {
@@ -66,3 +296,48 @@ impl From<String> for Error {
         Self::Other(s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_type_error() {
+        let err = Error::type_mismatch("Incompatible return value", Type::Int, Type::Str)
+            .with_path("pkg/mod.py")
+            .with_span(Span::point(4, 8));
+        assert_eq!(
+            err.to_string(),
+            "Type error: pkg/mod.py:5:9: Incompatible return value (expected int, found str)"
+        );
+        assert_eq!(err.span(), Some(Span::point(4, 8)));
+        match err {
+            Error::Type(e) => assert_eq!(e.found, Some(Type::Str)),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_partial_collects_errors() {
+        let mut batch = Partial::new(Vec::new());
+        for step in [Ok(1), Err(Error::from("first")), Ok(2), Err(Error::from("second"))] {
+            if let Some(v) = batch.record(step) {
+                batch.value.push(v);
+            }
+        }
+        assert_eq!(batch.value, vec![1, 2]);
+        assert_eq!(batch.errors.len(), 2);
+        let err = batch.into_result().unwrap_err();
+        assert_eq!(err.to_string(), "2 errors occurred; first: first");
+        assert_eq!(Error::Multiple(Vec::new()).to_string(), "no errors occurred");
+    }
+
+    #[test]
+    fn test_argument_error_display() {
+        let err = Error::invalid_value("--format", "xml", "unsupported output format");
+        assert_eq!(
+            err.to_string(),
+            "Invalid argument: --format: unsupported output format (got 'xml')"
+        );
+    }
+}
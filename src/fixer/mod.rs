@@ -1,11 +1,17 @@
 //! Module for automatically fixing type-related issues in source code.
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::error::Result;
-use crate::types::TypeEnv;
-use crate::utils::find_python_files;
+use tree_sitter::Node;
+
+use crate::analyzer::{is_annotated, parameter_name, parameters, Analyzer, FunctionKind};
+use crate::error::{Partial, Result};
+use crate::parser::{Parser, SourceText};
+use crate::solver::Unsolved;
+use crate::types::{Type, TypeEnv};
+use crate::utils::{DiscoveryOptions, PythonVersion, SourceFile, SourceWalker, Span};
 
 /// The main fixer that applies type fixes to source code.
 #[allow(dead_code)]
@@ -15,40 +21,114 @@ pub struct Fixer {
 
     /// Whether to apply changes in-place
     in_place: bool,
+
+    /// Gitignore-style patterns for paths to leave untouched
+    excludes: Vec<String>,
+
+    /// Size and generated-file filters for the files of a directory
+    discovery: DiscoveryOptions,
+
+    /// Oldest Python version the written annotations must be valid on
+    python_version: PythonVersion,
 }
 
 impl Fixer {
     /// Creates a new fixer with the given type environment.
     pub fn new(type_env: TypeEnv, in_place: bool) -> Self {
-        Self { type_env, in_place }
+        Self {
+            type_env,
+            in_place,
+            excludes: Vec::new(),
+            discovery: DiscoveryOptions::default(),
+            python_version: PythonVersion::DEFAULT,
+        }
     }
 
-    /// Fixes type annotations in the specified file or directory.
-    pub fn fix_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let path = path.as_ref();
-        if path.is_file() {
-            self.fix_file(path)?;
-        } else if path.is_dir() {
-            for file in find_python_files(path) {
-                self.fix_file(file)?;
+    /// Writes annotations valid on the given Python version, such as
+    /// `list[int]` rather than `List[int]` from 3.9.
+    pub fn with_python_version(mut self, version: PythonVersion) -> Self {
+        self.python_version = version;
+        self
+    }
+
+    /// Skips paths matching the given gitignore-style patterns when fixing a
+    /// directory.
+    pub fn exclude(mut self, patterns: Vec<String>) -> Self {
+        self.excludes = patterns;
+        self
+    }
+
+    /// Skips the files of a directory that `options` rejects. Only Python
+    /// files are fixed, whatever extensions it allows.
+    pub fn discovery(mut self, options: DiscoveryOptions) -> Self {
+        self.discovery = options;
+        self
+    }
+
+    /// Fixes type annotations in the specified file or directory, returning
+    /// the files that needed changes. Every file is attempted even if some
+    /// fail; the failures are reported together.
+    pub fn fix_path<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PathBuf>> {
+        self.fix_all(path).into_result()
+    }
+
+    /// Like [`Fixer::fix_path`], but returns the changed files together with
+    /// the failures instead of discarding them when something fails.
+    pub fn fix_all<P: AsRef<Path>>(&self, path: P) -> Partial<Vec<PathBuf>> {
+        match self.files(path.as_ref()) {
+            Ok(files) => self.fix_files(files),
+            Err(e) => {
+                let mut batch = Partial::new(Vec::new());
+                batch.errors.push(e);
+                batch
+            },
+        }
+    }
+
+    /// The files [`Fixer::fix_all`] fixes: the Python files under `path`
+    /// that aren't excluded, or `path` itself when it is a file.
+    pub fn files(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        if !path.is_dir() {
+            return Ok(vec![path.to_path_buf()]);
+        }
+        let walker = SourceWalker::new(path)
+            .exclude(self.excludes.iter().cloned())
+            .options(self.discovery.clone().extensions(["py"]));
+        Ok(walker.files()?.collect())
+    }
+
+    /// Fixes each of `files`, returning those that needed changes together
+    /// with the failures.
+    pub fn fix_files(&self, files: Vec<PathBuf>) -> Partial<Vec<PathBuf>> {
+        let mut batch = Partial::new(Vec::new());
+        for file in files {
+            if let Some(true) = batch.record(self.fix_file(&file)) {
+                batch.value.push(file);
             }
         }
-        Ok(())
+        batch
     }
 
-    /// Fixes type annotations in a single source file.
-    #[allow(dead_code)]
-    fn fix_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    /// Fixes type annotations in a single source file, returning whether it
+    /// needed changes.
+    fn fix_file<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
         let path = path.as_ref();
         if path.extension().and_then(|e| e.to_str()) != Some("py") {
-            return Ok(());
+            return Ok(false);
         }
-        let original = fs::read_to_string(path)?;
-        let fixed = Self::fix_source(&original);
-        if fixed != original && self.in_place {
-            fs::write(path, fixed)?;
+        let original = SourceText::read(path).map_err(|e| match e.path() {
+            Some(_) => e,
+            None => e.with_path(path),
+        })?;
+        let fixed = Self::fix_source_for(&original.text, self.python_version)?;
+        if fixed == original.text {
+            return Ok(false);
         }
-        Ok(())
+        if self.in_place {
+            // Write back in the file's declared encoding so coding comments stay truthful.
+            fs::write(path, original.encode(&fixed))?;
+        }
+        Ok(true)
     }
 
     /// Generates a type annotation for a node.
@@ -58,101 +138,295 @@ impl Fixer {
         None
     }
 
-    /// Text-based fixer to add Any annotations.
-    fn fix_source(source: &str) -> String {
-        let mut used_any = false;
-        let mut out = String::with_capacity(source.len() + 64);
-
-        for line in source.lines() {
-            let trimmed = line.trim_start();
-            if trimmed.starts_with("def ") {
-                if let Some(paren_start) = trimmed.find('(') {
-                    if let Some(paren_end) = trimmed[paren_start..].find(')') {
-                        let indent_len = line.len() - trimmed.len();
-                        let before = &trimmed[..paren_start + 1];
-                        let params = &trimmed[paren_start + 1..paren_start + paren_end];
-                        let after_params = &trimmed[paren_start + paren_end + 1..];
-
-                        let fixed_params = Self::annotate_params(params, &mut used_any);
-                        let mut signature_tail = after_params.to_string();
-                        if !after_params.contains("->") {
-                            if let Some(colon_idx) = signature_tail.find(':') {
-                                let mut new_tail = String::new();
-                                new_tail.push_str(" -> Any");
-                                new_tail.push_str(&signature_tail[colon_idx..]);
-                                signature_tail = new_tail;
-                                used_any = true;
-                            }
-                        }
-
-                        let mut rebuilt = String::new();
-                        rebuilt.push_str(&" ".repeat(indent_len));
-                        rebuilt.push_str(before);
-                        rebuilt.push_str(&fixed_params);
-                        rebuilt.push(')');
-                        rebuilt.push_str(&signature_tail);
-                        out.push_str(&rebuilt);
-                        out.push('\n');
-                        continue;
-                    }
-                }
-            }
-            out.push_str(line);
-            out.push('\n');
+    /// Annotates every untyped parameter and return in `source`: returns
+    /// with `int`, a list of `str`, or another builtin type when that is the
+    /// type inferred from the function body, parameters likewise when that
+    /// is the type the calls in the file pass, and everything else with
+    /// `Any`. Annotations are written for [`PythonVersion::DEFAULT`].
+    pub fn fix_source(source: &str) -> Result<String> {
+        Self::fix_source_for(source, PythonVersion::DEFAULT)
+    }
+
+    /// Like [`Fixer::fix_source`], but writes annotations valid on the
+    /// given Python version.
+    pub fn fix_source_for(source: &str, version: PythonVersion) -> Result<String> {
+        let file = SourceFile::from_text("<fix>", source);
+        let edits = Self::annotation_edits(&file, version)?;
+        Ok(apply_edits(source, &edits))
+    }
+
+    /// Computes the edits that annotate untyped signatures, including the
+    /// `from typing import ...` import of the names they use when one is
+    /// needed.
+    pub fn annotation_edits(file: &SourceFile, version: PythonVersion) -> Result<Vec<TextEdit>> {
+        let mut edits: Vec<TextEdit> = Self::signature_suggestions(file, version)?
+            .into_iter()
+            .flat_map(|suggestion| suggestion.edits)
+            .collect();
+        edits.extend(Self::import_edit(file, &edits));
+        edits.sort_by_key(|edit| edit.span.start_byte);
+        Ok(edits)
+    }
+
+    /// Computes one self-contained fix per untyped parameter or return, each
+    /// carrying the import it needs, so callers can apply them individually.
+    pub fn suggestions(file: &SourceFile) -> Result<Vec<Suggestion>> {
+        let mut suggestions = Self::signature_suggestions(file, PythonVersion::DEFAULT)?;
+        for suggestion in &mut suggestions {
+            let import = Self::import_edit(file, &suggestion.edits);
+            suggestion.edits.extend(import);
         }
+        Ok(suggestions)
+    }
 
-        if used_any
-            && !source.contains("from typing import Any")
-            && !out.contains("from typing import Any")
-        {
-            let lines: Vec<&str> = out.lines().collect();
-            let mut insert_at = 0usize;
-            if !lines.is_empty() && (lines[0].starts_with("#!") || lines[0].contains("coding")) {
-                insert_at = 1;
+    /// Suggestions for every function in the file, without imports.
+    fn signature_suggestions(file: &SourceFile, version: PythonVersion) -> Result<Vec<Suggestion>> {
+        let mut parser = Parser::new()?;
+        let tree = parser.parse_string(file.text())?;
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&tree, file.text().as_bytes())?;
+        let returns = analyzer.inferred_returns();
+        let parameters = analyzer
+            .infer_parameters(&tree, file, Unsolved::Report)
+            .types;
+        let mut suggestions = Vec::new();
+
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if node.kind() == "function_definition" {
+                let name = qualified_name(file, &node);
+                let inferred =
+                    Signature { parameters: parameters.get(&name), returns: returns.get(&name) };
+                Self::signature_edits(file, &node, inferred, version, &mut suggestions);
             }
-            let mut new_out = String::new();
-            for (i, l) in lines.iter().enumerate() {
-                if i == insert_at {
-                    new_out.push_str("from typing import Any\n");
-                }
-                new_out.push_str(l);
-                new_out.push('\n');
+            let mut cursor = node.walk();
+            for child in node.children(&mut cursor) {
+                stack.push(child);
             }
-            return new_out;
         }
+        suggestions.sort_by_key(|suggestion| suggestion.target.start_byte);
+        Ok(suggestions)
+    }
 
-        out
+    /// The `from typing import ...` insertion of the `typing` names `edits`
+    /// write, leaving out those the file already imports. `None` when there
+    /// is nothing left to import.
+    fn import_edit(file: &SourceFile, edits: &[TextEdit]) -> Option<TextEdit> {
+        let imported: BTreeSet<&str> = file
+            .text()
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("from typing import "))
+            .flat_map(|names| names.split(','))
+            .map(str::trim)
+            .collect();
+        let names: BTreeSet<&str> = edits
+            .iter()
+            .flat_map(|edit| typing_names(&edit.replacement))
+            .filter(|name| !imported.contains(name))
+            .collect();
+        if names.is_empty() {
+            return None;
+        }
+        let offset = Self::import_offset(file);
+        let names: Vec<&str> = names.into_iter().collect();
+        // A docstring on the last line may not end with a line break.
+        let separator = match &file.text()[..offset] {
+            "" => "",
+            before if before.ends_with('\n') => "",
+            _ => "\n",
+        };
+        Some(TextEdit::insert(
+            file.span(offset, offset),
+            format!("{}from typing import {}\n", separator, names.join(", ")),
+        ))
     }
 
-    fn annotate_params(params: &str, used_any: &mut bool) -> String {
-        let mut parts = Vec::new();
-        for raw in params.split(',') {
-            let p = raw.to_string();
-            let trimmed = p.trim();
-            if trimmed.is_empty() {
-                parts.push(p);
+    /// Collects insertions for the untyped parameters and return of one
+    /// function, using the inferred types that are builtins.
+    fn signature_edits(
+        file: &SourceFile,
+        node: &Node,
+        inferred: Signature,
+        version: PythonVersion,
+        suggestions: &mut Vec<Suggestion>,
+    ) {
+        let Some(params) = node.child_by_field_name("parameters") else {
+            return;
+        };
+
+        // `self` and `cls` are passed implicitly and left unannotated.
+        let receiver = FunctionKind::of(node, file.text().as_bytes()).receiver(&params);
+        for (p, _) in parameters(&params) {
+            if Some(p) == receiver || is_annotated(&p) {
                 continue;
             }
-            let leading_idx = p.find(trimmed).unwrap_or(0);
-            let leading = &p[..leading_idx];
-            let trailing = &p[leading_idx + trimmed.len()..];
-
-            if trimmed.starts_with('*') || trimmed.contains(':') {
-                parts.push(p);
+            let Some(name) = parameter_name(&p) else {
                 continue;
+            };
+            let end = name.end_byte();
+            let name = file.slice(&file.node_span(&name));
+            let annotation =
+                annotation(inferred.parameters.and_then(|types| types.get(name)), version);
+            suggestions.push(Suggestion {
+                target: file.node_span(&p),
+                title: format!("Annotate '{}' as {}", name, annotation),
+                edits: vec![TextEdit::insert(file.span(end, end), format!(": {}", annotation))],
+            });
+        }
+
+        if node.child_by_field_name("return_type").is_none() {
+            let name = node.child_by_field_name("name");
+            let end = params.end_byte();
+            let annotation = annotation(inferred.returns, version);
+            suggestions.push(Suggestion {
+                // The definition through its name, as diagnostics report it.
+                target: file.span(node.start_byte(), name.map_or(end, |n| n.end_byte())),
+                title: format!("Annotate return type as {}", annotation),
+                edits: vec![TextEdit::insert(file.span(end, end), format!(" -> {}", annotation))],
+            });
+        }
+    }
+
+    /// Byte offset for a new import: after a leading shebang and coding
+    /// line, the module docstring and any `from __future__` imports, which
+    /// Python requires before every other statement.
+    fn import_offset(file: &SourceFile) -> usize {
+        let line_start = |line| {
+            file.line_index()
+                .line_start(line)
+                .unwrap_or(file.text().len())
+        };
+        let mut line = 0;
+        while line < 2 {
+            match file.line_text(line) {
+                Some(text) if text.starts_with("#!") || text.contains("coding") => line += 1,
+                _ => break,
             }
-            let mut name = trimmed;
-            let mut default = "";
-            if let Some(eq_idx) = trimmed.find('=') {
-                name = trimmed[..eq_idx].trim();
-                default = &trimmed[eq_idx..];
+        }
+        let mut offset = line_start(line);
+        let Ok(tree) = Parser::new().and_then(|mut parser| parser.parse_string(file.text())) else {
+            return offset;
+        };
+        let root = tree.root_node();
+        let mut cursor = root.walk();
+        let mut first = true;
+        for statement in root.named_children(&mut cursor) {
+            let docstring = first
+                && statement.kind() == "expression_statement"
+                && statement
+                    .named_child(0)
+                    .is_some_and(|child| child.kind() == "string");
+            match statement.kind() {
+                "comment" => continue,
+                "future_import_statement" => {},
+                _ if docstring => {},
+                _ => break,
             }
-            let fixed = format!("{}{}: Any{}{}", leading, name, default, trailing);
-            *used_any = true;
-            parts.push(fixed);
+            first = false;
+            offset = line_start(statement.end_position().row + 1);
+        }
+        offset
+    }
+}
+
+/// Dotted name of a definition including enclosing classes and functions,
+/// as [`Analyzer`] names its scopes.
+fn qualified_name(file: &SourceFile, node: &Node) -> String {
+    let mut names: Vec<&str> = std::iter::successors(Some(*node), |n| n.parent())
+        .filter(|n| matches!(n.kind(), "function_definition" | "class_definition"))
+        .filter_map(|n| n.child_by_field_name("name"))
+        .map(|name| file.slice(&file.node_span(&name)))
+        .collect();
+    names.reverse();
+    names.join(".")
+}
+
+/// The types inferred for the parameters and return of a function.
+#[derive(Clone, Copy)]
+struct Signature<'a> {
+    parameters: Option<&'a BTreeMap<String, Type>>,
+    returns: Option<&'a Type>,
+}
+
+/// Names an annotation may need imported from `typing`.
+const TYPING_NAMES: &[&str] =
+    &["Any", "Callable", "Dict", "List", "Optional", "Set", "Tuple", "Type", "Union"];
+
+/// The annotation for an inferred type on Python `version`. Only builtin
+/// types and containers and unions of them are written out, as they need
+/// no import beyond `typing`. Everything else is `Any`.
+fn annotation(inferred: Option<&Type>, version: PythonVersion) -> String {
+    match inferred {
+        Some(ty) if is_builtin(ty) => ty.to_annotation(version),
+        _ => "Any".to_string(),
+    }
+}
+
+/// Whether `ty` is made up of builtin types only, with `Any` allowed for
+/// the parts of containers that weren't inferred.
+fn is_builtin(ty: &Type) -> bool {
+    let parts = |ty: &Type| matches!(ty, Type::Any) || is_builtin(ty);
+    match ty {
+        Type::None | Type::Bool | Type::Int | Type::Float | Type::Str | Type::Bytes => true,
+        Type::List(inner) | Type::Set(inner) => parts(inner),
+        Type::Dict(key, value) => parts(key) && parts(value),
+        Type::Tuple(items) => items.iter().all(parts),
+        Type::Union(members) => members.iter().all(is_builtin),
+        _ => false,
+    }
+}
+
+/// The `typing` names an annotation edit writes.
+fn typing_names(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| TYPING_NAMES.contains(word))
+}
+
+/// A single replacement of source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Range of the original text to replace; empty for insertions.
+    pub span: Span,
+    /// Text to put in place of the range.
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Creates an edit that inserts text at an empty span.
+    pub fn insert(span: Span, text: impl Into<String>) -> Self {
+        Self { span, replacement: text.into() }
+    }
+}
+
+/// A fix for one diagnostic, as offered to editors as a quick fix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The code the fix addresses; equal to the span of the diagnostic it resolves.
+    pub target: Span,
+    /// Short human-readable description.
+    pub title: String,
+    /// Edits that apply the fix, including any import it needs.
+    pub edits: Vec<TextEdit>,
+}
+
+/// Applies non-overlapping edits to `source`, returning the rewritten text.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| (edit.span.start_byte, edit.span.end_byte));
+
+    let mut out = String::with_capacity(source.len() + edits.len() * 8);
+    let mut last = 0;
+    for edit in sorted {
+        if edit.span.start_byte < last {
+            log::warn!("Skipping overlapping edit at {}", edit.span);
+            continue;
         }
-        parts.join(",")
+        out.push_str(&source[last..edit.span.start_byte]);
+        out.push_str(&edit.replacement);
+        last = edit.span.end_byte;
     }
+    out.push_str(&source[last..]);
+    out
 }
 
 #[cfg(test)]
@@ -165,4 +439,97 @@ mod tests {
         let fixer = Fixer::new(type_env, false);
         assert!(!fixer.in_place);
     }
+
+    #[test]
+    fn test_fix_source_annotates_signatures() {
+        let source = "#!/usr/bin/env python\ndef f(a, b=1, *args, c: int = 2, **kw):\n    \
+                      return a\n";
+        let fixed = Fixer::fix_source(source).unwrap();
+        assert_eq!(
+            fixed,
+            "#!/usr/bin/env python\nfrom typing import Any\ndef f(a: Any, b: Any=1, *args: Any, \
+             c: int = 2, **kw: Any) -> Any:\n    return a\n"
+        );
+        assert_eq!(Fixer::fix_source(&fixed).unwrap(), fixed);
+    }
+
+    #[test]
+    fn test_fix_imports_after_docstring_and_future_imports() {
+        let source = "#!/usr/bin/env python\n\"\"\"Tools.\n\nMore.\n\"\"\"\n# Note.\n\
+                      from __future__ import annotations\nfrom __future__ import division\n\
+                      import os\n\ndef f(a):\n    return a\n";
+        let fixed = Fixer::fix_source(source).unwrap();
+        assert_eq!(
+            fixed,
+            "#!/usr/bin/env python\n\"\"\"Tools.\n\nMore.\n\"\"\"\n# Note.\n\
+             from __future__ import annotations\nfrom __future__ import division\n\
+             from typing import Any\nimport os\n\ndef f(a: Any) -> Any:\n    return a\n"
+        );
+        let fixed = Fixer::fix_source("'''Docs.'''\ndef f(a):\n    return a\n").unwrap();
+        assert_eq!(
+            fixed,
+            "'''Docs.'''\nfrom typing import Any\ndef f(a: Any) -> Any:\n    return a\n"
+        );
+    }
+
+    #[test]
+    fn test_fix_uses_inferred_return_types() {
+        let source = "class C:\n    def name(self, flag: bool):\n        if flag:\n            \
+                      return 'c'\n        return 'd'\n\n    def clear(self):\n        pass\n";
+        let fixed = Fixer::fix_source(source).unwrap();
+        assert_eq!(
+            fixed,
+            "class C:\n    def name(self, flag: bool) -> str:\n        \
+             if flag:\n            return 'c'\n        return 'd'\n\n    def clear(self) \
+             -> None:\n        pass\n"
+        );
+        let fixed = Fixer::fix_source("def f(n: int):\n    return n * 2\n").unwrap();
+        assert_eq!(fixed, "def f(n: int) -> int:\n    return n * 2\n");
+    }
+
+    #[test]
+    fn test_fix_uses_inferred_parameter_types() {
+        let source = "def scale(n, factor, label):\n    return n * factor\n\n\
+                      scale(2, 1.5, 'a')\nscale(3, 2.0, label=None)\n";
+        let fixed = Fixer::fix_source(source).unwrap();
+        assert_eq!(
+            fixed,
+            "from typing import Any, Optional\ndef scale(n: int, factor: float, label: \
+             Optional[str]) -> Any:\n    return n * factor\n\nscale(2, 1.5, 'a')\n\
+             scale(3, 2.0, label=None)\n"
+        );
+    }
+
+    #[test]
+    fn test_fix_writes_annotations_for_python_version() {
+        let source = "from typing import Optional\ndef tag(label):\n    pass\n\n\
+                      def ids():\n    return {1: [2]}\n\ntag('a')\ntag(None)\n";
+        let fixed = Fixer::fix_source_for(source, PythonVersion::new(3, 8)).unwrap();
+        assert_eq!(
+            fixed,
+            "from typing import Dict, List\nfrom typing import Optional\ndef tag(label: \
+             Optional[str]) -> None:\n    pass\n\ndef ids() -> Dict[int, List[int]]:\n    \
+             return {1: [2]}\n\ntag('a')\ntag(None)\n"
+        );
+        let fixed = Fixer::fix_source_for(source, PythonVersion::new(3, 10)).unwrap();
+        assert_eq!(
+            fixed,
+            "from typing import Optional\ndef tag(label: str | None) -> None:\n    pass\n\n\
+             def ids() -> dict[int, list[int]]:\n    return {1: [2]}\n\ntag('a')\ntag(None)\n"
+        );
+    }
+
+    #[test]
+    fn test_suggestions_match_diagnostics() {
+        let file = SourceFile::from_text("a.py", "def f(a, b: int):\n    return a\n");
+        let suggestions = Fixer::suggestions(&file).unwrap();
+        let result = crate::analyzer::Analyzer::analyze_python_source(&file).unwrap();
+        let mut spans: Vec<Span> = result.diagnostics.iter().map(|d| d.span).collect();
+        spans.sort_by_key(|span| span.start_byte);
+        let targets: Vec<Span> = suggestions.iter().map(|s| s.target).collect();
+        assert_eq!(targets, spans);
+        assert_eq!(suggestions[1].title, "Annotate 'a' as Any");
+        let fixed = apply_edits(file.text(), &suggestions[1].edits);
+        assert_eq!(fixed, "from typing import Any\ndef f(a: Any, b: int):\n    return a\n");
+    }
 }
@@ -2,26 +2,46 @@
 //!
 //! This library provides static and runtime type checking capabilities,
 //! with support for type inference and automatic type annotation.
+//!
+//! [`project::Project`] is the simplest way to embed the checker; the
+//! modules below it expose the individual stages.
 
 #![warn(missing_docs)]
 #![warn(rustdoc::missing_crate_level_docs)]
 
 pub mod analyzer;
+pub mod config;
+pub mod coverage;
+pub mod daemon;
+pub mod deps;
+pub mod diagnostics;
+pub mod diff;
 pub mod error;
 pub mod fixer;
+pub mod lsp;
 pub mod parser;
+pub mod project;
+#[cfg(feature = "pyo3")]
+mod python;
 pub mod solver;
+#[cfg(feature = "tracer")]
 pub mod tracer;
 pub mod types;
 pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 /// Re-exports commonly used types and traits.
 pub mod prelude {
-    pub use crate::error::{Error, Result};
+    pub use crate::diagnostics::{Diagnostic, Severity};
+    pub use crate::error::{Error, Partial, Result};
+    pub use crate::project::Project;
 }
 
-/// The main entry point for the omnitype application.
-pub fn run() -> crate::error::Result<()> {
-    // Application initialization and execution will be implemented here
-    Ok(())
+/// Checks the current directory with its configuration, failing when its
+/// files can't be listed or analyzed.
+#[deprecated(note = "use `project::Project`, which also returns the diagnostics")]
+pub fn run() -> error::Result<()> {
+    let report = project::Project::discover(".")?.check()?;
+    error::Partial { value: (), errors: report.errors }.into_result()
 }
@@ -0,0 +1,115 @@
+//! Open editor buffers and incremental text synchronization.
+
+use std::path::PathBuf;
+
+use super::protocol::{offset_of, uri_to_path, ContentChange};
+use crate::parser::Language;
+use crate::utils::SourceFile;
+
+/// An open document, kept in sync with the client's buffer.
+#[derive(Debug, Clone)]
+pub struct Document {
+    uri: String,
+    path: Option<PathBuf>,
+    version: i64,
+    language: Language,
+    file: SourceFile,
+}
+
+impl Document {
+    /// Creates a document from the text sent on open. The language is taken
+    /// from the URI's extension, defaulting to Python.
+    pub fn new(uri: impl Into<String>, version: i64, text: impl Into<String>) -> Self {
+        let uri = uri.into();
+        let path = uri_to_path(&uri);
+        let language = path
+            .as_deref()
+            .and_then(Language::from_path)
+            .unwrap_or(Language::Python);
+        let file = SourceFile::from_text(display_path(&uri, &path), text);
+        Self { uri, path, version, language, file }
+    }
+
+    /// The document URI.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// The file the document was opened from, for `file://` URIs.
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// The client's version number for the current text.
+    pub fn version(&self) -> i64 {
+        self.version
+    }
+
+    /// The document's language.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// The current text with its line index.
+    pub fn file(&self) -> &SourceFile {
+        &self.file
+    }
+
+    /// Applies changes in order and records the new version. Ranged changes
+    /// splice the text; a change without a range replaces it entirely.
+    pub fn apply_changes(&mut self, version: i64, changes: &[ContentChange]) {
+        for change in changes {
+            let text = match change.range {
+                Some(range) => {
+                    let start = offset_of(&self.file, range.start);
+                    let end = offset_of(&self.file, range.end).max(start);
+                    let old = self.file.text();
+                    let mut text = String::with_capacity(old.len() + change.text.len());
+                    text.push_str(&old[..start]);
+                    text.push_str(&change.text);
+                    text.push_str(&old[end..]);
+                    text
+                },
+                None => change.text.clone(),
+            };
+            self.file = SourceFile::from_text(self.file.path(), text);
+        }
+        self.version = version;
+    }
+}
+
+/// The path diagnostics report for a document: its file path if it has one.
+fn display_path(uri: &str, path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map_or_else(|| uri.to_string(), |p| p.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp::protocol::{Position, Range};
+
+    #[test]
+    fn test_incremental_changes() {
+        let mut doc = Document::new("file:///p/a.py", 1, "s = 'é'\nx = 1\n");
+        assert_eq!(doc.file().path(), "/p/a.py");
+        let at = |line, character| Position { line, character };
+        // Replace `1` on the second line, then insert after the UTF-16 column of `'`.
+        let changes = [
+            ContentChange {
+                range: Some(Range { start: at(1, 4), end: at(1, 5) }),
+                text: "2".into(),
+            },
+            ContentChange {
+                range: Some(Range { start: at(0, 7), end: at(0, 7) }),
+                text: "!".into(),
+            },
+        ];
+        doc.apply_changes(2, &changes);
+        assert_eq!(doc.file().text(), "s = 'é'!\nx = 2\n");
+        assert_eq!(doc.version(), 2);
+
+        doc.apply_changes(3, &[ContentChange { range: None, text: "y = 3\n".into() }]);
+        assert_eq!(doc.file().text(), "y = 3\n");
+    }
+}
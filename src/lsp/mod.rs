@@ -0,0 +1,28 @@
+//! Language Server Protocol support.
+//!
+//! `omnitype lsp` speaks JSON-RPC over stdio and offers:
+//!
+//! - incremental document sync
+//! - pull diagnostics (`textDocument/diagnostic`), filtered by the project
+//!   configuration
+//! - hover with declared or inferred types
+//! - go-to-definition, following imports into other project modules
+//! - quick fixes backed by [`Fixer::suggestions`](crate::fixer::Fixer::suggestions)
+
+mod document;
+mod navigation;
+mod protocol;
+mod server;
+
+pub use document::Document;
+pub use protocol::{codes, path_to_uri, read_message, uri_to_path, write_message, Message};
+pub use server::Server;
+
+use crate::error::Result;
+
+/// Runs a language server on stdin and stdout until the client exits.
+pub fn run_stdio() -> Result<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    Server::new().serve(stdin.lock(), stdout.lock())
+}
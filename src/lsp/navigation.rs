@@ -0,0 +1,360 @@
+//! Symbol lookup behind hover and go-to-definition.
+//!
+//! Python files are scanned for the names they bind: functions, classes,
+//! parameters, assigned variables, and imports. Each binding is visible
+//! within the byte range of its enclosing function, class, or module, and a
+//! reference resolves to the innermost visible binding of its name.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use tree_sitter::{Node, Tree};
+
+use crate::analyzer::expression_type;
+use crate::types::Type;
+use crate::utils::{ModuleResolver, SourceFile, Span};
+
+/// What kind of binding a symbol is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SymbolKind {
+    Function,
+    Class,
+    Variable,
+    Parameter,
+    Import,
+}
+
+/// A name bound in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Span of the binding occurrence of the name.
+    pub span: Span,
+    /// Byte range in which the name is visible.
+    pub scope: Range<usize>,
+    /// Hover text, as Python code.
+    pub detail: String,
+    /// Where an imported name comes from.
+    pub import: Option<ImportTarget>,
+}
+
+/// The source of an imported name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ImportTarget {
+    /// Dotted module name, without leading dots.
+    pub module: String,
+    /// Number of leading dots of a relative import.
+    pub level: usize,
+    /// The imported attribute for `from module import name`.
+    pub name: Option<String>,
+}
+
+/// Collects every binding in a Python file.
+pub(super) fn symbols(tree: &Tree, file: &SourceFile) -> Vec<Symbol> {
+    let mut out = Vec::new();
+    let root = tree.root_node();
+    visit(&root, 0..file.text().len(), file, &mut out);
+    out
+}
+
+fn visit(node: &Node, scope: Range<usize>, file: &SourceFile, out: &mut Vec<Symbol>) {
+    let own_scope = node.start_byte()..node.end_byte();
+    match node.kind() {
+        "function_definition" => {
+            if let Some(name) = node.child_by_field_name("name") {
+                let detail = header(file, node);
+                out.push(symbol(file, &name, SymbolKind::Function, scope.clone(), detail));
+            }
+            if let Some(params) = node.child_by_field_name("parameters") {
+                let mut cursor = params.walk();
+                for param in params.named_children(&mut cursor) {
+                    parameter(file, &param, own_scope.clone(), out);
+                }
+            }
+            if let Some(body) = node.child_by_field_name("body") {
+                visit(&body, own_scope, file, out);
+            }
+            return;
+        },
+        "class_definition" => {
+            if let Some(name) = node.child_by_field_name("name") {
+                let detail = header(file, node);
+                out.push(symbol(file, &name, SymbolKind::Class, scope.clone(), detail));
+            }
+            if let Some(body) = node.child_by_field_name("body") {
+                visit(&body, own_scope, file, out);
+            }
+            return;
+        },
+        "assignment" => {
+            let left = node.child_by_field_name("left");
+            if let Some(left) = left.filter(|l| l.kind() == "identifier") {
+                let name = text(file, &left);
+                let ty = match node.child_by_field_name("type") {
+                    Some(annotation) => text(file, &annotation).to_string(),
+                    None => node
+                        .child_by_field_name("right")
+                        .map(|right| expression_type(&right, file.text().as_bytes()))
+                        .filter(|ty| *ty != Type::Unknown)
+                        .map(|ty| ty.to_string())
+                        .unwrap_or_default(),
+                };
+                let detail =
+                    if ty.is_empty() { name.to_string() } else { format!("{}: {}", name, ty) };
+                out.push(symbol(file, &left, SymbolKind::Variable, scope.clone(), detail));
+            }
+        },
+        "import_statement" => {
+            let mut cursor = node.walk();
+            for name in node.children_by_field_name("name", &mut cursor) {
+                let (dotted, alias) = split_alias(&name);
+                let module = text(file, &dotted).to_string();
+                // `import a.b` binds `a`; `import a.b as c` binds `c` to `a.b`.
+                let (bound, target) = match alias {
+                    Some(alias) => (alias, module.clone()),
+                    None => {
+                        let first = dotted.named_child(0).unwrap_or(dotted);
+                        (first, text(file, &first).to_string())
+                    },
+                };
+                let detail = format!("import {}", text(file, &name));
+                let import = ImportTarget { module: target, level: 0, name: None };
+                out.push(Symbol {
+                    import: Some(import),
+                    ..symbol(file, &bound, SymbolKind::Import, scope.clone(), detail)
+                });
+            }
+        },
+        "import_from_statement" => {
+            let Some(module) = node.child_by_field_name("module_name") else {
+                return;
+            };
+            let module_text = text(file, &module);
+            let level = module_text.chars().take_while(|&c| c == '.').count();
+            let dotted = module_text[level..].to_string();
+            let mut cursor = node.walk();
+            for name in node.children_by_field_name("name", &mut cursor) {
+                let (imported, alias) = split_alias(&name);
+                let bound = alias.unwrap_or(imported);
+                let detail = format!("from {} import {}", module_text, text(file, &name));
+                let import = ImportTarget {
+                    module: dotted.clone(),
+                    level,
+                    name: Some(text(file, &imported).to_string()),
+                };
+                out.push(Symbol {
+                    import: Some(import),
+                    ..symbol(file, &bound, SymbolKind::Import, scope.clone(), detail)
+                });
+            }
+            return;
+        },
+        _ => {},
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        visit(&child, scope.clone(), file, out);
+    }
+}
+
+/// Records a parameter, with its annotation or the inferred type of its default.
+fn parameter(file: &SourceFile, param: &Node, scope: Range<usize>, out: &mut Vec<Symbol>) {
+    let (name, ty) = match param.kind() {
+        "identifier" => (*param, None),
+        "typed_parameter" | "typed_default_parameter" => {
+            let name = match param.child_by_field_name("name") {
+                Some(name) => name,
+                // `*args: int` nests the name in a splat pattern.
+                None => match param.named_child(0) {
+                    Some(name) => name.named_child(0).unwrap_or(name),
+                    None => return,
+                },
+            };
+            let ty = param
+                .child_by_field_name("type")
+                .map(|t| text(file, &t).to_string());
+            (name, ty)
+        },
+        "default_parameter" => {
+            let Some(name) = param.child_by_field_name("name") else {
+                return;
+            };
+            let ty = param
+                .child_by_field_name("value")
+                .map(|value| expression_type(&value, file.text().as_bytes()))
+                .filter(|ty| *ty != Type::Unknown)
+                .map(|ty| ty.to_string());
+            (name, ty)
+        },
+        "list_splat_pattern" | "dictionary_splat_pattern" => match param.named_child(0) {
+            Some(name) => (name, None),
+            None => return,
+        },
+        _ => return,
+    };
+    let name_text = text(file, &name);
+    let detail = match ty {
+        Some(ty) => format!("(parameter) {}: {}", name_text, ty),
+        None => format!("(parameter) {}", name_text),
+    };
+    out.push(symbol(file, &name, SymbolKind::Parameter, scope, detail));
+}
+
+fn symbol(
+    file: &SourceFile,
+    name: &Node,
+    kind: SymbolKind,
+    scope: Range<usize>,
+    detail: String,
+) -> Symbol {
+    Symbol {
+        name: text(file, name).to_string(),
+        kind,
+        span: file.node_span(name),
+        scope,
+        detail,
+        import: None,
+    }
+}
+
+/// The source of a definition up to its body, e.g. `def f(x: int) -> str`.
+fn header(file: &SourceFile, node: &Node) -> String {
+    let end = node
+        .child_by_field_name("body")
+        .map_or(node.end_byte(), |body| body.start_byte());
+    let text = &file.text()[node.start_byte()..end];
+    text.trim_end().trim_end_matches(':').trim_end().to_string()
+}
+
+/// Splits an `aliased_import` into its name and alias.
+fn split_alias<'t>(node: &Node<'t>) -> (Node<'t>, Option<Node<'t>>) {
+    if node.kind() == "aliased_import" {
+        if let Some(name) = node.child_by_field_name("name") {
+            return (name, node.child_by_field_name("alias"));
+        }
+    }
+    (*node, None)
+}
+
+fn text<'f>(file: &'f SourceFile, node: &Node) -> &'f str {
+    file.slice(&file.node_span(node))
+}
+
+/// The identifier at or immediately before `offset`, so a cursor just past
+/// the end of a name still finds it.
+pub(super) fn identifier_at<'t>(tree: &'t Tree, offset: usize) -> Option<Node<'t>> {
+    let root = tree.root_node();
+    [offset, offset.saturating_sub(1)]
+        .into_iter()
+        .find_map(|at| {
+            root.descendant_for_byte_range(at, at)
+                .filter(|node| node.kind() == "identifier")
+        })
+}
+
+/// Resolves a name used at `offset` to the binding visible there: the one in
+/// the innermost enclosing scope, preferring the last binding before the use.
+pub(super) fn resolve<'s>(symbols: &'s [Symbol], name: &str, offset: usize) -> Option<&'s Symbol> {
+    let visible: Vec<&Symbol> = symbols
+        .iter()
+        .filter(|s| s.name == name && s.scope.start <= offset && offset <= s.scope.end)
+        .collect();
+    let innermost = visible.iter().map(|s| s.scope.len()).min()?;
+    let candidates: Vec<&Symbol> = visible
+        .into_iter()
+        .filter(|s| s.scope.len() == innermost)
+        .collect();
+    candidates
+        .iter()
+        .rev()
+        .find(|s| s.span.start_byte <= offset)
+        .or_else(|| candidates.first())
+        .copied()
+}
+
+/// Finds the file an import refers to. Relative imports are resolved
+/// against the importing file's directory.
+pub(super) fn import_file(
+    target: &ImportTarget,
+    importer: &Path,
+    resolver: &ModuleResolver,
+) -> Option<PathBuf> {
+    let resolver = if target.level > 0 {
+        let mut base = importer.parent()?;
+        for _ in 1..target.level {
+            base = base.parent()?;
+        }
+        if target.module.is_empty() {
+            return ["__init__.pyi", "__init__.py"]
+                .iter()
+                .map(|name| base.join(name))
+                .find(|path| path.is_file());
+        }
+        ModuleResolver::new([base])
+    } else {
+        resolver.clone()
+    };
+    // `from pkg import mod` may name a submodule rather than an attribute.
+    let submodule = target.name.as_ref().and_then(|name| {
+        let full = if target.module.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", target.module, name)
+        };
+        resolver.resolve_file(&full)
+    });
+    submodule.or_else(|| resolver.resolve_file(&target.module))
+}
+
+/// The last top-level binding of `name` in a file, for resolving names
+/// imported from it.
+pub(super) fn top_level<'s>(
+    symbols: &'s [Symbol],
+    file: &SourceFile,
+    name: &str,
+) -> Option<&'s Symbol> {
+    symbols
+        .iter()
+        .rev()
+        .find(|s| s.name == name && s.scope == (0..file.text().len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_symbols_and_resolution() {
+        let source = "from .models import User as U\nLIMIT = 10\n\ndef f(a, b=1.5, *args: int) \
+                      -> U:\n    LIMIT = 'x'\n    return a + LIMIT\n\nprint(LIMIT)\n";
+        let file = SourceFile::from_text("a.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+        let symbols = symbols(&tree, &file);
+
+        let details: Vec<&str> = symbols.iter().map(|s| s.detail.as_str()).collect();
+        assert_eq!(
+            details,
+            [
+                "from .models import User as U",
+                "LIMIT: int",
+                "def f(a, b=1.5, *args: int) -> U",
+                "(parameter) a",
+                "(parameter) b: float",
+                "(parameter) args: int",
+                "LIMIT: str",
+            ]
+        );
+        let import = symbols[0].import.as_ref().unwrap();
+        assert_eq!((import.module.as_str(), import.level), ("models", 1));
+        assert_eq!(import.name.as_deref(), Some("User"));
+
+        let inner = source.find("a + LIMIT").unwrap() + 4;
+        assert_eq!(resolve(&symbols, "LIMIT", inner).unwrap().detail, "LIMIT: str");
+        let outer = source.rfind("LIMIT").unwrap();
+        assert_eq!(resolve(&symbols, "LIMIT", outer).unwrap().detail, "LIMIT: int");
+        let use_of_a = identifier_at(&tree, inner - 3).unwrap();
+        assert_eq!(use_of_a.utf8_text(source.as_bytes()).unwrap(), "a");
+    }
+}
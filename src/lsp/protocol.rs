@@ -0,0 +1,351 @@
+//! JSON-RPC framing and the subset of LSP types the server uses.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::utils::{SourceFile, Span};
+
+/// JSON-RPC error codes used in responses.
+pub mod codes {
+    /// The request is not valid in the server's current state.
+    pub const INVALID_REQUEST: i64 = -32600;
+    /// The method is not supported.
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    /// The parameters could not be decoded.
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// The request failed inside the server.
+    pub const INTERNAL_ERROR: i64 = -32603;
+}
+
+/// A JSON-RPC request, notification, or response.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    /// Protocol version; always `"2.0"`.
+    #[serde(default)]
+    pub jsonrpc: String,
+    /// Request id; absent for notifications.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    /// Method name of a request or notification.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Parameters of a request or notification.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    /// Result of a successful request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Error of a failed request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+}
+
+/// The `error` member of a failed response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseError {
+    /// One of the [`codes`].
+    pub code: i64,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl Message {
+    /// Creates a request.
+    pub fn request(id: impl Into<Value>, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id.into()),
+            method: Some(method.to_string()),
+            params: Some(params),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a notification.
+    pub fn notification(method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: Some(method.to_string()),
+            params: Some(params),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a successful response.
+    pub fn response(id: Value, result: Value) -> Self {
+        Self { jsonrpc: "2.0".to_string(), id: Some(id), result: Some(result), ..Self::default() }
+    }
+
+    /// Creates an error response.
+    pub fn error_response(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            error: Some(ResponseError { code, message: message.into() }),
+            ..Self::default()
+        }
+    }
+
+    /// Decodes the parameters into `T`.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T> {
+        let params = self.params.clone().unwrap_or(Value::Null);
+        serde_json::from_value(params).map_err(|e| {
+            Error::invalid_value("params", self.method.clone().unwrap_or_default(), e.to_string())
+        })
+    }
+}
+
+/// Reads one `Content-Length`-framed message. Returns `None` at end of input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Message>> {
+    let invalid = |message: String| Error::Io(io::Error::new(io::ErrorKind::InvalidData, message));
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let value = value.trim();
+                length = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| invalid(format!("Invalid Content-Length: {}", value)))?,
+                );
+            }
+        }
+    }
+    let length = length.ok_or_else(|| invalid("Missing Content-Length header".to_string()))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| invalid(format!("Malformed message: {}", e)))
+}
+
+/// Writes one message with its `Content-Length` header.
+pub fn write_message<W: Write>(writer: &mut W, message: &Message) -> Result<()> {
+    let body = serde_json::to_string(message)
+        .map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// A position as LSP clients send it: line and UTF-16 column.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    /// 0-based line.
+    pub line: usize,
+    /// 0-based column in UTF-16 code units.
+    pub character: usize,
+}
+
+/// A half-open range between two positions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    /// Start position.
+    pub start: Position,
+    /// End position (exclusive).
+    pub end: Position,
+}
+
+impl Range {
+    /// The LSP range of a span.
+    pub fn of_span(span: &Span) -> Self {
+        Self {
+            start: Position { line: span.start.line, character: span.start.utf16_column },
+            end: Position { line: span.end.line, character: span.end.utf16_column },
+        }
+    }
+}
+
+/// Converts a client position into a byte offset, clamping positions past
+/// the end of a line or of the file.
+pub fn offset_of(file: &SourceFile, position: Position) -> usize {
+    file.line_index()
+        .offset_utf16(position.line, position.character)
+        .unwrap_or(file.text().len())
+}
+
+/// A document reference in request parameters.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextDocumentIdentifier {
+    /// Document URI.
+    pub uri: String,
+}
+
+/// Parameters of `textDocument/didOpen`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidOpenParams {
+    /// The opened document.
+    pub text_document: TextDocumentItem,
+}
+
+/// A document's full content, as sent on open.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDocumentItem {
+    /// Document URI.
+    pub uri: String,
+    /// Version number, increasing with each change.
+    pub version: i64,
+    /// Full text.
+    pub text: String,
+}
+
+/// Parameters of `textDocument/didChange`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeParams {
+    /// The changed document and its new version.
+    pub text_document: VersionedTextDocumentIdentifier,
+    /// Changes, to be applied in order.
+    pub content_changes: Vec<ContentChange>,
+}
+
+/// A document reference with a version.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionedTextDocumentIdentifier {
+    /// Document URI.
+    pub uri: String,
+    /// Version after the change.
+    pub version: i64,
+}
+
+/// One edit to a document; without a range it replaces the whole text.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentChange {
+    /// The replaced range.
+    #[serde(default)]
+    pub range: Option<Range>,
+    /// The new text.
+    pub text: String,
+}
+
+/// Parameters of requests that only name a document.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDocumentParams {
+    /// The document.
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Parameters of hover and definition requests.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDocumentPositionParams {
+    /// The document.
+    pub text_document: TextDocumentIdentifier,
+    /// The cursor position.
+    pub position: Position,
+}
+
+/// Parameters of `textDocument/codeAction`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeActionParams {
+    /// The document.
+    pub text_document: TextDocumentIdentifier,
+    /// The selected range.
+    pub range: Range,
+    /// Diagnostics overlapping the range.
+    #[serde(default)]
+    pub context: CodeActionContext,
+}
+
+/// Context of a code action request.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CodeActionContext {
+    /// Diagnostics the client shows within the range, passed back unchanged
+    /// on matching actions.
+    #[serde(default)]
+    pub diagnostics: Vec<Value>,
+}
+
+/// Converts a `file://` URI into a path, or `None` for other schemes.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // Skip an authority such as `localhost`.
+    let path = &rest[rest.find('/')?..];
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // `file:///C:/x` names the Windows path `C:/x`.
+    let windows = path.len() > 2 && path.as_bytes()[2] == b':';
+    Some(PathBuf::from(if windows { &path[1..] } else { &path[..] }))
+}
+
+/// Converts an absolute path into a `file://` URI.
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_message_framing_roundtrip() {
+        let message = Message::request(1, "textDocument/hover", json!({"x": "é"}));
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &message).unwrap();
+        write_message(&mut buffer, &Message::notification("exit", Value::Null)).unwrap();
+
+        let mut reader = io::Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        let exit = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(exit.method.as_deref(), Some("exit"));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_uri_conversion() {
+        let path = Path::new("/home/me/my project/a.py");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///home/me/my%20project/a.py");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+        assert_eq!(uri_to_path("file:///C:/src/a.py"), Some(PathBuf::from("C:/src/a.py")));
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    }
+}
@@ -0,0 +1,434 @@
+//! Request dispatch and the language features built on the analyzer.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use super::document::Document;
+use super::navigation::{self, Symbol, SymbolKind};
+use super::protocol::{
+    codes, offset_of, path_to_uri, read_message, uri_to_path, write_message, CodeActionParams,
+    DidChangeParams, DidOpenParams, Message, Position, Range, TextDocumentParams,
+    TextDocumentPositionParams,
+};
+use crate::analyzer::{Analyzer, RuleSet};
+use crate::config::Config;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::{Error, Result};
+use crate::fixer::{Fixer, TextEdit};
+use crate::parser::{Language, Parser};
+use crate::utils::{ModuleResolver, SourceFile};
+
+/// A language server session: the open documents and workspace settings.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    root: Option<PathBuf>,
+    config: Config,
+    rules: RuleSet,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Server {
+    /// Creates a server with no open documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves requests from `reader` until the client sends `exit` or closes
+    /// the stream.
+    pub fn serve<R: BufRead, W: Write>(&mut self, mut reader: R, mut writer: W) -> Result<()> {
+        while let Some(message) = read_message(&mut reader)? {
+            if let Some(response) = self.handle(message) {
+                write_message(&mut writer, &response)?;
+            }
+            if self.exited {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one message, returning the response for requests.
+    pub fn handle(&mut self, message: Message) -> Option<Message> {
+        // Responses to server-initiated requests carry no method.
+        let method = message.method.clone()?;
+        let Some(id) = message.id.clone() else {
+            if let Err(e) = self.notify(&method, &message) {
+                log::warn!("Failed to handle {}: {}", method, e);
+            }
+            return None;
+        };
+        if self.shutdown {
+            return Some(Message::error_response(
+                id,
+                codes::INVALID_REQUEST,
+                "Server is shut down",
+            ));
+        }
+        let result = match method.as_str() {
+            "initialize" => message.params().and_then(|p| self.initialize(p)),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            },
+            "textDocument/diagnostic" => message.params().and_then(|p| self.diagnostic(p)),
+            "textDocument/hover" => message.params().and_then(|p| self.hover(p)),
+            "textDocument/definition" => message.params().and_then(|p| self.definition(p)),
+            "textDocument/codeAction" => message.params().and_then(|p| self.code_action(p)),
+            _ => {
+                let text = format!("Unsupported method: {}", method);
+                return Some(Message::error_response(id, codes::METHOD_NOT_FOUND, text));
+            },
+        };
+        Some(match result {
+            Ok(result) => Message::response(id, result),
+            Err(e) => {
+                let code = match e {
+                    Error::Argument(_) => codes::INVALID_PARAMS,
+                    _ => codes::INTERNAL_ERROR,
+                };
+                Message::error_response(id, code, e.to_string())
+            },
+        })
+    }
+
+    /// The open document with the given URI.
+    pub fn document(&self, uri: &str) -> Option<&Document> {
+        self.documents.get(uri)
+    }
+
+    fn notify(&mut self, method: &str, message: &Message) -> Result<()> {
+        match method {
+            "textDocument/didOpen" => {
+                let params: DidOpenParams = message.params()?;
+                let item = params.text_document;
+                self.documents
+                    .insert(item.uri.clone(), Document::new(item.uri, item.version, item.text));
+            },
+            "textDocument/didChange" => {
+                let params: DidChangeParams = message.params()?;
+                let uri = &params.text_document.uri;
+                let document = self.documents.get_mut(uri).ok_or_else(|| unknown(uri))?;
+                document.apply_changes(params.text_document.version, &params.content_changes);
+            },
+            "textDocument/didClose" => {
+                let params: TextDocumentParams = message.params()?;
+                self.documents.remove(&params.text_document.uri);
+            },
+            "exit" => self.exited = true,
+            _ => log::debug!("Ignoring notification {}", method),
+        }
+        Ok(())
+    }
+
+    fn initialize(&mut self, params: Value) -> Result<Value> {
+        let root_uri = params.get("rootUri").and_then(Value::as_str).or_else(|| {
+            params
+                .pointer("/workspaceFolders/0/uri")
+                .and_then(Value::as_str)
+        });
+        self.root = root_uri.and_then(uri_to_path).or_else(|| {
+            params
+                .get("rootPath")
+                .and_then(Value::as_str)
+                .map(PathBuf::from)
+        });
+        if let Some(root) = &self.root {
+            self.config = Config::discover(root).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid configuration: {}", e);
+                Config::default()
+            });
+            self.rules = RuleSet::discover(root).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid custom rules: {}", e);
+                RuleSet::default()
+            });
+        }
+        Ok(json!({
+            "capabilities": {
+                "textDocumentSync": { "openClose": true, "change": 2 },
+                "diagnosticProvider": {
+                    "interFileDependencies": false,
+                    "workspaceDiagnostics": false,
+                },
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "codeActionProvider": { "codeActionKinds": ["quickfix"] },
+            },
+            "serverInfo": { "name": "omnitype", "version": env!("CARGO_PKG_VERSION") },
+        }))
+    }
+
+    fn diagnostic(&self, params: TextDocumentParams) -> Result<Value> {
+        let document = self.open(&params.text_document.uri)?;
+        let items: Vec<Value> = self
+            .diagnostics(document)?
+            .iter()
+            .map(lsp_diagnostic)
+            .collect();
+        Ok(json!({ "kind": "full", "items": items }))
+    }
+
+    fn hover(&self, params: TextDocumentPositionParams) -> Result<Value> {
+        let document = self.open(&params.text_document.uri)?;
+        let Some((symbol, range)) = self.symbol_at(document, params.position)? else {
+            return Ok(Value::Null);
+        };
+        let detail = match &symbol.import {
+            Some(_) => self
+                .import_definition(document, &symbol)
+                .and_then(|(_, _, target)| target)
+                .map_or(symbol.detail.clone(), |target| target.detail),
+            None => symbol.detail.clone(),
+        };
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": format!("```python\n{}\n```", detail) },
+            "range": range,
+        }))
+    }
+
+    fn definition(&self, params: TextDocumentPositionParams) -> Result<Value> {
+        let document = self.open(&params.text_document.uri)?;
+        let Some((symbol, _)) = self.symbol_at(document, params.position)? else {
+            return Ok(Value::Null);
+        };
+        if symbol.kind == SymbolKind::Import {
+            if let Some((uri, file, target)) = self.import_definition(document, &symbol) {
+                // A module, or a name the module doesn't bind itself, goes to the file.
+                let span = target.map_or_else(|| file.span(0, 0), |target| target.span);
+                return Ok(json!({ "uri": uri, "range": Range::of_span(&span) }));
+            }
+        }
+        Ok(json!({ "uri": document.uri(), "range": Range::of_span(&symbol.span) }))
+    }
+
+    fn code_action(&self, params: CodeActionParams) -> Result<Value> {
+        let document = self.open(&params.text_document.uri)?;
+        if document.language() != Language::Python {
+            return Ok(json!([]));
+        }
+        let file = document.file();
+        let start = offset_of(file, params.range.start);
+        let end = offset_of(file, params.range.end);
+        let actions: Vec<Value> = Fixer::suggestions(file)?
+            .into_iter()
+            .filter(|s| s.target.start_byte <= end && start <= s.target.end_byte)
+            .map(|suggestion| {
+                let range = json!(Range::of_span(&suggestion.target));
+                let fixes: Vec<&Value> = params
+                    .context
+                    .diagnostics
+                    .iter()
+                    .filter(|d| d.get("range") == Some(&range))
+                    .collect();
+                let edits: Vec<Value> = suggestion.edits.iter().map(lsp_edit).collect();
+                json!({
+                    "title": suggestion.title,
+                    "kind": "quickfix",
+                    "diagnostics": fixes,
+                    "isPreferred": true,
+                    "edit": { "changes": { document.uri(): edits } },
+                })
+            })
+            .collect();
+        Ok(json!(actions))
+    }
+
+    fn open(&self, uri: &str) -> Result<&Document> {
+        self.documents.get(uri).ok_or_else(|| unknown(uri))
+    }
+
+    /// Analyzes a document and applies the workspace configuration.
+    fn diagnostics(&self, document: &Document) -> Result<Vec<Diagnostic>> {
+        if !document.language().is_enabled() {
+            return Ok(Vec::new());
+        }
+        let mut result = Analyzer::analyze_source(document.file(), document.language())?;
+        result.apply_unsolved(self.config.unsolved);
+        let mut diagnostics = result.diagnostics;
+        diagnostics.extend(
+            self.rules
+                .check_source(document.file(), document.language())?,
+        );
+        self.config.finalize(&mut diagnostics);
+        Ok(diagnostics)
+    }
+
+    /// The binding of the identifier at a position, and the identifier's range.
+    fn symbol_at(
+        &self,
+        document: &Document,
+        position: Position,
+    ) -> Result<Option<(Symbol, Range)>> {
+        if document.language() != Language::Python {
+            return Ok(None);
+        }
+        let file = document.file();
+        let tree = Parser::new()?.parse_string(file.text())?;
+        let offset = offset_of(file, position);
+        let Some(identifier) = navigation::identifier_at(&tree, offset) else {
+            return Ok(None);
+        };
+        let span = file.node_span(&identifier);
+        let symbols = navigation::symbols(&tree, file);
+        let symbol = navigation::resolve(&symbols, file.slice(&span), identifier.start_byte());
+        Ok(symbol.map(|symbol| (symbol.clone(), Range::of_span(&span))))
+    }
+
+    /// Follows an import into the project: the target file's URI and text,
+    /// and the top-level binding of the imported name if it has one.
+    fn import_definition(
+        &self,
+        document: &Document,
+        symbol: &Symbol,
+    ) -> Option<(String, SourceFile, Option<Symbol>)> {
+        let target = symbol.import.as_ref()?;
+        let importer = document.path()?;
+        let path = navigation::import_file(target, importer, &self.resolver(importer))?;
+        let uri = path_to_uri(&path);
+        let file = match self.documents.get(&uri) {
+            Some(open) => open.file().clone(),
+            None => SourceFile::read(&path).ok()?,
+        };
+        let found = target.name.as_deref().and_then(|name| {
+            let tree = Parser::new().ok()?.parse_string(file.text()).ok()?;
+            let symbols = navigation::symbols(&tree, &file);
+            navigation::top_level(&symbols, &file, name).cloned()
+        });
+        Some((uri, file, found))
+    }
+
+    /// Module search roots: the workspace root, its `src` directory, and the
+    /// importing file's directory (as `sys.path[0]` would be for a script).
+    fn resolver(&self, importer: &Path) -> ModuleResolver {
+        let mut resolver = ModuleResolver::default();
+        if let Some(root) = &self.root {
+            resolver.add_root(root);
+            let src = root.join("src");
+            if src.is_dir() {
+                resolver.add_root(src);
+            }
+        }
+        if let Some(dir) = importer.parent() {
+            resolver.add_root(dir);
+        }
+        resolver
+    }
+}
+
+fn unknown(uri: &str) -> Error {
+    Error::invalid_value("uri", uri, "document is not open")
+}
+
+fn lsp_diagnostic(diagnostic: &Diagnostic) -> Value {
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Info => 3,
+        Severity::Hint => 4,
+    };
+    let related: Vec<Value> = diagnostic
+        .related
+        .iter()
+        .map(|related| {
+            json!({
+                "location": {
+                    "uri": path_to_uri(Path::new(&related.path)),
+                    "range": Range::of_span(&related.span),
+                },
+                "message": related.message,
+            })
+        })
+        .collect();
+    let mut value = json!({
+        "range": Range::of_span(&diagnostic.span),
+        "severity": severity,
+        "source": "omnitype",
+        "message": diagnostic.message,
+    });
+    if let Some(code) = &diagnostic.code {
+        value["code"] = json!(code);
+    }
+    if !related.is_empty() {
+        value["relatedInformation"] = json!(related);
+    }
+    value
+}
+
+fn lsp_edit(edit: &TextEdit) -> Value {
+    json!({ "range": Range::of_span(&edit.span), "newText": edit.replacement })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        let response = server.handle(Message::request(1, method, params)).unwrap();
+        assert!(response.error.is_none(), "{:?}", response.error);
+        response.result.unwrap()
+    }
+
+    #[test]
+    fn test_document_features() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("models.py"), "class User:\n    pass\n").unwrap();
+        let uri = path_to_uri(&dir.path().join("app.py"));
+        let mut server = Server::new();
+        let init =
+            request(&mut server, "initialize", json!({ "rootUri": path_to_uri(dir.path()) }));
+        assert_eq!(init["capabilities"]["hoverProvider"], json!(true));
+
+        let text = "from models import User\n\ndef make(n):\n    count = 1\n    return User()\n";
+        server.handle(Message::notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "python", "version": 1, "text": text } }),
+        ));
+        // Annotate `n` incrementally; only the return annotation is still missing.
+        server.handle(Message::notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{
+                    "range": { "start": { "line": 2, "character": 10 }, "end": { "line": 2, "character": 10 } },
+                    "text": ": int",
+                }],
+            }),
+        ));
+        let doc = json!({ "uri": uri });
+        let report =
+            request(&mut server, "textDocument/diagnostic", json!({ "textDocument": doc }));
+        let items = report["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["code"], json!("OT002"));
+
+        let at = |line: usize, character: usize| json!({ "textDocument": doc, "position": { "line": line, "character": character } });
+        let hover = request(&mut server, "textDocument/hover", at(3, 6));
+        assert_eq!(hover["contents"]["value"], json!("```python\ncount: int\n```"));
+        let hover = request(&mut server, "textDocument/hover", at(4, 12));
+        assert_eq!(hover["contents"]["value"], json!("```python\nclass User\n```"));
+
+        let definition = request(&mut server, "textDocument/definition", at(4, 12));
+        assert_eq!(definition["uri"], json!(path_to_uri(&dir.path().join("models.py"))));
+        assert_eq!(definition["range"]["start"], json!({ "line": 0, "character": 6 }));
+
+        let actions = request(
+            &mut server,
+            "textDocument/codeAction",
+            json!({ "textDocument": doc, "range": items[0]["range"], "context": { "diagnostics": items } }),
+        );
+        let action = &actions[0];
+        assert_eq!(action["title"], json!("Annotate return type as Any"));
+        assert_eq!(action["diagnostics"][0], items[0]);
+        assert_eq!(action["edit"]["changes"][&uri].as_array().unwrap().len(), 2);
+
+        request(&mut server, "shutdown", Value::Null);
+        server.handle(Message::notification("exit", Value::Null));
+        assert!(server.exited);
+    }
+}
@@ -4,12 +4,24 @@ mod ui;
 
 use clap::Parser;
 use log::LevelFilter;
-use omnitype::analyzer::{AnalysisResult, Analyzer};
-use omnitype::fixer::Fixer;
+use omnitype::analyzer::{
+    stats_to_csv, stats_to_jsonl, AnalysisResult, FileStats, FunctionStats, RuleSet, TypeSnapshot,
+};
+use omnitype::config::{Config, Profile};
+use omnitype::coverage::{retain_covered, Coverage};
+use omnitype::daemon::{CheckParams, CheckResponse, Client, Daemon, Status, DEFAULT_SOCKET};
+use omnitype::deps::{DepsAnalysis, DepsReport};
+use omnitype::diagnostics::{to_csv, to_jsonl, to_sarif, Catalog};
+use omnitype::diff::DiffCheck;
+use omnitype::parser::Language;
 use omnitype::prelude::*;
-use omnitype::types::TypeEnv;
-use omnitype::utils::find_python_files;
-use std::{io, path::PathBuf};
+use omnitype::project::FixOptions;
+use omnitype::tracer::RuntimeTracer;
+use omnitype::utils::{PythonVersion, SourceFile};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
 
 /// Command-line interface for omnitype.
 #[derive(Parser, Debug)]
@@ -39,7 +51,69 @@ enum Commands {
         /// Path to the project directory or file
         path: PathBuf,
 
-        /// Output format (text, json)
+        /// Output format (text, json, sarif, csv, jsonl)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+
+        /// Translated message catalog (.ftl) used for diagnostic text
+        #[arg(long)]
+        messages: Option<PathBuf>,
+
+        /// Python version to check against (default: detected from project metadata)
+        #[arg(long)]
+        python_version: Option<PythonVersion>,
+
+        /// coverage.py data (.coverage, coverage.xml, or coverage.json)
+        #[arg(long)]
+        coverage: Option<PathBuf>,
+
+        /// Only report diagnostics in code that ran according to --coverage
+        #[arg(long, requires = "coverage")]
+        only_covered: bool,
+
+        /// Write each function's inferred signature to this snapshot file
+        #[arg(long)]
+        emit_types: Option<PathBuf>,
+
+        /// Fail if inferred signatures differ from this snapshot file
+        #[arg(long)]
+        verify_types: Option<PathBuf>,
+
+        /// Number of files to analyze at once (default: one per CPU core)
+        #[arg(short, long, default_value = "0")]
+        jobs: usize,
+
+        /// Analyze every file instead of reusing results cached in .omnitype/cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Analysis profile: strict, standard, or lenient (default: from configuration)
+        #[arg(long)]
+        profile: Option<Profile>,
+
+        /// Skip files larger than this many bytes (default: from configuration)
+        #[arg(long)]
+        max_file_size: Option<u64>,
+
+        /// Skip generated files, such as protobuf modules (default: from configuration)
+        #[arg(long)]
+        skip_generated: bool,
+    },
+
+    /// Show annotation statistics, ranking untyped code by how much of it runs
+    Stats {
+        /// Path to the project directory or file
+        path: PathBuf,
+
+        /// coverage.py data (.coverage, coverage.xml, or coverage.json)
+        #[arg(long)]
+        coverage: Option<PathBuf>,
+
+        /// Number of functions to list
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+
+        /// Output format (text, json, csv, jsonl)
         #[arg(short, long, default_value = "text")]
         format: String,
     },
@@ -62,9 +136,104 @@ enum Commands {
         /// Test function to run (default: run all tests)
         #[arg(short, long)]
         test: Option<String>,
+
+        /// Keep the instrumented script, trace JSON, and scratch files for debugging
+        #[arg(long)]
+        keep_artifacts: bool,
+
+        /// Also run the doctest examples in the file's docstrings
+        #[arg(long, conflicts_with = "test")]
+        doctests: bool,
+    },
+
+    /// Run a language server over stdio
+    Lsp,
+
+    /// Report diagnostics introduced or resolved since a git revision
+    DiffCheck {
+        /// Revision to compare the working tree against, e.g. origin/main
+        #[arg(long)]
+        base: String,
+
+        /// Path to the project directory or file
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Output format (text, json, markdown)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Convert mypy or pyright settings into omnitype configuration
+    ImportConfig {
+        /// mypy.ini, setup.cfg, or pyrightconfig.json, or a directory containing one
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Write omnitype.toml next to the imported file instead of printing it
+        #[arg(long)]
+        write: bool,
+    },
+
+    /// List imported third-party packages and whether type stubs were found for them
+    Deps {
+        /// Path to the project directory or file
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// typeshed checkout whose stubs/ directory counts as type information
+        #[arg(long)]
+        typeshed: Option<PathBuf>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Run or query a background check server that keeps analyses in memory
+    Daemon {
+        /// Socket the daemon listens on
+        #[arg(long, default_value = DEFAULT_SOCKET)]
+        socket: PathBuf,
+
+        #[command(subcommand)]
+        command: DaemonCommand,
     },
 }
 
+#[derive(Parser, Debug)]
+enum DaemonCommand {
+    /// Start a daemon in the background
+    Start,
+
+    /// Run the daemon in the foreground
+    Run,
+
+    /// Show whether a daemon is running and what it has cached
+    Status,
+
+    /// Check files or directories with the daemon
+    Check {
+        /// Paths to check
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Check the paths of the last check again, re-analyzing changed files
+    Recheck {
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Stop the daemon
+    Stop,
+}
+
 fn setup_logging(level: &str) -> Result<()> {
     let log_level = match level.to_lowercase().as_str() {
         "trace" => LevelFilter::Trace,
@@ -83,12 +252,344 @@ fn setup_logging(level: &str) -> Result<()> {
     Ok(())
 }
 
+/// Loads `--coverage`, exiting on failure.
+fn load_coverage(path: Option<&Path>) -> Option<Coverage> {
+    let path = path?;
+    match Coverage::load(path) {
+        Ok(coverage) => Some(coverage),
+        Err(e) => {
+            eprintln!("Failed to load coverage: {}", e);
+            std::process::exit(2);
+        },
+    }
+}
+
+/// Unwraps what was loaded along with a path's configuration, exiting
+/// when the configuration is invalid.
+fn configured<T>(loaded: Result<T>) -> T {
+    match loaded {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Failed to load configuration: {}", e);
+            std::process::exit(2);
+        },
+    }
+}
+
+/// Annotation statistics for a file; only Python files have functions.
+fn file_stats(path: &Path) -> Result<FileStats> {
+    let file = SourceFile::read(path)?;
+    if Language::from_path(path) == Some(Language::Python) {
+        FileStats::collect(&file)
+    } else {
+        Ok(FileStats { path: file.path().to_string(), functions: Vec::new() })
+    }
+}
+
+/// Prints the annotation summary, then the `limit` files and functions with
+/// the highest priority.
+fn print_stats(stats: &[FileStats], limit: usize) {
+    let functions: Vec<(&FileStats, &FunctionStats)> = stats
+        .iter()
+        .flat_map(|file| file.functions.iter().map(move |f| (file, f)))
+        .collect();
+    let annotated = functions
+        .iter()
+        .filter(|(_, f)| f.is_fully_annotated())
+        .count();
+    let percent = if functions.is_empty() { 100 } else { annotated * 100 / functions.len() };
+    println!(
+        "{} files, {}/{} functions fully annotated ({}%)",
+        stats.len(),
+        annotated,
+        functions.len(),
+        percent
+    );
+
+    let mut files: Vec<&FileStats> = stats.iter().filter(|s| s.priority() > 0).collect();
+    files.sort_by_key(|file| std::cmp::Reverse(file.priority()));
+    if !files.is_empty() {
+        println!("\n{:>8}  {:>8}  File", "Priority", "Untyped");
+        for file in files.into_iter().take(limit) {
+            let untyped = file
+                .functions
+                .iter()
+                .filter(|f| !f.is_fully_annotated())
+                .count();
+            println!("{:>8}  {:>8}  {}", file.priority(), untyped, file.path);
+        }
+    }
+
+    let mut ranked: Vec<_> = functions
+        .into_iter()
+        .filter(|(_, f)| !f.is_fully_annotated())
+        .collect();
+    ranked.sort_by_key(|(_, f)| std::cmp::Reverse(f.priority()));
+    if ranked.is_empty() {
+        return;
+    }
+    println!("\n{:>8}  {:>8}  {:>7}  Function", "Priority", "Executed", "Missing");
+    for (file, f) in ranked.into_iter().take(limit) {
+        let executed = f.executed_lines.map_or("-".to_string(), |n| n.to_string());
+        println!(
+            "{:>8}  {:>8}  {:>7}  {}:{} {}",
+            f.priority(),
+            executed,
+            f.missing_annotations(),
+            file.path,
+            f.span.start.line + 1,
+            f.name
+        );
+    }
+}
+
+/// Writes and/or verifies the inferred-signature snapshot of the checked
+/// Python files, keyed relative to the checked directory. Returns true if
+/// signatures drifted from the verified snapshot; exits on I/O errors.
+fn check_type_snapshot(
+    path: &Path,
+    results: &[AnalysisResult],
+    emit: Option<&Path>,
+    verify: Option<&Path>,
+) -> bool {
+    let root = if path.is_file() { path.parent().unwrap_or(path) } else { path };
+    let files = results
+        .iter()
+        .map(|r| Path::new(&r.path))
+        .filter(|f| Language::from_path(f) == Some(Language::Python));
+    let snapshot = match TypeSnapshot::collect(root, files) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            eprintln!("Failed to infer signatures: {}", e);
+            std::process::exit(2);
+        },
+    };
+    let mut drifted = false;
+    if let Some(verify) = verify {
+        let expected = match TypeSnapshot::load(verify) {
+            Ok(expected) => expected,
+            Err(e) => {
+                eprintln!("Failed to load type snapshot: {}", e);
+                std::process::exit(2);
+            },
+        };
+        let drift = expected.drift(&snapshot);
+        for d in &drift {
+            eprintln!("type drift: {}", d);
+        }
+        drifted = !drift.is_empty();
+    }
+    if let Some(emit) = emit {
+        if let Err(e) = snapshot.save(emit) {
+            eprintln!("Failed to write type snapshot: {}", e);
+            std::process::exit(2);
+        }
+    }
+    drifted
+}
+
+/// Prints each file's counts and diagnostics, the default `check` output.
+fn print_results(results: &[AnalysisResult]) {
+    if results.is_empty() {
+        println!("No source files found or all analyses failed.");
+        return;
+    }
+    for r in results {
+        match r.metrics.typedness() {
+            Some(typed) => println!(
+                "{}: functions={}, classes={}, typed={:.0}%",
+                r.path, r.function_count, r.class_count, typed
+            ),
+            None => {
+                println!("{}: functions={}, classes={}", r.path, r.function_count, r.class_count)
+            },
+        }
+        for d in &r.diagnostics {
+            println!("  {}", d.render_text());
+        }
+    }
+}
+
+/// Prints the dependency table, then every untyped call.
+fn print_deps(report: &DepsReport) {
+    match &report.environment {
+        Some(env) => println!("Environment: {}", env.display()),
+        None => println!("No Python environment found; packages are listed as not installed"),
+    }
+    if report.dependencies.is_empty() {
+        println!("No third-party imports found.");
+        return;
+    }
+    println!(
+        "\n{:<24} {:<12} {:<14} {:>5} {:>13}",
+        "Package", "Version", "Stubs", "Files", "Untyped calls"
+    );
+    for d in &report.dependencies {
+        let version = match (&d.version, d.installed) {
+            (Some(version), _) => version.as_str(),
+            (None, true) => "?",
+            (None, false) => "not installed",
+        };
+        println!(
+            "{:<24} {:<12} {:<14} {:>5} {:>13}",
+            d.package,
+            version,
+            d.stubs.label(),
+            d.imported_by.len(),
+            d.untyped_calls.len()
+        );
+    }
+    for d in report
+        .missing_stubs()
+        .filter(|d| !d.untyped_calls.is_empty())
+    {
+        println!("\nUntyped calls into {}:", d.package);
+        for call in &d.untyped_calls {
+            println!("  {}:{}: {}", call.path, call.span, call.callee);
+        }
+    }
+}
+
+/// Connects to the daemon, exiting if none is running.
+fn connect_daemon(socket: &Path) -> Client {
+    match Client::connect(socket) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        },
+    }
+}
+
+/// Sends a request to the daemon, exiting on failure.
+fn daemon_request(
+    client: &mut Client,
+    method: &str,
+    params: serde_json::Value,
+) -> serde_json::Value {
+    match client.request(method, params) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Daemon request failed: {}", e);
+            std::process::exit(2);
+        },
+    }
+}
+
+fn run_daemon_command(socket: &Path, command: DaemonCommand) -> io::Result<()> {
+    match command {
+        DaemonCommand::Start => {
+            if Client::connect(socket).is_ok() {
+                println!("Daemon already running on {}", socket.display());
+                return Ok(());
+            }
+            std::process::Command::new(std::env::current_exe()?)
+                .arg("daemon")
+                .arg("--socket")
+                .arg(socket)
+                .arg("run")
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn()?;
+            for _ in 0..100 {
+                if let Ok(mut client) = Client::connect(socket) {
+                    let status = daemon_request(&mut client, "status", serde_json::Value::Null);
+                    println!("Daemon started (pid {})", status["pid"]);
+                    return Ok(());
+                }
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            eprintln!("Daemon did not start listening on {}", socket.display());
+            std::process::exit(2);
+        },
+        DaemonCommand::Run => {
+            let mut daemon = Daemon::new(std::env::current_dir()?);
+            omnitype::daemon::serve(socket, &mut daemon).map_err(io::Error::other)?;
+        },
+        DaemonCommand::Status => {
+            let mut client = connect_daemon(socket);
+            let status = daemon_request(&mut client, "status", serde_json::Value::Null);
+            match serde_json::from_value::<Status>(status) {
+                Ok(status) => {
+                    println!(
+                        "Daemon running (pid {}, up {}s): {} files cached",
+                        status.pid, status.uptime_secs, status.cached_files
+                    );
+                    for path in &status.paths {
+                        println!("  {}", path.display());
+                    }
+                },
+                Err(e) => eprintln!("Invalid status response: {}", e),
+            }
+        },
+        DaemonCommand::Check { paths, format } => {
+            // The daemon may run elsewhere, so send absolute paths.
+            let mut absolute = Vec::new();
+            for path in paths {
+                match path.canonicalize() {
+                    Ok(path) => absolute.push(path),
+                    Err(_) => {
+                        eprintln!("Path not found: {:?}", path);
+                        std::process::exit(2);
+                    },
+                }
+            }
+            let params = serde_json::json!(CheckParams { paths: absolute });
+            let mut client = connect_daemon(socket);
+            let result = daemon_request(&mut client, "check", params);
+            print_daemon_check(result, &format);
+        },
+        DaemonCommand::Recheck { format } => {
+            let mut client = connect_daemon(socket);
+            let result = daemon_request(&mut client, "recheck", serde_json::Value::Null);
+            print_daemon_check(result, &format);
+        },
+        DaemonCommand::Stop => {
+            let mut client = connect_daemon(socket);
+            daemon_request(&mut client, "shutdown", serde_json::Value::Null);
+            println!("Daemon stopped");
+        },
+    }
+    Ok(())
+}
+
+/// Prints a daemon check response and exits with 1 if it failed.
+fn print_daemon_check(result: serde_json::Value, format: &str) {
+    let response: CheckResponse = match serde_json::from_value(result) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Invalid check response: {}", e);
+            std::process::exit(2);
+        },
+    };
+    for e in &response.errors {
+        eprintln!("Failed to analyze: {}", e);
+    }
+    match format {
+        "json" => match serde_json::to_string_pretty(&response) {
+            Ok(s) => println!("{}", s),
+            Err(e) => eprintln!("Failed to serialize JSON: {}", e),
+        },
+        _ => print_results(&response.results),
+    }
+    if response.failed {
+        std::process::exit(1);
+    }
+}
+
 fn main() -> io::Result<()> {
     let cli = Cli::parse();
 
     // Set up logging
     setup_logging(&cli.log_level).map_err(io::Error::other)?;
 
+    // Fail early with a clear message if a grammar was built for another tree-sitter ABI
+    if let Err(e) = omnitype::parser::check_grammars() {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+
     // If no command is provided or TUI flag is set, run the TUI
     if cli.command.is_none() || cli.tui {
         let mut app = ui::App::new();
@@ -98,7 +599,21 @@ fn main() -> io::Result<()> {
     // Handle command-line commands
     if let Some(command) = cli.command {
         match command {
-            Commands::Check { path, format } => {
+            Commands::Check {
+                path,
+                format,
+                messages,
+                python_version,
+                coverage,
+                only_covered,
+                emit_types,
+                verify_types,
+                jobs,
+                no_cache,
+                profile,
+                max_file_size,
+                skip_generated,
+            } => {
                 let path_exists = std::fs::metadata(&path)
                     .map(|m| m.is_file() || m.is_dir())
                     .unwrap_or(false);
@@ -107,81 +622,280 @@ fn main() -> io::Result<()> {
                     return Ok(());
                 }
 
-                let mut results: Vec<AnalysisResult> = Vec::new();
-                if path.is_file() {
-                    if path.extension().and_then(|e| e.to_str()) == Some("py") {
-                        match Analyzer::analyze_python_file(&path) {
-                            Ok(res) => results.push(res),
-                            Err(e) => eprintln!("Failed to analyze {:?}: {}", path, e),
-                        }
-                    } else {
-                        eprintln!("File is not a Python file: {:?}", path);
+                let mut project = match Project::discover(&path) {
+                    Ok(project) => project,
+                    Err(e) => {
+                        eprintln!("Failed to load project: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                if let Some(version) = python_version {
+                    project = project.with_python_version(version);
+                }
+                project = project.with_jobs(jobs);
+                if let Some(profile) = profile {
+                    let mut config = project.config().clone();
+                    // A project-wide choice replaces per-directory ones too.
+                    config.profile = profile;
+                    config.profiles.clear();
+                    project = project.with_config(config);
+                }
+                if max_file_size.is_some() || skip_generated {
+                    let mut config = project.config().clone();
+                    if let Some(bytes) = max_file_size {
+                        config.discovery.max_file_size = Some(bytes);
                     }
-                } else {
-                    for file in find_python_files(&path) {
-                        match Analyzer::analyze_python_file(&file) {
-                            Ok(res) => results.push(res),
-                            Err(e) => eprintln!("Failed to analyze {:?}: {}", file, e),
+                    config.discovery.skip_generated |= skip_generated;
+                    project = project.with_config(config);
+                }
+                log::debug!("Checking against Python {}", project.python_version());
+                let coverage = load_coverage(coverage.as_deref());
+
+                let report = if no_cache { project.check() } else { project.check_cached() };
+                let report = match report {
+                    Ok(report) => report,
+                    Err(e) => {
+                        eprintln!("Check failed: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                for e in &report.errors {
+                    eprintln!("Failed to analyze: {}", e);
+                }
+                let config = project.config();
+                let mut results = report.results;
+                if let Some(coverage) = coverage.as_ref().filter(|_| only_covered) {
+                    for r in &mut results {
+                        let file = Path::new(&r.path);
+                        match file_stats(file) {
+                            Ok(stats) => {
+                                retain_covered(&mut r.diagnostics, &stats, coverage.file(file))
+                            },
+                            Err(e) => eprintln!("Failed to read {}: {}", r.path, e),
                         }
                     }
                 }
 
-                let mut total_diagnostics = 0usize;
+                if let Some(messages) = messages {
+                    match Catalog::load(&messages) {
+                        Ok(catalog) => {
+                            for d in results.iter_mut().flat_map(|r| r.diagnostics.iter_mut()) {
+                                d.localize(&catalog);
+                            }
+                        },
+                        Err(e) => eprintln!("Failed to load message catalog: {}", e),
+                    }
+                }
+
+                let drifted = (emit_types.is_some() || verify_types.is_some())
+                    && check_type_snapshot(
+                        &path,
+                        &results,
+                        emit_types.as_deref(),
+                        verify_types.as_deref(),
+                    );
+
+                let failed = results.iter().any(|r| config.fails(&r.diagnostics));
                 match format.as_str() {
                     "json" => match serde_json::to_string_pretty(&results) {
                         Ok(s) => println!("{}", s),
                         Err(e) => eprintln!("Failed to serialize JSON: {}", e),
                     },
-                    _ => {
-                        if results.is_empty() {
-                            println!("No Python files found or all analyses failed.");
+                    "sarif" => {
+                        let diagnostics: Vec<_> =
+                            results.iter().flat_map(|r| r.diagnostics.clone()).collect();
+                        match serde_json::to_string_pretty(&to_sarif(&diagnostics)) {
+                            Ok(s) => println!("{}", s),
+                            Err(e) => eprintln!("Failed to serialize SARIF: {}", e),
+                        }
+                    },
+                    "csv" | "jsonl" => {
+                        let diagnostics: Vec<_> =
+                            results.iter().flat_map(|r| r.diagnostics.clone()).collect();
+                        if format == "csv" {
+                            print!("{}", to_csv(&diagnostics));
                         } else {
-                            for r in &results {
-                                println!(
-                                    "{}: functions={}, classes={}",
-                                    r.path, r.function_count, r.class_count
-                                );
-                                for d in &r.diagnostics {
-                                    println!(
-                                        "  {}:{}:{}: {} {}",
-                                        r.path,
-                                        d.line + 1,
-                                        d.column + 1,
-                                        d.severity,
-                                        d.message
-                                    );
-                                }
-                                total_diagnostics += r.diagnostics.len();
+                            match to_jsonl(&diagnostics) {
+                                Ok(s) => print!("{}", s),
+                                Err(e) => eprintln!("Failed to serialize JSON: {}", e),
                             }
                         }
                     },
+                    _ => print_results(&results),
                 }
-                if total_diagnostics > 0 {
+                if failed || drifted {
                     std::process::exit(1);
                 }
             },
+            Commands::Stats { path, coverage, limit, format } => {
+                let config = configured(Config::discover(&path));
+                let coverage = load_coverage(coverage.as_deref());
+                let files: Vec<PathBuf> = if path.is_file() {
+                    vec![path.clone()]
+                } else {
+                    match config.walker(&path).files() {
+                        Ok(files) => files.collect(),
+                        Err(e) => {
+                            eprintln!("Failed to list files: {}", e);
+                            std::process::exit(2);
+                        },
+                    }
+                };
+                let mut stats = Vec::new();
+                for file in files
+                    .iter()
+                    .filter(|f| Language::from_path(f) == Some(Language::Python))
+                {
+                    match file_stats(file) {
+                        Ok(mut s) => {
+                            if let Some(coverage) = &coverage {
+                                s.apply_coverage(
+                                    coverage.file(file).unwrap_or(&Default::default()),
+                                );
+                            }
+                            stats.push(s);
+                        },
+                        Err(e) => eprintln!("Failed to analyze {}: {}", file.display(), e),
+                    }
+                }
+                match format.as_str() {
+                    "json" => match serde_json::to_string_pretty(&stats) {
+                        Ok(s) => println!("{}", s),
+                        Err(e) => eprintln!("Failed to serialize JSON: {}", e),
+                    },
+                    "csv" => print!("{}", stats_to_csv(&stats)),
+                    "jsonl" => match stats_to_jsonl(&stats) {
+                        Ok(s) => print!("{}", s),
+                        Err(e) => eprintln!("Failed to serialize JSON: {}", e),
+                    },
+                    _ => print_stats(&stats, limit),
+                }
+            },
             Commands::Fix { path, in_place } => {
-                let fixer = Fixer::new(TypeEnv::new(), in_place);
-                if let Err(e) = fixer.fix_path(&path) {
+                let project = match Project::discover(&path) {
+                    Ok(project) => project,
+                    Err(e) => {
+                        eprintln!("Failed to load project: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                let report = match project.fix(FixOptions::new().in_place(in_place)) {
+                    Ok(report) => report,
+                    Err(e) => {
+                        eprintln!("Failed to list files: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                for e in &report.errors {
                     eprintln!("Fix failed: {}", e);
-                } else {
-                    println!("Fix completed{}", if in_place { " (in-place)" } else { "" });
+                }
+                if report.errors.is_empty() {
+                    println!(
+                        "Fix completed{}: {} file(s) {}",
+                        if in_place { " (in-place)" } else { "" },
+                        report.changed.len(),
+                        if in_place { "changed" } else { "need changes" }
+                    );
+                }
+            },
+            Commands::Trace { path, test, keep_artifacts, doctests } => {
+                let mut tracer = RuntimeTracer::new(true)
+                    .keep_artifacts(keep_artifacts)
+                    .doctests(doctests);
+                if let Err(e) = tracer.run(&path, test.as_deref()) {
+                    eprintln!("Trace failed: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            Commands::Lsp => omnitype::lsp::run_stdio().map_err(io::Error::other)?,
+            Commands::Deps { path, typeshed, format } => {
+                let mut analysis = configured(DepsAnalysis::new(&path));
+                if let Some(typeshed) = typeshed {
+                    analysis = analysis.typeshed(typeshed);
+                }
+                let report = match analysis.run() {
+                    Ok(report) => report,
+                    Err(e) => {
+                        eprintln!("Dependency scan failed: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                match format.as_str() {
+                    "json" => match serde_json::to_string_pretty(&report) {
+                        Ok(s) => println!("{}", s),
+                        Err(e) => eprintln!("Failed to serialize JSON: {}", e),
+                    },
+                    _ => print_deps(&report),
+                }
+            },
+            Commands::Daemon { socket, command } => run_daemon_command(&socket, command)?,
+            Commands::DiffCheck { base, path, format } => {
+                let config = configured(Config::discover(&path));
+                let rules = match RuleSet::discover(&path) {
+                    Ok(rules) => rules,
+                    Err(e) => {
+                        eprintln!("Failed to load custom rules: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                let check = DiffCheck::new(&path, base)
+                    .config(config.clone())
+                    .rules(rules);
+                let report = match check.run() {
+                    Ok(report) => report,
+                    Err(e) => {
+                        eprintln!("Diff check failed: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                match format.as_str() {
+                    "json" => match serde_json::to_string_pretty(&report) {
+                        Ok(s) => println!("{}", s),
+                        Err(e) => eprintln!("Failed to serialize JSON: {}", e),
+                    },
+                    "markdown" | "md" => print!("{}", report.to_markdown()),
+                    _ => {
+                        for d in &report.introduced {
+                            println!("+ {}", d.render_text());
+                        }
+                        for d in &report.resolved {
+                            println!("- {}", d.render_text());
+                        }
+                        println!("{}", report.summary());
+                    },
+                }
+                if config.fails(&report.introduced) {
+                    std::process::exit(1);
                 }
             },
-            Commands::Trace { path: _path, test: _test } => {
-                fn setup_logging(level: &str) -> std::io::Result<()> {
-                    // RUST_LOG takes precedence if set; otherwise fall back to CLI value.
-                    let env = env_logger::Env::default().filter_or("RUST_LOG", level);
-                    env_logger::Builder::from_env(env)
-                        .format_timestamp(None)
-                        .init();
-                    Ok(())
-                }
-
-                // Rest of the Trace command implementation
-                setup_logging("info")?;
-                // TODO: Add actual trace command implementation
-                println!("Tracing not yet implemented");
+            Commands::ImportConfig { path, write } => {
+                let source = if path.is_dir() { Config::find_foreign(&path) } else { Some(path) };
+                let Some(source) = source else {
+                    eprintln!("No mypy or pyright configuration found");
+                    std::process::exit(2);
+                };
+                let imported = match Config::import(&source) {
+                    Ok(imported) => imported,
+                    Err(e) => {
+                        eprintln!("Failed to import configuration: {}", e);
+                        std::process::exit(2);
+                    },
+                };
+                for skipped in &imported.skipped {
+                    eprintln!("Skipped {}", skipped);
+                }
+                let toml = toml::to_string(&imported.config).map_err(io::Error::other)?;
+                if write {
+                    let target = source.with_file_name(omnitype::config::CONFIG_FILE);
+                    if target.exists() {
+                        eprintln!("{} already exists", target.display());
+                        std::process::exit(2);
+                    }
+                    std::fs::write(&target, toml)?;
+                    println!("Wrote {}", target.display());
+                } else {
+                    print!("{}", toml);
+                }
             },
         }
     }
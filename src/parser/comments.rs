@@ -0,0 +1,195 @@
+//! Comment extraction and directive classification.
+//!
+//! Comments are collected once from the syntax tree so that features such as
+//! suppressions and type comments don't each rescan the raw source.
+
+use tree_sitter::Tree;
+
+use crate::utils::{SourceFile, Span};
+
+/// A source comment and any directives it carries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// Range of the comment, including its `#` or `//` prefix.
+    pub span: Span,
+    /// Full comment text.
+    pub text: String,
+    /// Directives found in the comment, in order of appearance.
+    pub directives: Vec<Directive>,
+}
+
+impl Comment {
+    /// Returns true if the comment suppresses diagnostics with the given code.
+    pub fn suppresses(&self, code: &str) -> bool {
+        self.directives.iter().any(|d| d.suppresses(code))
+    }
+
+    /// Returns the PEP 484 type comment, if any (`# type: List[int]`).
+    pub fn type_comment(&self) -> Option<&str> {
+        self.directives.iter().find_map(|d| match d {
+            Directive::TypeComment(ty) => Some(ty.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// A machine-readable instruction embedded in a comment.
+///
+/// Code lists are empty when the directive applies to every code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+    /// `# type: <annotation>`
+    TypeComment(String),
+    /// `# type: ignore` or `# type: ignore[code, ...]`
+    TypeIgnore(Vec<String>),
+    /// `# omnitype: ignore` or `# omnitype: ignore[code, ...]`
+    Ignore(Vec<String>),
+    /// `# noqa` or `# noqa: code, ...`
+    Noqa(Vec<String>),
+}
+
+impl Directive {
+    /// Classifies a single comment segment, without its leading `#`.
+    pub fn parse(segment: &str) -> Option<Self> {
+        let segment = segment.trim();
+        if let Some(rest) = strip_keyword(segment, "type:") {
+            return Some(match strip_keyword(rest, "ignore") {
+                Some(codes) if codes.is_empty() || codes.starts_with('[') => {
+                    Directive::TypeIgnore(bracketed_codes(codes))
+                },
+                _ => Directive::TypeComment(rest.to_string()),
+            });
+        }
+        if let Some(rest) = strip_keyword(segment, "omnitype:") {
+            let codes = strip_keyword(rest, "ignore")?;
+            return Some(Directive::Ignore(bracketed_codes(codes)));
+        }
+        if segment.get(..4)?.eq_ignore_ascii_case("noqa") {
+            let rest = segment[4..].trim_start();
+            let codes = rest.strip_prefix(':').unwrap_or(rest);
+            return Some(Directive::Noqa(split_codes(codes)));
+        }
+        None
+    }
+
+    /// Returns true if this directive suppresses diagnostics with the given code.
+    pub fn suppresses(&self, code: &str) -> bool {
+        match self {
+            Directive::TypeIgnore(codes) | Directive::Ignore(codes) | Directive::Noqa(codes) => {
+                codes.is_empty() || codes.iter().any(|c| c.eq_ignore_ascii_case(code))
+            },
+            Directive::TypeComment(_) => false,
+        }
+    }
+}
+
+/// Extracts every comment from a parsed tree.
+pub fn extract_comments(tree: &Tree, file: &SourceFile) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut cursor = tree.walk();
+    let mut visited_children = false;
+    loop {
+        let node = cursor.node();
+        if !visited_children && node.kind() == "comment" {
+            let span = file.node_span(&node);
+            let text = file.slice(&span).to_string();
+            let directives = classify(&text);
+            comments.push(Comment { span, text, directives });
+        }
+        if !visited_children && cursor.goto_first_child() {
+            continue;
+        }
+        if cursor.goto_next_sibling() {
+            visited_children = false;
+        } else if cursor.goto_parent() {
+            visited_children = true;
+        } else {
+            break;
+        }
+    }
+    comments
+}
+
+/// Splits a comment at its first `#` after the prefix and classifies both
+/// segments, so that `# type: int  # noqa` yields both directives while a
+/// `#` later in the text, as in `# noqa: see #12 # and #13`, stays text.
+fn classify(text: &str) -> Vec<Directive> {
+    let body = text
+        .strip_prefix("//")
+        .or_else(|| text.strip_prefix('#'))
+        .unwrap_or(text);
+    let (first, rest) = match body.split_once('#') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (body, None),
+    };
+    std::iter::once(first)
+        .chain(rest)
+        .filter_map(Directive::parse)
+        .collect()
+}
+
+/// Strips `keyword` (and following whitespace) from the start of `text`.
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    text.strip_prefix(keyword).map(str::trim_start)
+}
+
+fn bracketed_codes(text: &str) -> Vec<String> {
+    text.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(inner, _)| split_codes(inner))
+        .unwrap_or_default()
+}
+
+fn split_codes(text: &str) -> Vec<String> {
+    text.split([',', ' '])
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_directive_parsing() {
+        assert_eq!(
+            Directive::parse(" type: List[int]"),
+            Some(Directive::TypeComment("List[int]".into()))
+        );
+        assert_eq!(Directive::parse(" type: ignore"), Some(Directive::TypeIgnore(vec![])));
+        assert_eq!(
+            Directive::parse(" omnitype: ignore[OT001, OT002]"),
+            Some(Directive::Ignore(vec!["OT001".into(), "OT002".into()]))
+        );
+        assert_eq!(Directive::parse(" noqa: E501"), Some(Directive::Noqa(vec!["E501".into()])));
+        assert_eq!(Directive::parse(" just a note"), None);
+    }
+
+    #[test]
+    fn test_extract_comments() {
+        let source = "# header\nx = []  # type: List[int]  # noqa\ny = f()  # omnitype: ignore\n";
+        let file = SourceFile::from_text("t.py", source);
+        let tree = Parser::new().unwrap().parse_string(source).unwrap();
+
+        let comments = extract_comments(&tree, &file);
+        assert_eq!(comments.len(), 3);
+        assert!(comments[0].directives.is_empty());
+        assert_eq!(comments[1].type_comment(), Some("List[int]"));
+        assert!(comments[1].suppresses("E501"));
+        assert_eq!(comments[2].span.start.line, 2);
+        assert!(comments[2].suppresses("OT001"));
+    }
+
+    #[test]
+    fn test_hashes_in_comment_text() {
+        assert_eq!(classify("# noqa: OT001 # see #12"), [Directive::Noqa(vec!["OT001".into()])]);
+        assert_eq!(
+            classify("# type: int  # omnitype: ignore[OT003] #4"),
+            [Directive::TypeComment("int".into()), Directive::Ignore(vec!["OT003".into()])]
+        );
+        assert_eq!(classify("# issue #12: type: ignore"), []);
+    }
+}
@@ -0,0 +1,207 @@
+//! Encoding-aware source reading.
+//!
+//! Python files may declare a non-UTF-8 encoding via a PEP 263 coding comment
+//! (`# -*- coding: latin-1 -*-`) or carry a byte order mark. Sources are decoded
+//! to UTF-8 for tree-sitter, and [`SourceText`] keeps enough information to map
+//! positions back to byte columns in the original file.
+
+use std::path::Path;
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+use super::injection::notebook_to_python;
+use crate::error::{Error, Result};
+
+/// Decoded source text along with the encoding it was read from.
+#[derive(Debug, Clone)]
+pub struct SourceText {
+    /// The decoded UTF-8 text, without any byte order mark.
+    pub text: String,
+    encoding: &'static Encoding,
+    bom: bool,
+}
+
+impl SourceText {
+    /// Reads and decodes a file from disk.
+    ///
+    /// Jupyter notebooks are converted to a virtual Python file made of their
+    /// code cells.
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).map_err(|e| {
+            Error::parser_error(format!("Failed to read file: {}", e)).with_path(path)
+        })?;
+        Self::decode_file(path, &bytes)
+    }
+
+    /// Decodes the contents of a file obtained elsewhere, such as from a git
+    /// revision, the same way [`SourceText::read`] would.
+    pub fn decode_file(path: &Path, bytes: &[u8]) -> Result<Self> {
+        if path.extension().is_some_and(|ext| ext == "ipynb") {
+            let json = String::from_utf8_lossy(bytes);
+            return notebook_to_python(&json)
+                .map(Self::from_utf8)
+                .map_err(|e| e.with_path(path));
+        }
+        Self::decode(bytes).map_err(|e| e.with_path(path))
+    }
+
+    /// Decodes raw bytes, honouring a byte order mark or PEP 263 declaration.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (encoding, bom_len) = match Encoding::for_bom(bytes) {
+            Some((encoding, len)) => (encoding, len),
+            None => (declared_encoding(bytes).unwrap_or(UTF_8), 0),
+        };
+
+        let text = encoding
+            .decode_without_bom_handling_and_without_replacement(&bytes[bom_len..])
+            .ok_or_else(|| Error::parser_error(format!("source is not valid {}", encoding.name())))?
+            .into_owned();
+
+        Ok(Self { text, encoding, bom: bom_len > 0 })
+    }
+
+    /// Wraps text that is already UTF-8.
+    pub fn from_utf8(text: impl Into<String>) -> Self {
+        Self { text: text.into(), encoding: UTF_8, bom: false }
+    }
+
+    /// The WHATWG name of the encoding the source was decoded from.
+    pub fn encoding_name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// Returns true if the source started with a byte order mark.
+    pub fn has_bom(&self) -> bool {
+        self.bom
+    }
+
+    /// Returns true if positions in the decoded text differ from the original bytes.
+    pub fn needs_mapping(&self) -> bool {
+        self.encoding != UTF_8
+    }
+
+    /// Maps a 0-based (row, UTF-8 byte column) position in the decoded text to
+    /// the byte column in the original file.
+    pub fn original_column(&self, row: usize, column: usize) -> usize {
+        if !self.needs_mapping() {
+            return column;
+        }
+        let Some(line) = self.text.split('\n').nth(row) else {
+            return column;
+        };
+        let prefix = line.get(..column.min(line.len())).unwrap_or(line);
+        self.encoded_len(prefix)
+    }
+
+    /// Re-encodes UTF-8 text into the source's original encoding, restoring any BOM.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(text.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            if self.bom {
+                out.extend(self.utf16_units("\u{feff}"));
+            }
+            out.extend(self.utf16_units(text));
+            return out;
+        }
+        if self.bom {
+            out.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        let (encoded, _, _) = self.encoding.encode(text);
+        out.extend_from_slice(&encoded);
+        out
+    }
+
+    /// Length in bytes of `text` once encoded in the original encoding.
+    pub fn encoded_len(&self, text: &str) -> usize {
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            return text.encode_utf16().count() * 2;
+        }
+        self.encoding.encode(text).0.len()
+    }
+
+    fn utf16_units(&self, text: &str) -> Vec<u8> {
+        let big_endian = self.encoding == UTF_16BE;
+        text.encode_utf16()
+            .flat_map(|unit| if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() })
+            .collect()
+    }
+}
+
+/// Finds a PEP 263 coding declaration in the first two lines of a file.
+///
+/// The declaration only counts on the second line if the first line is itself
+/// a comment or blank, matching the interpreter's rules.
+fn declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let mut lines = bytes.split(|&b| b == b'\n').take(2);
+    let first = lines.next()?;
+    if let Some(encoding) = coding_from_line(first) {
+        return Some(encoding);
+    }
+    let first = String::from_utf8_lossy(first);
+    let first = first.trim();
+    if first.is_empty() || first.starts_with('#') {
+        return lines.next().and_then(coding_from_line);
+    }
+    None
+}
+
+fn coding_from_line(line: &[u8]) -> Option<&'static Encoding> {
+    let line = String::from_utf8_lossy(line);
+    let comment = line
+        .trim_start_matches([' ', '\t', '\x0c'])
+        .strip_prefix('#')?;
+    let idx = comment.find("coding")?;
+    let rest = comment[idx + "coding".len()..].strip_prefix([':', '='])?;
+    let name: String = rest
+        .trim_start_matches([' ', '\t'])
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+    encoding_for_python_name(&name)
+}
+
+/// Resolves a Python codec name, which may differ from WHATWG labels.
+fn encoding_for_python_name(name: &str) -> Option<&'static Encoding> {
+    let normalized = name.to_ascii_lowercase().replace('_', "-");
+    let label = match normalized.as_str() {
+        "utf-8-sig" | "utf8" => "utf-8",
+        "latin-1" | "latin1" | "iso-8859-1" | "l1" => "iso-8859-1",
+        "cp1252" => "windows-1252",
+        other => other,
+    };
+    Encoding::for_label(label.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_latin1_declaration() {
+        let bytes = b"# -*- coding: latin-1 -*-\nname = '\xe9t\xe9'; x = 1\n";
+        let source = SourceText::decode(bytes).unwrap();
+        assert_eq!(source.encoding_name(), "windows-1252");
+        assert!(source.text.contains("'été'"));
+
+        // `x` sits after two 2-byte UTF-8 characters that are 1 byte in Latin-1.
+        let utf8_col = source.text.lines().nth(1).unwrap().find('x').unwrap();
+        assert_eq!(source.original_column(1, utf8_col), utf8_col - 2);
+        assert_eq!(source.encode(&source.text), bytes.to_vec());
+    }
+
+    #[test]
+    fn test_decode_bom_and_invalid_utf8() {
+        let source = SourceText::decode(b"\xEF\xBB\xBFx = 1\n").unwrap();
+        assert!(source.has_bom());
+        assert_eq!(source.text, "x = 1\n");
+        assert!(!source.needs_mapping());
+
+        assert!(SourceText::decode(b"x = '\xe9'\n").is_err());
+    }
+
+    #[test]
+    fn test_declaration_must_be_in_leading_comments() {
+        assert!(declared_encoding(b"#!/usr/bin/env python\n# coding=latin-1\n").is_some());
+        assert!(declared_encoding(b"import os\n# coding=latin-1\n").is_none());
+    }
+}
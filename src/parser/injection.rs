@@ -0,0 +1,274 @@
+//! Embedded-language support.
+//!
+//! Two kinds of embedded Python are handled through the regular parser entry
+//! points:
+//!
+//! - Jupyter notebooks (`.ipynb`), whose code cells are concatenated into a
+//!   virtual Python file when the notebook is read.
+//! - Doctest examples inside docstrings, which [`Parser::parse_injections`]
+//!   extracts and parses with positions mapped back to the host file.
+//!
+//! [`Parser::parse_injections`]: super::Parser::parse_injections
+
+use tree_sitter::{Node, Tree};
+
+use super::Language;
+use crate::error::{Error, Result};
+use crate::utils::{SourceFile, Span};
+
+/// Where an injected region came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectionKind {
+    /// `>>>` examples in a docstring.
+    Doctest,
+}
+
+/// Source code embedded in a host file, parsed on its own.
+pub struct Injection {
+    /// Where the region came from.
+    pub kind: InjectionKind,
+    /// Language of the embedded code.
+    pub language: Language,
+    /// The extracted code, as a standalone file.
+    pub source: SourceFile,
+    /// Syntax tree of the extracted code.
+    pub tree: Tree,
+    /// The docstring the code was extracted from.
+    pub docstring: Span,
+    /// Expected output of each doctest example that has one, keyed by the
+    /// 0-based line of the extracted code the example starts on.
+    pub outputs: Vec<(usize, String)>,
+    /// Host byte offset at which each extracted line starts.
+    line_origins: Vec<usize>,
+}
+
+impl Injection {
+    /// Maps a byte offset in the extracted code to a byte offset in the host.
+    pub fn host_offset(&self, byte: usize) -> usize {
+        let pos = self.source.line_index().line_col(byte);
+        let origin = self.line_origins.get(pos.line).copied().unwrap_or_default();
+        origin + pos.col
+    }
+
+    /// Maps a span in the extracted code onto the host file.
+    pub fn host_span(&self, span: &Span, host: &SourceFile) -> Span {
+        host.span(self.host_offset(span.start_byte), self.host_offset(span.end_byte))
+    }
+}
+
+/// A doctest region before parsing: extracted code plus line origins.
+pub(super) struct Region {
+    pub(super) code: String,
+    pub(super) docstring: Span,
+    pub(super) outputs: Vec<(usize, String)>,
+    pub(super) line_origins: Vec<usize>,
+}
+
+impl Region {
+    pub(super) fn into_injection(self, tree: Tree) -> Injection {
+        Injection {
+            kind: InjectionKind::Doctest,
+            language: Language::Python,
+            source: SourceFile::from_text("<doctest>", self.code),
+            tree,
+            docstring: self.docstring,
+            outputs: self.outputs,
+            line_origins: self.line_origins,
+        }
+    }
+}
+
+/// Collects the doctest examples of every docstring in a Python tree.
+pub(super) fn doctest_regions(tree: &Tree, file: &SourceFile) -> Vec<Region> {
+    let mut regions = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        if let Some(doc) = docstring_node(&node) {
+            if let Some(region) = doctest_region(&doc, file) {
+                regions.push(region);
+            }
+        }
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            stack.push(child);
+        }
+    }
+    regions.sort_by_key(|r| r.line_origins.first().copied());
+    regions
+}
+
+/// Returns the docstring of a module, class, or function body.
+fn docstring_node<'t>(node: &Node<'t>) -> Option<Node<'t>> {
+    let body = match node.kind() {
+        "module" => *node,
+        "function_definition" | "class_definition" => node.child_by_field_name("body")?,
+        _ => return None,
+    };
+    let first = body.named_child(0)?;
+    if first.kind() != "expression_statement" || first.named_child_count() != 1 {
+        return None;
+    }
+    first.named_child(0).filter(|s| s.kind() == "string")
+}
+
+/// Extracts `>>>` and `...` lines from a docstring, along with the output
+/// lines that follow each example up to the next blank line or prompt.
+fn doctest_region(doc: &Node, file: &SourceFile) -> Option<Region> {
+    let mut code = String::new();
+    let mut outputs: Vec<(usize, String)> = Vec::new();
+    let mut line_origins = Vec::new();
+    let mut in_example = false;
+    let mut in_output = false;
+    let mut example_line = 0;
+    let mut offset = doc.start_byte();
+    // The closing quotes may share a line with the last example, and
+    // anything before them, quotes of the other kind included, is text.
+    let text = &file.text()[doc.start_byte()..doc.end_byte()];
+    let opening = text.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let quote = ["\"\"\"", "'''", "\"", "'"]
+        .into_iter()
+        .find(|quote| opening.starts_with(quote))?;
+    let text = text.strip_suffix(quote)?;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        let prompt = if trimmed.starts_with(">>>") {
+            in_example = true;
+            example_line = line_origins.len();
+            Some(">>>")
+        } else if in_example && trimmed.starts_with("...") {
+            Some("...")
+        } else {
+            if in_example {
+                in_output = true;
+            }
+            in_example = false;
+            None
+        };
+
+        if let Some(prompt) = prompt {
+            in_output = false;
+            let after = &trimmed[prompt.len()..];
+            let body = after.strip_prefix(' ').unwrap_or(after);
+            let start = offset + indent + (trimmed.len() - body.len());
+            let body = body.trim_end_matches(['\n', '\r']);
+            line_origins.push(start);
+            code.push_str(body);
+            code.push('\n');
+        } else if in_output {
+            let text = trimmed.trim_end();
+            if text.is_empty() {
+                in_output = false;
+            } else {
+                match outputs.last_mut() {
+                    Some((line, output)) if *line == example_line => {
+                        output.push('\n');
+                        output.push_str(text);
+                    },
+                    _ => outputs.push((example_line, text.to_string())),
+                }
+            }
+        }
+        offset += line.len();
+    }
+
+    (!line_origins.is_empty()).then(|| Region {
+        code,
+        docstring: file.node_span(doc),
+        outputs,
+        line_origins,
+    })
+}
+
+/// Converts a Jupyter notebook into a virtual Python file.
+///
+/// Each code cell is preceded by a `# %% [cell N]` marker so line numbers can
+/// be traced back to cells. IPython magics and shell escapes are commented
+/// out so the result parses as plain Python.
+pub fn notebook_to_python(json: &str) -> Result<String> {
+    let notebook: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| Error::parser_error(format!("Invalid notebook JSON: {}", e)))?;
+    let cells = notebook
+        .get("cells")
+        .and_then(|c| c.as_array())
+        .ok_or_else(|| Error::parser_error("Notebook has no cells array"))?;
+
+    let mut out = String::new();
+    for (index, cell) in cells.iter().enumerate() {
+        if cell.get("cell_type").and_then(|t| t.as_str()) != Some("code") {
+            continue;
+        }
+        let source = match cell.get("source") {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Array(lines)) => {
+                lines.iter().filter_map(|l| l.as_str()).collect()
+            },
+            _ => String::new(),
+        };
+        out.push_str(&format!("# %% [cell {}]\n", index));
+        for line in source.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('%') || trimmed.starts_with('!') {
+                out.push_str("# ");
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_doctest_injection_maps_to_host() {
+        let source = "def double(x):\n    \"\"\"Double x.\n\n    >>> double(2)\n    4\n    \
+                      >>> y = [1,\n    ...      2]\n    \"\"\"\n    return x * 2\n";
+        let host = SourceFile::from_text("t.py", source);
+        let mut parser = Parser::new().unwrap();
+        let tree = parser.parse_string(source).unwrap();
+
+        let injections = parser.parse_injections(&tree, &host).unwrap();
+        assert_eq!(injections.len(), 1);
+        let inj = &injections[0];
+        assert_eq!(inj.source.text(), "double(2)\ny = [1,\n     2]\n");
+        assert!(!inj.tree.root_node().has_error());
+        assert_eq!(inj.outputs, [(0, "4".to_string())]);
+
+        let y = inj.source.text().find('y').unwrap();
+        let host_span = inj.host_span(&inj.source.span(y, y + 1), &host);
+        assert_eq!(host.slice(&host_span), "y");
+        assert_eq!(host_span.start.line, 5);
+    }
+
+    #[test]
+    fn test_doctest_keeps_quotes_of_the_other_kind() {
+        let source = "def f():\n    '''Quote.\n\n    >>> print('\"\"\"')\n    \"\"\"'''\n";
+        let host = SourceFile::from_text("t.py", source);
+        let mut parser = Parser::new().unwrap();
+        let tree = parser.parse_string(source).unwrap();
+
+        let injections = parser.parse_injections(&tree, &host).unwrap();
+        assert_eq!(injections[0].source.text(), "print('\"\"\"')\n");
+        assert_eq!(injections[0].outputs, [(0, "\"\"\"".to_string())]);
+    }
+
+    #[test]
+    fn test_notebook_to_python() {
+        let json = r##"{"cells": [
+            {"cell_type": "markdown", "source": ["# Title"]},
+            {"cell_type": "code", "source": ["%matplotlib inline\n", "x = 1\n"]},
+            {"cell_type": "code", "source": "def f(a):\n    return a"}
+        ]}"##;
+        let python = notebook_to_python(json).unwrap();
+        assert_eq!(
+            python,
+            "# %% [cell 1]\n# %matplotlib inline\nx = 1\n# %% [cell 2]\ndef f(a):\n    return \
+             a\n"
+        );
+    }
+}
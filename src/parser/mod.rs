@@ -1,37 +1,201 @@
 //! Parser module for converting source code into an abstract syntax tree (AST).
 
+mod comments;
+mod encoding;
+mod injection;
+mod query;
+
+pub use comments::{extract_comments, Comment, Directive};
+pub use encoding::SourceText;
+pub use injection::{notebook_to_python, Injection, InjectionKind};
+pub use query::{Query, QueryCapture, QueryMatch};
+
 use crate::error::{Error, Result};
+use crate::utils::SourceFile;
+use std::fmt;
 use std::path::Path;
 use tree_sitter::Parser as TSParser;
 
+/// A source language supported by omnitype.
+///
+/// Each language maps to a tree-sitter grammar that is only available when the
+/// corresponding cargo feature is enabled (`python` or `javascript`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// Python source files (`.py`, `.pyi`) and Jupyter notebooks (`.ipynb`).
+    Python,
+    /// JavaScript source files (`.js`, `.mjs`, `.cjs`, `.jsx`).
+    JavaScript,
+    /// TypeScript source files (`.ts`, `.mts`, `.cts`).
+    TypeScript,
+    /// TypeScript with JSX (`.tsx`).
+    Tsx,
+}
+
+impl Language {
+    /// All languages known to omnitype, regardless of enabled features.
+    pub const ALL: [Language; 4] =
+        [Language::Python, Language::JavaScript, Language::TypeScript, Language::Tsx];
+
+    /// Detects the language of a file from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|lang| lang.extensions().contains(&ext))
+    }
+
+    /// File extensions (without the leading dot) handled by this language.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Language::Python => &["py", "pyi", "ipynb"],
+            Language::JavaScript => &["js", "mjs", "cjs", "jsx"],
+            Language::TypeScript => &["ts", "mts", "cts"],
+            Language::Tsx => &["tsx"],
+        }
+    }
+
+    /// Returns true if the grammar for this language was compiled in.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            Language::Python => cfg!(feature = "python"),
+            Language::JavaScript | Language::TypeScript | Language::Tsx => {
+                cfg!(feature = "javascript")
+            },
+        }
+    }
+
+    /// Returns the languages whose grammars were compiled in.
+    pub fn enabled() -> impl Iterator<Item = Language> {
+        Self::ALL.into_iter().filter(|lang| lang.is_enabled())
+    }
+
+    /// Returns true for the JavaScript family (JavaScript, TypeScript, TSX).
+    pub fn is_script(self) -> bool {
+        matches!(self, Language::JavaScript | Language::TypeScript | Language::Tsx)
+    }
+
+    /// Returns true if the language has inline type annotations.
+    pub fn has_type_annotations(self) -> bool {
+        !matches!(self, Language::JavaScript)
+    }
+
+    /// Verifies that the compiled grammar's ABI version is supported by the
+    /// linked tree-sitter runtime.
+    ///
+    /// A mismatch happens when a grammar crate is built against a different
+    /// tree-sitter release; it is reported as an error rather than a panic.
+    pub fn check_abi(self) -> Result<()> {
+        let version = self.grammar()?.abi_version();
+        let supported =
+            tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION..=tree_sitter::LANGUAGE_VERSION;
+        if supported.contains(&version) {
+            return Ok(());
+        }
+        Err(Error::parser_error(format!(
+            "The {} grammar uses tree-sitter ABI version {}, but this build supports versions \
+             {} through {}; rebuild with a grammar crate matching the tree-sitter dependency",
+            self,
+            version,
+            supported.start(),
+            supported.end()
+        )))
+    }
+
+    /// Returns the tree-sitter grammar for this language.
+    pub fn grammar(self) -> Result<tree_sitter::Language> {
+        match self {
+            #[cfg(feature = "python")]
+            Language::Python => Ok(tree_sitter_python::LANGUAGE.into()),
+            #[cfg(feature = "javascript")]
+            Language::JavaScript => Ok(tree_sitter_javascript::LANGUAGE.into()),
+            #[cfg(feature = "javascript")]
+            Language::TypeScript => Ok(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()),
+            #[cfg(feature = "javascript")]
+            Language::Tsx => Ok(tree_sitter_typescript::LANGUAGE_TSX.into()),
+            #[allow(unreachable_patterns)]
+            other => Err(Error::not_implemented(format!(
+                "{} support (enable the corresponding cargo feature)",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Language::Python => "python",
+            Language::JavaScript => "javascript",
+            Language::TypeScript => "typescript",
+            Language::Tsx => "tsx",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Checks every enabled grammar for ABI compatibility.
+///
+/// Intended to run once at startup so a mismatched build fails with a clear
+/// message before any file is processed.
+pub fn check_grammars() -> Result<()> {
+    Language::enabled().try_for_each(Language::check_abi)
+}
+
 /// The main parser struct that handles parsing source code.
 #[allow(dead_code)]
 pub struct Parser {
     /// The tree-sitter parser instance.
     parser: TSParser,
+    /// The language this parser was configured for.
+    language: Language,
 }
 
 impl Parser {
     /// Creates a new parser for Python.
     pub fn new() -> Result<Self> {
-        let mut parser = TSParser::new();
+        Self::for_language(Language::Python)
+    }
 
-        // Get the Python language definition
-        let language = tree_sitter_python::language();
+    /// Creates a new parser for the given language.
+    pub fn for_language(language: Language) -> Result<Self> {
+        language.check_abi()?;
+        let mut parser = TSParser::new();
 
         parser
-            .set_language(language)
+            .set_language(&language.grammar()?)
             .map_err(|e| Error::parser_error(format!("Failed to load language: {}", e)))?;
 
-        Ok(Self { parser })
+        Ok(Self { parser, language })
+    }
+
+    /// Creates a parser for the language inferred from a file's extension.
+    pub fn for_path(path: &Path) -> Result<Self> {
+        let language = Language::from_path(path).ok_or_else(|| {
+            Error::invalid_value("path", path.display().to_string(), "unsupported file type")
+        })?;
+        Self::for_language(language)
+    }
+
+    /// Returns the language this parser was configured for.
+    pub fn language(&self) -> Language {
+        self.language
     }
 
     /// Parses a source file into a syntax tree.
     pub fn parse_file(&mut self, path: &Path) -> Result<tree_sitter::Tree> {
-        let source_code = std::fs::read_to_string(path)
-            .map_err(|e| Error::parser_error(format!("Failed to read file: {}", e)))?;
+        self.parse_source_file(path).map(|(tree, _)| tree)
+    }
 
-        self.parse_string(&source_code)
+    /// Parses a source file, returning the decoded text alongside the tree.
+    ///
+    /// The file's declared encoding is honoured; use
+    /// [`SourceText::original_column`] to map tree positions back to the file.
+    pub fn parse_source_file(&mut self, path: &Path) -> Result<(tree_sitter::Tree, SourceText)> {
+        let source = SourceText::read(path)?;
+        let tree = self.parse_string(&source.text)?;
+        Ok((tree, source))
     }
 
     /// Parses a source code string into a syntax tree.
@@ -40,6 +204,32 @@ impl Parser {
             .parse(source, None)
             .ok_or_else(|| Error::parser_error("Failed to parse source code".to_string()))
     }
+
+    /// Extracts and parses code embedded in a parsed file, such as doctest
+    /// examples in Python docstrings.
+    pub fn parse_injections(
+        &mut self,
+        tree: &tree_sitter::Tree,
+        file: &SourceFile,
+    ) -> Result<Vec<Injection>> {
+        if self.language != Language::Python {
+            return Ok(Vec::new());
+        }
+        injection::doctest_regions(tree, file)
+            .into_iter()
+            .map(|region| {
+                let tree = self.parse_string(&region.code)?;
+                Ok(region.into_injection(tree))
+            })
+            .collect()
+    }
+
+    /// Parses `source` and runs a tree-sitter query over it.
+    pub fn query(&mut self, source: &str, query: &str) -> Result<Vec<QueryMatch>> {
+        let query = Query::new(self.language, query)?;
+        let tree = self.parse_string(source)?;
+        Ok(query.matches(&tree, &SourceFile::from_text("<query>", source)))
+    }
 }
 
 #[cfg(test)]
@@ -62,4 +252,28 @@ mod tests {
         let tree = parser.parse_string(source);
         assert!(tree.is_ok());
     }
+
+    #[test]
+    fn test_language_from_path() {
+        assert_eq!(Language::from_path(Path::new("a/b.py")), Some(Language::Python));
+        assert_eq!(Language::from_path(Path::new("app.mjs")), Some(Language::JavaScript));
+        assert_eq!(Language::from_path(Path::new("index.ts")), Some(Language::TypeScript));
+        assert_eq!(Language::from_path(Path::new("view.tsx")), Some(Language::Tsx));
+        assert_eq!(Language::from_path(Path::new("README.md")), None);
+    }
+
+    #[test]
+    fn test_enabled_grammars_are_compatible() {
+        assert!(check_grammars().is_ok());
+    }
+
+    #[cfg(feature = "javascript")]
+    #[test]
+    fn test_parse_typescript() {
+        let mut parser = Parser::for_language(Language::TypeScript).unwrap();
+        let tree = parser
+            .parse_string("function add(a: number, b: number): number { return a + b; }")
+            .unwrap();
+        assert!(!tree.root_node().has_error());
+    }
 }
@@ -0,0 +1,143 @@
+//! Declarative tree-sitter queries with typed capture results.
+//!
+//! Queries let checks be written as S-expression patterns instead of manual
+//! tree walks:
+//!
+//! ```
+//! use omnitype::parser::Parser;
+//!
+//! let mut parser = Parser::new().unwrap();
+//! let matches = parser
+//!     .query("def f(): pass\n", "(function_definition name: (identifier) @name)")
+//!     .unwrap();
+//! assert_eq!(matches[0].capture("name").unwrap().text, "f");
+//! ```
+
+use tree_sitter::{Query as TSQuery, QueryCursor, StreamingIterator, Tree};
+
+use super::Language;
+use crate::error::{Error, Result};
+use crate::utils::{SourceFile, Span};
+
+/// A compiled query for a specific language.
+#[derive(Debug)]
+pub struct Query {
+    inner: TSQuery,
+    language: Language,
+}
+
+impl Query {
+    /// Compiles a query written in tree-sitter's S-expression syntax.
+    pub fn new(language: Language, source: &str) -> Result<Self> {
+        let inner = TSQuery::new(&language.grammar()?, source)
+            .map_err(|e| Error::parser_error(format!("Invalid query: {}", e)))?;
+        Ok(Self { inner, language })
+    }
+
+    /// The language the query was compiled for.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Names of the captures declared in the query, without the `@` prefix.
+    pub fn capture_names(&self) -> &[&str] {
+        self.inner.capture_names()
+    }
+
+    /// Number of top-level patterns in the query.
+    pub fn pattern_count(&self) -> usize {
+        self.inner.pattern_count()
+    }
+
+    /// Runs the query over a tree parsed from `file`, evaluating text
+    /// predicates such as `#eq?` and `#match?`.
+    pub fn matches(&self, tree: &Tree, file: &SourceFile) -> Vec<QueryMatch> {
+        let mut cursor = QueryCursor::new();
+        let names = self.inner.capture_names();
+        let mut matches = cursor.matches(&self.inner, tree.root_node(), file.text().as_bytes());
+        let mut found = Vec::new();
+        while let Some(m) = matches.next() {
+            found.push(QueryMatch {
+                pattern_index: m.pattern_index,
+                captures: m
+                    .captures
+                    .iter()
+                    .map(|c| {
+                        let span = file.node_span(&c.node);
+                        QueryCapture {
+                            name: names[c.index as usize].to_string(),
+                            kind: c.node.kind().to_string(),
+                            text: file.slice(&span).to_string(),
+                            span,
+                        }
+                    })
+                    .collect(),
+            });
+        }
+        found
+    }
+}
+
+/// One match of a query pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryMatch {
+    /// Index of the pattern within the query that produced this match.
+    pub pattern_index: usize,
+    /// Captured nodes, in the order tree-sitter reports them.
+    pub captures: Vec<QueryCapture>,
+}
+
+impl QueryMatch {
+    /// Returns the first capture with the given name.
+    pub fn capture(&self, name: &str) -> Option<&QueryCapture> {
+        self.captures.iter().find(|c| c.name == name)
+    }
+
+    /// Returns every capture with the given name (for quantified captures).
+    pub fn captures_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a QueryCapture> {
+        self.captures.iter().filter(move |c| c.name == name)
+    }
+}
+
+/// A node captured by a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryCapture {
+    /// Capture name, without the `@` prefix.
+    pub name: String,
+    /// Syntax node kind of the captured node.
+    pub kind: String,
+    /// Source range of the captured node.
+    pub span: Span,
+    /// Source text of the captured node.
+    pub text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_query_captures_and_predicates() {
+        let source = "def keep(a): pass\ndef skip(b): pass\n";
+        let mut parser = Parser::new().unwrap();
+        let matches = parser
+            .query(
+                source,
+                r#"(function_definition
+                     name: (identifier) @name
+                     parameters: (parameters (identifier) @param)
+                     (#eq? @name "keep"))"#,
+            )
+            .unwrap();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].capture("param").unwrap().text, "a");
+        assert_eq!(matches[0].capture("name").unwrap().span.start.column, 4);
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        assert!(Query::new(Language::Python, "(no_such_node) @x").is_err());
+    }
+}
@@ -0,0 +1,460 @@
+//! A high-level entry point for embedding omnitype in other tools.
+//!
+//! [`Project`] bundles what the command-line checker discovers for a path
+//! (configuration, custom rules, and the target Python version) and runs
+//! the same checks, fixes, and traces, returning structured results:
+//!
+//! ```no_run
+//! use omnitype::project::Project;
+//!
+//! let project = Project::discover("src")?;
+//! let report = project.check()?;
+//! for d in report.diagnostics() {
+//!     println!("{}", d);
+//! }
+//! # Ok::<(), omnitype::error::Error>(())
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::analyzer::{
+    AnalysisCache, AnalysisResult, Analyzer, IncrementalAnalysis, ParallelAnalysis, RuleSet,
+    StubPaths, SymbolTable, CACHE_DIR,
+};
+use crate::config::Config;
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Partial, Result};
+use crate::fixer::Fixer;
+use crate::parser::Language;
+#[cfg(feature = "tracer")]
+use crate::tracer::{RuntimeTracer, TypeTrace};
+use crate::types::TypeEnv;
+use crate::utils::{detect_python_version, PythonVersion};
+
+/// A file or directory to check, with the settings discovered for it.
+#[derive(Debug)]
+pub struct Project {
+    path: PathBuf,
+    config: Config,
+    rules: RuleSet,
+    python_version: PythonVersion,
+    jobs: usize,
+}
+
+impl Project {
+    /// Loads the configuration and custom rules that apply to `path` and
+    /// determines the Python version: the configured one, else the one
+    /// detected from project metadata.
+    pub fn discover(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            return Err(Error::invalid_value(
+                "path",
+                path.display().to_string(),
+                "no such file or directory",
+            ));
+        }
+        let config = Config::discover(&path)?;
+        let rules = RuleSet::discover(&path)?;
+        let python_version = config
+            .python_version
+            .or_else(|| detect_python_version(&path))
+            .unwrap_or(PythonVersion::DEFAULT);
+        Ok(Self { path, config, rules, python_version, jobs: 0 })
+    }
+
+    /// Replaces the discovered configuration.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Overrides the Python version to check against.
+    pub fn with_python_version(mut self, version: PythonVersion) -> Self {
+        self.python_version = version;
+        self
+    }
+
+    /// Sets how many files are analyzed at once; 0, the default, uses
+    /// every CPU core.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// The checked file or directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The configuration in effect.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The custom rules run alongside the built-in checks.
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// The Python version checked against.
+    pub fn python_version(&self) -> PythonVersion {
+        self.python_version
+    }
+
+    /// Analyzes every enabled source file, several at once (see
+    /// [`Project::with_jobs`]), applies the custom rules and the
+    /// configuration, and returns the results sorted by path. Files that
+    /// fail to read or parse are listed in [`CheckReport::errors`]; only a
+    /// failure to list the files is returned as an error.
+    pub fn check(&self) -> Result<CheckReport> {
+        let mut report = CheckReport::default();
+        if self.path.is_file() {
+            if Language::from_path(&self.path).is_some_and(Language::is_enabled) {
+                match Analyzer::analyze_file_with_stubs(&self.path, &self.stub_paths()) {
+                    Ok(result) => report.results.push(result),
+                    Err(e) => report.errors.push(e),
+                }
+            } else {
+                report.errors.push(Error::invalid_value(
+                    "path",
+                    self.path.display().to_string(),
+                    "not a supported source file",
+                ));
+            }
+        } else {
+            // Analysis consumes files while the walk is still discovering more.
+            let files = self.config.walker(&self.path).files_parallel(0)?;
+            let batch = self.engine().analyze(files);
+            report.results = batch.value;
+            report.errors = batch.errors;
+            report.results.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        self.finish(&mut report);
+        Ok(report)
+    }
+
+    /// Checks the project like [`Project::check`], but reuses the results
+    /// `state` kept from earlier runs for files that haven't changed and
+    /// import no module that has. [`CheckReport::analyzed`] lists the files
+    /// analyzed again.
+    pub fn check_incremental(&self, state: &mut IncrementalAnalysis) -> Result<CheckReport> {
+        let mut report = CheckReport::default();
+        let files: Vec<PathBuf> = self
+            .files()?
+            .into_iter()
+            .filter(|f| Language::from_path(f).is_some_and(Language::is_enabled))
+            .collect();
+        if self.path.is_file() && files.is_empty() {
+            report.errors.push(Error::invalid_value(
+                "path",
+                self.path.display().to_string(),
+                "not a supported source file",
+            ));
+        }
+        let run = state.analyze(self.base(), &files, &self.engine());
+        report.results = run.value.results;
+        report.analyzed = run.value.analyzed;
+        report.errors = run.errors;
+        report.results.sort_by(|a, b| a.path.cmp(&b.path));
+        self.finish(&mut report);
+        Ok(report)
+    }
+
+    /// Checks the project like [`Project::check_incremental`], keeping
+    /// results on disk in [`Project::cache`] so that later runs, in this
+    /// process or another, only analyze what changed.
+    pub fn check_cached(&self) -> Result<CheckReport> {
+        let mut state = IncrementalAnalysis::new().with_cache(self.cache()?);
+        self.check_incremental(&mut state)
+    }
+
+    /// The analysis cache for the project's current settings, in the
+    /// [`CACHE_DIR`] beside its configuration file or, without one, in the
+    /// checked directory.
+    pub fn cache(&self) -> Result<AnalysisCache> {
+        let root = match Config::find(&self.path)? {
+            Some(config) => config.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            None => self.base().to_path_buf(),
+        };
+        let mut settings = serde_json::to_vec(&self.config)
+            .map_err(|e| Error::Other(format!("Failed to serialize config: {}", e)))?;
+        settings.extend_from_slice(self.python_version.to_string().as_bytes());
+        Ok(AnalysisCache::new(root.join(CACHE_DIR), &settings))
+    }
+
+    /// Applies the custom rules and the configuration to every result.
+    fn finish(&self, report: &mut CheckReport) {
+        for result in &mut report.results {
+            if let Err(e) = self.apply_settings(result) {
+                report.errors.push(e);
+            }
+        }
+        report.failed = report
+            .results
+            .iter()
+            .any(|r| self.config.fails(&r.diagnostics));
+    }
+
+    /// The source files [`Project::check`] analyzes, in walk order.
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        if self.path.is_file() {
+            return Ok(vec![self.path.clone()]);
+        }
+        Ok(self.config.walker(&self.path).files()?.collect())
+    }
+
+    /// Builds the symbol table of the project's Python modules (notebooks
+    /// excluded), naming modules relative to the project directory.
+    pub fn symbols(&self) -> Result<Partial<SymbolTable>> {
+        let root = self.base();
+        let files = self
+            .files()?
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "py" || e == "pyi"));
+        Ok(SymbolTable::from_files(root, files))
+    }
+
+    /// Checks one file the way [`Project::check`] does, for callers that
+    /// track files themselves.
+    pub fn check_file(&self, path: &Path) -> Result<AnalysisResult> {
+        let mut result =
+            Analyzer::analyze_file_with_stubs(path, &self.stub_paths()).map_err(|e| {
+                match e.path() {
+                    Some(_) => e,
+                    None => e.with_path(path),
+                }
+            })?;
+        self.apply_settings(&mut result)?;
+        Ok(result)
+    }
+
+    /// Analyzes files on `jobs` threads with the configured stubs.
+    fn engine(&self) -> ParallelAnalysis {
+        ParallelAnalysis::new(self.stub_paths()).threads(self.jobs)
+    }
+
+    /// The checked directory, or the one containing the checked file.
+    fn base(&self) -> &Path {
+        if self.path.is_file() {
+            self.path.parent().unwrap_or(Path::new("."))
+        } else {
+            &self.path
+        }
+    }
+
+    /// The configured stub directories and typeshed checkout, relative to
+    /// the checked directory.
+    fn stub_paths(&self) -> StubPaths {
+        let base = self.base();
+        StubPaths {
+            dirs: self.config.stubs.iter().map(|dir| base.join(dir)).collect(),
+            typeshed: self.config.typeshed.as_ref().map(|dir| base.join(dir)),
+        }
+    }
+
+    /// Adds the custom rules' diagnostics to an analyzer result and applies
+    /// the configuration. The result is finalized even if the rules fail.
+    pub fn apply_settings(&self, result: &mut AnalysisResult) -> Result<()> {
+        result.apply_unsolved(self.config.unsolved);
+        let custom = match self.rules.check_file(Path::new(&result.path)) {
+            Ok(diagnostics) => {
+                result.diagnostics.extend(diagnostics);
+                Ok(())
+            },
+            Err(e) => Err(e.with_path(&result.path)),
+        };
+        self.config.finalize(&mut result.diagnostics);
+        custom
+    }
+
+    /// Adds missing annotations to the project's Python files, skipping
+    /// excluded paths. Files that fail to read or parse are listed in
+    /// [`FixReport::errors`]; only a failure to list the files is returned
+    /// as an error.
+    pub fn fix(&self, options: FixOptions) -> Result<FixReport> {
+        let fixer = Fixer::new(TypeEnv::new(), options.in_place)
+            .exclude(self.config.exclude.clone())
+            .discovery(self.config.discovery.clone())
+            .with_python_version(self.python_version);
+        let batch = fixer.fix_files(fixer.files(&self.path)?);
+        Ok(FixReport { changed: batch.value, written: options.in_place, errors: batch.errors })
+    }
+
+    /// Runs a script under the runtime tracer and returns the observed
+    /// types. The script defaults to the project path, which must then be a
+    /// Python file.
+    #[cfg(feature = "tracer")]
+    pub fn trace(&self, options: TraceOptions) -> Result<TypeTrace> {
+        let script = options.script.as_deref().unwrap_or(&self.path);
+        let mut tracer = RuntimeTracer::new(false)
+            .keep_artifacts(options.keep_artifacts)
+            .doctests(options.doctests);
+        tracer.run(script, options.test.as_deref())?;
+        Ok(tracer.into_traces())
+    }
+}
+
+/// The outcome of [`Project::check`].
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    /// Analysis results for each file, sorted by path.
+    pub results: Vec<AnalysisResult>,
+    /// Files that couldn't be analyzed.
+    pub errors: Vec<Error>,
+    /// Files [`Project::check_incremental`] and [`Project::check_cached`]
+    /// analyzed again rather than
+    /// reusing an earlier result, in walk order.
+    pub analyzed: Vec<PathBuf>,
+    /// Whether any diagnostic reaches the configured `fail_on` severity.
+    pub failed: bool,
+}
+
+impl CheckReport {
+    /// Every diagnostic, in file order.
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.results.iter().flat_map(|r| r.diagnostics.iter())
+    }
+}
+
+/// Options for [`Project::fix`].
+#[derive(Debug, Clone, Default)]
+pub struct FixOptions {
+    in_place: bool,
+}
+
+impl FixOptions {
+    /// Creates options that only report which files would change.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the fixes back to the files.
+    pub fn in_place(mut self, in_place: bool) -> Self {
+        self.in_place = in_place;
+        self
+    }
+}
+
+/// The outcome of [`Project::fix`].
+#[derive(Debug, Default)]
+pub struct FixReport {
+    /// Files that needed annotations.
+    pub changed: Vec<PathBuf>,
+    /// Whether the changes were written to disk.
+    pub written: bool,
+    /// Files that couldn't be fixed.
+    pub errors: Vec<Error>,
+}
+
+/// Options for [`Project::trace`].
+#[cfg(feature = "tracer")]
+#[derive(Debug, Clone, Default)]
+pub struct TraceOptions {
+    script: Option<PathBuf>,
+    test: Option<String>,
+    keep_artifacts: bool,
+    doctests: bool,
+}
+
+#[cfg(feature = "tracer")]
+impl TraceOptions {
+    /// Creates options that trace the project path itself.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Traces this script instead of the project path.
+    pub fn script(mut self, script: impl Into<PathBuf>) -> Self {
+        self.script = Some(script.into());
+        self
+    }
+
+    /// Runs only the named test function from the script.
+    pub fn test(mut self, name: impl Into<String>) -> Self {
+        self.test = Some(name.into());
+        self
+    }
+
+    /// Keeps the run's generated files for debugging.
+    pub fn keep_artifacts(mut self, keep: bool) -> Self {
+        self.keep_artifacts = keep;
+        self
+    }
+
+    /// Also runs the script's doctest examples under the tracer.
+    pub fn doctests(mut self, doctests: bool) -> Self {
+        self.doctests = doctests;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_check_and_fix_project() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("omnitype.toml"), "exclude = [\"skip/\"]\n").unwrap();
+        fs::create_dir(dir.path().join("skip")).unwrap();
+        fs::write(dir.path().join("skip/b.py"), "def g(y):\n    pass\n").unwrap();
+        fs::write(dir.path().join("a.py"), "def f(x):\n    pass\n").unwrap();
+
+        let project = Project::discover(dir.path()).unwrap();
+        let report = project.check().unwrap();
+        assert_eq!(report.results.len(), 1);
+        assert_eq!(report.diagnostics().count(), 2);
+        assert!(report.failed);
+
+        let fixed = project.fix(FixOptions::new()).unwrap();
+        assert_eq!(fixed.changed, [dir.path().join("a.py")]);
+        assert!(fixed.errors.is_empty());
+        let fixed = project.fix(FixOptions::new().in_place(true)).unwrap();
+        assert!(fixed.written);
+        assert!(!project.check().unwrap().failed);
+
+        let symbols = project.symbols().unwrap();
+        assert!(symbols.errors.is_empty());
+        assert!(symbols.value.get("a.f").is_some());
+        assert!(symbols.value.get("skip.b.g").is_none());
+
+        assert!(Project::discover(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_check_uses_configured_stubs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("omnitype.toml"),
+            "stubs = [\"typings\"]\ntypeshed = \"typeshed\"\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("typings")).unwrap();
+        fs::write(dir.path().join("typings/a.pyi"), "def f(x: int, y: int) -> None: ...\n")
+            .unwrap();
+        fs::create_dir_all(dir.path().join("typeshed/stdlib")).unwrap();
+        fs::write(dir.path().join("typeshed/stdlib/os.pyi"), "def getcwd() -> str: ...\n").unwrap();
+        fs::write(
+            dir.path().join("a.py"),
+            "import os\n\ndef f(x: int) -> None:\n    pass\n\nf(1)\nos.getcwd(1)\n",
+        )
+        .unwrap();
+
+        let project = Project::discover(dir.path()).unwrap();
+        let report = project.check().unwrap();
+        let messages: Vec<&str> = report
+            .diagnostics()
+            .filter(|d| d.path.ends_with("a.py"))
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Call to 'f' is missing an argument for parameter 'y'",
+                "'os.getcwd' takes 0 positional arguments, but 1 were given",
+            ]
+        );
+    }
+}
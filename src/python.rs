@@ -0,0 +1,215 @@
+//! Python bindings, built as the `omnitype` extension module when the `pyo3`
+//! feature is enabled.
+//!
+//! ```python
+//! import omnitype
+//!
+//! for d in omnitype.check("src"):
+//!     print(d["path"], d["span"]["start"]["line"], d["message"])
+//! fixed = omnitype.annotate("def f(x):\n    return x\n")
+//! trace = omnitype.load_trace("trace.json")
+//! ```
+
+// `#[pyfunction]` expands to `PyErr` conversions that clippy flags as useless.
+#![allow(clippy::useless_conversion)]
+
+use std::path::{Path, PathBuf};
+
+use pyo3::exceptions::{PyOSError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_json::Value;
+
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Partial, Result};
+use crate::fixer::Fixer;
+use crate::project::Project;
+use crate::tracer::TypeTrace;
+
+/// Checks a file or directory with the project's configuration applied and
+/// returns the diagnostics as dictionaries, in the shape of `check --format json`.
+#[pyfunction]
+fn check(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let diagnostics = py
+        .allow_threads(|| collect_diagnostics(&path))
+        .map_err(to_py_err)?;
+    let value =
+        serde_json::to_value(&diagnostics).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    to_py(py, &value)
+}
+
+/// Returns `source` with `Any` annotations added to untyped parameters and
+/// returns.
+#[pyfunction]
+fn annotate(py: Python<'_>, source: &str) -> PyResult<String> {
+    py.allow_threads(|| Fixer::fix_source(source))
+        .map_err(to_py_err)
+}
+
+/// Loads a runtime trace saved by `omnitype trace --keep-artifacts`. Returns
+/// `{"variables": {name: [type, ...]}, "functions": {name: [{"args": [...],
+/// "returns": type}, ...]}}` with types written as annotations.
+#[pyfunction]
+fn load_trace(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let trace = TypeTrace::load(&path).map_err(to_py_err)?;
+    to_py(py, &trace_to_json(&trace))
+}
+
+/// The `omnitype` Python module.
+#[pymodule]
+fn omnitype(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(check, m)?)?;
+    m.add_function(wrap_pyfunction!(annotate, m)?)?;
+    m.add_function(wrap_pyfunction!(load_trace, m)?)?;
+    Ok(())
+}
+
+fn collect_diagnostics(path: &Path) -> Result<Vec<Diagnostic>> {
+    let report = Project::discover(path)?.check()?;
+    let diagnostics = report.diagnostics().cloned().collect();
+    Partial { value: diagnostics, errors: report.errors }.into_result()
+}
+
+fn trace_to_json(trace: &TypeTrace) -> Value {
+    let variables: serde_json::Map<String, Value> = trace
+        .variables
+        .keys()
+        .map(|name| {
+            let types: Vec<String> = trace
+                .get_variable_types(name)
+                .iter()
+                .map(|t| t.to_string())
+                .collect();
+            (name.clone(), Value::from(types))
+        })
+        .collect();
+    let functions: serde_json::Map<String, Value> = trace
+        .functions
+        .iter()
+        .map(|(name, (args, returns))| {
+            let calls: Vec<Value> = args
+                .iter()
+                .zip(returns)
+                .map(|(args, ret)| {
+                    let args: Vec<String> = args.iter().map(|t| t.to_string()).collect();
+                    serde_json::json!({ "args": args, "returns": ret.to_string() })
+                })
+                .collect();
+            (name.clone(), Value::from(calls))
+        })
+        .collect();
+    serde_json::json!({ "variables": variables, "functions": functions })
+}
+
+fn to_py(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into_py(py),
+            (None, Some(u)) => u.into_py(py),
+            _ => n.as_f64().unwrap_or_default().into_py(py),
+        },
+        Value::String(s) => s.into_py(py),
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| to_py(py, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new_bound(py, items).into_py(py)
+        },
+        Value::Object(map) => {
+            let dict = PyDict::new_bound(py);
+            for (key, item) in map {
+                dict.set_item(key, to_py(py, item)?)?;
+            }
+            dict.into_py(py)
+        },
+    })
+}
+
+fn to_py_err(e: Error) -> PyErr {
+    match e {
+        Error::Io(e) => PyOSError::new_err(e.to_string()),
+        e @ Error::Argument(_) => PyValueError::new_err(e.to_string()),
+        e => PyRuntimeError::new_err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_check_and_annotate() {
+        pyo3::prepare_freethreaded_python();
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.py"), "def f(x):\n    pass\n").unwrap();
+        Python::with_gil(|py| {
+            let diagnostics = check(py, dir.path().to_path_buf()).unwrap();
+            let diagnostics = diagnostics.downcast_bound::<PyList>(py).unwrap();
+            assert_eq!(diagnostics.len(), 2);
+            let code = diagnostics.get_item(0).unwrap().get_item("code").unwrap();
+            assert_eq!(code.extract::<String>().unwrap(), "OT001");
+
+            let missing = check(py, dir.path().join("missing")).unwrap_err();
+            assert!(missing.is_instance_of::<PyValueError>(py));
+
+            assert_eq!(
+                annotate(py, "def f(x):\n    return x\n").unwrap(),
+                "from typing import Any\ndef f(x: Any) -> Any:\n    return x\n"
+            );
+        });
+    }
+
+    #[test]
+    fn test_load_trace() {
+        pyo3::prepare_freethreaded_python();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.json");
+        fs::write(
+            &path,
+            r#"{"variables": {"x": ["int", "str", "int"]},
+                "functions": {"add": {"args": [["int", "int"]], "returns": ["int"]}}}"#,
+        )
+        .unwrap();
+        Python::with_gil(|py| {
+            let trace = load_trace(py, path.clone()).unwrap();
+            let trace = trace.downcast_bound::<PyDict>(py).unwrap();
+            let x = trace
+                .get_item("variables")
+                .unwrap()
+                .unwrap()
+                .get_item("x")
+                .unwrap();
+            assert_eq!(x.extract::<Vec<String>>().unwrap(), ["int", "str"]);
+            let add = trace
+                .get_item("functions")
+                .unwrap()
+                .unwrap()
+                .get_item("add")
+                .unwrap();
+            let call = add.get_item(0).unwrap();
+            assert_eq!(
+                call.get_item("args")
+                    .unwrap()
+                    .extract::<Vec<String>>()
+                    .unwrap(),
+                ["int", "int"]
+            );
+            assert_eq!(
+                call.get_item("returns")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap(),
+                "int"
+            );
+
+            let missing = load_trace(py, dir.path().join("missing.json")).unwrap_err();
+            assert!(missing.is_instance_of::<PyOSError>(py));
+        });
+    }
+}
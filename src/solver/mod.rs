@@ -1,9 +1,82 @@
 //! Constraint-based type inference engine.
+//!
+//! Constraints are solved one at a time into a substitution for type
+//! variables. `Equal` unifies its sides structurally. `Subtype` follows
+//! Python's rules: the numeric tower `bool <: int <: float`, a union is a
+//! subtype if each member is and a supertype if one member is (so
+//! `None <: Optional[T]`), everything is a subtype of `Any` and `object`,
+//! tuples are covariant, other containers are invariant, callables are
+//! contravariant in their parameters, which are paired up by position and
+//! keyword as a call would pass arguments, and classes are subtypes of their
+//! bases as registered with [`ConstraintSolver::add_class`]. Generators
+//! and coroutines are subtypes of the protocols they implement, as
+//! [`Type::protocol_base`] describes: a `Generator[Y, S, R]` is an
+//! `Iterator[Y]` and a `Coroutine[Y, S, R]` an `Awaitable[R]`. A type
+//! variable on either side of a subtype constraint is bound to the other
+//! side.
+//!
+//! Generic types are instantiated by replacing their type parameters with
+//! fresh variables, as [`ConstraintSolver::instantiate`] does for `Dict[K, V]`
+//! and [`ConstraintSolver::instance_of`] for user generic classes, so that
+//! constraints against concrete types such as `Dict[str, int]` infer the
+//! parameters. A generic class passes its type arguments along to the bases
+//! registered with [`ConstraintSolver::add_generic_class`].
+//!
+//! The type arguments of two instances of a generic class are compared by
+//! the [`Variance`] of each parameter. Read-only protocols such as
+//! `Sequence`, `Iterable`, and `Mapping`'s values are covariant, the `send`
+//! type of `Generator` is contravariant, and everything else, including
+//! user classes unless [`ConstraintSolver::set_variance`] says otherwise,
+//! is invariant.
+//!
+//! A solver can outlive one round of solving, as an editor session needs:
+//! [`ConstraintSolver::solve_pending`] solves only the constraints added
+//! since the last round, and [`ConstraintSolver::remove_constraints`]
+//! retracts outdated ones, re-solving just those that shared variables
+//! with them.
+//!
+//! Attribute accesses on inferred values become structural requirements
+//! ([`Constraint::HasAttribute`]). They are checked against the attributes
+//! declared with [`ConstraintSolver::add_attribute`] once the value's type
+//! is known, and while it isn't, [`ConstraintSolver::protocol`] describes
+//! them as a `Protocol` a duck-typed parameter could be annotated with.
+//! A [`Type::Protocol`] is structural: a class is a subtype of it when it
+//! has each member with a compatible type, whatever its bases. An
+//! intersection such as `Closeable & Flushable` is a subtype of whatever
+//! one of its members is a subtype of, a supertype of whatever is a subtype
+//! of every member, and has the attributes of all its members.
+//!
+//! Type aliases registered with [`ConstraintSolver::add_alias`] may refer
+//! to themselves, as `JSON = int | str | List[JSON] | Dict[str, JSON]`
+//! does. They are expanded one level at a time when compared, and a
+//! comparison that recurs into itself is assumed to hold, so two aliases
+//! with the same shape are interchangeable.
+//!
+//! Solving is bounded by [`SolverLimits`] on the number of constraints, the
+//! unification and subtyping steps, and the time one round may take. A
+//! round that runs out gives up on the constraints it has left, binding
+//! their variables to `Any`, and reports why through
+//! [`ConstraintSolver::fallback`] rather than hanging on pathological code.
+//!
+//! [`ConstraintSolver::solve`] attempts every constraint rather than
+//! stopping at the first conflict, so each one is reported. The variables
+//! a failed constraint leaves unbound are solved as `Unknown`, keeping one
+//! mistake from surfacing again wherever they are used.
+//!
+//! Variables that no constraint binds are handled as [`Unsolved`] says:
+//! they are reported to the caller by default, or solved as `Any` or
+//! `Unknown` for callers that would rather have a type than a gap.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use crate::error::Result;
-use crate::types::{Type, TypeVar};
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::{Diagnostic, MessageId, Severity};
+use crate::error::{Error, Partial, Result};
+use crate::types::{ClassHierarchy, Param, ParameterKind, Type, TypeVar, MAX_UNION_MEMBERS};
+use crate::utils::Span;
 
 /// Represents a constraint between two types.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -16,6 +89,60 @@ pub enum Constraint {
 
     /// Occurs check constraint (prevents infinite types)
     Occurs(TypeVar, Type),
+
+    /// Attribute constraint: T1 has an attribute of the given name whose
+    /// value is a T2, as reading `x.name` requires of `x`
+    HasAttribute(Type, String, Type),
+}
+
+/// Where a constraint comes from, to explain why solving it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    /// Why the types are constrained, such as "argument 2 of call expected
+    /// int because of parameter annotation".
+    pub reason: String,
+    /// The file the constraint was derived from, if known.
+    pub path: Option<PathBuf>,
+    /// The code the constraint was derived from, such as a call argument.
+    pub span: Option<Span>,
+}
+
+impl Origin {
+    /// Creates an origin with a reason but no location.
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self { reason: reason.into(), path: None, span: None }
+    }
+
+    /// Attaches the file the constraint was derived from.
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Attaches the code the constraint was derived from.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// `error`, from solving a constraint of this origin, located at the
+    /// origin and prefixed with its reason.
+    fn explain(&self, error: Error) -> Error {
+        let mut error = match error {
+            Error::Type(mut e) => {
+                e.message = format!("{}: {}", self.reason, e.message);
+                Error::Type(e)
+            },
+            e => e,
+        };
+        if let Some(path) = &self.path {
+            error = error.with_path(path);
+        }
+        match self.span {
+            Some(span) => error.with_span(span),
+            None => error,
+        }
+    }
 }
 
 /// A constraint solver for type inference.
@@ -23,13 +150,160 @@ pub struct ConstraintSolver {
     /// Type variable counter for generating fresh type variables
     next_var: u32,
 
-    /// Set of constraints to solve
-    constraints: Vec<Constraint>,
+    /// Set of constraints to solve, with where each comes from
+    constraints: Vec<(Constraint, Option<Origin>)>,
+
+    /// Constraints already solved into the substitution, kept so removing
+    /// constraints can re-solve the ones that shared variables with them
+    solved: Vec<(Constraint, Option<Origin>)>,
 
     /// Substitution mapping from type variables to types
     substitution: HashMap<TypeVar, Type>,
+
+    /// Attributes required of unbound type variables, checked once they
+    /// are bound
+    requirements: HashMap<TypeVar, BTreeMap<String, Type>>,
+
+    /// Changes to the substitution and requirements made while solving the
+    /// current constraint, undone if it or an alternative within it fails
+    trail: Vec<Undo>,
+
+    /// Type parameters and direct base classes of each known class, for
+    /// nominal subtyping
+    classes: HashMap<String, ClassInfo>,
+
+    /// Variance of the type parameters of generic classes, by name
+    variances: HashMap<String, Vec<Variance>>,
+
+    /// Type aliases by name, which may refer to themselves
+    aliases: HashMap<String, Type>,
+
+    /// Pairs of types being compared through an alias, assumed to match
+    /// if they come up again so recursive types compare in finite time
+    assumed: HashSet<(Type, Type)>,
+
+    /// Most members a union in the solution keeps before it is widened
+    max_union_members: usize,
+
+    /// How much work solving may do
+    limits: SolverLimits,
+
+    /// Unification and subtyping steps taken in the current round
+    steps: usize,
+
+    /// When the current round runs out of time, if it is limited
+    deadline: Option<Instant>,
+
+    /// Why the current round ran out, once it has
+    exhausted: Option<String>,
+
+    /// The warning for the constraints the last round gave up on, if any
+    fallback: Option<Diagnostic>,
+
+    /// What the solution holds for variables no constraint binds
+    unsolved: Unsolved,
+}
+
+/// What becomes of the type variables left unbound once the constraints
+/// are solved, which depends on how strict the caller wants to be.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unsolved {
+    /// They are solved as `Any`, which accepts everything silently.
+    Any,
+    /// They are solved as `Unknown`, which is as lenient as `Any` but
+    /// marks the type as not inferred.
+    Unknown,
+    /// They are left out of the solution, for the caller to report with
+    /// [`ConstraintSolver::unsolved_vars`].
+    #[default]
+    Report,
+}
+
+impl Unsolved {
+    /// The type unbound variables are solved as, or `None` if they are
+    /// left for the caller to report.
+    pub fn fallback(self) -> Option<Type> {
+        match self {
+            Unsolved::Any => Some(Type::Any),
+            Unsolved::Unknown => Some(Type::Unknown),
+            Unsolved::Report => None,
+        }
+    }
+}
+
+/// Limits on the work [`ConstraintSolver::solve_pending`] does, so that
+/// pathological code degrades to `Any` instead of hanging the checker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolverLimits {
+    /// Most constraints, pending and solved, the solver takes on.
+    pub max_constraints: usize,
+    /// Most unification and subtyping steps one round of solving takes.
+    pub max_steps: usize,
+    /// Longest one round of solving runs, if limited.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for SolverLimits {
+    fn default() -> Self {
+        Self {
+            max_constraints: 10_000,
+            max_steps: 1_000_000,
+            timeout: Some(Duration::from_secs(5)),
+        }
+    }
+}
+
+/// How a generic class's subtyping follows that of a type argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variance {
+    /// `C[A] <: C[B]` when `A <: B`, as for read-only containers.
+    Covariant,
+    /// `C[A] <: C[B]` when `B <: A`, as for values a class only consumes.
+    Contravariant,
+    /// `C[A] <: C[B]` only when `A` and `B` are the same, as for mutable
+    /// containers.
+    Invariant,
+}
+
+/// A class registered with the solver.
+#[derive(Debug, Clone, Default)]
+struct ClassInfo {
+    /// Names of the type parameters, such as the `T` of `Box[T]`.
+    params: Vec<String>,
+    /// Direct base classes, which may mention the type parameters.
+    bases: Vec<Type>,
+    /// Types of the attributes the class itself defines, which may mention
+    /// the type parameters.
+    attributes: HashMap<String, Type>,
+}
+
+/// A change to the substitution or requirements, recorded so that
+/// [`ConstraintSolver::rollback`] can undo it.
+#[derive(Debug, Clone)]
+enum Undo {
+    /// The variable was bound.
+    Bind(TypeVar),
+    /// The variable was required to have the named attribute.
+    Require(TypeVar, String),
+    /// The requirements of the variable were taken when it was bound.
+    Take(TypeVar, BTreeMap<String, Type>),
 }
 
+/// What looking up an attribute of a class finds.
+enum Lookup {
+    /// The class or one of its bases defines the attribute with this type.
+    Found(Type),
+    /// The class and all its bases are known and lack the attribute.
+    Missing,
+    /// Some class involved isn't registered, so the attribute may exist.
+    Unknown,
+}
+
+/// How deep [`ConstraintSolver::ancestor`] follows base classes, which
+/// stops it on inheritance cycles.
+const MAX_CLASS_DEPTH: usize = 64;
+
 impl Default for ConstraintSolver {
     fn default() -> Self {
         Self::new()
@@ -39,7 +313,45 @@ impl Default for ConstraintSolver {
 impl ConstraintSolver {
     /// Creates a new constraint solver.
     pub fn new() -> Self {
-        Self { next_var: 0, constraints: Vec::new(), substitution: HashMap::new() }
+        Self {
+            next_var: 0,
+            constraints: Vec::new(),
+            solved: Vec::new(),
+            substitution: HashMap::new(),
+            requirements: HashMap::new(),
+            trail: Vec::new(),
+            classes: HashMap::new(),
+            variances: default_variances(),
+            aliases: HashMap::new(),
+            assumed: HashSet::new(),
+            max_union_members: MAX_UNION_MEMBERS,
+            limits: SolverLimits::default(),
+            steps: 0,
+            deadline: None,
+            exhausted: None,
+            fallback: None,
+            unsolved: Unsolved::default(),
+        }
+    }
+
+    /// Widens the unions in the solution once they have more than `max`
+    /// members, as [`Type::capped`] does.
+    pub fn with_max_union_members(mut self, max: usize) -> Self {
+        self.max_union_members = max;
+        self
+    }
+
+    /// Bounds how much work solving does, as described by [`SolverLimits`].
+    pub fn with_limits(mut self, limits: SolverLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Decides what the solution holds for variables that no constraint
+    /// binds. By default they are left out ([`Unsolved::Report`]).
+    pub fn with_unsolved(mut self, unsolved: Unsolved) -> Self {
+        self.unsolved = unsolved;
+        self
     }
 
     /// Generates a fresh type variable.
@@ -51,15 +363,449 @@ impl ConstraintSolver {
 
     /// Adds a new constraint to the solver.
     pub fn add_constraint(&mut self, constraint: Constraint) {
-        self.constraints.push(constraint);
+        self.constraints.push((constraint, None));
+    }
+
+    /// Adds a constraint derived from the code `origin` describes. If the
+    /// constraint can't be satisfied, [`ConstraintSolver::solve`] fails
+    /// with an error located there that starts with the origin's reason.
+    pub fn add_constraint_from(&mut self, constraint: Constraint, origin: Origin) {
+        self.constraints.push((constraint, Some(origin)));
     }
 
-    /// Solves the collected constraints and returns the substitution.
-    pub fn solve(mut self) -> Result<HashMap<TypeVar, Type>> {
-        while let Some(constraint) = self.constraints.pop() {
-            self.solve_constraint(constraint)?;
+    /// Registers a class and its direct base classes, so that instances of
+    /// the class are subtypes of instances of its bases.
+    pub fn add_class(&mut self, name: impl Into<String>, bases: Vec<String>) {
+        let info = self.classes.entry(name.into()).or_default();
+        info.bases = bases.into_iter().map(Type::Named).collect();
+    }
+
+    /// Registers a generic class with its type parameters and direct base
+    /// classes, whose types may mention the parameters: `class Pair(Base[T],
+    /// Generic[T, U])` is `add_generic_class("Pair", ["T", "U"],
+    /// [Base[T]])`. An instance `Pair[int, str]` is then a subtype of
+    /// `Base[int]`.
+    pub fn add_generic_class(
+        &mut self,
+        name: impl Into<String>,
+        params: Vec<String>,
+        bases: Vec<Type>,
+    ) {
+        let info = self.classes.entry(name.into()).or_default();
+        (info.params, info.bases) = (params, bases);
+    }
+
+    /// Declares an attribute, or a method as a `Function` without `self`,
+    /// defined by a class, registering the class if needed. Instances of a
+    /// registered class only satisfy [`Constraint::HasAttribute`] for the
+    /// attributes declared on it or its bases.
+    pub fn add_attribute(&mut self, class: impl Into<String>, name: impl Into<String>, ty: Type) {
+        let info = self.classes.entry(class.into()).or_default();
+        info.attributes.insert(name.into(), ty);
+    }
+
+    /// Registers every class of `classes` with its direct bases and the
+    /// members it declares, as [`ConstraintSolver::add_class`] and
+    /// [`ConstraintSolver::add_attribute`] do one at a time.
+    pub fn add_hierarchy(&mut self, classes: &ClassHierarchy) {
+        for (name, bases) in classes.classes() {
+            self.add_class(name.clone(), bases.to_vec());
+            for (member, ty) in classes.members(name).into_iter().flatten() {
+                self.add_attribute(name.clone(), member.clone(), ty.clone());
+            }
         }
-        Ok(self.substitution)
+    }
+
+    /// Declares a type alias, which `Named(name)` then stands for. The
+    /// alias may refer to itself, which is how recursive types are written:
+    /// `JSON = None | bool | int | float | str | List[JSON] | Dict[str,
+    /// JSON]`. Recursive classes, such as a linked-list node whose `next`
+    /// is another node, need no alias since classes are compared by name.
+    pub fn add_alias(&mut self, name: impl Into<String>, ty: Type) {
+        self.aliases.insert(name.into(), ty);
+    }
+
+    /// Sets the variance of each type parameter of the generic class
+    /// `name`, in order. Parameters without one are invariant.
+    pub fn set_variance(&mut self, name: impl Into<String>, variances: Vec<Variance>) {
+        self.variances.insert(name.into(), variances);
+    }
+
+    /// Replaces the type parameters named by `params` in `ty` with fresh
+    /// type variables, returned in the same order. Constraining the result
+    /// infers the parameters: instantiating `Dict[K, V]` and unifying it
+    /// with `Dict[str, int]` binds the variables for `K` and `V` to `str`
+    /// and `int`.
+    pub fn instantiate(&mut self, ty: &Type, params: &[String]) -> (Type, Vec<TypeVar>) {
+        let vars: Vec<TypeVar> = params.iter().map(|_| self.fresh_var()).collect();
+        let args: Vec<Type> = vars.iter().map(|&var| Type::Var(var)).collect();
+        (substitute(ty, params, &args), vars)
+    }
+
+    /// An instance of a registered class whose type arguments are fresh
+    /// type variables, such as `Box[T0]` for `Box[T]`, to infer from the
+    /// values the instance is built from. Unknown classes and classes
+    /// without type parameters are plain named types.
+    pub fn instance_of(&mut self, class: &str) -> Type {
+        let params = match self.classes.get(class) {
+            Some(info) if !info.params.is_empty() => info.params.clone(),
+            _ => return Type::Named(class.to_string()),
+        };
+        let params = params.iter().map(|_| Type::Var(self.fresh_var())).collect();
+        Type::Generic { name: class.to_string(), params }
+    }
+
+    /// Solves the collected constraints and returns the substitution, with
+    /// every variable bound to a type free of bound variables and large
+    /// unions widened (see [`ConstraintSolver::with_max_union_members`]).
+    /// Every constraint is attempted, so one conflict doesn't hide the
+    /// others: a single failure is returned as is, and several are wrapped
+    /// in [`Error::Multiple`].
+    pub fn solve(self) -> Result<HashMap<TypeVar, Type>> {
+        self.solve_partial().into_result()
+    }
+
+    /// Like [`ConstraintSolver::solve`], but returns the substitution
+    /// together with the conflicts instead of discarding it when a
+    /// constraint fails. The variables a failed constraint leaves unbound
+    /// are solved as `Unknown`, as [`ConstraintSolver::solve_all`] does.
+    pub fn solve_partial(mut self) -> Partial<HashMap<TypeVar, Type>> {
+        let failed = self.solve_all();
+        let mut batch = Partial::new(self.substitution());
+        batch.errors = failed.into_iter().map(|(_, e)| e).collect();
+        batch
+    }
+
+    /// Solves the constraints added since the last call, building on the
+    /// substitution found so far, so a long-lived solver only pays for what
+    /// changed. Solving stops at the first constraint that fails, which is
+    /// dropped without binding anything; the rest stay pending.
+    ///
+    /// A round that exceeds the [`SolverLimits`] isn't an error: the
+    /// constraints it didn't get to are dropped, their unbound variables
+    /// are bound to `Any`, and [`ConstraintSolver::fallback`] says why.
+    pub fn solve_pending(&mut self) -> Result<()> {
+        match self.solve_round(false).pop() {
+            Some((_, e)) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Solves the pending constraints like
+    /// [`ConstraintSolver::solve_pending`], but keeps going past those that
+    /// fail, which are dropped and returned with their errors, so a few
+    /// contradictory constraints don't hold up the rest. The variables a
+    /// failed constraint leaves unbound are bound to `Unknown`, which fits
+    /// everything, so the error doesn't resurface in every constraint that
+    /// shares them.
+    pub fn solve_all(&mut self) -> Vec<(Constraint, Error)> {
+        self.solve_round(true)
+    }
+
+    /// One round of solving, which stops at the first failure unless
+    /// `keep_going`, and returns the constraints that failed.
+    fn solve_round(&mut self, keep_going: bool) -> Vec<(Constraint, Error)> {
+        self.steps = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+        self.exhausted = None;
+        self.fallback = None;
+        let mut failed = Vec::new();
+        let total = self.constraints.len() + self.solved.len();
+        if total > self.limits.max_constraints {
+            let reason = format!("more than {} constraints", self.limits.max_constraints);
+            self.give_up(reason);
+            return failed;
+        }
+        while let Some((constraint, origin)) = self.constraints.pop() {
+            let solved = self.solve_constraint(constraint.clone());
+            if solved.is_err() {
+                self.rollback(0);
+            }
+            self.trail.clear();
+            if let Err(e) = solved {
+                if let Some(reason) = self.exhausted.take() {
+                    self.constraints.push((constraint, origin));
+                    self.give_up(reason);
+                    break;
+                }
+                let e = match &origin {
+                    Some(origin) => origin.explain(e),
+                    None => e,
+                };
+                if !keep_going {
+                    failed.push((constraint, e));
+                    break;
+                }
+                for var in constraint_vars(&constraint) {
+                    if matches!(self.resolve(&Type::Var(var)), Type::Var(_)) {
+                        self.requirements.remove(&var);
+                        self.substitution.insert(var, Type::Unknown);
+                    }
+                }
+                failed.push((constraint, e));
+                continue;
+            }
+            self.solved.push((constraint, origin));
+        }
+        failed
+    }
+
+    /// The "inference too complex" warning for the constraints the last
+    /// round of solving gave up on, located at the first of them, or `None`
+    /// if it stayed within the [`SolverLimits`].
+    pub fn fallback(&self) -> Option<&Diagnostic> {
+        self.fallback.as_ref()
+    }
+
+    /// Drops the pending constraints, binding their unbound variables to
+    /// `Any`, and records the warning for them. They count as solved, so
+    /// removing constraints can still queue them to be solved again.
+    fn give_up(&mut self, reason: String) {
+        let origin = self
+            .constraints
+            .last()
+            .and_then(|(_, origin)| origin.clone());
+        for (constraint, origin) in std::mem::take(&mut self.constraints).into_iter().rev() {
+            for var in constraint_vars(&constraint) {
+                if matches!(self.resolve(&Type::Var(var)), Type::Var(_)) {
+                    self.substitution.insert(var, Type::Any);
+                    self.requirements.remove(&var);
+                }
+            }
+            self.solved.push((constraint, origin));
+        }
+        let path = origin
+            .as_ref()
+            .and_then(|o| o.path.as_ref())
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let span = origin.and_then(|o| o.span).unwrap_or_default();
+        self.fallback = Some(Diagnostic::from_message(
+            Severity::Warning,
+            path,
+            span,
+            MessageId::InferenceTooComplex,
+            &[("reason", &reason)],
+        ));
+    }
+
+    /// Counts a unification or subtyping step, failing once the round has
+    /// run out of steps or time.
+    fn step(&mut self) -> Result<()> {
+        if self.exhausted.is_none() {
+            self.steps += 1;
+            // Reading the clock is slow next to a step, so it's read rarely.
+            let late = self.steps.is_multiple_of(256)
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+            if self.steps > self.limits.max_steps {
+                self.exhausted = Some(format!("more than {} steps", self.limits.max_steps));
+            } else if late {
+                let timeout = self.limits.timeout.unwrap_or_default();
+                self.exhausted = Some(format!("took longer than {:?}", timeout));
+            }
+        }
+        match &self.exhausted {
+            Some(reason) => Err(Error::Other(format!("Inference too complex: {}", reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes the constraints, pending or solved, that `remove` selects,
+    /// such as those from an edited file, and returns how many there were.
+    /// Variables that were bound through them become unbound again, and
+    /// the solved constraints that mention those variables are queued to
+    /// be solved again by [`ConstraintSolver::solve_pending`]. Constraints
+    /// that share no variables with the removed ones keep their solution.
+    pub fn remove_constraints(
+        &mut self,
+        mut remove: impl FnMut(&Constraint, Option<&Origin>) -> bool,
+    ) -> usize {
+        let count = self.constraints.len() + self.solved.len();
+        self.constraints
+            .retain(|(constraint, origin)| !remove(constraint, origin.as_ref()));
+        let (removed, mut kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.solved)
+            .into_iter()
+            .partition(|(constraint, origin)| remove(constraint, origin.as_ref()));
+        let removed_count = count - self.constraints.len() - kept.len();
+
+        // Solutions spread between constraints that share variables, so
+        // everything connected to a removed constraint is solved again.
+        let mut affected: HashSet<TypeVar> = removed
+            .iter()
+            .flat_map(|(c, _)| constraint_vars(c))
+            .collect();
+        let mut requeued = Vec::new();
+        loop {
+            let (touched, untouched): (Vec<_>, Vec<_>) = kept
+                .into_iter()
+                .partition(|(c, _)| constraint_vars(c).iter().any(|v| affected.contains(v)));
+            kept = untouched;
+            if touched.is_empty() {
+                break;
+            }
+            affected.extend(touched.iter().flat_map(|(c, _)| constraint_vars(c)));
+            requeued.extend(touched);
+        }
+        self.substitution.retain(|var, _| !affected.contains(var));
+        self.requirements.retain(|var, _| !affected.contains(var));
+        self.solved = kept;
+        // Pending constraints are solved last to first, so the requeued
+        // ones go underneath to be solved in their original order.
+        requeued.reverse();
+        requeued.append(&mut self.constraints);
+        self.constraints = requeued;
+        removed_count
+    }
+
+    /// The solution so far: every bound variable, bound to a type free of
+    /// bound variables, in the canonical form of [`Type::normalize`], and
+    /// with large unions widened (see
+    /// [`ConstraintSolver::with_max_union_members`]). Unbound variables,
+    /// including those left in the types of bound ones, are solved as
+    /// [`ConstraintSolver::with_unsolved`] says.
+    pub fn substitution(&self) -> HashMap<TypeVar, Type> {
+        let Some(fallback) = self.unsolved.fallback() else {
+            return self
+                .substitution
+                .keys()
+                .map(|&var| (var, self.canonical(self.resolve(&Type::Var(var)))))
+                .collect();
+        };
+        let mut substitution = self.substitution.clone();
+        for var in self.unsolved_vars() {
+            substitution.insert(var, fallback.clone());
+        }
+        (0..self.next_var)
+            .map(TypeVar)
+            .map(|var| (var, self.canonical(resolve_in(&substitution, &Type::Var(var)))))
+            .collect()
+    }
+
+    /// The variables that no constraint has bound so far, directly or
+    /// through other variables, in the order they were created.
+    pub fn unsolved_vars(&self) -> Vec<TypeVar> {
+        (0..self.next_var)
+            .map(TypeVar)
+            .filter(|&var| matches!(self.resolve(&Type::Var(var)), Type::Var(_)))
+            .collect()
+    }
+
+    /// A `Protocol` class named `name` with the attributes required of the
+    /// unbound variable `var`, which describes the values a duck-typed
+    /// parameter accepts. Methods are those required as functions, with
+    /// positional-only parameters, and types still unknown are `Any`.
+    /// `None` if nothing is required of `var`.
+    pub fn protocol(&self, var: TypeVar, name: &str) -> Option<String> {
+        self.protocol_type(var, name)?.protocol_definition()
+    }
+
+    /// The protocol [`ConstraintSolver::protocol`] defines, as a
+    /// [`Type::Protocol`] that other types can be checked against.
+    pub fn protocol_type(&self, var: TypeVar, name: &str) -> Option<Type> {
+        let requirements = self.requirements.get(&var)?;
+        let members = requirements
+            .iter()
+            .map(|(attribute, ty)| {
+                let ty = self.resolve(ty);
+                let mut unknown = Vec::new();
+                type_vars(&ty, &mut unknown);
+                let unknown: HashMap<TypeVar, Type> =
+                    unknown.into_iter().map(|var| (var, Type::Any)).collect();
+                (attribute.clone(), resolve_in(&unknown, &ty))
+            })
+            .collect();
+        Some(Type::Protocol { name: name.to_string(), members })
+    }
+
+    /// A Graphviz DOT graph of the constraints and the substitution, for
+    /// debugging inference. Type variables are ellipses and other types
+    /// boxes. Each constraint is an edge between its sides, dashed while
+    /// still pending, and each binding a bold blue edge from the variable
+    /// to its type.
+    pub fn to_dot(&self) -> String {
+        let mut nodes: Vec<(String, bool)> = Vec::new();
+        let mut edges = Vec::new();
+        let mut node = |ty: &Type| {
+            let name = ty.to_string();
+            if !nodes.iter().any(|(n, _)| *n == name) {
+                nodes.push((name.clone(), matches!(ty, Type::Var(_))));
+            }
+            name
+        };
+        let solved = self.solved.iter().map(|c| (c, "solid"));
+        let pending = self.constraints.iter().rev().map(|c| (c, "dashed"));
+        for ((constraint, origin), style) in solved.chain(pending) {
+            let (from, to, label) = match constraint {
+                Constraint::Equal(a, b) => (node(a), node(b), "=".to_string()),
+                Constraint::Subtype(a, b) => (node(a), node(b), "<:".to_string()),
+                Constraint::Occurs(var, ty) => {
+                    (node(&Type::Var(*var)), node(ty), "occurs".to_string())
+                },
+                Constraint::HasAttribute(ty, name, attribute) => {
+                    (node(ty), node(attribute), format!(".{}", name))
+                },
+            };
+            let label = match origin {
+                Some(origin) => format!("{} ({})", label, origin.reason),
+                None => label,
+            };
+            edges.push(format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\", style={}];",
+                escape(&from),
+                escape(&to),
+                escape(&label),
+                style
+            ));
+        }
+        let mut bindings: Vec<_> = self.substitution.iter().collect();
+        bindings.sort_by_key(|(var, _)| var.0);
+        for (var, ty) in bindings {
+            let (from, to) = (node(&Type::Var(*var)), node(ty));
+            edges.push(format!(
+                "    \"{}\" -> \"{}\" [label=\":=\", style=bold, color=blue];",
+                escape(&from),
+                escape(&to)
+            ));
+        }
+
+        let mut dot = String::from("digraph constraints {\n    rankdir=LR;\n");
+        for (name, is_var) in &nodes {
+            let shape = if *is_var { "ellipse" } else { "box" };
+            dot.push_str(&format!("    \"{}\" [shape={}];\n", escape(name), shape));
+        }
+        for edge in edges {
+            dot.push_str(&edge);
+            dot.push('\n');
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Returns true if `sub` is a subtype of `sup` under the substitution
+    /// so far, without binding any variables.
+    pub fn is_subtype(&self, sub: &Type, sup: &Type) -> bool {
+        let mut scratch = Self {
+            next_var: self.next_var,
+            constraints: Vec::new(),
+            solved: Vec::new(),
+            substitution: self.substitution.clone(),
+            requirements: self.requirements.clone(),
+            trail: Vec::new(),
+            classes: self.classes.clone(),
+            variances: self.variances.clone(),
+            aliases: self.aliases.clone(),
+            assumed: HashSet::new(),
+            max_union_members: self.max_union_members,
+            limits: self.limits,
+            steps: 0,
+            deadline: None,
+            exhausted: None,
+            fallback: None,
+            unsolved: self.unsolved,
+        };
+        scratch.subtype(sub.clone(), sup.clone()).is_ok()
     }
 
     /// Solves a single constraint.
@@ -68,32 +814,677 @@ impl ConstraintSolver {
             Constraint::Equal(t1, t2) => self.unify(t1, t2)?,
             Constraint::Subtype(t1, t2) => self.subtype(t1, t2)?,
             Constraint::Occurs(var, ty) => self.occurs_check(var, &ty)?,
+            Constraint::HasAttribute(ty, name, attribute) => {
+                self.has_attribute(ty, name, attribute)?
+            },
         }
         Ok(())
     }
 
+    /// Requires `ty` to have an attribute `name` whose value is an
+    /// `attribute`. An unbound variable records the requirement until it
+    /// is bound; a registered class must define the attribute itself or
+    /// through its bases; other types are assumed to have it.
+    fn has_attribute(&mut self, ty: Type, name: String, attribute: Type) -> Result<()> {
+        match self.resolve(&ty) {
+            Type::Var(var) => {
+                let existing = self
+                    .requirements
+                    .get(&var)
+                    .and_then(|required| required.get(&name))
+                    .cloned();
+                match existing {
+                    Some(existing) => self.unify(existing, attribute),
+                    None => {
+                        self.requirements
+                            .entry(var)
+                            .or_default()
+                            .insert(name.clone(), attribute);
+                        self.trail.push(Undo::Require(var, name));
+                        Ok(())
+                    },
+                }
+            },
+            Type::Union(members) => members
+                .into_iter()
+                .try_for_each(|m| self.has_attribute(m, name.clone(), attribute.clone())),
+            // An intersection has the attributes of each of its members.
+            Type::Intersection(members) => {
+                for member in &members {
+                    let mark = self.trail.len();
+                    if self
+                        .has_attribute(member.clone(), name.clone(), attribute.clone())
+                        .is_ok()
+                    {
+                        return Ok(());
+                    }
+                    self.rollback(mark);
+                }
+                let ty = Type::Intersection(members);
+                Err(Error::type_mismatch(
+                    format!("{} has no attribute '{}'", ty, name),
+                    attribute,
+                    ty,
+                ))
+            },
+            ty if self.is_alias(&ty) => {
+                let expanded = self.expand(ty);
+                self.has_attribute(expanded, name, attribute)
+            },
+            ty @ (Type::Named(_) | Type::Generic { .. }) => {
+                match self.lookup_attribute(&ty, &name, 0) {
+                    Lookup::Found(found) => self.subtype(found, attribute),
+                    Lookup::Missing => Err(Error::type_mismatch(
+                        format!("{} has no attribute '{}'", ty, name),
+                        attribute,
+                        ty,
+                    )),
+                    Lookup::Unknown => Ok(()),
+                }
+            },
+            Type::Protocol { name: protocol, members } => match members.get(&name) {
+                Some(member) => self.subtype(member.clone(), attribute),
+                None => {
+                    let ty = Type::Protocol { name: protocol, members };
+                    Err(Error::type_mismatch(
+                        format!("{} has no attribute '{}'", ty, name),
+                        attribute,
+                        ty,
+                    ))
+                },
+            },
+            _ => Ok(()),
+        }
+    }
+
     /// Unifies two types.
-    fn unify(&mut self, _t1: Type, _t2: Type) -> Result<()> {
-        // TODO: Implement unification algorithm
-        Ok(())
+    fn unify(&mut self, t1: Type, t2: Type) -> Result<()> {
+        self.step()?;
+        let (t1, t2) = (self.resolve(&t1), self.resolve(&t2));
+        match (t1, t2) {
+            (t1, t2) if t1 == t2 => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => self.bind(var, ty),
+            (Type::Any | Type::Unknown, _) | (_, Type::Any | Type::Unknown) => Ok(()),
+            (t1, t2) if self.is_alias(&t1) || self.is_alias(&t2) => {
+                self.assuming(t1, t2, Self::unify)
+            },
+            (Type::List(a), Type::List(b))
+            | (Type::Set(a), Type::Set(b))
+            | (Type::TypeOf(a), Type::TypeOf(b)) => self.unify(*a, *b),
+            (Type::Dict(ak, av), Type::Dict(bk, bv)) => {
+                self.unify(*ak, *bk)?;
+                self.unify(*av, *bv)
+            },
+            // Unions and intersections are sorted, so equal ones line up
+            // member by member.
+            (Type::Tuple(a), Type::Tuple(b))
+            | (Type::Union(a), Type::Union(b))
+            | (Type::Intersection(a), Type::Intersection(b))
+                if a.len() == b.len() =>
+            {
+                a.into_iter().zip(b).try_for_each(|(a, b)| self.unify(a, b))
+            },
+            (
+                Type::Function { params: ap, returns: ar },
+                Type::Function { params: bp, returns: br },
+            ) if ap.len() == bp.len() && ap.iter().zip(&bp).all(|(a, b)| same_shape(a, b)) => {
+                ap.into_iter()
+                    .zip(bp)
+                    .try_for_each(|(a, b)| self.unify(a.ty, b.ty))?;
+                self.unify(*ar, *br)
+            },
+            (Type::Generic { name: an, params: ap }, Type::Generic { name: bn, params: bp })
+                if an == bn && ap.len() == bp.len() =>
+            {
+                ap.into_iter()
+                    .zip(bp)
+                    .try_for_each(|(a, b)| self.unify(a, b))
+            },
+            // A generic class without arguments leaves them unconstrained.
+            (Type::Generic { name: a, .. }, Type::Named(b))
+            | (Type::Named(b), Type::Generic { name: a, .. })
+                if a == b =>
+            {
+                Ok(())
+            },
+            (t1, t2) => {
+                Err(Error::type_mismatch(format!("Cannot unify {} with {}", t1, t2), t1, t2))
+            },
+        }
     }
 
     /// Handles subtyping relationships.
-    fn subtype(&mut self, _t1: Type, _t2: Type) -> Result<()> {
-        // TODO: Implement subtyping rules
-        Ok(())
+    fn subtype(&mut self, t1: Type, t2: Type) -> Result<()> {
+        self.step()?;
+        let (sub, sup) = (self.resolve(&t1), self.resolve(&t2));
+        let mismatch = |sub: Type, sup: Type| {
+            Err(Error::type_mismatch(format!("{} is not a subtype of {}", sub, sup), sup, sub))
+        };
+        match (sub, sup) {
+            (sub, sup) if sub == sup => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => self.bind(var, ty),
+            (Type::Any | Type::Unknown, _) | (_, Type::Any | Type::Unknown) => Ok(()),
+            (sub, sup) if self.is_alias(&sub) || self.is_alias(&sup) => {
+                self.assuming(sub, sup, Self::subtype)
+            },
+            (_, Type::Named(name)) if name == "object" => Ok(()),
+            (Type::Union(members), sup) => members
+                .into_iter()
+                .try_for_each(|m| self.subtype(m, sup.clone())),
+            (sub, Type::Union(members)) => {
+                // The first member that fits decides the variables it binds.
+                for member in &members {
+                    let mark = self.trail.len();
+                    if self.subtype(sub.clone(), member.clone()).is_ok() {
+                        return Ok(());
+                    }
+                    self.rollback(mark);
+                }
+                mismatch(sub, Type::Union(members))
+            },
+            (sub, Type::Intersection(members)) => members
+                .into_iter()
+                .try_for_each(|m| self.subtype(sub.clone(), m)),
+            (Type::Intersection(members), sup) => {
+                // A value of each member is a value of the intersection.
+                for member in &members {
+                    let mark = self.trail.len();
+                    if self.subtype(member.clone(), sup.clone()).is_ok() {
+                        return Ok(());
+                    }
+                    self.rollback(mark);
+                }
+                mismatch(Type::Intersection(members), sup)
+            },
+            (Type::Literal(value), sup) => {
+                let widened = Type::Literal(value).widened();
+                match widened {
+                    Type::Literal(_) => mismatch(widened, sup),
+                    widened => self.subtype(widened, sup),
+                }
+            },
+            (Type::Bool, Type::Int | Type::Float) | (Type::Int, Type::Float) => Ok(()),
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => a
+                .into_iter()
+                .zip(b)
+                .try_for_each(|(a, b)| self.subtype(a, b)),
+            // A subclass object can stand in for its base's.
+            (Type::TypeOf(a), Type::TypeOf(b)) => self.subtype(*a, *b),
+            // Mutable containers are invariant in their elements.
+            (Type::List(a), Type::List(b)) | (Type::Set(a), Type::Set(b)) => self.unify(*a, *b),
+            (Type::Dict(ak, av), Type::Dict(bk, bv)) => {
+                self.unify(*ak, *bk)?;
+                self.unify(*av, *bv)
+            },
+            (
+                Type::Function { params: ap, returns: ar },
+                Type::Function { params: bp, returns: br },
+            ) => match matching_params(&ap, &bp) {
+                Some(pairs) => {
+                    pairs
+                        .into_iter()
+                        .try_for_each(|(a, b)| self.subtype(b, a))?;
+                    self.subtype(*ar, *br)
+                },
+                None => mismatch(
+                    Type::Function { params: ap, returns: ar },
+                    Type::Function { params: bp, returns: br },
+                ),
+            },
+            (
+                sub @ (Type::Named(_) | Type::Generic { .. }),
+                sup @ (Type::Named(_) | Type::Generic { .. }),
+            ) => match (self.ancestor(&sub, class_name(&sup), 0), &sup) {
+                (Some(Type::Generic { params: ap, .. }), Type::Generic { params: bp, .. }) => {
+                    if ap.len() != bp.len() {
+                        return mismatch(sub, sup);
+                    }
+                    let variances = self.variances_of(class_name(&sup));
+                    ap.into_iter()
+                        .zip(bp.clone())
+                        .enumerate()
+                        .try_for_each(|(i, (a, b))| {
+                            match variances.get(i).copied().unwrap_or(Variance::Invariant) {
+                                Variance::Covariant => self.subtype(a, b),
+                                Variance::Contravariant => self.subtype(b, a),
+                                Variance::Invariant => self.unify(a, b),
+                            }
+                        })
+                },
+                // A class without arguments is a subtype of any instance.
+                (Some(_), _) => Ok(()),
+                (None, _) => mismatch(sub, sup),
+            },
+            // Protocols are structural: a type conforms by having every member.
+            (sub, Type::Protocol { members, .. }) => members
+                .into_iter()
+                .try_for_each(|(name, ty)| self.has_attribute(sub.clone(), name, ty)),
+            (sub, sup) => mismatch(sub, sup),
+        }
     }
 
     /// Performs the occurs check to prevent infinite types.
-    fn occurs_check(&self, _var: TypeVar, _ty: &Type) -> Result<()> {
-        // TODO: Implement occurs check
+    fn occurs_check(&self, var: TypeVar, ty: &Type) -> Result<()> {
+        let ty = self.resolve(ty);
+        if ty != Type::Var(var) && occurs(var, &ty) {
+            return Err(Error::type_mismatch(
+                format!("{} occurs in {}", var, ty),
+                Type::Var(var),
+                ty,
+            ));
+        }
         Ok(())
     }
+
+    /// Binds an unbound variable, unless that would make an infinite type.
+    fn bind(&mut self, var: TypeVar, ty: Type) -> Result<()> {
+        if ty == Type::Var(var) {
+            return Ok(());
+        }
+        self.occurs_check(var, &ty)?;
+        self.substitution.insert(var, ty.clone());
+        self.trail.push(Undo::Bind(var));
+        // What was required of the variable is now required of its type.
+        let Some(required) = self.requirements.remove(&var) else {
+            return Ok(());
+        };
+        self.trail.push(Undo::Take(var, required.clone()));
+        required
+            .into_iter()
+            .try_for_each(|(name, attribute)| self.has_attribute(ty.clone(), name, attribute))
+    }
+
+    /// Undoes the changes recorded on the trail since it was `mark` long,
+    /// latest first.
+    fn rollback(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            match self.trail.pop() {
+                Some(Undo::Bind(var)) => {
+                    self.substitution.remove(&var);
+                },
+                Some(Undo::Require(var, name)) => {
+                    if let Some(required) = self.requirements.get_mut(&var) {
+                        required.remove(&name);
+                        if required.is_empty() {
+                            self.requirements.remove(&var);
+                        }
+                    }
+                },
+                Some(Undo::Take(var, required)) => {
+                    self.requirements.insert(var, required);
+                },
+                None => break,
+            }
+        }
+    }
+
+    /// Whether `ty` names a type alias.
+    fn is_alias(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Named(name) if self.aliases.contains_key(name))
+    }
+
+    /// The type an alias stands for, or any other type as it is.
+    fn expand(&self, ty: Type) -> Type {
+        match &ty {
+            Type::Named(name) => self.aliases.get(name).cloned().unwrap_or(ty),
+            _ => ty,
+        }
+    }
+
+    /// Compares two types with `compare` after expanding aliases, assuming
+    /// they match if the same comparison is already underway further up:
+    /// comparing recursive types comes back to where it started, and
+    /// nothing along the way contradicted the match.
+    fn assuming(
+        &mut self,
+        a: Type,
+        b: Type,
+        compare: fn(&mut Self, Type, Type) -> Result<()>,
+    ) -> Result<()> {
+        let pair = (a, b);
+        if !self.assumed.insert(pair.clone()) {
+            return Ok(());
+        }
+        let (a, b) = (self.expand(pair.0.clone()), self.expand(pair.1.clone()));
+        let result = compare(self, a, b);
+        self.assumed.remove(&pair);
+        result
+    }
+
+    /// Applies the substitution to a type, following chains of variables.
+    fn resolve(&self, ty: &Type) -> Type {
+        resolve_in(&self.substitution, ty)
+    }
+
+    /// A resolved type as the solution presents it: normalized, with large
+    /// unions widened.
+    fn canonical(&self, ty: Type) -> Type {
+        ty.normalize().capped(self.max_union_members)
+    }
+
+    /// The type of attribute `name` on instances of the class type `ty`,
+    /// as defined by the class or the nearest base that defines it.
+    fn lookup_attribute(&self, ty: &Type, name: &str, depth: usize) -> Lookup {
+        let (class, args) = match ty {
+            Type::Named(class) => (class, &[][..]),
+            Type::Generic { name, params } => (name, params.as_slice()),
+            _ => return Lookup::Unknown,
+        };
+        let Some(info) = self.classes.get(class) else {
+            // Everything has `object`'s dunder attributes, and nothing else.
+            return match class.as_str() {
+                "object" if !name.starts_with("__") => Lookup::Missing,
+                _ => Lookup::Unknown,
+            };
+        };
+        if let Some(attribute) = info.attributes.get(name) {
+            return Lookup::Found(substitute(attribute, &info.params, args));
+        }
+        if depth == MAX_CLASS_DEPTH {
+            return Lookup::Unknown;
+        }
+        let mut lookup = Lookup::Missing;
+        for base in &info.bases {
+            match self.lookup_attribute(&substitute(base, &info.params, args), name, depth + 1) {
+                Lookup::Found(found) => return Lookup::Found(found),
+                Lookup::Unknown => lookup = Lookup::Unknown,
+                Lookup::Missing => {},
+            }
+        }
+        lookup
+    }
+
+    /// The variances of a generic class's parameters, looked up by its
+    /// full name, then by its last component, so `typing.Sequence` is
+    /// covariant like `Sequence`.
+    fn variances_of(&self, name: &str) -> Vec<Variance> {
+        let last = name.rsplit('.').next().unwrap_or(name);
+        self.variances
+            .get(name)
+            .or_else(|| self.variances.get(last))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The instance of class `target` that `ty` is or derives from, with
+    /// the type arguments `ty` passes to its bases: `Base[int]` for
+    /// `Pair[int, str]` where `class Pair(Base[T], Generic[T, U])`.
+    fn ancestor(&self, ty: &Type, target: &str, depth: usize) -> Option<Type> {
+        let (name, args) = match ty {
+            Type::Named(name) => (name, &[][..]),
+            Type::Generic { name, params } => (name, params.as_slice()),
+            _ => return None,
+        };
+        if name == target {
+            return Some(ty.clone());
+        }
+        if depth == MAX_CLASS_DEPTH {
+            return None;
+        }
+        let Some(class) = self.classes.get(name) else {
+            // `Generator[int, None, None]` is an `Iterator[int]`, and so on.
+            let base = ty.protocol_base()?;
+            return self.ancestor(&base, target, depth + 1);
+        };
+        class.bases.iter().find_map(|base| {
+            self.ancestor(&substitute(base, &class.params, args), target, depth + 1)
+        })
+    }
+}
+
+/// `ty` with the variables `substitution` binds replaced by their types,
+/// following chains of variables.
+fn resolve_in(substitution: &HashMap<TypeVar, Type>, ty: &Type) -> Type {
+    let resolve = |ty: &Type| Box::new(resolve_in(substitution, ty));
+    let resolve_all = |types: &[Type]| {
+        types
+            .iter()
+            .map(|ty| resolve_in(substitution, ty))
+            .collect()
+    };
+    match ty {
+        Type::Var(var) => match substitution.get(var) {
+            Some(bound) => resolve_in(substitution, bound),
+            None => ty.clone(),
+        },
+        Type::List(inner) => Type::List(resolve(inner)),
+        Type::Set(inner) => Type::Set(resolve(inner)),
+        Type::TypeOf(inner) => Type::TypeOf(resolve(inner)),
+        Type::Dict(key, value) => Type::Dict(resolve(key), resolve(value)),
+        Type::Tuple(items) => Type::Tuple(resolve_all(items)),
+        Type::Union(members) => Type::Union(resolve_all(members)),
+        Type::Intersection(members) => Type::Intersection(resolve_all(members)),
+        Type::Function { params, returns } => Type::Function {
+            params: params
+                .iter()
+                .map(|p| p.clone().map_type(|ty| resolve_in(substitution, &ty)))
+                .collect(),
+            returns: resolve(returns),
+        },
+        Type::Generic { name, params } => {
+            Type::Generic { name: name.clone(), params: resolve_all(params) }
+        },
+        ty => ty.clone(),
+    }
+}
+
+/// The variances of the standard library's generic protocols and
+/// immutable containers. Mutable ones are invariant, like user classes.
+fn default_variances() -> HashMap<String, Vec<Variance>> {
+    use Variance::{Contravariant, Covariant, Invariant};
+    let covariant = [
+        "AbstractSet",
+        "AsyncIterable",
+        "AsyncIterator",
+        "Awaitable",
+        "Collection",
+        "Container",
+        "FrozenSet",
+        "ItemsView",
+        "Iterable",
+        "Iterator",
+        "KeysView",
+        "Reversible",
+        "Sequence",
+        "Tuple",
+        "Type",
+        "ValuesView",
+        "frozenset",
+        "tuple",
+        "type",
+    ];
+    let mut variances: HashMap<String, Vec<Variance>> = covariant
+        .iter()
+        // Two entries cover `tuple[T, ...]` and `ItemsView[K, V]`.
+        .map(|name| (name.to_string(), vec![Covariant; 2]))
+        .collect();
+    variances.insert("Mapping".to_string(), vec![Invariant, Covariant]);
+    variances.insert("Generator".to_string(), vec![Covariant, Contravariant, Covariant]);
+    variances.insert("Coroutine".to_string(), vec![Covariant, Contravariant, Covariant]);
+    variances.insert("AsyncGenerator".to_string(), vec![Covariant, Contravariant]);
+    variances
+}
+
+/// The name of a class type, `Base` for both `Base` and `Base[int]`.
+fn class_name(ty: &Type) -> &str {
+    match ty {
+        Type::Named(name) | Type::Generic { name, .. } => name,
+        _ => "",
+    }
+}
+
+/// `ty` with each type parameter named in `params` replaced by the
+/// argument at the same position, or `Any` if there are fewer arguments.
+fn substitute(ty: &Type, params: &[String], args: &[Type]) -> Type {
+    let sub = |ty: &Type| Box::new(substitute(ty, params, args));
+    let sub_all = |types: &[Type]| {
+        types
+            .iter()
+            .map(|ty| substitute(ty, params, args))
+            .collect()
+    };
+    match ty {
+        Type::Named(name) => match params.iter().position(|param| param == name) {
+            Some(i) => args.get(i).cloned().unwrap_or(Type::Any),
+            None => ty.clone(),
+        },
+        Type::List(inner) => Type::List(sub(inner)),
+        Type::Set(inner) => Type::Set(sub(inner)),
+        Type::TypeOf(inner) => Type::TypeOf(sub(inner)),
+        Type::Dict(key, value) => Type::Dict(sub(key), sub(value)),
+        Type::Tuple(items) => Type::Tuple(sub_all(items)),
+        Type::Union(members) => Type::union_of(sub_all(members)),
+        Type::Intersection(members) => Type::intersection_of(sub_all(members)),
+        Type::Function { params: fn_params, returns } => Type::Function {
+            params: fn_params
+                .iter()
+                .map(|p| p.clone().map_type(|ty| substitute(&ty, params, args)))
+                .collect(),
+            returns: sub(returns),
+        },
+        Type::Generic { name, params: type_args } => {
+            Type::Generic { name: name.clone(), params: sub_all(type_args) }
+        },
+        ty => ty.clone(),
+    }
+}
+
+/// Whether two parameters take arguments the same way, so that functions
+/// with them are equal once their types are. A name only matters when
+/// both parameters have one, and a parameter passed by position only is
+/// alike a named one that may also be passed by keyword.
+fn same_shape(a: &Param, b: &Param) -> bool {
+    let names_agree = match (&a.name, &b.name) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    };
+    (a.kind == b.kind || a.is_positional() && b.is_positional())
+        && (names_agree || [a.kind, b.kind].contains(&ParameterKind::PositionalOnly))
+}
+
+/// Pairs each parameter of `expected` with the parameter of `actual` that
+/// receives its arguments, as `(actual, expected)` types, or `None` if a
+/// function with the parameters `actual` can't be called the way one with
+/// `expected` can: an argument `expected` accepts has nowhere to go, or a
+/// required parameter of `actual` would be left without one.
+fn matching_params(actual: &[Param], expected: &[Param]) -> Option<Vec<(Type, Type)>> {
+    // A `ParamSpec` stands for parameters not known here, assumed to fit.
+    if Type::param_spec(actual).is_some() || Type::param_spec(expected).is_some() {
+        let known = |p: &&Param| !matches!(p.ty, Type::ParamSpec(_));
+        let pairs = actual
+            .iter()
+            .take_while(known)
+            .zip(expected.iter().take_while(known));
+        return Some(pairs.map(|(a, e)| (a.ty.clone(), e.ty.clone())).collect());
+    }
+    let variadic = |kind| actual.iter().position(|p| p.kind == kind);
+    let mut positional = (0..actual.len()).filter(|&i| actual[i].is_positional());
+    let mut used = HashSet::new();
+    let mut pairs = Vec::new();
+    for param in expected {
+        let matched = match param.kind {
+            ParameterKind::PositionalOnly | ParameterKind::Positional => {
+                let i = positional
+                    .next()
+                    .or(variadic(ParameterKind::VarPositional))?;
+                // Callers may pass a named parameter by keyword as well.
+                let by_keyword = param.kind == ParameterKind::Positional && param.name.is_some();
+                let found = &actual[i];
+                if by_keyword
+                    && found.kind != ParameterKind::VarPositional
+                    && (found.kind != ParameterKind::Positional || found.name != param.name)
+                {
+                    return None;
+                }
+                i
+            },
+            ParameterKind::KeywordOnly => actual
+                .iter()
+                .position(|p| {
+                    p.name == param.name
+                        && matches!(p.kind, ParameterKind::Positional | ParameterKind::KeywordOnly)
+                })
+                .or(variadic(ParameterKind::VarKeyword))?,
+            kind => variadic(kind)?,
+        };
+        used.insert(matched);
+        pairs.push((actual[matched].ty.clone(), param.ty.clone()));
+    }
+    let unfilled = actual.iter().enumerate().any(|(i, p)| {
+        p.default.is_none()
+            && !matches!(p.kind, ParameterKind::VarPositional | ParameterKind::VarKeyword)
+            && !used.contains(&i)
+    });
+    (!unfilled).then_some(pairs)
+}
+
+/// `text` escaped for a quoted DOT identifier or label.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The type variables a constraint mentions.
+fn constraint_vars(constraint: &Constraint) -> Vec<TypeVar> {
+    let mut vars = Vec::new();
+    match constraint {
+        Constraint::Equal(a, b) | Constraint::Subtype(a, b) => {
+            type_vars(a, &mut vars);
+            type_vars(b, &mut vars);
+        },
+        Constraint::Occurs(var, ty) => {
+            vars.push(*var);
+            type_vars(ty, &mut vars);
+        },
+        Constraint::HasAttribute(ty, _, attribute) => {
+            type_vars(ty, &mut vars);
+            type_vars(attribute, &mut vars);
+        },
+    }
+    vars
+}
+
+/// Adds the type variables in `ty` to `vars`.
+fn type_vars(ty: &Type, vars: &mut Vec<TypeVar>) {
+    match ty {
+        Type::Var(var) => vars.push(*var),
+        Type::List(inner) | Type::Set(inner) | Type::TypeOf(inner) => type_vars(inner, vars),
+        Type::Dict(key, value) => {
+            type_vars(key, vars);
+            type_vars(value, vars);
+        },
+        Type::Tuple(types)
+        | Type::Union(types)
+        | Type::Intersection(types)
+        | Type::Generic { params: types, .. } => types.iter().for_each(|ty| type_vars(ty, vars)),
+        Type::Function { params, returns } => {
+            params.iter().for_each(|p| type_vars(&p.ty, vars));
+            type_vars(returns, vars);
+        },
+        _ => {},
+    }
+}
+
+/// Whether `var` appears in `ty`.
+fn occurs(var: TypeVar, ty: &Type) -> bool {
+    match ty {
+        Type::Var(other) => *other == var,
+        Type::List(inner) | Type::Set(inner) | Type::TypeOf(inner) => occurs(var, inner),
+        Type::Dict(key, value) => occurs(var, key) || occurs(var, value),
+        Type::Tuple(types) | Type::Union(types) | Type::Intersection(types) => {
+            types.iter().any(|ty| occurs(var, ty))
+        },
+        Type::Function { params, returns } => {
+            params.iter().any(|p| occurs(var, &p.ty)) || occurs(var, returns)
+        },
+        Type::Generic { params, .. } => params.iter().any(|ty| occurs(var, ty)),
+        _ => false,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::SourceFile;
 
     #[test]
     fn test_fresh_var() {
@@ -102,4 +1493,601 @@ mod tests {
         let var2 = solver.fresh_var();
         assert_ne!(var1, var2);
     }
+
+    #[test]
+    fn test_subtyping_rules() {
+        let mut solver = ConstraintSolver::new();
+        solver.add_class("Base", vec!["object".to_string()]);
+        solver.add_class("Child", vec!["Base".to_string()]);
+        let optional_int = Type::Union(vec![Type::Int, Type::None]);
+        let named = |name: &str| Type::Named(name.to_string());
+
+        assert!(solver.is_subtype(&Type::Bool, &Type::Float));
+        assert!(solver.is_subtype(&Type::Int, &Type::Float));
+        assert!(!solver.is_subtype(&Type::Float, &Type::Int));
+        assert!(solver.is_subtype(&Type::None, &optional_int));
+        assert!(solver.is_subtype(&Type::Bool, &optional_int));
+        assert!(!solver.is_subtype(&optional_int, &Type::Int));
+        assert!(solver.is_subtype(&named("Child"), &Type::Any));
+        assert!(solver.is_subtype(&Type::Str, &named("object")));
+        assert!(solver.is_subtype(&named("Child"), &named("Base")));
+        assert!(!solver.is_subtype(&named("Base"), &named("Child")));
+        assert!(solver.is_subtype(
+            &Type::Tuple(vec![Type::Bool, named("Child")]),
+            &Type::Tuple(vec![Type::Int, named("Base")])
+        ));
+        assert!(
+            !solver.is_subtype(&Type::List(Box::new(Type::Bool)), &Type::List(Box::new(Type::Int)))
+        );
+        assert!(solver.is_subtype(&Type::Literal("'a'".to_string()), &Type::Str));
+    }
+
+    #[test]
+    fn test_subtype_constraints_bind_variables() {
+        let mut solver = ConstraintSolver::new();
+        let t = solver.fresh_var();
+        let u = solver.fresh_var();
+        solver.add_constraint(Constraint::Subtype(Type::Var(u), Type::Float));
+        solver.add_constraint(Constraint::Subtype(
+            Type::Tuple(vec![Type::Bool, Type::Var(t)]),
+            Type::Tuple(vec![Type::Var(u), Type::Str]),
+        ));
+        let solution = solver.solve().unwrap();
+        assert_eq!(solution[&t], Type::Str);
+        assert_eq!(solution[&u], Type::Bool);
+
+        let mut solver = ConstraintSolver::new();
+        let t = solver.fresh_var();
+        solver.add_constraint(Constraint::Subtype(Type::Str, Type::Int));
+        assert!(solver.solve().is_err());
+        let mut solver = ConstraintSolver::new();
+        solver.add_constraint(Constraint::Equal(Type::Var(t), Type::List(Box::new(Type::Var(t)))));
+        assert!(solver.solve().is_err());
+    }
+
+    #[test]
+    fn test_failed_alternatives_roll_back() {
+        let mut solver = ConstraintSolver::new();
+        let t = solver.fresh_var();
+        let u = solver.fresh_var();
+        solver.add_constraint(Constraint::HasAttribute(
+            Type::Var(u),
+            "real".to_string(),
+            Type::Int,
+        ));
+        // The first member binds `t` and `u` before failing on `str`.
+        solver.add_constraint(Constraint::Subtype(
+            Type::Tuple(vec![Type::Var(t), Type::Var(u), Type::Str]),
+            Type::Union(vec![
+                Type::Tuple(vec![Type::Int, Type::Int, Type::Int]),
+                Type::Tuple(vec![Type::Str, Type::Var(u), Type::Str]),
+            ]),
+        ));
+        solver.solve_pending().unwrap();
+        assert!(solver.trail.is_empty());
+        assert_eq!(solver.resolve(&Type::Var(t)), Type::Str);
+        assert_eq!(solver.resolve(&Type::Var(u)), Type::Var(u));
+        assert_eq!(solver.requirements[&u]["real"], Type::Int);
+    }
+
+    #[test]
+    fn test_generic_instantiation() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let generic =
+            |name: &str, params: Vec<Type>| Type::Generic { name: name.to_string(), params };
+        let params = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let mut solver = ConstraintSolver::new();
+        let mapping = Type::Dict(Box::new(named("K")), Box::new(named("V")));
+        let (mapping, vars) = solver.instantiate(&mapping, &params(&["K", "V"]));
+        let concrete = Type::Dict(Box::new(Type::Str), Box::new(Type::Int));
+        solver.add_constraint(Constraint::Equal(mapping, concrete));
+        let solution = solver.solve().unwrap();
+        assert_eq!((&solution[&vars[0]], &solution[&vars[1]]), (&Type::Str, &Type::Int));
+
+        let mut solver = ConstraintSolver::new();
+        solver.add_generic_class("Base", params(&["T"]), Vec::new());
+        solver.add_generic_class(
+            "Pair",
+            params(&["T", "U"]),
+            vec![generic("Base", vec![named("T")])],
+        );
+        solver.add_class("Names", vec!["Pair".to_string()]);
+        assert_eq!(solver.instance_of("Names"), named("Names"));
+        let pair = solver.instance_of("Pair");
+        let (first, second, element) = (TypeVar(0), TypeVar(1), solver.fresh_var());
+        assert_eq!(pair, generic("Pair", vec![Type::Var(first), Type::Var(second)]));
+        let base = generic("Base", vec![Type::Var(element)]);
+        solver.add_constraint(Constraint::Subtype(pair, base));
+        solver.add_constraint(Constraint::Subtype(Type::Bool, Type::Var(second)));
+        solver.add_constraint(Constraint::Subtype(Type::Int, Type::Var(first)));
+        assert!(solver.is_subtype(
+            &generic("Pair", vec![Type::Int, Type::Str]),
+            &generic("Base", vec![Type::Int])
+        ));
+        assert!(!solver.is_subtype(
+            &generic("Pair", vec![Type::Int, Type::Str]),
+            &generic("Base", vec![Type::Str])
+        ));
+        assert!(solver.is_subtype(&named("Names"), &generic("Base", vec![Type::Str])));
+        assert!(solver.is_subtype(&generic("Base", vec![Type::Int]), &named("Base")));
+        let solution = solver.solve().unwrap();
+        assert_eq!(solution[&element], Type::Int);
+        assert_eq!(solution[&second], Type::Bool);
+    }
+
+    #[test]
+    fn test_variance() {
+        let generic =
+            |name: &str, params: Vec<Type>| Type::Generic { name: name.to_string(), params };
+        let list = |item: Type| Type::List(Box::new(item));
+        let callable = |param: Type, returns: Type| Type::callable(vec![param], returns);
+        let mut solver = ConstraintSolver::new();
+
+        assert!(!solver.is_subtype(&list(Type::Bool), &list(Type::Int)));
+        assert!(solver.is_subtype(
+            &generic("typing.Sequence", vec![Type::Bool]),
+            &generic("typing.Sequence", vec![Type::Int])
+        ));
+        assert!(!solver.is_subtype(
+            &generic("Sequence", vec![Type::Int]),
+            &generic("Sequence", vec![Type::Bool])
+        ));
+        assert!(
+            solver.is_subtype(&Type::variadic_tuple(Type::Int), &Type::variadic_tuple(Type::Float))
+        );
+        assert!(solver.is_subtype(
+            &generic("Mapping", vec![Type::Str, Type::Int]),
+            &generic("Mapping", vec![Type::Str, Type::Float])
+        ));
+        assert!(!solver.is_subtype(
+            &generic("Mapping", vec![Type::Bool, Type::Int]),
+            &generic("Mapping", vec![Type::Int, Type::Int])
+        ));
+        assert!(solver.is_subtype(
+            &generic("Generator", vec![Type::Int, Type::Float, Type::None]),
+            &generic("Generator", vec![Type::Float, Type::Int, Type::None])
+        ));
+        assert!(
+            solver.is_subtype(&callable(Type::Float, Type::Bool), &callable(Type::Int, Type::Int))
+        );
+        assert!(
+            !solver.is_subtype(&callable(Type::Int, Type::Int), &callable(Type::Float, Type::Int))
+        );
+
+        assert!(!solver
+            .is_subtype(&generic("Box", vec![Type::Int]), &generic("Box", vec![Type::Float])));
+        solver.set_variance("Box", vec![Variance::Covariant]);
+        solver.set_variance("Sink", vec![Variance::Contravariant]);
+        assert!(
+            solver.is_subtype(&generic("Box", vec![Type::Int]), &generic("Box", vec![Type::Float]))
+        );
+        assert!(solver
+            .is_subtype(&generic("Sink", vec![Type::Float]), &generic("Sink", vec![Type::Int])));
+        assert!(!solver
+            .is_subtype(&generic("Sink", vec![Type::Int]), &generic("Sink", vec![Type::Float])));
+    }
+
+    #[test]
+    fn test_iterator_and_async_forms() {
+        let generic =
+            |name: &str, params: Vec<Type>| Type::Generic { name: name.to_string(), params };
+        let solver = ConstraintSolver::new();
+        let ticks = Type::generator(Type::Bool, Type::None, Type::None);
+        assert!(solver.is_subtype(&ticks, &Type::iterator(Type::Int)));
+        assert!(solver.is_subtype(&ticks, &generic("Iterable", vec![Type::Int])));
+        assert!(!solver.is_subtype(&ticks, &generic("Iterable", vec![Type::Str])));
+        assert!(!solver.is_subtype(&Type::iterator(Type::Int), &ticks));
+        assert!(solver.is_subtype(&Type::coroutine(Type::Int), &Type::awaitable(Type::Float)));
+        assert!(!solver.is_subtype(&Type::coroutine(Type::Str), &Type::awaitable(Type::Int)));
+        let stream = generic("AsyncGenerator", vec![Type::Str, Type::None]);
+        assert!(solver.is_subtype(&stream, &Type::async_iterator(Type::Str)));
+        assert!(solver.is_subtype(&stream, &generic("AsyncIterable", vec![Type::Str])));
+        assert!(!solver.is_subtype(&stream, &Type::iterator(Type::Str)));
+    }
+
+    #[test]
+    fn test_constraint_origins() {
+        let mut solver = ConstraintSolver::new();
+        let span = SourceFile::from_text("foo.py", "f(1, 'a')\n").span(5, 8);
+        let origin = Origin::new("argument 2 of call expected int because of parameter annotation")
+            .with_path("foo.py")
+            .with_span(span);
+        solver.add_constraint_from(Constraint::Subtype(Type::Str, Type::Int), origin);
+        solver.add_constraint(Constraint::Subtype(Type::Int, Type::Float));
+        let error = solver.solve().unwrap_err();
+        assert_eq!(error.path(), Some(Path::new("foo.py")));
+        assert_eq!(
+            error.to_string(),
+            "Type error: foo.py:1:6: argument 2 of call expected int because of parameter \
+             annotation: str is not a subtype of int (expected int, found str)"
+        );
+    }
+
+    #[test]
+    fn test_solver_limits() {
+        let limits = SolverLimits { max_constraints: 3, max_steps: 4, timeout: None };
+        let mut solver = ConstraintSolver::new().with_limits(limits);
+        let vars: Vec<Type> = (0..4).map(|_| Type::Var(solver.fresh_var())).collect();
+        // Pending constraints are solved last to first, so the tuple is last.
+        solver.add_constraint_from(
+            Constraint::Equal(
+                Type::Tuple(vec![vars[2].clone(), vars[3].clone()]),
+                Type::Tuple(vec![Type::Bytes, Type::Bool]),
+            ),
+            Origin::new("tuple").with_path("foo.py"),
+        );
+        solver.add_constraint(Constraint::Equal(
+            Type::List(Box::new(vars[1].clone())),
+            Type::List(Box::new(Type::Str)),
+        ));
+        solver.add_constraint(Constraint::Equal(vars[0].clone(), Type::Int));
+        assert!(solver.solve_pending().is_ok());
+        let fallback = solver.fallback().unwrap();
+        assert_eq!(fallback.path, "foo.py");
+        assert_eq!(
+            fallback.message,
+            "Inference too complex (more than 4 steps), falling back to Any"
+        );
+        let solution = solver.substitution();
+        assert_eq!(solution[&TypeVar(0)], Type::Int);
+        assert_eq!(solution[&TypeVar(1)], Type::Str);
+        assert_eq!(solution[&TypeVar(2)], Type::Any);
+        assert_eq!(solution[&TypeVar(3)], Type::Any);
+
+        // Too many constraints give up on all of them up front.
+        let var = Type::Var(solver.fresh_var());
+        solver.add_constraint(Constraint::Equal(var, Type::Int));
+        assert!(solver.solve_pending().is_ok());
+        assert_eq!(solver.fallback().unwrap().args["reason"], "more than 3 constraints");
+        assert_eq!(solver.substitution()[&TypeVar(4)], Type::Any);
+    }
+
+    #[test]
+    fn test_incremental_solving() {
+        let mut solver = ConstraintSolver::new();
+        let (a, b, c) = (solver.fresh_var(), solver.fresh_var(), solver.fresh_var());
+        let from = |path: &str| Origin::new("assignment").with_path(path);
+        solver.add_constraint_from(Constraint::Equal(Type::Var(a), Type::Int), from("a.py"));
+        solver.add_constraint_from(Constraint::Equal(Type::Var(b), Type::Str), from("b.py"));
+        solver.add_constraint_from(Constraint::Equal(Type::Var(c), Type::Var(a)), from("c.py"));
+        solver.solve_pending().unwrap();
+        assert_eq!(solver.substitution()[&c], Type::Int);
+
+        let in_a = |_: &Constraint, origin: Option<&Origin>| {
+            origin.is_some_and(|o| o.path.as_deref() == Some(Path::new("a.py")))
+        };
+        assert_eq!(solver.remove_constraints(in_a), 1);
+        assert_eq!(solver.substitution(), HashMap::from([(b, Type::Str)]));
+        solver.add_constraint_from(Constraint::Equal(Type::Var(a), Type::Float), from("a.py"));
+        solver.solve_pending().unwrap();
+        let solution = solver.substitution();
+        assert_eq!(
+            (&solution[&a], &solution[&b], &solution[&c]),
+            (&Type::Float, &Type::Str, &Type::Float)
+        );
+
+        solver.add_constraint(Constraint::Equal(Type::Var(b), Type::Int));
+        assert!(solver.solve_pending().is_err());
+        assert_eq!(solver.substitution(), solution);
+        assert!(solver.solve_pending().is_ok());
+    }
+
+    #[test]
+    fn test_solve_all() {
+        let mut solver = ConstraintSolver::new();
+        let (a, b) = (solver.fresh_var(), solver.fresh_var());
+        solver.add_constraint(Constraint::Equal(Type::Var(b), Type::Str));
+        solver.add_constraint(Constraint::Subtype(Type::Str, Type::Var(a)));
+        solver.add_constraint(Constraint::Subtype(Type::Int, Type::Var(a)));
+        let failed = solver.solve_all();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, Constraint::Subtype(Type::Str, Type::Var(a)));
+        assert_eq!(solver.substitution()[&a], Type::Int);
+        assert_eq!(solver.substitution()[&b], Type::Str);
+    }
+
+    #[test]
+    fn test_all_conflicts_reported() {
+        let mut solver = ConstraintSolver::new();
+        let p = Type::Var(solver.fresh_var());
+        // Pending constraints are solved last to first.
+        solver.add_constraint_from(
+            Constraint::Equal(Type::List(Box::new(Type::Str)), Type::List(Box::new(Type::Int))),
+            Origin::new("second"),
+        );
+        solver.add_constraint(Constraint::Equal(p.clone(), Type::Bytes));
+        solver.add_constraint_from(
+            Constraint::Subtype(
+                Type::Tuple(vec![p, Type::Str]),
+                Type::Tuple(vec![Type::Int, Type::Int]),
+            ),
+            Origin::new("first"),
+        );
+        let solution = solver.solve_partial();
+        // The first conflict leaves `p` as `Unknown`, which fits `bytes`.
+        assert_eq!(solution.value[&TypeVar(0)], Type::Unknown);
+        let messages: Vec<String> = solution.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("first: "), "{}", messages[0]);
+        assert!(messages[1].contains("second: "), "{}", messages[1]);
+        assert!(
+            matches!(solution.into_result(), Err(Error::Multiple(errors)) if errors.len() == 2)
+        );
+    }
+
+    #[test]
+    fn test_simplified_solution() {
+        let mut solver = ConstraintSolver::new();
+        let (a, b, c, d, e) = (
+            solver.fresh_var(),
+            solver.fresh_var(),
+            solver.fresh_var(),
+            solver.fresh_var(),
+            solver.fresh_var(),
+        );
+        solver.add_constraint(Constraint::Equal(Type::Var(a), Type::Var(b)));
+        solver.add_constraint(Constraint::Equal(Type::Var(b), Type::Int));
+        solver.add_constraint(Constraint::Equal(
+            Type::Var(c),
+            Type::Union(vec![Type::Var(a), Type::Union(vec![Type::Int, Type::Var(b)])]),
+        ));
+        solver.add_constraint(Constraint::Equal(Type::Var(d), Type::Any));
+        solver.add_constraint(Constraint::Equal(
+            Type::Var(e),
+            Type::List(Box::new(Type::Union(vec![Type::Str, Type::Var(d)]))),
+        ));
+        let solution = solver.solve().unwrap();
+        assert_eq!((&solution[&a], &solution[&c]), (&Type::Int, &Type::Int));
+        assert_eq!(solution[&e], Type::List(Box::new(Type::Any)));
+    }
+
+    #[test]
+    fn test_unsolved_variables() {
+        let mut solver = ConstraintSolver::new();
+        let (a, b, c) = (solver.fresh_var(), solver.fresh_var(), solver.fresh_var());
+        solver.add_constraint(Constraint::Equal(Type::Var(a), Type::List(Box::new(Type::Var(b)))));
+        solver.add_constraint(Constraint::Equal(Type::Var(c), Type::Var(b)));
+        solver.solve_pending().unwrap();
+        assert_eq!(solver.unsolved_vars(), [b, c]);
+        assert!(!solver.substitution().contains_key(&b));
+        let solver = solver.with_unsolved(Unsolved::Any);
+        let solution = solver.substitution();
+        assert_eq!(solution[&a], Type::List(Box::new(Type::Any)));
+        assert_eq!((&solution[&b], &solution[&c]), (&Type::Any, &Type::Any));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut solver = ConstraintSolver::new();
+        let t = solver.fresh_var();
+        solver.add_constraint_from(
+            Constraint::Subtype(Type::Bool, Type::Var(t)),
+            Origin::new("argument \"x\""),
+        );
+        solver.solve_pending().unwrap();
+        solver.add_constraint(Constraint::Equal(Type::List(Box::new(Type::Var(t))), Type::Any));
+        assert_eq!(
+            solver.to_dot(),
+            "digraph constraints {\n    rankdir=LR;\n    \"bool\" [shape=box];\n    \
+             \"T0\" [shape=ellipse];\n    \"List[T0]\" [shape=box];\n    \"Any\" [shape=box];\n    \
+             \"bool\" -> \"T0\" [label=\"<: (argument \\\"x\\\")\", style=solid];\n    \
+             \"List[T0]\" -> \"Any\" [label=\"=\", style=dashed];\n    \
+             \"T0\" -> \"bool\" [label=\":=\", style=bold, color=blue];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_callable_signatures() {
+        let param = |name: &str, kind, ty, default: Option<&str>| Param {
+            name: Some(name.to_string()),
+            kind,
+            ty,
+            default: default.map(str::to_string),
+        };
+        // def f(x: float, *, flag: bool = False, **opts: str) -> int
+        let f = Type::Function {
+            params: vec![
+                param("x", ParameterKind::Positional, Type::Float, None),
+                param("flag", ParameterKind::KeywordOnly, Type::Bool, Some("False")),
+                param("opts", ParameterKind::VarKeyword, Type::Str, None),
+            ],
+            returns: Box::new(Type::Int),
+        };
+        assert_eq!(f.to_string(), "(x: float, *, flag: bool = False, **opts: str) -> int");
+        let solver = ConstraintSolver::new();
+        assert!(solver.is_subtype(&f, &Type::callable(vec![Type::Int], Type::Float)));
+        assert!(!solver.is_subtype(&f, &Type::callable(vec![Type::Str], Type::Int)));
+        assert!(!solver.is_subtype(&f, &Type::callable(vec![], Type::Int)));
+        let by_keyword = |name: &str, ty| Type::Function {
+            params: vec![param(name, ParameterKind::KeywordOnly, ty, None)],
+            returns: Box::new(Type::Int),
+        };
+        assert!(solver.is_subtype(&f, &by_keyword("x", Type::Int)));
+        // `x` would be left without an argument.
+        assert!(!solver.is_subtype(&f, &by_keyword("y", Type::Str)));
+        let with_x = Type::Function {
+            params: vec![
+                param("x", ParameterKind::Positional, Type::Int, None),
+                param("flag", ParameterKind::KeywordOnly, Type::Bool, None),
+                param("color", ParameterKind::KeywordOnly, Type::Str, None),
+            ],
+            returns: Box::new(Type::Int),
+        };
+        assert!(solver.is_subtype(&f, &with_x));
+        let Type::Function { mut params, returns } = with_x else {
+            unreachable!();
+        };
+        params[2].ty = Type::Int;
+        assert!(!solver.is_subtype(&f, &Type::Function { params, returns }));
+        // A positional-only callable can't stand in for a named parameter.
+        let unnamed = Type::callable(vec![Type::Float], Type::Int);
+        let named = Type::Function {
+            params: vec![param("x", ParameterKind::Positional, Type::Float, None)],
+            returns: Box::new(Type::Int),
+        };
+        assert!(solver.is_subtype(&named, &unnamed));
+        assert!(!solver.is_subtype(&unnamed, &named));
+    }
+
+    #[test]
+    fn test_protocol_conformance() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let close = Type::callable(vec![], Type::None);
+        let closeable = Type::Protocol {
+            name: "Closeable".to_string(),
+            members: BTreeMap::from([("close".to_string(), close.clone())]),
+        };
+        let mut solver = ConstraintSolver::new();
+        solver.add_class("File", vec!["object".to_string()]);
+        solver.add_attribute("File", "close", close.clone());
+        solver.add_class("Text", vec!["object".to_string()]);
+        solver.add_attribute("Text", "close", Type::Int);
+        solver.add_class("Number", vec!["object".to_string()]);
+        assert!(solver.is_subtype(&named("File"), &closeable));
+        assert!(!solver.is_subtype(&named("Text"), &closeable));
+        assert!(!solver.is_subtype(&named("Number"), &closeable));
+        assert!(solver.is_subtype(&named("Unregistered"), &closeable));
+
+        let wider = Type::Protocol {
+            name: String::new(),
+            members: BTreeMap::from([
+                ("close".to_string(), close),
+                ("name".to_string(), Type::Str),
+            ]),
+        };
+        assert!(solver.is_subtype(&wider, &closeable));
+        assert!(!solver.is_subtype(&closeable, &wider));
+        assert_eq!(wider.to_string(), "Protocol[close: Callable[[], None], name: str]");
+
+        let t = solver.fresh_var();
+        solver.add_constraint(Constraint::Subtype(named("Number"), closeable.clone()));
+        solver.add_constraint(Constraint::Subtype(Type::Var(t), closeable));
+        let error = solver.solve().unwrap_err();
+        assert_eq!(error.to_string(), "Type error: Number has no attribute 'close' (expected Callable[[], None], found Number)"
+        );
+    }
+
+    #[test]
+    fn test_intersection_subtyping() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let protocol = |name: &str, member: &str| Type::Protocol {
+            name: name.to_string(),
+            members: BTreeMap::from([(member.to_string(), Type::callable(vec![], Type::None))]),
+        };
+        let bound = Type::intersection_of(vec![
+            protocol("Closeable", "close"),
+            protocol("Flushable", "flush"),
+        ]);
+        assert_eq!(bound.to_string(), "Closeable & Flushable");
+        let mut solver = ConstraintSolver::new();
+        solver.add_class("File", vec!["object".to_string()]);
+        solver.add_attribute("File", "close", Type::callable(vec![], Type::None));
+        solver.add_attribute("File", "flush", Type::callable(vec![], Type::None));
+        solver.add_class("Socket", vec!["object".to_string()]);
+        solver.add_attribute("Socket", "close", Type::callable(vec![], Type::None));
+        solver.add_class("Buffered", vec!["File".to_string()]);
+        assert!(solver.is_subtype(&named("File"), &bound));
+        assert!(!solver.is_subtype(&named("Socket"), &bound));
+
+        let both = Type::intersection_of(vec![named("Socket"), named("Buffered")]);
+        assert!(solver.is_subtype(&both, &named("File")));
+        assert!(solver.is_subtype(&both, &bound));
+        assert!(!solver.is_subtype(&named("Socket"), &both));
+
+        let t = solver.fresh_var();
+        solver.add_constraint(Constraint::HasAttribute(both, "flush".to_string(), Type::Var(t)));
+        let solution = solver.solve().unwrap();
+        assert_eq!(solution[&t].to_string(), "Callable[[], None]");
+    }
+
+    #[test]
+    fn test_attribute_constraints() {
+        let function = Type::callable;
+        let named = |name: &str| Type::Named(name.to_string());
+        let mut solver = ConstraintSolver::new();
+        solver.add_class("Base", vec!["object".to_string()]);
+        solver.add_class("File", vec!["Base".to_string()]);
+        solver.add_attribute("File", "read", function(vec![Type::Int], Type::Str));
+        solver.add_attribute("Base", "name", Type::Str);
+
+        let (reader, size, data) = (solver.fresh_var(), solver.fresh_var(), solver.fresh_var());
+        let read = function(vec![Type::Var(size)], Type::Var(data));
+        solver.add_constraint(Constraint::HasAttribute(
+            Type::Var(reader),
+            "read".to_string(),
+            read,
+        ));
+        solver.add_constraint(Constraint::HasAttribute(
+            Type::Var(reader),
+            "name".to_string(),
+            Type::Str,
+        ));
+        solver.add_constraint(Constraint::Subtype(Type::Var(data), Type::Str));
+        solver.solve_pending().unwrap();
+        assert_eq!(
+            solver.protocol(reader, "Readable").unwrap(),
+            "class Readable(Protocol):\n    name: str\n    def read(self, arg0: Any, /) -> str: ...\n"
+        );
+        assert_eq!(solver.protocol(size, "Size"), None);
+
+        solver.add_constraint(Constraint::Equal(Type::Var(reader), named("File")));
+        solver.solve_pending().unwrap();
+        assert_eq!(solver.substitution()[&size], Type::Int);
+        assert_eq!(solver.protocol(reader, "Readable"), None);
+
+        solver.add_constraint(Constraint::HasAttribute(
+            named("File"),
+            "write".to_string(),
+            Type::Any,
+        ));
+        assert!(solver.solve_pending().is_err());
+        solver.add_constraint(Constraint::HasAttribute(
+            named("Socket"),
+            "write".to_string(),
+            Type::Any,
+        ));
+        assert!(solver.solve_pending().is_ok());
+    }
+
+    #[test]
+    fn test_recursive_types() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let json = |name: &str| {
+            Type::union_of(vec![
+                Type::None,
+                Type::Bool,
+                Type::Int,
+                Type::Float,
+                Type::Str,
+                Type::List(Box::new(named(name))),
+                Type::Dict(Box::new(Type::Str), Box::new(named(name))),
+            ])
+        };
+        let mut solver = ConstraintSolver::new();
+        solver.add_alias("JSON", json("JSON"));
+        solver.add_alias("Json", json("Json"));
+        assert!(solver.is_subtype(&Type::Int, &named("JSON")));
+        assert!(solver.is_subtype(&Type::List(Box::new(named("JSON"))), &named("JSON")));
+        assert!(!solver.is_subtype(&Type::Bytes, &named("JSON")));
+        assert!(!solver.is_subtype(&Type::List(Box::new(Type::Bytes)), &named("JSON")));
+        assert!(solver.is_subtype(&named("JSON"), &named("Json")));
+        assert!(solver.is_subtype(
+            &Type::List(Box::new(named("JSON"))),
+            &Type::List(Box::new(named("Json")))
+        ));
+
+        let node = Type::union_of(vec![named("Node"), Type::None]);
+        solver.add_class("Node", Vec::new());
+        solver.add_attribute("Node", "next", node.clone());
+        let (value, next) = (solver.fresh_var(), solver.fresh_var());
+        solver.add_constraint(Constraint::Subtype(Type::Var(value), named("JSON")));
+        solver.add_constraint(Constraint::HasAttribute(
+            named("Node"),
+            "next".to_string(),
+            Type::Var(next),
+        ));
+        let solution = solver.solve().unwrap();
+        assert_eq!((&solution[&value], &solution[&next]), (&named("JSON"), &node));
+    }
 }
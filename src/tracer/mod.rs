@@ -1,17 +1,18 @@
 //! Runtime type tracing for dynamic type information collection.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::process::{Command, Stdio};
-use tempfile::NamedTempFile;
 use wait_timeout::ChildExt;
 
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
 
+use crate::analyzer::ClassInfo;
 use crate::error::{Error, Result};
-use crate::types::Type;
+use crate::types::{Type, TypedDictField, MAX_UNION_MEMBERS};
+use crate::utils::{select_interpreter, ArtifactWorkspace};
 
 /// Represents a runtime type trace.
 #[derive(Debug, Default)]
@@ -25,6 +26,23 @@ pub struct TypeTrace {
 }
 
 impl TypeTrace {
+    /// Parses a trace in the JSON format the instrumented script prints.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let data: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| Error::parser_error(format!("Invalid trace JSON: {}", e)))?;
+        let mut tracer = RuntimeTracer::new(false);
+        tracer.process_trace_data(&data)?;
+        Ok(tracer.into_traces())
+    }
+
+    /// Loads a trace saved to disk, such as the `trace.json` kept by
+    /// `omnitype trace --keep-artifacts`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| e.with_path(path))
+    }
+
     /// Add a variable observation to the trace
     pub fn add_variable(&mut self, name: String, type_info: Type) {
         self.variables.entry(name).or_default().push(type_info);
@@ -53,6 +71,65 @@ impl TypeTrace {
             Vec::new()
         }
     }
+
+    /// A `Protocol` named `name` for argument `index` of `function`, with
+    /// the attributes shared by every class the argument was observed as,
+    /// looked up in `classes` (such as [`crate::analyzer::Analyzer::classes`]).
+    /// `None` unless the argument was an instance of at least two classes,
+    /// all of them in `classes`, that have attributes in common.
+    pub fn argument_protocol(
+        &self,
+        function: &str,
+        index: usize,
+        name: &str,
+        classes: &BTreeMap<String, ClassInfo>,
+    ) -> Option<Type> {
+        let (calls, _) = self.functions.get(function)?;
+        let observed: BTreeSet<&Type> = calls.iter().filter_map(|args| args.get(index)).collect();
+        if observed.len() < 2 {
+            return None;
+        }
+        let mut shared: Option<BTreeMap<String, Vec<Type>>> = None;
+        for ty in observed {
+            let Type::Named(class) = ty else {
+                return None;
+            };
+            let attributes = &classes.get(class)?.attributes;
+            shared = Some(match shared {
+                None => attributes
+                    .iter()
+                    .map(|(a, ty)| (a.clone(), vec![ty.clone()]))
+                    .collect(),
+                Some(mut shared) => {
+                    shared.retain(|attribute, _| attributes.contains_key(attribute));
+                    for (attribute, types) in &mut shared {
+                        types.push(attributes[attribute].clone());
+                    }
+                    shared
+                },
+            });
+        }
+        let members: BTreeMap<String, Type> = shared?
+            .into_iter()
+            .map(|(attribute, types)| (attribute, Type::union_of(types)))
+            .collect();
+        (!members.is_empty()).then(|| Type::Protocol { name: name.to_string(), members })
+    }
+
+    /// The union of the types observed for a variable, widened with
+    /// [`Type::capped`] once it has more than `max_union_members` members.
+    /// Dicts observed with different keys are merged into one
+    /// `TypedDict`, whose keys missing from some of them aren't required.
+    /// `Unknown` if the variable was never observed.
+    pub fn variable_type(&self, name: &str, max_union_members: usize) -> Type {
+        let (shapes, mut types): (Vec<Type>, Vec<Type>) = self
+            .get_variable_types(name)
+            .into_iter()
+            .cloned()
+            .partition(|ty| matches!(ty, Type::TypedDict { name, .. } if name.is_empty()));
+        types.extend(merge_shapes(shapes));
+        Type::union_of(types).capped(max_union_members)
+    }
 }
 
 /// The main runtime tracer that collects type information.
@@ -62,23 +139,42 @@ pub struct RuntimeTracer {
 
     /// Whether to enable detailed logging
     verbose: bool,
+
+    /// Whether to keep the run's artifact workspace for debugging
+    keep_artifacts: bool,
+
+    /// Whether to also run the file's doctest examples
+    doctests: bool,
 }
 
 impl RuntimeTracer {
     /// Creates a new runtime tracer.
     pub fn new(verbose: bool) -> Self {
-        Self { traces: TypeTrace::default(), verbose }
+        Self { traces: TypeTrace::default(), verbose, keep_artifacts: false, doctests: false }
+    }
+
+    /// Keeps the instrumented script, trace JSON, and scratch files of each
+    /// run instead of deleting them.
+    pub fn keep_artifacts(mut self, keep: bool) -> Self {
+        self.keep_artifacts = keep;
+        self
+    }
+
+    /// Also runs the doctest examples in the file's docstrings, so calls
+    /// they make are traced alongside the `test_*` functions. Has no effect
+    /// when a single test is selected.
+    pub fn doctests(mut self, doctests: bool) -> Self {
+        self.doctests = doctests;
+        self
     }
 
     /// Runs the tracer on the specified test file or module.
     pub fn run<P: AsRef<Path>>(&mut self, path: P, test_name: Option<&str>) -> Result<()> {
         let path = path.as_ref();
 
-        if self.verbose {
-            println!("Running runtime tracer on: {:?}", path);
-            if let Some(name) = test_name {
-                println!("Specific test: {}", name);
-            }
+        log::info!("Running runtime tracer on {}", path.display());
+        if let Some(name) = test_name {
+            log::info!("Tracing test {}", name);
         }
 
         // Check if the file exists and is a Python file
@@ -103,22 +199,20 @@ impl RuntimeTracer {
             self.instrument_python_file(path)?
         };
 
-        // Create a temporary file with proper cleanup handling
-        let temp_file = NamedTempFile::with_suffix(".py").map_err(|e| {
-            Error::Io(std::io::Error::other(format!("Failed to create temp file: {}", e)))
-        })?;
-
-        // Write the instrumented content
-        fs::write(temp_file.path(), instrumented_content)?;
+        // Generated files live in a per-run workspace that is removed on drop
+        let workspace = ArtifactWorkspace::create("trace")?.keep_artifacts(self.keep_artifacts);
+        let script = workspace.write("instrumented.py", instrumented_content)?;
+        let scratch = workspace.scratch_dir("tmp")?;
 
         // Allow override of the Python interpreter and prevent hangs with a timeout
-        let python = std::env::var("OMNITYPE_PYTHON")
-            .or_else(|_| std::env::var("PYTHON"))
-            .unwrap_or_else(|_| "python3".to_string());
+        let python = select_interpreter(path);
 
         // Execute the instrumented Python file
         let mut child = Command::new(python)
-            .arg(temp_file.path())
+            .arg(&script)
+            .env("TMPDIR", &scratch)
+            .env("TEMP", &scratch)
+            .env("TMP", &scratch)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
@@ -149,11 +243,10 @@ impl RuntimeTracer {
             Err(Error::Io(std::io::Error::other("Python execution timed out")))
         };
 
-        // temp_file is automatically cleaned up when it goes out of scope
-
         match output {
             Ok(output) => {
                 if !output.status.success() {
+                    workspace.write("stderr.log", &output.stderr)?;
                     let stderr = String::from_utf8_lossy(&output.stderr);
                     if self.verbose {
                         eprintln!("Python execution failed: {}", stderr);
@@ -165,10 +258,12 @@ impl RuntimeTracer {
                 }
 
                 let stdout = String::from_utf8_lossy(&output.stdout);
-                self.parse_trace_output(&stdout)?;
+                if let Some(trace_json) = self.parse_trace_output(&stdout)? {
+                    workspace.write("trace.json", trace_json)?;
+                }
 
+                log::info!("Trace collection completed");
                 if self.verbose {
-                    println!("Trace collection completed successfully");
                     self.print_trace_summary();
                 }
             },
@@ -221,6 +316,11 @@ class TypeTracer:
                 return f"List[{inner_type}]"
             return "List[Any]"
         elif isinstance(value, dict):
+            if 0 < len(value) <= 32 and all(isinstance(k, str) and k.isidentifier() for k in value):
+                value_types = {k: self.get_type_name(v) for k, v in value.items()}
+                if len(set(value_types.values())) > 1:
+                    fields = ", ".join(f"{k}: {t}" for k, t in sorted(value_types.items()))
+                    return f"TypedDict[{fields}]"
             if value:
                 key_type = self.get_type_name(next(iter(value.keys())))
                 val_type = self.get_type_name(next(iter(value.values())))
@@ -236,6 +336,8 @@ class TypeTracer:
                 inner_type = self.get_type_name(next(iter(value)))
                 return f"Set[{inner_type}]"
             return "Set[Any]"
+        elif isinstance(value, type):
+            return f"Type[{value.__name__}]"
         else:
             return type(value).__name__
     
@@ -307,7 +409,7 @@ for name in dir(current_module):
     if callable(obj) and name.startswith('test_') and not name.startswith('_'):
         try:
             sig = inspect.signature(obj)
-            let has_required = any(
+            has_required = any(
                 p.default is inspect._empty
                 and p.kind in (inspect.Parameter.POSITIONAL_ONLY, inspect.Parameter.POSITIONAL_OR_KEYWORD)
                 for p in sig.parameters.values()
@@ -321,6 +423,20 @@ for name in dir(current_module):
             print(f"Error in test {name}: {e}")
 # Note: Other functions will be traced when called by test functions
 # This avoids the security risk of calling arbitrary functions with guessed arguments
+"#,
+        );
+        if self.doctests {
+            full_code.push_str(
+                r#"
+# Run doctest examples; calls into this file are traced like test calls
+import doctest
+_doctest_results = doctest.testmod(current_module)
+print(f"Doctests: {_doctest_results.attempted} run, {_doctest_results.failed} failed")
+"#,
+            );
+        }
+        full_code.push_str(
+            r#"
 
 # Disable tracing
 sys.settrace(None)
@@ -377,6 +493,11 @@ class TypeTracer:
                 return f"List[{{inner_type}}]"
             return "List[Any]"
         elif isinstance(value, dict):
+            if 0 < len(value) <= 32 and all(isinstance(k, str) and k.isidentifier() for k in value):
+                value_types = {{k: self.get_type_name(v) for k, v in value.items()}}
+                if len(set(value_types.values())) > 1:
+                    fields = ", ".join(f"{{k}}: {{t}}" for k, t in sorted(value_types.items()))
+                    return f"TypedDict[{{fields}}]"
             if value:
                 key_type = self.get_type_name(next(iter(value.keys())))
                 val_type = self.get_type_name(next(iter(value.values())))
@@ -392,6 +513,8 @@ class TypeTracer:
                 inner_type = self.get_type_name(next(iter(value)))
                 return f"Set[{{inner_type}}]"
             return "Set[Any]"
+        elif isinstance(value, type):
+            return f"Type[{{value.__name__}}]"
         else:
             return type(value).__name__
     
@@ -467,17 +590,19 @@ _tracer.print_traces()
         Ok(tracer_code)
     }
 
-    /// Parse the trace output from the executed Python code
-    fn parse_trace_output(&mut self, output: &str) -> Result<()> {
+    /// Parse the trace output from the executed Python code, returning the
+    /// raw trace JSON if it was found
+    fn parse_trace_output<'a>(&mut self, output: &'a str) -> Result<Option<&'a str>> {
         // Look for trace output between markers
         if let Some(start) = output.find("TRACE_OUTPUT_START") {
             if let Some(end) = output[start..].find("TRACE_OUTPUT_END") {
                 let end = start + end;
-                let trace_json = &output[start + "TRACE_OUTPUT_START".len()..end].trim();
+                let trace_json = output[start + "TRACE_OUTPUT_START".len()..end].trim();
 
                 match serde_json::from_str::<serde_json::Value>(trace_json) {
                     Ok(trace_data) => {
                         self.process_trace_data(&trace_data)?;
+                        return Ok(Some(trace_json));
                     },
                     Err(e) => {
                         log::error!("Failed to parse trace JSON from Python script: {}", e);
@@ -501,7 +626,7 @@ _tracer.print_traces()
             }
         }
 
-        Ok(())
+        Ok(None)
     }
 
     /// Process the parsed trace data and convert to our Type system
@@ -562,6 +687,15 @@ _tracer.print_traces()
 
     /// Convert Python type string to our Type enum
     fn convert_python_type_to_our_type(type_str: &str) -> Type {
+        let members = split_outside_brackets(type_str, '|');
+        if members.len() > 1 {
+            return Type::union_of(
+                members
+                    .into_iter()
+                    .map(Self::convert_python_type_to_our_type)
+                    .collect(),
+            );
+        }
         match type_str {
             "None" => Type::None,
             "bool" => Type::Bool,
@@ -602,6 +736,38 @@ _tracer.print_traces()
                 let inner = &s[4..s.len() - 1];
                 Type::Set(Box::new(Self::convert_python_type_to_our_type(inner)))
             },
+            s if s.starts_with("TypedDict[") => {
+                let inner = &s[10..s.len() - 1];
+                let fields = split_outside_brackets(inner, ',')
+                    .into_iter()
+                    .filter_map(|field| field.split_once(':'))
+                    .map(|(key, ty)| {
+                        let ty = ty.trim();
+                        let field = match ty.strip_prefix("NotRequired[") {
+                            Some(inner) => TypedDictField {
+                                ty: Self::convert_python_type_to_our_type(
+                                    &inner[..inner.len() - 1],
+                                ),
+                                required: false,
+                            },
+                            None => TypedDictField {
+                                ty: Self::convert_python_type_to_our_type(ty),
+                                required: true,
+                            },
+                        };
+                        (key.trim().to_string(), field)
+                    })
+                    .collect();
+                Type::TypedDict { name: String::new(), fields, total: true }
+            },
+            s if s.starts_with("Optional[") => {
+                let inner = &s[9..s.len() - 1];
+                Type::optional(Self::convert_python_type_to_our_type(inner))
+            },
+            s if s.starts_with("Type[") => {
+                let inner = &s[5..s.len() - 1];
+                Type::class_object(Self::convert_python_type_to_our_type(inner))
+            },
             "Any" => Type::Any,
             other => Type::Named(other.to_string()),
         }
@@ -613,15 +779,8 @@ _tracer.print_traces()
 
         if !self.traces.variables.is_empty() {
             println!("\nVariable Types:");
-            for (name, types) in &self.traces.variables {
-                let mut unique_types: Vec<String> = types
-                    .iter()
-                    .map(|t| t.to_string())
-                    .collect::<std::collections::HashSet<_>>()
-                    .into_iter()
-                    .collect();
-                unique_types.sort();
-                println!("  {}: {}", name, unique_types.join(" | "));
+            for name in self.traces.variables.keys() {
+                println!("  {}: {}", name, self.traces.variable_type(name, MAX_UNION_MEMBERS));
             }
         }
 
@@ -650,6 +809,55 @@ _tracer.print_traces()
     }
 }
 
+/// Merges the anonymous `TypedDict`s observed for one value into a single
+/// one: each key has the union of the types seen for it, and is required
+/// only if every observation had it.
+fn merge_shapes(shapes: Vec<Type>) -> Option<Type> {
+    let count = shapes.len();
+    let mut seen: BTreeMap<String, (Vec<Type>, usize)> = BTreeMap::new();
+    for shape in shapes {
+        let Type::TypedDict { fields, .. } = shape else {
+            continue;
+        };
+        for (key, field) in fields {
+            let (types, times) = seen.entry(key).or_default();
+            types.push(field.ty);
+            *times += usize::from(field.required);
+        }
+    }
+    if seen.is_empty() {
+        return None;
+    }
+    let fields = seen
+        .into_iter()
+        .map(|(key, (types, times))| {
+            (key, TypedDictField { ty: Type::union_of(types), required: times == count })
+        })
+        .collect();
+    Some(Type::TypedDict { name: String::new(), fields, total: true })
+}
+
+/// The parts of `type_str` between the `separator`s outside brackets, such
+/// as the members of a union written as `A | B | ...`, so that
+/// `List[int | str]` stays whole.
+fn split_outside_brackets(type_str: &str, separator: char) -> Vec<&str> {
+    let mut members = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in type_str.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                members.push(type_str[start..i].trim());
+                start = i + 1;
+            },
+            _ => (),
+        }
+    }
+    members.push(type_str[start..].trim());
+    members
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -686,6 +894,16 @@ mod tests {
         assert_eq!(returns[0], Type::Bool);
     }
 
+    #[test]
+    fn test_trace_from_json() {
+        let json = r#"{"variables": {"x": ["int", "str"]},
+                       "functions": {"add": {"args": [["int", "int"]], "returns": ["int"]}}}"#;
+        let trace = TypeTrace::from_json(json).unwrap();
+        assert_eq!(trace.get_variable_types("x"), vec![&Type::Int, &Type::Str]);
+        assert_eq!(trace.functions["add"], (vec![vec![Type::Int, Type::Int]], vec![Type::Int]));
+        assert!(TypeTrace::from_json("{").is_err());
+    }
+
     #[test]
     fn test_get_variable_types_deduplication() {
         let mut trace = TypeTrace::default();
@@ -715,6 +933,67 @@ mod tests {
         assert!(z_types.contains(&&Type::List(Box::new(Type::Str))));
     }
 
+    #[test]
+    fn test_variable_type_caps_unions() {
+        let mut trace = TypeTrace::default();
+        for ty in [Type::Int, Type::Str, Type::None] {
+            trace.add_variable("x".to_string(), ty);
+        }
+        let x = Type::union_of(vec![Type::Int, Type::Str, Type::None]);
+        assert_eq!(trace.variable_type("x", 5), x);
+        assert_eq!(trace.variable_type("x", 2), Type::Any);
+        assert_eq!(trace.variable_type("missing", 5), Type::Unknown);
+    }
+
+    #[test]
+    fn test_traced_dict_shapes() {
+        let shape = |text: &str| RuntimeTracer::convert_python_type_to_our_type(text);
+        let mut trace = TypeTrace::default();
+        trace.add_variable("movie".to_string(), shape("TypedDict[title: str, year: int]"));
+        trace
+            .add_variable("movie".to_string(), shape("TypedDict[title: str, year: Optional[int]]"));
+        trace.add_variable("movie".to_string(), shape("TypedDict[rating: float, title: str]"));
+        trace.add_variable("movie".to_string(), Type::None);
+        let movie = trace.variable_type("movie", 5);
+        assert!(movie.is_optional());
+        let shape_type = movie.unwrap_optional();
+        assert_eq!(
+            shape_type.to_string(),
+            "TypedDict[rating: NotRequired[float], title: str, year: NotRequired[Optional[int]]]"
+        );
+        assert_eq!(shape(&shape_type.to_string()), shape_type);
+    }
+
+    #[test]
+    fn test_argument_protocols() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let quack = Type::callable(vec![], Type::Str);
+        let class = |attributes: Vec<(&str, Type)>| ClassInfo {
+            attributes: attributes
+                .into_iter()
+                .map(|(a, ty)| (a.to_string(), ty))
+                .collect(),
+            ..ClassInfo::default()
+        };
+        let classes = BTreeMap::from([
+            ("Duck".to_string(), class(vec![("quack", quack.clone()), ("feathers", Type::Int)])),
+            ("Robot".to_string(), class(vec![("quack", quack.clone()), ("volts", Type::Float)])),
+        ]);
+        let mut trace = TypeTrace::default();
+        trace.add_function_call("call".to_string(), vec![named("Duck"), Type::Int], Type::Str);
+        trace.add_function_call("call".to_string(), vec![named("Robot"), Type::Int], Type::Str);
+        let protocol = trace
+            .argument_protocol("call", 0, "Quacks", &classes)
+            .unwrap();
+        assert_eq!(
+            protocol.protocol_definition().unwrap(),
+            "class Quacks(Protocol):\n    def quack(self) -> str: ...\n"
+        );
+        assert_eq!(trace.argument_protocol("call", 1, "Number", &classes), None);
+        trace.add_function_call("call".to_string(), vec![named("Cat"), Type::Int], Type::Str);
+        assert_eq!(trace.argument_protocol("call", 0, "Quacks", &classes), None);
+    }
+
     #[test]
     fn test_python_type_conversion() {
         assert_eq!(RuntimeTracer::convert_python_type_to_our_type("int"), Type::Int);
@@ -727,6 +1006,16 @@ mod tests {
 
         let dict_type = RuntimeTracer::convert_python_type_to_our_type("Dict[str, int]");
         assert_eq!(dict_type, Type::Dict(Box::new(Type::Str), Box::new(Type::Int)));
+
+        let optional = Type::optional(Type::List(Box::new(Type::Int)));
+        for text in ["Optional[List[int]]", "List[int] | None", "None | List[int]"] {
+            assert_eq!(RuntimeTracer::convert_python_type_to_our_type(text), optional);
+        }
+        assert_eq!(optional.to_string(), "Optional[List[int]]");
+        let union = RuntimeTracer::convert_python_type_to_our_type("Set[int | str] | None");
+        assert_eq!(union.unwrap_optional().to_string(), "Set[int | str]");
+        let class = RuntimeTracer::convert_python_type_to_our_type("Type[Widget]");
+        assert_eq!(class.instance(), Some(&Type::Named("Widget".to_string())));
     }
 
     #[test]
@@ -752,6 +1041,13 @@ def test_simple():
         assert!(content.contains("TypeTracer"));
         assert!(content.contains("TRACE_OUTPUT_START"));
         assert!(content.contains("sys.settrace"));
+        assert!(!content.contains("doctest.testmod"));
+
+        let with_doctests = RuntimeTracer::new(false)
+            .doctests(true)
+            .instrument_python_file(temp_file.path())
+            .unwrap();
+        assert!(with_doctests.contains("doctest.testmod(current_module)"));
 
         // temp_file is automatically cleaned up when it goes out of scope
     }
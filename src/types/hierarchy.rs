@@ -0,0 +1,194 @@
+//! The class hierarchy types are checked against.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{read_json, write_json, Type};
+use crate::error::Result;
+
+/// The classes a program defines, by dotted name, with the direct base
+/// classes and the members each declares, which decide when an instance
+/// of one class can be used as an instance of another. Classes it doesn't
+/// know are assumed to be unrelated to any other.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ClassHierarchy {
+    classes: BTreeMap<String, ClassEntry>,
+}
+
+/// What a [`ClassHierarchy`] records for one class.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct ClassEntry {
+    /// Direct base classes, in the order the class statement lists them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    bases: Vec<String>,
+    /// Types of the attributes and methods the class itself declares.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    members: BTreeMap<String, Type>,
+}
+
+impl ClassHierarchy {
+    /// Creates an empty hierarchy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a hierarchy written by [`ClassHierarchy::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        read_json(path.as_ref(), "class hierarchy")
+    }
+
+    /// Writes the hierarchy as JSON, keyed by class name.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_json(self, path.as_ref())
+    }
+
+    /// Registers a class and its direct base classes, replacing the bases
+    /// registered for it before.
+    pub fn add_class(&mut self, name: impl Into<String>, bases: Vec<String>) {
+        self.classes.entry(name.into()).or_default().bases = bases;
+    }
+
+    /// Declares a member of a class, an attribute or a method as a
+    /// `Function` without `self`, registering the class if needed.
+    pub fn add_member(&mut self, class: impl Into<String>, name: impl Into<String>, ty: Type) {
+        let entry = self.classes.entry(class.into()).or_default();
+        entry.members.insert(name.into(), ty);
+    }
+
+    /// The direct base classes of a registered class.
+    pub fn bases(&self, name: &str) -> Option<&[String]> {
+        self.classes.get(name).map(|entry| entry.bases.as_slice())
+    }
+
+    /// The members a registered class itself declares, by name.
+    pub fn members(&self, name: &str) -> Option<&BTreeMap<String, Type>> {
+        self.classes.get(name).map(|entry| &entry.members)
+    }
+
+    /// Iterates over the registered classes and their direct bases.
+    pub fn classes(&self) -> impl Iterator<Item = (&String, &[String])> {
+        self.classes
+            .iter()
+            .map(|(name, entry)| (name, entry.bases.as_slice()))
+    }
+
+    /// The method resolution order of a registered class: the class, then
+    /// its bases as Python's C3 linearization orders them. Bases that
+    /// aren't registered are taken to derive from `object` alone. `None`
+    /// for unregistered classes and for hierarchies Python would reject as
+    /// inconsistent, including cycles.
+    pub fn mro(&self, name: &str) -> Option<Vec<String>> {
+        self.linearize(name, &mut Vec::new())
+    }
+
+    /// The type of member `member` of a registered class, from the first
+    /// class in its method resolution order that declares it.
+    pub fn member(&self, class: &str, member: &str) -> Option<&Type> {
+        self.mro(class)?
+            .iter()
+            .find_map(|name| self.members(name)?.get(member))
+    }
+
+    /// Whether `sub` is `sup` or derives from it. `None` when that depends
+    /// on a class that isn't registered, as `sub` may derive from `sup`
+    /// through it.
+    pub fn is_subclass(&self, sub: &str, sup: &str) -> Option<bool> {
+        if sub == sup || sup == "object" {
+            return Some(true);
+        }
+        let mro = self.mro(sub)?;
+        if mro.iter().any(|name| name == sup) {
+            return Some(true);
+        }
+        let known = mro
+            .iter()
+            .all(|name| name == "object" || self.classes.contains_key(name));
+        known.then_some(false)
+    }
+
+    /// The C3 linearization of `name`, with the classes being linearized
+    /// in `visiting` to detect cycles.
+    fn linearize(&self, name: &str, visiting: &mut Vec<String>) -> Option<Vec<String>> {
+        if visiting.iter().any(|v| v == name) {
+            return None;
+        }
+        let entry = self.classes.get(name)?;
+        visiting.push(name.to_string());
+        let mut sequences = Vec::new();
+        for base in &entry.bases {
+            // An unknown base derives from `object` as far as is known.
+            let linearized = match self.classes.contains_key(base) {
+                true => self.linearize(base, visiting)?,
+                false if base == "object" => vec![base.clone()],
+                false => vec![base.clone(), "object".to_string()],
+            };
+            sequences.push(linearized);
+        }
+        visiting.pop();
+        sequences.push(entry.bases.clone());
+
+        let mut mro = vec![name.to_string()];
+        while sequences.iter().any(|s| !s.is_empty()) {
+            // The first head that no other sequence has in its tail.
+            let head = sequences
+                .iter()
+                .filter_map(|s| s.first())
+                .find(|head| {
+                    !sequences
+                        .iter()
+                        .any(|s| s.iter().skip(1).any(|c| c == *head))
+                })?
+                .clone();
+            for sequence in &mut sequences {
+                if sequence.first() == Some(&head) {
+                    sequence.remove(0);
+                }
+            }
+            mro.push(head);
+        }
+        Some(mro)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_resolution_order() {
+        let mut classes = ClassHierarchy::new();
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        classes.add_class("Base", names(&["object"]));
+        classes.add_class("Left", names(&["Base"]));
+        classes.add_class("Right", names(&["Base", "Mixin"]));
+        classes.add_class("Child", names(&["Left", "Right"]));
+        classes.add_member("Base", "name", Type::Str);
+        classes.add_member("Right", "name", Type::Int);
+        assert_eq!(
+            classes.mro("Child").unwrap(),
+            names(&["Child", "Left", "Right", "Base", "Mixin", "object"])
+        );
+        assert_eq!(classes.member("Child", "name"), Some(&Type::Int));
+        assert_eq!(classes.member("Left", "name"), Some(&Type::Str));
+        assert_eq!(classes.member("Left", "size"), None);
+
+        assert_eq!(classes.is_subclass("Child", "Base"), Some(true));
+        assert_eq!(classes.is_subclass("Left", "Right"), Some(false));
+        // `Mixin` isn't known, so it may derive from anything.
+        assert_eq!(classes.is_subclass("Right", "Left"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("classes.json");
+        classes.save(&path).unwrap();
+        assert_eq!(ClassHierarchy::load(&path).unwrap(), classes);
+
+        classes.add_class("Bad", names(&["Base", "Left"]));
+        assert_eq!(classes.mro("Bad"), None);
+        classes.add_class("Loop", names(&["Loop"]));
+        assert_eq!(classes.mro("Loop"), None);
+        assert_eq!(classes.mro("Unknown"), None);
+    }
+}
@@ -0,0 +1,147 @@
+//! Sharing equal types instead of storing a copy of each.
+//!
+//! A [`TypeEnv`](super::TypeEnv) holds its bindings and aliases as
+//! [`Interned`] types, so the copy of an environment taken for each branch
+//! of an `if` or `try` shares their types rather than cloning them. The
+//! [`Analyzer`](crate::analyzer::Analyzer) interns what it binds and the
+//! tables it keeps per syntax node through one [`TypeInterner`], so equal
+//! types there are stored once. [`Type`] itself, and the class and stub
+//! tables, still own their types, boxed parts included.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Type;
+
+/// A type that may be shared with every other holder of an equal type.
+/// It dereferences to the [`Type`], so it is read like one. Two values
+/// handed out by the same [`TypeInterner`] are equal exactly when they
+/// point at the same type, which is checked before comparing structure.
+#[derive(Clone)]
+pub struct Interned(Arc<Type>);
+
+impl Interned {
+    /// Wraps a type without sharing it with any other.
+    pub fn new(ty: Type) -> Self {
+        Self(Arc::new(ty))
+    }
+
+    /// Whether both values share the same type.
+    pub fn ptr_eq(a: &Interned, b: &Interned) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+
+    /// The type, cloned only if it is still shared.
+    pub fn into_type(self) -> Type {
+        Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone())
+    }
+}
+
+impl Deref for Interned {
+    type Target = Type;
+
+    fn deref(&self) -> &Type {
+        &self.0
+    }
+}
+
+impl PartialEq for Interned {
+    fn eq(&self, other: &Self) -> bool {
+        Interned::ptr_eq(self, other) || self.0 == other.0
+    }
+}
+
+impl Eq for Interned {}
+
+impl Hash for Interned {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl Serialize for Interned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interned {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Type::deserialize(deserializer).map(Interned::new)
+    }
+}
+
+impl fmt::Debug for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+/// A table of the distinct types seen so far, so that each is stored once
+/// however many times it comes up: a file that reads a variable a
+/// thousand times holds its type once, with a thousand references to it.
+/// Only whole types are shared; the parts of a type are its own.
+#[derive(Debug, Clone, Default)]
+pub struct TypeInterner {
+    types: HashSet<Arc<Type>>,
+}
+
+impl TypeInterner {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared copy of `ty`, adding a copy to the table if it holds no
+    /// equal type yet.
+    pub fn intern(&mut self, ty: &Type) -> Interned {
+        if let Some(shared) = self.types.get(ty) {
+            return Interned(Arc::clone(shared));
+        }
+        let shared = Arc::new(ty.clone());
+        self.types.insert(Arc::clone(&shared));
+        Interned(shared)
+    }
+
+    /// The number of distinct types in the table.
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    /// Whether the table holds no types.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interned_types() {
+        let mut types = TypeInterner::new();
+        let list = || Type::List(Box::new(Type::optional(Type::Str)));
+        let (a, b) = (types.intern(&list()), types.intern(&list()));
+        let other = types.intern(&Type::Int);
+        assert!(Interned::ptr_eq(&a, &b));
+        assert_eq!(a, b);
+        assert_ne!(a, other);
+        assert_eq!(types.len(), 2);
+        assert_eq!(a.to_string(), "List[Optional[str]]");
+        assert!(matches!(*a, Type::List(_)));
+        // Values from elsewhere still compare by structure.
+        assert_eq!(Interned::new(list()), a);
+    }
+}
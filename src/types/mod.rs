@@ -1,14 +1,27 @@
 //! Type system definitions for omnitype.
 
+mod hierarchy;
+mod interner;
+
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
+use std::fs;
 use std::hash::{Hash, Hasher};
+use std::path::Path;
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use crate::error::{Error, Result};
+use crate::solver::ConstraintSolver;
+use crate::utils::PythonVersion;
+
+pub use hierarchy::ClassHierarchy;
+pub use interner::{Interned, TypeInterner};
+
 /// A type variable used during type inference.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct TypeVar(pub u32);
 
 impl fmt::Display for TypeVar {
@@ -18,9 +31,10 @@ impl fmt::Display for TypeVar {
 }
 
 /// Represents a type in the omnitype system.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum Type {
     /// The unknown type (used during inference)
+    #[default]
     Unknown,
 
     /// The `None` type
@@ -58,13 +72,14 @@ pub enum Type {
 
     /// Function type with parameter and return types
     Function {
-        /// List of parameter types
-        params: Vec<Type>,
+        /// The parameters in order, with their names and kinds where known
+        params: Vec<Param>,
         /// Return type
         returns: Box<Type>,
     },
 
-    /// Union type representing one of several possible types (T1 | T2 | ...)
+    /// Union type representing one of several possible types (T1 | T2 | ...).
+    /// `Optional[T]` is the union of `T` and `None`, built by [`Type::optional`]
     Union(Vec<Type>),
 
     /// Type variable used during type inference
@@ -73,6 +88,10 @@ pub enum Type {
     /// Named type (e.g., user-defined class or type alias)
     Named(String),
 
+    /// A single value of a `Literal[...]` annotation, as written in source
+    /// with strings single-quoted (e.g., `'r'`, `1`, `Color.RED`)
+    Literal(String),
+
     /// Generic type with type parameters
     Generic {
         /// Name of the generic type
@@ -80,6 +99,103 @@ pub enum Type {
         /// Type parameters
         params: Vec<Type>,
     },
+
+    /// A dict with a fixed set of string keys, each with its own value type
+    TypedDict {
+        /// Name of the `TypedDict` class, or empty for one inferred from
+        /// the shape of values, as the tracer does
+        name: String,
+        /// The keys, with their value types and whether they are required
+        fields: BTreeMap<String, TypedDictField>,
+        /// Whether keys are required unless marked otherwise (`total=False`
+        /// makes them optional)
+        total: bool,
+    },
+
+    /// A structural type: any value with these members, whatever its class
+    Protocol {
+        /// Name of the `Protocol` class, or empty for one synthesized from
+        /// the attributes a value is used with
+        name: String,
+        /// The required attributes, with methods as `Function` types
+        /// without `self`
+        members: BTreeMap<String, Type>,
+    },
+
+    /// The parameters captured by a `ParamSpec`. Only found as the type of
+    /// the last parameter of a `Function`, where it stands for the rest of
+    /// the parameter list: `Callable[P, R]`, or `Callable[Concatenate[int,
+    /// P], R]` after an `int` parameter.
+    ParamSpec(String),
+
+    /// The class object whose instances have the given type, as written
+    /// `Type[C]` or `type[C]`: what a class name evaluates to, and what
+    /// `cls` receives in a class method.
+    #[allow(clippy::enum_variant_names)]
+    TypeOf(Box<Type>),
+
+    /// A value of every one of these types at once, such as an object that
+    /// passed `isinstance` checks for two unrelated classes. Built with
+    /// [`Type::intersection_of`], which keeps it normalized.
+    Intersection(Vec<Type>),
+}
+
+/// How arguments are passed to a parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParameterKind {
+    /// Before a `/` separator: passed by position only.
+    PositionalOnly,
+    /// Passed by position or by keyword.
+    Positional,
+    /// `*args`, which collects extra positional arguments.
+    VarPositional,
+    /// After `*` or `*args`: passed by keyword only.
+    KeywordOnly,
+    /// `**kwargs`, which collects extra keyword arguments.
+    VarKeyword,
+}
+
+/// A parameter of a [`Type::Function`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Param {
+    /// Name as written, without the `*` or `**` of variadic parameters.
+    /// `None` for the parameters of a `Callable[[...], R]` annotation.
+    pub name: Option<String>,
+    /// How arguments are passed to the parameter.
+    pub kind: ParameterKind,
+    /// The type of the argument, or of each argument a variadic parameter
+    /// collects rather than of the collection.
+    pub ty: Type,
+    /// The default value as written, if the parameter has one.
+    pub default: Option<String>,
+}
+
+impl Param {
+    /// An unnamed parameter passed by position only, as the parameters of
+    /// `Callable[[int], str]` are.
+    pub fn positional(ty: Type) -> Self {
+        Param { name: None, kind: ParameterKind::PositionalOnly, ty, default: None }
+    }
+
+    /// Whether an argument can be passed to this parameter by position.
+    pub fn is_positional(&self) -> bool {
+        matches!(self.kind, ParameterKind::PositionalOnly | ParameterKind::Positional)
+    }
+
+    /// This parameter with `f` applied to its type.
+    pub fn map_type(self, f: impl FnOnce(Type) -> Type) -> Self {
+        Param { ty: f(self.ty), ..self }
+    }
+}
+
+/// A key of a [`Type::TypedDict`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TypedDictField {
+    /// The type of the key's value
+    pub ty: Type,
+    /// Whether every value must have the key
+    pub required: bool,
 }
 
 impl Hash for Type {
@@ -92,18 +208,27 @@ impl Hash for Type {
                 v.hash(state);
             },
             Type::Tuple(types) => types.hash(state),
-            Type::Set(inner) => inner.hash(state),
+            Type::Set(inner) | Type::TypeOf(inner) => inner.hash(state),
             Type::Function { params, returns } => {
                 params.hash(state);
                 returns.hash(state);
             },
-            Type::Union(types) => types.hash(state),
+            Type::Union(types) | Type::Intersection(types) => types.hash(state),
             Type::Var(var) => var.hash(state),
-            Type::Named(name) => name.hash(state),
+            Type::Named(name) | Type::Literal(name) | Type::ParamSpec(name) => name.hash(state),
             Type::Generic { name, params } => {
                 name.hash(state);
                 params.hash(state);
             },
+            Type::TypedDict { name, fields, total } => {
+                name.hash(state);
+                fields.hash(state);
+                total.hash(state);
+            },
+            Type::Protocol { name, members } => {
+                name.hash(state);
+                members.hash(state);
+            },
             _ => (),
         }
     }
@@ -116,17 +241,29 @@ impl PartialEq for Type {
             (Type::Dict(ak, av), Type::Dict(bk, bv)) => ak == bk && av == bv,
             (Type::Tuple(a), Type::Tuple(b)) => a == b,
             (Type::Set(a), Type::Set(b)) => a == b,
+            (Type::TypeOf(a), Type::TypeOf(b)) => a == b,
             (
                 Type::Function { params: a_params, returns: a_ret },
                 Type::Function { params: b_params, returns: b_ret },
             ) => a_params == b_params && a_ret == b_ret,
             (Type::Union(a), Type::Union(b)) => a == b,
+            (Type::Intersection(a), Type::Intersection(b)) => a == b,
             (Type::Var(a), Type::Var(b)) => a == b,
             (Type::Named(a), Type::Named(b)) => a == b,
+            (Type::Literal(a), Type::Literal(b)) => a == b,
+            (Type::ParamSpec(a), Type::ParamSpec(b)) => a == b,
             (
                 Type::Generic { name: a_name, params: a_params },
                 Type::Generic { name: b_name, params: b_params },
             ) => a_name == b_name && a_params == b_params,
+            (
+                Type::TypedDict { name: a_name, fields: a_fields, total: a_total },
+                Type::TypedDict { name: b_name, fields: b_fields, total: b_total },
+            ) => a_name == b_name && a_fields == b_fields && a_total == b_total,
+            (
+                Type::Protocol { name: a_name, members: a_members },
+                Type::Protocol { name: b_name, members: b_members },
+            ) => a_name == b_name && a_members == b_members,
             (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
         }
     }
@@ -158,6 +295,7 @@ impl Ord for Type {
             },
             (Type::Tuple(a), Type::Tuple(b)) => a.cmp(b),
             (Type::Set(a), Type::Set(b)) => a.cmp(b),
+            (Type::TypeOf(a), Type::TypeOf(b)) => a.cmp(b),
             (
                 Type::Function { params: a_params, returns: a_ret },
                 Type::Function { params: b_params, returns: b_ret },
@@ -166,8 +304,11 @@ impl Ord for Type {
                 ord => ord,
             },
             (Type::Union(a), Type::Union(b)) => a.cmp(b),
+            (Type::Intersection(a), Type::Intersection(b)) => a.cmp(b),
             (Type::Var(a), Type::Var(b)) => a.0.cmp(&b.0),
             (Type::Named(a), Type::Named(b)) => a.cmp(b),
+            (Type::Literal(a), Type::Literal(b)) => a.cmp(b),
+            (Type::ParamSpec(a), Type::ParamSpec(b)) => a.cmp(b),
             (
                 Type::Generic { name: a_name, params: a_params },
                 Type::Generic { name: b_name, params: b_params },
@@ -175,6 +316,14 @@ impl Ord for Type {
                 Ordering::Equal => a_params.cmp(b_params),
                 ord => ord,
             },
+            (
+                Type::TypedDict { name: a_name, fields: a_fields, total: a_total },
+                Type::TypedDict { name: b_name, fields: b_fields, total: b_total },
+            ) => (a_name, a_fields, a_total).cmp(&(b_name, b_fields, b_total)),
+            (
+                Type::Protocol { name: a_name, members: a_members },
+                Type::Protocol { name: b_name, members: b_members },
+            ) => (a_name, a_members).cmp(&(b_name, b_members)),
             (a, b) => {
                 // Compare discriminants by matching all possible variants
                 match (a, b) {
@@ -210,7 +359,19 @@ impl Ord for Type {
                     (_, Type::Var(_)) => Ordering::Greater,
                     (Type::Named(_), _) => Ordering::Less,
                     (_, Type::Named(_)) => Ordering::Greater,
-                    (Type::Generic { .. }, _) => Ordering::Equal,
+                    (Type::Literal(_), _) => Ordering::Less,
+                    (_, Type::Literal(_)) => Ordering::Greater,
+                    (Type::Generic { .. }, _) => Ordering::Less,
+                    (_, Type::Generic { .. }) => Ordering::Greater,
+                    (Type::TypedDict { .. }, _) => Ordering::Less,
+                    (_, Type::TypedDict { .. }) => Ordering::Greater,
+                    (Type::Protocol { .. }, _) => Ordering::Less,
+                    (_, Type::Protocol { .. }) => Ordering::Greater,
+                    (Type::ParamSpec(_), _) => Ordering::Less,
+                    (_, Type::ParamSpec(_)) => Ordering::Greater,
+                    (Type::TypeOf(_), _) => Ordering::Less,
+                    (_, Type::TypeOf(_)) => Ordering::Greater,
+                    (Type::Intersection(_), _) => Ordering::Equal,
                 }
             },
         }
@@ -249,11 +410,618 @@ impl Type {
         // Convert back to a sorted vector
         Type::Union(unique_types.into_iter().collect())
     }
-}
 
-impl Default for Type {
-    fn default() -> Self {
-        Self::Unknown
+    /// Creates a normalized intersection: nested intersections are
+    /// flattened, members sorted and deduplicated, and `Any` and `object`,
+    /// which add nothing, dropped. An intersection with a union member is
+    /// distributed into a union of intersections, so `(A | B) & C` becomes
+    /// `(A & C) | (B & C)`. One remaining member is that type, and none is
+    /// `Any`.
+    pub fn intersection_of(types: Vec<Type>) -> Type {
+        let mut members = BTreeSet::new();
+        for ty in types {
+            match ty {
+                Type::Intersection(nested) => members.extend(nested),
+                Type::Any => {},
+                Type::Named(name) if name == "object" => {},
+                ty => {
+                    members.insert(ty);
+                },
+            }
+        }
+        let union = members.iter().find_map(|m| match m {
+            Type::Union(alternatives) => Some(alternatives.clone()),
+            _ => None,
+        });
+        if let Some(alternatives) = union {
+            members.remove(&Type::Union(alternatives.clone()));
+            return Type::union_of(
+                alternatives
+                    .into_iter()
+                    .map(|alternative| {
+                        let mut types: Vec<Type> = members.iter().cloned().collect();
+                        types.push(alternative);
+                        Type::intersection_of(types)
+                    })
+                    .collect(),
+            );
+        }
+        match members.len() {
+            0 => Type::Any,
+            1 => members.into_iter().next().unwrap(),
+            _ => Type::Intersection(members.into_iter().collect()),
+        }
+    }
+
+    /// `Callable[[params...], returns]`: a function whose parameters are
+    /// unnamed and passed by position only.
+    pub fn callable(params: Vec<Type>, returns: Type) -> Type {
+        Type::Function {
+            params: params.into_iter().map(Param::positional).collect(),
+            returns: Box::new(returns),
+        }
+    }
+
+    /// The name of the `ParamSpec` that ends a function's parameters, if
+    /// they end with one.
+    pub fn param_spec(params: &[Param]) -> Option<&str> {
+        match &params.last()?.ty {
+            Type::ParamSpec(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// `Optional[ty]`, which is the union of `ty` and `None` rather than a
+    /// type of its own, so `Optional[int]`, `Union[int, None]`, and
+    /// `int | None` are all the same type.
+    pub fn optional(ty: Type) -> Type {
+        Type::union_of(vec![ty, Type::None])
+    }
+
+    /// Whether this is a union that includes `None`, such as `Optional[int]`.
+    pub fn is_optional(&self) -> bool {
+        matches!(self, Type::Union(members) if members.contains(&Type::None))
+    }
+
+    /// This type without `None`: `int` for `Optional[int]`, and `int | str`
+    /// for `int | str | None`. Types that are only `None`, or that don't
+    /// include it, are returned unchanged.
+    pub fn unwrap_optional(&self) -> Type {
+        match self {
+            Type::Union(members) => Type::union_of(
+                members
+                    .iter()
+                    .filter(|m| **m != Type::None)
+                    .cloned()
+                    .collect(),
+            ),
+            ty => ty.clone(),
+        }
+    }
+
+    /// The type of a value that may come from either side, such as a
+    /// variable assigned in two branches. `Unknown` absorbs everything, so
+    /// a partially known type never passes for a fully known one.
+    pub fn join(self, other: Type) -> Type {
+        match (self, other) {
+            (Type::Unknown, _) | (_, Type::Unknown) => Type::Unknown,
+            (a, b) => Type::union_of(vec![a, b]),
+        }
+    }
+
+    /// Whether a value of this type can be assigned to a variable, or
+    /// passed to a parameter, of type `other`, following PEP 483 and 484:
+    /// `Any` and `Unknown` are assignable both ways, `bool` to `int` to
+    /// `float`, a class to its bases in `classes`, and a union when each
+    /// member is. Unlike [`Type::accepts`], which only answers for
+    /// builtins, this always decides, taking a class `classes` doesn't
+    /// know for unrelated to others.
+    pub fn is_assignable_to(&self, other: &Type, classes: &ClassHierarchy) -> bool {
+        let mut solver = ConstraintSolver::new();
+        solver.add_hierarchy(classes);
+        solver.is_subtype(self, other)
+    }
+
+    /// Whether a value of type `value` may be stored where this type is
+    /// expected, with the numeric promotions `bool` to `int` to `float`.
+    /// Containers are compared by kind and element types, and a `Literal`
+    /// value by the type of its value. `None` when either side involves a
+    /// type that isn't a builtin, such as a class that may subclass a
+    /// builtin or a protocol like `Iterable`.
+    pub fn accepts(&self, value: &Type) -> Option<bool> {
+        let rank = |ty: &Type| match ty {
+            Type::Bool => Some(0),
+            Type::Int => Some(1),
+            Type::Float => Some(2),
+            _ => None,
+        };
+        let all = |results: Vec<Option<bool>>| {
+            if results.contains(&Some(false)) {
+                Some(false)
+            } else if results.contains(&None) {
+                None
+            } else {
+                Some(true)
+            }
+        };
+        match (self, value) {
+            (Type::Any, _) | (_, Type::Any) => Some(true),
+            (_, Type::Union(members)) => all(members.iter().map(|m| self.accepts(m)).collect()),
+            (Type::Union(members), value) => {
+                let results: Vec<Option<bool>> = members.iter().map(|m| m.accepts(value)).collect();
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(false)
+                }
+            },
+            // A value of an intersection fits wherever one of its members does;
+            // the other members may be subclasses of anything.
+            (_, Type::Intersection(members)) => members
+                .iter()
+                .any(|m| self.accepts(m) == Some(true))
+                .then_some(true),
+            (Type::Intersection(members), value) => {
+                all(members.iter().map(|m| m.accepts(value)).collect())
+            },
+            (Type::Literal(a), Type::Literal(b)) if a == b => Some(true),
+            (Type::Literal(_), Type::Literal(_)) => {
+                (self.widened().is_builtin() && value.widened().is_builtin()).then_some(false)
+            },
+            // A `str` may or may not be the one string a `Literal` allows.
+            (Type::Literal(_), value) => self.widened().accepts(value).filter(|fits| !fits),
+            (expected, Type::Literal(_)) => expected.accepts(&value.widened()),
+            (expected, value) if rank(expected).is_some() && rank(value).is_some() => {
+                Some(rank(value) <= rank(expected))
+            },
+            (Type::List(a), Type::List(b)) | (Type::Set(a), Type::Set(b)) => a.accepts(b),
+            // A class object is accepted where its class or a base is.
+            (Type::TypeOf(a), Type::TypeOf(b)) => a.accepts(b),
+            (Type::TypeOf(_), value) | (value, Type::TypeOf(_)) if value.is_builtin() => {
+                Some(false)
+            },
+            (Type::Dict(ak, av), Type::Dict(bk, bv)) => all(vec![ak.accepts(bk), av.accepts(bv)]),
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => {
+                all(a.iter().zip(b).map(|(a, b)| a.accepts(b)).collect())
+            },
+            (Type::Tuple(_), Type::Tuple(_)) => None,
+            (expected, value) if expected.is_builtin() && value.is_builtin() => {
+                Some(std::mem::discriminant(expected) == std::mem::discriminant(value))
+            },
+            _ => None,
+        }
+    }
+
+    /// This type with each `Literal` replaced by the type of its value:
+    /// `str` for `Literal['a']`, `int` for `Literal[-1]`, and the enum for
+    /// `Literal[Color.RED]`.
+    pub fn widened(&self) -> Type {
+        match self {
+            Type::Literal(value) => match value.as_str() {
+                "True" | "False" => Type::Bool,
+                v if v.starts_with('\'') => Type::Str,
+                v if v
+                    .trim_start_matches(['-', '+'])
+                    .starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    Type::Int
+                },
+                v => match v.rsplit_once('.') {
+                    Some((enum_name, _)) => Type::Named(enum_name.to_string()),
+                    None => Type::Unknown,
+                },
+            },
+            Type::Union(members) => Type::union_of(members.iter().map(Type::widened).collect()),
+            Type::Intersection(members) => {
+                Type::intersection_of(members.iter().map(Type::widened).collect())
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    /// This type in canonical form, so that equivalent types compare,
+    /// hash, and display the same: unions are flattened, sorted, and
+    /// deduplicated, which also collapses `Optional[Optional[T]]`, a union
+    /// of one type is that type, and a union with an `Any` member is `Any`,
+    /// which accepts everything the other members do. The spellings of a
+    /// type are unified: `typing.List[int]`, `list[int]`, and `List[int]`
+    /// are all [`Type::List`], `Union[...]` and `Optional[...]` are unions,
+    /// `type[C]` is [`Type::TypeOf`], and `NoneType` is `None`. Types nested
+    /// in others are normalized too.
+    pub fn normalize(self) -> Type {
+        let normalize = |ty: Box<Type>| Box::new(ty.normalize());
+        let normalize_all = |types: Vec<Type>| types.into_iter().map(Type::normalize).collect();
+        match self {
+            Type::Named(name) => match unqualified(&name) {
+                "None" | "NoneType" => Type::None,
+                "Any" => Type::Any,
+                "bool" => Type::Bool,
+                "int" => Type::Int,
+                "float" => Type::Float,
+                "str" => Type::Str,
+                "bytes" => Type::Bytes,
+                "list" | "List" => Type::List(Box::new(Type::Any)),
+                "dict" | "Dict" => Type::Dict(Box::new(Type::Any), Box::new(Type::Any)),
+                "set" | "Set" | "frozenset" | "FrozenSet" => Type::Set(Box::new(Type::Any)),
+                _ => Type::Named(name),
+            },
+            Type::Generic { name, params } => {
+                let params: Vec<Type> = normalize_all(params);
+                let boxed = |ty: &Type| Box::new(ty.clone());
+                match (unqualified(&name), params.as_slice()) {
+                    ("list" | "List", [item]) => Type::List(boxed(item)),
+                    ("set" | "Set" | "frozenset" | "FrozenSet", [item]) => Type::Set(boxed(item)),
+                    ("dict" | "Dict", [key, value]) => Type::Dict(boxed(key), boxed(value)),
+                    ("type" | "Type", [instance]) => Type::TypeOf(boxed(instance)),
+                    ("Optional", [ty]) => Type::optional(ty.clone()).normalize(),
+                    ("Union", _) => Type::Union(params.clone()).normalize(),
+                    // `tuple[T, ...]` keeps its spelling, as `Tuple` has no other.
+                    ("tuple" | "Tuple", [item, ellipsis])
+                        if *ellipsis == Type::Named("...".to_string()) =>
+                    {
+                        Type::variadic_tuple(item.clone())
+                    },
+                    ("tuple" | "Tuple", _) => Type::Tuple(params.clone()),
+                    (name, _) => Type::Generic { name: name.to_string(), params: params.clone() },
+                }
+            },
+            Type::Union(members) => match Type::union_of(normalize_all(members)) {
+                Type::Union(members) if members.contains(&Type::Any) => Type::Any,
+                ty => ty,
+            },
+            Type::Intersection(members) => Type::intersection_of(normalize_all(members)),
+            Type::List(inner) => Type::List(normalize(inner)),
+            Type::Dict(k, v) => Type::Dict(normalize(k), normalize(v)),
+            Type::Tuple(items) => Type::Tuple(normalize_all(items)),
+            Type::Set(inner) => Type::Set(normalize(inner)),
+            Type::TypeOf(inner) => Type::TypeOf(normalize(inner)),
+            Type::Function { params, returns } => Type::Function {
+                params: params
+                    .into_iter()
+                    .map(|p| p.map_type(Type::normalize))
+                    .collect(),
+                returns: normalize(returns),
+            },
+            Type::TypedDict { name, fields, total } => {
+                Type::TypedDict { name, fields: map_fields(fields, Type::normalize), total }
+            },
+            Type::Protocol { name, members } => Type::Protocol {
+                name,
+                members: members
+                    .into_iter()
+                    .map(|(k, ty)| (k, ty.normalize()))
+                    .collect(),
+            },
+            ty => ty,
+        }
+    }
+
+    /// This type with each union of more than `max_members` members
+    /// widened so it stays readable: literals become their types, `bool`
+    /// and `int` are absorbed by a wider numeric member, and a union still
+    /// too large becomes `Any`. Unions nested in other types are capped too.
+    pub fn capped(self, max_members: usize) -> Type {
+        let cap = |ty: Box<Type>| Box::new(ty.capped(max_members));
+        let cap_all = |types: Vec<Type>| types.into_iter().map(|t| t.capped(max_members)).collect();
+        match self {
+            Type::Union(members) if members.len() > max_members => {
+                let members = match Type::union_of(cap_all(members)).widened() {
+                    Type::Union(members) => members,
+                    ty => return ty,
+                };
+                let numeric = [Type::Bool, Type::Int, Type::Float];
+                let widest = numeric.iter().rposition(|n| members.contains(n));
+                let members: Vec<Type> = members
+                    .into_iter()
+                    .filter(|m| match numeric.iter().position(|n| n == m) {
+                        Some(rank) => Some(rank) == widest,
+                        None => true,
+                    })
+                    .collect();
+                if members.len() > max_members {
+                    return Type::Any;
+                }
+                Type::union_of(members)
+            },
+            Type::Union(members) => Type::union_of(cap_all(members)),
+            Type::Intersection(members) => Type::intersection_of(cap_all(members)),
+            Type::List(inner) => Type::List(cap(inner)),
+            Type::Dict(k, v) => Type::Dict(cap(k), cap(v)),
+            Type::Tuple(items) => Type::Tuple(cap_all(items)),
+            Type::Set(inner) => Type::Set(cap(inner)),
+            Type::TypeOf(inner) => Type::TypeOf(cap(inner)),
+            Type::Function { params, returns } => Type::Function {
+                params: params
+                    .into_iter()
+                    .map(|p| p.map_type(|t| t.capped(max_members)))
+                    .collect(),
+                returns: cap(returns),
+            },
+            Type::Generic { name, params } => Type::Generic { name, params: cap_all(params) },
+            Type::TypedDict { name, fields, total } => Type::TypedDict {
+                name,
+                fields: map_fields(fields, |ty| ty.capped(max_members)),
+                total,
+            },
+            Type::Protocol { name, members } => Type::Protocol {
+                name,
+                members: members
+                    .into_iter()
+                    .map(|(k, ty)| (k, ty.capped(max_members)))
+                    .collect(),
+            },
+            ty => ty,
+        }
+    }
+
+    /// This type as an annotation valid on Python `version`: builtin
+    /// generics such as `list[int]` from 3.9 and `int | None` unions from
+    /// 3.10, and their `typing` spellings (`List[int]`, `Optional[int]`,
+    /// `Union[int, str]`) before. Names from `typing` are written bare, to
+    /// be imported from it. Types annotations can't spell, such as
+    /// intersections and unnamed protocols, are `Any`.
+    pub fn to_annotation(&self, version: PythonVersion) -> String {
+        let builtin_generics = version.at_least(3, 9);
+        let generic = |builtin: &str, typing: &str, params: &[&Type]| {
+            let params: Vec<String> = params.iter().map(|t| t.to_annotation(version)).collect();
+            let name = if builtin_generics { builtin } else { typing };
+            format!("{}[{}]", name, params.join(", "))
+        };
+        match self {
+            Type::None | Type::Bool | Type::Int | Type::Float | Type::Str | Type::Bytes => {
+                self.to_string()
+            },
+            Type::List(inner) => generic("list", "List", &[inner]),
+            Type::Set(inner) => generic("set", "Set", &[inner]),
+            Type::Dict(k, v) => generic("dict", "Dict", &[k, v]),
+            Type::Tuple(items) if items.is_empty() => {
+                generic("tuple", "Tuple", &[]).replace("[]", "[()]")
+            },
+            Type::Tuple(items) => generic("tuple", "Tuple", &items.iter().collect::<Vec<_>>()),
+            Type::TypeOf(instance) => generic("type", "Type", &[instance]),
+            Type::Union(members) => {
+                let optional = members.contains(&Type::None);
+                let mut others: Vec<String> = members
+                    .iter()
+                    .filter(|m| **m != Type::None)
+                    .map(|m| m.to_annotation(version))
+                    .collect();
+                if version.at_least(3, 10) {
+                    if optional {
+                        others.push("None".to_string());
+                    }
+                    return others.join(" | ");
+                }
+                let union = match others.as_slice() {
+                    [single] => single.clone(),
+                    _ => format!("Union[{}]", others.join(", ")),
+                };
+                if optional {
+                    format!("Optional[{}]", union)
+                } else {
+                    union
+                }
+            },
+            Type::Function { params, returns }
+                if params
+                    .iter()
+                    .all(|p| p.is_positional() && p.default.is_none())
+                    && Type::param_spec(params).is_none() =>
+            {
+                let params: Vec<String> =
+                    params.iter().map(|p| p.ty.to_annotation(version)).collect();
+                format!("Callable[[{}], {}]", params.join(", "), returns.to_annotation(version))
+            },
+            Type::Function { returns, .. } => {
+                format!("Callable[..., {}]", returns.to_annotation(version))
+            },
+            Type::Generic { name, params } => {
+                let params: Vec<&Type> = params.iter().collect();
+                match name.as_str() {
+                    "tuple" | "Tuple" => generic("tuple", "Tuple", &params),
+                    "type" | "Type" => generic("type", "Type", &params),
+                    name => generic(name, name, &params),
+                }
+            },
+            Type::TypedDict { name, .. } if !name.is_empty() => name.clone(),
+            Type::TypedDict { .. } => generic("dict", "Dict", &[&Type::Str, &Type::Any]),
+            Type::Protocol { name, .. } if !name.is_empty() => name.clone(),
+            Type::Named(name) | Type::ParamSpec(name) => name.clone(),
+            Type::Literal(value) => format!("Literal[{}]", value),
+            Type::Any
+            | Type::Unknown
+            | Type::Var(_)
+            | Type::Protocol { .. }
+            | Type::Intersection(_) => "Any".to_string(),
+        }
+    }
+
+    /// The source of a `class ...(Protocol)` definition for a protocol
+    /// type, with methods as `def`s whose parameters are positional-only
+    /// since their names aren't known. `None` for other types.
+    pub fn protocol_definition(&self) -> Option<String> {
+        let Type::Protocol { name, members } = self else {
+            return None;
+        };
+        let mut source = format!("class {}(Protocol):\n", name);
+        for (member, ty) in members {
+            let line = match ty {
+                Type::Function { params, returns } if params.is_empty() => {
+                    format!("def {}(self) -> {}: ...", member, returns)
+                },
+                Type::Function { params, returns } => {
+                    format!("def {}(self, {}) -> {}: ...", member, parameter_list(params), returns)
+                },
+                ty => format!("{}: {}", member, ty),
+            };
+            source.push_str(&format!("    {}\n", line));
+        }
+        Some(source)
+    }
+
+    /// Whether this is `None` or a builtin scalar or container type.
+    pub fn is_builtin(&self) -> bool {
+        matches!(
+            self,
+            Type::None
+                | Type::Bool
+                | Type::Int
+                | Type::Float
+                | Type::Str
+                | Type::Bytes
+                | Type::List(_)
+                | Type::Dict(..)
+                | Type::Set(_)
+                | Type::Tuple(_)
+        )
+    }
+
+    /// The type of a tuple of any length whose items are `item`:
+    /// `tuple[item, ...]`, as `*args: item` binds.
+    pub fn variadic_tuple(item: Type) -> Type {
+        Type::Generic {
+            name: "tuple".to_string(),
+            params: vec![item, Type::Named("...".to_string())],
+        }
+    }
+
+    /// The type of a class object whose instances have type `instance`:
+    /// `Type[instance]`, as `cls` receives in a class method.
+    pub fn class_object(instance: Type) -> Type {
+        Type::TypeOf(Box::new(instance))
+    }
+
+    /// The type of the instances of a class object of this type: `C` for
+    /// `Type[C]` or `type[C]`.
+    pub fn instance(&self) -> Option<&Type> {
+        match self {
+            Type::TypeOf(instance) => Some(instance),
+            _ => None,
+        }
+    }
+
+    /// This type with `Self` replaced by `class`, the type of the instance
+    /// a method is looked up on.
+    pub fn bind_self(self, class: &Type) -> Type {
+        let bind = |ty: Box<Type>| Box::new(ty.bind_self(class));
+        let bind_all = |types: Vec<Type>| types.into_iter().map(|t| t.bind_self(class)).collect();
+        match self {
+            Type::Named(name) if name == "Self" => class.clone(),
+            Type::List(inner) => Type::List(bind(inner)),
+            Type::Dict(k, v) => Type::Dict(bind(k), bind(v)),
+            Type::Tuple(items) => Type::Tuple(bind_all(items)),
+            Type::Set(inner) => Type::Set(bind(inner)),
+            Type::TypeOf(inner) => Type::TypeOf(bind(inner)),
+            Type::Function { params, returns } => Type::Function {
+                params: params
+                    .into_iter()
+                    .map(|p| p.map_type(|t| t.bind_self(class)))
+                    .collect(),
+                returns: bind(returns),
+            },
+            Type::Union(members) => Type::union_of(bind_all(members)),
+            Type::Intersection(members) => Type::intersection_of(bind_all(members)),
+            Type::Generic { name, params } => Type::Generic { name, params: bind_all(params) },
+            ty => ty,
+        }
+    }
+
+    /// The type of calling an `async def` that returns `result`:
+    /// `Coroutine[Any, Any, result]`.
+    pub fn coroutine(result: Type) -> Type {
+        Type::Generic { name: "Coroutine".to_string(), params: vec![Type::Any, Type::Any, result] }
+    }
+
+    /// `Awaitable[result]`, anything `await` can be applied to.
+    pub fn awaitable(result: Type) -> Type {
+        Type::Generic { name: "Awaitable".to_string(), params: vec![result] }
+    }
+
+    /// `Iterator[item]`, as `iter()`, `map()`, and `filter()` return.
+    pub fn iterator(item: Type) -> Type {
+        Type::Generic { name: "Iterator".to_string(), params: vec![item] }
+    }
+
+    /// `AsyncIterator[item]`, what `async for` iterates over.
+    pub fn async_iterator(item: Type) -> Type {
+        Type::Generic { name: "AsyncIterator".to_string(), params: vec![item] }
+    }
+
+    /// `Generator[yielded, sent, returned]`: the type of calling a
+    /// generator function that yields `yielded`, is sent `sent` by
+    /// `send()`, and returns `returned` to a `yield from`.
+    pub fn generator(yielded: Type, sent: Type, returned: Type) -> Type {
+        Type::Generic { name: "Generator".to_string(), params: vec![yielded, sent, returned] }
+    }
+
+    /// The standard protocol an iterator or async form directly implements,
+    /// with its type arguments: `Iterator[Y]` for `Generator[Y, S, R]`,
+    /// `Iterable[T]` for `Iterator[T]`, `AsyncIterator[Y]` for
+    /// `AsyncGenerator[Y, S]`, `AsyncIterable[T]` for `AsyncIterator[T]`,
+    /// and `Awaitable[R]` for `Coroutine[Y, S, R]`.
+    pub fn protocol_base(&self) -> Option<Type> {
+        let Type::Generic { name, params } = self else {
+            return None;
+        };
+        let (base, param) = match name.rsplit('.').next().unwrap_or(name) {
+            "Generator" => ("Iterator", 0),
+            "Iterator" => ("Iterable", 0),
+            "AsyncGenerator" => ("AsyncIterator", 0),
+            "AsyncIterator" => ("AsyncIterable", 0),
+            "Coroutine" => ("Awaitable", 2),
+            _ => return None,
+        };
+        let param = params.get(param).cloned().unwrap_or_default();
+        Some(Type::Generic { name: base.to_string(), params: vec![param] })
+    }
+
+    /// The type of the elements iterating over a value of this type gives:
+    /// the items of a sequence, set, iterator, or generator, the keys of a
+    /// dict, the characters of a string, or the integers in bytes.
+    pub fn iterated(&self) -> Type {
+        match self {
+            Type::List(inner) | Type::Set(inner) | Type::Dict(inner, _) => (**inner).clone(),
+            Type::Str => Type::Str,
+            Type::Bytes => Type::Int,
+            Type::Tuple(items) if !items.is_empty() => Type::union_of(items.clone()),
+            Type::Generic { name, params } => match name.rsplit('.').next().unwrap_or(name) {
+                "Iterator" | "Iterable" | "Generator" | "Sequence" | "tuple" => {
+                    params.first().cloned().unwrap_or_default()
+                },
+                _ => Type::Unknown,
+            },
+            _ => Type::Unknown,
+        }
+    }
+
+    /// The type of the elements `async for` gives over a value of this
+    /// type: the items of an async iterator, iterable, or generator.
+    pub fn async_iterated(&self) -> Type {
+        let Type::Generic { name, params } = self else {
+            return Type::Unknown;
+        };
+        match name.rsplit('.').next().unwrap_or(name) {
+            "AsyncIterator" | "AsyncIterable" | "AsyncGenerator" => {
+                params.first().cloned().unwrap_or_default()
+            },
+            _ => Type::Unknown,
+        }
+    }
+
+    /// The type of `await` on a value of this type: the result of a
+    /// coroutine, awaitable, task, or future, else `Unknown`.
+    pub fn awaited(&self) -> Type {
+        let Type::Generic { name, params } = self else {
+            return Type::Unknown;
+        };
+        let result = match name.rsplit('.').next().unwrap_or(name) {
+            "Coroutine" => params.get(2),
+            "Awaitable" | "Task" | "Future" => params.first(),
+            _ => None,
+        };
+        result.cloned().unwrap_or_default()
     }
 }
 
@@ -279,24 +1047,63 @@ impl fmt::Display for Type {
                 write!(f, "Tuple[{}]", items_str)
             },
             Type::Set(inner) => write!(f, "Set[{}]", inner),
-            Type::Function { params, returns } => {
+            Type::Function { params, returns } if Type::param_spec(params).is_some() => {
+                let (spec, leading) = (&params[params.len() - 1].ty, &params[..params.len() - 1]);
+                if leading.is_empty() {
+                    return write!(f, "Callable[{}, {}]", spec, returns);
+                }
+                let leading: Vec<String> = leading.iter().map(|p| p.ty.to_string()).collect();
+                write!(f, "Callable[Concatenate[{}, {}], {}]", leading.join(", "), spec, returns)
+            },
+            Type::Function { params, returns }
+                if params
+                    .iter()
+                    .all(|p| p.is_positional() && p.default.is_none()) =>
+            {
                 let params_str = params
                     .iter()
-                    .map(|t| t.to_string())
+                    .map(|p| p.ty.to_string())
                     .collect::<Vec<_>>()
                     .join(", ");
                 write!(f, "Callable[[{}], {}]", params_str, returns)
             },
-            Type::Union(types) => {
+            // `Callable` can't express keywords, defaults, or variadics.
+            Type::Function { params, returns } => {
+                write!(f, "({}) -> {}", parameter_list(params), returns)
+            },
+            Type::Union(types) => match types.as_slice() {
+                [Type::None, ty] | [ty, Type::None] if *ty != Type::None => {
+                    write!(f, "Optional[{}]", ty)
+                },
+                types => {
+                    let types_str = types
+                        .iter()
+                        .map(|t| t.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" | ");
+                    write!(f, "{}", types_str)
+                },
+            },
+            Type::Intersection(types) => {
+                // `&` binds tighter than `|` and `->`.
                 let types_str = types
                     .iter()
-                    .map(|t| t.to_string())
+                    .map(|t| match (t, t.to_string()) {
+                        (Type::Union(_), s) => format!("({})", s),
+                        (Type::Function { .. }, s) if !s.starts_with("Callable[") => {
+                            format!("({})", s)
+                        },
+                        (_, s) => s,
+                    })
                     .collect::<Vec<_>>()
-                    .join(" | ");
+                    .join(" & ");
                 write!(f, "{}", types_str)
             },
             Type::Var(var) => write!(f, "{}", var),
             Type::Named(name) => write!(f, "{}", name),
+            Type::Literal(value) => write!(f, "Literal[{}]", value),
+            Type::ParamSpec(name) => write!(f, "{}", name),
+            Type::TypeOf(instance) => write!(f, "Type[{}]", instance),
             Type::Generic { name, params } => {
                 let params_str = params
                     .iter()
@@ -305,44 +1112,366 @@ impl fmt::Display for Type {
                     .join(", ");
                 write!(f, "{}[{}]", name, params_str)
             },
+            Type::TypedDict { name, .. } if !name.is_empty() => write!(f, "{}", name),
+            Type::TypedDict { fields, .. } => {
+                let fields_str = fields
+                    .iter()
+                    .map(|(key, field)| match field.required {
+                        true => format!("{}: {}", key, field.ty),
+                        false => format!("{}: NotRequired[{}]", key, field.ty),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "TypedDict[{}]", fields_str)
+            },
+            Type::Protocol { name, .. } if !name.is_empty() => write!(f, "{}", name),
+            Type::Protocol { members, .. } => {
+                let members_str = members
+                    .iter()
+                    .map(|(member, ty)| format!("{}: {}", member, ty))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Protocol[{}]", members_str)
+            },
         }
     }
 }
 
+/// A parameter list as Python writes it, with the `/` and `*` separators
+/// where the parameters' kinds change. Unnamed parameters are named by
+/// position, `arg0`, `arg1`, and so on.
+fn parameter_list(params: &[Param]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut previous = None;
+    for (i, p) in params.iter().enumerate() {
+        if previous == Some(ParameterKind::PositionalOnly)
+            && p.kind != ParameterKind::PositionalOnly
+        {
+            parts.push("/".to_string());
+        }
+        if p.kind == ParameterKind::KeywordOnly
+            && !matches!(previous, Some(ParameterKind::VarPositional | ParameterKind::KeywordOnly))
+        {
+            parts.push("*".to_string());
+        }
+        let stars = match p.kind {
+            ParameterKind::VarPositional => "*",
+            ParameterKind::VarKeyword => "**",
+            _ => "",
+        };
+        let name = p.name.clone().unwrap_or_else(|| format!("arg{}", i));
+        parts.push(match &p.default {
+            Some(default) => format!("{}{}: {} = {}", stars, name, p.ty, default),
+            None => format!("{}{}: {}", stars, name, p.ty),
+        });
+        previous = Some(p.kind);
+    }
+    if previous == Some(ParameterKind::PositionalOnly) {
+        parts.push("/".to_string());
+    }
+    parts.join(", ")
+}
+
+/// The fields of a `TypedDict` with `f` applied to their types.
+fn map_fields(
+    fields: BTreeMap<String, TypedDictField>,
+    f: impl Fn(Type) -> Type,
+) -> BTreeMap<String, TypedDictField> {
+    fields
+        .into_iter()
+        .map(|(key, field)| (key, TypedDictField { ty: f(field.ty), ..field }))
+        .collect()
+}
+
+/// A name without the `typing.` or `builtins.` module it may be qualified
+/// with.
+fn unqualified(name: &str) -> &str {
+    name.strip_prefix("typing.")
+        .or_else(|| name.strip_prefix("builtins."))
+        .unwrap_or(name)
+}
+
+/// The most members a union inferred by joining types keeps before it is
+/// widened with [`Type::capped`].
+pub const MAX_UNION_MEMBERS: usize = 5;
+
 /// Type environment that maps variable names to their types.
-#[derive(Debug, Default, Clone)]
+///
+/// It also holds the type aliases defined in each scope, such as
+/// `Pairs = List[Tuple[str, int]]`. Annotations keep naming an alias as
+/// [`Type::Named`], so messages show the name; [`TypeEnv::expand`]
+/// replaces it with what it stands for where types are compared.
+///
+/// Other modules are kept in namespaces of their own, registered with
+/// [`TypeEnv::define_module`], so that two modules may bind the same name.
+/// A dotted name such as `pkg.mod.Widget` is looked up in the module with
+/// the longest matching name, here `pkg.mod` or else `pkg`.
+///
+/// An environment can be saved with [`TypeEnv::save`] and read back with
+/// [`TypeEnv::load`], so what one run inferred can be reused by the next.
+///
+/// Types are held as [`Interned`] values, so a clone of an environment
+/// shares them with the original instead of copying each one.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct TypeEnv {
-    bindings: HashMap<String, Type>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    bindings: HashMap<String, Interned>,
+    /// Type aliases defined in the current scope, by name.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    aliases: HashMap<String, Interned>,
+    /// Namespaces of the modules the current scope sees, by dotted name.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    modules: HashMap<String, TypeEnv>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     parent: Option<Box<TypeEnv>>,
+    /// Cap applied to the unions made by [`TypeEnv::join`], if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_union_members: Option<usize>,
 }
 
 impl TypeEnv {
     /// Creates a new empty type environment.
     pub fn new() -> Self {
-        Self { bindings: HashMap::new(), parent: None }
+        Self {
+            bindings: HashMap::new(),
+            aliases: HashMap::new(),
+            modules: HashMap::new(),
+            parent: None,
+            max_union_members: None,
+        }
     }
 
-    /// Creates a new nested type environment.
+    /// Creates a new nested type environment, with the parent's union cap.
     pub fn nested(env: TypeEnv) -> Self {
-        Self { bindings: HashMap::new(), parent: Some(Box::new(env)) }
+        let max_union_members = env.max_union_members;
+        Self {
+            bindings: HashMap::new(),
+            aliases: HashMap::new(),
+            modules: HashMap::new(),
+            parent: Some(Box::new(env)),
+            max_union_members,
+        }
     }
 
-    /// Looks up a variable in the environment.
+    /// Reads an environment written by [`TypeEnv::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        read_json(path.as_ref(), "type environment")
+    }
+
+    /// Writes the environment, with its parents and modules, as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_json(self, path.as_ref())
+    }
+
+    /// Caps the unions [`TypeEnv::join`] makes at `max` members, widening
+    /// larger ones with [`Type::capped`].
+    pub fn with_max_union_members(mut self, max: usize) -> Self {
+        self.max_union_members = Some(max);
+        self
+    }
+
+    /// Looks up a variable in the environment. A dotted name that isn't
+    /// bound itself is looked up in the module it is qualified with.
     pub fn lookup(&self, name: &str) -> Option<&Type> {
+        self.lookup_interned(name).map(|ty| &**ty)
+    }
+
+    /// [`TypeEnv::lookup`], returning the shared type.
+    fn lookup_interned(&self, name: &str) -> Option<&Interned> {
         self.bindings
             .get(name)
-            .or_else(|| self.parent.as_ref().and_then(|parent| parent.lookup(name)))
+            .or_else(|| {
+                self.parent
+                    .as_ref()
+                    .and_then(|parent| parent.lookup_interned(name))
+            })
+            .or_else(|| self.qualified(name, TypeEnv::lookup_interned))
     }
 
     /// Binds a variable to a type in the current scope.
     pub fn bind(&mut self, name: String, ty: Type) -> Option<Type> {
-        self.bindings.insert(name, ty)
+        self.bind_interned(name, Interned::new(ty))
+    }
+
+    /// Binds a variable to a type shared with other holders, such as one
+    /// handed out by a [`TypeInterner`].
+    pub fn bind_interned(&mut self, name: String, ty: Interned) -> Option<Type> {
+        self.bindings.insert(name, ty).map(Interned::into_type)
     }
 
     /// Returns the parent environment, if any.
     pub fn parent(&self) -> Option<&TypeEnv> {
         self.parent.as_deref()
     }
+
+    /// Iterates over the bindings of the current scope, excluding parents.
+    pub fn bindings(&self) -> impl Iterator<Item = (&String, &Type)> {
+        self.bindings.iter().map(|(name, ty)| (name, &**ty))
+    }
+
+    /// Defines a type alias in the current scope, which `Named(name)` then
+    /// stands for. The alias may refer to itself.
+    pub fn define_alias(&mut self, name: String, ty: Type) -> Option<Type> {
+        self.aliases
+            .insert(name, Interned::new(ty))
+            .map(Interned::into_type)
+    }
+
+    /// Looks up a type alias in the environment, in the module a dotted
+    /// name is qualified with as [`TypeEnv::lookup`] does.
+    pub fn alias(&self, name: &str) -> Option<&Type> {
+        self.aliases
+            .get(name)
+            .map(|ty| &**ty)
+            .or_else(|| self.parent.as_ref().and_then(|parent| parent.alias(name)))
+            .or_else(|| self.qualified(name, TypeEnv::alias))
+    }
+
+    /// Registers the namespace of the module with the dotted name `name`,
+    /// such as `pkg.mod`, replacing the one registered before.
+    pub fn define_module(&mut self, name: impl Into<String>, env: TypeEnv) -> Option<TypeEnv> {
+        self.modules.insert(name.into(), env)
+    }
+
+    /// Looks up the namespace of a module in the environment.
+    pub fn module(&self, name: &str) -> Option<&TypeEnv> {
+        self.modules
+            .get(name)
+            .or_else(|| self.parent.as_ref().and_then(|parent| parent.module(name)))
+    }
+
+    /// `find` applied to the rest of the dotted name `name` in the module
+    /// that qualifies it, trying the longest module name first.
+    fn qualified<'e, T: ?Sized>(
+        &'e self,
+        name: &str,
+        find: impl Fn(&'e TypeEnv, &str) -> Option<&'e T>,
+    ) -> Option<&'e T> {
+        name.match_indices('.').rev().find_map(|(dot, _)| {
+            let module = self.module(&name[..dot])?;
+            find(module, &name[dot + 1..])
+        })
+    }
+
+    /// Iterates over the type aliases of the current scope, excluding
+    /// parents.
+    pub fn aliases(&self) -> impl Iterator<Item = (&String, &Type)> {
+        self.aliases.iter().map(|(name, ty)| (name, &**ty))
+    }
+
+    /// `ty` with the type aliases the environment sees replaced by the
+    /// types they stand for, including aliases nested in those. A
+    /// recursive alias is expanded once, leaving its references to itself
+    /// as names.
+    pub fn expand(&self, ty: &Type) -> Type {
+        self.expand_in(ty, &mut Vec::new())
+    }
+
+    /// [`TypeEnv::expand`], leaving the aliases in `expanding` as names.
+    fn expand_in(&self, ty: &Type, expanding: &mut Vec<String>) -> Type {
+        match ty {
+            Type::Named(name) if !expanding.contains(name) => match self.alias(name) {
+                Some(alias) => {
+                    expanding.push(name.clone());
+                    let expanded = self.expand_in(alias, expanding);
+                    expanding.pop();
+                    expanded
+                },
+                None => ty.clone(),
+            },
+            Type::List(inner) => Type::List(Box::new(self.expand_in(inner, expanding))),
+            Type::Set(inner) => Type::Set(Box::new(self.expand_in(inner, expanding))),
+            Type::TypeOf(inner) => Type::TypeOf(Box::new(self.expand_in(inner, expanding))),
+            Type::Dict(k, v) => Type::Dict(
+                Box::new(self.expand_in(k, expanding)),
+                Box::new(self.expand_in(v, expanding)),
+            ),
+            Type::Tuple(items) => {
+                Type::Tuple(items.iter().map(|t| self.expand_in(t, expanding)).collect())
+            },
+            Type::Union(members) => Type::union_of(
+                members
+                    .iter()
+                    .map(|t| self.expand_in(t, expanding))
+                    .collect(),
+            ),
+            Type::Intersection(members) => Type::intersection_of(
+                members
+                    .iter()
+                    .map(|t| self.expand_in(t, expanding))
+                    .collect(),
+            ),
+            Type::Function { params, returns } => Type::Function {
+                params: params
+                    .iter()
+                    .map(|p| p.clone().map_type(|t| self.expand_in(&t, expanding)))
+                    .collect(),
+                returns: Box::new(self.expand_in(returns, expanding)),
+            },
+            Type::Generic { name, params } => Type::Generic {
+                name: name.clone(),
+                params: params
+                    .iter()
+                    .map(|t| self.expand_in(t, expanding))
+                    .collect(),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    /// Merges the current-scope bindings of another path through the same
+    /// scope, such as the other branch of an `if`. Names bound on both
+    /// paths get the [`Type::join`] of their types. A name bound on only
+    /// one path is joined with what the other path sees in its parents,
+    /// and keeps its type if the other path can't see it at all. A name
+    /// whose type the join leaves unchanged keeps sharing it.
+    pub fn join(&mut self, other: &TypeEnv) {
+        let names: BTreeSet<&String> = self.bindings.keys().chain(other.bindings.keys()).collect();
+        let mut joined = HashMap::new();
+        for name in names {
+            let ty = match (self.lookup_interned(name), other.lookup_interned(name)) {
+                (Some(own), Some(theirs)) => {
+                    let ty = match self.max_union_members {
+                        Some(max) => (**own).clone().join((**theirs).clone()).capped(max),
+                        None => (**own).clone().join((**theirs).clone()),
+                    };
+                    if ty == **own {
+                        own.clone()
+                    } else {
+                        Interned::new(ty)
+                    }
+                },
+                (Some(ty), None) | (None, Some(ty)) => ty.clone(),
+                (None, None) => continue,
+            };
+            joined.insert(name.clone(), ty);
+        }
+        self.bindings = joined;
+        for (name, ty) in &other.aliases {
+            self.aliases
+                .entry(name.clone())
+                .or_insert_with(|| ty.clone());
+        }
+        for (name, module) in &other.modules {
+            self.modules
+                .entry(name.clone())
+                .or_insert_with(|| module.clone());
+        }
+    }
+}
+
+/// Reads a value saved as JSON, naming it `what` if the file doesn't hold
+/// one.
+fn read_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let text = fs::read_to_string(path).map_err(|e| Error::from(e).with_path(path))?;
+    serde_json::from_str(&text)
+        .map_err(|e| Error::parser_error(format!("Invalid {}: {}", what, e)).with_path(path))
+}
+
+/// Writes a value as pretty-printed JSON.
+fn write_json<T: Serialize>(value: &T, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| Error::Other(e.to_string()))?;
+    fs::write(path, json + "\n").map_err(|e| Error::from(e).with_path(path))
 }
 
 #[cfg(test)]
@@ -358,8 +1487,7 @@ mod tests {
             "Dict[str, int]"
         );
         assert_eq!(
-            Type::Function { params: vec![Type::Int, Type::Str], returns: Box::new(Type::Bool) }
-                .to_string(),
+            Type::callable(vec![Type::Int, Type::Str], Type::Bool).to_string(),
             "Callable[[int, str], bool]"
         );
     }
@@ -377,5 +1505,366 @@ mod tests {
 
         assert_eq!(inner_env.lookup("x"), Some(&Type::Int));
         assert_eq!(inner_env.lookup("y"), Some(&Type::Str));
+
+        let mut branch = inner_env.clone();
+        branch.bind("y".to_string(), Type::None);
+        branch.bind("z".to_string(), Type::Bool);
+        inner_env.join(&branch);
+        assert_eq!(inner_env.lookup("y").unwrap().to_string(), "Optional[str]");
+        assert_eq!(inner_env.lookup("z"), Some(&Type::Bool));
+        assert_eq!(Type::Int.join(Type::Unknown), Type::Unknown);
+    }
+
+    #[test]
+    fn test_cloned_environments_share_types() {
+        let mut env = TypeEnv::new();
+        env.bind("x".to_string(), Type::List(Box::new(Type::optional(Type::Str))));
+        let mut branch = env.clone();
+        assert!(std::ptr::eq(env.lookup("x").unwrap(), branch.lookup("x").unwrap()));
+
+        branch.bind("y".to_string(), Type::Int);
+        env.join(&branch);
+        assert!(std::ptr::eq(env.lookup("x").unwrap(), branch.lookup("x").unwrap()));
+        assert_eq!(
+            env.bind("x".to_string(), Type::Str).unwrap().to_string(),
+            "List[Optional[str]]"
+        );
+    }
+
+    #[test]
+    fn test_module_namespaces() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let mut models = TypeEnv::new();
+        models.bind("Widget".to_string(), Type::class_object(named("pkg.models.Widget")));
+        models.define_alias("Ids".to_string(), Type::List(Box::new(Type::Int)));
+        let mut views = TypeEnv::new();
+        views.bind("Widget".to_string(), Type::class_object(named("pkg.views.Widget")));
+        let mut pkg = TypeEnv::new();
+        pkg.bind("VERSION".to_string(), Type::Str);
+        pkg.define_module("views", views);
+
+        let mut env = TypeEnv::new();
+        env.bind("Widget".to_string(), Type::Int);
+        env.define_module("pkg", pkg);
+        env.define_module("pkg.models", models);
+        let inner = TypeEnv::nested(env);
+
+        assert_eq!(inner.lookup("Widget"), Some(&Type::Int));
+        assert_eq!(
+            inner.lookup("pkg.models.Widget").unwrap().to_string(),
+            "Type[pkg.models.Widget]"
+        );
+        assert_eq!(inner.lookup("pkg.views.Widget").unwrap().to_string(), "Type[pkg.views.Widget]");
+        assert_eq!(inner.lookup("pkg.VERSION"), Some(&Type::Str));
+        assert_eq!(inner.lookup("pkg.models.VERSION"), None);
+        assert_eq!(inner.lookup("other.Widget"), None);
+        assert_eq!(inner.expand(&named("pkg.models.Ids")).to_string(), "List[int]");
+        assert_eq!(inner.module("pkg.models").and_then(|m| m.lookup("Ids")), None);
+    }
+
+    #[test]
+    fn test_saved_environments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("env.json");
+        let mut module = TypeEnv::new();
+        module.define_alias("Ids".to_string(), Type::List(Box::new(Type::Int)));
+        let mut env = TypeEnv::new().with_max_union_members(4);
+        env.bind("count".to_string(), Type::optional(Type::Int));
+        env.define_module("pkg", module);
+        let mut inner = TypeEnv::nested(env);
+        inner.bind("f".to_string(), Type::callable(vec![Type::Str], Type::None));
+        inner.save(&path).unwrap();
+
+        let loaded = TypeEnv::load(&path).unwrap();
+        assert_eq!(loaded.lookup("count"), Some(&Type::optional(Type::Int)));
+        assert_eq!(loaded.lookup("f").unwrap().to_string(), "Callable[[str], None]");
+        assert_eq!(
+            loaded
+                .expand(&Type::Named("pkg.Ids".to_string()))
+                .to_string(),
+            "List[int]"
+        );
+        assert_eq!(loaded.max_union_members, Some(4));
+        assert!(loaded.parent().is_some());
+
+        fs::write(&path, "[1]").unwrap();
+        assert!(TypeEnv::load(&path)
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid type environment"));
+    }
+
+    #[test]
+    fn test_type_aliases() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let mut env = TypeEnv::new();
+        env.define_alias(
+            "Scores".to_string(),
+            Type::Dict(Box::new(Type::Str), Box::new(Type::List(Box::new(Type::Int)))),
+        );
+        env.define_alias("Table".to_string(), Type::List(Box::new(named("Scores"))));
+        env.define_alias(
+            "Tree".to_string(),
+            Type::union_of(vec![Type::Int, Type::List(Box::new(named("Tree")))]),
+        );
+        let inner = TypeEnv::nested(env);
+
+        assert_eq!(inner.alias("Scores").unwrap().to_string(), "Dict[str, List[int]]");
+        assert_eq!(inner.expand(&named("Table")).to_string(), "List[Dict[str, List[int]]]");
+        assert_eq!(
+            inner.expand(&Type::optional(named("Scores"))).to_string(),
+            "Optional[Dict[str, List[int]]]"
+        );
+        assert_eq!(inner.expand(&named("Tree")).to_string(), "int | List[Tree]");
+        assert_eq!(inner.expand(&named("Node")), named("Node"));
+        assert_eq!(named("Table").to_string(), "Table");
+    }
+
+    #[test]
+    fn test_intersection_types() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let both = Type::intersection_of(vec![named("B"), named("A"), named("B")]);
+        assert_eq!(both.to_string(), "A & B");
+        assert_eq!(
+            Type::intersection_of(vec![both.clone(), named("C"), Type::Any]).to_string(),
+            "A & B & C"
+        );
+        assert_eq!(Type::intersection_of(vec![named("A"), named("object")]), named("A"));
+        assert_eq!(Type::intersection_of(vec![]), Type::Any);
+        let distributed =
+            Type::intersection_of(vec![Type::union_of(vec![named("A"), named("B")]), named("C")]);
+        assert_eq!(distributed.to_string(), "A & C | B & C");
+        let callable = Type::Function {
+            params: vec![Param {
+                name: Some("x".to_string()),
+                default: Some("0".to_string()),
+                ..Param::positional(Type::Int)
+            }],
+            returns: Box::new(Type::None),
+        };
+        assert_eq!(
+            Type::Intersection(vec![callable, named("A")]).to_string(),
+            "((x: int = 0, /) -> None) & A"
+        );
+
+        assert_eq!(named("A").accepts(&both), None);
+        assert_eq!(
+            Type::Int.accepts(&Type::intersection_of(vec![Type::Bool, named("A")])),
+            Some(true)
+        );
+        assert_eq!(both.accepts(&Type::Int), None);
+        assert_eq!(
+            Type::intersection_of(vec![Type::Int, named("A")]).accepts(&Type::Str),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_assignability() {
+        let named = |name: &str| Type::Named(name.to_string());
+        let list = |ty: Type| Type::List(Box::new(ty));
+        let mut classes = ClassHierarchy::new();
+        classes.add_class("Animal", vec!["object".to_string()]);
+        classes.add_class("Dog", vec!["Animal".to_string()]);
+        let assignable = |a: &Type, b: &Type| a.is_assignable_to(b, &classes);
+
+        assert!(assignable(&Type::Bool, &Type::Float));
+        assert!(!assignable(&Type::Float, &Type::Int));
+        assert!(assignable(&named("Dog"), &named("Animal")));
+        assert!(!assignable(&named("Animal"), &named("Dog")));
+        assert!(assignable(&named("Dog"), &Type::optional(named("Animal"))));
+        assert!(!assignable(&Type::optional(named("Dog")), &named("Animal")));
+        assert!(assignable(&Type::Any, &named("Dog")) && assignable(&named("Dog"), &Type::Any));
+        assert!(!assignable(&list(named("Dog")), &list(named("Animal"))));
+        assert!(assignable(&Type::Tuple(vec![named("Dog")]), &Type::Tuple(vec![named("Animal")])));
+        assert!(assignable(&Type::Literal("3".to_string()), &Type::Float));
+        assert!(!assignable(&named("Cat"), &named("Animal")));
+        assert!(assignable(&named("Cat"), &named("object")));
+        assert_eq!(classes.bases("Dog"), Some(&["Animal".to_string()][..]));
+    }
+
+    #[test]
+    fn test_accepts() {
+        let list = |ty: Type| Type::List(Box::new(ty));
+        let optional_int = Type::union_of(vec![Type::Int, Type::None]);
+        assert_eq!(Type::Float.accepts(&Type::Bool), Some(true));
+        assert_eq!(Type::Int.accepts(&Type::Float), Some(false));
+        assert_eq!(Type::Str.accepts(&Type::None), Some(false));
+        assert_eq!(optional_int.accepts(&Type::None), Some(true));
+        assert_eq!(Type::Int.accepts(&optional_int), Some(false));
+        assert_eq!(list(Type::Float).accepts(&list(Type::Int)), Some(true));
+        assert_eq!(list(Type::Str).accepts(&list(Type::Int)), Some(false));
+        assert_eq!(list(Type::Str).accepts(&list(Type::Any)), Some(true));
+        assert_eq!(Type::Str.accepts(&Type::Named("Name".to_string())), None);
+        assert_eq!(Type::Named("Node".to_string()).accepts(&Type::Int), None);
+        assert_eq!(Type::Str.accepts(&Type::Unknown), None);
+        let class = |ty: Type| Type::class_object(ty);
+        assert_eq!(class(Type::Float).accepts(&class(Type::Int)), Some(true));
+        assert_eq!(class(Type::Int).accepts(&class(Type::Str)), Some(false));
+        assert_eq!(class(Type::Int).accepts(&Type::Int), Some(false));
+        assert_eq!(
+            class(Type::Named("Base".to_string())).accepts(&Type::Named("C".to_string())),
+            None
+        );
+
+        let literal = |value: &str| Type::Literal(value.to_string());
+        let mode = Type::union_of(vec![literal("'r'"), literal("'w'")]);
+        assert_eq!(mode.accepts(&literal("'r'")), Some(true));
+        assert_eq!(mode.accepts(&literal("'x'")), Some(false));
+        assert_eq!(mode.accepts(&Type::Str), None);
+        assert_eq!(mode.accepts(&Type::Int), Some(false));
+        assert_eq!(Type::Float.accepts(&literal("-1")), Some(true));
+        assert_eq!(Type::Str.accepts(&literal("True")), Some(false));
+        assert_eq!(literal("Color.RED").accepts(&literal("Color.BLUE")), None);
+        assert_eq!(mode.widened(), Type::Str);
+    }
+
+    #[test]
+    fn test_optional_types() {
+        let optional = Type::optional(Type::Str);
+        assert_eq!(optional, Type::union_of(vec![Type::None, Type::Str]));
+        assert!(optional.is_optional());
+        assert_eq!(optional.unwrap_optional(), Type::Str);
+        assert_eq!(optional.to_string(), "Optional[str]");
+        let wider = Type::optional(Type::union_of(vec![Type::Int, Type::Str]));
+        assert_eq!(wider.to_string(), "None | int | str");
+        assert_eq!(wider.unwrap_optional(), Type::union_of(vec![Type::Int, Type::Str]));
+        assert_eq!(Type::optional(Type::None), Type::None);
+        assert!(!Type::None.is_optional() && !Type::Int.is_optional());
+        assert_eq!(Type::None.unwrap_optional(), Type::None);
+    }
+
+    #[test]
+    fn test_normalized_types() {
+        let nested = Type::Union(vec![
+            Type::Int,
+            Type::Union(vec![Type::Str, Type::Union(vec![Type::Int, Type::None])]),
+        ]);
+        assert_eq!(nested.normalize(), Type::union_of(vec![Type::Int, Type::Str, Type::None]));
+        let repeated = Type::Union(vec![Type::Int, Type::Int]);
+        assert_eq!(Type::List(Box::new(repeated)).normalize(), Type::List(Box::new(Type::Int)));
+        let with_any = Type::Union(vec![Type::Str, Type::Any, Type::None]);
+        assert_eq!(Type::Tuple(vec![with_any]).normalize(), Type::Tuple(vec![Type::Any]));
+
+        let generic =
+            |name: &str, params: Vec<Type>| Type::Generic { name: name.to_string(), params };
+        let named = |name: &str| Type::Named(name.to_string());
+        let list_of_int = Type::List(Box::new(Type::Int));
+        for spelling in ["typing.List", "list", "List"] {
+            assert_eq!(generic(spelling, vec![named("int")]).normalize(), list_of_int);
+        }
+        let twice = Type::optional(generic("Optional", vec![Type::Str]));
+        assert_eq!(twice.normalize(), Type::optional(Type::Str));
+        assert_eq!(
+            generic("typing.Union", vec![Type::Int, named("NoneType"), Type::Int]).normalize(),
+            Type::optional(Type::Int)
+        );
+        assert_eq!(
+            generic("Dict", vec![Type::Str, generic("Union", vec![Type::Any, Type::Int])])
+                .normalize(),
+            Type::Dict(Box::new(Type::Str), Box::new(Type::Any))
+        );
+        assert_eq!(generic("type", vec![named("C")]).normalize(), Type::class_object(named("C")));
+        assert_eq!(
+            generic("Tuple", vec![Type::Int, named("...")]).normalize(),
+            Type::variadic_tuple(Type::Int)
+        );
+        assert_eq!(
+            generic("Tuple", vec![Type::Int, Type::Str])
+                .normalize()
+                .to_string(),
+            "Tuple[int, str]"
+        );
+        assert_eq!(
+            generic("typing.Iterable", vec![Type::Int])
+                .normalize()
+                .to_string(),
+            "Iterable[int]"
+        );
+        assert_eq!(named("builtins.str").normalize(), Type::Str);
+        assert_eq!(named("Widget").normalize(), named("Widget"));
+    }
+
+    #[test]
+    fn test_annotations_for_versions() {
+        let (py38, py39, py310) =
+            (PythonVersion::new(3, 8), PythonVersion::new(3, 9), PythonVersion::new(3, 10));
+        let list = Type::List(Box::new(Type::optional(Type::Int)));
+        assert_eq!(list.to_annotation(py38), "List[Optional[int]]");
+        assert_eq!(list.to_annotation(py39), "list[Optional[int]]");
+        assert_eq!(list.to_annotation(py310), "list[int | None]");
+        let union = Type::union_of(vec![Type::None, Type::Int, Type::Str]);
+        assert_eq!(union.to_annotation(py38), "Optional[Union[int, str]]");
+        assert_eq!(union.to_annotation(py310), "int | str | None");
+        let dict = Type::Dict(Box::new(Type::Str), Box::new(Type::Tuple(vec![])));
+        assert_eq!(dict.to_annotation(py38), "Dict[str, Tuple[()]]");
+        assert_eq!(dict.to_annotation(py39), "dict[str, tuple[()]]");
+        let class = Type::class_object(Type::Named("Widget".to_string()));
+        assert_eq!(class.to_annotation(py38), "Type[Widget]");
+        assert_eq!(class.to_annotation(py39), "type[Widget]");
+        let callable = Type::callable(vec![Type::Int, Type::Set(Box::new(Type::Str))], Type::None);
+        assert_eq!(callable.to_annotation(py38), "Callable[[int, Set[str]], None]");
+        assert_eq!(Type::Literal("'a'".to_string()).to_annotation(py38), "Literal['a']");
+        let both =
+            Type::intersection_of(vec![Type::Named("A".to_string()), Type::Named("B".to_string())]);
+        assert_eq!(both.to_annotation(py310), "Any");
+    }
+
+    #[test]
+    fn test_capped_unions() {
+        let literal = |value: &str| Type::Literal(value.to_string());
+        let small = Type::union_of(vec![Type::Int, Type::Str]);
+        assert_eq!(small.clone().capped(2), small);
+        let literals =
+            Type::union_of(vec![literal("'r'"), literal("'w'"), literal("1"), Type::None]);
+        assert_eq!(literals.capped(3), Type::union_of(vec![Type::Str, Type::Int, Type::None]));
+        let numbers = Type::union_of(vec![Type::Bool, Type::Int, Type::Float, Type::Str]);
+        assert_eq!(numbers.clone().capped(2), Type::union_of(vec![Type::Float, Type::Str]));
+        assert_eq!(numbers.clone().capped(1), Type::Any);
+        assert_eq!(
+            Type::List(Box::new(numbers)).capped(2),
+            Type::List(Box::new(Type::union_of(vec![Type::Float, Type::Str])))
+        );
+
+        let mut env = TypeEnv::new().with_max_union_members(2);
+        env.bind("x".to_string(), Type::union_of(vec![Type::Int, Type::Str]));
+        let mut other = TypeEnv::nested(TypeEnv::new());
+        other.bind("x".to_string(), Type::Bytes);
+        env.join(&other);
+        assert_eq!(env.lookup("x"), Some(&Type::Any));
+    }
+
+    #[test]
+    fn test_awaited_types() {
+        let coroutine = Type::coroutine(Type::Int);
+        assert_eq!(coroutine.to_string(), "Coroutine[Any, Any, int]");
+        assert_eq!(coroutine.awaited(), Type::Int);
+        let task = Type::Generic { name: "asyncio.Task".to_string(), params: vec![Type::Str] };
+        assert_eq!(task.awaited(), Type::Str);
+        assert_eq!(Type::Int.awaited(), Type::Unknown);
+        assert_eq!(Type::awaitable(Type::Bytes).awaited(), Type::Bytes);
+        assert_eq!(coroutine.protocol_base(), Some(Type::awaitable(Type::Int)));
+    }
+
+    #[test]
+    fn test_iterator_forms() {
+        let generator = Type::generator(Type::Int, Type::None, Type::Str);
+        assert_eq!(generator.to_string(), "Generator[int, None, str]");
+        assert_eq!(generator.iterated(), Type::Int);
+        assert_eq!(generator.protocol_base(), Some(Type::iterator(Type::Int)));
+        let iterable = Type::iterator(Type::Int).protocol_base().unwrap();
+        assert_eq!(iterable.to_string(), "Iterable[int]");
+        assert_eq!(iterable.iterated(), Type::Int);
+        assert_eq!(iterable.protocol_base(), None);
+        assert_eq!(Type::Dict(Box::new(Type::Str), Box::new(Type::Int)).iterated(), Type::Str);
+
+        let stream = Type::async_iterator(Type::Bytes);
+        assert_eq!(stream.async_iterated(), Type::Bytes);
+        assert_eq!(stream.iterated(), Type::Unknown);
+        assert_eq!(Type::List(Box::new(Type::Int)).async_iterated(), Type::Unknown);
+        let async_generator =
+            Type::Generic { name: "typing.AsyncGenerator".to_string(), params: vec![Type::Str] };
+        assert_eq!(async_generator.async_iterated(), Type::Str);
+        assert_eq!(async_generator.protocol_base(), Some(Type::async_iterator(Type::Str)));
     }
 }
@@ -3,7 +3,11 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use omnitype::analyzer::{AnalysisResult, Analyzer, Diagnostic};
+use omnitype::analyzer::{AnalysisResult, Analyzer};
+use omnitype::config::Config;
+use omnitype::diagnostics::{Diagnostic, Severity};
+use omnitype::parser::Language;
+use omnitype::utils::SourceWalker;
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
@@ -293,6 +297,10 @@ impl App {
                 Line::from(format!("Path: {}", res.path)),
                 Line::from(format!("Functions: {}", res.function_count)),
                 Line::from(format!("Classes: {}", res.class_count)),
+                Line::from(match res.metrics.typedness() {
+                    Some(typed) => format!("Typed: {:.0}%", typed),
+                    None => "Typed: -".to_string(),
+                }),
                 Line::from(""),
                 Line::from(Span::styled(
                     "Diagnostics",
@@ -305,14 +313,22 @@ impl App {
                 lines.push(Line::from("(none)"));
             } else {
                 for d in &res.diagnostics {
-                    lines.push(Line::from(format!(
-                        "{}:{}:{}: {} {}",
-                        d.path,
-                        d.line + 1,
-                        d.column + 1,
-                        d.severity,
-                        d.message
-                    )));
+                    lines.push(Line::styled(d.to_string(), severity_style(d.severity)));
+                }
+            }
+            if !res.classes.is_empty() {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    "Class Attributes",
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                for (name, class) in &res.classes {
+                    lines.push(Line::from(name.clone()));
+                    for (attribute, ty) in &class.attributes {
+                        lines.push(Line::from(format!("  {}: {}", attribute, ty)));
+                    }
                 }
             }
             Text::from(lines)
@@ -332,17 +348,7 @@ impl App {
         } else {
             self.errors
                 .iter()
-                .map(|d| {
-                    let text = format!(
-                        "{}:{}:{}: {} {}",
-                        d.path,
-                        d.line + 1,
-                        d.column + 1,
-                        d.severity,
-                        d.message
-                    );
-                    ListItem::new(text)
-                })
+                .map(|d| ListItem::new(d.to_string()).style(severity_style(d.severity)))
                 .collect()
         };
 
@@ -415,15 +421,11 @@ impl App {
                                 self.file_list_state.select(Some(0));
                             }
                         },
-                        KeyCode::Home if self.selected_tab == 0 => {
-                            if !self.files.is_empty() {
-                                self.file_list_state.select(Some(0));
-                            }
+                        KeyCode::Home if self.selected_tab == 0 && !self.files.is_empty() => {
+                            self.file_list_state.select(Some(0));
                         },
-                        KeyCode::End if self.selected_tab == 0 => {
-                            if !self.files.is_empty() {
-                                self.file_list_state.select(Some(self.files.len() - 1));
-                            }
+                        KeyCode::End if self.selected_tab == 0 && !self.files.is_empty() => {
+                            self.file_list_state.select(Some(self.files.len() - 1));
                         },
                         KeyCode::PageUp if self.selected_tab == 0 => {
                             if let Some(selected) = self.file_list_state.selected() {
@@ -454,9 +456,10 @@ impl App {
                                                     "Opened file: {}",
                                                     path_buf.display()
                                                 ));
-                                                // If it's a Python file, analyze it immediately
-                                                if path_buf.extension().and_then(|e| e.to_str())
-                                                    == Some("py")
+                                                // If it's a supported source file, analyze it
+                                                // immediately
+                                                if Language::from_path(&path_buf)
+                                                    .is_some_and(Language::is_enabled)
                                                 {
                                                     match self.run_analysis(&path_buf) {
                                                         Ok(_) => self
@@ -521,7 +524,7 @@ impl App {
                                             ));
                                         } else {
                                             // Jump editor to the diagnostic line
-                                            self.editor_scroll = d.line as u16;
+                                            self.editor_scroll = d.span.start.line as u16;
                                             self.selected_tab = 4; // Editor
                                             self.logs.push(format!(
                                                 "Opened from error: {}",
@@ -546,7 +549,7 @@ impl App {
                                 if let Some(path) = self.files.get(selected) {
                                     let p = path.clone();
                                     if p.is_file()
-                                        && p.extension().and_then(|e| e.to_str()) == Some("py")
+                                        && Language::from_path(&p).is_some_and(Language::is_enabled)
                                     {
                                         match self.run_analysis(&p) {
                                             Ok(_) => {
@@ -560,7 +563,7 @@ impl App {
                                         }
                                     } else {
                                         self.logs.push(
-                                            "Select a Python (.py) file to analyze".to_string(),
+                                            "Select a supported source file to analyze".to_string(),
                                         );
                                     }
                                 }
@@ -605,8 +608,11 @@ impl App {
     }
 
     fn run_analysis(&mut self, path: &Path) -> Result<(), String> {
-        match Analyzer::analyze_python_file(path) {
-            Ok(res) => {
+        match Analyzer::analyze_file(path) {
+            Ok(mut res) => {
+                let config = Config::discover(path).map_err(|e| e.to_string())?;
+                res.apply_unsolved(config.unsolved);
+                config.finalize(&mut res.diagnostics);
                 self.analysis_result = Some(res);
                 self.errors = self
                     .analysis_result
@@ -623,34 +629,23 @@ impl App {
     }
 }
 
-fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
-    let mut entries = fs::read_dir(dir)?
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            let file_name = path.file_name()?.to_string_lossy();
-
-            // Skip hidden files and directories
-            if file_name.starts_with('.') {
-                return None;
-            }
-
-            Some((path, entry.file_type().ok()?.is_dir()))
-        })
-        .collect::<Vec<_>>();
-
-    // Sort directories first, then by name
-    entries.sort_by(|(a_path, a_is_dir), (b_path, b_is_dir)| {
-        // Directories first
-        match (a_is_dir, b_is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a_path
-                .file_name()
-                .unwrap_or_default()
-                .cmp(b_path.file_name().unwrap_or_default()),
-        }
-    });
+/// Colors diagnostics by their finalized severity.
+fn severity_style(severity: Severity) -> Style {
+    let color = match severity {
+        Severity::Error => Color::Red,
+        Severity::Warning => Color::Yellow,
+        Severity::Info => Color::Cyan,
+        Severity::Hint => Color::Gray,
+    };
+    Style::default().fg(color)
+}
 
-    Ok(entries.into_iter().map(|(path, _)| path).collect())
+fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    // Share ignore rules with `check` and `fix`; the config is looked up from
+    // the directory being browsed.
+    let excludes = Config::discover(dir).map(|c| c.exclude).unwrap_or_default();
+    SourceWalker::new(dir)
+        .exclude(excludes)
+        .list_dir(dir)
+        .map_err(io::Error::other)
 }
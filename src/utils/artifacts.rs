@@ -0,0 +1,166 @@
+//! Scratch workspaces for files omnitype generates while it runs.
+//!
+//! Each run of the tracer gets its own directory under
+//! `<cache>/artifacts/`, holding the instrumented script, the raw trace
+//! JSON, and a scratch directory the traced program uses as `TMPDIR`. The
+//! directory is removed when the workspace is dropped unless it was asked
+//! to keep its artifacts for debugging. Workspaces left behind by crashed
+//! runs are pruned once they are older than [`STALE_AFTER`].
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tempfile::TempDir;
+
+use crate::error::{Error, Result};
+
+/// Age after which an abandoned workspace is deleted by the next run.
+pub const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Returns omnitype's cache directory: `$OMNITYPE_CACHE_DIR` if set, then
+/// `$XDG_CACHE_HOME/omnitype`, then the platform default (`~/.cache` on
+/// Unix, `%LOCALAPPDATA%` on Windows), falling back to the system
+/// temporary directory.
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("OMNITYPE_CACHE_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    let base = env::var_os("XDG_CACHE_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .unwrap_or_else(env::temp_dir);
+    base.join("omnitype")
+}
+
+/// A per-run directory for generated files.
+#[derive(Debug)]
+pub struct ArtifactWorkspace {
+    dir: Option<TempDir>,
+    path: PathBuf,
+    keep: bool,
+}
+
+impl ArtifactWorkspace {
+    /// Creates a fresh workspace under [`cache_dir`], named after `label`.
+    pub fn create(label: &str) -> Result<Self> {
+        Self::create_in(&cache_dir().join("artifacts"), label)
+    }
+
+    /// Creates a fresh workspace under `root`, first pruning stale ones.
+    pub fn create_in(root: &Path, label: &str) -> Result<Self> {
+        let failed = |e: std::io::Error| {
+            Error::Io(std::io::Error::new(
+                e.kind(),
+                format!("Failed to create artifact directory in {}: {}", root.display(), e),
+            ))
+        };
+        fs::create_dir_all(root).map_err(failed)?;
+        prune_stale(root, STALE_AFTER);
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("{}-", label))
+            .tempdir_in(root)
+            .map_err(failed)?;
+        let path = dir.path().to_path_buf();
+        Ok(Self { dir: Some(dir), path, keep: false })
+    }
+
+    /// Keeps the directory and its contents after the workspace is dropped.
+    pub fn keep_artifacts(mut self, keep: bool) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// The workspace directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes an artifact and returns its path.
+    pub fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> Result<PathBuf> {
+        let path = self.path.join(name);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Creates (if needed) and returns a scratch subdirectory.
+    pub fn scratch_dir(&self, name: &str) -> Result<PathBuf> {
+        let path = self.path.join(name);
+        fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+}
+
+impl Drop for ArtifactWorkspace {
+    fn drop(&mut self) {
+        if let Some(dir) = self.dir.take() {
+            if self.keep {
+                let path = dir.keep();
+                log::info!("Kept artifacts in {}", path.display());
+            } else if let Err(e) = dir.close() {
+                log::warn!("Failed to remove {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// Removes workspaces under `root` that haven't been modified for `max_age`.
+/// Failures are logged; another process may be removing the same entries.
+fn prune_stale(root: &Path, max_age: Duration) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let stale = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age >= max_age);
+        if stale && entry.path().is_dir() {
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                log::debug!("Failed to prune {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_workspace_cleanup_and_keep() {
+        let root = tempdir().unwrap();
+        let workspace = ArtifactWorkspace::create_in(root.path(), "trace").unwrap();
+        let script = workspace.write("instrumented.py", "print(1)\n").unwrap();
+        let scratch = workspace.scratch_dir("tmp").unwrap();
+        assert!(script.is_file() && scratch.is_dir());
+        let path = workspace.path().to_path_buf();
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("trace-"));
+        drop(workspace);
+        assert!(!path.exists());
+
+        let kept = ArtifactWorkspace::create_in(root.path(), "trace")
+            .unwrap()
+            .keep_artifacts(true);
+        let path = kept.path().to_path_buf();
+        drop(kept);
+        assert!(path.is_dir());
+
+        // A fresh workspace prunes nothing younger than the cutoff.
+        prune_stale(root.path(), STALE_AFTER);
+        assert!(path.is_dir());
+        prune_stale(root.path(), Duration::ZERO);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,40 @@
+//! Minimal CSV writing (RFC 4180) for tabular exports.
+
+/// Formats one CSV record, quoting fields that contain a comma, quote, or
+/// line break. The result ends with `\n`.
+pub fn csv_record<I, S>(fields: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut out = String::new();
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let field = field.as_ref();
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_quoting() {
+        assert_eq!(csv_record(["a", "b c", ""]), "a,b c,\n");
+        assert_eq!(
+            csv_record(["x,y", "say \"hi\"", "1\n2"]),
+            "\"x,y\",\"say \"\"hi\"\"\",\"1\n2\"\n"
+        );
+    }
+}
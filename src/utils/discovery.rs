@@ -0,0 +1,139 @@
+//! Filters applied while discovering source files.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// File name suffixes of well-known code generators (protobuf, gRPC).
+const GENERATED_SUFFIXES: &[&str] = &["_pb2.py", "_pb2.pyi", "_pb2_grpc.py", "_pb2_grpc.pyi"];
+
+/// Markers that identify a generated file when they appear near its top.
+const GENERATED_MARKERS: &[&str] =
+    &["@generated", "DO NOT EDIT", "Generated by the protocol buffer"];
+
+/// How many leading bytes are scanned for [`GENERATED_MARKERS`].
+const HEADER_BYTES: u64 = 1024;
+
+/// Limits and filters for source file discovery. The default accepts every
+/// file the language filter accepts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryOptions {
+    /// Skip files larger than this many bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<u64>,
+    /// Skip files that look generated, by name or by a header marker.
+    pub skip_generated: bool,
+    /// Only accept these extensions (without the dot), if set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<String>>,
+}
+
+impl DiscoveryOptions {
+    /// Creates options that accept every file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum file size in bytes.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Enables or disables skipping of generated files.
+    pub fn skip_generated(mut self, skip: bool) -> Self {
+        self.skip_generated = skip;
+        self
+    }
+
+    /// Restricts discovery to the given extensions.
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = Some(extensions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Returns true if no filter is set, so every file is accepted.
+    pub fn accepts_all(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns true if `path` passes every configured filter. Cheap checks
+    /// run first; the file header is only read when `skip_generated` is set.
+    pub fn accepts(&self, path: &Path) -> bool {
+        if let Some(allowed) = &self.extensions {
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            if !allowed.iter().any(|a| a.eq_ignore_ascii_case(ext)) {
+                return false;
+            }
+        }
+        if let Some(max) = self.max_file_size {
+            if fs::metadata(path).map_or(true, |m| m.len() > max) {
+                return false;
+            }
+        }
+        !(self.skip_generated && is_generated(path))
+    }
+}
+
+/// Returns true if the file is produced by a code generator.
+pub fn is_generated(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    if GENERATED_SUFFIXES
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+    {
+        return true;
+    }
+    let mut header = Vec::new();
+    let read = fs::File::open(path).and_then(|f| f.take(HEADER_BYTES).read_to_end(&mut header));
+    if read.is_err() {
+        return false;
+    }
+    let header = String::from_utf8_lossy(&header);
+    GENERATED_MARKERS
+        .iter()
+        .any(|marker| header.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discovery_filters() {
+        let dir = tempdir().unwrap();
+        let plain = dir.path().join("plain.py");
+        let big = dir.path().join("big.py");
+        let proto = dir.path().join("msg_pb2.py");
+        let marked = dir.path().join("marked.py");
+        fs::write(&plain, "x = 1\n").unwrap();
+        fs::write(&big, "x = 1\n".repeat(100)).unwrap();
+        fs::write(&proto, "").unwrap();
+        fs::write(&marked, "# @generated by tool\n").unwrap();
+
+        let options = DiscoveryOptions::new()
+            .max_file_size(100)
+            .skip_generated(true);
+        assert!(options.accepts(&plain));
+        assert!(!options.accepts(&big));
+        assert!(!options.accepts(&proto));
+        assert!(!options.accepts(&marked));
+
+        let stubs_only = DiscoveryOptions::new().extensions(["pyi"]);
+        assert!(!stubs_only.accepts(&plain));
+    }
+}
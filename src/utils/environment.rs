@@ -0,0 +1,251 @@
+//! Locating Python environments and the distributions installed in them.
+//!
+//! Discovery only inspects the filesystem; no interpreter is started.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::detect_python_version;
+use crate::error::{Error, Result};
+
+/// Directory names checked for a project-local virtualenv.
+const LOCAL_VENV_NAMES: &[&str] = &[".venv", "venv", "env"];
+
+/// A Python installation prefix and its `site-packages` directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PythonEnvironment {
+    /// Root of the environment (`sys.prefix`).
+    pub prefix: PathBuf,
+    /// Every `site-packages` directory found under the prefix.
+    pub site_packages: Vec<PathBuf>,
+}
+
+/// An installed package, as described by its `*.dist-info` directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Distribution {
+    /// Distribution name from `METADATA`.
+    pub name: String,
+    /// Installed version from `METADATA`.
+    pub version: String,
+    /// The `*.dist-info` directory.
+    pub path: PathBuf,
+    /// Top-level importable names the distribution provides.
+    pub top_level: Vec<String>,
+}
+
+impl PythonEnvironment {
+    /// Finds the environment that applies to `project_root`: the activated
+    /// virtualenv (`$VIRTUAL_ENV`), then an active conda environment
+    /// (`$CONDA_PREFIX`), then a `.venv`, `venv`, or `env` directory in the
+    /// project root or one of its ancestors.
+    pub fn discover(project_root: &Path) -> Option<Self> {
+        let activated = ["VIRTUAL_ENV", "CONDA_PREFIX"]
+            .iter()
+            .filter_map(env::var_os)
+            .map(PathBuf::from);
+        let local = project_root
+            .ancestors()
+            .flat_map(|dir| LOCAL_VENV_NAMES.iter().map(move |name| dir.join(name)))
+            .filter(|dir| dir.join("pyvenv.cfg").is_file());
+        activated.chain(local).find_map(Self::from_prefix)
+    }
+
+    /// Describes the environment rooted at `prefix`, or `None` if it has no
+    /// `site-packages` directory.
+    pub fn from_prefix(prefix: impl Into<PathBuf>) -> Option<Self> {
+        let prefix = prefix.into();
+        let mut site_packages = Vec::new();
+        // Windows layout.
+        let windows = prefix.join("Lib").join("site-packages");
+        if windows.is_dir() {
+            site_packages.push(windows);
+        }
+        // POSIX layout: lib/pythonX.Y/site-packages, possibly also under lib64.
+        for lib in ["lib", "lib64"] {
+            let Ok(entries) = fs::read_dir(prefix.join(lib)) else {
+                continue;
+            };
+            let mut found: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("python"))
+                .map(|entry| entry.path().join("site-packages"))
+                .filter(|dir| dir.is_dir() && !site_packages.contains(dir))
+                .collect();
+            found.sort();
+            site_packages.extend(found);
+        }
+        (!site_packages.is_empty()).then_some(Self { prefix, site_packages })
+    }
+
+    /// Path of the environment's interpreter, if present.
+    pub fn interpreter(&self) -> Option<PathBuf> {
+        [
+            self.prefix.join("bin").join("python"),
+            self.prefix.join("bin").join("python3"),
+            self.prefix.join("Scripts").join("python.exe"),
+            self.prefix.join("python.exe"),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+    }
+
+    /// Returns true if `path` lives inside one of the environment's
+    /// `site-packages` directories, i.e. belongs to third-party code.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.site_packages.iter().any(|dir| path.starts_with(dir))
+    }
+
+    /// Lists the distributions installed in the environment, sorted by name.
+    /// Broken `*.dist-info` directories, which half-finished installs leave
+    /// behind, are skipped with a warning.
+    pub fn distributions(&self) -> Result<Vec<Distribution>> {
+        let mut distributions = Vec::new();
+        for dir in &self.site_packages {
+            for entry in fs::read_dir(dir)? {
+                let path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(e) => {
+                        log::warn!("Skipping unreadable entry: {}", e);
+                        continue;
+                    },
+                };
+                if path.extension().is_none_or(|ext| ext != "dist-info") {
+                    continue;
+                }
+                match Distribution::read(&path) {
+                    Ok(distribution) => distributions.push(distribution),
+                    Err(e) => log::warn!("Skipping distribution: {}", e),
+                }
+            }
+        }
+        distributions.sort_by_key(|d| d.name.to_lowercase());
+        Ok(distributions)
+    }
+}
+
+impl Distribution {
+    /// Reads a `*.dist-info` directory.
+    pub fn read(path: &Path) -> Result<Self> {
+        let metadata = fs::read_to_string(path.join("METADATA")).map_err(|e| {
+            Error::parser_error(format!("Failed to read METADATA: {}", e)).with_path(path)
+        })?;
+        let header = |key: &str| {
+            metadata
+                .lines()
+                .take_while(|line| !line.is_empty())
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+                .map(|value| value.trim().to_string())
+        };
+        let name = header("Name")
+            .ok_or_else(|| Error::parser_error("METADATA has no Name field").with_path(path))?;
+        let version = header("Version").unwrap_or_default();
+
+        let top_level = match fs::read_to_string(path.join("top_level.txt")) {
+            Ok(text) => text
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(String::from)
+                .collect(),
+            Err(_) => top_level_from_record(path),
+        };
+        Ok(Self { name, version, path: path.to_path_buf(), top_level })
+    }
+}
+
+/// Derives top-level names from the first path component of each `RECORD`
+/// entry, for wheels that don't ship `top_level.txt`.
+fn top_level_from_record(dist_info: &Path) -> Vec<String> {
+    let Ok(record) = fs::read_to_string(dist_info.join("RECORD")) else {
+        return Vec::new();
+    };
+    let names: BTreeSet<String> = record
+        .lines()
+        .filter_map(|line| line.split(',').next())
+        .filter_map(|file| {
+            let (first, nested) = match file.split_once('/') {
+                Some((first, _)) => (first, true),
+                None => (file, false),
+            };
+            let skip = [".dist-info", ".data"].iter().any(|s| first.ends_with(s))
+                || first == ".."
+                || first == "__pycache__";
+            if skip {
+                None
+            } else if nested {
+                Some(first.to_string())
+            } else {
+                first.strip_suffix(".py").map(str::to_string)
+            }
+        })
+        .collect();
+    names.into_iter().collect()
+}
+
+/// Picks the interpreter for running code from the project containing the
+/// file `path`: `$OMNITYPE_PYTHON` or `$PYTHON` if set, then the project's
+/// virtualenv, then a `pythonX.Y` on `PATH` matching the project's declared
+/// version, then `python3`.
+pub fn select_interpreter(path: &Path) -> PathBuf {
+    if let Some(python) = env::var_os("OMNITYPE_PYTHON").or_else(|| env::var_os("PYTHON")) {
+        return PathBuf::from(python);
+    }
+    let project = path.parent().unwrap_or(Path::new("."));
+    if let Some(python) = PythonEnvironment::discover(project).and_then(|env| env.interpreter()) {
+        return python;
+    }
+    if let Some(version) = detect_python_version(project) {
+        let name = format!("python{}", version);
+        let on_path = env::var_os("PATH")
+            .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(&name).is_file()));
+        if on_path {
+            return PathBuf::from(name);
+        }
+    }
+    PathBuf::from("python3")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_environment_and_distributions() {
+        let dir = tempdir().unwrap();
+        let venv = dir.path().join(".venv");
+        let site = venv.join("lib/python3.12/site-packages");
+        fs::create_dir_all(&site).unwrap();
+        fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+
+        let requests = site.join("requests-2.31.0.dist-info");
+        fs::create_dir(&requests).unwrap();
+        fs::write(
+            requests.join("METADATA"),
+            "Metadata-Version: 2.1\nName: requests\nVersion: 2.31.0\n\nbody\n",
+        )
+        .unwrap();
+        fs::write(requests.join("top_level.txt"), "requests\n").unwrap();
+
+        let six = site.join("six-1.16.0.dist-info");
+        fs::create_dir(&six).unwrap();
+        fs::write(six.join("METADATA"), "Name: six\nVersion: 1.16.0\n").unwrap();
+        fs::write(six.join("RECORD"), "six.py,sha256=x,1\nsix-1.16.0.dist-info/METADATA,,\n")
+            .unwrap();
+        // Left behind by an interrupted install, without METADATA.
+        fs::create_dir(site.join("broken-0.1.dist-info")).unwrap();
+
+        let env = PythonEnvironment::from_prefix(&venv).unwrap();
+        assert_eq!(env.site_packages, vec![site.clone()]);
+        assert!(env.contains(&site.join("requests/api.py")));
+
+        let dists = env.distributions().unwrap();
+        assert_eq!(dists.len(), 2);
+        assert_eq!(dists[0].name, "requests");
+        assert_eq!(dists[0].top_level, vec!["requests".to_string()]);
+        assert_eq!(dists[1].version, "1.16.0");
+        assert_eq!(dists[1].top_level, vec!["six".to_string()]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Stable content hashes used as cache keys.
+//!
+//! Hashes are BLAKE3 digests, so they are identical across runs, platforms,
+//! and builds, unlike `std::hash`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{Error, Result};
+
+/// A 256-bit content digest, written as 64 lowercase hex digits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    /// Hashes a byte slice.
+    pub fn of_bytes(bytes: &[u8]) -> Self {
+        Self(*blake3::hash(bytes).as_bytes())
+    }
+
+    /// The raw digest.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The digest as lowercase hex.
+    pub fn to_hex(self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ContentHash({})", self)
+    }
+}
+
+impl FromStr for ContentHash {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::invalid_value("hash", s, "expected 64 hex digits");
+        if s.len() != 64 {
+            return Err(invalid());
+        }
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(s.get(i * 2..i * 2 + 2).ok_or_else(invalid)?, 16)
+                .map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for ContentHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Hashes the contents of a file.
+pub fn hash_file(path: impl AsRef<Path>) -> Result<ContentHash> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+        .map_err(|e| Error::parser_error(format!("Failed to read file: {}", e)).with_path(path))?;
+    Ok(ContentHash::of_bytes(&bytes))
+}
+
+/// Incrementally combines named inputs into one fingerprint. Entries are
+/// sorted before hashing, so the result doesn't depend on insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct Fingerprint {
+    entries: Vec<(String, ContentHash)>,
+}
+
+impl Fingerprint {
+    /// Creates an empty fingerprint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a named input by its hash.
+    pub fn add(&mut self, key: impl Into<String>, hash: ContentHash) -> &mut Self {
+        self.entries.push((key.into(), hash));
+        self
+    }
+
+    /// Adds a named input by its raw bytes, e.g. a settings blob.
+    pub fn add_bytes(&mut self, key: impl Into<String>, bytes: &[u8]) -> &mut Self {
+        self.add(key, ContentHash::of_bytes(bytes))
+    }
+
+    /// Hashes a file and adds it under its path.
+    pub fn add_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self> {
+        let path = path.as_ref();
+        let hash = hash_file(path)?;
+        Ok(self.add(path.to_string_lossy(), hash))
+    }
+
+    /// Produces the combined hash.
+    pub fn finish(&self) -> ContentHash {
+        let mut entries: Vec<&(String, ContentHash)> = self.entries.iter().collect();
+        entries.sort();
+        let mut hasher = blake3::Hasher::new();
+        for (key, hash) in entries {
+            // Length-prefix keys so adjacent entries can't run together.
+            hasher.update(&(key.len() as u64).to_le_bytes());
+            hasher.update(key.as_bytes());
+            hasher.update(hash.as_bytes());
+        }
+        ContentHash(*hasher.finalize().as_bytes())
+    }
+}
+
+/// Fingerprints a set of project files, keyed by their paths relative to
+/// `root` so the result survives moving the checkout.
+pub fn project_fingerprint(root: &Path, files: &[PathBuf]) -> Result<ContentHash> {
+    let mut fingerprint = Fingerprint::new();
+    for file in files {
+        let key = file.strip_prefix(root).unwrap_or(file);
+        fingerprint.add(key.to_string_lossy(), hash_file(file)?);
+    }
+    Ok(fingerprint.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_content_hash_roundtrip() {
+        let hash = ContentHash::of_bytes(b"x = 1\n");
+        assert_eq!(hash, ContentHash::of_bytes(b"x = 1\n"));
+        assert_ne!(hash, ContentHash::of_bytes(b"x = 2\n"));
+        assert_eq!(hash.to_hex().parse::<ContentHash>().unwrap(), hash);
+
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{}\"", hash));
+        assert!("zz".parse::<ContentHash>().is_err());
+    }
+
+    #[test]
+    fn test_project_fingerprint_is_stable() {
+        let (a, b) = (tempdir().unwrap(), tempdir().unwrap());
+        for dir in [&a, &b] {
+            std::fs::write(dir.path().join("one.py"), "x = 1\n").unwrap();
+            std::fs::write(dir.path().join("two.py"), "y = 2\n").unwrap();
+        }
+        let files = |dir: &Path| vec![dir.join("two.py"), dir.join("one.py")];
+        let fa = project_fingerprint(a.path(), &files(a.path())).unwrap();
+        let mut reversed = files(b.path());
+        reversed.reverse();
+        let fb = project_fingerprint(b.path(), &reversed).unwrap();
+        assert_eq!(fa, fb);
+
+        std::fs::write(b.path().join("two.py"), "y = 3\n").unwrap();
+        assert_ne!(fa, project_fingerprint(b.path(), &files(b.path())).unwrap());
+    }
+}
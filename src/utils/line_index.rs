@@ -0,0 +1,177 @@
+//! Conversions between byte offsets, line/column pairs, and UTF-16 columns.
+//!
+//! A [`LineIndex`] is built once per file. Besides line starts it records the
+//! position of every non-ASCII character, so UTF-16 conversions (as used by
+//! the Language Server Protocol) don't need to rescan the text.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A 0-based line and byte column in UTF-8 text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LineCol {
+    /// 0-based line number.
+    pub line: usize,
+    /// 0-based byte offset from the start of the line.
+    pub col: usize,
+}
+
+/// A multi-byte character within a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WideChar {
+    /// Byte column where the character starts.
+    start: usize,
+    /// Length in UTF-8 bytes.
+    len: usize,
+}
+
+impl WideChar {
+    fn len_utf16(&self) -> usize {
+        if self.len == 4 {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Precomputed line starts and multi-byte characters of a text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+    wide_chars: HashMap<usize, Vec<WideChar>>,
+    len: usize,
+}
+
+impl LineIndex {
+    /// Indexes `text`. Lines are terminated by `\n`; a preceding `\r` is
+    /// treated as part of the line's content.
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars: HashMap<usize, Vec<WideChar>> = HashMap::new();
+        let mut line = 0;
+        for (offset, c) in text.char_indices() {
+            if c == '\n' {
+                line_starts.push(offset + 1);
+                line += 1;
+            } else if !c.is_ascii() {
+                let start = offset - line_starts[line];
+                wide_chars
+                    .entry(line)
+                    .or_default()
+                    .push(WideChar { start, len: c.len_utf8() });
+            }
+        }
+        Self { line_starts, wide_chars, len: text.len() }
+    }
+
+    /// Number of lines; a trailing newline starts a final empty line.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Length of the indexed text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the indexed text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Byte offset at which a line starts.
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line).copied()
+    }
+
+    /// Byte range of a line, excluding its `\n` terminator.
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = self.line_start(line)?;
+        let end = self.line_start(line + 1).map_or(self.len, |next| next - 1);
+        Some(start..end)
+    }
+
+    /// Converts a byte offset (clamped to the text) into a line and column.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        LineCol { line, col: offset - self.line_starts[line] }
+    }
+
+    /// Converts a line and byte column into a byte offset. Returns `None` if
+    /// the position lies beyond the end of its line.
+    pub fn offset(&self, pos: LineCol) -> Option<usize> {
+        let range = self.line_range(pos.line)?;
+        let offset = range.start + pos.col;
+        (offset <= range.end).then_some(offset)
+    }
+
+    /// Converts a byte column on a line into UTF-16 code units.
+    pub fn utf16_col(&self, line: usize, col: usize) -> usize {
+        let mut utf16 = col;
+        for wide in self.wide_chars(line) {
+            if wide.start >= col {
+                break;
+            }
+            utf16 = utf16 + wide.len_utf16() - wide.len;
+        }
+        utf16
+    }
+
+    /// Converts a UTF-16 column on a line into a byte column.
+    pub fn byte_col(&self, line: usize, utf16_col: usize) -> usize {
+        // Bytes minus UTF-16 units of the wide characters passed so far.
+        let mut extra = 0;
+        for wide in self.wide_chars(line) {
+            if wide.start - extra >= utf16_col {
+                break;
+            }
+            extra += wide.len - wide.len_utf16();
+        }
+        utf16_col + extra
+    }
+
+    /// Converts a line and UTF-16 column, as sent by LSP clients, into a
+    /// byte offset. Columns past the end of the line clamp to its end.
+    pub fn offset_utf16(&self, line: usize, utf16_col: usize) -> Option<usize> {
+        let range = self.line_range(line)?;
+        Some((range.start + self.byte_col(line, utf16_col)).min(range.end))
+    }
+
+    fn wide_chars(&self, line: usize) -> &[WideChar] {
+        self.wide_chars.get(&line).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_col_roundtrip() {
+        let text = "ab\ncd\n\nxyz";
+        let index = LineIndex::new(text);
+        assert_eq!(index.line_count(), 4);
+        for offset in 0..=text.len() {
+            let pos = index.line_col(offset);
+            assert_eq!(index.offset(pos), Some(offset));
+        }
+        assert_eq!(index.line_col(4), LineCol { line: 1, col: 1 });
+        assert_eq!(index.offset(LineCol { line: 0, col: 5 }), None);
+    }
+
+    #[test]
+    fn test_utf16_conversions() {
+        let text = "x = 1\ns = 'é😀'; y\n";
+        let index = LineIndex::new(text);
+        let y = text.find('y').unwrap();
+        let pos = index.line_col(y);
+        // 'é' is 2 bytes / 1 unit, the emoji 4 bytes / 2 units.
+        assert_eq!(pos.col, 14);
+        assert_eq!(index.utf16_col(pos.line, pos.col), 11);
+        assert_eq!(index.byte_col(1, 11), 14);
+        assert_eq!(index.offset_utf16(1, 11), Some(y));
+        assert_eq!(index.offset_utf16(1, 99), Some(text.len() - 1));
+    }
+}
@@ -1,24 +1,76 @@
 //! Utility functions and helpers for omnitype.
 
-use std::path::{Path, PathBuf};
+#[cfg(feature = "tracer")]
+mod artifacts;
+mod csv;
+mod discovery;
+mod environment;
+mod hash;
+mod line_index;
+mod module;
+mod python_version;
+mod span;
+mod walk;
+
+#[cfg(feature = "tracer")]
+pub use artifacts::{cache_dir, ArtifactWorkspace};
+pub use csv::csv_record;
+pub use discovery::{is_generated, DiscoveryOptions};
+pub use environment::{select_interpreter, Distribution, PythonEnvironment};
+pub use hash::{hash_file, project_fingerprint, ContentHash, Fingerprint};
+pub use line_index::{LineCol, LineIndex};
+pub use module::{infer_module_name, ModuleResolver, ResolvedModule};
+pub use python_version::{detect_python_version, lowest_allowed, PythonVersion};
+pub use span::{Position, SourceFile, Span};
+pub use walk::{SourceWalker, IGNORE_FILE};
 
-use walkdir::WalkDir;
+use std::path::{Path, PathBuf};
 
-/// Returns an iterator over all Python files in the given directory.
+/// Returns an iterator over all Python files in the given directory, skipping
+/// ignored paths (see [`SourceWalker`]).
 pub fn find_python_files<P: AsRef<Path>>(path: P) -> impl Iterator<Item = PathBuf> {
-    WalkDir::new(path)
+    find_python_files_with(path, &DiscoveryOptions::default())
+}
+
+/// Returns an iterator over the Python files in `path` that pass `options`.
+pub fn find_python_files_with<P: AsRef<Path>>(
+    path: P,
+    options: &DiscoveryOptions,
+) -> impl Iterator<Item = PathBuf> {
+    let options = options.clone();
+    find_source_files(path).filter(move |file| {
+        file.extension().is_some_and(|ext| ext == "py") && options.accepts(file)
+    })
+}
+
+/// Returns an iterator over all source files whose language grammar is
+/// enabled, skipping ignored paths (see [`SourceWalker`]).
+pub fn find_source_files<P: AsRef<Path>>(path: P) -> impl Iterator<Item = PathBuf> {
+    // Without configured excludes the walker can't fail to build.
+    SourceWalker::new(path.as_ref())
+        .files()
+        .into_iter()
+        .flatten()
+}
+
+/// Returns an iterator over the source files in `path` that pass `options`.
+pub fn find_source_files_with<P: AsRef<Path>>(
+    path: P,
+    options: &DiscoveryOptions,
+) -> impl Iterator<Item = PathBuf> {
+    SourceWalker::new(path.as_ref())
+        .options(options.clone())
+        .files()
         .into_iter()
-        .filter_map(Result::ok)
-        .filter(|entry| {
-            let path = entry.path();
-            path.is_file() && path.extension().is_some_and(|ext| ext == "py")
-        })
-        .map(|entry| entry.path().to_path_buf())
+        .flatten()
 }
 
 /// Converts a path to a module name.
 /// Returns None if the path ends with a trailing slash (indicating a directory)
 /// or has no file stem.
+///
+/// This is only the file stem; use [`ModuleResolver::module_name`] or
+/// [`infer_module_name`] for the full dotted name.
 pub fn path_to_module_name(path: &Path) -> Option<String> {
     // Check if the path ends with a separator (indicates a directory)
     if path.to_str()?.ends_with(std::path::MAIN_SEPARATOR) || path.to_str()?.ends_with('/') {
@@ -0,0 +1,241 @@
+//! Mapping between source files and dotted Python module names.
+//!
+//! A file's module name is its path relative to a search root, with
+//! `__init__.py` standing for its package: under root `src`,
+//! `src/pkg/sub/mod.py` is `pkg.sub.mod` and `src/pkg/__init__.py` is `pkg`.
+
+use std::path::{Component, Path, PathBuf};
+
+/// File extensions tried when resolving a module, in order of preference.
+const MODULE_EXTENSIONS: &[&str] = &["pyi", "py"];
+
+/// Resolves module names against an ordered list of search roots, like
+/// entries on `sys.path`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleResolver {
+    roots: Vec<PathBuf>,
+}
+
+impl ModuleResolver {
+    /// Creates a resolver searching `roots` in order.
+    pub fn new<I, P>(roots: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Self { roots: roots.into_iter().map(Into::into).collect() }
+    }
+
+    /// The search roots, in priority order.
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Appends a search root with the lowest priority.
+    pub fn add_root(&mut self, root: impl Into<PathBuf>) {
+        self.roots.push(root.into());
+    }
+
+    /// Returns the dotted module name of `path` relative to the most specific
+    /// root containing it. Files outside every root fall back to
+    /// [`infer_module_name`].
+    pub fn module_name(&self, path: &Path) -> Option<String> {
+        let root = self
+            .roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count());
+        match root {
+            Some(root) => module_name_relative(path.strip_prefix(root).ok()?),
+            None => infer_module_name(path),
+        }
+    }
+
+    /// Resolves a dotted module name following Python's import rules: each
+    /// component is looked up in the search path of its parent, a regular
+    /// module or package wins as soon as it is found, and directories
+    /// without an `__init__` are collected across every entry as portions
+    /// of a PEP 420 namespace package.
+    pub fn resolve(&self, name: &str) -> Option<ResolvedModule> {
+        let parts: Vec<&str> = name.split('.').collect();
+        if !parts.iter().all(|p| is_identifier(p)) {
+            return None;
+        }
+        let mut search = self.roots.clone();
+        let (last, parents) = parts.split_last()?;
+        for part in parents {
+            search = match find_in(&search, part)? {
+                ResolvedModule::File(path) if is_package_init(&path) => {
+                    vec![path.parent()?.to_path_buf()]
+                },
+                // A plain module has no submodules.
+                ResolvedModule::File(_) => return None,
+                ResolvedModule::Namespace(portions) => portions,
+            };
+        }
+        find_in(&search, last)
+    }
+
+    /// Resolves a dotted module name to the file that defines it. Namespace
+    /// packages have no such file and resolve to `None`.
+    pub fn resolve_file(&self, name: &str) -> Option<PathBuf> {
+        match self.resolve(name)? {
+            ResolvedModule::File(path) => Some(path),
+            ResolvedModule::Namespace(_) => None,
+        }
+    }
+}
+
+/// Where a module name resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedModule {
+    /// A module file or a regular package's `__init__` file.
+    File(PathBuf),
+    /// A namespace package spread over these directories, in search order.
+    Namespace(Vec<PathBuf>),
+}
+
+/// Looks up one name component in a search path.
+fn find_in(search: &[PathBuf], part: &str) -> Option<ResolvedModule> {
+    let mut portions = Vec::new();
+    for dir in search {
+        let base = dir.join(part);
+        if let Some(file) = module_file(&base) {
+            return Some(ResolvedModule::File(file));
+        }
+        if base.is_dir() {
+            portions.push(base);
+        }
+    }
+    (!portions.is_empty()).then_some(ResolvedModule::Namespace(portions))
+}
+
+fn is_package_init(path: &Path) -> bool {
+    path.file_stem().is_some_and(|stem| stem == "__init__")
+}
+
+/// Infers the dotted module name of a file from its enclosing packages,
+/// walking up through directories that contain an `__init__.py`.
+///
+/// Namespace packages can't be detected this way, since nothing marks their
+/// directories; files inside them need a [`ModuleResolver`] with the right
+/// roots.
+pub fn infer_module_name(path: &Path) -> Option<String> {
+    let mut root = path.parent()?;
+    while root.join("__init__.py").is_file() || root.join("__init__.pyi").is_file() {
+        match root.parent() {
+            Some(parent) => root = parent,
+            None => break,
+        }
+    }
+    module_name_relative(path.strip_prefix(root).ok()?)
+}
+
+/// Converts a root-relative file path to a dotted name.
+fn module_name_relative(relative: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {},
+            _ => return None,
+        }
+    }
+    let file = parts.pop()?;
+    let (stem, ext) = file.rsplit_once('.')?;
+    if !MODULE_EXTENSIONS.contains(&ext) {
+        return None;
+    }
+    if stem != "__init__" {
+        parts.push(stem);
+    }
+    if parts.is_empty() || !parts.iter().all(|p| is_identifier(p)) {
+        return None;
+    }
+    Some(parts.join("."))
+}
+
+/// Returns the module file for a path without extension: a stub or source
+/// file, or a package's `__init__`.
+fn module_file(base: &Path) -> Option<PathBuf> {
+    let file_name = base.file_name()?.to_str()?;
+    MODULE_EXTENSIONS
+        .iter()
+        .map(|ext| base.with_file_name(format!("{}.{}", file_name, ext)))
+        .chain(
+            MODULE_EXTENSIONS
+                .iter()
+                .map(|ext| base.join(format!("__init__.{}", ext))),
+        )
+        .find(|candidate| candidate.is_file())
+}
+
+fn is_identifier(part: &str) -> bool {
+    let mut chars = part.chars();
+    chars.next().is_some_and(|c| c == '_' || c.is_alphabetic())
+        && chars.all(|c| c == '_' || c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn test_module_name_relative_to_root() {
+        let resolver = ModuleResolver::new(["src"]);
+        assert_eq!(
+            resolver.module_name(Path::new("src/pkg/sub/mod.py")),
+            Some("pkg.sub.mod".to_string())
+        );
+        assert_eq!(resolver.module_name(Path::new("src/pkg/__init__.py")), Some("pkg".to_string()));
+        assert_eq!(resolver.module_name(Path::new("src/my-script.py")), None);
+    }
+
+    #[test]
+    fn test_infer_and_resolve() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        touch(&root.join("pkg/__init__.py"));
+        touch(&root.join("pkg/sub/__init__.py"));
+        touch(&root.join("pkg/sub/mod.py"));
+
+        let file = root.join("pkg/sub/mod.py");
+        assert_eq!(infer_module_name(&file), Some("pkg.sub.mod".to_string()));
+
+        let resolver = ModuleResolver::new([root]);
+        assert_eq!(resolver.resolve_file("pkg.sub.mod"), Some(file));
+        assert_eq!(resolver.resolve_file("pkg.sub"), Some(root.join("pkg/sub/__init__.py")));
+        assert_eq!(resolver.resolve("pkg.missing"), None);
+    }
+
+    #[test]
+    fn test_namespace_package_across_roots() {
+        let dir = tempdir().unwrap();
+        let (first, second) = (dir.path().join("a"), dir.path().join("b"));
+        touch(&first.join("ns/one.py"));
+        touch(&second.join("ns/two/__init__.py"));
+        touch(&second.join("ns/two/leaf.py"));
+
+        let resolver = ModuleResolver::new([&first, &second]);
+        assert_eq!(
+            resolver.resolve("ns"),
+            Some(ResolvedModule::Namespace(vec![first.join("ns"), second.join("ns")]))
+        );
+        assert_eq!(resolver.resolve_file("ns"), None);
+        assert_eq!(resolver.resolve_file("ns.one"), Some(first.join("ns/one.py")));
+        assert_eq!(resolver.resolve_file("ns.two.leaf"), Some(second.join("ns/two/leaf.py")));
+        assert_eq!(
+            resolver
+                .module_name(&second.join("ns/two/leaf.py"))
+                .unwrap(),
+            "ns.two.leaf"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Target Python version and its detection from project metadata.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// A Python language version (`major.minor`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PythonVersion {
+    /// Major version, e.g. `3`.
+    pub major: u8,
+    /// Minor version, e.g. `12`.
+    pub minor: u8,
+}
+
+impl PythonVersion {
+    /// The version assumed when a project doesn't declare one.
+    pub const DEFAULT: PythonVersion = PythonVersion::new(3, 8);
+
+    /// Creates a version.
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Self { major, minor }
+    }
+
+    /// Returns true if this version is at least `major.minor`.
+    pub fn at_least(self, major: u8, minor: u8) -> bool {
+        self >= Self::new(major, minor)
+    }
+}
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for PythonVersion {
+    type Err = Error;
+
+    /// Parses `3.11`, `3.11.4`, or `py311`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::invalid_value("python-version", s, "expected a version like 3.11");
+        let text = s.trim();
+        if let Some(digits) = text.strip_prefix("py") {
+            let (major, minor) = digits.split_at(digits.len().min(1));
+            return Ok(Self::new(
+                major.parse().map_err(|_| invalid())?,
+                minor.parse().map_err(|_| invalid())?,
+            ));
+        }
+        let mut parts = text.split('.');
+        let major = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)?;
+        let minor = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)?;
+        Ok(Self::new(major, minor))
+    }
+}
+
+impl TryFrom<String> for PythonVersion {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<PythonVersion> for String {
+    fn from(version: PythonVersion) -> Self {
+        version.to_string()
+    }
+}
+
+/// Detects the Python version a project targets by searching `start` and
+/// its ancestors for, in order of precedence within each directory:
+///
+/// 1. `.python-version` (a pinned interpreter, e.g. from pyenv)
+/// 2. `requires-python` in the `[project]` table of `pyproject.toml`
+/// 3. `python_requires` in the `[options]` section of `setup.cfg`
+///
+/// Version ranges resolve to their lowest allowed version, since code must
+/// run there.
+pub fn detect_python_version(start: &Path) -> Option<PythonVersion> {
+    let dir = if start.is_file() { start.parent()? } else { start };
+    dir.ancestors().find_map(|dir| {
+        from_python_version_file(dir)
+            .or_else(|| from_pyproject(dir))
+            .or_else(|| from_setup_cfg(dir))
+    })
+}
+
+fn from_python_version_file(dir: &Path) -> Option<PythonVersion> {
+    let text = fs::read_to_string(dir.join(".python-version")).ok()?;
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))?;
+    // pyenv also accepts names such as `pypy3.10-7.3.12`.
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let version = &line[start..];
+    let end = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+    version[..end].parse().ok()
+}
+
+fn from_pyproject(dir: &Path) -> Option<PythonVersion> {
+    let text = fs::read_to_string(dir.join("pyproject.toml")).ok()?;
+    let doc: toml::Table = toml::from_str(&text).ok()?;
+    let spec = doc.get("project")?.get("requires-python")?.as_str()?;
+    lowest_allowed(spec)
+}
+
+fn from_setup_cfg(dir: &Path) -> Option<PythonVersion> {
+    let text = fs::read_to_string(dir.join("setup.cfg")).ok()?;
+    let mut in_options = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_options = line == "[options]";
+        } else if in_options {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == "python_requires" {
+                    return lowest_allowed(value);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Returns the lowest version a PEP 440 specifier such as `>=3.9,<4`
+/// admits, or `None` if it has no lower bound. `>3.8` admits 3.8.1, so
+/// its lowest version is 3.8 as well.
+pub fn lowest_allowed(spec: &str) -> Option<PythonVersion> {
+    spec.split(',')
+        .filter_map(|clause| {
+            let clause = clause.trim();
+            let version = ["~=", ">=", "==", ">"]
+                .iter()
+                .find_map(|op| clause.strip_prefix(op))?;
+            version.trim().trim_end_matches(".*").parse().ok()
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_and_specifiers() {
+        assert_eq!("3.11.4".parse::<PythonVersion>().unwrap(), PythonVersion::new(3, 11));
+        assert_eq!("py310".parse::<PythonVersion>().unwrap(), PythonVersion::new(3, 10));
+        assert!("three".parse::<PythonVersion>().is_err());
+
+        assert_eq!(lowest_allowed(">=3.9, <4"), Some(PythonVersion::new(3, 9)));
+        assert_eq!(lowest_allowed(">3.8"), Some(PythonVersion::new(3, 8)));
+        assert_eq!(lowest_allowed(">=3.8"), Some(PythonVersion::new(3, 8)));
+        assert_eq!(lowest_allowed("~=3.8"), Some(PythonVersion::new(3, 8)));
+        assert_eq!(lowest_allowed(">3.8.1"), Some(PythonVersion::new(3, 8)));
+        assert_eq!(lowest_allowed("~=3.10"), Some(PythonVersion::new(3, 10)));
+        assert_eq!(lowest_allowed("<3.12"), None);
+    }
+
+    #[test]
+    fn test_detect_python_version() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("pkg");
+        fs::create_dir(&sub).unwrap();
+        fs::write(dir.path().join("pyproject.toml"), "[project]\nrequires-python = \">=3.10\"\n")
+            .unwrap();
+        assert_eq!(detect_python_version(&sub), Some(PythonVersion::new(3, 10)));
+
+        fs::write(dir.path().join(".python-version"), "pypy3.11-7.3.17\n").unwrap();
+        assert_eq!(detect_python_version(&sub), Some(PythonVersion::new(3, 11)));
+    }
+}
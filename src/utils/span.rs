@@ -0,0 +1,201 @@
+//! Source spans and position lookups.
+//!
+//! A [`Span`] records both byte offsets into the decoded text and line/column
+//! positions, where columns are bytes in the file's original encoding and
+//! UTF-16 code units (as the Language Server Protocol expects).
+
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+use super::LineIndex;
+use crate::error::Result;
+use crate::parser::SourceText;
+
+/// A 0-based position in a source file.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Position {
+    /// 0-based line number.
+    pub line: usize,
+    /// 0-based byte column within the line, in the file's original encoding.
+    pub column: usize,
+    /// 0-based column in UTF-16 code units.
+    pub utf16_column: usize,
+}
+
+impl Position {
+    /// Creates a position whose UTF-16 column equals its byte column (ASCII text).
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column, utf16_column: column }
+    }
+}
+
+/// A half-open range of source text.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Span {
+    /// Start offset in bytes of the decoded UTF-8 text.
+    pub start_byte: usize,
+    /// End offset (exclusive) in bytes of the decoded UTF-8 text.
+    pub end_byte: usize,
+    /// Start position.
+    pub start: Position,
+    /// End position (exclusive).
+    pub end: Position,
+}
+
+impl Span {
+    /// Creates a zero-width span at a line and column, for producers without
+    /// access to the source text.
+    pub fn point(line: usize, column: usize) -> Self {
+        let pos = Position::new(line, column);
+        Self { start_byte: 0, end_byte: 0, start: pos, end: pos }
+    }
+
+    /// Length of the span in bytes of the decoded text.
+    pub fn len(&self) -> usize {
+        self.end_byte - self.start_byte
+    }
+
+    /// Returns true if the span covers no text.
+    pub fn is_empty(&self) -> bool {
+        self.end_byte == self.start_byte
+    }
+
+    /// Returns true if the byte offset falls within the span.
+    pub fn contains(&self, byte: usize) -> bool {
+        self.start_byte <= byte && byte < self.end_byte
+    }
+}
+
+impl fmt::Display for Span {
+    /// Formats the start as a 1-based `line:column` pair.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.start.line + 1, self.start.column + 1)
+    }
+}
+
+/// A decoded source file with a precomputed [`LineIndex`] for span lookups.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    path: String,
+    source: SourceText,
+    index: LineIndex,
+}
+
+impl SourceFile {
+    /// Creates a source file from already decoded text.
+    pub fn new(path: impl Into<String>, source: SourceText) -> Self {
+        let index = LineIndex::new(&source.text);
+        Self { path: path.into(), source, index }
+    }
+
+    /// Reads and decodes a file from disk.
+    pub fn read(path: &Path) -> Result<Self> {
+        Ok(Self::new(path.to_string_lossy(), SourceText::read(path)?))
+    }
+
+    /// Creates a source file from UTF-8 text.
+    pub fn from_text(path: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::new(path, SourceText::from_utf8(text))
+    }
+
+    /// The path the file was read from.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The decoded text.
+    pub fn text(&self) -> &str {
+        &self.source.text
+    }
+
+    /// The decoded source, including its original encoding.
+    pub fn source(&self) -> &SourceText {
+        &self.source
+    }
+
+    /// The line index of the decoded text.
+    pub fn line_index(&self) -> &LineIndex {
+        &self.index
+    }
+
+    /// Number of lines in the file.
+    pub fn line_count(&self) -> usize {
+        self.index.line_count()
+    }
+
+    /// Returns the text of a 0-based line, without its terminator.
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        let range = self.index.line_range(line)?;
+        Some(self.text()[range].trim_end_matches('\r'))
+    }
+
+    /// Converts a byte offset in the decoded text into a position.
+    pub fn position(&self, byte: usize) -> Position {
+        let pos = self.index.line_col(byte);
+        let column = if self.source.needs_mapping() {
+            let line_start = self.index.line_start(pos.line).unwrap_or_default();
+            self.source
+                .encoded_len(&self.text()[line_start..line_start + pos.col])
+        } else {
+            pos.col
+        };
+        Position { line: pos.line, column, utf16_column: self.index.utf16_col(pos.line, pos.col) }
+    }
+
+    /// Builds a span from byte offsets in the decoded text.
+    pub fn span(&self, start_byte: usize, end_byte: usize) -> Span {
+        Span {
+            start_byte,
+            end_byte,
+            start: self.position(start_byte),
+            end: self.position(end_byte),
+        }
+    }
+
+    /// Builds the span covered by a syntax node.
+    pub fn node_span(&self, node: &Node) -> Span {
+        self.span(node.start_byte(), node.end_byte())
+    }
+
+    /// Returns the source text covered by a span.
+    pub fn slice(&self, span: &Span) -> &str {
+        &self.text()[span.start_byte..span.end_byte]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_track_utf16_columns() {
+        let file = SourceFile::from_text("t.py", "x = 1\ns = '😀'; y = 2\n");
+        let y = file.text().find('y').unwrap();
+        let pos = file.position(y);
+        assert_eq!(pos.line, 1);
+        // The emoji is 4 UTF-8 bytes but 2 UTF-16 code units.
+        assert_eq!(pos.column, 12);
+        assert_eq!(pos.utf16_column, 10);
+
+        let span = file.span(y, y + 1);
+        assert_eq!(file.slice(&span), "y");
+        assert_eq!(span.to_string(), "2:13");
+        assert_eq!(file.line_text(1), Some("s = '😀'; y = 2"));
+    }
+
+    #[test]
+    fn test_positions_map_to_original_encoding() {
+        let source = SourceText::decode(b"# coding: latin-1\ns = '\xe9'; y = 2\n").unwrap();
+        let file = SourceFile::new("t.py", source);
+        let pos = file.position(file.text().find('y').unwrap());
+        assert_eq!(pos.column, 9);
+        assert_eq!(pos.utf16_column, 9);
+    }
+}
@@ -0,0 +1,219 @@
+//! Directory walking that honours ignore files.
+//!
+//! Every command that enumerates files goes through [`SourceWalker`], so a
+//! path skipped by `check` is also skipped by `fix` and the TUI. Paths are
+//! excluded when they match any of:
+//!
+//! - `.gitignore`, `.git/info/exclude`, and the global git excludes file
+//! - `.ignore` and `.omnitypeignore` files
+//! - the `exclude` patterns from the omnitype configuration
+//!
+//! Hidden files and directories are skipped as well.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{WalkBuilder, WalkState};
+
+use super::DiscoveryOptions;
+use crate::error::{Error, Result};
+use crate::parser::Language;
+
+/// Name of omnitype's own ignore file, using gitignore syntax.
+pub const IGNORE_FILE: &str = ".omnitypeignore";
+
+/// Finds source files under a root while respecting ignore rules.
+#[derive(Debug, Clone)]
+pub struct SourceWalker {
+    root: PathBuf,
+    excludes: Vec<String>,
+    options: DiscoveryOptions,
+}
+
+impl SourceWalker {
+    /// Creates a walker rooted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), excludes: Vec::new(), options: DiscoveryOptions::default() }
+    }
+
+    /// Adds gitignore-style patterns, relative to the root, to exclude.
+    pub fn exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.excludes.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the size, generated-file, and extension filters.
+    pub fn options(mut self, options: DiscoveryOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The directory being walked.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns every non-ignored source file whose language is enabled and
+    /// that passes the discovery options, in a stable order.
+    pub fn files(&self) -> Result<impl Iterator<Item = PathBuf>> {
+        let options = self.options.clone();
+        let walk = self.builder()?.build();
+        Ok(walk
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::warn!("Skipping unreadable entry: {}", e);
+                    None
+                },
+            })
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(ignore::DirEntry::into_path)
+            .filter(move |path| accepts(path, &options)))
+    }
+
+    /// Walks the tree on `threads` worker threads (0 picks a default from
+    /// the CPU count) and streams each accepted file as soon as it is found,
+    /// so consumers can start analysing before the walk finishes. Files
+    /// arrive in no particular order; the receiver closes once the walk is
+    /// complete. Dropping the receiver stops the walk early.
+    pub fn files_parallel(&self, threads: usize) -> Result<Receiver<PathBuf>> {
+        let walk = self.builder()?.threads(threads).build_parallel();
+        let options = self.options.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            walk.run(|| {
+                let tx = tx.clone();
+                let options = options.clone();
+                Box::new(move |entry| {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            log::warn!("Skipping unreadable entry: {}", e);
+                            return WalkState::Continue;
+                        },
+                    };
+                    if entry.file_type().is_some_and(|t| t.is_file())
+                        && accepts(entry.path(), &options)
+                        && tx.send(entry.into_path()).is_err()
+                    {
+                        return WalkState::Quit;
+                    }
+                    WalkState::Continue
+                })
+            });
+        });
+        Ok(rx)
+    }
+
+    /// Lists the non-ignored immediate children of `dir`, directories first,
+    /// for file browsers. `dir` must be inside the root for configured
+    /// excludes to apply.
+    pub fn list_dir(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut entries: Vec<(PathBuf, bool)> = self
+            .configure(WalkBuilder::new(dir))?
+            .max_depth(Some(1))
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.depth() == 1)
+            .map(|entry| {
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                (entry.into_path(), is_dir)
+            })
+            .collect();
+        entries.sort_by(|(a, a_dir), (b, b_dir)| {
+            b_dir
+                .cmp(a_dir)
+                .then_with(|| a.file_name().cmp(&b.file_name()))
+        });
+        Ok(entries.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// A configured walk builder, for callers that drive the walk themselves.
+    pub fn builder(&self) -> Result<WalkBuilder> {
+        self.configure(WalkBuilder::new(&self.root))
+    }
+
+    fn configure(&self, mut builder: WalkBuilder) -> Result<WalkBuilder> {
+        builder
+            .hidden(true)
+            .git_ignore(true)
+            .git_global(true)
+            .git_exclude(true)
+            // Honour .gitignore even outside a git checkout (e.g. exported trees).
+            .require_git(false)
+            .add_custom_ignore_filename(IGNORE_FILE)
+            .overrides(self.overrides()?)
+            .sort_by_file_name(|a, b| a.cmp(b));
+        Ok(builder)
+    }
+
+    fn overrides(&self) -> Result<Override> {
+        let mut builder = OverrideBuilder::new(&self.root);
+        for pattern in &self.excludes {
+            builder
+                .add(&format!("!{}", pattern))
+                .map_err(|e| Error::invalid_value("exclude", pattern.as_str(), e.to_string()))?;
+        }
+        builder
+            .build()
+            .map_err(|e| Error::invalid_value("exclude", self.excludes.join(", "), e.to_string()))
+    }
+}
+
+fn accepts(path: &Path, options: &DiscoveryOptions) -> bool {
+    Language::from_path(path).is_some_and(Language::is_enabled) && options.accepts(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_walker_honours_ignore_sources() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        for file in
+            ["keep.py", "src/a.py", "build/out.py", "vendor/lib.py", "skip/me.py", ".hidden/x.py"]
+        {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        fs::write(root.join(IGNORE_FILE), "vendor/\n").unwrap();
+
+        let walker = SourceWalker::new(root).exclude(["skip/"]);
+        let files: Vec<PathBuf> = walker.files().unwrap().collect();
+        assert_eq!(files, vec![root.join("keep.py"), root.join("src/a.py")]);
+
+        let listing = walker.list_dir(root).unwrap();
+        assert_eq!(listing, vec![root.join("src"), root.join("keep.py")]);
+    }
+
+    #[test]
+    fn test_parallel_walk_matches_serial() {
+        let dir = tempdir().unwrap();
+        for i in 0..40 {
+            let path = dir.path().join(format!("pkg{}/mod{}.py", i % 7, i));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let walker = SourceWalker::new(dir.path());
+        let serial: Vec<PathBuf> = walker.files().unwrap().collect();
+        let mut parallel: Vec<PathBuf> = walker.files_parallel(4).unwrap().into_iter().collect();
+        parallel.sort();
+
+        assert_eq!(serial.len(), 40);
+        let mut expected = serial;
+        expected.sort();
+        assert_eq!(parallel, expected);
+    }
+}
@@ -0,0 +1,77 @@
+//! Browser bindings, built with the `wasm` feature for playgrounds and
+//! web-based editors.
+//!
+//! ```js
+//! import init, { check_source, annotate_source } from "omnitype";
+//!
+//! await init();
+//! for (const d of check_source("def f(x):\n    return x\n").diagnostics) {
+//!     console.log(d.span.start.line, d.message);
+//! }
+//! ```
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::analyzer::{AnalysisResult, Analyzer};
+use crate::error::Result;
+use crate::fixer::Fixer;
+use crate::parser::Language;
+use crate::utils::SourceFile;
+
+/// Checks Python source and returns the analysis result, in the shape of a
+/// `check --format json` entry. Throws if the source cannot be analyzed.
+#[wasm_bindgen]
+pub fn check_source(source: &str) -> std::result::Result<JsValue, JsError> {
+    let result = check(source).map_err(|e| JsError::new(&e.to_string()))?;
+    // Plain objects rather than `Map`s, so results read like the JSON output.
+    result
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Returns `source` with `Any` annotations added to untyped parameters and
+/// returns.
+#[wasm_bindgen]
+pub fn annotate_source(source: &str) -> std::result::Result<String, JsError> {
+    annotate(source).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// What [`check_source`] returns, before conversion to JavaScript.
+fn check(source: &str) -> Result<AnalysisResult> {
+    let file = SourceFile::from_text("<input>", source);
+    Analyzer::analyze_source(&file, Language::Python)
+}
+
+/// What [`annotate_source`] returns, before conversion to JavaScript.
+fn annotate(source: &str) -> Result<String> {
+    Fixer::fix_source(source)
+}
+
+// The bindings themselves only run on wasm32; these check what they convert.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_source() {
+        let result = check("def f(x):\n    return x\n").unwrap();
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["path"], "<input>");
+        let codes: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect();
+        assert_eq!(codes, ["OT001", "OT002"]);
+        assert_eq!(json["diagnostics"][0]["span"]["start"]["line"], 0);
+    }
+
+    #[test]
+    fn test_annotate_source() {
+        assert_eq!(
+            annotate("def f(x):\n    return x\n").unwrap(),
+            "from typing import Any\ndef f(x: Any) -> Any:\n    return x\n"
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::process::Command;
 
 fn run_check_test(file_path: &str, expected_counts: &str) {
     let output = Command::new("cargo")
-        .args(&["run", "--", "check", file_path])
+        .args(["run", "--", "check", file_path])
         .output()
         .expect("Failed to run omnitype check");
 
@@ -27,3 +27,30 @@ fn test_check_sample_py() {
 fn test_check_classes_py() {
     run_check_test("tests/classes.py", "functions=7, classes=1");
 }
+
+#[test]
+fn test_check_suppressed_py() {
+    let output = Command::new("cargo")
+        .args(["run", "--", "check", "tests/suppressed.py"])
+        .output()
+        .expect("Failed to run omnitype check");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let warnings: Vec<&str> = stdout.lines().filter(|l| l.contains("warning")).collect();
+
+    // Only the code `# noqa: OT001` leaves unsuppressed is reported.
+    assert_eq!(warnings.len(), 1, "Expected one warning, got: {}", stdout);
+    assert!(warnings[0].contains("suppressed.py:8:1") && warnings[0].contains("[OT002]"));
+    assert!(!stdout.contains(":4:") && !stdout.contains(":12:"));
+}
+
+#[test]
+fn test_check_exit_code_for_every_format() {
+    for format in ["text", "json", "sarif", "csv", "jsonl"] {
+        let output = Command::new("cargo")
+            .args(["run", "--", "check", "tests/sample.py", "--format", format])
+            .output()
+            .expect("Failed to run omnitype check");
+        assert_eq!(output.status.code(), Some(1), "Expected exit code 1 for --format {}", format);
+    }
+}
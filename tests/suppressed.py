@@ -0,0 +1,13 @@
+"""Functions whose missing annotations are suppressed by comments."""
+
+
+def scale(value, factor=2):  # type: ignore
+    return value * factor
+
+
+def label(name):  # noqa: OT001
+    return name
+
+
+def greet(name):  # omnitype: ignore[OT001, OT002]
+    return "hi " + name